@@ -0,0 +1,136 @@
+// Copyright (c) 2019 Andreas Auernhammer. All rights reserved.
+// Use of this source code is governed by a license that can be
+// found in the LICENSE file.
+
+use super::{Aad, Algorithm};
+use ring::digest::{self, Context};
+use std::fmt;
+use std::io::{self, Read, Write};
+
+/// The label that separates the digest of the associated data from
+/// a plain SHA-256 digest of the same data.
+const LABEL: &[u8] = b"sio associated data\x00";
+
+/// Computes the associated data of a stream from arbitrarily long
+/// input incrementally.
+///
+/// Instead of holding very large associated data - e.g. a multi-megabyte
+/// manifest - in memory, an `AadHasher` consumes it piece by piece and
+/// produces a 32 byte `AadDigest`. The digest is used as the associated
+/// data of the stream. Since SHA-256 is collision resistant, binding the
+/// digest to the stream is as good as binding the data itself.
+///
+/// An `AadHasher` implements `Write`. Hence, the associated data can be
+/// fed from any reader - e.g. using `io::copy` or `read_from`. Both the
+/// encrypting and the decrypting party must hash the same data.
+///
+/// # Examples
+///
+/// ```
+/// use std::io::{Read, Write};
+/// use sio::{Key, Nonce, AadHasher, EncWriter, DecReader, CHACHA20_POLY1305};
+///
+/// // Obviously, don't use this all-zeros key for anything real.
+/// let key: Key<CHACHA20_POLY1305> = Key::new([0; Key::<CHACHA20_POLY1305>::SIZE]);
+///
+/// let manifest = vec![0; 1 << 20];
+/// let mut hasher = AadHasher::new();
+/// hasher.read_from(manifest.as_slice()).unwrap();
+/// let digest = hasher.finish();
+///
+/// let mut ciphertext: Vec<u8> = Vec::default();
+/// let mut writer = EncWriter::new(
+///     &mut ciphertext,
+///     &key,
+///     Nonce::new([0; Nonce::<CHACHA20_POLY1305>::SIZE]),
+///     digest.aad(),
+/// );
+/// writer.write_all(b"Hello World").unwrap();
+/// writer.close().unwrap();
+///
+/// let mut reader = DecReader::new(
+///     ciphertext.as_slice(),
+///     &key,
+///     Nonce::new([0; Nonce::<CHACHA20_POLY1305>::SIZE]),
+///     digest.aad(),
+/// );
+/// let mut plaintext = String::default();
+/// reader.read_to_string(&mut plaintext).unwrap();
+///
+/// assert_eq!(plaintext, "Hello World");
+/// ```
+pub struct AadHasher(Context);
+
+impl AadHasher {
+    /// Creates a new `AadHasher`.
+    pub fn new() -> Self {
+        let mut ctx = Context::new(&digest::SHA256);
+        ctx.update(LABEL);
+        AadHasher(ctx)
+    }
+
+    /// Adds `data` to the associated data.
+    #[inline]
+    pub fn update(&mut self, data: &[u8]) {
+        self.0.update(data);
+    }
+
+    /// Reads the `reader` until EOF and adds everything read to the
+    /// associated data. It returns the number of bytes read.
+    pub fn read_from<R: Read>(&mut self, mut reader: R) -> io::Result<u64> {
+        io::copy(&mut reader, self)
+    }
+
+    /// Returns the digest of all associated data.
+    pub fn finish(self) -> AadDigest {
+        let mut digest = [0; 32];
+        digest.copy_from_slice(self.0.finish().as_ref());
+        AadDigest(digest)
+    }
+}
+
+impl Default for AadHasher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Write for AadHasher {
+    #[inline]
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.update(buf);
+        Ok(buf.len())
+    }
+
+    #[inline]
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// The digest of associated data computed by an `AadHasher`.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct AadDigest([u8; 32]);
+
+impl AadDigest {
+    /// Returns the digest as associated data of a stream.
+    #[inline]
+    pub fn aad<A: Algorithm>(&self) -> Aad<'_, A> {
+        Aad::from(&self.0[..])
+    }
+}
+
+impl AsRef<[u8]> for AadDigest {
+    #[inline]
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl fmt::Debug for AadDigest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "AadDigest(")?;
+        self.0.iter().try_for_each(|b| write!(f, "{:02x}", b))?;
+        write!(f, ")")
+    }
+}
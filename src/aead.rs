@@ -3,28 +3,117 @@
 // found in the LICENSE file.
 
 use crate::error::{Exceeded, Invalid, NotAuthentic};
+use crate::MAX_NONCE_LEN;
 use std::marker::PhantomData;
 
 pub trait Algorithm {
     const KEY_LEN: usize;
+
+    /// The length of the nonce passed to `seal_in_place` and `open_in_place`
+    /// in bytes. It must be at least `8` and at most `MAX_NONCE_LEN` bytes.
+    ///
+    /// The framings derive the nonce of every fragment from a stream `Nonce`
+    /// of `NONCE_LEN - 4` bytes. Hence, algorithms with large nonces - e.g.
+    /// XChaCha20-Poly1305 with 192 bit nonces - and legacy algorithms with
+    /// 64 bit nonces work with all writers and readers.
     const NONCE_LEN: usize;
     const TAG_LEN: usize;
 
+    /// The JOSE algorithm identifier - i.e. the `alg` of a JWK - of the
+    /// algorithm. Keys can only be imported from a JWK if it is set.
+    const JWK_ALG: Option<&'static str> = None;
+
     fn new(key: &[u8; 32]) -> Self;
 
     fn seal_in_place<'a>(
         &self,
-        nonce: &[u8; 12],
+        nonce: &[u8],
         aad: &[u8],
         in_out: &'a mut [u8],
     ) -> Result<&'a [u8], Invalid>;
 
     fn open_in_place<'a>(
         &self,
-        nonce: &[u8; 12],
+        nonce: &[u8],
         aad: &[u8],
         in_out: &'a mut [u8],
     ) -> Result<&'a [u8], NotAuthentic>;
+
+    /// Encrypts and authenticates a batch of fragments in place.
+    ///
+    /// The `nonces` consist of one `NONCE_LEN` byte nonce per fragment and
+    /// the `in_out` buffer consists of one fragment per nonce. Each
+    /// fragment is `fragment_len` bytes long - i.e. the plaintext followed
+    /// by `TAG_LEN` bytes for the tag - and all fragments use the same
+    /// associated data.
+    ///
+    /// It fails with `Invalid::Nonce` if the number of nonces does not
+    /// match the number of fragments.
+    ///
+    /// Implementations that can pipeline or offload several AEAD operations
+    /// may override it to amortize the per-call overhead. By default, it
+    /// seals the fragments one by one.
+    fn seal_batch(
+        &self,
+        nonces: &[u8],
+        aad: &[u8],
+        in_out: &mut [u8],
+        fragment_len: usize,
+    ) -> Result<(), Invalid> {
+        batch_len::<Self>(nonces, in_out, fragment_len).ok_or(Invalid::Nonce)?;
+        for (nonce, fragment) in nonces
+            .chunks(Self::NONCE_LEN)
+            .zip(in_out.chunks_mut(fragment_len))
+        {
+            self.seal_in_place(nonce, aad, fragment)?;
+        }
+        Ok(())
+    }
+
+    /// Decrypts and verifies a batch of fragments in place.
+    ///
+    /// The `nonces` consist of one `NONCE_LEN` byte nonce per fragment and
+    /// the `in_out` buffer consists of one fragment per nonce. Each
+    /// fragment is `fragment_len` bytes long - i.e. the ciphertext including
+    /// the tag - and all fragments use the same associated data. It fails
+    /// if any fragment is not authentic or if the number of nonces does
+    /// not match the number of fragments.
+    ///
+    /// By default, it opens the fragments one by one.
+    fn open_batch(
+        &self,
+        nonces: &[u8],
+        aad: &[u8],
+        in_out: &mut [u8],
+        fragment_len: usize,
+    ) -> Result<(), NotAuthentic> {
+        batch_len::<Self>(nonces, in_out, fragment_len).ok_or(NotAuthentic)?;
+        for (nonce, fragment) in nonces
+            .chunks(Self::NONCE_LEN)
+            .zip(in_out.chunks_mut(fragment_len))
+        {
+            self.open_in_place(nonce, aad, fragment)?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns the number of fragments of a batch - provided the `in_out`
+/// buffer consists of exactly one fragment of `fragment_len` bytes per
+/// `A::NONCE_LEN` byte nonce.
+pub(crate) fn batch_len<A: Algorithm + ?Sized>(
+    nonces: &[u8],
+    in_out: &[u8],
+    fragment_len: usize,
+) -> Option<usize> {
+    if fragment_len == 0 || in_out.len() % fragment_len != 0 {
+        return None;
+    }
+    let n = in_out.len() / fragment_len;
+    if nonces.len() != n.checked_mul(A::NONCE_LEN)? {
+        return None;
+    }
+    Some(n)
 }
 
 pub struct Key<A: Algorithm>([u8; 32], PhantomData<A>);
@@ -43,19 +132,64 @@ impl<A: Algorithm> AsRef<[u8; 32]> for Key<A> {
     }
 }
 
-pub struct Nonce<A: Algorithm>([u8; 8], PhantomData<A>);
+/// The nonce of a stream.
+///
+/// It is `NONCE_LEN - 4` bytes long since the framings derive the nonce
+/// of every fragment from it and a 4 byte sequence number.
+pub struct Nonce<A: Algorithm>([u8; MAX_NONCE_LEN - 4], PhantomData<A>);
 
 impl<A: Algorithm> Nonce<A> {
     pub const SIZE: usize = A::NONCE_LEN - 4;
 
-    pub fn new(bytes: [u8; 8]) -> Self {
-        Nonce(bytes, PhantomData)
+    /// Creates a new `Nonce` from `SIZE` bytes. Any other number of bytes
+    /// is rejected at compile time.
+    pub fn new<const N: usize>(bytes: [u8; N]) -> Self {
+        #[allow(clippy::let_unit_value)]
+        let () = NonceLen::<A, N>::CHECK;
+
+        let mut nonce = [0; MAX_NONCE_LEN - 4];
+        nonce[..N].copy_from_slice(&bytes);
+        Nonce(nonce, PhantomData)
+    }
+
+    /// Creates the all-zero `Nonce` of `SIZE` bytes - e.g. for a key
+    /// that is used to encrypt just one stream.
+    pub fn zero() -> Self {
+        Nonce([0; MAX_NONCE_LEN - 4], PhantomData)
     }
 }
 
-impl<A: Algorithm> AsRef<[u8; 8]> for Nonce<A> {
-    fn as_ref(&self) -> &[u8; 8] {
-        &self.0
+/// Checks the length of the bytes passed to `Nonce::new`. Evaluating
+/// `CHECK` fails to compile if `N` is not `Nonce::<A>::SIZE`.
+struct NonceLen<A: Algorithm, const N: usize>(PhantomData<A>);
+
+impl<A: Algorithm, const N: usize> NonceLen<A, N> {
+    const CHECK: () = assert!(
+        N == Nonce::<A>::SIZE && N <= MAX_NONCE_LEN - 4,
+        "invalid nonce length"
+    );
+}
+
+impl<A: Algorithm> Nonce<A> {
+    /// Creates a new `Nonce` from the `SIZE` bytes of `bytes`.
+    pub(crate) fn from_slice(bytes: &[u8]) -> Self {
+        let mut nonce = [0; MAX_NONCE_LEN - 4];
+        nonce[..Self::SIZE].copy_from_slice(bytes);
+        Nonce(nonce, PhantomData)
+    }
+}
+
+impl<A: Algorithm> Copy for Nonce<A> {}
+
+impl<A: Algorithm> Clone for Nonce<A> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<A: Algorithm> AsRef<[u8]> for Nonce<A> {
+    fn as_ref(&self) -> &[u8] {
+        &self.0[..Self::SIZE]
     }
 }
 
@@ -92,37 +226,272 @@ impl<'a, A: Algorithm> From<&'a [u8]> for Aad<'a, A> {
     }
 }
 
-pub(crate) struct Counter<A: Algorithm> {
-    nonce: [u8; 12],
-    pub seq_num: u32,
+/// A key-nonce combination that can be used for exactly one stream.
+///
+/// An `EncWriter` must never be created twice from the same key and nonce.
+/// `EncWriter::new` cannot enforce this since it borrows the `Key` and
+/// copies the `Nonce`. A `Token` takes ownership of the `Key` instead and
+/// `EncWriter::from_token` consumes the `Token`. Since neither `Key` nor
+/// `Token` can be cloned, creating a second `EncWriter` from the same
+/// `Token` - or a second `Token` from the same `Key` - does not compile.
+///
+/// ```compile_fail
+/// use sio::{Key, Nonce, Aad, Token, EncWriter, CHACHA20_POLY1305};
+///
+/// let key: Key<CHACHA20_POLY1305> = Key::new([0; Key::<CHACHA20_POLY1305>::SIZE]);
+/// let token = Token::new(key, Nonce::new([0; Nonce::<CHACHA20_POLY1305>::SIZE]));
+///
+/// let first = EncWriter::from_token(Vec::default(), token, Aad::empty());
+/// let second = EncWriter::from_token(Vec::default(), token, Aad::empty());
+/// ```
+///
+/// A `Token` only prevents reuse within the program. Keys that are loaded
+/// more than once - e.g. from a file - must still be combined with a
+/// fresh nonce every time. The `Session` takes care of that.
+pub struct Token<A: Algorithm> {
+    key: Key<A>,
+    nonce: Nonce<A>,
+}
+
+impl<A: Algorithm> Token<A> {
+    /// Creates a new `Token` that owns the `key` and the `nonce`.
+    pub fn new(key: Key<A>, nonce: Nonce<A>) -> Self {
+        Token { key, nonce }
+    }
+
+    /// Returns the nonce of the `Token`. It is required to decrypt the
+    /// stream again.
+    #[inline]
+    pub fn nonce(&self) -> Nonce<A> {
+        self.nonce
+    }
+
+    /// Splits the `Token` into its key and nonce.
+    #[inline]
+    pub(crate) fn into_parts(self) -> (Key<A>, Nonce<A>) {
+        (self.key, self.nonce)
+    }
+}
+
+/// The layout of the nonces produced by a `Counter`.
+///
+/// A nonce consists of a prefix, taken from the `Nonce` of a stream,
+/// followed by the sequence number. The default layout is the one of the
+/// `Sio` framing: The entire `Nonce` as prefix followed by a 4 byte
+/// little-endian sequence number. Formats that split the nonce
+/// differently - e.g. a 96 bit nonce into a 4 byte prefix and an 8 byte
+/// big-endian counter - can be matched by a custom layout.
+///
+/// The sequence number is between 4 and 8 bytes long. The prefix fills
+/// the rest of the nonce. A `Nonce` is always 4 bytes shorter than the
+/// nonce of the algorithm. Hence, the last bytes of the `Nonce` that
+/// overlap with a longer sequence number must be zero.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NonceLayout {
+    counter_len: usize,
+    big_endian: bool,
+}
+
+impl NonceLayout {
+    /// Returns a layout with a `counter_len` byte little-endian sequence
+    /// number. The `counter_len` must be between `4` and `8`.
+    pub const fn little_endian(counter_len: usize) -> Result<Self, Invalid> {
+        Self::new(counter_len, false)
+    }
+
+    /// Returns a layout with a `counter_len` byte big-endian sequence
+    /// number. The `counter_len` must be between `4` and `8`.
+    pub const fn big_endian(counter_len: usize) -> Result<Self, Invalid> {
+        Self::new(counter_len, true)
+    }
+
+    const fn new(counter_len: usize, big_endian: bool) -> Result<Self, Invalid> {
+        if counter_len < 4 || counter_len > 8 {
+            return Err(Invalid::Nonce);
+        }
+        Ok(NonceLayout {
+            counter_len,
+            big_endian,
+        })
+    }
+
+    /// Returns the length of the sequence number in bytes.
+    #[inline]
+    pub const fn counter_len(&self) -> usize {
+        self.counter_len
+    }
+
+    /// Returns whether the sequence number is encoded in big-endian
+    /// byte order.
+    #[inline]
+    pub const fn is_big_endian(&self) -> bool {
+        self.big_endian
+    }
+}
+
+impl Default for NonceLayout {
+    /// Returns the layout of the `Sio` framing: The entire `Nonce` as
+    /// prefix followed by a 4 byte little-endian sequence number.
+    fn default() -> Self {
+        NonceLayout {
+            counter_len: 4,
+            big_endian: false,
+        }
+    }
+}
+
+/// A sequence of unique nonces derived from a nonce prefix and a 32 bit
+/// sequence number.
+///
+/// By default, every nonce consists of the prefix followed by the sequence
+/// number in 4 byte little-endian encoding. A different `NonceLayout` can
+/// be chosen with `with_layout`. The sequence number is incremented after
+/// each nonce. This is the scheme the `Sio` framing uses to derive the
+/// nonce of every fragment from the `Nonce` of the stream. Hence,
+/// protocols - e.g. record layers - that derive their nonces in the same
+/// way can use a `Counter` instead of implementing the scheme themselves.
+///
+/// A `Counter` produces at most 2^32 nonces. Once the sequence number
+/// has been exhausted, `next` fails with `Exceeded` instead of returning
+/// a nonce twice.
+///
+/// # Examples
+///
+/// ```
+/// use sio::{Counter, NonceLayout};
+///
+/// let mut counter = Counter::new(&[7; 8], u32::MAX - 1);
+/// assert_eq!(counter.next().unwrap()[8..], [0xfe, 0xff, 0xff, 0xff]);
+/// assert_eq!(counter.next().unwrap()[8..], [0xff, 0xff, 0xff, 0xff]);
+/// assert!(counter.next().is_err());
+///
+/// let layout = NonceLayout::big_endian(8).unwrap();
+/// let mut counter = Counter::with_layout(&[7, 7, 7, 7, 0, 0, 0, 0], 1, layout).unwrap();
+/// assert_eq!(counter.next().unwrap(), &[7, 7, 7, 7, 0, 0, 0, 0, 0, 0, 0, 1]);
+/// ```
+#[derive(Clone, Debug)]
+pub struct Counter {
+    nonce: [u8; MAX_NONCE_LEN],
+    len: usize,
+    seq_num: u32,
     exceeded: bool,
-    phantom_data: PhantomData<A>,
+    layout: NonceLayout,
 }
 
-impl<A: Algorithm> Counter<A> {
-    pub fn zero(nonce: Nonce<A>) -> Self {
-        let mut value = [0; 12];
-        &mut value[..8].copy_from_slice(&nonce.0);
-        Counter {
+impl Default for Counter {
+    /// Returns a `Counter` with an all-zero 8 byte prefix that starts at
+    /// sequence number `0` - i.e. a counter for 12 byte nonces.
+    fn default() -> Self {
+        Self::zero(&[0; 8])
+    }
+}
+
+impl Counter {
+    /// Creates a new `Counter` with the nonce `prefix` that starts at
+    /// sequence number `0`.
+    ///
+    /// # Panics
+    ///
+    /// It panics if the `prefix` is longer than `MAX_NONCE_LEN - 4` bytes.
+    pub fn zero(prefix: &[u8]) -> Self {
+        Self::new(prefix, 0)
+    }
+
+    /// Creates a new `Counter` with the nonce `prefix` that starts at
+    /// sequence number `seq_num`. The nonces are 4 bytes longer than
+    /// the `prefix`.
+    ///
+    /// # Panics
+    ///
+    /// It panics if the `prefix` is longer than `MAX_NONCE_LEN - 4` bytes.
+    pub fn new(prefix: &[u8], seq_num: u32) -> Self {
+        Self::with_layout(prefix, seq_num, NonceLayout::default())
+            .expect("the nonce prefix must not be longer than MAX_NONCE_LEN - 4 bytes")
+    }
+
+    /// Creates a new `Counter` that starts at sequence number `seq_num`
+    /// and produces nonces with the given `layout`. The nonces are 4 bytes
+    /// longer than the `nonce`.
+    ///
+    /// The prefix consists of the leading bytes of `nonce` that do not
+    /// overlap with the sequence number. It fails with `Invalid::Nonce` if
+    /// any of the overlapping bytes of `nonce` is not zero or if the `nonce`
+    /// is longer than `MAX_NONCE_LEN - 4` bytes.
+    pub fn with_layout(nonce: &[u8], seq_num: u32, layout: NonceLayout) -> Result<Self, Invalid> {
+        let len = nonce.len() + 4;
+        if len > MAX_NONCE_LEN || len < layout.counter_len {
+            return Err(Invalid::Nonce);
+        }
+        let (prefix, rest) = nonce.split_at(len - layout.counter_len);
+        if rest.iter().any(|b| *b != 0) {
+            return Err(Invalid::Nonce);
+        }
+        let mut value = [0; MAX_NONCE_LEN];
+        value[..prefix.len()].copy_from_slice(prefix);
+        Ok(Counter {
             nonce: value,
-            seq_num: 0,
+            len,
+            seq_num,
             exceeded: false,
-            phantom_data: PhantomData,
-        }
+            layout,
+        })
+    }
+
+    /// Returns the layout of the nonces.
+    #[inline]
+    pub fn layout(&self) -> NonceLayout {
+        self.layout
+    }
+
+    /// Returns the sequence number of the nonce returned by the next
+    /// call of `next`.
+    #[inline]
+    pub fn seq_num(&self) -> u32 {
+        self.seq_num
     }
 
+    /// Returns the next nonce of the sequence. It fails with `Exceeded`
+    /// once all sequence numbers have been used.
+    ///
+    /// A `Counter` is not an `Iterator` since the returned nonce borrows
+    /// the `Counter`.
+    #[allow(clippy::should_implement_trait)]
     #[inline]
-    pub fn next<'a>(&'a mut self) -> Result<&'a [u8; 12], Exceeded> {
+    pub fn next(&mut self) -> Result<&[u8], Exceeded> {
         if self.exceeded {
-            return Err(Exceeded);
+            return Err(Exceeded::default());
         }
 
-        self.nonce[8..].copy_from_slice(self.seq_num.to_le_bytes().as_ref());
+        let seq_num = u64::from(self.seq_num);
+        let counter_len = self.layout.counter_len;
+        let counter = &mut self.nonce[self.len - counter_len..self.len];
+        if self.layout.big_endian {
+            counter.copy_from_slice(&seq_num.to_be_bytes()[8 - counter_len..]);
+        } else {
+            counter.copy_from_slice(&seq_num.to_le_bytes()[..counter_len]);
+        }
         if let Some(seq_num) = self.seq_num.checked_add(1) {
             self.seq_num = seq_num;
         } else {
             self.exceeded = true;
         }
-        Ok(&self.nonce)
+        Ok(&self.nonce[..self.len])
+    }
+
+    /// Sets the sequence number returned by the next call of `next`.
+    #[inline]
+    pub fn seek(&mut self, seq_num: u32) {
+        self.seq_num = seq_num;
+        self.exceeded = false;
+    }
+
+    /// Returns how many more times `next` can be called successfully.
+    #[inline]
+    pub fn remaining(&self) -> u64 {
+        if self.exceeded {
+            0
+        } else {
+            u64::from(u32::MAX - self.seq_num) + 1
+        }
     }
 }
@@ -0,0 +1,398 @@
+// Copyright (c) 2019 Andreas Auernhammer. All rights reserved.
+// Use of this source code is governed by a license that can be
+// found in the LICENSE file.
+
+use crate::aead::{batch_len, Algorithm};
+use crate::error::{Invalid, NotAuthentic};
+use std::io;
+use std::mem;
+use std::os::unix::io::{AsRawFd, FromRawFd, OwnedFd, RawFd};
+use std::ptr;
+use std::sync::Mutex;
+
+/// AES-256-GCM computed by the Linux kernel crypto API via `AF_ALG`
+/// sockets.
+///
+/// The kernel picks the best `gcm(aes)` implementation available - e.g.
+/// a driver for a crypto accelerator like NXP CAAM or Intel QAT. Hence,
+/// `AF_ALG_AES_256_GCM` offloads the encryption to such hardware while
+/// producing exactly the same ciphertext as `AES_256_GCM`. It requires
+/// Linux 4.9 or newer.
+///
+/// Every instance keeps a socket that holds the key and a socket for the
+/// en/decryption requests. Concurrent requests, e.g. of `decrypt_parallel`,
+/// use additional request sockets. `seal_batch` and `open_batch` submit all
+/// fragments of a batch through the same request socket.
+///
+/// If the kernel does not provide `AF_ALG` or `gcm(aes)`, creating an
+/// `EncWriter` or a `DecWriter` via `with_buffer_size` fails with
+/// `Invalid::Key` and every decryption fails with `NotAuthentic`. Use
+/// `is_available` to check whether the kernel supports it.
+#[allow(non_camel_case_types)]
+pub struct AF_ALG_AES_256_GCM {
+    sockets: Option<Sockets>,
+}
+
+struct Sockets {
+    tfm: OwnedFd,
+    op: Mutex<OwnedFd>,
+}
+
+impl AF_ALG_AES_256_GCM {
+    /// Returns true if the kernel provides AES-256-GCM via `AF_ALG`.
+    pub fn is_available() -> bool {
+        Sockets::new(&[0; Self::KEY_LEN]).is_ok()
+    }
+
+    /// Calls `f` with a request socket. It returns `unavailable` if no
+    /// request socket can be obtained.
+    fn with_op<T, E>(
+        &self,
+        unavailable: E,
+        mut f: impl FnMut(RawFd) -> Result<T, E>,
+    ) -> Result<T, E> {
+        let sockets = match self.sockets {
+            Some(ref sockets) => sockets,
+            None => return Err(unavailable),
+        };
+        match sockets.op.try_lock() {
+            Ok(mut op) => f(op.as_raw_fd()).inspect_err(|_| {
+                // A failed request may leave data behind in the socket.
+                if let Ok(fresh) = accept(&sockets.tfm) {
+                    *op = fresh;
+                }
+            }),
+            // Another thread uses the request socket.
+            Err(_) => match accept(&sockets.tfm) {
+                Ok(op) => f(op.as_raw_fd()),
+                Err(_) => Err(unavailable),
+            },
+        }
+    }
+}
+
+impl Sockets {
+    fn new(key: &[u8; 32]) -> io::Result<Self> {
+        let fd =
+            unsafe { libc::socket(libc::AF_ALG, libc::SOCK_SEQPACKET | libc::SOCK_CLOEXEC, 0) };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let tfm = unsafe { OwnedFd::from_raw_fd(fd) };
+
+        let mut addr: libc::sockaddr_alg = unsafe { mem::zeroed() };
+        addr.salg_family = libc::AF_ALG as libc::sa_family_t;
+        addr.salg_type[..4].copy_from_slice(b"aead");
+        addr.salg_name[..8].copy_from_slice(b"gcm(aes)");
+        cvt(unsafe {
+            libc::bind(
+                fd,
+                &addr as *const libc::sockaddr_alg as *const libc::sockaddr,
+                mem::size_of::<libc::sockaddr_alg>() as libc::socklen_t,
+            )
+        })?;
+        cvt(unsafe {
+            libc::setsockopt(
+                fd,
+                libc::SOL_ALG,
+                libc::ALG_SET_KEY,
+                key.as_ptr() as *const libc::c_void,
+                key.len() as libc::socklen_t,
+            )
+        })?;
+        cvt(unsafe {
+            libc::setsockopt(
+                fd,
+                libc::SOL_ALG,
+                libc::ALG_SET_AEAD_AUTHSIZE,
+                ptr::null(),
+                AF_ALG_AES_256_GCM::TAG_LEN as libc::socklen_t,
+            )
+        })?;
+
+        let op = accept(&tfm)?;
+        Ok(Sockets {
+            tfm,
+            op: Mutex::new(op),
+        })
+    }
+}
+
+/// Returns a new request socket for the key held by `tfm`.
+fn accept(tfm: &OwnedFd) -> io::Result<OwnedFd> {
+    let fd = unsafe {
+        libc::accept4(
+            tfm.as_raw_fd(),
+            ptr::null_mut(),
+            ptr::null_mut(),
+            libc::SOCK_CLOEXEC,
+        )
+    };
+    if fd < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(unsafe { OwnedFd::from_raw_fd(fd) })
+}
+
+fn cvt(r: libc::c_int) -> io::Result<()> {
+    if r < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+fn cvt_len(n: libc::ssize_t) -> io::Result<usize> {
+    if n < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(n as usize)
+}
+
+/// Performs one en/decryption request on the request socket `fd`.
+///
+/// The kernel reads the `aad` followed by the first `input_len` bytes
+/// of `in_out` and writes the `aad` followed by `output_len` bytes of
+/// output, which get stored in `in_out`.
+///
+/// The input is sent with `MSG_MORE` such that the kernel does not
+/// process the request before all of it has been sent. Hence, the
+/// kernel may accept the input in pieces - e.g. when the send gets
+/// interrupted by a signal.
+fn crypt(
+    fd: RawFd,
+    op: libc::c_int,
+    nonce: &[u8],
+    aad: &[u8],
+    in_out: &mut [u8],
+    input_len: usize,
+    output_len: usize,
+) -> io::Result<()> {
+    const NONCE_LEN: usize = AF_ALG_AES_256_GCM::NONCE_LEN;
+    if nonce.len() != NONCE_LEN {
+        return Err(io::Error::from(io::ErrorKind::InvalidInput));
+    }
+
+    // The control messages: the operation, the IV and the length
+    // of the associated data. The buffer must be aligned for cmsghdr.
+    let mut control = [0u64; 16];
+    let iv_len = mem::size_of::<libc::af_alg_iv>() + NONCE_LEN;
+    let control_len = unsafe {
+        2 * libc::CMSG_SPACE(mem::size_of::<u32>() as u32) + libc::CMSG_SPACE(iv_len as u32)
+    } as usize;
+    debug_assert!(control_len <= mem::size_of_val(&control));
+
+    let mut iov = [
+        libc::iovec {
+            iov_base: aad.as_ptr() as *mut libc::c_void,
+            iov_len: aad.len(),
+        },
+        libc::iovec {
+            iov_base: in_out.as_ptr() as *mut libc::c_void,
+            iov_len: input_len,
+        },
+    ];
+    let mut msg: libc::msghdr = unsafe { mem::zeroed() };
+    msg.msg_iov = iov.as_mut_ptr();
+    msg.msg_iovlen = iov.len() as _;
+    msg.msg_control = control.as_mut_ptr() as *mut libc::c_void;
+    msg.msg_controllen = control_len as _;
+    unsafe {
+        let cmsg = libc::CMSG_FIRSTHDR(&msg);
+        (*cmsg).cmsg_level = libc::SOL_ALG;
+        (*cmsg).cmsg_type = libc::ALG_SET_OP;
+        (*cmsg).cmsg_len = libc::CMSG_LEN(mem::size_of::<u32>() as u32) as _;
+        ptr::write_unaligned(libc::CMSG_DATA(cmsg) as *mut u32, op as u32);
+
+        let cmsg = libc::CMSG_NXTHDR(&msg, cmsg);
+        (*cmsg).cmsg_level = libc::SOL_ALG;
+        (*cmsg).cmsg_type = libc::ALG_SET_IV;
+        (*cmsg).cmsg_len = libc::CMSG_LEN(iv_len as u32) as _;
+        let data = libc::CMSG_DATA(cmsg);
+        ptr::write_unaligned(data as *mut u32, NONCE_LEN as u32);
+        ptr::copy_nonoverlapping(
+            nonce.as_ptr(),
+            data.add(mem::size_of::<libc::af_alg_iv>()),
+            NONCE_LEN,
+        );
+
+        let cmsg = libc::CMSG_NXTHDR(&msg, cmsg);
+        (*cmsg).cmsg_level = libc::SOL_ALG;
+        (*cmsg).cmsg_type = libc::ALG_SET_AEAD_ASSOCLEN;
+        (*cmsg).cmsg_len = libc::CMSG_LEN(mem::size_of::<u32>() as u32) as _;
+        ptr::write_unaligned(libc::CMSG_DATA(cmsg) as *mut u32, aad.len() as u32);
+    }
+    let mut sent = loop {
+        let n = unsafe { libc::sendmsg(fd, &msg, libc::MSG_MORE) };
+        match cvt_len(n) {
+            Ok(n) => break n,
+            Err(ref err) if err.kind() == io::ErrorKind::Interrupted => (),
+            Err(err) => return Err(err),
+        }
+    };
+    while sent < aad.len() + input_len {
+        let rest = if sent < aad.len() {
+            &aad[sent..]
+        } else {
+            &in_out[sent - aad.len()..input_len]
+        };
+        let n = unsafe {
+            libc::send(
+                fd,
+                rest.as_ptr() as *const libc::c_void,
+                rest.len(),
+                libc::MSG_MORE,
+            )
+        };
+        match cvt_len(n) {
+            Ok(0) => return Err(io::Error::from(io::ErrorKind::WriteZero)),
+            Ok(n) => sent += n,
+            Err(ref err) if err.kind() == io::ErrorKind::Interrupted => (),
+            Err(err) => return Err(err),
+        }
+    }
+
+    // A send without MSG_MORE completes the request.
+    loop {
+        let n = unsafe { libc::send(fd, ptr::null(), 0, 0) };
+        match cvt_len(n) {
+            Ok(_) => break,
+            Err(ref err) if err.kind() == io::ErrorKind::Interrupted => (),
+            Err(err) => return Err(err),
+        }
+    }
+
+    // The kernel writes the associated data in front of the output.
+    let mut echo = vec![0; aad.len()];
+    let iov = [
+        libc::iovec {
+            iov_base: echo.as_mut_ptr() as *mut libc::c_void,
+            iov_len: echo.len(),
+        },
+        libc::iovec {
+            iov_base: in_out.as_mut_ptr() as *mut libc::c_void,
+            iov_len: output_len,
+        },
+    ];
+    let n = unsafe { libc::readv(fd, iov.as_ptr(), iov.len() as libc::c_int) };
+    if n < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    if n as usize != aad.len() + output_len {
+        return Err(io::Error::from(io::ErrorKind::UnexpectedEof));
+    }
+    Ok(())
+}
+
+fn seal(fd: RawFd, nonce: &[u8], aad: &[u8], in_out: &mut [u8]) -> Result<(), Invalid> {
+    let tag_len = AF_ALG_AES_256_GCM::TAG_LEN;
+    if in_out.len() < tag_len {
+        return Err(Invalid::BufSize);
+    }
+    let len = in_out.len();
+    crypt(
+        fd,
+        libc::ALG_OP_ENCRYPT,
+        nonce,
+        aad,
+        in_out,
+        len - tag_len,
+        len,
+    )
+    .map_err(|err| {
+        if err.kind() == io::ErrorKind::InvalidInput {
+            Invalid::Nonce
+        } else {
+            Invalid::BufSize
+        }
+    })
+}
+
+fn open(fd: RawFd, nonce: &[u8], aad: &[u8], in_out: &mut [u8]) -> Result<usize, NotAuthentic> {
+    let tag_len = AF_ALG_AES_256_GCM::TAG_LEN;
+    if in_out.len() < tag_len {
+        return Err(NotAuthentic);
+    }
+    let len = in_out.len();
+    crypt(
+        fd,
+        libc::ALG_OP_DECRYPT,
+        nonce,
+        aad,
+        in_out,
+        len,
+        len - tag_len,
+    )
+    .map_err(|_| NotAuthentic)?;
+    Ok(len - tag_len)
+}
+
+impl Algorithm for AF_ALG_AES_256_GCM {
+    const KEY_LEN: usize = 256 / 8;
+    const NONCE_LEN: usize = 96 / 8;
+    const TAG_LEN: usize = 128 / 8;
+    const JWK_ALG: Option<&'static str> = Some("A256GCM");
+
+    fn new(key: &[u8; Self::KEY_LEN]) -> Self {
+        Self {
+            sockets: Sockets::new(key).ok(),
+        }
+    }
+
+    fn seal_in_place<'a>(
+        &self,
+        nonce: &[u8],
+        aad: &[u8],
+        in_out: &'a mut [u8],
+    ) -> Result<&'a [u8], Invalid> {
+        self.with_op(Invalid::Key, |fd| seal(fd, nonce, aad, in_out))?;
+        Ok(in_out)
+    }
+
+    fn open_in_place<'a>(
+        &self,
+        nonce: &[u8],
+        aad: &[u8],
+        in_out: &'a mut [u8],
+    ) -> Result<&'a [u8], NotAuthentic> {
+        let len = self.with_op(NotAuthentic, |fd| open(fd, nonce, aad, in_out))?;
+        Ok(&in_out[..len])
+    }
+
+    fn seal_batch(
+        &self,
+        nonces: &[u8],
+        aad: &[u8],
+        in_out: &mut [u8],
+        fragment_len: usize,
+    ) -> Result<(), Invalid> {
+        batch_len::<Self>(nonces, in_out, fragment_len).ok_or(Invalid::Nonce)?;
+        self.with_op(Invalid::Key, |fd| {
+            for (nonce, fragment) in nonces
+                .chunks(Self::NONCE_LEN)
+                .zip(in_out.chunks_mut(fragment_len))
+            {
+                seal(fd, nonce, aad, fragment)?;
+            }
+            Ok(())
+        })
+    }
+
+    fn open_batch(
+        &self,
+        nonces: &[u8],
+        aad: &[u8],
+        in_out: &mut [u8],
+        fragment_len: usize,
+    ) -> Result<(), NotAuthentic> {
+        batch_len::<Self>(nonces, in_out, fragment_len).ok_or(NotAuthentic)?;
+        self.with_op(NotAuthentic, |fd| {
+            for (nonce, fragment) in nonces
+                .chunks(Self::NONCE_LEN)
+                .zip(in_out.chunks_mut(fragment_len))
+            {
+                open(fd, nonce, aad, fragment)?;
+            }
+            Ok(())
+        })
+    }
+}
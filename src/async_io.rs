@@ -0,0 +1,1245 @@
+// Copyright (c) 2019 Andreas Auernhammer. All rights reserved.
+// Use of this source code is governed by a license that can be
+// found in the LICENSE file.
+
+use super::framing::{Framing, Sio};
+use super::hook;
+use super::writer::internal::Close;
+use super::{
+    Aad, Algorithm, DecWriter, EncWriter, Invalid, Key, Layout, Nonce, NotAuthentic, BUF_SIZE,
+    MAX_BUF_SIZE,
+};
+#[cfg(feature = "futures")]
+use bytes::Bytes;
+#[cfg(feature = "futures")]
+use futures_core::Stream;
+use futures_io::{AsyncRead, AsyncSeek, AsyncWrite};
+#[cfg(feature = "futures")]
+use futures_sink::Sink;
+use std::collections::VecDeque;
+use std::io::{self, SeekFrom, Write};
+use std::mem;
+use std::pin::Pin;
+use std::sync::{mpsc, Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+use std::thread::{self, panicking};
+
+/// Wraps an asynchronous writer and encrypts and authenticates
+/// everything written to it.
+///
+/// `AsyncEncWriter` is the counterpart of `EncWriter` for writers that
+/// implement the `AsyncWrite` trait of the `futures-io` crate - e.g. the
+/// I/O types of `async-std` and `smol`. It produces exactly the same
+/// ciphertext as an `EncWriter` with the same parameters.
+///
+/// Sealed fragments are buffered until the inner writer accepts them.
+/// An `AsyncEncWriter` accepts at most one fragment of plaintext per
+/// write and only once the previous fragment has been written to the
+/// inner writer. Hence, it buffers at most two fragments.
+///
+/// As with an `EncWriter`, the `AsyncEncWriter` must be closed - using
+/// `AsyncWriteExt::close` - to complete the encryption process.
+///
+/// # Cancellation
+///
+/// All state of an `AsyncEncWriter` lives in the writer itself - not in
+/// the futures polling it. Hence, dropping a `write`, `flush` or `close`
+/// future before it completes never loses nor duplicates data.
+///
+/// A write that returns `Poll::Pending` has not consumed any plaintext.
+/// A write that returns `Poll::Ready(Ok(n))` has consumed exactly `n`
+/// bytes - even if the corresponding fragment has not been sealed or
+/// written to the inner writer yet. A sealed fragment that has been
+/// written only partially to the inner writer is tracked by the
+/// `AsyncEncWriter`. The next write, flush or close continues with the
+/// first byte that has not been written yet. Therefore, the inner writer
+/// never receives a half-written fragment followed by another one.
+///
+/// Once a close has been started, the stream cannot be extended anymore
+/// and any further write fails. Polling close again completes the stream.
+///
+/// Helper futures like `AsyncWriteExt::write_all` may have written a
+/// prefix of their buffer when they get dropped. Callers that need to
+/// resume such a write must use `AsyncWriteExt::write` and track the
+/// number of written bytes themselves.
+///
+/// If the inner writer fails, the stream cannot be completed anymore.
+///
+/// # Examples
+///
+/// ```
+/// use futures_lite::future::block_on;
+/// use futures_lite::io::AsyncWriteExt;
+/// use sio::{Key, Nonce, Aad, AsyncEncWriter, CHACHA20_POLY1305};
+///
+/// // Obviously, don't use this all-zeros key for anything real.
+/// let key: Key<CHACHA20_POLY1305> = Key::new([0; Key::<CHACHA20_POLY1305>::SIZE]);
+///
+/// block_on(async {
+///     let mut ciphertext: Vec<u8> = Vec::default();
+///     let mut writer = AsyncEncWriter::new(
+///         &mut ciphertext,
+///         &key,
+///         Nonce::new([0; Nonce::<CHACHA20_POLY1305>::SIZE]),
+///         Aad::empty(),
+///     );
+///     writer.write_all(b"Hello World").await.unwrap();
+///     writer.close().await.unwrap();
+/// });
+/// ```
+pub struct AsyncEncWriter<A: Algorithm, W: AsyncWrite + Unpin> {
+    inner: W,
+    writer: EncWriter<A, Vec<u8>>,
+    buf_size: usize,
+
+    // The number of buffered ciphertext bytes that have
+    // been written to the inner writer.
+    pos: usize,
+
+    // Whether the final fragment has been sealed.
+    sealed: bool,
+}
+
+impl<A: Algorithm, W: AsyncWrite + Unpin> AsyncEncWriter<A, W> {
+    /// Creates a new `AsyncEncWriter` with a default buffer size of 16 KiB.
+    pub fn new(inner: W, key: &Key<A>, nonce: Nonce<A>, aad: Aad<A>) -> Self {
+        Self::with_buffer_size(inner, key, nonce, aad, BUF_SIZE).unwrap()
+    }
+
+    /// Creates a new `AsyncEncWriter` with the specified buffer size as
+    /// fragment size. The `buf_size` must not be `0` nor greater than
+    /// `MAX_BUF_SIZE`.
+    pub fn with_buffer_size(
+        inner: W,
+        key: &Key<A>,
+        nonce: Nonce<A>,
+        aad: Aad<A>,
+        buf_size: usize,
+    ) -> Result<Self, Invalid> {
+        Ok(AsyncEncWriter {
+            inner,
+            writer: EncWriter::with_buffer_size(Vec::default(), key, nonce, aad, buf_size)?,
+            buf_size,
+            pos: 0,
+            sealed: false,
+        })
+    }
+
+    /// Writes the buffered ciphertext to the inner writer.
+    fn poll_drain(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let pending = self.writer.get_mut();
+        match poll_write_buffer(&mut self.inner, cx, pending, &mut self.pos) {
+            Poll::Ready(Err(err)) => {
+                // Once the inner writer has failed, the stream cannot be
+                // completed anymore and the EncWriter must not panic when
+                // it gets dropped.
+                if !self.sealed {
+                    self.sealed = true;
+                    let _ = Close::close(&mut self.writer);
+                }
+                Poll::Ready(Err(err))
+            }
+            poll => poll,
+        }
+    }
+}
+
+// The `AsyncEncWriter` never pins any of its fields.
+impl<A: Algorithm, W: AsyncWrite + Unpin> Unpin for AsyncEncWriter<A, W> {}
+
+impl<A: Algorithm, W: AsyncWrite + Unpin> AsyncWrite for AsyncEncWriter<A, W> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        if this.sealed {
+            return Poll::Ready(Err(io::Error::other("the writer has been closed")));
+        }
+        if let Err(err) = futures_ready(this.poll_drain(cx)) {
+            return err;
+        }
+        let n = buf.len().min(this.buf_size);
+        Poll::Ready(this.writer.write_all(&buf[..n]).map(|()| n))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        if let Err(err) = futures_ready(this.poll_drain(cx)) {
+            return err;
+        }
+        Pin::new(&mut this.inner).poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        if !this.sealed {
+            this.sealed = true;
+            if let Err(err) = Close::close(&mut this.writer) {
+                return Poll::Ready(Err(err));
+            }
+        }
+        if let Err(err) = futures_ready(this.poll_drain(cx)) {
+            return err;
+        }
+        Pin::new(&mut this.inner).poll_close(cx)
+    }
+}
+
+/// An `AsyncEncWriter` is a `Sink` of plaintext chunks. Hence, it can be
+/// used in `SinkExt::send_all` pipelines.
+///
+/// In contrast to `poll_write`, `start_send` accepts the entire chunk at
+/// once. Its ciphertext is buffered until the inner writer accepts it.
+/// Closing the sink completes the encryption process.
+///
+/// # Examples
+///
+/// ```
+/// use bytes::Bytes;
+/// use futures_lite::future::block_on;
+/// use futures_util::{stream, SinkExt};
+/// use sio::{Key, Nonce, Aad, AsyncEncWriter, CHACHA20_POLY1305};
+///
+/// // Obviously, don't use this all-zeros key for anything real.
+/// let key: Key<CHACHA20_POLY1305> = Key::new([0; Key::<CHACHA20_POLY1305>::SIZE]);
+///
+/// block_on(async {
+///     let mut ciphertext: Vec<u8> = Vec::default();
+///     let mut writer = AsyncEncWriter::new(
+///         &mut ciphertext,
+///         &key,
+///         Nonce::new([0; Nonce::<CHACHA20_POLY1305>::SIZE]),
+///         Aad::empty(),
+///     );
+///     let mut chunks = stream::iter(vec![Ok(Bytes::from("Hello ")), Ok(Bytes::from("World"))]);
+///     writer.send_all(&mut chunks).await.unwrap();
+///     writer.close().await.unwrap();
+/// });
+/// ```
+#[cfg(feature = "futures")]
+impl<A: Algorithm, W: AsyncWrite + Unpin> Sink<Bytes> for AsyncEncWriter<A, W> {
+    type Error = io::Error;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        if this.sealed {
+            return Poll::Ready(Err(io::Error::other("the writer has been closed")));
+        }
+        this.poll_drain(cx)
+    }
+
+    fn start_send(self: Pin<&mut Self>, item: Bytes) -> io::Result<()> {
+        let this = self.get_mut();
+        if this.sealed {
+            return Err(io::Error::other("the writer has been closed"));
+        }
+        this.writer.write_all(&item)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        AsyncWrite::poll_flush(self, cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        AsyncWrite::poll_close(self, cx)
+    }
+}
+
+/// Wraps an asynchronous writer and encrypts and authenticates
+/// everything written to it using a pool of worker threads.
+///
+/// An `AsyncParallelEncWriter` produces exactly the same ciphertext as an
+/// `AsyncEncWriter` with the same parameters. However, it does not seal
+/// the fragments on the task that polls it. Instead, it hands every
+/// completed fragment to one of its worker threads and keeps accepting
+/// plaintext while the workers seal the previous fragments. Hence, a
+/// single stream can keep more than one core busy - e.g. when uploading
+/// a large object - without blocking the executor.
+///
+/// At most `window` fragments are sealed at the same time. Once the
+/// window is full, a write returns `Poll::Pending` until the oldest
+/// fragment has been sealed and written to the inner writer. Therefore,
+/// an `AsyncParallelEncWriter` buffers at most `window + 2` fragments.
+/// The sealed fragments are always written in stream order.
+///
+/// An `AsyncParallelEncWriter` provides the same cancellation guarantees
+/// as an `AsyncEncWriter` and must be closed - using
+/// `AsyncWriteExt::close` - to complete the encryption process. The
+/// workers exit once the writer has been dropped.
+///
+/// # Examples
+///
+/// ```
+/// use futures_lite::future::block_on;
+/// use futures_lite::io::AsyncWriteExt;
+/// use sio::{Key, Nonce, Aad, AsyncParallelEncWriter, CHACHA20_POLY1305};
+///
+/// // Obviously, don't use this all-zeros key for anything real.
+/// let key: Key<CHACHA20_POLY1305> = Key::new([0; Key::<CHACHA20_POLY1305>::SIZE]);
+///
+/// block_on(async {
+///     let mut ciphertext: Vec<u8> = Vec::default();
+///     let mut writer = AsyncParallelEncWriter::new(
+///         &mut ciphertext,
+///         &key,
+///         Nonce::new([0; Nonce::<CHACHA20_POLY1305>::SIZE]),
+///         Aad::empty(),
+///         4,
+///     )
+///     .window(8);
+///     writer.write_all(&vec![0; 1 << 20]).await.unwrap();
+///     writer.close().await.unwrap();
+/// });
+/// ```
+pub struct AsyncParallelEncWriter<A: Algorithm, W: AsyncWrite + Unpin> {
+    inner: W,
+    algorithm: A,
+    framing: Sio,
+    jobs: mpsc::Sender<Job>,
+    buf_size: usize,
+    window: usize,
+
+    // The plaintext of the fragment that has not been handed
+    // to the workers yet.
+    buffer: Vec<u8>,
+
+    // The fragments handed to the workers - in stream order.
+    in_flight: VecDeque<Arc<Slot>>,
+
+    // The sealed fragment that is written to the inner writer
+    // and the number of its bytes that have been written.
+    pending: Vec<u8>,
+    pos: usize,
+
+    // Whether the final fragment has been handed to the workers
+    // or the stream cannot be completed anymore.
+    sealed: bool,
+}
+
+impl<A: Algorithm + 'static, W: AsyncWrite + Unpin> AsyncParallelEncWriter<A, W> {
+    /// Creates a new `AsyncParallelEncWriter` with a default buffer size
+    /// of 16 KiB that seals the fragments on `workers` threads. At least
+    /// one worker is used.
+    ///
+    /// The window is twice the number of workers.
+    pub fn new(inner: W, key: &Key<A>, nonce: Nonce<A>, aad: Aad<A>, workers: usize) -> Self {
+        Self::with_buffer_size(inner, key, nonce, aad, BUF_SIZE, workers).unwrap()
+    }
+
+    /// Creates a new `AsyncParallelEncWriter` with the specified buffer
+    /// size as fragment size. The `buf_size` must not be `0` nor greater
+    /// than `MAX_BUF_SIZE`.
+    pub fn with_buffer_size(
+        inner: W,
+        key: &Key<A>,
+        nonce: Nonce<A>,
+        aad: Aad<A>,
+        buf_size: usize,
+        workers: usize,
+    ) -> Result<Self, Invalid> {
+        if buf_size == 0 || buf_size > MAX_BUF_SIZE {
+            return Err(Invalid::BufSize);
+        }
+        let algorithm = A::new(key.as_ref());
+        let mut framing = Sio::default();
+        framing.init(key.as_ref(), &algorithm, nonce.as_ref(), aad.as_ref())?;
+
+        let workers = workers.max(1);
+        let (jobs, queue) = mpsc::channel();
+        let queue = Arc::new(Mutex::new(queue));
+        for _ in 0..workers {
+            let key = *key.as_ref();
+            let queue = queue.clone();
+            thread::spawn(move || seal_fragments::<A>(&key, &queue));
+        }
+        Ok(AsyncParallelEncWriter {
+            inner,
+            algorithm,
+            framing,
+            jobs,
+            buf_size,
+            window: 2 * workers,
+            buffer: Vec::with_capacity(buf_size + A::TAG_LEN),
+            in_flight: VecDeque::with_capacity(2 * workers),
+            pending: Vec::default(),
+            pos: 0,
+            sealed: false,
+        })
+    }
+
+    /// Sets the number of fragments that are sealed at the same time.
+    /// At least one fragment is sealed at a time.
+    pub fn window(mut self, window: usize) -> Self {
+        self.window = window.max(1);
+        self
+    }
+
+    /// Hands the buffered plaintext to the workers.
+    fn seal(&mut self, last: bool) -> io::Result<()> {
+        let (nonce, aad) = match self.framing.next(&mut self.algorithm, last) {
+            Ok(next) => next,
+            Err(err) => {
+                self.sealed = true;
+                return Err(err.into());
+            }
+        };
+        let mut fragment = mem::replace(
+            &mut self.buffer,
+            Vec::with_capacity(self.buf_size + A::TAG_LEN),
+        );
+        fragment.resize(fragment.len() + A::TAG_LEN, 0);
+
+        let slot = Arc::new(Slot::default());
+        let job = Job {
+            nonce: nonce.to_vec(),
+            aad: aad.to_vec(),
+            fragment,
+            slot: slot.clone(),
+        };
+        if self.jobs.send(job).is_err() {
+            self.sealed = true;
+            return Err(io::Error::other("the workers have stopped"));
+        }
+        self.in_flight.push_back(slot);
+        Ok(())
+    }
+
+    /// Writes the sealed fragments to the inner writer - in stream
+    /// order - until fewer than `limit` fragments are in flight.
+    fn poll_drain(&mut self, cx: &mut Context<'_>, limit: usize) -> Poll<io::Result<()>> {
+        loop {
+            match poll_write_buffer(&mut self.inner, cx, &mut self.pending, &mut self.pos) {
+                Poll::Ready(Ok(())) => (),
+                Poll::Ready(Err(err)) => {
+                    self.sealed = true;
+                    return Poll::Ready(Err(err));
+                }
+                Poll::Pending => return Poll::Pending,
+            }
+            if self.in_flight.len() < limit {
+                return Poll::Ready(Ok(()));
+            }
+            match self.in_flight[0].poll_take(cx) {
+                Poll::Ready(Ok(fragment)) => self.pending = fragment,
+                Poll::Ready(Err(err)) => {
+                    self.sealed = true;
+                    return Poll::Ready(Err(err.into()));
+                }
+                Poll::Pending => return Poll::Pending,
+            }
+            self.in_flight.pop_front();
+        }
+    }
+}
+
+// The `AsyncParallelEncWriter` never pins any of its fields.
+impl<A: Algorithm, W: AsyncWrite + Unpin> Unpin for AsyncParallelEncWriter<A, W> {}
+
+impl<A: Algorithm + 'static, W: AsyncWrite + Unpin> AsyncWrite for AsyncParallelEncWriter<A, W> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        if this.sealed {
+            return Poll::Ready(Err(io::Error::other("the writer has been closed")));
+        }
+        if buf.is_empty() {
+            return Poll::Ready(Ok(0));
+        }
+
+        // A full fragment is not the final one once there is more
+        // plaintext. Hence, it can be handed to the workers as soon
+        // as the window has room for it.
+        if this.buffer.len() == this.buf_size {
+            if let Err(err) = futures_ready(this.poll_drain(cx, this.window)) {
+                return err;
+            }
+            if let Err(err) = this.seal(false) {
+                return Poll::Ready(Err(err));
+            }
+        }
+        let n = buf.len().min(this.buf_size - this.buffer.len());
+        this.buffer.extend_from_slice(&buf[..n]);
+        Poll::Ready(Ok(n))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        if let Err(err) = futures_ready(this.poll_drain(cx, 1)) {
+            return err;
+        }
+        Pin::new(&mut this.inner).poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        if !this.sealed {
+            // The final fragment does not wait for room in the window
+            // since its plaintext has been buffered already.
+            if let Err(err) = this.seal(true) {
+                return Poll::Ready(Err(err));
+            }
+            this.sealed = true;
+        }
+        if let Err(err) = futures_ready(this.poll_drain(cx, 1)) {
+            return err;
+        }
+        Pin::new(&mut this.inner).poll_close(cx)
+    }
+}
+
+impl<A: Algorithm, W: AsyncWrite + Unpin> Drop for AsyncParallelEncWriter<A, W> {
+    fn drop(&mut self) {
+        // Like an EncWriter, an AsyncParallelEncWriter must be closed
+        // unless the stream cannot be completed anymore.
+        if !self.sealed
+            && !panicking()
+            && !hook::unclosed::<Self>()
+            && !cfg!(feature = "panic-free")
+        {
+            panic!("AsyncParallelEncWriter must be closed explicitly via the close method before being dropped!")
+        }
+    }
+}
+
+/// A fragment to be sealed by a worker. The key is kept by the workers
+/// such that jobs can be moved to them regardless of the `Algorithm`.
+struct Job {
+    nonce: Vec<u8>,
+    aad: Vec<u8>,
+    fragment: Vec<u8>,
+    slot: Arc<Slot>,
+}
+
+/// The sealed fragment of a `Job` and the waker of the task waiting
+/// for it.
+#[derive(Default)]
+struct Slot(Mutex<SlotState>);
+
+#[derive(Default)]
+struct SlotState {
+    fragment: Option<Result<Vec<u8>, Invalid>>,
+    waker: Option<Waker>,
+}
+
+impl Slot {
+    fn complete(&self, fragment: Result<Vec<u8>, Invalid>) {
+        let waker = {
+            let mut slot = self.0.lock().unwrap_or_else(|err| err.into_inner());
+            slot.fragment = Some(fragment);
+            slot.waker.take()
+        };
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+
+    fn poll_take(&self, cx: &mut Context<'_>) -> Poll<Result<Vec<u8>, Invalid>> {
+        let mut slot = self.0.lock().unwrap_or_else(|err| err.into_inner());
+        match slot.fragment.take() {
+            Some(fragment) => Poll::Ready(fragment),
+            None => {
+                slot.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+/// Seals the fragments of the `queue` until the writer has been dropped.
+fn seal_fragments<A: Algorithm>(key: &[u8; 32], queue: &Mutex<mpsc::Receiver<Job>>) {
+    let algorithm = A::new(key);
+    loop {
+        let job = queue.lock().unwrap_or_else(|err| err.into_inner()).recv();
+        let Job {
+            nonce,
+            aad,
+            mut fragment,
+            slot,
+        } = match job {
+            Ok(job) => job,
+            Err(_) => return,
+        };
+        let fragment = match algorithm.seal_in_place(&nonce, &aad, &mut fragment) {
+            Ok(_) => Ok(fragment),
+            Err(err) => Err(err),
+        };
+        slot.complete(fragment);
+    }
+}
+
+/// Wraps an asynchronous writer and decrypts and verifies everything
+/// written to it.
+///
+/// `AsyncDecWriter` is the counterpart of `DecWriter` for writers that
+/// implement the `AsyncWrite` trait of the `futures-io` crate - e.g. the
+/// I/O types of `async-std` and `smol`.
+///
+/// The plaintext of verified fragments is buffered until the inner writer
+/// accepts it. An `AsyncDecWriter` accepts at most one fragment of
+/// ciphertext per write and only once the plaintext of the previous
+/// fragment has been written to the inner writer.
+///
+/// As with a `DecWriter`, the `AsyncDecWriter` must be closed - using
+/// `AsyncWriteExt::close` - to verify that the stream is complete.
+///
+/// # Cancellation
+///
+/// An `AsyncDecWriter` provides the same cancellation guarantees as an
+/// `AsyncEncWriter`. A write that returns `Poll::Pending` has not consumed
+/// any ciphertext and partially written plaintext is tracked by the
+/// `AsyncDecWriter` itself. Hence, a dropped `write`, `flush` or `close`
+/// future can be resumed by polling the writer again.
+pub struct AsyncDecWriter<A: Algorithm, W: AsyncWrite + Unpin> {
+    inner: W,
+    writer: DecWriter<A, Vec<u8>>,
+    fragment_len: usize,
+
+    // The number of buffered plaintext bytes that have
+    // been written to the inner writer.
+    pos: usize,
+
+    // Whether the final fragment has been verified.
+    verified: bool,
+}
+
+impl<A: Algorithm, W: AsyncWrite + Unpin> AsyncDecWriter<A, W> {
+    /// Creates a new `AsyncDecWriter` with a default buffer size of 16 KiB.
+    pub fn new(inner: W, key: &Key<A>, nonce: Nonce<A>, aad: Aad<A>) -> Self {
+        Self::with_buffer_size(inner, key, nonce, aad, BUF_SIZE).unwrap()
+    }
+
+    /// Creates a new `AsyncDecWriter` with the specified buffer size as
+    /// fragment size. The `buf_size` must not be `0` nor greater than
+    /// `MAX_BUF_SIZE` and must match the buffer size used to encrypt
+    /// the data.
+    pub fn with_buffer_size(
+        inner: W,
+        key: &Key<A>,
+        nonce: Nonce<A>,
+        aad: Aad<A>,
+        buf_size: usize,
+    ) -> Result<Self, Invalid> {
+        Ok(AsyncDecWriter {
+            inner,
+            writer: DecWriter::with_buffer_size(Vec::default(), key, nonce, aad, buf_size)?,
+            fragment_len: buf_size + A::TAG_LEN,
+            pos: 0,
+            verified: false,
+        })
+    }
+
+    /// Writes the buffered plaintext to the inner writer.
+    fn poll_drain(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let pending = self.writer.get_mut();
+        match poll_write_buffer(&mut self.inner, cx, pending, &mut self.pos) {
+            Poll::Ready(Err(err)) => {
+                // Once the inner writer has failed, the stream cannot be
+                // completed anymore and the DecWriter must not panic when
+                // it gets dropped.
+                if !self.verified {
+                    self.verified = true;
+                    let _ = Close::close(&mut self.writer);
+                }
+                Poll::Ready(Err(err))
+            }
+            poll => poll,
+        }
+    }
+}
+
+// The `AsyncDecWriter` never pins any of its fields.
+impl<A: Algorithm, W: AsyncWrite + Unpin> Unpin for AsyncDecWriter<A, W> {}
+
+impl<A: Algorithm, W: AsyncWrite + Unpin> AsyncWrite for AsyncDecWriter<A, W> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        if this.verified {
+            return Poll::Ready(Err(io::Error::other("the writer has been closed")));
+        }
+        if let Err(err) = futures_ready(this.poll_drain(cx)) {
+            return err;
+        }
+        let n = buf.len().min(this.fragment_len);
+        Poll::Ready(this.writer.write(&buf[..n]))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        if let Err(err) = futures_ready(this.poll_drain(cx)) {
+            return err;
+        }
+        Pin::new(&mut this.inner).poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        if !this.verified {
+            this.verified = true;
+            if let Err(err) = Close::close(&mut this.writer) {
+                return Poll::Ready(Err(err));
+            }
+        }
+        if let Err(err) = futures_ready(this.poll_drain(cx)) {
+            return err;
+        }
+        Pin::new(&mut this.inner).poll_close(cx)
+    }
+}
+
+/// Wraps an asynchronous reader and decrypts and verifies everything
+/// read from it.
+///
+/// `AsyncDecReader` is the counterpart of `DecReader` for readers that
+/// implement the `AsyncRead` trait of the `futures-io` crate - e.g. the
+/// I/O types of `async-std` and `smol`. It decrypts streams produced by an
+/// `EncWriter` or `AsyncEncWriter` with the same parameters.
+///
+/// As with a `DecReader`, the plaintext of a fragment is only returned
+/// once the fragment has been verified. An `AsyncDecReader` only signals
+/// EOF once the final fragment has been verified. Hence, a truncated
+/// stream fails with an error of kind `InvalidData`.
+///
+/// # Examples
+///
+/// ```
+/// use futures_lite::future::block_on;
+/// use futures_lite::io::{AsyncReadExt, AsyncWriteExt};
+/// use sio::{Key, Nonce, Aad, AsyncDecReader, AsyncEncWriter, CHACHA20_POLY1305};
+///
+/// // Obviously, don't use this all-zeros key for anything real.
+/// let key: Key<CHACHA20_POLY1305> = Key::new([0; Key::<CHACHA20_POLY1305>::SIZE]);
+///
+/// block_on(async {
+///     let mut ciphertext: Vec<u8> = Vec::default();
+///     let mut writer = AsyncEncWriter::new(
+///         &mut ciphertext,
+///         &key,
+///         Nonce::new([0; Nonce::<CHACHA20_POLY1305>::SIZE]),
+///         Aad::empty(),
+///     );
+///     writer.write_all(b"Hello World").await.unwrap();
+///     writer.close().await.unwrap();
+///
+///     let mut reader = AsyncDecReader::new(
+///         ciphertext.as_slice(),
+///         &key,
+///         Nonce::new([0; Nonce::<CHACHA20_POLY1305>::SIZE]),
+///         Aad::empty(),
+///     );
+///     let mut plaintext = String::default();
+///     reader.read_to_string(&mut plaintext).await.unwrap();
+///     assert_eq!(plaintext, "Hello World");
+/// });
+/// ```
+pub struct AsyncDecReader<A: Algorithm, R: AsyncRead + Unpin> {
+    inner: R,
+    algorithm: A,
+    framing: Sio,
+    buffer: Box<[u8]>,
+    buf_size: usize,
+
+    // The number of ciphertext bytes in the buffer.
+    len: usize,
+
+    // The range of decrypted plaintext bytes in the buffer
+    // that have not been returned yet.
+    pos: usize,
+    end: usize,
+
+    // Whether the inner reader has returned EOF.
+    eof: bool,
+
+    // Whether the final fragment has been verified.
+    done: bool,
+
+    // Whether a fragment could not be verified.
+    errored: bool,
+}
+
+impl<A: Algorithm, R: AsyncRead + Unpin> AsyncDecReader<A, R> {
+    /// Creates a new `AsyncDecReader` with a default buffer size of 16 KiB.
+    pub fn new(inner: R, key: &Key<A>, nonce: Nonce<A>, aad: Aad<A>) -> Self {
+        Self::with_buffer_size(inner, key, nonce, aad, BUF_SIZE).unwrap()
+    }
+
+    /// Creates a new `AsyncDecReader` with the specified buffer size as
+    /// fragment size. The `buf_size` must not be `0` nor greater than
+    /// `MAX_BUF_SIZE` and must match the buffer size used to encrypt
+    /// the data.
+    pub fn with_buffer_size(
+        inner: R,
+        key: &Key<A>,
+        nonce: Nonce<A>,
+        aad: Aad<A>,
+        buf_size: usize,
+    ) -> Result<Self, Invalid> {
+        if buf_size == 0 || buf_size > MAX_BUF_SIZE {
+            return Err(Invalid::BufSize);
+        }
+        let algorithm = A::new(key.as_ref());
+        let mut framing = Sio::default();
+        framing.init(key.as_ref(), &algorithm, nonce.as_ref(), aad.as_ref())?;
+
+        // We read one byte more than a fragment to detect
+        // whether a fragment is the final one.
+        Ok(AsyncDecReader {
+            inner,
+            algorithm,
+            framing,
+            buffer: vec![0; buf_size + A::TAG_LEN + 1].into_boxed_slice(),
+            buf_size,
+            len: 0,
+            pos: 0,
+            end: 0,
+            eof: false,
+            done: false,
+            errored: false,
+        })
+    }
+
+    /// Reads, decrypts and verifies the next fragment.
+    fn poll_fragment(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let fragment_len = self.buf_size + A::TAG_LEN;
+        if self.len > fragment_len {
+            // Keep the first byte of the next fragment.
+            self.buffer[0] = self.buffer[fragment_len];
+            self.len = 1;
+        }
+        while !self.eof && self.len < self.buffer.len() {
+            match Pin::new(&mut self.inner).poll_read(cx, &mut self.buffer[self.len..]) {
+                Poll::Ready(Ok(0)) => self.eof = true,
+                Poll::Ready(Ok(n)) => self.len += n,
+                Poll::Ready(Err(ref err)) if err.kind() == io::ErrorKind::Interrupted => (),
+                Poll::Ready(Err(err)) => return Poll::Ready(Err(err)),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+
+        let last = self.len <= fragment_len;
+        let len = self.len.min(fragment_len);
+        if len < A::TAG_LEN {
+            self.errored = true;
+            return Poll::Ready(Err(NotAuthentic.into()));
+        }
+        let (nonce, aad) = self.framing.next(&mut self.algorithm, last)?;
+        match self
+            .algorithm
+            .open_in_place(nonce, aad, &mut self.buffer[..len])
+        {
+            Ok(plaintext) => {
+                self.pos = 0;
+                self.end = plaintext.len();
+                self.done = last;
+                Poll::Ready(Ok(()))
+            }
+            Err(err) => {
+                self.errored = true;
+                Poll::Ready(Err(err.into()))
+            }
+        }
+    }
+}
+
+// The `AsyncDecReader` never pins any of its fields.
+impl<A: Algorithm, R: AsyncRead + Unpin> Unpin for AsyncDecReader<A, R> {}
+
+impl<A: Algorithm, R: AsyncRead + Unpin> AsyncRead for AsyncDecReader<A, R> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        loop {
+            if this.pos < this.end {
+                let n = buf.len().min(this.end - this.pos);
+                buf[..n].copy_from_slice(&this.buffer[this.pos..this.pos + n]);
+                this.pos += n;
+                return Poll::Ready(Ok(n));
+            }
+            if this.errored {
+                return Poll::Ready(Err(NotAuthentic.into()));
+            }
+            if this.done || buf.is_empty() {
+                return Poll::Ready(Ok(0));
+            }
+            if let Err(err) = futures_ready(this.poll_fragment(cx)) {
+                return err;
+            }
+        }
+    }
+}
+
+/// An `AsyncDecReader` is a `Stream` of verified plaintext chunks. Hence,
+/// it can be used in `StreamExt` pipelines.
+///
+/// Every item is the plaintext of one fragment - or the rest of it if the
+/// fragment has been read partially before. The stream ends once the final
+/// fragment has been verified. If a fragment cannot be verified, the stream
+/// yields an error of kind `InvalidData` and ends.
+///
+/// # Examples
+///
+/// ```
+/// use futures_lite::future::block_on;
+/// use futures_lite::io::AsyncWriteExt;
+/// use futures_lite::StreamExt;
+/// use sio::{Key, Nonce, Aad, AsyncDecReader, AsyncEncWriter, CHACHA20_POLY1305};
+///
+/// // Obviously, don't use this all-zeros key for anything real.
+/// let key: Key<CHACHA20_POLY1305> = Key::new([0; Key::<CHACHA20_POLY1305>::SIZE]);
+///
+/// block_on(async {
+///     let mut ciphertext: Vec<u8> = Vec::default();
+///     let mut writer = AsyncEncWriter::new(
+///         &mut ciphertext,
+///         &key,
+///         Nonce::new([0; Nonce::<CHACHA20_POLY1305>::SIZE]),
+///         Aad::empty(),
+///     );
+///     writer.write_all(b"Hello World").await.unwrap();
+///     writer.close().await.unwrap();
+///
+///     let mut reader = AsyncDecReader::new(
+///         ciphertext.as_slice(),
+///         &key,
+///         Nonce::new([0; Nonce::<CHACHA20_POLY1305>::SIZE]),
+///         Aad::empty(),
+///     );
+///     let chunk = reader.next().await.unwrap().unwrap();
+///     assert_eq!(chunk, "Hello World");
+///     assert!(reader.next().await.is_none());
+/// });
+/// ```
+#[cfg(feature = "futures")]
+impl<A: Algorithm, R: AsyncRead + Unpin> Stream for AsyncDecReader<A, R> {
+    type Item = io::Result<Bytes>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        loop {
+            if this.pos < this.end {
+                let chunk = Bytes::copy_from_slice(&this.buffer[this.pos..this.end]);
+                this.pos = this.end;
+                return Poll::Ready(Some(Ok(chunk)));
+            }
+            if this.errored || this.done {
+                return Poll::Ready(None);
+            }
+            match this.poll_fragment(cx) {
+                Poll::Ready(Ok(())) => (),
+                Poll::Ready(Err(err)) => return Poll::Ready(Some(Err(err))),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+/// Wraps an asynchronous reader that implements `AsyncSeek` and decrypts
+/// and verifies the fragments covering the data read from it.
+///
+/// `AsyncSeekDecReader` is the asynchronous counterpart of `decrypt_range`.
+/// It implements `AsyncRead` and `AsyncSeek` over the plaintext of a stream
+/// that has been produced by an `EncWriter` with the default `Sio` framing
+/// and the given buffer size. Seeking is cheap since the reader maps the
+/// plaintext position to the fragment containing it and only reads,
+/// decrypts and verifies this fragment on the next read. Hence, async
+/// services can use it to serve range requests from encrypted files.
+///
+/// The size of the stream is determined by seeking to the end of the
+/// inner reader once. Since only the fragments that are read are verified,
+/// an `AsyncSeekDecReader` cannot detect that a stream has been truncated
+/// at a fragment boundary - unless the final fragment is read.
+///
+/// Tokio users can wrap a `tokio::fs::File` with the `compat` adapter of
+/// the `tokio-util` crate to obtain a `futures-io` reader.
+///
+/// # Examples
+///
+/// ```
+/// use futures_lite::future::block_on;
+/// use futures_lite::io::{AsyncReadExt, AsyncSeekExt, Cursor};
+/// use std::io::{SeekFrom, Write};
+/// use sio::{Key, Nonce, Aad, AsyncSeekDecReader, EncWriter, CHACHA20_POLY1305};
+///
+/// // Obviously, don't use this all-zeros key for anything real.
+/// let key: Key<CHACHA20_POLY1305> = Key::new([0; Key::<CHACHA20_POLY1305>::SIZE]);
+///
+/// let mut ciphertext: Vec<u8> = Vec::default();
+/// let mut writer = EncWriter::with_buffer_size(
+///     &mut ciphertext,
+///     &key,
+///     Nonce::new([0; Nonce::<CHACHA20_POLY1305>::SIZE]),
+///     Aad::empty(),
+///     4,
+/// )
+/// .unwrap();
+/// writer.write_all(b"Hello World").unwrap();
+/// writer.close().unwrap();
+///
+/// block_on(async {
+///     let mut reader = AsyncSeekDecReader::with_buffer_size(
+///         Cursor::new(ciphertext),
+///         &key,
+///         Nonce::new([0; Nonce::<CHACHA20_POLY1305>::SIZE]),
+///         Aad::empty(),
+///         4,
+///     )
+///     .unwrap();
+///     reader.seek(SeekFrom::Start(6)).await.unwrap();
+///     let mut plaintext = String::default();
+///     reader.read_to_string(&mut plaintext).await.unwrap();
+///     assert_eq!(plaintext, "World");
+/// });
+/// ```
+pub struct AsyncSeekDecReader<A: Algorithm, R: AsyncRead + AsyncSeek + Unpin> {
+    inner: R,
+    algorithm: A,
+    framing: Sio,
+    layout: Layout,
+    buffer: Box<[u8]>,
+
+    // The plaintext position of the reader.
+    pos: u64,
+
+    // The ciphertext and plaintext size of the stream once it is known.
+    size: Option<(u64, u64)>,
+
+    // The position of the inner reader if it is known.
+    inner_pos: Option<u64>,
+
+    // The index of the fragment whose plaintext is in the buffer.
+    fragment: Option<u64>,
+
+    // The index of the fragment that is being read and the number
+    // of its ciphertext bytes in the buffer.
+    reading: Option<u64>,
+    len: usize,
+}
+
+impl<A: Algorithm, R: AsyncRead + AsyncSeek + Unpin> AsyncSeekDecReader<A, R> {
+    /// Creates a new `AsyncSeekDecReader` with a default buffer size of
+    /// 16 KiB.
+    pub fn new(inner: R, key: &Key<A>, nonce: Nonce<A>, aad: Aad<A>) -> Self {
+        Self::with_buffer_size(inner, key, nonce, aad, BUF_SIZE).unwrap()
+    }
+
+    /// Creates a new `AsyncSeekDecReader` with the specified buffer size
+    /// as fragment size. The `buf_size` must not be `0` nor greater than
+    /// `MAX_BUF_SIZE` and must match the buffer size used to encrypt
+    /// the data.
+    pub fn with_buffer_size(
+        inner: R,
+        key: &Key<A>,
+        nonce: Nonce<A>,
+        aad: Aad<A>,
+        buf_size: usize,
+    ) -> Result<Self, Invalid> {
+        let layout = Layout::of::<A>(buf_size)?;
+        let algorithm = A::new(key.as_ref());
+        let mut framing = Sio::default();
+        framing.init(key.as_ref(), &algorithm, nonce.as_ref(), aad.as_ref())?;
+        Ok(AsyncSeekDecReader {
+            inner,
+            algorithm,
+            framing,
+            layout,
+            buffer: vec![0; layout.fragment_len() as usize].into_boxed_slice(),
+            pos: 0,
+            size: None,
+            inner_pos: None,
+            fragment: None,
+            reading: None,
+            len: 0,
+        })
+    }
+
+    /// Determines the ciphertext and plaintext size of the stream by
+    /// seeking to the end of the inner reader.
+    fn poll_size(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<(u64, u64)>> {
+        if let Some(size) = self.size {
+            return Poll::Ready(Ok(size));
+        }
+        self.inner_pos = None;
+        match Pin::new(&mut self.inner).poll_seek(cx, SeekFrom::End(0)) {
+            Poll::Ready(Ok(size)) => {
+                self.inner_pos = Some(size);
+                let plaintext_len = self.layout.plaintext_len(size).ok_or(NotAuthentic)?;
+                self.size = Some((size, plaintext_len));
+                Poll::Ready(Ok((size, plaintext_len)))
+            }
+            Poll::Ready(Err(err)) => Poll::Ready(Err(err)),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+
+    /// Reads, decrypts and verifies the fragment with the given `index`.
+    fn poll_fragment(&mut self, cx: &mut Context<'_>, index: u64) -> Poll<io::Result<()>> {
+        let size = match self.poll_size(cx) {
+            Poll::Ready(Ok((size, _))) => size,
+            Poll::Ready(Err(err)) => return Poll::Ready(Err(err)),
+            Poll::Pending => return Poll::Pending,
+        };
+
+        // Even an empty stream consists of one (final) fragment.
+        let fragment_len = self.layout.fragment_len();
+        let final_index = size.saturating_sub(1) / fragment_len;
+        let offset = index * fragment_len;
+        let len = if index == final_index {
+            (size - offset) as usize
+        } else {
+            fragment_len as usize
+        };
+
+        if self.reading != Some(index) {
+            self.fragment = None;
+            self.reading = Some(index);
+            self.len = 0;
+        }
+        if self.inner_pos != Some(offset + self.len as u64) {
+            self.inner_pos = None;
+            let pos = SeekFrom::Start(offset + self.len as u64);
+            match Pin::new(&mut self.inner).poll_seek(cx, pos) {
+                Poll::Ready(Ok(pos)) => self.inner_pos = Some(pos),
+                Poll::Ready(Err(err)) => return Poll::Ready(Err(err)),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+        while self.len < len {
+            match Pin::new(&mut self.inner).poll_read(cx, &mut self.buffer[self.len..len]) {
+                Poll::Ready(Ok(0)) => {
+                    return Poll::Ready(Err(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        "failed to read the entire fragment",
+                    )))
+                }
+                Poll::Ready(Ok(n)) => {
+                    self.len += n;
+                    self.inner_pos = self.inner_pos.map(|pos| pos + n as u64);
+                }
+                Poll::Ready(Err(ref err)) if err.kind() == io::ErrorKind::Interrupted => (),
+                Poll::Ready(Err(err)) => return Poll::Ready(Err(err)),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+
+        self.reading = None;
+        self.framing.seek(index)?;
+        let (nonce, aad) = self
+            .framing
+            .next(&mut self.algorithm, index == final_index)?;
+        self.algorithm
+            .open_in_place(nonce, aad, &mut self.buffer[..len])?;
+        self.fragment = Some(index);
+        Poll::Ready(Ok(()))
+    }
+}
+
+// The `AsyncSeekDecReader` never pins any of its fields.
+impl<A: Algorithm, R: AsyncRead + AsyncSeek + Unpin> Unpin for AsyncSeekDecReader<A, R> {}
+
+impl<A: Algorithm, R: AsyncRead + AsyncSeek + Unpin> AsyncRead for AsyncSeekDecReader<A, R> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        let plaintext_len = match this.poll_size(cx) {
+            Poll::Ready(Ok((_, plaintext_len))) => plaintext_len,
+            Poll::Ready(Err(err)) => return Poll::Ready(Err(err)),
+            Poll::Pending => return Poll::Pending,
+        };
+        if this.pos >= plaintext_len || buf.is_empty() {
+            return Poll::Ready(Ok(0));
+        }
+
+        let buf_size = this.layout.buf_size() as u64;
+        let index = this.pos / buf_size;
+        if this.fragment != Some(index) {
+            if let Err(err) = futures_ready(this.poll_fragment(cx, index)) {
+                return err;
+            }
+        }
+        let end = (plaintext_len - index * buf_size).min(buf_size) as usize;
+        let offset = (this.pos - index * buf_size) as usize;
+        let n = buf.len().min(end - offset);
+        buf[..n].copy_from_slice(&this.buffer[offset..offset + n]);
+        this.pos += n as u64;
+        Poll::Ready(Ok(n))
+    }
+}
+
+impl<A: Algorithm, R: AsyncRead + AsyncSeek + Unpin> AsyncSeek for AsyncSeekDecReader<A, R> {
+    fn poll_seek(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        pos: SeekFrom,
+    ) -> Poll<io::Result<u64>> {
+        let this = self.get_mut();
+        let pos = match pos {
+            SeekFrom::Start(pos) => Some(pos),
+            SeekFrom::End(offset) => match this.poll_size(cx) {
+                Poll::Ready(Ok((_, plaintext_len))) => plaintext_len.checked_add_signed(offset),
+                Poll::Ready(Err(err)) => return Poll::Ready(Err(err)),
+                Poll::Pending => return Poll::Pending,
+            },
+            SeekFrom::Current(offset) => this.pos.checked_add_signed(offset),
+        };
+        match pos {
+            Some(pos) => {
+                this.pos = pos;
+                Poll::Ready(Ok(pos))
+            }
+            None => Poll::Ready(Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "invalid seek to a negative or overflowing position",
+            ))),
+        }
+    }
+}
+
+/// Writes `buffer[*pos..]` to the `inner` writer and clears the
+/// `buffer` once all of it has been written.
+fn poll_write_buffer<W: AsyncWrite + Unpin>(
+    inner: &mut W,
+    cx: &mut Context<'_>,
+    buffer: &mut Vec<u8>,
+    pos: &mut usize,
+) -> Poll<io::Result<()>> {
+    while *pos < buffer.len() {
+        match Pin::new(&mut *inner).poll_write(cx, &buffer[*pos..]) {
+            Poll::Ready(Ok(0)) => {
+                return Poll::Ready(Err(io::Error::new(
+                    io::ErrorKind::WriteZero,
+                    "failed to write the buffered data",
+                )))
+            }
+            Poll::Ready(Ok(n)) => *pos += n,
+            Poll::Ready(Err(err)) => return Poll::Ready(Err(err)),
+            Poll::Pending => return Poll::Pending,
+        }
+    }
+    buffer.clear();
+    *pos = 0;
+    Poll::Ready(Ok(()))
+}
+
+/// Returns `Ok(())` if the `poll` has completed successfully and the
+/// poll to return to the caller otherwise.
+fn futures_ready<T>(poll: Poll<io::Result<()>>) -> Result<(), Poll<io::Result<T>>> {
+    match poll {
+        Poll::Ready(Ok(())) => Ok(()),
+        Poll::Ready(Err(err)) => Err(Poll::Ready(Err(err))),
+        Poll::Pending => Err(Poll::Pending),
+    }
+}
@@ -0,0 +1,127 @@
+// Copyright (c) 2019 Andreas Auernhammer. All rights reserved.
+// Use of this source code is governed by a license that can be
+// found in the LICENSE file.
+
+//! Reporting of authentication failures.
+//!
+//! Whenever a `DecWriter` or `DecReader` encounters a fragment that is
+//! not authentic, it reports an `Event` to the audit hook - if one has
+//! been registered using `set_hook`. This allows routing tamper events
+//! to a central place - e.g. a SIEM - without wrapping every decryption
+//! call site.
+//!
+//! # Examples
+//!
+//! ```
+//! use std::io::Write;
+//! use sio::{Key, Nonce, Aad, DecWriter, CHACHA20_POLY1305};
+//!
+//! sio::audit::set_hook(Box::new(|event| {
+//!     eprintln!(
+//!         "tampered fragment {} at offset {}",
+//!         event.sequence(),
+//!         event.offset(),
+//!     );
+//! }));
+//!
+//! // Obviously, don't use this all-zeros key for anything real.
+//! let key: Key<CHACHA20_POLY1305> = Key::new([0; Key::<CHACHA20_POLY1305>::SIZE]);
+//!
+//! let mut writer = DecWriter::new(
+//!     Vec::default(),
+//!     &key,
+//!     Nonce::new([0; Nonce::<CHACHA20_POLY1305>::SIZE]),
+//!     Aad::empty(),
+//! );
+//! writer.write_all(&[0; 64]).unwrap();
+//! assert!(writer.close().is_err());
+//!
+//! let _ = sio::audit::take_hook();
+//! ```
+
+use std::sync::RwLock;
+
+/// The type of an audit hook.
+pub type Hook = Box<dyn Fn(&Event) + Send + Sync + 'static>;
+
+static HOOK: RwLock<Option<Hook>> = RwLock::new(None);
+
+/// Registers a new audit hook, replacing any previously registered one.
+///
+/// The hook is invoked for every fragment that is not authentic. It must
+/// not call `set_hook` or `take_hook` itself.
+pub fn set_hook(hook: Hook) {
+    *HOOK.write().unwrap_or_else(|err| err.into_inner()) = Some(hook);
+}
+
+/// Unregisters the current audit hook and returns it.
+pub fn take_hook() -> Option<Hook> {
+    HOOK.write().unwrap_or_else(|err| err.into_inner()).take()
+}
+
+/// An authentication failure reported to the audit hook.
+#[derive(Clone, Debug)]
+pub struct Event {
+    sequence: u64,
+    offset: u64,
+    len: usize,
+    aad_fingerprint: [u8; 16],
+}
+
+impl Event {
+    /// Returns the index of the fragment that is not authentic.
+    #[inline]
+    pub fn sequence(&self) -> u64 {
+        self.sequence
+    }
+
+    /// Returns the offset of the fragment within the ciphertext stream.
+    #[inline]
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Returns the length of the fragment in bytes.
+    #[inline]
+    #[allow(clippy::len_without_is_empty)]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns the first 16 bytes of the SHA-256 hash of the associated
+    /// data of the stream. It allows correlating events without revealing
+    /// the associated data itself.
+    #[inline]
+    pub fn aad_fingerprint(&self) -> &[u8; 16] {
+        &self.aad_fingerprint
+    }
+}
+
+/// Reports the authentication failures of one stream to the audit hook.
+pub(crate) struct Audit {
+    aad_fingerprint: [u8; 16],
+}
+
+impl Audit {
+    pub fn new(aad: &[u8]) -> Self {
+        use ring::digest;
+
+        let mut aad_fingerprint = [0; 16];
+        aad_fingerprint.copy_from_slice(&digest::digest(&digest::SHA256, aad).as_ref()[..16]);
+        Audit { aad_fingerprint }
+    }
+
+    /// Reports that the fragment with the given `sequence` number,
+    /// ciphertext `offset` and length `len` is not authentic.
+    pub fn report(&self, sequence: u64, offset: u64, len: usize) {
+        let hook = HOOK.read().unwrap_or_else(|err| err.into_inner());
+        if let Some(ref hook) = *hook {
+            hook(&Event {
+                sequence,
+                offset,
+                len,
+                aad_fingerprint: self.aad_fingerprint,
+            });
+        }
+    }
+}
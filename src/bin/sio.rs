@@ -0,0 +1,251 @@
+// Copyright (c) 2019 Andreas Auernhammer. All rights reserved.
+// Use of this source code is governed by a license that can be
+// found in the LICENSE file.
+
+//! A command line tool to encrypt, decrypt, verify and inspect streams.
+//!
+//! It encrypts with ChaCha20-Poly1305 and the default `Sio` framing
+//! such that it can serve as reference for other implementations of
+//! the format. Run `sio help` for usage information.
+
+use sio::{Aad, DecReader, EncWriter, Key, Layout, Nonce, NopCloser, CHACHA20_POLY1305};
+use std::env;
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::process;
+
+const USAGE: &str = "\
+Usage: sio <command> [options]
+
+Commands:
+  encrypt    Encrypt the input and write the ciphertext to the output.
+  decrypt    Decrypt and verify the input and write the plaintext to the output.
+  verify     Verify the input and print the size of its plaintext.
+  inspect    Print the format version and size of the input. No key is required.
+  help       Print this message.
+
+Options:
+  -i, --in <path>            Read the input from <path> instead of stdin.
+  -o, --out <path>           Write the output to <path> instead of stdout.
+  --key-file <path>          Read the key from <path> - 32 raw bytes or 64 hex characters.
+  --key-env <name>           Read the key from the environment variable <name> as 64 hex characters.
+  --passphrase-env <name>    Derive the key from the passphrase in the environment variable <name>.
+  --nonce <hex>              The 8 byte nonce as 16 hex characters. Default: all zeros.
+  --aad <string>             The associated data. Default: empty.
+  --buffer-size <n>          The fragment size in bytes. Default: 16384.
+
+A key must never encrypt two streams with the same nonce. Passphrase-encrypted
+streams use a random salt and neither require nor accept a nonce.";
+
+#[allow(clippy::upper_case_acronyms)]
+type AEAD = CHACHA20_POLY1305;
+
+enum Secret {
+    Key(Key<AEAD>),
+    Passphrase(Vec<u8>),
+}
+
+#[derive(Default)]
+struct Options {
+    input: Option<String>,
+    output: Option<String>,
+    key_file: Option<String>,
+    key_env: Option<String>,
+    passphrase_env: Option<String>,
+    nonce: Option<String>,
+    aad: String,
+    buf_size: Option<usize>,
+}
+
+impl Options {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut options = Options::default();
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or(format!("missing value of '{}'", arg));
+            match arg.as_str() {
+                "-i" | "--in" => options.input = Some(value()?),
+                "-o" | "--out" => options.output = Some(value()?),
+                "--key-file" => options.key_file = Some(value()?),
+                "--key-env" => options.key_env = Some(value()?),
+                "--passphrase-env" => options.passphrase_env = Some(value()?),
+                "--nonce" => options.nonce = Some(value()?),
+                "--aad" => options.aad = value()?,
+                "--buffer-size" => {
+                    let n = value()?;
+                    let n = n
+                        .parse()
+                        .map_err(|_| format!("invalid buffer size '{}'", n))?;
+                    options.buf_size = Some(n);
+                }
+                _ => return Err(format!("unknown option '{}'", arg)),
+            }
+        }
+        Ok(options)
+    }
+
+    fn input(&self) -> io::Result<Box<dyn Read>> {
+        Ok(match self.input {
+            Some(ref path) => Box::new(File::open(path)?),
+            None => Box::new(io::stdin()),
+        })
+    }
+
+    fn output(&self) -> io::Result<Box<dyn Write>> {
+        Ok(match self.output {
+            Some(ref path) => Box::new(File::create(path)?),
+            None => Box::new(io::stdout()),
+        })
+    }
+
+    fn secret(&self) -> Result<Secret, String> {
+        let key = match (&self.key_file, &self.key_env, &self.passphrase_env) {
+            (Some(path), None, None) => {
+                let key = std::fs::read(path).map_err(|err| format!("{}: {}", path, err))?;
+                if key.len() == Key::<AEAD>::SIZE {
+                    key
+                } else {
+                    let key = String::from_utf8(key).map_err(|_| "invalid key file")?;
+                    decode_hex(key.trim()).ok_or("invalid key file")?
+                }
+            }
+            (None, Some(name), None) => {
+                let key = env::var(name).map_err(|_| format!("{} is not set", name))?;
+                decode_hex(key.trim()).ok_or("invalid key")?
+            }
+            (None, None, Some(name)) => {
+                let passphrase = env::var(name).map_err(|_| format!("{} is not set", name))?;
+                if self.nonce.is_some() {
+                    return Err("a passphrase does not accept a nonce".into());
+                }
+                return Ok(Secret::Passphrase(passphrase.into_bytes()));
+            }
+            (None, None, None) => return Err("no key specified".into()),
+            _ => return Err("more than one key specified".into()),
+        };
+        let mut bytes = [0; Key::<AEAD>::SIZE];
+        if key.len() != bytes.len() {
+            return Err("the key must be 32 bytes long".into());
+        }
+        bytes.copy_from_slice(&key);
+        Ok(Secret::Key(Key::new(bytes)))
+    }
+
+    fn nonce(&self) -> Result<Nonce<AEAD>, String> {
+        let mut nonce = [0; Nonce::<AEAD>::SIZE];
+        if let Some(ref hex) = self.nonce {
+            match decode_hex(hex) {
+                Some(ref v) if v.len() == nonce.len() => nonce.copy_from_slice(v),
+                _ => return Err("the nonce must be 16 hex characters".into()),
+            }
+        }
+        Ok(Nonce::new(nonce))
+    }
+
+    fn buf_size(&self) -> usize {
+        self.buf_size.unwrap_or(sio::BUF_SIZE)
+    }
+
+    fn reader(&self) -> Result<DecReader<AEAD, Box<dyn Read>>, String> {
+        let input = self.input().map_err(|err| err.to_string())?;
+        let reader = match self.secret()? {
+            Secret::Key(key) => DecReader::with_buffer_size(
+                input,
+                &key,
+                self.nonce()?,
+                Aad::from(self.aad.as_bytes()),
+                self.buf_size(),
+            )
+            .map_err(|err| err.to_string())?,
+            Secret::Passphrase(passphrase) => {
+                DecReader::with_password(input, &passphrase).map_err(|err| err.to_string())?
+            }
+        };
+        Ok(reader)
+    }
+}
+
+fn decode_hex(s: &str) -> Option<Vec<u8>> {
+    if s.len() % 2 != 0 || !s.is_ascii() {
+        return None;
+    }
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&s[i..i + 2], 16).ok())
+        .collect()
+}
+
+fn encrypt(options: &Options) -> Result<(), String> {
+    let input = options.input().map_err(|err| err.to_string())?;
+    let output = NopCloser::wrap(options.output().map_err(|err| err.to_string())?);
+    let mut writer = match options.secret()? {
+        Secret::Key(key) => EncWriter::with_buffer_size(
+            output,
+            &key,
+            options.nonce()?,
+            Aad::from(options.aad.as_bytes()),
+            options.buf_size(),
+        )
+        .map_err(|err| err.to_string())?,
+        Secret::Passphrase(passphrase) => {
+            if options.buf_size.is_some() {
+                return Err("a passphrase does not accept a buffer size".into());
+            }
+            EncWriter::with_password(output, &passphrase).map_err(|err| err.to_string())?
+        }
+    };
+    let r = writer.copy_from(input);
+    let c = writer.close();
+    r.and(c).map(|_| ()).map_err(|err| err.to_string())
+}
+
+fn decrypt(options: &Options) -> Result<(), String> {
+    let mut reader = options.reader()?;
+    let mut output = options.output().map_err(|err| err.to_string())?;
+    io::copy(&mut reader, &mut output).map_err(|err| err.to_string())?;
+    output.flush().map_err(|err| err.to_string())
+}
+
+fn verify(options: &Options) -> Result<(), String> {
+    let mut reader = options.reader()?;
+    let n = io::copy(&mut reader, &mut io::sink()).map_err(|err| err.to_string())?;
+    println!("{}", n);
+    Ok(())
+}
+
+fn inspect(options: &Options) -> Result<(), String> {
+    let input = options.input().map_err(|err| err.to_string())?;
+    let info = sio::inspect(input).map_err(|err| err.to_string())?;
+    println!("version: {:?}", info.version());
+    println!("header: {} bytes", info.header_len());
+    println!("size: {} bytes", info.len());
+    let layout = Layout::of::<AEAD>(options.buf_size()).map_err(|err| err.to_string())?;
+    match info.plaintext_len(&layout) {
+        Some(n) => println!("plaintext: {} bytes", n),
+        None => println!("plaintext: unknown"),
+    }
+    Ok(())
+}
+
+fn main() {
+    let mut args = env::args().skip(1);
+    let command = args.next().unwrap_or_default();
+    let run = match command.as_str() {
+        "encrypt" => encrypt,
+        "decrypt" => decrypt,
+        "verify" => verify,
+        "inspect" => inspect,
+        "help" | "-h" | "--help" => {
+            println!("{}", USAGE);
+            return;
+        }
+        _ => {
+            eprintln!("{}", USAGE);
+            process::exit(2);
+        }
+    };
+    let result = Options::parse(args).and_then(|options| run(&options));
+    if let Err(err) = result {
+        eprintln!("sio: {}", err);
+        process::exit(1);
+    }
+}
@@ -18,6 +18,7 @@ impl Algorithm for CHACHA20_POLY1305 {
     const KEY_LEN: usize = 256 / 8;
     const NONCE_LEN: usize = 96 / 8;
     const TAG_LEN: usize = 128 / 8;
+    const JWK_ALG: Option<&'static str> = Some("C20P");
 
     fn new(key: &[u8; Self::KEY_LEN]) -> Self {
         Self {
@@ -28,13 +29,13 @@ impl Algorithm for CHACHA20_POLY1305 {
 
     fn seal_in_place<'a>(
         &self,
-        nonce: &[u8; Self::NONCE_LEN],
+        nonce: &[u8],
         aad: &[u8],
         in_out: &'a mut [u8],
     ) -> Result<&'a [u8], Invalid> {
         match aead::seal_in_place(
             &self.seal_key,
-            aead::Nonce::assume_unique_for_key(*nonce),
+            aead::Nonce::try_assume_unique_for_key(nonce).map_err(|_| Invalid::Nonce)?,
             aead::Aad::from(aad),
             in_out,
             Self::TAG_LEN,
@@ -46,13 +47,13 @@ impl Algorithm for CHACHA20_POLY1305 {
 
     fn open_in_place<'a>(
         &self,
-        nonce: &[u8; Self::NONCE_LEN],
+        nonce: &[u8],
         aad: &[u8],
         in_out: &'a mut [u8],
     ) -> Result<&'a [u8], NotAuthentic> {
         match aead::open_in_place(
             &self.open_key,
-            aead::Nonce::assume_unique_for_key(*nonce),
+            aead::Nonce::try_assume_unique_for_key(nonce).map_err(|_| NotAuthentic)?,
             aead::Aad::from(aad),
             0,
             in_out,
@@ -0,0 +1,574 @@
+// Copyright (c) 2019 Andreas Auernhammer. All rights reserved.
+// Use of this source code is governed by a license that can be
+// found in the LICENSE file.
+
+//! Content-defined chunking of encrypted streams.
+//!
+//! <p style="margin-left: 40px; margin-right: 50px; border:1px; border-style:solid; border-color:#000000; padding: 0.3em">
+//!      <b>The chunks are encrypted deterministically. The ciphertext leaks
+//!      whether two chunks - encrypted with the same key and associated
+//!      data - contain the same plaintext.</b> Only use this module when
+//!      deduplication of encrypted data is required. Otherwise, use an
+//!      <code>EncWriter</code>.
+//! </p>
+//!
+//! An `EncWriter` splits a stream into fragments of a fixed size. Inserting
+//! a single byte shifts all subsequent fragment boundaries and changes the
+//! ciphertext of every following fragment. A `CdcEncWriter` places the
+//! boundaries of its chunks where the content - a rolling hash computed by
+//! the FastCDC algorithm - matches a pattern. Hence, an insertion only
+//! changes the chunks around it and the boundaries of all other chunks
+//! remain where they were.
+//!
+//! Every chunk is encrypted with a nonce derived from the key, the
+//! associated data and the chunk itself. Therefore, an unchanged chunk
+//! always produces the same record and an incremental backup only has to
+//! upload the records of changed chunks - e.g. by storing the records in
+//! a content-addressed store and splitting the ciphertext via `records`.
+//!
+//! The ciphertext consists of one record per chunk followed by a final
+//! record. A chunk record consists of the 4 byte big-endian length of the
+//! chunk, the nonce and the ciphertext of the chunk including the tag. The
+//! length is authenticated as part of the associated data of the chunk.
+//! The final record consists of the 4 bytes `0x80 0 0 0` and a tag that
+//! authenticates a SHA-256 digest of all chunk records. Hence, the
+//! `CdcDecWriter` detects whether chunks have been modified, reordered,
+//! removed or whether the stream has been truncated.
+//!
+//! # Examples
+//!
+//! ```
+//! use std::io::Write;
+//! use sio::{Key, Aad, CHACHA20_POLY1305};
+//! use sio::cdc::{CdcDecWriter, CdcEncWriter};
+//!
+//! // Obviously, don't use this all-zeros key for anything real.
+//! let key: Key<CHACHA20_POLY1305> = Key::new([0; Key::<CHACHA20_POLY1305>::SIZE]);
+//!
+//! let mut ciphertext: Vec<u8> = Vec::default();
+//! let mut writer = CdcEncWriter::new(&mut ciphertext, &key, Aad::empty());
+//! writer.write_all(b"Hello World").unwrap();
+//! writer.close().unwrap();
+//!
+//! let mut plaintext: Vec<u8> = Vec::default();
+//! let mut writer = CdcDecWriter::new(&mut plaintext, &key, Aad::empty());
+//! writer.write_all(&ciphertext).unwrap();
+//! writer.close().unwrap();
+//!
+//! assert_eq!(plaintext, b"Hello World");
+//! ```
+
+use super::hook;
+use super::writer::internal;
+use super::{Aad, Algorithm, Invalid, Key, NotAuthentic, TrailingData, MAX_BUF_SIZE};
+use ring::{digest, hmac};
+use std::io::{self, Write};
+use std::thread::panicking;
+
+/// The length of the header of every record.
+pub const HEADER_LEN: usize = 4;
+
+/// The header flag of the final record.
+const FINAL: u32 = 0x8000_0000;
+
+/// The label that separates the nonce derivation from other uses
+/// of the key.
+const LABEL: &[u8] = b"sio cdc nonce";
+
+/// The gear table of the rolling hash: 256 pseudo-random values
+/// generated by SplitMix64. Changing it changes the chunk boundaries.
+const GEAR: [u64; 256] = {
+    let mut table = [0; 256];
+    let mut state: u64 = 0;
+    let mut i = 0;
+    while i < table.len() {
+        state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        table[i] = z ^ (z >> 31);
+        i += 1;
+    }
+    table
+};
+
+/// Finds content-defined chunk boundaries using FastCDC with normalized
+/// chunking.
+///
+/// A `Chunker` produces chunks of at least `min` and at most `max` bytes.
+/// Most chunks are close to `avg` bytes. The same parameters must be used
+/// for all backups of a dataset. Otherwise, the chunk boundaries differ and
+/// no chunks can be deduplicated.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Chunker {
+    min: usize,
+    avg: usize,
+    max: usize,
+    mask_small: u64,
+    mask_large: u64,
+}
+
+impl Default for Chunker {
+    /// Returns a `Chunker` with a minimum chunk size of 2 KiB, an average
+    /// chunk size of 8 KiB and a maximum chunk size of 64 KiB.
+    fn default() -> Self {
+        Self::new(2 << 10, 8 << 10, 64 << 10).unwrap()
+    }
+}
+
+impl Chunker {
+    /// Creates a new `Chunker` from the minimum, average and maximum chunk
+    /// size. The `avg` must be a power of two between 64 bytes and 8 MiB
+    /// and `min <= avg <= max` must hold. The `max` must not be greater
+    /// than `MAX_BUF_SIZE`.
+    pub fn new(min: usize, avg: usize, max: usize) -> Result<Self, Invalid> {
+        if !avg.is_power_of_two()
+            || !(64..=1 << 23).contains(&avg)
+            || min == 0
+            || min > avg
+            || avg > max
+            || max > MAX_BUF_SIZE
+        {
+            return Err(Invalid::BufSize);
+        }
+        // The masks select the most significant bits of the hash since
+        // they depend on the last 64 bytes. A chunk boundary is less likely
+        // before and more likely after the average chunk size.
+        let bits = avg.trailing_zeros();
+        Ok(Chunker {
+            min,
+            avg,
+            max,
+            mask_small: !(u64::MAX >> (bits + 1)),
+            mask_large: !(u64::MAX >> (bits - 1)),
+        })
+    }
+
+    /// Returns the minimum chunk size.
+    #[inline]
+    pub fn min(&self) -> usize {
+        self.min
+    }
+
+    /// Returns the average chunk size.
+    #[inline]
+    pub fn avg(&self) -> usize {
+        self.avg
+    }
+
+    /// Returns the maximum chunk size.
+    #[inline]
+    pub fn max(&self) -> usize {
+        self.max
+    }
+
+    /// Returns the length of the first chunk of `data`.
+    ///
+    /// The boundary only depends on the first `max` bytes of `data`. If
+    /// `data` is shorter and contains no boundary, the entire `data` is
+    /// returned as chunk. Hence, `data` must contain at least `max` bytes -
+    /// unless it is the end of the stream.
+    pub fn cut(&self, data: &[u8]) -> usize {
+        if data.len() <= self.min {
+            return data.len();
+        }
+        let end = data.len().min(self.max);
+        let normal = end.min(self.avg);
+
+        let mut hash: u64 = 0;
+        for (i, b) in data.iter().enumerate().take(normal).skip(self.min) {
+            hash = (hash << 1).wrapping_add(GEAR[*b as usize]);
+            if hash & self.mask_small == 0 {
+                return i + 1;
+            }
+        }
+        for (i, b) in data.iter().enumerate().take(end).skip(normal) {
+            hash = (hash << 1).wrapping_add(GEAR[*b as usize]);
+            if hash & self.mask_large == 0 {
+                return i + 1;
+            }
+        }
+        end
+    }
+}
+
+/// Splits the ciphertext produced by a `CdcEncWriter` into its records.
+///
+/// The final record is the last item. It fails with `NotAuthentic` if a
+/// record header is malformed or the `ciphertext` ends within a record.
+/// The records are not decrypted nor verified.
+pub fn records<A: Algorithm>(mut ciphertext: &[u8]) -> impl Iterator<Item = io::Result<&[u8]>> {
+    let mut failed = false;
+    std::iter::from_fn(move || {
+        if ciphertext.is_empty() || failed {
+            return None;
+        }
+        match record_len::<A>(ciphertext) {
+            Ok(Some(len)) if len <= ciphertext.len() => {
+                let (record, rest) = ciphertext.split_at(len);
+                ciphertext = rest;
+                Some(Ok(record))
+            }
+            Ok(_) | Err(_) => {
+                failed = true;
+                Some(Err(NotAuthentic.into()))
+            }
+        }
+    })
+}
+
+/// Returns the length of the record that starts with the given bytes - or
+/// `None` if the header is incomplete.
+fn record_len<A: Algorithm>(record: &[u8]) -> Result<Option<usize>, NotAuthentic> {
+    if record.len() < HEADER_LEN {
+        return Ok(None);
+    }
+    let mut header = [0; HEADER_LEN];
+    header.copy_from_slice(&record[..HEADER_LEN]);
+    match u32::from_be_bytes(header) {
+        FINAL => Ok(Some(HEADER_LEN + A::TAG_LEN)),
+        0 => Err(NotAuthentic),
+        len if len as usize > MAX_BUF_SIZE => Err(NotAuthentic),
+        len => Ok(Some(HEADER_LEN + A::NONCE_LEN + len as usize + A::TAG_LEN)),
+    }
+}
+
+/// The state shared by the `CdcEncWriter` and the `CdcDecWriter`.
+struct Cipher<A: Algorithm> {
+    algorithm: A,
+    nonce_key: hmac::SigningKey,
+    aad: Vec<u8>,
+    chunk_aad: Vec<u8>,
+    digest: digest::Context,
+}
+
+impl<A: Algorithm> Cipher<A> {
+    fn new(key: &Key<A>, aad: Aad<A>) -> Self {
+        let key = key.as_ref();
+        let nonce_key = hmac::sign(&hmac::SigningKey::new(&digest::SHA256, key), LABEL);
+
+        let aad = aad.as_ref().to_vec();
+        let mut chunk_aad = vec![0; 1 + HEADER_LEN];
+        chunk_aad.extend_from_slice(&aad);
+        Cipher {
+            algorithm: A::new(key),
+            nonce_key: hmac::SigningKey::new(&digest::SHA256, nonce_key.as_ref()),
+            aad,
+            chunk_aad,
+            digest: digest::Context::new(&digest::SHA256),
+        }
+    }
+
+    /// Returns the nonce of the `chunk`.
+    fn chunk_nonce(&self, chunk: &[u8]) -> hmac::Signature {
+        let mut ctx = hmac::SigningContext::with_key(&self.nonce_key);
+        ctx.update(&[0x00]);
+        ctx.update(&(self.aad.len() as u64).to_be_bytes());
+        ctx.update(&self.aad);
+        ctx.update(chunk);
+        ctx.sign()
+    }
+
+    /// Sets the header of the next chunk record in its associated data.
+    fn set_header(&mut self, header: &[u8]) {
+        self.chunk_aad[1..1 + HEADER_LEN].copy_from_slice(header);
+    }
+
+    /// Returns the nonce and associated data of the final record.
+    fn final_record(&self) -> (hmac::Signature, Vec<u8>) {
+        let digest = self.digest.clone().finish();
+        let mut aad = Vec::with_capacity(1 + digest.as_ref().len() + self.aad.len());
+        aad.push(0x80);
+        aad.extend_from_slice(digest.as_ref());
+        aad.extend_from_slice(&self.aad);
+        (
+            hmac::sign(&self.nonce_key, &aad[..1 + digest.as_ref().len()]),
+            aad,
+        )
+    }
+}
+
+/// Wraps a writer and encrypts and authenticates everything written to it
+/// in content-defined chunks.
+///
+/// The chunk boundaries only depend on the plaintext and the `Chunker` -
+/// not on how the plaintext is written. A `CdcEncWriter` buffers up to
+/// `max` bytes of plaintext before it determines the next boundary. Like
+/// an `EncWriter`, a `CdcEncWriter` must be closed explicitly.
+pub struct CdcEncWriter<A: Algorithm, W: Write + internal::Close> {
+    inner: W,
+    cipher: Cipher<A>,
+    chunker: Chunker,
+    buffer: Vec<u8>,
+    record: Vec<u8>,
+    errored: bool,
+    closed: bool,
+}
+
+impl<A: Algorithm, W: Write + internal::Close> CdcEncWriter<A, W> {
+    /// Creates a new `CdcEncWriter` with the default `Chunker`.
+    pub fn new(inner: W, key: &Key<A>, aad: Aad<A>) -> Self {
+        Self::with_chunker(inner, key, aad, Chunker::default())
+    }
+
+    /// Creates a new `CdcEncWriter` that splits the plaintext using the
+    /// `chunker`.
+    pub fn with_chunker(inner: W, key: &Key<A>, aad: Aad<A>, chunker: Chunker) -> Self {
+        CdcEncWriter {
+            inner,
+            cipher: Cipher::new(key, aad),
+            chunker,
+            buffer: Vec::with_capacity(chunker.max()),
+            record: Vec::default(),
+            errored: false,
+            closed: false,
+        }
+    }
+
+    /// Encrypts the remaining plaintext, writes the final record and closes
+    /// the inner writer.
+    #[must_use = "A CdcEncWriter must be closed to successfully complete the encryption process. Ignoring this result may cause incomplete ciphertext data."]
+    #[inline(always)]
+    pub fn close(mut self) -> io::Result<()> {
+        internal::Close::close(&mut self)
+    }
+
+    /// Encrypts the chunks of the buffered plaintext - if `all` is true,
+    /// the entire buffered plaintext and, otherwise, only the chunks that
+    /// are followed by at least `max` bytes.
+    fn seal_chunks(&mut self, all: bool) -> io::Result<()> {
+        let mut start = 0;
+        let result = loop {
+            let rest = self.buffer.len() - start;
+            if rest == 0 || (!all && rest < self.chunker.max()) {
+                break Ok(());
+            }
+            let n = self.chunker.cut(&self.buffer[start..]);
+            if let Err(err) = self.seal(start, n) {
+                break Err(err);
+            }
+            start += n;
+        };
+        self.buffer.drain(..start);
+        result
+    }
+
+    /// Encrypts the `n` bytes of buffered plaintext at `start` as one
+    /// chunk and writes its record to the inner writer.
+    fn seal(&mut self, start: usize, n: usize) -> io::Result<()> {
+        let chunk = &self.buffer[start..start + n];
+        let nonce = self.cipher.chunk_nonce(chunk);
+        let header = (n as u32).to_be_bytes();
+
+        self.record.clear();
+        self.record.extend_from_slice(&header);
+        self.record
+            .extend_from_slice(&nonce.as_ref()[..A::NONCE_LEN]);
+        self.record.extend_from_slice(chunk);
+        self.record.resize(self.record.len() + A::TAG_LEN, 0);
+
+        self.cipher.set_header(&header);
+        let (_, ciphertext) = self.record.split_at_mut(HEADER_LEN + A::NONCE_LEN);
+        self.cipher.algorithm.seal_in_place(
+            &nonce.as_ref()[..A::NONCE_LEN],
+            &self.cipher.chunk_aad,
+            ciphertext,
+        )?;
+        self.cipher.digest.update(&self.record);
+        self.inner.write_all(&self.record)
+    }
+}
+
+impl<A: Algorithm, W: Write + internal::Close> Write for CdcEncWriter<A, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.errored {
+            return Err(io::Error::from(io::ErrorKind::Other));
+        }
+        self.buffer.extend_from_slice(buf);
+        self.seal_chunks(false)
+            .inspect_err(|_| self.errored = true)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if self.errored {
+            return Err(io::Error::from(io::ErrorKind::Other));
+        }
+        self.inner.flush()
+    }
+}
+
+impl<A: Algorithm, W: Write + internal::Close> internal::Close for CdcEncWriter<A, W> {
+    fn close(&mut self) -> io::Result<()> {
+        if self.errored {
+            return Err(io::Error::from(io::ErrorKind::Other));
+        }
+        self.closed = true;
+        self.seal_chunks(true)?;
+
+        let (nonce, aad) = self.cipher.final_record();
+        let mut record = vec![0; HEADER_LEN + A::TAG_LEN];
+        record[..HEADER_LEN].copy_from_slice(&FINAL.to_be_bytes());
+        self.cipher.algorithm.seal_in_place(
+            &nonce.as_ref()[..A::NONCE_LEN],
+            &aad,
+            &mut record[HEADER_LEN..],
+        )?;
+        self.inner.write_all(&record)?;
+        self.inner.close()
+    }
+}
+
+impl<A: Algorithm, W: Write + internal::Close> Drop for CdcEncWriter<A, W> {
+    fn drop(&mut self) {
+        // See: EncWriter::drop
+        if !self.errored
+            && !self.closed
+            && !panicking()
+            && !hook::unclosed::<Self>()
+            && !cfg!(feature = "panic-free")
+        {
+            panic!(
+                "CdcEncWriter must be closed explicitly via the close method before being dropped!"
+            )
+        }
+    }
+}
+
+/// Wraps a writer and decrypts and verifies everything written to it.
+///
+/// A `CdcDecWriter` decrypts the ciphertext produced by a `CdcEncWriter`
+/// record by record. It writes the plaintext of every chunk to the inner
+/// writer once the chunk has been verified. Only `close` verifies that no
+/// chunks have been reordered or removed. Like a `DecWriter`, a
+/// `CdcDecWriter` must be closed explicitly.
+pub struct CdcDecWriter<A: Algorithm, W: Write + internal::Close> {
+    inner: W,
+    cipher: Cipher<A>,
+    buffer: Vec<u8>,
+    finished: bool,
+    errored: bool,
+    closed: bool,
+}
+
+impl<A: Algorithm, W: Write + internal::Close> CdcDecWriter<A, W> {
+    /// Creates a new `CdcDecWriter`. The `key` and `aad` must match the
+    /// ones used for encryption.
+    pub fn new(inner: W, key: &Key<A>, aad: Aad<A>) -> Self {
+        CdcDecWriter {
+            inner,
+            cipher: Cipher::new(key, aad),
+            buffer: Vec::default(),
+            finished: false,
+            errored: false,
+            closed: false,
+        }
+    }
+
+    /// Verifies that the final record has been received and closes the
+    /// inner writer.
+    ///
+    /// It fails with `NotAuthentic` if the ciphertext has been truncated.
+    #[must_use = "A CdcDecWriter must be closed to successfully complete the decryption process. Ignoring this result may cause incomplete plaintext data."]
+    #[inline(always)]
+    pub fn close(mut self) -> io::Result<()> {
+        internal::Close::close(&mut self)
+    }
+
+    /// Decrypts and verifies all complete records in the buffer.
+    fn open_records(&mut self) -> io::Result<()> {
+        let mut start = 0;
+        let result = loop {
+            if start == self.buffer.len() {
+                break Ok(());
+            }
+            if self.finished {
+                break Err(TrailingData.into());
+            }
+            let len = match record_len::<A>(&self.buffer[start..]) {
+                Ok(Some(len)) if len <= self.buffer.len() - start => len,
+                Ok(_) => break Ok(()),
+                Err(err) => break Err(err.into()),
+            };
+            if let Err(err) = self.open(start, len) {
+                break Err(err);
+            }
+            start += len;
+        };
+        self.buffer.drain(..start);
+        result
+    }
+
+    /// Decrypts and verifies the record of length `len` at `start` and
+    /// writes its plaintext to the inner writer.
+    fn open(&mut self, start: usize, len: usize) -> io::Result<()> {
+        let record = &mut self.buffer[start..start + len];
+        let (header, record) = record.split_at_mut(HEADER_LEN);
+        if header == FINAL.to_be_bytes() {
+            let (nonce, aad) = self.cipher.final_record();
+            self.cipher
+                .algorithm
+                .open_in_place(&nonce.as_ref()[..A::NONCE_LEN], &aad, record)?;
+            self.finished = true;
+            return Ok(());
+        }
+
+        self.cipher.digest.update(header);
+        self.cipher.digest.update(record);
+        let (nonce, ciphertext) = record.split_at_mut(A::NONCE_LEN);
+        self.cipher.set_header(header);
+        let plaintext =
+            self.cipher
+                .algorithm
+                .open_in_place(nonce, &self.cipher.chunk_aad, ciphertext)?;
+        self.inner.write_all(plaintext)
+    }
+}
+
+impl<A: Algorithm, W: Write + internal::Close> Write for CdcDecWriter<A, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.errored {
+            return Err(io::Error::from(io::ErrorKind::Other));
+        }
+        self.buffer.extend_from_slice(buf);
+        self.open_records().inspect_err(|_| self.errored = true)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if self.errored {
+            return Err(io::Error::from(io::ErrorKind::Other));
+        }
+        self.inner.flush()
+    }
+}
+
+impl<A: Algorithm, W: Write + internal::Close> internal::Close for CdcDecWriter<A, W> {
+    fn close(&mut self) -> io::Result<()> {
+        if self.errored {
+            return Err(io::Error::from(io::ErrorKind::Other));
+        }
+        self.closed = true;
+        if !self.finished || !self.buffer.is_empty() {
+            self.errored = true;
+            return Err(NotAuthentic.into());
+        }
+        self.inner.close()
+    }
+}
+
+impl<A: Algorithm, W: Write + internal::Close> Drop for CdcDecWriter<A, W> {
+    fn drop(&mut self) {
+        // See: DecWriter::drop
+        if !self.errored
+            && !self.closed
+            && !panicking()
+            && !hook::unclosed::<Self>()
+            && !cfg!(feature = "panic-free")
+        {
+            panic!(
+                "CdcDecWriter must be closed explicitly via the close method before being dropped!"
+            )
+        }
+    }
+}
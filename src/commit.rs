@@ -0,0 +1,124 @@
+// Copyright (c) 2019 Andreas Auernhammer. All rights reserved.
+// Use of this source code is governed by a license that can be
+// found in the LICENSE file.
+
+extern crate ring;
+
+use crate::aead::Algorithm;
+use crate::error::{Invalid, NotAuthentic};
+use ring::{constant_time, digest, hmac};
+
+/// The length of the key commitment appended to every fragment.
+const COMMITMENT_LEN: usize = digest::SHA256_OUTPUT_LEN;
+
+/// A wrapper that turns any `Algorithm` into a key-committing AEAD.
+///
+/// Neither AES-GCM nor ChaCha20-Poly1305 are key-committing. An attacker
+/// can compute a ciphertext that decrypts successfully under two (or more)
+/// different keys. This becomes a problem whenever the decrypting party
+/// tries multiple keys - e.g. during a key rotation or when searching
+/// for the key that belongs to a ciphertext.
+///
+/// `Committing` derives an encryption key and a commitment key from the
+/// secret key - each as HMAC-SHA256 of the key and a fixed label. The
+/// encryption key is used by the wrapped algorithm. The key commitment of
+/// a fragment is the HMAC-SHA256 of its nonce under the commitment key
+/// and is appended to the authentication tag. When decrypting, the
+/// commitment is verified before the wrapped algorithm gets invoked.
+/// Therefore, a ciphertext can only be decrypted with the key used to
+/// produce it.
+///
+/// Since every fragment has a unique nonce, the commitments differ from
+/// fragment to fragment and from stream to stream. Hence, they do not
+/// reveal which ciphertexts have been produced with the same key.
+///
+/// Using `Committing` increases the tag size of every fragment by 32 bytes.
+/// Ciphertexts produced with and without the wrapper are not compatible.
+///
+/// # Examples
+///
+/// ```
+/// use std::io::Write;
+/// use sio::{Key, Nonce, Aad, EncWriter, Committing, CHACHA20_POLY1305};
+///
+/// // Obviously, don't use this all-zeros key for anything real.
+/// let key: Key<Committing<CHACHA20_POLY1305>> = Key::new([0; Key::<CHACHA20_POLY1305>::SIZE]);
+///
+/// let mut ciphertext: Vec<u8> = Vec::default();
+/// let mut writer = EncWriter::new(
+///     &mut ciphertext,
+///     &key,
+///     Nonce::new([0; Nonce::<CHACHA20_POLY1305>::SIZE]),
+///     Aad::empty(),
+/// );
+///
+/// writer.write_all(b"Hello World").unwrap();
+/// writer.close().unwrap();
+/// ```
+pub struct Committing<A: Algorithm> {
+    algorithm: A,
+    commitment_key: hmac::SigningKey,
+}
+
+impl<A: Algorithm> Committing<A> {
+    const ENCRYPTION_KEY_LABEL: &'static [u8] = b"sio key-committing AEAD: encryption key";
+    const COMMITMENT_LABEL: &'static [u8] = b"sio key-committing AEAD: key commitment";
+
+    /// Returns the key commitment of the fragment with the given `nonce`.
+    #[inline]
+    fn commitment(&self, nonce: &[u8]) -> hmac::Signature {
+        hmac::sign(&self.commitment_key, nonce)
+    }
+}
+
+impl<A: Algorithm> Algorithm for Committing<A> {
+    const KEY_LEN: usize = A::KEY_LEN;
+    const NONCE_LEN: usize = A::NONCE_LEN;
+    const TAG_LEN: usize = A::TAG_LEN + COMMITMENT_LEN;
+
+    fn new(key: &[u8; 32]) -> Self {
+        let key = hmac::SigningKey::new(&digest::SHA256, key);
+
+        let mut encryption_key = [0; 32];
+        encryption_key.copy_from_slice(hmac::sign(&key, Self::ENCRYPTION_KEY_LABEL).as_ref());
+        let commitment_key = hmac::sign(&key, Self::COMMITMENT_LABEL);
+
+        Self {
+            algorithm: A::new(&encryption_key),
+            commitment_key: hmac::SigningKey::new(&digest::SHA256, commitment_key.as_ref()),
+        }
+    }
+
+    fn seal_in_place<'a>(
+        &self,
+        nonce: &[u8],
+        aad: &[u8],
+        in_out: &'a mut [u8],
+    ) -> Result<&'a [u8], Invalid> {
+        if in_out.len() < Self::TAG_LEN {
+            return Err(Invalid::BufSize);
+        }
+        let len = in_out.len() - COMMITMENT_LEN;
+        self.algorithm
+            .seal_in_place(nonce, aad, &mut in_out[..len])?;
+        in_out[len..].copy_from_slice(self.commitment(nonce).as_ref());
+        Ok(in_out)
+    }
+
+    fn open_in_place<'a>(
+        &self,
+        nonce: &[u8],
+        aad: &[u8],
+        in_out: &'a mut [u8],
+    ) -> Result<&'a [u8], NotAuthentic> {
+        if in_out.len() < Self::TAG_LEN {
+            return Err(NotAuthentic);
+        }
+        let len = in_out.len() - COMMITMENT_LEN;
+        let commitment = self.commitment(nonce);
+        if constant_time::verify_slices_are_equal(&in_out[len..], commitment.as_ref()).is_err() {
+            return Err(NotAuthentic);
+        }
+        self.algorithm.open_in_place(nonce, aad, &mut in_out[..len])
+    }
+}
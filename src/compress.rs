@@ -0,0 +1,432 @@
+// Copyright (c) 2019 Andreas Auernhammer. All rights reserved.
+// Use of this source code is governed by a license that can be
+// found in the LICENSE file.
+
+use super::writer::{Close, Closer};
+use super::{Aad, Algorithm, DecWriter, EncWriter, Key, Nonce};
+use std::io::{self, Write};
+
+#[cfg(any(feature = "zstd", feature = "brotli"))]
+use super::BUF_SIZE;
+
+#[cfg(feature = "gzip")]
+use flate2::write::{GzDecoder, GzEncoder};
+#[cfg(feature = "zstd")]
+use zstd::stream::raw::{Decoder, Encoder};
+#[cfg(feature = "zstd")]
+use zstd::stream::zio;
+
+/// The compression format of a `CompressedEncWriter` and a
+/// `CompressedDecWriter`.
+///
+/// Every format is only available if the feature of the same name is
+/// enabled. The ciphertext does not record the format. Hence, it must
+/// be known when decrypting.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Codec {
+    /// Zstandard. Its compression levels range from `1` to `22`. The
+    /// level `0` selects zstd's default level.
+    #[cfg(feature = "zstd")]
+    Zstd,
+
+    /// Gzip (RFC 1952) - e.g. for consumers that can only decompress
+    /// gzip. Its compression levels range from `0` (no compression) to
+    /// `9`.
+    #[cfg(feature = "gzip")]
+    Gzip,
+
+    /// Brotli (RFC 7932). Its compression levels range from `0` to `11`.
+    #[cfg(feature = "brotli")]
+    Brotli,
+}
+
+/// Wraps a writer and compresses, encrypts and authenticates everything
+/// written to it.
+///
+/// The plaintext is compressed using the selected `Codec` before it gets
+/// encrypted by an `EncWriter`. Closing the `CompressedEncWriter` finishes
+/// the compressed stream and then closes the `EncWriter`. Hence, the end
+/// of the compressed stream is always part of the authenticated stream.
+/// The ciphertext can be decrypted and decompressed by a
+/// `CompressedDecWriter` that uses the same `Codec`.
+///
+/// Like an `EncWriter`, a `CompressedEncWriter` must be closed explicitly.
+///
+/// # Examples
+///
+/// ```
+/// # #[cfg(feature = "zstd")]
+/// # fn main() {
+/// use std::io::Write;
+/// use sio::{Key, Nonce, Aad, CompressedEncWriter, CompressedDecWriter, CHACHA20_POLY1305};
+///
+/// // Obviously, don't use this all-zeros key for anything real.
+/// let key: Key<CHACHA20_POLY1305> = Key::new([0; Key::<CHACHA20_POLY1305>::SIZE]);
+///
+/// let mut ciphertext: Vec<u8> = Vec::default();
+/// let mut writer = CompressedEncWriter::new(
+///     &mut ciphertext,
+///     &key,
+///     Nonce::new([0; Nonce::<CHACHA20_POLY1305>::SIZE]),
+///     Aad::empty(),
+///     3,
+/// )
+/// .unwrap();
+/// writer.write_all(&[0; 1 << 20]).unwrap();
+/// writer.close().unwrap();
+/// assert!(ciphertext.len() < 1 << 10);
+///
+/// let mut plaintext: Vec<u8> = Vec::default();
+/// let mut writer = CompressedDecWriter::new(
+///     &mut plaintext,
+///     &key,
+///     Nonce::new([0; Nonce::<CHACHA20_POLY1305>::SIZE]),
+///     Aad::empty(),
+/// )
+/// .unwrap();
+/// writer.write_all(ciphertext.as_slice()).unwrap();
+/// writer.close().unwrap();
+/// assert_eq!(plaintext, vec![0; 1 << 20]);
+/// # }
+/// # #[cfg(not(feature = "zstd"))]
+/// # fn main() {}
+/// ```
+pub struct CompressedEncWriter<A: Algorithm, W: Write + Close> {
+    encoder: Compressor<Closer<EncWriter<A, W>>>,
+}
+
+impl<A: Algorithm, W: Write + Close> CompressedEncWriter<A, W> {
+    /// Creates a new `CompressedEncWriter` with a default buffer size
+    /// of 16 KiB that compresses the plaintext with the given zstd
+    /// compression `level`. The level `0` selects zstd's default level.
+    #[cfg(feature = "zstd")]
+    pub fn new(
+        inner: W,
+        key: &Key<A>,
+        nonce: Nonce<A>,
+        aad: Aad<A>,
+        level: i32,
+    ) -> io::Result<Self> {
+        Self::with_writer(EncWriter::new(inner, key, nonce, aad), level)
+    }
+
+    /// Creates a new `CompressedEncWriter` that compresses the plaintext
+    /// with the given zstd compression `level` and writes it to the `writer`.
+    /// The `EncWriter` can be configured - e.g. to use another buffer size.
+    #[cfg(feature = "zstd")]
+    pub fn with_writer(writer: EncWriter<A, W>, level: i32) -> io::Result<Self> {
+        Self::with_codec(writer, Codec::Zstd, level)
+    }
+
+    /// Creates a new `CompressedEncWriter` that compresses the plaintext
+    /// using the `codec` with the given compression `level` and writes it
+    /// to the `writer`. It fails if the `codec` does not support the
+    /// `level`.
+    pub fn with_codec(writer: EncWriter<A, W>, codec: Codec, level: i32) -> io::Result<Self> {
+        match Settings::new(codec, level) {
+            Ok(settings) => Ok(CompressedEncWriter {
+                encoder: Compressor::new(settings, writer.closer()),
+            }),
+            Err(err) => {
+                // The EncWriter would panic if dropped without being closed.
+                writer.close()?;
+                Err(err)
+            }
+        }
+    }
+
+    /// Finishes the compressed stream and closes the `EncWriter`.
+    #[must_use = "A CompressedEncWriter must be closed to successfully complete the encryption process. Ignoring this result may cause incomplete ciphertext data."]
+    #[inline]
+    pub fn close(mut self) -> io::Result<()> {
+        Close::close(&mut self)
+    }
+}
+
+impl<A: Algorithm, W: Write + Close> Write for CompressedEncWriter<A, W> {
+    #[inline]
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.encoder.write(buf)
+    }
+
+    #[inline]
+    fn flush(&mut self) -> io::Result<()> {
+        self.encoder.flush()
+    }
+}
+
+impl<A: Algorithm, W: Write + Close> Close for CompressedEncWriter<A, W> {
+    fn close(&mut self) -> io::Result<()> {
+        self.encoder.finish()?.close()
+    }
+}
+
+/// Wraps a writer and decrypts, verifies and decompresses everything
+/// written to it.
+///
+/// It decrypts and verifies ciphertext produced by a `CompressedEncWriter`
+/// using a `DecWriter` and decompresses the resulting plaintext. Like a
+/// `DecWriter`, a `CompressedDecWriter` must be closed explicitly. Closing
+/// fails if the plaintext does not end with a complete compressed stream.
+pub struct CompressedDecWriter<A: Algorithm, W: Write + Close> {
+    writer: DecWriter<A, Decompressor<W>>,
+}
+
+impl<A: Algorithm, W: Write + Close> CompressedDecWriter<A, W> {
+    /// Creates a new `CompressedDecWriter` with a default buffer size
+    /// of 16 KiB that decompresses zstd.
+    #[cfg(feature = "zstd")]
+    pub fn new(inner: W, key: &Key<A>, nonce: Nonce<A>, aad: Aad<A>) -> io::Result<Self> {
+        Self::with_codec(inner, key, nonce, aad, BUF_SIZE, Codec::Zstd)
+    }
+
+    /// Creates a new `CompressedDecWriter` with the specified buffer size
+    /// that decompresses zstd. It must match the buffer size used to
+    /// encrypt the data.
+    #[cfg(feature = "zstd")]
+    pub fn with_buffer_size(
+        inner: W,
+        key: &Key<A>,
+        nonce: Nonce<A>,
+        aad: Aad<A>,
+        buf_size: usize,
+    ) -> io::Result<Self> {
+        Self::with_codec(inner, key, nonce, aad, buf_size, Codec::Zstd)
+    }
+
+    /// Creates a new `CompressedDecWriter` with the specified buffer size
+    /// that decompresses the plaintext using the `codec`. Both must match
+    /// the buffer size and the codec used to encrypt the data.
+    pub fn with_codec(
+        inner: W,
+        key: &Key<A>,
+        nonce: Nonce<A>,
+        aad: Aad<A>,
+        buf_size: usize,
+        codec: Codec,
+    ) -> io::Result<Self> {
+        let decoder = Decompressor::new(inner, codec)?;
+        Ok(CompressedDecWriter {
+            writer: DecWriter::with_buffer_size(decoder, key, nonce, aad, buf_size)?,
+        })
+    }
+
+    /// Verifies the remaining ciphertext and closes the `DecWriter` and
+    /// the inner writer.
+    #[must_use = "A CompressedDecWriter must be closed to successfully complete the decryption process. Ignoring this result may cause incomplete plaintext data."]
+    #[inline]
+    pub fn close(self) -> io::Result<()> {
+        self.writer.close()
+    }
+}
+
+impl<A: Algorithm, W: Write + Close> Write for CompressedDecWriter<A, W> {
+    #[inline]
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.writer.write(buf)
+    }
+
+    #[inline]
+    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        self.writer.write_all(buf)
+    }
+
+    #[inline]
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+#[cfg(any(feature = "gzip", feature = "brotli"))]
+fn invalid_level(codec: Codec, level: i32) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!("invalid {:?} compression level: {}", codec, level),
+    )
+}
+
+/// The validated compression level of a `Codec`.
+enum Settings {
+    #[cfg(feature = "zstd")]
+    Zstd(Encoder),
+    #[cfg(feature = "gzip")]
+    Gzip(flate2::Compression),
+    #[cfg(feature = "brotli")]
+    Brotli(u32),
+}
+
+impl Settings {
+    fn new(codec: Codec, level: i32) -> io::Result<Self> {
+        match codec {
+            #[cfg(feature = "zstd")]
+            Codec::Zstd => Ok(Settings::Zstd(Encoder::new(level)?)),
+            #[cfg(feature = "gzip")]
+            Codec::Gzip => match level {
+                0..=9 => Ok(Settings::Gzip(flate2::Compression::new(level as u32))),
+                _ => Err(invalid_level(codec, level)),
+            },
+            #[cfg(feature = "brotli")]
+            Codec::Brotli => match level {
+                0..=11 => Ok(Settings::Brotli(level as u32)),
+                _ => Err(invalid_level(codec, level)),
+            },
+        }
+    }
+}
+
+/// The encoder of a `Codec`.
+enum Compressor<W: Write> {
+    #[cfg(feature = "zstd")]
+    Zstd(zio::Writer<W, Encoder>),
+    #[cfg(feature = "gzip")]
+    Gzip(GzEncoder<W>),
+    /// The brotli encoder until the stream has been finished.
+    #[cfg(feature = "brotli")]
+    Brotli(Option<Box<brotli::CompressorWriter<W>>>, Option<W>),
+}
+
+impl<W: Write> Compressor<W> {
+    fn new(settings: Settings, inner: W) -> Self {
+        match settings {
+            #[cfg(feature = "zstd")]
+            Settings::Zstd(encoder) => Compressor::Zstd(zio::Writer::new(inner, encoder)),
+            #[cfg(feature = "gzip")]
+            Settings::Gzip(level) => Compressor::Gzip(GzEncoder::new(inner, level)),
+            #[cfg(feature = "brotli")]
+            Settings::Brotli(level) => {
+                let encoder = brotli::CompressorWriter::new(inner, BUF_SIZE, level, 22);
+                Compressor::Brotli(Some(Box::new(encoder)), None)
+            }
+        }
+    }
+
+    /// Completes the compressed stream and returns the inner writer.
+    fn finish(&mut self) -> io::Result<&mut W> {
+        match self {
+            #[cfg(feature = "zstd")]
+            Compressor::Zstd(encoder) => {
+                encoder.finish()?;
+                Ok(encoder.writer_mut())
+            }
+            #[cfg(feature = "gzip")]
+            Compressor::Gzip(encoder) => {
+                encoder.try_finish()?;
+                Ok(encoder.get_mut())
+            }
+            #[cfg(feature = "brotli")]
+            Compressor::Brotli(encoder, inner) => {
+                // Finishing the brotli stream ignores errors of the inner
+                // writer. However, the inner Closer remembers them such
+                // that closing it fails.
+                if let Some(encoder) = encoder.take() {
+                    *inner = Some(encoder.into_inner());
+                }
+                Ok(inner.as_mut().unwrap())
+            }
+        }
+    }
+}
+
+impl<W: Write> Write for Compressor<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            #[cfg(feature = "zstd")]
+            Compressor::Zstd(encoder) => encoder.write(buf),
+            #[cfg(feature = "gzip")]
+            Compressor::Gzip(encoder) => encoder.write(buf),
+            #[cfg(feature = "brotli")]
+            Compressor::Brotli(encoder, inner) => match encoder {
+                Some(encoder) => encoder.write(buf),
+                None => inner.as_mut().unwrap().write(buf),
+            },
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            #[cfg(feature = "zstd")]
+            Compressor::Zstd(encoder) => encoder.flush(),
+            #[cfg(feature = "gzip")]
+            Compressor::Gzip(encoder) => encoder.flush(),
+            #[cfg(feature = "brotli")]
+            Compressor::Brotli(encoder, inner) => match encoder {
+                Some(encoder) => encoder.flush(),
+                None => inner.as_mut().unwrap().flush(),
+            },
+        }
+    }
+}
+
+/// The decoder of a `Codec` that verifies that the compressed data ends
+/// with a complete stream when closed and then closes the inner writer.
+enum Decompressor<W: Write + Close> {
+    #[cfg(feature = "zstd")]
+    Zstd(zio::Writer<W, Decoder>),
+    #[cfg(feature = "gzip")]
+    Gzip(GzDecoder<W>),
+    #[cfg(feature = "brotli")]
+    Brotli(Box<brotli::DecompressorWriter<W>>),
+}
+
+impl<W: Write + Close> Decompressor<W> {
+    fn new(inner: W, codec: Codec) -> io::Result<Self> {
+        match codec {
+            #[cfg(feature = "zstd")]
+            Codec::Zstd => Ok(Decompressor::Zstd(zio::Writer::new(inner, Decoder::new()?))),
+            #[cfg(feature = "gzip")]
+            Codec::Gzip => Ok(Decompressor::Gzip(GzDecoder::new(inner))),
+            #[cfg(feature = "brotli")]
+            Codec::Brotli => Ok(Decompressor::Brotli(Box::new(
+                brotli::DecompressorWriter::new(inner, BUF_SIZE),
+            ))),
+        }
+    }
+}
+
+impl<W: Write + Close> Write for Decompressor<W> {
+    #[inline]
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            #[cfg(feature = "zstd")]
+            Decompressor::Zstd(decoder) => decoder.write(buf),
+            #[cfg(feature = "gzip")]
+            Decompressor::Gzip(decoder) => decoder.write(buf),
+            #[cfg(feature = "brotli")]
+            Decompressor::Brotli(decoder) => decoder.write(buf),
+        }
+    }
+
+    #[inline]
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            #[cfg(feature = "zstd")]
+            Decompressor::Zstd(decoder) => decoder.flush(),
+            #[cfg(feature = "gzip")]
+            Decompressor::Gzip(decoder) => decoder.flush(),
+            #[cfg(feature = "brotli")]
+            Decompressor::Brotli(decoder) => decoder.flush(),
+        }
+    }
+}
+
+impl<W: Write + Close> Close for Decompressor<W> {
+    fn close(&mut self) -> io::Result<()> {
+        match self {
+            #[cfg(feature = "zstd")]
+            Decompressor::Zstd(decoder) => {
+                decoder.finish()?;
+                decoder.writer_mut().close()
+            }
+            #[cfg(feature = "gzip")]
+            Decompressor::Gzip(decoder) => {
+                decoder.try_finish()?;
+                decoder.get_mut().close()
+            }
+            #[cfg(feature = "brotli")]
+            Decompressor::Brotli(decoder) => {
+                decoder.close()?;
+                decoder.get_mut().close()
+            }
+        }
+    }
+}
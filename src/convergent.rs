@@ -0,0 +1,95 @@
+// Copyright (c) 2019 Andreas Auernhammer. All rights reserved.
+// Use of this source code is governed by a license that can be
+// found in the LICENSE file.
+
+//! Convergent - i.e. deterministic - encryption of chunks.
+//!
+//! <p style="margin-left: 40px; margin-right: 50px; border:1px; border-style:solid; border-color:#000000; padding: 0.3em">
+//!      <b>Convergent encryption is deterministic. It leaks whether two
+//!      ciphertexts contain the same plaintext.</b> Anyone who can guess a
+//!      plaintext - and knows the convergence secret - can confirm that a
+//!      ciphertext contains it. Only use this module when deduplication
+//!      of encrypted data is required. Otherwise, use an <code>EncWriter</code>
+//!      with a random key or nonce.
+//! </p>
+//!
+//! The key of a chunk is derived from the chunk itself using HMAC-SHA256
+//! keyed with a convergence secret. Therefore, identical chunks encrypted
+//! with the same convergence secret produce identical ciphertexts and
+//! can be deduplicated. The convergence secret limits the confirmation
+//! attack described above to the parties sharing it - e.g. the clients
+//! of one tenant. An empty convergence secret makes deduplication work
+//! across everyone.
+//!
+//! Since every key is only used to encrypt one distinct plaintext, the
+//! nonce is always zero. The key returned by `encrypt` is required to
+//! decrypt the chunk and has to be stored separately - e.g. in a manifest
+//! that is encrypted with a regular key.
+//!
+//! # Examples
+//!
+//! ```
+//! use sio::{Aad, CHACHA20_POLY1305};
+//! use sio::convergent;
+//!
+//! let secret = b"my tenant's convergence secret";
+//!
+//! let (key, ciphertext) = convergent::encrypt::<CHACHA20_POLY1305>(secret, b"chunk", Aad::empty());
+//! let (_, duplicate) = convergent::encrypt::<CHACHA20_POLY1305>(secret, b"chunk", Aad::empty());
+//! assert_eq!(ciphertext, duplicate);
+//!
+//! let plaintext = convergent::decrypt(&key, &ciphertext, Aad::empty()).unwrap();
+//! assert_eq!(plaintext, b"chunk");
+//! ```
+
+use super::{Aad, Algorithm, DecWriter, EncWriter, Key, Nonce};
+use ring::{digest, hmac};
+use std::io::{self, Write};
+
+/// The label that separates the key derivation from other uses
+/// of the convergence secret.
+const LABEL: &[u8] = b"sio convergent key";
+
+/// Returns the key of the `plaintext` chunk derived from the
+/// `convergence_secret`.
+pub fn key<A: Algorithm>(convergence_secret: &[u8], plaintext: &[u8]) -> Key<A> {
+    let secret = hmac::SigningKey::new(&digest::SHA256, convergence_secret);
+    let mut ctx = hmac::SigningContext::with_key(&secret);
+    ctx.update(LABEL);
+    ctx.update(plaintext);
+
+    let mut key = [0; 32];
+    key.copy_from_slice(ctx.sign().as_ref());
+    Key::new(key)
+}
+
+/// Encrypts the `plaintext` chunk with a key derived from the chunk and
+/// the `convergence_secret`. It returns the key and the ciphertext.
+///
+/// The ciphertext only depends on the `convergence_secret`, the `plaintext`
+/// and the `aad`.
+pub fn encrypt<A: Algorithm>(
+    convergence_secret: &[u8],
+    plaintext: &[u8],
+    aad: Aad<A>,
+) -> (Key<A>, Vec<u8>) {
+    let key = key(convergence_secret, plaintext);
+
+    let mut ciphertext = Vec::default();
+    let mut writer = EncWriter::new(&mut ciphertext, &key, Nonce::zero(), aad);
+    // Writing to a Vec cannot fail and a single chunk cannot
+    // exceed the data limit of the stream.
+    writer.write_all(plaintext).unwrap();
+    writer.close().unwrap();
+    (key, ciphertext)
+}
+
+/// Decrypts and verifies a `ciphertext` chunk produced by `encrypt` using
+/// the returned `key`.
+pub fn decrypt<A: Algorithm>(key: &Key<A>, ciphertext: &[u8], aad: Aad<A>) -> io::Result<Vec<u8>> {
+    let mut plaintext = Vec::default();
+    let mut writer = DecWriter::new(&mut plaintext, key, Nonce::zero(), aad);
+    writer.write_all(ciphertext)?;
+    writer.close()?;
+    Ok(plaintext)
+}
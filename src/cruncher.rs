@@ -0,0 +1,234 @@
+// Copyright (c) 2019 Andreas Auernhammer. All rights reserved.
+// Use of this source code is governed by a license that can be
+// found in the LICENSE file.
+
+use super::pool::{BufferAllocator, BufferPool};
+use super::writer::Close;
+use super::{Aad, Algorithm, EncWriter, Invalid, Key, Nonce, BUF_SIZE, MAX_BUF_SIZE};
+use std::io::{self, Read, Write};
+use std::iter;
+use std::marker::PhantomData;
+use std::sync::Mutex;
+use std::thread;
+
+/// Encrypts many streams concurrently.
+///
+/// Backup tools and object stores often encrypt a large number of files or
+/// objects at once. A `Cruncher` takes a list of jobs - each consisting of
+/// a source, a destination and the key, nonce and associated data of the
+/// stream - and encrypts them using a bounded number of worker threads. All
+/// workers share one `BufferPool`. Hence, the memory used for buffers is
+/// bounded by the number of workers, not by the number of jobs.
+///
+/// Every job reads its source until the end, writes the ciphertext to its
+/// destination and closes the destination. `run` returns one result per job
+/// in the order in which the jobs have been added: either the number of
+/// plaintext bytes encrypted or the error of the job. A failing job does not
+/// affect any other job. The ciphertext of a failed job is incomplete and
+/// cannot be decrypted.
+///
+/// # Examples
+///
+/// ```
+/// use sio::{Key, Nonce, Aad, Cruncher, CHACHA20_POLY1305};
+///
+/// // Obviously, don't use this all-zeros key for anything real.
+/// let key: Key<CHACHA20_POLY1305> = Key::new([0; Key::<CHACHA20_POLY1305>::SIZE]);
+///
+/// let objects: Vec<&[u8]> = vec![b"Hello", b"World", b"!"];
+/// let mut ciphertexts: Vec<Vec<u8>> = vec![Vec::default(); objects.len()];
+///
+/// let mut cruncher = Cruncher::new(2);
+/// for (i, (object, ciphertext)) in objects.iter().zip(ciphertexts.iter_mut()).enumerate() {
+///     let nonce = Nonce::new((i as u64).to_be_bytes());
+///     cruncher.add(*object, ciphertext, &key, nonce, Aad::empty());
+/// }
+/// let results = cruncher.run_with_progress(|job, bytes| {
+///     println!("job {}: {} bytes encrypted", job, bytes);
+/// });
+///
+/// assert!(results.iter().all(|result| result.is_ok()));
+/// ```
+pub struct Cruncher<A: Algorithm, R: Read + Send, W: Write + Close + Send> {
+    workers: usize,
+    buf_size: usize,
+    pool: BufferPool,
+    jobs: Vec<Job<R, W>>,
+    _algorithm: PhantomData<A>,
+}
+
+/// A stream to be encrypted. The key and nonce are kept as bytes such that
+/// jobs can be moved to the workers regardless of the `Algorithm`.
+struct Job<R, W> {
+    source: R,
+    destination: W,
+    key: [u8; 32],
+    nonce: Vec<u8>,
+    aad: Vec<u8>,
+}
+
+impl<A: Algorithm, R: Read + Send, W: Write + Close + Send> Cruncher<A, R, W> {
+    /// Creates a new `Cruncher` with a buffer size of 16 KiB that uses at
+    /// most `workers` threads. At least one worker is used.
+    pub fn new(workers: usize) -> Self {
+        Self::with_buffer_size(workers, BUF_SIZE).unwrap()
+    }
+
+    /// Creates a new `Cruncher` with the specified buffer size as fragment
+    /// size for all streams. The `buf_size` must not be `0` nor greater than
+    /// `MAX_BUF_SIZE`.
+    pub fn with_buffer_size(workers: usize, buf_size: usize) -> Result<Self, Invalid> {
+        if buf_size == 0 || buf_size > MAX_BUF_SIZE {
+            return Err(Invalid::BufSize);
+        }
+        let workers = workers.max(1);
+        Ok(Cruncher {
+            workers,
+            buf_size,
+            pool: BufferPool::new(2 * workers),
+            jobs: Vec::default(),
+            _algorithm: PhantomData,
+        })
+    }
+
+    /// Lets the workers take their buffers from the given `pool` - e.g. to
+    /// share the buffers with other writers of the application.
+    pub fn pool(mut self, pool: BufferPool) -> Self {
+        self.pool = pool;
+        self
+    }
+
+    /// Adds a job that encrypts everything read from `source` and writes
+    /// the ciphertext to `destination`. It returns the index of the job.
+    ///
+    /// Every job must use a unique key-nonce combination.
+    pub fn add(
+        &mut self,
+        source: R,
+        destination: W,
+        key: &Key<A>,
+        nonce: Nonce<A>,
+        aad: Aad<A>,
+    ) -> usize {
+        self.jobs.push(Job {
+            source,
+            destination,
+            key: *key.as_ref(),
+            nonce: nonce.as_ref().to_vec(),
+            aad: aad.as_ref().to_vec(),
+        });
+        self.jobs.len() - 1
+    }
+
+    /// Returns the number of jobs.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.jobs.len()
+    }
+
+    /// Returns true if no job has been added.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.jobs.is_empty()
+    }
+
+    /// Runs all jobs and returns their results once all jobs have
+    /// completed.
+    pub fn run(self) -> Vec<io::Result<u64>> {
+        self.run_with_progress(|_, _| {})
+    }
+
+    /// Runs all jobs and returns their results once all jobs have
+    /// completed.
+    ///
+    /// The `progress` function is called by the workers whenever a job
+    /// has encrypted another chunk of plaintext. It receives the index of
+    /// the job and the number of plaintext bytes encrypted by it so far.
+    pub fn run_with_progress<F: Fn(usize, u64) + Sync>(self, progress: F) -> Vec<io::Result<u64>> {
+        let Cruncher {
+            workers,
+            buf_size,
+            pool,
+            jobs,
+            ..
+        } = self;
+        let len = jobs.len();
+        let queue = Mutex::new(jobs.into_iter().enumerate());
+        let (queue, pool, progress) = (&queue, &pool, &progress);
+
+        let mut results: Vec<Option<io::Result<u64>>> =
+            iter::repeat_with(|| None).take(len).collect();
+        thread::scope(|scope| {
+            let workers: Vec<_> = (0..workers.min(len))
+                .map(|_| {
+                    scope.spawn(move || {
+                        let mut results = Vec::default();
+                        loop {
+                            // A job is moved out of the queue before it
+                            // runs. Hence, a panicking job leaves the queue
+                            // in a consistent state.
+                            let next = queue.lock().unwrap_or_else(|err| err.into_inner()).next();
+                            let Some((index, job)) = next else {
+                                return results;
+                            };
+                            let result =
+                                encrypt::<A, R, W>(job, buf_size, pool, |n| progress(index, n));
+                            results.push((index, result));
+                        }
+                    })
+                })
+                .collect();
+            for worker in workers {
+                let worker_results = match worker.join() {
+                    Ok(worker_results) => worker_results,
+                    Err(panic) => std::panic::resume_unwind(panic),
+                };
+                for (index, result) in worker_results {
+                    results[index] = Some(result);
+                }
+            }
+        });
+        results.into_iter().map(Option::unwrap).collect()
+    }
+}
+
+/// Encrypts the source of the `job`, writes the ciphertext to its
+/// destination and returns the number of plaintext bytes.
+fn encrypt<A: Algorithm, R: Read, W: Write + Close>(
+    mut job: Job<R, W>,
+    buf_size: usize,
+    pool: &BufferPool,
+    progress: impl Fn(u64),
+) -> io::Result<u64> {
+    let mut writer = EncWriter::<A, W>::with_pool(
+        job.destination,
+        &Key::new(job.key),
+        Nonce::from_slice(&job.nonce),
+        Aad::from(job.aad.as_slice()),
+        buf_size,
+        pool,
+    )?;
+
+    let mut buffer = pool.allocate(buf_size);
+    let mut n = 0;
+    let result = loop {
+        match job.source.read(&mut buffer) {
+            Ok(0) => break Ok(()),
+            Ok(len) => {
+                if let Err(err) = writer.write_all(&buffer[..len]) {
+                    break Err(err);
+                }
+                n += len as u64;
+                progress(n);
+            }
+            Err(ref err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err) => break Err(err),
+        }
+    };
+    pool.release(buffer);
+    match result {
+        Ok(()) => writer.close().map(|()| n),
+        // Fails the EncWriter without completing the ciphertext.
+        Err(err) => writer.consume(iter::once(Err(err))),
+    }
+}
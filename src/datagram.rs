@@ -0,0 +1,154 @@
+// Copyright (c) 2019 Andreas Auernhammer. All rights reserved.
+// Use of this source code is governed by a license that can be
+// found in the LICENSE file.
+
+use super::{
+    Aad, Algorithm, Invalid, Key, Nonce, NotAuthentic, Opener, ReplayWindow, Sealer, MAX_BUF_SIZE,
+};
+use std::io;
+
+/// The size of the sequence number that precedes the ciphertext of
+/// every datagram.
+const SEQ_NUM_LEN: usize = 4;
+
+/// Encrypts datagrams - e.g. UDP packets - that may get lost, duplicated
+/// or reordered on their way to a `DatagramOpener`.
+///
+/// A datagram consists of the big-endian 4 byte sequence number of the
+/// message followed by its ciphertext. The ciphertext is computed like
+/// the ones of a `Sealer` - i.e. as a stream consisting of one final
+/// fragment whose nonce is the `Nonce` passed to `new` with its last 4
+/// bytes replaced by the sequence number. Hence, every datagram can be
+/// decrypted on its own and the receiver can detect replayed datagrams.
+///
+/// As a `Sealer`, a `DatagramSealer` encrypts at most 2^32 datagrams
+/// and fails with `Exceeded` afterwards.
+///
+/// # Examples
+///
+/// ```
+/// use sio::{Key, Nonce, Aad, DatagramOpener, DatagramSealer, CHACHA20_POLY1305};
+///
+/// // Obviously, don't use this all-zeros key for anything real.
+/// let key: Key<CHACHA20_POLY1305> = Key::new([0; Key::<CHACHA20_POLY1305>::SIZE]);
+/// let nonce = Nonce::new([0; Nonce::<CHACHA20_POLY1305>::SIZE]);
+///
+/// let mut sealer = DatagramSealer::new(&key, nonce);
+/// let first = sealer.seal(Aad::empty(), b"Hello").unwrap();
+/// let second = sealer.seal(Aad::empty(), b"World").unwrap();
+///
+/// let mut opener = DatagramOpener::new(&key, nonce);
+/// assert_eq!(opener.open(Aad::empty(), &second).unwrap(), (1, b"World".to_vec()));
+/// assert_eq!(opener.open(Aad::empty(), &first).unwrap(), (0, b"Hello".to_vec()));
+/// assert!(opener.open(Aad::empty(), &first).is_err()); // replayed
+/// ```
+pub struct DatagramSealer<A: Algorithm> {
+    sealer: Sealer<A>,
+}
+
+impl<A: Algorithm> DatagramSealer<A> {
+    /// Creates a new `DatagramSealer` that derives the nonces of its
+    /// datagrams from `nonce`.
+    pub fn new(key: &Key<A>, nonce: Nonce<A>) -> Self {
+        DatagramSealer {
+            sealer: Sealer::new(key, nonce),
+        }
+    }
+
+    /// Encrypts and authenticates the `plaintext` and returns the
+    /// datagram.
+    ///
+    /// The `plaintext` must not be larger than `MAX_BUF_SIZE`. Rejected
+    /// messages don't consume a sequence number.
+    pub fn seal(&mut self, aad: Aad<A>, plaintext: &[u8]) -> io::Result<Vec<u8>> {
+        if plaintext.len() > MAX_BUF_SIZE {
+            return Err(Invalid::BufSize.into());
+        }
+        let mut datagram = vec![0; SEQ_NUM_LEN + plaintext.len() + A::TAG_LEN];
+        datagram[SEQ_NUM_LEN..SEQ_NUM_LEN + plaintext.len()].copy_from_slice(plaintext);
+        let nonce = self
+            .sealer
+            .seal_in_place(aad, &mut datagram[SEQ_NUM_LEN..])?;
+        datagram[..SEQ_NUM_LEN].copy_from_slice(&nonce.as_ref()[Nonce::<A>::SIZE - SEQ_NUM_LEN..]);
+        Ok(datagram)
+    }
+
+    /// Returns the number of datagrams that can still be encrypted.
+    #[inline]
+    pub fn remaining(&self) -> u64 {
+        self.sealer.remaining()
+    }
+}
+
+/// Decrypts and verifies datagrams produced by a `DatagramSealer`.
+///
+/// A `DatagramOpener` accepts datagrams in any order but rejects every
+/// sequence number it has already accepted with `Replayed`. It tracks
+/// the accepted sequence numbers in a `ReplayWindow` - by default the 64
+/// most recent ones. Datagrams that are older are rejected as well.
+///
+/// Datagrams that are not authentic never change the state of the
+/// `DatagramOpener`. Hence, an attacker cannot move the window by
+/// injecting forged datagrams.
+pub struct DatagramOpener<A: Algorithm> {
+    opener: Opener<A>,
+}
+
+impl<A: Algorithm> DatagramOpener<A> {
+    /// Creates a new `DatagramOpener` with the default `ReplayWindow`
+    /// that decrypts the datagrams of a `DatagramSealer` created with the
+    /// same `key` and `nonce`.
+    pub fn new(key: &Key<A>, nonce: Nonce<A>) -> Self {
+        Self::with_window(key, nonce, ReplayWindow::default())
+    }
+
+    /// Creates a new `DatagramOpener` that tracks the accepted datagrams
+    /// in the given `window`. A larger window tolerates more reordering.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sio::{Key, Nonce, Aad, DatagramOpener, DatagramSealer, ReplayWindow, CHACHA20_POLY1305};
+    ///
+    /// // Obviously, don't use this all-zeros key for anything real.
+    /// let key: Key<CHACHA20_POLY1305> = Key::new([0; Key::<CHACHA20_POLY1305>::SIZE]);
+    /// let nonce = Nonce::new([0; Nonce::<CHACHA20_POLY1305>::SIZE]);
+    ///
+    /// let mut sealer = DatagramSealer::new(&key, nonce);
+    /// let datagrams: Vec<_> = (0..1000)
+    ///     .map(|_| sealer.seal(Aad::empty(), b"Hello").unwrap())
+    ///     .collect();
+    ///
+    /// let mut opener = DatagramOpener::with_window(&key, nonce, ReplayWindow::new(1024));
+    /// assert!(opener.open(Aad::empty(), &datagrams[999]).is_ok());
+    /// assert!(opener.open(Aad::empty(), &datagrams[0]).is_ok());
+    /// ```
+    pub fn with_window(key: &Key<A>, nonce: Nonce<A>, window: ReplayWindow) -> Self {
+        DatagramOpener {
+            opener: Opener::with_window(key, nonce, window),
+        }
+    }
+
+    /// Returns the window of accepted datagrams.
+    #[inline]
+    pub fn window(&self) -> &ReplayWindow {
+        self.opener.window()
+    }
+
+    /// Decrypts and verifies the `datagram` and returns its sequence
+    /// number together with the plaintext.
+    ///
+    /// It fails with `NotAuthentic` if the `datagram` has been modified
+    /// or encrypted with a different key, nonce or associated data and
+    /// with `Replayed` if the sequence number has already been accepted
+    /// or is too old.
+    pub fn open(&mut self, aad: Aad<A>, datagram: &[u8]) -> io::Result<(u32, Vec<u8>)> {
+        if datagram.len() < SEQ_NUM_LEN {
+            return Err(NotAuthentic.into());
+        }
+        let (seq_num, ciphertext) = datagram.split_at(SEQ_NUM_LEN);
+        let seq_num = u32::from_be_bytes([seq_num[0], seq_num[1], seq_num[2], seq_num[3]]);
+        let plaintext = self.opener.open_seq_num(seq_num, aad, ciphertext)?;
+        Ok((seq_num, plaintext))
+    }
+}
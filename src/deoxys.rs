@@ -0,0 +1,285 @@
+// Copyright (c) 2019 Andreas Auernhammer. All rights reserved.
+// Use of this source code is governed by a license that can be
+// found in the LICENSE file.
+
+use crate::aead::Algorithm;
+use crate::error::{Invalid, NotAuthentic};
+
+/// Deoxys-II-256-128 - the nonce-misuse resistant AEAD algorithm of the
+/// final CAESAR portfolio.
+///
+/// Deoxys-II is built on the tweakable block cipher Deoxys-BC-384 instead
+/// of a universal hash - i.e. it shares no design with AES-GCM or
+/// AES-GCM-SIV except for the AES round function. Like AES-GCM-SIV, it
+/// computes the tag over the associated data and the plaintext first and
+/// derives the keystream from the tag. Hence, reusing a nonce only reveals
+/// whether two fragments are equal. It uses a 256 bit key, a 120 bit nonce
+/// and a 128 bit tag.
+///
+/// This is a portable implementation that does not use table lookups or
+/// branches that depend on secret data. It runs in constant time but is
+/// considerably slower than `AES_256_GCM` and `CHACHA20_POLY1305`.
+#[allow(non_camel_case_types)]
+pub struct DEOXYS_II_256_128 {
+    /// The round tweakeys of the key (`TK2` and `TK3`) combined with the
+    /// round constants. Only the tweak (`TK1`) must be added per block.
+    round_keys: [[u8; 16]; ROUNDS + 1],
+}
+
+const ROUNDS: usize = 16;
+
+/// The tweakey permutation `h`.
+const H: [usize; 16] = [1, 6, 11, 12, 5, 10, 15, 0, 9, 14, 3, 4, 13, 2, 7, 8];
+
+const RCON: [u8; ROUNDS + 1] = [
+    0x2f, 0x5e, 0xbc, 0x63, 0xc6, 0x97, 0x35, 0x6a, 0xd4, 0xb3, 0x7d, 0xfa, 0xef, 0xc5, 0x91, 0x39,
+    0x72,
+];
+
+/// The prefixes of the tweak that separate the different uses of the
+/// block cipher.
+const TWEAK_AD: u8 = 0x20;
+const TWEAK_AD_PARTIAL: u8 = 0x60;
+const TWEAK_MSG: u8 = 0x00;
+const TWEAK_MSG_PARTIAL: u8 = 0x40;
+const TWEAK_TAG: u8 = 0x10;
+const TWEAK_ENC: u8 = 0x80;
+
+impl Algorithm for DEOXYS_II_256_128 {
+    const KEY_LEN: usize = 256 / 8;
+    const NONCE_LEN: usize = 120 / 8;
+    const TAG_LEN: usize = 128 / 8;
+
+    fn new(key: &[u8; Self::KEY_LEN]) -> Self {
+        let mut tk2 = [0; 16];
+        let mut tk3 = [0; 16];
+        tk2.copy_from_slice(&key[16..]);
+        tk3.copy_from_slice(&key[..16]);
+
+        let mut round_keys = [[0; 16]; ROUNDS + 1];
+        for (i, round_key) in round_keys.iter_mut().enumerate() {
+            if i > 0 {
+                tk2 = permute(&tk2);
+                tk3 = permute(&tk3);
+                for (x, y) in tk2.iter_mut().zip(tk3.iter_mut()) {
+                    *x = (*x << 1) | ((*x >> 7) ^ ((*x >> 5) & 1));
+                    *y = (*y >> 1) | (((*y ^ (*y >> 6)) & 1) << 7);
+                }
+            }
+            for j in 0..16 {
+                round_key[j] = tk2[j] ^ tk3[j];
+            }
+            for (j, rc) in [1, 2, 4, 8].iter().enumerate() {
+                round_key[j] ^= rc;
+                round_key[4 + j] ^= RCON[i];
+            }
+        }
+        DEOXYS_II_256_128 { round_keys }
+    }
+
+    fn seal_in_place<'a>(
+        &self,
+        nonce: &[u8],
+        aad: &[u8],
+        in_out: &'a mut [u8],
+    ) -> Result<&'a [u8], Invalid> {
+        if nonce.len() != Self::NONCE_LEN {
+            return Err(Invalid::Nonce);
+        }
+        if in_out.len() < Self::TAG_LEN {
+            return Err(Invalid::BufSize);
+        }
+        let (msg, tag) = in_out.split_at_mut(in_out.len() - Self::TAG_LEN);
+        let t = self.tag(nonce, aad, msg);
+        self.xor_keystream(nonce, &t, msg);
+        tag.copy_from_slice(&t);
+        Ok(in_out)
+    }
+
+    fn open_in_place<'a>(
+        &self,
+        nonce: &[u8],
+        aad: &[u8],
+        in_out: &'a mut [u8],
+    ) -> Result<&'a [u8], NotAuthentic> {
+        if nonce.len() != Self::NONCE_LEN || in_out.len() < Self::TAG_LEN {
+            return Err(NotAuthentic);
+        }
+        let (msg, tag) = in_out.split_at_mut(in_out.len() - Self::TAG_LEN);
+        let mut expected = [0; 16];
+        expected.copy_from_slice(tag);
+        self.xor_keystream(nonce, &expected, msg);
+
+        let t = self.tag(nonce, aad, msg);
+        let diff = t
+            .iter()
+            .zip(expected.iter())
+            .fold(0, |d, (x, y)| d | (x ^ y));
+        if diff != 0 {
+            // Never reveal the plaintext of a fragment that is not authentic.
+            for b in msg.iter_mut() {
+                *b = 0;
+            }
+            return Err(NotAuthentic);
+        }
+        Ok(msg)
+    }
+}
+
+impl DEOXYS_II_256_128 {
+    /// Computes the tag of the associated data and the plaintext.
+    fn tag(&self, nonce: &[u8], aad: &[u8], msg: &[u8]) -> [u8; 16] {
+        let mut auth = [0; 16];
+        self.absorb(&mut auth, aad, TWEAK_AD, TWEAK_AD_PARTIAL);
+        self.absorb(&mut auth, msg, TWEAK_MSG, TWEAK_MSG_PARTIAL);
+
+        let mut tweak = [0; 16];
+        tweak[0] = TWEAK_TAG;
+        tweak[1..].copy_from_slice(nonce);
+        self.encrypt_block(&tweak, &mut auth);
+        auth
+    }
+
+    /// Encrypts every block of `data` with its index as tweak and adds the
+    /// result to `auth`. A partial last block is padded with `0x80` and
+    /// zeros and uses the `partial` tweak prefix.
+    fn absorb(&self, auth: &mut [u8; 16], data: &[u8], full: u8, partial: u8) {
+        for (i, chunk) in data.chunks(16).enumerate() {
+            let mut tweak = [0; 16];
+            tweak[8..].copy_from_slice(&(i as u64).to_be_bytes());
+            let mut block = [0; 16];
+            block[..chunk.len()].copy_from_slice(chunk);
+            if chunk.len() < 16 {
+                tweak[0] = partial;
+                block[chunk.len()] = 0x80;
+            } else {
+                tweak[0] = full;
+            }
+            self.encrypt_block(&tweak, &mut block);
+            for (a, b) in auth.iter_mut().zip(block.iter()) {
+                *a ^= b;
+            }
+        }
+    }
+
+    /// En/decrypts `data` with the keystream derived from the tag and the
+    /// nonce.
+    fn xor_keystream(&self, nonce: &[u8], tag: &[u8; 16], data: &mut [u8]) {
+        let mut counter = [0; 16];
+        counter[1..].copy_from_slice(nonce);
+        for (i, chunk) in data.chunks_mut(16).enumerate() {
+            let mut tweak = *tag;
+            tweak[0] |= TWEAK_ENC;
+            for (t, j) in tweak[8..].iter_mut().zip((i as u64).to_be_bytes().iter()) {
+                *t ^= j;
+            }
+            let mut keystream = counter;
+            self.encrypt_block(&tweak, &mut keystream);
+            for (b, k) in chunk.iter_mut().zip(keystream.iter()) {
+                *b ^= k;
+            }
+        }
+    }
+
+    /// Encrypts one block with Deoxys-BC-384 using the given tweak as `TK1`.
+    fn encrypt_block(&self, tweak: &[u8; 16], block: &mut [u8; 16]) {
+        let mut tk1 = *tweak;
+        add_round_key(block, &tk1, &self.round_keys[0]);
+        for round_key in &self.round_keys[1..] {
+            tk1 = permute(&tk1);
+            sub_bytes(block);
+            shift_rows(block);
+            mix_columns(block);
+            add_round_key(block, &tk1, round_key);
+        }
+    }
+}
+
+/// Applies the tweakey permutation `h`.
+#[inline]
+fn permute(tk: &[u8; 16]) -> [u8; 16] {
+    let mut out = [0; 16];
+    for (o, h) in out.iter_mut().zip(H.iter()) {
+        *o = tk[*h];
+    }
+    out
+}
+
+#[inline]
+fn add_round_key(state: &mut [u8; 16], tk1: &[u8; 16], round_key: &[u8; 16]) {
+    for i in 0..16 {
+        state[i] ^= tk1[i] ^ round_key[i];
+    }
+}
+
+/// One bit in every byte of a `u128`.
+const LO: u128 = u128::MAX / 0xff;
+
+/// Doubles all 16 bytes of `x` in GF(2^8) at once.
+#[inline]
+fn xtime16(x: u128) -> u128 {
+    ((x & (LO * 0x7f)) << 1) ^ (((x >> 7) & LO) * 0x1b)
+}
+
+/// Multiplies all 16 bytes of `a` and `b` pairwise in GF(2^8) without
+/// branches or table lookups.
+#[inline]
+fn mul16(mut a: u128, mut b: u128) -> u128 {
+    let mut r = 0;
+    for _ in 0..8 {
+        r ^= a & ((b & LO) * 0xff);
+        a = xtime16(a);
+        b = (b >> 1) & (LO * 0x7f);
+    }
+    r
+}
+
+/// Rotates all 16 bytes of `x` to the left by `n` bits.
+#[inline]
+fn rotl16(x: u128, n: u32) -> u128 {
+    let hi = LO * ((0xff << n) & 0xff);
+    ((x << n) & hi) | ((x >> (8 - n)) & !hi)
+}
+
+/// Applies the AES S-box to the state - i.e. the inverse in GF(2^8)
+/// computed as `x^254` followed by the affine transformation.
+fn sub_bytes(state: &mut [u8; 16]) {
+    let x = u128::from_le_bytes(*state);
+    let x2 = mul16(x, x);
+    let x3 = mul16(x2, x);
+    let x6 = mul16(x3, x3);
+    let x12 = mul16(x6, x6);
+    let x15 = mul16(x12, x3);
+    let x30 = mul16(x15, x15);
+    let x60 = mul16(x30, x30);
+    let x120 = mul16(x60, x60);
+    let x240 = mul16(x120, x120);
+    let x252 = mul16(x240, x12);
+    let inv = mul16(x252, x2);
+
+    let s = inv ^ rotl16(inv, 1) ^ rotl16(inv, 2) ^ rotl16(inv, 3) ^ rotl16(inv, 4) ^ (LO * 0x63);
+    *state = s.to_le_bytes();
+}
+
+/// Rotates row `r` of the - column-major - state by `r` columns.
+#[inline]
+fn shift_rows(state: &mut [u8; 16]) {
+    let s = *state;
+    for c in 0..4 {
+        for r in 0..4 {
+            state[4 * c + r] = s[4 * ((c + r) % 4) + r];
+        }
+    }
+}
+
+#[inline]
+fn mix_columns(state: &mut [u8; 16]) {
+    let xtime = |x: u8| (x << 1) ^ (0x1b & 0u8.wrapping_sub(x >> 7));
+    for column in state.chunks_mut(4) {
+        let a = [column[0], column[1], column[2], column[3]];
+        let t = a[0] ^ a[1] ^ a[2] ^ a[3];
+        for r in 0..4 {
+            column[r] = a[r] ^ t ^ xtime(a[r] ^ a[(r + 1) % 4]);
+        }
+    }
+}
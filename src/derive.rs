@@ -0,0 +1,58 @@
+// Copyright (c) 2019 Andreas Auernhammer. All rights reserved.
+// Use of this source code is governed by a license that can be
+// found in the LICENSE file.
+
+use super::{Algorithm, Nonce};
+use ring::{digest, hmac};
+
+/// The label that separates the nonce derivation from other uses
+/// of the derivation key.
+const LABEL: &[u8] = b"sio stream nonce";
+
+impl<A: Algorithm> Nonce<A> {
+    /// Derives the nonce of a stream from its unique identifier - the
+    /// `object_id` and its `version` - using HMAC-SHA256 keyed with the
+    /// `derivation_key`.
+    ///
+    /// Systems that already assign a unique identifier to every stream -
+    /// e.g. an object ID and a version that changes whenever the object is
+    /// rewritten - can re-derive the nonce whenever they need it instead
+    /// of storing it. The same `object_id` and `version` always produce
+    /// the same nonce. Hence, an identifier must never be used for two
+    /// different streams encrypted with the same key.
+    ///
+    /// The `derivation_key` should be a dedicated secret - not the key
+    /// that encrypts the streams. A secret derivation key prevents anyone
+    /// else from predicting the nonces.
+    ///
+    /// The derived nonces are pseudo-random. Two distinct identifiers
+    /// produce the same nonce with a probability of about 2^-64 for 8
+    /// byte nonces. Therefore, one key should not encrypt more than a few
+    /// billion streams - e.g. by rotating keys or by deriving the key of a
+    /// stream from its identifier as well.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sio::{Nonce, CHACHA20_POLY1305};
+    ///
+    /// let derivation_key = b"my secret nonce derivation key";
+    ///
+    /// let nonce = Nonce::<CHACHA20_POLY1305>::derive(derivation_key, b"bucket/object", 7);
+    /// let again = Nonce::<CHACHA20_POLY1305>::derive(derivation_key, b"bucket/object", 7);
+    /// assert_eq!(nonce.as_ref(), again.as_ref());
+    ///
+    /// let next = Nonce::<CHACHA20_POLY1305>::derive(derivation_key, b"bucket/object", 8);
+    /// assert_ne!(nonce.as_ref(), next.as_ref());
+    /// ```
+    pub fn derive(derivation_key: &[u8], object_id: &[u8], version: u64) -> Self {
+        let key = hmac::SigningKey::new(&digest::SHA256, derivation_key);
+        let mut ctx = hmac::SigningContext::with_key(&key);
+        ctx.update(LABEL);
+        // The length prefix keeps the encoding of the identifier unambiguous.
+        ctx.update(&(object_id.len() as u64).to_be_bytes());
+        ctx.update(object_id);
+        ctx.update(&version.to_be_bytes());
+        Nonce::from_slice(&ctx.sign().as_ref()[..Self::SIZE])
+    }
+}
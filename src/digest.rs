@@ -0,0 +1,173 @@
+// Copyright (c) 2019 Andreas Auernhammer. All rights reserved.
+// Use of this source code is governed by a license that can be
+// found in the LICENSE file.
+
+use super::{Aad, Algorithm, Close, DecWriter, DigestMismatch, Key, Nonce};
+use ring::constant_time;
+use ring::digest::{self, Context, Digest};
+use std::io::{self, Read, Write};
+use std::{fmt, iter, mem};
+
+/// The SHA-256 digests of the plaintext and the ciphertext of a stream.
+///
+/// They are computed by an `EncWriter` while the data flows through it
+/// and returned by `EncWriter::close_with_digests`. Hence, backup tools
+/// can record checksums of the plaintext and the ciphertext without a
+/// second pass over the data.
+#[derive(Clone, Copy)]
+pub struct Digests {
+    plaintext: Digest,
+    ciphertext: Digest,
+}
+
+impl Digests {
+    /// Returns the SHA-256 digest of all plaintext written to the
+    /// `EncWriter`.
+    #[inline]
+    pub fn plaintext(&self) -> &[u8] {
+        self.plaintext.as_ref()
+    }
+
+    /// Returns the SHA-256 digest of all ciphertext - including any
+    /// header and trailer of the framing - the `EncWriter` has written
+    /// to its inner writer.
+    #[inline]
+    pub fn ciphertext(&self) -> &[u8] {
+        self.ciphertext.as_ref()
+    }
+}
+
+impl fmt::Debug for Digests {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Digests")
+            .field("plaintext", &self.plaintext)
+            .field("ciphertext", &self.ciphertext)
+            .finish()
+    }
+}
+
+/// Computes the `Digests` of a stream.
+pub(crate) struct Hasher {
+    plaintext: Context,
+    ciphertext: Context,
+}
+
+impl Hasher {
+    pub(crate) fn new() -> Self {
+        Hasher {
+            plaintext: Context::new(&digest::SHA256),
+            ciphertext: Context::new(&digest::SHA256),
+        }
+    }
+
+    #[inline]
+    pub(crate) fn plaintext(&mut self, data: &[u8]) {
+        self.plaintext.update(data)
+    }
+
+    #[inline]
+    pub(crate) fn ciphertext(&mut self, data: &[u8]) {
+        self.ciphertext.update(data)
+    }
+
+    pub(crate) fn finish(self) -> Digests {
+        Digests {
+            plaintext: self.plaintext.finish(),
+            ciphertext: self.ciphertext.finish(),
+        }
+    }
+}
+
+/// Decrypts and verifies the ciphertext read from `reader`, writes the
+/// plaintext to `writer` and verifies that the SHA-256 digest of the
+/// plaintext matches `expected_sha256`.
+///
+/// The `writer` gets closed once the entire ciphertext has been verified
+/// and the digest of the plaintext matches. If the ciphertext is not
+/// authentic or the digest does not match - which is reported as
+/// `DigestMismatch` - the `writer` is dropped without being closed. Since
+/// the plaintext is streamed, it has been written to the `writer` before
+/// the digest can be checked. Hence, the `writer` should only publish the
+/// plaintext once it gets closed - e.g. by renaming a temporary file -
+/// such that a failure leaves no plaintext behind.
+///
+/// # Examples
+///
+/// ```
+/// use sio::{Key, Nonce, Aad, NopCloser, CHACHA20_POLY1305};
+///
+/// // Obviously, don't use this all-zeros key for anything real.
+/// let key: Key<CHACHA20_POLY1305> = Key::new([0; Key::<CHACHA20_POLY1305>::SIZE]);
+///
+/// let ciphertext = sio::encrypt_vec(
+///     &key,
+///     Nonce::new([0; Nonce::<CHACHA20_POLY1305>::SIZE]),
+///     Aad::empty(),
+///     b"Hello World",
+/// );
+/// let digest = ring::digest::digest(&ring::digest::SHA256, b"Hello World");
+///
+/// let mut plaintext = Vec::default();
+/// let mut expected = [0; 32];
+/// expected.copy_from_slice(digest.as_ref());
+/// sio::decrypt_verify_digest(
+///     ciphertext.as_slice(),
+///     NopCloser::wrap(&mut plaintext),
+///     &key,
+///     Nonce::new([0; Nonce::<CHACHA20_POLY1305>::SIZE]),
+///     Aad::empty(),
+///     &expected,
+/// )
+/// .unwrap();
+/// assert_eq!(plaintext, b"Hello World");
+/// ```
+pub fn decrypt_verify_digest<A: Algorithm, R: Read, W: Write + Close>(
+    mut reader: R,
+    writer: W,
+    key: &Key<A>,
+    nonce: Nonce<A>,
+    aad: Aad<A>,
+    expected_sha256: &[u8; 32],
+) -> io::Result<()> {
+    let verifier = Verifier {
+        inner: writer,
+        context: Context::new(&digest::SHA256),
+        expected: *expected_sha256,
+    };
+    let mut writer = DecWriter::new(verifier, key, nonce, aad);
+    match io::copy(&mut reader, &mut writer) {
+        Ok(_) => writer.close(),
+        // Fails the DecWriter without closing the inner writer.
+        Err(err) => writer.consume(iter::once(Err(err))).map(|_| ()),
+    }
+}
+
+/// Computes the SHA-256 digest of everything written to it and closes
+/// the inner writer only if the digest matches.
+struct Verifier<W: Write + Close> {
+    inner: W,
+    context: Context,
+    expected: [u8; 32],
+}
+
+impl<W: Write + Close> Write for Verifier<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.context.update(&buf[..n]);
+        Ok(n)
+    }
+
+    #[inline]
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl<W: Write + Close> Close for Verifier<W> {
+    fn close(&mut self) -> io::Result<()> {
+        let context = mem::replace(&mut self.context, Context::new(&digest::SHA256));
+        constant_time::verify_slices_are_equal(context.finish().as_ref(), &self.expected)
+            .map_err(|_| DigestMismatch)?;
+        self.inner.close()
+    }
+}
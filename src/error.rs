@@ -45,13 +45,42 @@ impl From<NotAuthentic> for io::Error {
     }
 }
 
-#[derive(Clone, Copy, PartialEq)]
-pub struct Exceeded;
+/// An error indicating that a stream cannot be processed any further
+/// because the nonce space of its key-nonce combination is exhausted.
+///
+/// It reports how many fragments and plaintext bytes have been processed
+/// before the limit has been reached. Callers should query the remaining
+/// capacity of an `EncWriter` beforehand and rotate the key - or nonce -
+/// in time.
+#[derive(Clone, Copy, Default, PartialEq)]
+pub struct Exceeded {
+    fragments: u64,
+    bytes: u64,
+}
 
 impl Exceeded {
     const fn description() -> &'static str {
         "data limit exceeded"
     }
+
+    /// Returns the number of fragments that have been processed
+    /// before the limit has been exceeded.
+    #[inline]
+    pub fn fragments(&self) -> u64 {
+        self.fragments
+    }
+
+    /// Returns the number of plaintext bytes that have been processed
+    /// before the limit has been exceeded.
+    #[inline]
+    pub fn bytes(&self) -> u64 {
+        self.bytes
+    }
+
+    #[inline]
+    pub(crate) fn processed(self, fragments: u64, bytes: u64) -> Self {
+        Exceeded { fragments, bytes }
+    }
 }
 
 impl Error for Exceeded {
@@ -64,21 +93,304 @@ impl Error for Exceeded {
 impl fmt::Display for Exceeded {
     #[inline]
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", Self::description())
+        write!(
+            f,
+            "{} after {} fragments ({} bytes)",
+            Self::description(),
+            self.fragments,
+            self.bytes
+        )
     }
 }
 
 impl fmt::Debug for Exceeded {
     #[inline]
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", Self::description())
+        fmt::Display::fmt(self, f)
     }
 }
 
 impl From<Exceeded> for io::Error {
     #[inline]
-    fn from(_: Exceeded) -> Self {
-        io::Error::new(io::ErrorKind::InvalidData, Exceeded)
+    fn from(e: Exceeded) -> Self {
+        io::Error::new(io::ErrorKind::InvalidData, e)
+    }
+}
+
+/// An error indicating that a write to an `EncWriter` would exceed its
+/// plaintext limit set via `EncWriter::max_plaintext`.
+///
+/// The write that would exceed the limit is rejected. Hence, the
+/// `EncWriter` can still be closed and contains exactly the plaintext
+/// written before. `max_plaintext` itself fails with a `QuotaExceeded`
+/// error if more than the limit has already been written.
+#[derive(Clone, Copy, PartialEq)]
+pub struct QuotaExceeded {
+    limit: u64,
+    written: u64,
+}
+
+impl QuotaExceeded {
+    const fn description() -> &'static str {
+        "plaintext quota exceeded"
+    }
+
+    #[inline]
+    pub(crate) fn new(limit: u64, written: u64) -> Self {
+        QuotaExceeded { limit, written }
+    }
+
+    /// Returns the maximum number of plaintext bytes.
+    #[inline]
+    pub fn limit(&self) -> u64 {
+        self.limit
+    }
+
+    /// Returns the number of plaintext bytes that have been
+    /// written before.
+    #[inline]
+    pub fn written(&self) -> u64 {
+        self.written
+    }
+}
+
+impl Error for QuotaExceeded {
+    #[inline]
+    fn description(&self) -> &str {
+        Self::description()
+    }
+}
+
+impl fmt::Display for QuotaExceeded {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: {} of {} bytes written",
+            Self::description(),
+            self.written,
+            self.limit
+        )
+    }
+}
+
+impl fmt::Debug for QuotaExceeded {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+impl From<QuotaExceeded> for io::Error {
+    #[inline]
+    fn from(e: QuotaExceeded) -> Self {
+        io::Error::other(e)
+    }
+}
+
+/// An error indicating that more ciphertext has been written
+/// to a `DecWriter` after the final fragment of the stream.
+///
+/// It is only returned by a `DecWriter` in strict mode.
+#[derive(Clone, Copy, PartialEq)]
+pub struct TrailingData;
+
+impl TrailingData {
+    const fn description() -> &'static str {
+        "unexpected data after the final fragment"
+    }
+}
+
+impl Error for TrailingData {
+    #[inline]
+    fn description(&self) -> &str {
+        Self::description()
+    }
+}
+
+impl fmt::Display for TrailingData {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", Self::description())
+    }
+}
+
+impl fmt::Debug for TrailingData {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", Self::description())
+    }
+}
+
+impl From<TrailingData> for io::Error {
+    #[inline]
+    fn from(_: TrailingData) -> Self {
+        io::Error::new(io::ErrorKind::InvalidData, TrailingData)
+    }
+}
+
+/// An error indicating that the length of a stream does not match the
+/// total plaintext length recorded by its framing - e.g. `Delimited`.
+///
+/// It is returned when more or less plaintext is written to an `EncWriter`
+/// than recorded and when a `DecReader` reaches the end of a truncated
+/// stream.
+#[derive(Clone, Copy, PartialEq)]
+pub struct LengthMismatch;
+
+impl LengthMismatch {
+    const fn description() -> &'static str {
+        "plaintext length does not match the declared length"
+    }
+}
+
+impl Error for LengthMismatch {
+    #[inline]
+    fn description(&self) -> &str {
+        Self::description()
+    }
+}
+
+impl fmt::Display for LengthMismatch {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", Self::description())
+    }
+}
+
+impl fmt::Debug for LengthMismatch {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", Self::description())
+    }
+}
+
+impl From<LengthMismatch> for io::Error {
+    #[inline]
+    fn from(_: LengthMismatch) -> Self {
+        io::Error::new(io::ErrorKind::InvalidData, LengthMismatch)
+    }
+}
+
+/// An error indicating that the plaintext of an authentic stream does
+/// not match the expected SHA-256 digest.
+///
+/// It is returned by `decrypt_verify_digest`.
+#[derive(Clone, Copy, PartialEq)]
+pub struct DigestMismatch;
+
+impl DigestMismatch {
+    const fn description() -> &'static str {
+        "plaintext does not match the expected digest"
+    }
+}
+
+impl Error for DigestMismatch {
+    #[inline]
+    fn description(&self) -> &str {
+        Self::description()
+    }
+}
+
+impl fmt::Display for DigestMismatch {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", Self::description())
+    }
+}
+
+impl fmt::Debug for DigestMismatch {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", Self::description())
+    }
+}
+
+impl From<DigestMismatch> for io::Error {
+    #[inline]
+    fn from(_: DigestMismatch) -> Self {
+        io::Error::new(io::ErrorKind::InvalidData, DigestMismatch)
+    }
+}
+
+/// An error indicating that a message has already been received or is
+/// too old to tell.
+///
+/// It is returned by a `ReplayWindow` - e.g. when an `Opener` or a
+/// `DatagramOpener` receives a replayed message.
+#[derive(Clone, Copy, PartialEq)]
+pub struct Replayed;
+
+impl Replayed {
+    const fn description() -> &'static str {
+        "message has already been received or is too old"
+    }
+}
+
+impl Error for Replayed {
+    #[inline]
+    fn description(&self) -> &str {
+        Self::description()
+    }
+}
+
+impl fmt::Display for Replayed {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", Self::description())
+    }
+}
+
+impl fmt::Debug for Replayed {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", Self::description())
+    }
+}
+
+impl From<Replayed> for io::Error {
+    #[inline]
+    fn from(_: Replayed) -> Self {
+        io::Error::new(io::ErrorKind::InvalidData, Replayed)
+    }
+}
+
+/// An error indicating that an encrypted stream has a format version
+/// that is unknown or has not been allowed explicitly.
+#[derive(Clone, Copy, PartialEq)]
+pub struct UnsupportedVersion;
+
+impl UnsupportedVersion {
+    const fn description() -> &'static str {
+        "unsupported format version"
+    }
+}
+
+impl Error for UnsupportedVersion {
+    #[inline]
+    fn description(&self) -> &str {
+        Self::description()
+    }
+}
+
+impl fmt::Display for UnsupportedVersion {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", Self::description())
+    }
+}
+
+impl fmt::Debug for UnsupportedVersion {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", Self::description())
+    }
+}
+
+impl From<UnsupportedVersion> for io::Error {
+    #[inline]
+    fn from(_: UnsupportedVersion) -> Self {
+        io::Error::new(io::ErrorKind::InvalidData, UnsupportedVersion)
     }
 }
 
@@ -87,6 +399,8 @@ pub enum Invalid {
     Key,
     Nonce,
     BufSize,
+    Metadata,
+    CiphertextLen,
 }
 
 impl Error for Invalid {
@@ -95,6 +409,8 @@ impl Error for Invalid {
             Invalid::Key => "sio::Invalid::Key",
             Invalid::Nonce => "sio::Invalid::Nonce",
             Invalid::BufSize => "sio::Invalid::BufSize",
+            Invalid::Metadata => "sio::Invalid::Metadata",
+            Invalid::CiphertextLen => "sio::Invalid::CiphertextLen",
         }
     }
 }
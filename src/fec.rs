@@ -0,0 +1,450 @@
+// Copyright (c) 2019 Andreas Auernhammer. All rights reserved.
+// Use of this source code is governed by a license that can be
+// found in the LICENSE file.
+
+//! Reed-Solomon erasure coding of encrypted streams.
+//!
+//! A `FecEncWriter` groups the ciphertext fragments of an `EncWriter` into
+//! groups of `data` shards - one shard per fragment - and appends `parity`
+//! parity shards to every group. Each shard is followed by a short
+//! checksum. A `FecDecWriter` uses the checksums to detect damaged shards,
+//! reconstructs up to `parity` damaged shards per group and only then
+//! passes the ciphertext to a `DecWriter` for authentication.
+//!
+//! The checksums only detect accidental damage - like bit rot on archival
+//! media. They do not provide any security. A group with more damaged
+//! shards than parity shards is passed to the `DecWriter` as is, such that
+//! its authentication fails - or the damaged fragments get skipped in
+//! forensic mode.
+//!
+//! # Examples
+//!
+//! ```
+//! use std::io::Write;
+//! use sio::{Key, Nonce, Aad, CHACHA20_POLY1305};
+//! use sio::fec::{FecEncWriter, FecDecWriter};
+//!
+//! // Obviously, don't use this all-zeros key for anything real.
+//! let key: Key<CHACHA20_POLY1305> = Key::new([0; Key::<CHACHA20_POLY1305>::SIZE]);
+//!
+//! let mut ciphertext: Vec<u8> = Vec::default();
+//! let mut writer = FecEncWriter::with_buffer_size(
+//!     &mut ciphertext,
+//!     &key,
+//!     Nonce::new([0; Nonce::<CHACHA20_POLY1305>::SIZE]),
+//!     Aad::empty(),
+//!     64,
+//!     4,
+//!     2,
+//! )
+//! .unwrap();
+//! writer.write_all(&[1; 1000]).unwrap();
+//! writer.close().unwrap();
+//!
+//! ciphertext[10] ^= 1; // Damage the first fragment.
+//!
+//! let mut plaintext: Vec<u8> = Vec::default();
+//! let mut writer = FecDecWriter::with_buffer_size(
+//!     &mut plaintext,
+//!     &key,
+//!     Nonce::new([0; Nonce::<CHACHA20_POLY1305>::SIZE]),
+//!     Aad::empty(),
+//!     64,
+//!     4,
+//!     2,
+//! )
+//! .unwrap();
+//! writer.write_all(ciphertext.as_slice()).unwrap();
+//! assert_eq!(writer.close().unwrap(), 1);
+//! assert_eq!(plaintext, vec![1; 1000]);
+//! ```
+
+use super::writer::{Close, Closer};
+use super::{Aad, Algorithm, DecWriter, EncWriter, Key, Nonce, BUF_SIZE};
+use reed_solomon_erasure::galois_8::ReedSolomon;
+use ring::digest;
+use std::io::{self, Write};
+
+/// The length of the checksum following every shard.
+pub const CHECKSUM_LEN: usize = 8;
+
+/// Wraps a writer and encrypts, authenticates and erasure codes everything
+/// written to it.
+///
+/// The ciphertext produced by an `EncWriter` is split into groups of `data`
+/// shards of one fragment each. Every group is followed by `parity` parity
+/// shards. The ciphertext can be repaired, decrypted and verified by a
+/// `FecDecWriter` using the same number of data and parity shards.
+///
+/// Like an `EncWriter`, a `FecEncWriter` must be closed explicitly.
+pub struct FecEncWriter<A: Algorithm, W: Write + Close> {
+    writer: EncWriter<A, Parity<W>>,
+}
+
+impl<A: Algorithm, W: Write + Close> FecEncWriter<A, W> {
+    /// Creates a new `FecEncWriter` with a default buffer size of 16 KiB
+    /// that appends `parity` parity shards to every group of `data`
+    /// fragments.
+    ///
+    /// The sum of `data` and `parity` must not be greater than 256 and
+    /// neither must be `0`.
+    pub fn new(
+        inner: W,
+        key: &Key<A>,
+        nonce: Nonce<A>,
+        aad: Aad<A>,
+        data: usize,
+        parity: usize,
+    ) -> io::Result<Self> {
+        Self::with_buffer_size(inner, key, nonce, aad, BUF_SIZE, data, parity)
+    }
+
+    /// Creates a new `FecEncWriter` with the specified buffer size as
+    /// fragment size.
+    pub fn with_buffer_size(
+        inner: W,
+        key: &Key<A>,
+        nonce: Nonce<A>,
+        aad: Aad<A>,
+        buf_size: usize,
+        data: usize,
+        parity: usize,
+    ) -> io::Result<Self> {
+        let shard_len = shard_len::<A>(buf_size)?;
+        let inner = Parity::new(inner, shard_len, data, parity)?;
+        Ok(FecEncWriter {
+            writer: EncWriter::with_buffer_size(inner, key, nonce, aad, buf_size)?,
+        })
+    }
+
+    /// Encrypts the remaining plaintext, writes the parity shards of the
+    /// last group and closes the inner writer.
+    #[must_use = "A FecEncWriter must be closed to successfully complete the encryption process. Ignoring this result may cause incomplete ciphertext data."]
+    #[inline]
+    pub fn close(self) -> io::Result<()> {
+        self.writer.close()
+    }
+}
+
+impl<A: Algorithm, W: Write + Close> Write for FecEncWriter<A, W> {
+    #[inline]
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.writer.write(buf)
+    }
+
+    #[inline]
+    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        self.writer.write_all(buf)
+    }
+
+    #[inline]
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+/// Wraps a writer and repairs, decrypts and verifies everything written
+/// to it.
+///
+/// It reconstructs damaged fragments of ciphertext produced by a
+/// `FecEncWriter` - if possible - before decrypting and verifying them
+/// using a `DecWriter`. Like a `DecWriter`, a `FecDecWriter` must be
+/// closed explicitly.
+pub struct FecDecWriter<A: Algorithm, W: Write + Close> {
+    writer: Repair<Closer<DecWriter<A, W>>>,
+}
+
+impl<A: Algorithm, W: Write + Close> FecDecWriter<A, W> {
+    /// Creates a new `FecDecWriter` with a default buffer size of 16 KiB.
+    /// The number of `data` and `parity` shards must match the ones used
+    /// to encrypt the data.
+    pub fn new(
+        inner: W,
+        key: &Key<A>,
+        nonce: Nonce<A>,
+        aad: Aad<A>,
+        data: usize,
+        parity: usize,
+    ) -> io::Result<Self> {
+        Self::with_buffer_size(inner, key, nonce, aad, BUF_SIZE, data, parity)
+    }
+
+    /// Creates a new `FecDecWriter` with the specified buffer size.
+    /// It must match the buffer size used to encrypt the data.
+    pub fn with_buffer_size(
+        inner: W,
+        key: &Key<A>,
+        nonce: Nonce<A>,
+        aad: Aad<A>,
+        buf_size: usize,
+        data: usize,
+        parity: usize,
+    ) -> io::Result<Self> {
+        Self::with_writer(
+            DecWriter::with_buffer_size(inner, key, nonce, aad, buf_size)?,
+            buf_size,
+            data,
+            parity,
+        )
+    }
+
+    /// Creates a new `FecDecWriter` that passes the repaired ciphertext
+    /// to the `writer`. The `DecWriter` can be configured - e.g. to enable
+    /// the strict mode. The `buf_size` must match the buffer size of the
+    /// `writer`.
+    pub fn with_writer(
+        writer: DecWriter<A, W>,
+        buf_size: usize,
+        data: usize,
+        parity: usize,
+    ) -> io::Result<Self> {
+        let params = shard_len::<A>(buf_size).and_then(|n| Ok((n, reed_solomon(data, parity)?)));
+        match params {
+            Ok((shard_len, rs)) => Ok(FecDecWriter {
+                writer: Repair::new(writer.closer(), shard_len, rs),
+            }),
+            Err(err) => {
+                // The DecWriter would panic if dropped without being closed.
+                let _ = writer.close();
+                Err(err)
+            }
+        }
+    }
+
+    /// Returns the number of shards that have been reconstructed so far.
+    #[inline]
+    pub fn repaired(&self) -> u64 {
+        self.writer.repaired
+    }
+
+    /// Repairs, decrypts and verifies the remaining ciphertext and closes
+    /// the `DecWriter` and the inner writer. It returns the number of
+    /// shards that have been reconstructed.
+    #[must_use = "A FecDecWriter must be closed to successfully complete the decryption process. Ignoring this result may cause incomplete plaintext data."]
+    pub fn close(mut self) -> io::Result<u64> {
+        Close::close(&mut self.writer).map(|()| self.writer.repaired)
+    }
+}
+
+impl<A: Algorithm, W: Write + Close> Write for FecDecWriter<A, W> {
+    #[inline]
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.writer.write(buf)
+    }
+
+    #[inline]
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+fn shard_len<A: Algorithm>(buf_size: usize) -> io::Result<usize> {
+    buf_size
+        .checked_add(A::TAG_LEN)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "invalid buffer size"))
+}
+
+fn reed_solomon(data: usize, parity: usize) -> io::Result<ReedSolomon> {
+    ReedSolomon::new(data, parity).map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "invalid number of data or parity shards",
+        )
+    })
+}
+
+fn checksum(shard: &[u8]) -> [u8; CHECKSUM_LEN] {
+    let mut sum = [0; CHECKSUM_LEN];
+    sum.copy_from_slice(&digest::digest(&digest::SHA256, shard).as_ref()[..CHECKSUM_LEN]);
+    sum
+}
+
+/// Computes the parity shards of the `data` shards. All shards must have
+/// the same length.
+fn encode(rs: &ReedSolomon, data: &[&[u8]], parity: &mut [Vec<u8>]) -> io::Result<()> {
+    let mut parity: Vec<&mut [u8]> = parity.iter_mut().map(|p| p.as_mut_slice()).collect();
+    rs.encode_sep(data, &mut parity)
+        .map_err(|_| io::Error::other("failed to compute the parity shards"))
+}
+
+/// Appends parity shards to every group of data shards written to it.
+struct Parity<W: Write + Close> {
+    inner: W,
+    rs: ReedSolomon,
+    shard_len: usize,
+    group: Vec<u8>,
+    parity: Vec<Vec<u8>>,
+}
+
+impl<W: Write + Close> Parity<W> {
+    fn new(inner: W, shard_len: usize, data: usize, parity: usize) -> io::Result<Self> {
+        Ok(Parity {
+            inner,
+            rs: reed_solomon(data, parity)?,
+            shard_len,
+            group: Vec::with_capacity(data * shard_len),
+            parity: vec![vec![0; shard_len]; parity],
+        })
+    }
+
+    /// Writes the data shards of the current group, which may be shorter
+    /// than a full group, followed by its parity shards.
+    fn write_group(&mut self) -> io::Result<()> {
+        let zeros = vec![0; self.shard_len];
+        let mut shards: Vec<&[u8]> = Vec::with_capacity(self.rs.data_shard_count());
+        let mut last = Vec::default();
+        for shard in self.group.chunks(self.shard_len) {
+            self.inner.write_all(shard)?;
+            self.inner.write_all(&checksum(shard))?;
+            if shard.len() < self.shard_len {
+                last = shard.to_vec();
+                last.resize(self.shard_len, 0);
+            } else {
+                shards.push(shard);
+            }
+        }
+        if !last.is_empty() {
+            shards.push(&last);
+        }
+        shards.resize(self.rs.data_shard_count(), &zeros);
+
+        encode(&self.rs, &shards, &mut self.parity)?;
+        for shard in &self.parity {
+            self.inner.write_all(shard)?;
+            self.inner.write_all(&checksum(shard))?;
+        }
+        self.group.clear();
+        Ok(())
+    }
+}
+
+impl<W: Write + Close> Write for Parity<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let group_len = self.rs.data_shard_count() * self.shard_len;
+        let n = buf.len().min(group_len - self.group.len());
+        self.group.extend_from_slice(&buf[..n]);
+        if self.group.len() == group_len {
+            self.write_group()?;
+        }
+        Ok(n)
+    }
+
+    #[inline]
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl<W: Write + Close> Close for Parity<W> {
+    fn close(&mut self) -> io::Result<()> {
+        if !self.group.is_empty() {
+            self.write_group()?;
+        }
+        self.inner.close()
+    }
+}
+
+/// Reconstructs damaged data shards of every group written to it and
+/// writes the data shards to the inner writer.
+struct Repair<W: Write + Close> {
+    inner: W,
+    rs: ReedSolomon,
+    shard_len: usize,
+    group: Vec<u8>,
+    repaired: u64,
+}
+
+impl<W: Write + Close> Repair<W> {
+    fn new(inner: W, shard_len: usize, rs: ReedSolomon) -> Self {
+        Repair {
+            inner,
+            group: Vec::with_capacity(rs.total_shard_count() * (shard_len + CHECKSUM_LEN)),
+            rs,
+            shard_len,
+            repaired: 0,
+        }
+    }
+
+    /// Repairs and writes the group of `len` bytes at the beginning of the
+    /// buffer. Only the last group may contain fewer than all data shards
+    /// and a shorter last data shard.
+    fn write_group(&mut self, len: usize) -> io::Result<()> {
+        let stride = self.shard_len + CHECKSUM_LEN;
+        let parity_len = self.rs.parity_shard_count() * stride;
+        let data_len = len.saturating_sub(parity_len);
+        let count = data_len.div_ceil(stride);
+        if data_len <= count.saturating_sub(1) * stride + CHECKSUM_LEN
+            || count > self.rs.data_shard_count()
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "the erasure coded stream is truncated",
+            ));
+        }
+
+        let shard_len = self.shard_len;
+        let group = &self.group[..len];
+        let verify = |shard: &[u8]| -> Option<Vec<u8>> {
+            let (shard, sum) = shard.split_at(shard.len() - CHECKSUM_LEN);
+            if checksum(shard) == sum {
+                let mut shard = shard.to_vec();
+                shard.resize(shard_len, 0);
+                Some(shard)
+            } else {
+                None
+            }
+        };
+        let mut shards: Vec<Option<Vec<u8>>> =
+            group[..data_len].chunks(stride).map(verify).collect();
+        shards.resize(self.rs.data_shard_count(), Some(vec![0; shard_len]));
+        shards.extend(group[data_len..].chunks(stride).map(verify));
+
+        let damaged = shards[..count].iter().filter(|s| s.is_none()).count();
+        if damaged > 0 && self.rs.reconstruct_data(&mut shards).is_ok() {
+            self.repaired += damaged as u64;
+        }
+        // A shard that could not be reconstructed is passed on as is such
+        // that its authentication fails.
+        for (i, shard) in group[..data_len].chunks(stride).enumerate() {
+            let len = shard.len() - CHECKSUM_LEN;
+            let shard = shards[i].as_deref().unwrap_or(shard);
+            self.inner.write_all(&shard[..len])?;
+        }
+        self.group.drain(..len);
+        Ok(())
+    }
+}
+
+impl<W: Write + Close> Write for Repair<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        // A full group is only processed once more data arrives.
+        // Otherwise, it may be the last group.
+        let group_len = self.rs.total_shard_count() * (self.shard_len + CHECKSUM_LEN);
+        if self.group.len() == group_len && !buf.is_empty() {
+            self.write_group(group_len)?;
+        }
+        let n = buf.len().min(group_len - self.group.len());
+        self.group.extend_from_slice(&buf[..n]);
+        Ok(n)
+    }
+
+    #[inline]
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl<W: Write + Close> Close for Repair<W> {
+    fn close(&mut self) -> io::Result<()> {
+        let r = if self.group.is_empty() {
+            Ok(())
+        } else {
+            self.write_group(self.group.len())
+        };
+
+        // The inner writer must be closed even if the last group is
+        // truncated. Otherwise, the DecWriter would panic when dropped.
+        let c = self.inner.close();
+        r.and(c)
+    }
+}
@@ -0,0 +1,353 @@
+// Copyright (c) 2019 Andreas Auernhammer. All rights reserved.
+// Use of this source code is governed by a license that can be
+// found in the LICENSE file.
+
+use super::framing::{Framing, Sio};
+use super::utils;
+use super::{
+    Aad, Algorithm, DecWriter, Invalid, Key, Nonce, NopCloser, NotAuthentic, BUF_SIZE, MAX_BUF_SIZE,
+};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Decrypts the file `src` and writes the plaintext to the file `dst`.
+///
+/// The plaintext is written to a temporary file in the directory of `dst`
+/// first. Only once the entire ciphertext has been verified successfully,
+/// the temporary file is renamed to `dst`. Therefore, `dst` never contains
+/// partially verified plaintext. If decryption fails, the temporary file is
+/// removed and `dst` remains untouched.
+///
+/// # Examples
+///
+/// ```no_run
+/// use sio::{Key, Nonce, Aad, CHACHA20_POLY1305};
+///
+/// // Obviously, don't use this all-zeros key for anything real.
+/// let key: Key<CHACHA20_POLY1305> = Key::new([0; Key::<CHACHA20_POLY1305>::SIZE]);
+///
+/// sio::decrypt_file_atomic(
+///     "foo.txt.enc",
+///     "foo.txt",
+///     &key,
+///     Nonce::new([0; Nonce::<CHACHA20_POLY1305>::SIZE]),
+///     Aad::empty(),
+/// )
+/// .unwrap();
+/// ```
+pub fn decrypt_file_atomic<A: Algorithm, P: AsRef<Path>, Q: AsRef<Path>>(
+    src: P,
+    dst: Q,
+    key: &Key<A>,
+    nonce: Nonce<A>,
+    aad: Aad<A>,
+) -> io::Result<()> {
+    let mut src = File::open(src)?;
+    let dst = dst.as_ref();
+    let (tmp, mut file) = create_temp_file(dst)?;
+
+    let mut writer = DecWriter::new(NopCloser::wrap(&mut file), key, nonce, aad);
+    let result = match io::copy(&mut src, &mut writer) {
+        Ok(_) => writer.close(),
+        Err(err) => {
+            // We have to close the DecWriter before dropping it. The
+            // incomplete plaintext gets removed anyway.
+            let _ = writer.close();
+            Err(err)
+        }
+    };
+    let result = result
+        .and_then(|()| file.sync_all())
+        .and_then(|()| fs::rename(&tmp, dst));
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp);
+    }
+    result
+}
+
+/// Creates a new temporary file next to `path`.
+fn create_temp_file(path: &Path) -> io::Result<(PathBuf, File)> {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+    let name = path
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "invalid file name"))?;
+    loop {
+        let mut tmp_name = std::ffi::OsString::from(".");
+        tmp_name.push(name);
+        tmp_name.push(format!(
+            ".{}.{}.tmp",
+            process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        let tmp = path.with_file_name(tmp_name);
+        match OpenOptions::new().write(true).create_new(true).open(&tmp) {
+            Ok(file) => return Ok((tmp, file)),
+            Err(ref err) if err.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+/// An encrypted file that supports random-access reads and appending.
+///
+/// The content of an `EncryptedFile` is a regular encrypted stream - as
+/// produced by an `EncWriter` with the same buffer size - stored in the
+/// `inner` storage. An empty storage is treated as an empty file.
+///
+/// Reads and seeks operate on the plaintext. Reading decrypts and verifies
+/// only the fragment containing the current position. The length of the
+/// file is verified by decrypting the final fragment whenever `len` is
+/// called.
+///
+/// Appending decrypts the final fragment, extends its plaintext and
+/// re-seals it as regular fragment - or as final fragment if all appended
+/// data fits into it. Only the final fragment and the new fragments are
+/// written to the `inner` storage.
+///
+/// # Security
+///
+/// A final fragment that is not full gets re-sealed with the same key and
+/// nonce when data is appended. Its plaintext only grows such that the
+/// re-sealed fragment reveals no additional plaintext. However, an attacker
+/// who observes the storage before and after appending obtains two different
+/// fragments for the same key and nonce and may be able to forge fragments
+/// afterwards. Therefore, `append` must only be used when the previous
+/// versions of the file are not observable by an attacker.
+///
+/// Further, an `append` that gets interrupted - e.g. by a crash - may leave
+/// a file whose final fragment is not authentic anymore.
+///
+/// # Examples
+///
+/// ```
+/// use std::io::{Cursor, Read, Seek, SeekFrom};
+/// use sio::{Key, Nonce, Aad, EncryptedFile, CHACHA20_POLY1305};
+///
+/// // Obviously, don't use this all-zeros key for anything real.
+/// let key: Key<CHACHA20_POLY1305> = Key::new([0; Key::<CHACHA20_POLY1305>::SIZE]);
+///
+/// let mut file = EncryptedFile::new(
+///     Cursor::new(Vec::default()),
+///     &key,
+///     Nonce::new([0; Nonce::<CHACHA20_POLY1305>::SIZE]),
+///     Aad::empty(),
+/// );
+/// file.append(b"Hello").unwrap();
+/// file.append(b" World").unwrap();
+/// assert_eq!(file.len().unwrap(), 11);
+///
+/// let mut plaintext = String::default();
+/// file.seek(SeekFrom::Start(6)).unwrap();
+/// file.read_to_string(&mut plaintext).unwrap();
+/// assert_eq!(plaintext, "World");
+/// ```
+pub struct EncryptedFile<A: Algorithm, S: Read + Write + Seek> {
+    inner: S,
+    algorithm: A,
+    framing: Sio,
+    buffer: Box<[u8]>,
+    buf_size: usize,
+
+    // The current plaintext position.
+    pos: u64,
+
+    // The plaintext length once the final fragment has been verified.
+    len: Option<u64>,
+
+    // The index of the fragment whose plaintext is stored in the
+    // buffer - if any.
+    fragment: Option<u64>,
+}
+
+impl<A: Algorithm, S: Read + Write + Seek> EncryptedFile<A, S> {
+    /// Creates a new `EncryptedFile` with a default buffer size of 16 KiB.
+    pub fn new(inner: S, key: &Key<A>, nonce: Nonce<A>, aad: Aad<A>) -> Self {
+        Self::with_buffer_size(inner, key, nonce, aad, BUF_SIZE).unwrap()
+    }
+
+    /// Creates a new `EncryptedFile` with the specified buffer size as
+    /// fragment size. The `buf_size` must not be `0` nor greater than
+    /// `MAX_BUF_SIZE` and must match the buffer size used to encrypt
+    /// any existing content.
+    pub fn with_buffer_size(
+        inner: S,
+        key: &Key<A>,
+        nonce: Nonce<A>,
+        aad: Aad<A>,
+        buf_size: usize,
+    ) -> Result<Self, Invalid> {
+        if buf_size == 0 || buf_size > MAX_BUF_SIZE {
+            return Err(Invalid::BufSize);
+        }
+        let algorithm = A::new(key.as_ref());
+        let mut framing = Sio::default();
+        framing.init(key.as_ref(), &algorithm, nonce.as_ref(), aad.as_ref())?;
+        Ok(EncryptedFile {
+            inner,
+            algorithm,
+            framing,
+            buffer: vec![0; buf_size + A::TAG_LEN].into_boxed_slice(),
+            buf_size,
+            pos: 0,
+            len: None,
+            fragment: None,
+        })
+    }
+
+    /// Returns the length of the plaintext.
+    ///
+    /// It verifies the final fragment and, therefore, detects whether
+    /// the file has been truncated.
+    #[allow(clippy::len_without_is_empty)]
+    pub fn len(&mut self) -> io::Result<u64> {
+        if let Some(len) = self.len {
+            return Ok(len);
+        }
+        self.final_fragment()?;
+        Ok(self.len.unwrap_or(0))
+    }
+
+    /// Appends `data` to the end of the file.
+    ///
+    /// See the security considerations of the `EncryptedFile` type.
+    pub fn append(&mut self, data: &[u8]) -> io::Result<()> {
+        if data.is_empty() {
+            return Ok(());
+        }
+        let (mut index, mut pos) = self.final_fragment()?.unwrap_or((0, 0));
+        let len = index * self.buf_size as u64 + (pos + data.len()) as u64;
+
+        // The buffer gets overwritten and, if appending fails,
+        // the length of the file is unknown.
+        self.fragment = None;
+        self.len = None;
+
+        let fragment_len = (self.buf_size + A::TAG_LEN) as u64;
+        self.inner.seek(SeekFrom::Start(index * fragment_len))?;
+        let mut data = data;
+        loop {
+            let n = data.len().min(self.buf_size - pos);
+            self.buffer[pos..pos + n].copy_from_slice(&data[..n]);
+            pos += n;
+            data = &data[n..];
+
+            let last = data.is_empty();
+            let mut framing = self.framing.clone();
+            framing.seek(index)?;
+            let (nonce, aad) = framing.next(&mut self.algorithm, last)?;
+            let ciphertext =
+                self.algorithm
+                    .seal_in_place(nonce, aad, &mut self.buffer[..pos + A::TAG_LEN])?;
+            utils::write_all(
+                &mut self.inner,
+                ciphertext,
+                format_args!("the ciphertext of fragment {}", index),
+            )?;
+            if last {
+                self.len = Some(len);
+                return Ok(());
+            }
+            index += 1;
+            pos = 0;
+        }
+    }
+
+    /// Unwraps the `EncryptedFile` and returns the inner storage.
+    #[inline]
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+
+    /// Decrypts and verifies the final fragment and returns its index and
+    /// the length of its plaintext - or `None` if the storage is empty.
+    /// The plaintext of the final fragment is kept in the buffer.
+    fn final_fragment(&mut self) -> io::Result<Option<(u64, usize)>> {
+        let size = self.inner.seek(SeekFrom::End(0))?;
+        if size == 0 {
+            self.len = Some(0);
+            return Ok(None);
+        }
+        let fragment_len = (self.buf_size + A::TAG_LEN) as u64;
+        let index = (size - 1) / fragment_len;
+        let len = (size - index * fragment_len) as usize;
+        if len < A::TAG_LEN {
+            return Err(NotAuthentic.into());
+        }
+        self.read_fragment(index, len, true)?;
+
+        let len = len - A::TAG_LEN;
+        self.len = Some(index * self.buf_size as u64 + len as u64);
+        Ok(Some((index, len)))
+    }
+
+    /// Reads the fragment with the given `index` and length `len` from
+    /// the storage and decrypts and verifies it.
+    fn read_fragment(&mut self, index: u64, len: usize, last: bool) -> io::Result<()> {
+        self.fragment = None;
+        let fragment_len = (self.buf_size + A::TAG_LEN) as u64;
+        self.inner.seek(SeekFrom::Start(index * fragment_len))?;
+        self.inner.read_exact(&mut self.buffer[..len])?;
+
+        let mut framing = self.framing.clone();
+        framing.seek(index)?;
+        let (nonce, aad) = framing.next(&mut self.algorithm, last)?;
+        self.algorithm
+            .open_in_place(nonce, aad, &mut self.buffer[..len])?;
+        self.fragment = Some(index);
+        Ok(())
+    }
+}
+
+impl<A: Algorithm, S: Read + Write + Seek> Read for EncryptedFile<A, S> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let size = self.len()?;
+        if self.pos >= size || buf.is_empty() {
+            return Ok(0);
+        }
+
+        let buf_size = self.buf_size as u64;
+        let index = self.pos / buf_size;
+        let last = (size - 1) / buf_size;
+        if self.fragment != Some(index) {
+            if index == last {
+                self.final_fragment()?;
+            } else {
+                self.read_fragment(index, self.buf_size + A::TAG_LEN, false)?;
+            }
+        }
+        let end = if index == last {
+            (size - index * buf_size) as usize
+        } else {
+            self.buf_size
+        };
+        let offset = (self.pos - index * buf_size) as usize;
+        let n = buf.len().min(end - offset);
+        buf[..n].copy_from_slice(&self.buffer[offset..offset + n]);
+        self.pos += n as u64;
+        Ok(n)
+    }
+}
+
+impl<A: Algorithm, S: Read + Write + Seek> Seek for EncryptedFile<A, S> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let pos = match pos {
+            SeekFrom::Start(pos) => Some(pos),
+            SeekFrom::End(offset) => self.len()?.checked_add_signed(offset),
+            SeekFrom::Current(offset) => self.pos.checked_add_signed(offset),
+        };
+        match pos {
+            Some(pos) => {
+                self.pos = pos;
+                Ok(pos)
+            }
+            None => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "invalid seek to a negative or overflowing position",
+            )),
+        }
+    }
+}
@@ -0,0 +1,543 @@
+// Copyright (c) 2019 Andreas Auernhammer. All rights reserved.
+// Use of this source code is governed by a license that can be
+// found in the LICENSE file.
+
+use super::aead::Counter;
+use super::hook;
+use super::oneshot::MAX_TAG_LEN;
+use super::utils;
+use super::writer::internal;
+use super::{Aad, Algorithm, Invalid, Key, Nonce, NotAuthentic, MAX_BUF_SIZE};
+use std::io::{self, Write};
+use std::marker::PhantomData;
+use std::thread::panicking;
+
+/// Returns the `Counter` and the associated data of the `Sio` framing.
+///
+/// It computes them like the `Sio` framing does but without allocating.
+fn init<A: Algorithm>(
+    algorithm: &A,
+    nonce: Nonce<A>,
+    aad: Aad<A>,
+) -> Result<(Counter, [u8; 1 + MAX_TAG_LEN]), Invalid> {
+    let mut counter = Counter::zero(nonce.as_ref());
+    let mut associated_data = [0; 1 + MAX_TAG_LEN];
+    algorithm.seal_in_place(
+        counter.next().map_err(|_| Invalid::Nonce)?,
+        aad.as_ref(),
+        &mut associated_data[1..1 + A::TAG_LEN],
+    )?;
+    Ok((counter, associated_data))
+}
+
+/// Checks the fragment size `N` of a `FixedEncWriter` or `FixedDecWriter`.
+/// Evaluating `CHECK` fails to compile if `N` is not larger than the tag
+/// or exceeds `MAX_BUF_SIZE` plus the tag.
+struct FragmentLen<A: Algorithm, const N: usize>(PhantomData<A>);
+
+impl<A: Algorithm, const N: usize> FragmentLen<A, N> {
+    const CHECK: () = assert!(
+        N > A::TAG_LEN && N - A::TAG_LEN <= MAX_BUF_SIZE && A::TAG_LEN <= MAX_TAG_LEN,
+        "invalid fragment size"
+    );
+}
+
+/// Wraps a writer and encrypts and authenticates everything written to it
+/// without allocating.
+///
+/// A `HeaplessEncWriter` is an `EncWriter` with the default `Sio` framing
+/// for environments without a heap - e.g. firmware or kernels. It does not
+/// allocate at all. Instead, it encrypts every fragment in place within the
+/// buffer `B`. The buffer holds one encrypted fragment - i.e. `buf_size`
+/// bytes of plaintext followed by the tag. It is either an array that is
+/// part of the writer itself - see `FixedEncWriter` - or a scratch buffer
+/// provided by the caller - see `with_buffer`.
+///
+/// The ciphertext is exactly the one of an `EncWriter` with the same buffer
+/// size. Therefore, it can be decrypted by a `HeaplessDecWriter` with a
+/// buffer of the same length as well as by any `DecWriter` or `DecReader`.
+///
+/// # Examples
+///
+/// Encrypting with a scratch buffer on the stack:
+///
+/// ```
+/// use std::io::Write;
+/// use sio::{Key, Nonce, Aad, HeaplessEncWriter, HeaplessDecWriter, CHACHA20_POLY1305};
+///
+/// // Obviously, don't use this all-zeros key for anything real.
+/// let key: Key<CHACHA20_POLY1305> = Key::new([0; Key::<CHACHA20_POLY1305>::SIZE]);
+///
+/// let mut scratch = [0; 256];
+/// let mut ciphertext: Vec<u8> = Vec::default();
+/// let mut writer = HeaplessEncWriter::with_buffer(
+///     &mut ciphertext,
+///     &key,
+///     Nonce::new([0; Nonce::<CHACHA20_POLY1305>::SIZE]),
+///     Aad::empty(),
+///     &mut scratch,
+/// )
+/// .unwrap();
+/// assert_eq!(writer.buf_size(), 256 - 16);
+/// writer.write_all(b"Hello World").unwrap();
+/// writer.close().unwrap();
+///
+/// let mut plaintext: Vec<u8> = Vec::default();
+/// let mut writer = HeaplessDecWriter::with_buffer(
+///     &mut plaintext,
+///     &key,
+///     Nonce::new([0; Nonce::<CHACHA20_POLY1305>::SIZE]),
+///     Aad::empty(),
+///     &mut scratch,
+/// )
+/// .unwrap();
+/// writer.write_all(&ciphertext).unwrap();
+/// writer.close().unwrap();
+/// assert_eq!(plaintext, b"Hello World");
+/// ```
+pub struct HeaplessEncWriter<A: Algorithm, W: Write + internal::Close, B: AsRef<[u8]> + AsMut<[u8]>>
+{
+    inner: W,
+    algorithm: A,
+    counter: Counter,
+    associated_data: [u8; 1 + MAX_TAG_LEN],
+    buffer: B,
+    pos: usize,
+    errored: bool,
+    closed: bool,
+}
+
+/// A `HeaplessEncWriter` with a fragment buffer of a fixed size.
+///
+/// Its fragment buffer is an array of `N` bytes that is part of the writer
+/// itself. Hence, a `FixedEncWriter` on the stack does not allocate at all
+/// and the fragment boundaries are known at compile time.
+///
+/// `N` is the size of an encrypted fragment: A fragment holds `BUF_SIZE`
+/// bytes of plaintext - i.e. `N - A::TAG_LEN` bytes. The ciphertext is
+/// exactly the one of an `EncWriter` with this buffer size. Therefore,
+/// it can be decrypted by a `FixedDecWriter` with the same `N` as well
+/// as by any `DecWriter` or `DecReader` with a buffer size of `BUF_SIZE`.
+/// An `N` that is not larger than the tag or exceeds `MAX_BUF_SIZE` plus
+/// the tag is rejected at compile time.
+///
+/// # Examples
+///
+/// ```
+/// use std::io::Write;
+/// use sio::{Key, Nonce, Aad, FixedEncWriter, DecWriter, CHACHA20_POLY1305};
+///
+/// // Obviously, don't use this all-zeros key for anything real.
+/// let key: Key<CHACHA20_POLY1305> = Key::new([0; Key::<CHACHA20_POLY1305>::SIZE]);
+///
+/// // Fragments of 1 KiB plaintext plus the 16 byte tag.
+/// let mut ciphertext: Vec<u8> = Vec::default();
+/// let mut writer = FixedEncWriter::<_, _, { 1024 + 16 }>::new(
+///     &mut ciphertext,
+///     &key,
+///     Nonce::new([0; Nonce::<CHACHA20_POLY1305>::SIZE]),
+///     Aad::empty(),
+/// );
+/// writer.write_all(b"Hello World").unwrap();
+/// writer.close().unwrap();
+///
+/// let mut plaintext: Vec<u8> = Vec::default();
+/// let mut writer = DecWriter::with_buffer_size(
+///     &mut plaintext,
+///     &key,
+///     Nonce::new([0; Nonce::<CHACHA20_POLY1305>::SIZE]),
+///     Aad::empty(),
+///     1024,
+/// )
+/// .unwrap();
+/// writer.write_all(&ciphertext).unwrap();
+/// writer.close().unwrap();
+/// assert_eq!(plaintext, b"Hello World");
+/// ```
+pub type FixedEncWriter<A, W, const N: usize> = HeaplessEncWriter<A, W, [u8; N]>;
+
+impl<A: Algorithm, W: Write + internal::Close, const N: usize> HeaplessEncWriter<A, W, [u8; N]> {
+    /// The number of plaintext bytes per fragment.
+    pub const BUF_SIZE: usize = N - A::TAG_LEN;
+
+    /// Creates a new `FixedEncWriter` that encrypts and authenticates
+    /// everything written to it using the provided `key` and `nonce`.
+    /// The `aad` is only authenticated and neither encrypted nor written
+    /// to the `inner` writer.
+    pub fn new(inner: W, key: &Key<A>, nonce: Nonce<A>, aad: Aad<A>) -> Self {
+        #[allow(clippy::let_unit_value)]
+        let () = FragmentLen::<A, N>::CHECK;
+        Self::with_storage(inner, key, nonce, aad, [0; N]).unwrap()
+    }
+}
+
+impl<'a, A: Algorithm, W: Write + internal::Close> HeaplessEncWriter<A, W, &'a mut [u8]> {
+    /// Creates a new `HeaplessEncWriter` that encrypts every fragment in
+    /// the caller-provided `buffer`. The `buffer` holds one encrypted
+    /// fragment. Hence, it must be larger than `A::TAG_LEN` and must not
+    /// be larger than `MAX_BUF_SIZE + A::TAG_LEN`.
+    ///
+    /// The `buffer` may contain plaintext once the writer is dropped.
+    pub fn with_buffer(
+        inner: W,
+        key: &Key<A>,
+        nonce: Nonce<A>,
+        aad: Aad<A>,
+        buffer: &'a mut [u8],
+    ) -> Result<Self, Invalid> {
+        Self::with_storage(inner, key, nonce, aad, buffer)
+    }
+}
+
+impl<A: Algorithm, W: Write + internal::Close, B: AsRef<[u8]> + AsMut<[u8]>>
+    HeaplessEncWriter<A, W, B>
+{
+    fn with_storage(
+        inner: W,
+        key: &Key<A>,
+        nonce: Nonce<A>,
+        aad: Aad<A>,
+        buffer: B,
+    ) -> Result<Self, Invalid> {
+        let len = buffer.as_ref().len();
+        if A::TAG_LEN > MAX_TAG_LEN || len <= A::TAG_LEN || len - A::TAG_LEN > MAX_BUF_SIZE {
+            return Err(Invalid::BufSize);
+        }
+        let algorithm = A::new(key.as_ref());
+        let (counter, associated_data) = init(&algorithm, nonce, aad)?;
+        Ok(HeaplessEncWriter {
+            inner,
+            algorithm,
+            counter,
+            associated_data,
+            buffer,
+            pos: 0,
+            errored: false,
+            closed: false,
+        })
+    }
+
+    /// Returns the number of plaintext bytes per fragment.
+    #[inline]
+    pub fn buf_size(&self) -> usize {
+        self.buffer.as_ref().len() - A::TAG_LEN
+    }
+
+    #[must_use = "A HeaplessEncWriter must be closed to successfully complete the encryption process. Ignoring this result may cause incomplete ciphertext data."]
+    #[inline(always)]
+    pub fn close(mut self) -> io::Result<()> {
+        internal::Close::close(&mut self)
+    }
+
+    /// Encrypts the buffered plaintext as one fragment and writes it to
+    /// the inner writer.
+    fn seal(&mut self, last: bool) -> io::Result<()> {
+        if last {
+            self.associated_data[0] = 0x80;
+        }
+        let inner = &mut self.inner;
+        let r = match self.counter.next() {
+            Ok(nonce) => self
+                .algorithm
+                .seal_in_place(
+                    nonce,
+                    &self.associated_data[..1 + A::TAG_LEN],
+                    &mut self.buffer.as_mut()[..self.pos + A::TAG_LEN],
+                )
+                .map_err(io::Error::from)
+                .and_then(|ciphertext| {
+                    utils::write_all(inner, ciphertext, format_args!("fragment"))
+                }),
+            Err(err) => Err(err.into()),
+        };
+        self.errored = r.is_err();
+        self.pos = 0;
+        r
+    }
+}
+
+impl<A: Algorithm, W: Write + internal::Close, B: AsRef<[u8]> + AsMut<[u8]>> Write
+    for HeaplessEncWriter<A, W, B>
+{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.errored {
+            return Err(io::Error::from(io::ErrorKind::Other));
+        }
+        let buf_size = self.buf_size();
+        let mut remaining = buf;
+        while !remaining.is_empty() {
+            // A full buffer is only sealed once more plaintext follows
+            // since the final fragment must be sealed differently.
+            if self.pos == buf_size {
+                self.seal(false)?;
+            }
+            let n = remaining.len().min(buf_size - self.pos);
+            self.buffer.as_mut()[self.pos..self.pos + n].copy_from_slice(&remaining[..n]);
+            self.pos += n;
+            remaining = &remaining[n..];
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if self.errored {
+            return Err(io::Error::from(io::ErrorKind::Other));
+        }
+        let r = self.inner.flush();
+        self.errored = r.is_err();
+        r
+    }
+}
+
+impl<A: Algorithm, W: Write + internal::Close, B: AsRef<[u8]> + AsMut<[u8]>> internal::Close
+    for HeaplessEncWriter<A, W, B>
+{
+    fn close(&mut self) -> io::Result<()> {
+        if self.errored {
+            return Err(io::Error::from(io::ErrorKind::Other));
+        }
+        self.closed = true;
+        self.seal(true)?;
+        self.inner.close()
+    }
+}
+
+impl<A: Algorithm, W: Write + internal::Close, B: AsRef<[u8]> + AsMut<[u8]>> Drop
+    for HeaplessEncWriter<A, W, B>
+{
+    fn drop(&mut self) {
+        // See: EncWriter::drop
+        if !self.errored
+            && !self.closed
+            && !panicking()
+            && !hook::unclosed::<Self>()
+            && !cfg!(feature = "panic-free")
+        {
+            panic!("HeaplessEncWriter must be closed explicitly via the close method before being dropped!")
+        }
+    }
+}
+
+/// Wraps a writer and decrypts and verifies everything written to it
+/// without allocating.
+///
+/// A `HeaplessDecWriter` is the counterpart of the `HeaplessEncWriter`.
+/// It decrypts every fragment in place within the buffer `B`, which holds
+/// one encrypted fragment. It decrypts the ciphertext of a
+/// `HeaplessEncWriter` with a buffer of the same length - or of an
+/// `EncWriter` with the default `Sio` framing and the same buffer size.
+/// See `HeaplessEncWriter` for an example.
+pub struct HeaplessDecWriter<A: Algorithm, W: Write + internal::Close, B: AsRef<[u8]> + AsMut<[u8]>>
+{
+    inner: W,
+    algorithm: A,
+    counter: Counter,
+    associated_data: [u8; 1 + MAX_TAG_LEN],
+    buffer: B,
+    pos: usize,
+    errored: bool,
+    closed: bool,
+}
+
+/// A `HeaplessDecWriter` with a fragment buffer of a fixed size.
+///
+/// A `FixedDecWriter` is the counterpart of the `FixedEncWriter`. It
+/// decrypts the ciphertext of a `FixedEncWriter` with the same `N` - or
+/// of an `EncWriter` with the default `Sio` framing and a buffer size of
+/// `BUF_SIZE` - without allocating.
+///
+/// # Examples
+///
+/// ```
+/// use std::io::Write;
+/// use sio::{Key, Nonce, Aad, NopCloser, FixedEncWriter, FixedDecWriter, CHACHA20_POLY1305};
+///
+/// // Obviously, don't use this all-zeros key for anything real.
+/// let key: Key<CHACHA20_POLY1305> = Key::new([0; Key::<CHACHA20_POLY1305>::SIZE]);
+///
+/// let mut ciphertext: Vec<u8> = Vec::default();
+/// let mut writer = FixedEncWriter::<_, _, 32>::new(
+///     &mut ciphertext,
+///     &key,
+///     Nonce::new([0; Nonce::<CHACHA20_POLY1305>::SIZE]),
+///     Aad::empty(),
+/// );
+/// writer.write_all(b"Hello World").unwrap();
+/// writer.close().unwrap();
+///
+/// let mut plaintext = [0; 11];
+/// let mut writer = FixedDecWriter::<_, _, 32>::new(
+///     NopCloser::wrap(&mut plaintext[..]),
+///     &key,
+///     Nonce::new([0; Nonce::<CHACHA20_POLY1305>::SIZE]),
+///     Aad::empty(),
+/// );
+/// writer.write_all(&ciphertext).unwrap();
+/// writer.close().unwrap();
+/// assert_eq!(&plaintext, b"Hello World");
+/// ```
+pub type FixedDecWriter<A, W, const N: usize> = HeaplessDecWriter<A, W, [u8; N]>;
+
+impl<A: Algorithm, W: Write + internal::Close, const N: usize> HeaplessDecWriter<A, W, [u8; N]> {
+    /// The number of plaintext bytes per fragment.
+    pub const BUF_SIZE: usize = N - A::TAG_LEN;
+
+    /// Creates a new `FixedDecWriter` that decrypts and verifies everything
+    /// written to it using the provided `key` and `nonce`. The `aad` must
+    /// match the one used for encryption.
+    pub fn new(inner: W, key: &Key<A>, nonce: Nonce<A>, aad: Aad<A>) -> Self {
+        #[allow(clippy::let_unit_value)]
+        let () = FragmentLen::<A, N>::CHECK;
+        Self::with_storage(inner, key, nonce, aad, [0; N]).unwrap()
+    }
+}
+
+impl<'a, A: Algorithm, W: Write + internal::Close> HeaplessDecWriter<A, W, &'a mut [u8]> {
+    /// Creates a new `HeaplessDecWriter` that decrypts every fragment in
+    /// the caller-provided `buffer`. The `buffer` must have the same length
+    /// as the one used for encryption.
+    ///
+    /// The `buffer` may contain plaintext once the writer is dropped.
+    pub fn with_buffer(
+        inner: W,
+        key: &Key<A>,
+        nonce: Nonce<A>,
+        aad: Aad<A>,
+        buffer: &'a mut [u8],
+    ) -> Result<Self, Invalid> {
+        Self::with_storage(inner, key, nonce, aad, buffer)
+    }
+}
+
+impl<A: Algorithm, W: Write + internal::Close, B: AsRef<[u8]> + AsMut<[u8]>>
+    HeaplessDecWriter<A, W, B>
+{
+    fn with_storage(
+        inner: W,
+        key: &Key<A>,
+        nonce: Nonce<A>,
+        aad: Aad<A>,
+        buffer: B,
+    ) -> Result<Self, Invalid> {
+        let len = buffer.as_ref().len();
+        if A::TAG_LEN > MAX_TAG_LEN || len <= A::TAG_LEN || len - A::TAG_LEN > MAX_BUF_SIZE {
+            return Err(Invalid::BufSize);
+        }
+        let algorithm = A::new(key.as_ref());
+        let (counter, associated_data) = init(&algorithm, nonce, aad)?;
+        Ok(HeaplessDecWriter {
+            inner,
+            algorithm,
+            counter,
+            associated_data,
+            buffer,
+            pos: 0,
+            errored: false,
+            closed: false,
+        })
+    }
+
+    /// Returns the number of plaintext bytes per fragment.
+    #[inline]
+    pub fn buf_size(&self) -> usize {
+        self.buffer.as_ref().len() - A::TAG_LEN
+    }
+
+    #[must_use = "A HeaplessDecWriter must be closed to successfully complete the decryption process. Ignoring this result may cause incomplete plaintext data."]
+    #[inline(always)]
+    pub fn close(mut self) -> io::Result<()> {
+        internal::Close::close(&mut self)
+    }
+
+    /// Decrypts and verifies the buffered ciphertext as one fragment and
+    /// writes the plaintext to the inner writer.
+    fn open(&mut self, last: bool) -> io::Result<()> {
+        if self.pos < A::TAG_LEN {
+            self.errored = true;
+            return Err(NotAuthentic.into());
+        }
+        if last {
+            self.associated_data[0] = 0x80;
+        }
+        let inner = &mut self.inner;
+        let r = match self.counter.next() {
+            Ok(nonce) => self
+                .algorithm
+                .open_in_place(
+                    nonce,
+                    &self.associated_data[..1 + A::TAG_LEN],
+                    &mut self.buffer.as_mut()[..self.pos],
+                )
+                .map_err(io::Error::from)
+                .and_then(|plaintext| {
+                    utils::write_all(inner, plaintext, format_args!("plaintext"))
+                }),
+            Err(err) => Err(err.into()),
+        };
+        self.errored = r.is_err();
+        self.pos = 0;
+        r
+    }
+}
+
+impl<A: Algorithm, W: Write + internal::Close, B: AsRef<[u8]> + AsMut<[u8]>> Write
+    for HeaplessDecWriter<A, W, B>
+{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.errored {
+            return Err(io::Error::from(io::ErrorKind::Other));
+        }
+        let len = self.buffer.as_ref().len();
+        let mut remaining = buf;
+        while !remaining.is_empty() {
+            // A full buffer is only opened once more ciphertext follows
+            // since the final fragment must be opened differently.
+            if self.pos == len {
+                self.open(false)?;
+            }
+            let n = remaining.len().min(len - self.pos);
+            self.buffer.as_mut()[self.pos..self.pos + n].copy_from_slice(&remaining[..n]);
+            self.pos += n;
+            remaining = &remaining[n..];
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if self.errored {
+            return Err(io::Error::from(io::ErrorKind::Other));
+        }
+        let r = self.inner.flush();
+        self.errored = r.is_err();
+        r
+    }
+}
+
+impl<A: Algorithm, W: Write + internal::Close, B: AsRef<[u8]> + AsMut<[u8]>> internal::Close
+    for HeaplessDecWriter<A, W, B>
+{
+    fn close(&mut self) -> io::Result<()> {
+        if self.errored {
+            return Err(io::Error::from(io::ErrorKind::Other));
+        }
+        self.closed = true;
+        self.open(true)?;
+        self.inner.close()
+    }
+}
+
+impl<A: Algorithm, W: Write + internal::Close, B: AsRef<[u8]> + AsMut<[u8]>> Drop
+    for HeaplessDecWriter<A, W, B>
+{
+    fn drop(&mut self) {
+        // See: DecWriter::drop
+        if !self.errored
+            && !self.closed
+            && !panicking()
+            && !hook::unclosed::<Self>()
+            && !cfg!(feature = "panic-free")
+        {
+            panic!("HeaplessDecWriter must be closed explicitly via the close method before being dropped!")
+        }
+    }
+}
@@ -0,0 +1,1276 @@
+// Copyright (c) 2019 Andreas Auernhammer. All rights reserved.
+// Use of this source code is governed by a license that can be
+// found in the LICENSE file.
+
+//! Fragment framings supported by `EncWriter`, `DecWriter` and `DecReader`.
+//!
+//! A framing defines how the nonce and the associated data of each
+//! fragment are computed, how the last fragment of a stream is
+//! marked and which header and trailer surround the fragments. Data
+//! encrypted with one framing can only be decrypted with the same
+//! framing.
+//!
+//! - `Sio` is the default channel construction of this crate. It prefixes
+//!   the associated data of the last fragment with `0x80` (instead of `0x00`).
+//! - `Stream` is the STREAM construction by Hoang, Reyhanitabar, Rogaway
+//!   and Vizár. It marks the last fragment with a flag byte in the nonce.
+//! - `DerivedKeys` derives a fresh key for every fragment from the secret
+//!   key and the fragment index instead of incrementing a nonce counter.
+//! - `V1` is the `Sio` framing preceded by a header that identifies the
+//!   format version of the stream.
+//! - `Auto` detects whether a stream is a raw `Sio` stream or a `V1`
+//!   stream. It allows consumers to decrypt the data of producers that
+//!   have not been upgraded yet.
+//! - `FragmentAad` wraps another framing and binds every fragment to
+//!   additional associated data computed from the fragment index.
+//! - `Delimited` wraps another framing and records the plaintext length
+//!   of the stream in its header.
+//! - `FragmentSize` wraps another framing and records the fragment size
+//!   of the stream in its header.
+//!
+//! Applications with an existing on-disk format can implement the `Framing`
+//! trait to plug their layout into the writers and readers of this crate.
+
+use crate::aead::{Algorithm, Counter, NonceLayout};
+use crate::error::{Exceeded, Invalid, UnsupportedVersion};
+use crate::MAX_NONCE_LEN;
+use std::convert::TryFrom;
+
+/// A trait implemented by all fragment framings.
+///
+/// A framing controls the layout of an encrypted stream: The nonce and
+/// associated data of each fragment, how the final fragment is marked and
+/// the header and trailer that precede and follow the fragments.
+///
+/// # Examples
+///
+/// A framing that surrounds the fragments of the default `Sio` framing
+/// with a magic header and trailer:
+///
+/// ```
+/// use std::io::Write;
+/// use sio::{Key, Nonce, Aad, EncWriter, DecWriter, Algorithm, Exceeded, Invalid, BUF_SIZE, CHACHA20_POLY1305};
+/// use sio::framing::{Framing, Sio};
+///
+/// #[derive(Clone, Default)]
+/// struct Magic(Sio);
+///
+/// impl Framing for Magic {
+///     fn init<A: Algorithm>(
+///         &mut self,
+///         key: &[u8; 32],
+///         algorithm: &A,
+///         nonce: &[u8],
+///         aad: &[u8],
+///     ) -> Result<(), Invalid> {
+///         self.0.init(key, algorithm, nonce, aad)
+///     }
+///
+///     fn next<A: Algorithm>(
+///         &mut self,
+///         algorithm: &mut A,
+///         last: bool,
+///     ) -> Result<(&[u8], &[u8]), Exceeded> {
+///         self.0.next(algorithm, last)
+///     }
+///
+///     fn remaining(&self) -> u64 {
+///         self.0.remaining()
+///     }
+///
+///     fn header(&self) -> &[u8] {
+///         b"MAGIC"
+///     }
+///
+///     fn trailer_len(&self) -> usize {
+///         3
+///     }
+///
+///     fn trailer(&self) -> &[u8] {
+///         b"END"
+///     }
+/// }
+///
+/// // Obviously, don't use this all-zeros key for anything real.
+/// let key: Key<CHACHA20_POLY1305> = Key::new([0; Key::<CHACHA20_POLY1305>::SIZE]);
+///
+/// let mut ciphertext: Vec<u8> = Vec::default();
+/// let mut writer = EncWriter::with_framing(
+///     &mut ciphertext,
+///     &key,
+///     Nonce::new([0; Nonce::<CHACHA20_POLY1305>::SIZE]),
+///     Aad::empty(),
+///     BUF_SIZE,
+///     Magic::default(),
+/// )
+/// .unwrap();
+/// writer.write_all(b"Hello World").unwrap();
+/// writer.close().unwrap();
+///
+/// assert!(ciphertext.starts_with(b"MAGIC"));
+/// assert!(ciphertext.ends_with(b"END"));
+///
+/// let mut plaintext: Vec<u8> = Vec::default();
+/// let mut writer = DecWriter::with_framing(
+///     &mut plaintext,
+///     &key,
+///     Nonce::new([0; Nonce::<CHACHA20_POLY1305>::SIZE]),
+///     Aad::empty(),
+///     BUF_SIZE,
+///     Magic::default(),
+/// )
+/// .unwrap();
+/// writer.write_all(ciphertext.as_slice()).unwrap();
+/// writer.close().unwrap();
+///
+/// assert_eq!(plaintext.as_slice(), b"Hello World");
+/// ```
+pub trait Framing: Clone {
+    /// Prepares the framing for a new stream using the secret `key`,
+    /// the `nonce` and the associated data provided by the caller. The
+    /// `algorithm` has been created from the secret `key`. The `nonce`
+    /// is `A::NONCE_LEN - 4` bytes long.
+    fn init<A: Algorithm>(
+        &mut self,
+        key: &[u8; 32],
+        algorithm: &A,
+        nonce: &[u8],
+        aad: &[u8],
+    ) -> Result<(), Invalid>;
+
+    /// Returns the nonce and the associated data for the next fragment.
+    /// The `last` flag indicates whether the next fragment is the final
+    /// fragment of the stream. The nonce must be `A::NONCE_LEN` bytes long.
+    ///
+    /// A framing may replace the `algorithm` to en/decrypt the next fragment
+    /// with a different key.
+    fn next<A: Algorithm>(
+        &mut self,
+        algorithm: &mut A,
+        last: bool,
+    ) -> Result<(&[u8], &[u8]), Exceeded>;
+
+    /// Returns the number of fragments that can still be processed
+    /// before `next` fails with an `Exceeded` error.
+    fn remaining(&self) -> u64;
+
+    /// Returns whether consecutive non-final fragments can be processed
+    /// as a batch - i.e. whether `next` never replaces the `algorithm`
+    /// and returns the same associated data for all non-final fragments.
+    ///
+    /// Only then, several fragments are en/decrypted with one call of
+    /// `Algorithm::seal_batch` or `Algorithm::open_batch`. By default,
+    /// it returns `false`.
+    fn batchable(&self) -> bool {
+        false
+    }
+
+    /// Returns the header that precedes the first fragment.
+    ///
+    /// The header must only depend on the parameters passed to `init`
+    /// such that the decrypting party can verify it. By default, there
+    /// is no header.
+    fn header(&self) -> &[u8] {
+        &[]
+    }
+
+    /// Returns the length of the trailer that follows the final fragment.
+    /// By default, there is no trailer.
+    fn trailer_len(&self) -> usize {
+        0
+    }
+
+    /// Returns the trailer that follows the final fragment.
+    ///
+    /// It is requested once `next` has returned the nonce and associated
+    /// data of the final fragment and must be exactly `trailer_len` bytes
+    /// long.
+    fn trailer(&self) -> &[u8] {
+        &[]
+    }
+
+    /// Returns the number of bytes at the beginning of a stream that
+    /// the framing inspects - using `detect` - before decrypting it.
+    /// By default, no bytes are inspected.
+    fn detect_len(&self) -> usize {
+        0
+    }
+
+    /// Detects the layout of a stream - e.g. its format version - from
+    /// its first `detect_len` bytes, or fewer if the stream is shorter.
+    ///
+    /// It is called by the decrypting party after `init` and before the
+    /// header is verified. The bytes passed to `detect` are not consumed.
+    /// They are processed as part of the header and the fragments as usual.
+    /// The `trailer_len` must not change.
+    fn detect(&mut self, _prefix: &[u8]) -> Result<(), UnsupportedVersion> {
+        Ok(())
+    }
+
+    /// Returns the number of plaintext bytes of all fragments of the
+    /// stream if the framing records it - e.g. `Delimited`.
+    ///
+    /// The decrypting party requests it after `detect` to locate the
+    /// final fragment before decrypting it. By default, the length is
+    /// not recorded and the final fragment ends where the stream ends.
+    fn stream_len(&self) -> Option<u64> {
+        None
+    }
+
+    /// Returns the fragment size - i.e. the number of plaintext bytes of
+    /// every fragment but the final one - if the framing records it -
+    /// e.g. `FragmentSize`.
+    ///
+    /// The decrypting party requests it after `detect` and uses it instead
+    /// of the buffer size it has been created with. The encrypting party
+    /// must use the same buffer size. By default, the fragment size is not
+    /// recorded.
+    fn buf_size(&self) -> Option<usize> {
+        None
+    }
+}
+
+/// The default channel construction of this crate.
+///
+/// The nonce of each fragment consists of the `Nonce` followed by a
+/// 4 byte little-endian sequence number. Sequence number `0` is used to
+/// authenticate the associated data. The resulting tag, prefixed with the
+/// `0x00` byte - or the `0x80` byte for the final fragment - is used as
+/// associated data for each fragment.
+///
+/// Formats that split the nonce differently into prefix and sequence
+/// number - e.g. a 4 byte prefix followed by an 8 byte big-endian counter -
+/// can use `Sio::with_nonce_layout`. Data encrypted with one `NonceLayout`
+/// can only be decrypted with the same layout.
+///
+/// # Examples
+///
+/// ```
+/// use std::io::Write;
+/// use sio::{Key, Nonce, Aad, EncWriter, NonceLayout, BUF_SIZE, CHACHA20_POLY1305};
+/// use sio::framing::Sio;
+///
+/// // Obviously, don't use this all-zeros key for anything real.
+/// let key: Key<CHACHA20_POLY1305> = Key::new([0; Key::<CHACHA20_POLY1305>::SIZE]);
+///
+/// // The last 4 bytes of the nonce must be zero for a 4 byte prefix.
+/// let mut ciphertext: Vec<u8> = Vec::default();
+/// let mut writer = EncWriter::with_framing(
+///     &mut ciphertext,
+///     &key,
+///     Nonce::new([1, 2, 3, 4, 0, 0, 0, 0]),
+///     Aad::empty(),
+///     BUF_SIZE,
+///     Sio::with_nonce_layout(NonceLayout::big_endian(8).unwrap()),
+/// )
+/// .unwrap();
+/// writer.write_all(b"Hello World").unwrap();
+/// writer.close().unwrap();
+/// ```
+#[derive(Clone, Default)]
+pub struct Sio {
+    counter: Counter,
+    aad: Box<[u8]>,
+    layout: NonceLayout,
+}
+
+impl Sio {
+    /// Returns a `Sio` framing that derives the nonce of each fragment
+    /// using the given `layout` instead of the default one.
+    pub fn with_nonce_layout(layout: NonceLayout) -> Self {
+        Sio {
+            layout,
+            ..Sio::default()
+        }
+    }
+
+    /// Returns the layout of the fragment nonces.
+    #[inline]
+    pub fn nonce_layout(&self) -> NonceLayout {
+        self.layout
+    }
+
+    /// Positions the framing such that the next call of `next` returns
+    /// the nonce and associated data of the fragment with the given index.
+    pub(crate) fn seek(&mut self, index: u64) -> Result<(), Exceeded> {
+        // Sequence number 0 is used to authenticate the associated data.
+        if index >= u64::from(u32::MAX) {
+            return Err(Exceeded::default());
+        }
+        self.counter.seek(index as u32 + 1);
+        self.aad[0] = 0x00;
+        Ok(())
+    }
+}
+
+impl Framing for Sio {
+    fn init<A: Algorithm>(
+        &mut self,
+        _key: &[u8; 32],
+        algorithm: &A,
+        nonce: &[u8],
+        aad: &[u8],
+    ) -> Result<(), Invalid> {
+        let mut counter = Counter::with_layout(nonce, 0, self.layout)?;
+        let mut associated_data = vec![0; 1 + A::TAG_LEN].into_boxed_slice();
+        algorithm.seal_in_place(
+            counter.next().map_err(|_| Invalid::Nonce)?,
+            aad,
+            &mut associated_data[1..],
+        )?;
+
+        self.counter = counter;
+        self.aad = associated_data;
+        Ok(())
+    }
+
+    #[inline]
+    fn next<A: Algorithm>(
+        &mut self,
+        _algorithm: &mut A,
+        last: bool,
+    ) -> Result<(&[u8], &[u8]), Exceeded> {
+        if last {
+            self.aad[0] = 0x80; // For the last fragment change the AAD
+        }
+        Ok((self.counter.next()?, &self.aad))
+    }
+
+    #[inline]
+    fn remaining(&self) -> u64 {
+        self.counter.remaining()
+    }
+
+    #[inline]
+    fn batchable(&self) -> bool {
+        true
+    }
+}
+
+/// The STREAM construction as defined by Hoang, Reyhanitabar, Rogaway and
+/// Vizár in "Online Authenticated-Encryption and its Nonce-Reuse Misuse-Resistance".
+///
+/// The nonce of each fragment consists of a prefix, a 4 byte big-endian
+/// counter and a 1 byte flag which is `1` for the final fragment and `0` otherwise.
+/// The associated data is passed to every fragment as it is.
+///
+/// The prefix is taken from all but the last byte of the `Nonce` - i.e. it is
+/// 7 bytes long for algorithms with 96 bit nonces. The last byte of the `Nonce`
+/// must be `0`.
+#[derive(Clone, Default)]
+pub struct Stream {
+    nonce: [u8; MAX_NONCE_LEN],
+    len: usize,
+    aad: Vec<u8>,
+    counter: u32,
+    exceeded: bool,
+}
+
+impl Framing for Stream {
+    fn init<A: Algorithm>(
+        &mut self,
+        _key: &[u8; 32],
+        _algorithm: &A,
+        nonce: &[u8],
+        aad: &[u8],
+    ) -> Result<(), Invalid> {
+        let len = nonce.len() + 4;
+        if len > MAX_NONCE_LEN || nonce.last() != Some(&0) {
+            return Err(Invalid::Nonce);
+        }
+        let prefix = &nonce[..nonce.len() - 1];
+        self.nonce = [0; MAX_NONCE_LEN];
+        self.nonce[..prefix.len()].copy_from_slice(prefix);
+        self.len = len;
+        self.aad = aad.to_vec();
+        self.counter = 0;
+        self.exceeded = false;
+        Ok(())
+    }
+
+    #[inline]
+    fn next<A: Algorithm>(
+        &mut self,
+        _algorithm: &mut A,
+        last: bool,
+    ) -> Result<(&[u8], &[u8]), Exceeded> {
+        if self.exceeded {
+            return Err(Exceeded::default());
+        }
+
+        let len = self.len;
+        self.nonce[len - 5..len - 1].copy_from_slice(&self.counter.to_be_bytes());
+        self.nonce[len - 1] = last as u8;
+        if let Some(counter) = self.counter.checked_add(1) {
+            self.counter = counter;
+        } else {
+            self.exceeded = true;
+        }
+        Ok((&self.nonce[..len], &self.aad))
+    }
+
+    #[inline]
+    fn remaining(&self) -> u64 {
+        if self.exceeded {
+            0
+        } else {
+            (1 << 32) - u64::from(self.counter)
+        }
+    }
+
+    #[inline]
+    fn batchable(&self) -> bool {
+        true
+    }
+}
+
+/// A framing that derives a fresh key for every fragment.
+///
+/// The key of each fragment is derived from the secret key, the nonce and
+/// the 64 bit fragment index using HKDF-SHA256. The final fragment of a
+/// stream uses a separate key derived with a final-fragment flag. Since
+/// every key is used to encrypt exactly one fragment, the nonce passed to
+/// the algorithm is always zero and the associated data is passed to every
+/// fragment as it is.
+///
+/// In contrast to the `Sio` and `Stream` framings, a stream is not limited
+/// to 2<sup>32</sup> fragments. However, deriving a key for every fragment
+/// is more expensive than incrementing a nonce counter.
+#[cfg(feature = "ring")]
+#[derive(Clone, Default)]
+pub struct DerivedKeys {
+    prk: [u8; 32],
+    aad: Vec<u8>,
+    index: u64,
+    exceeded: bool,
+}
+
+#[cfg(feature = "ring")]
+impl DerivedKeys {
+    const INFO: &'static [u8] = b"sio fragment key";
+    const NONCE: [u8; MAX_NONCE_LEN] = [0; MAX_NONCE_LEN];
+}
+
+#[cfg(feature = "ring")]
+impl Framing for DerivedKeys {
+    fn init<A: Algorithm>(
+        &mut self,
+        key: &[u8; 32],
+        _algorithm: &A,
+        nonce: &[u8],
+        aad: &[u8],
+    ) -> Result<(), Invalid> {
+        use ring::{digest, hmac};
+
+        // HKDF-Extract(salt = nonce, IKM = key)
+        let salt = hmac::SigningKey::new(&digest::SHA256, nonce);
+        self.prk.copy_from_slice(hmac::sign(&salt, key).as_ref());
+        self.aad = aad.to_vec();
+        self.index = 0;
+        self.exceeded = false;
+        Ok(())
+    }
+
+    fn next<A: Algorithm>(
+        &mut self,
+        algorithm: &mut A,
+        last: bool,
+    ) -> Result<(&[u8], &[u8]), Exceeded> {
+        use ring::{digest, hkdf, hmac};
+
+        if self.exceeded {
+            return Err(Exceeded::default());
+        }
+
+        let mut info = [0; 16 + 8 + 1];
+        info[..16].copy_from_slice(Self::INFO);
+        info[16..24].copy_from_slice(&self.index.to_be_bytes());
+        info[24] = last as u8;
+
+        let mut key = [0; 32];
+        let prk = hmac::SigningKey::new(&digest::SHA256, &self.prk);
+        hkdf::expand(&prk, &info, &mut key);
+        *algorithm = A::new(&key);
+
+        if let Some(index) = self.index.checked_add(1) {
+            self.index = index;
+        } else {
+            self.exceeded = true;
+        }
+        Ok((&Self::NONCE[..A::NONCE_LEN], &self.aad))
+    }
+
+    #[inline]
+    fn remaining(&self) -> u64 {
+        if self.exceeded {
+            0
+        } else {
+            // The actual capacity of 2^64 - index fragments
+            // may not be representable as u64.
+            (u64::MAX - self.index).saturating_add(1)
+        }
+    }
+}
+
+/// The format versions of encrypted streams.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Version {
+    /// The raw fragments of the `Sio` framing without any header.
+    Legacy,
+
+    /// The fragments of the `Sio` framing preceded by the `V1` header.
+    V1,
+}
+
+impl Version {
+    /// The magic bytes at the beginning of every versioned stream. They
+    /// are followed by one byte that contains the format version.
+    pub const MAGIC: [u8; 7] = *b"\x89SIO\r\n\x1a";
+
+    /// Returns the header that identifies the format version.
+    pub fn header(self) -> &'static [u8] {
+        match self {
+            Version::Legacy => &[],
+            Version::V1 => b"\x89SIO\r\n\x1a\x01",
+        }
+    }
+}
+
+/// The `Sio` framing preceded by a header that identifies the format
+/// version.
+///
+/// The header consists of the `Version::MAGIC` bytes followed by the
+/// version byte `0x01`. It is authenticated as part of the associated
+/// data. Therefore, a `V1` stream cannot be turned into a valid raw `Sio`
+/// stream by stripping its header.
+#[derive(Clone, Default)]
+pub struct V1(Sio);
+
+impl Framing for V1 {
+    fn init<A: Algorithm>(
+        &mut self,
+        key: &[u8; 32],
+        algorithm: &A,
+        nonce: &[u8],
+        aad: &[u8],
+    ) -> Result<(), Invalid> {
+        let aad = [Version::V1.header(), aad].concat();
+        self.0.init(key, algorithm, nonce, &aad)
+    }
+
+    #[inline]
+    fn next<A: Algorithm>(
+        &mut self,
+        algorithm: &mut A,
+        last: bool,
+    ) -> Result<(&[u8], &[u8]), Exceeded> {
+        self.0.next(algorithm, last)
+    }
+
+    #[inline]
+    fn remaining(&self) -> u64 {
+        self.0.remaining()
+    }
+
+    #[inline]
+    fn batchable(&self) -> bool {
+        true
+    }
+
+    #[inline]
+    fn header(&self) -> &[u8] {
+        Version::V1.header()
+    }
+}
+
+/// A framing that detects the format version of a stream.
+///
+/// A stream that starts with the `Version::MAGIC` bytes is decrypted
+/// according to the version byte following them. Any other stream is
+/// treated as raw `Sio` stream. Streams of unknown - e.g. future -
+/// versions are rejected with an `UnsupportedVersion` error.
+///
+/// By default, all versions are accepted. Strict deployments can restrict
+/// the accepted versions using `Auto::allow`. When used for encryption,
+/// `Auto` produces streams of the newest allowed version.
+///
+/// # Examples
+///
+/// ```
+/// use std::io::Write;
+/// use sio::{Key, Nonce, Aad, EncWriter, DecWriter, BUF_SIZE, CHACHA20_POLY1305};
+/// use sio::framing::{Auto, Version};
+///
+/// // Obviously, don't use this all-zeros key for anything real.
+/// let key: Key<CHACHA20_POLY1305> = Key::new([0; Key::<CHACHA20_POLY1305>::SIZE]);
+///
+/// // A producer that has not been upgraded yet.
+/// let mut ciphertext: Vec<u8> = Vec::default();
+/// let mut writer = EncWriter::new(
+///     &mut ciphertext,
+///     &key,
+///     Nonce::new([0; Nonce::<CHACHA20_POLY1305>::SIZE]),
+///     Aad::empty(),
+/// );
+/// writer.write_all(b"Hello World").unwrap();
+/// writer.close().unwrap();
+///
+/// let mut plaintext: Vec<u8> = Vec::default();
+/// let mut writer = DecWriter::with_framing(
+///     &mut plaintext,
+///     &key,
+///     Nonce::new([0; Nonce::<CHACHA20_POLY1305>::SIZE]),
+///     Aad::empty(),
+///     BUF_SIZE,
+///     Auto::default(),
+/// )
+/// .unwrap();
+/// writer.write_all(ciphertext.as_slice()).unwrap();
+/// assert_eq!(writer.framing().version(), Version::Legacy);
+/// writer.close().unwrap();
+///
+/// assert_eq!(plaintext.as_slice(), b"Hello World");
+/// ```
+#[derive(Clone)]
+pub struct Auto {
+    allowed: Vec<Version>,
+    version: Version,
+    legacy: Sio,
+    v1: V1,
+}
+
+impl Auto {
+    /// Returns a new `Auto` framing that only accepts streams
+    /// of the given format `versions`.
+    pub fn allow(versions: &[Version]) -> Self {
+        let version = if versions.contains(&Version::V1) {
+            Version::V1
+        } else {
+            Version::Legacy
+        };
+        Auto {
+            allowed: versions.to_vec(),
+            version,
+            legacy: Sio::default(),
+            v1: V1::default(),
+        }
+    }
+
+    /// Returns the format version of the stream. Before the
+    /// version has been detected, it is the newest allowed one.
+    #[inline]
+    pub fn version(&self) -> Version {
+        self.version
+    }
+}
+
+impl Default for Auto {
+    fn default() -> Self {
+        Self::allow(&[Version::Legacy, Version::V1])
+    }
+}
+
+impl Framing for Auto {
+    fn init<A: Algorithm>(
+        &mut self,
+        key: &[u8; 32],
+        algorithm: &A,
+        nonce: &[u8],
+        aad: &[u8],
+    ) -> Result<(), Invalid> {
+        self.legacy.init(key, algorithm, nonce, aad)?;
+        self.v1.init(key, algorithm, nonce, aad)
+    }
+
+    #[inline]
+    fn next<A: Algorithm>(
+        &mut self,
+        algorithm: &mut A,
+        last: bool,
+    ) -> Result<(&[u8], &[u8]), Exceeded> {
+        match self.version {
+            Version::Legacy => self.legacy.next(algorithm, last),
+            Version::V1 => self.v1.next(algorithm, last),
+        }
+    }
+
+    #[inline]
+    fn remaining(&self) -> u64 {
+        match self.version {
+            Version::Legacy => self.legacy.remaining(),
+            Version::V1 => self.v1.remaining(),
+        }
+    }
+
+    #[inline]
+    fn batchable(&self) -> bool {
+        true
+    }
+
+    #[inline]
+    fn header(&self) -> &[u8] {
+        self.version.header()
+    }
+
+    #[inline]
+    fn detect_len(&self) -> usize {
+        Version::MAGIC.len() + 1
+    }
+
+    fn detect(&mut self, prefix: &[u8]) -> Result<(), UnsupportedVersion> {
+        let magic = Version::MAGIC.len();
+        let version = if prefix.len() > magic && prefix[..magic] == Version::MAGIC {
+            match prefix[magic] {
+                0x01 => Version::V1,
+                _ => return Err(UnsupportedVersion),
+            }
+        } else {
+            Version::Legacy
+        };
+        if !self.allowed.contains(&version) {
+            return Err(UnsupportedVersion);
+        }
+        self.version = version;
+        Ok(())
+    }
+}
+
+/// Searches `ciphertext` for a valid final fragment which is at most
+/// `max_len` bytes long by trying to decrypt its prefixes - the shortest
+/// first. A final fragment must be followed by the trailer of the
+/// `framing`. The `framing` must be in the state before the fragment gets
+/// processed.
+///
+/// Since the search stops at the first valid final fragment, its cost
+/// grows quadratically with the length of the final fragment - not with
+/// `max_len`. The prefixes are decrypted in the `buffer`. If a final
+/// fragment is found, its length is returned and the `buffer` contains
+/// the plaintext.
+pub(crate) fn find_final_fragment<A: Algorithm, F: Framing>(
+    algorithm: &mut A,
+    framing: &F,
+    ciphertext: &[u8],
+    buffer: &mut [u8],
+    max_len: usize,
+) -> Option<usize> {
+    let trailer_len = framing.trailer_len();
+    for n in A::TAG_LEN..=max_len {
+        let mut framing = framing.clone();
+        let (nonce, aad) = framing.next(algorithm, true).ok()?;
+        buffer[..n].copy_from_slice(&ciphertext[..n]);
+        if algorithm
+            .open_in_place(nonce, aad, &mut buffer[..n])
+            .is_ok()
+            && ciphertext.get(n..n + trailer_len) == Some(framing.trailer())
+        {
+            return Some(n);
+        }
+    }
+    None
+}
+
+/// A framing that binds every fragment to additional associated data
+/// returned by a callback.
+///
+/// The callback is called with the index of each fragment - starting at
+/// `0` - and returns data that is appended to the associated data the
+/// `inner` framing computes for this fragment. Hence, each fragment can
+/// be bound to out-of-band metadata - e.g. an entry of an external
+/// manifest. The data is not written to the stream. The decrypting party
+/// has to use a callback that returns the same data for every fragment.
+///
+/// The data is preceded by its length in 4 byte big-endian encoding such
+/// that different splits of the same bytes - e.g. `("ab", "c")` and
+/// `("a", "bc")` - produce different associated data. Therefore, the
+/// stream differs from a stream of the `inner` framing even if the
+/// callback returns no data. Sealing or opening a fragment fails with
+/// `Exceeded` if the callback returns 4 GiB or more.
+///
+/// # Examples
+///
+/// ```
+/// use std::io::Write;
+/// use sio::{Key, Nonce, Aad, EncWriter, DecWriter, CHACHA20_POLY1305};
+/// use sio::framing::{FragmentAad, Sio};
+///
+/// // Obviously, don't use this all-zeros key for anything real.
+/// let key: Key<CHACHA20_POLY1305> = Key::new([0; Key::<CHACHA20_POLY1305>::SIZE]);
+/// let manifest = ["part-a", "part-b", "part-c"];
+/// let framing = FragmentAad::new(Sio::default(), |index: u32| manifest[index as usize]);
+///
+/// let mut ciphertext: Vec<u8> = Vec::default();
+/// let mut writer = EncWriter::with_framing(
+///     &mut ciphertext,
+///     &key,
+///     Nonce::new([0; Nonce::<CHACHA20_POLY1305>::SIZE]),
+///     Aad::empty(),
+///     4,
+///     framing.clone(),
+/// )
+/// .unwrap();
+/// writer.write_all(b"Hello World").unwrap();
+/// writer.close().unwrap();
+///
+/// let mut plaintext: Vec<u8> = Vec::default();
+/// let mut writer = DecWriter::with_framing(
+///     &mut plaintext,
+///     &key,
+///     Nonce::new([0; Nonce::<CHACHA20_POLY1305>::SIZE]),
+///     Aad::empty(),
+///     4,
+///     framing,
+/// )
+/// .unwrap();
+/// writer.write_all(ciphertext.as_slice()).unwrap();
+/// writer.close().unwrap();
+///
+/// assert_eq!(plaintext.as_slice(), b"Hello World");
+/// ```
+#[derive(Clone)]
+pub struct FragmentAad<F, C> {
+    inner: F,
+    callback: C,
+    aad: Vec<u8>,
+    index: u32,
+    exceeded: bool,
+}
+
+impl<F: Framing, C> FragmentAad<F, C> {
+    /// Returns a framing that appends the data returned by `callback`
+    /// to the associated data of each fragment of the `inner` framing.
+    pub fn new(inner: F, callback: C) -> Self {
+        FragmentAad {
+            inner,
+            callback,
+            aad: Vec::default(),
+            index: 0,
+            exceeded: false,
+        }
+    }
+
+    /// Returns the wrapped framing.
+    #[inline]
+    pub fn inner(&self) -> &F {
+        &self.inner
+    }
+}
+
+impl<F, C, T> Framing for FragmentAad<F, C>
+where
+    F: Framing,
+    C: FnMut(u32) -> T + Clone,
+    T: AsRef<[u8]>,
+{
+    fn init<A: Algorithm>(
+        &mut self,
+        key: &[u8; 32],
+        algorithm: &A,
+        nonce: &[u8],
+        aad: &[u8],
+    ) -> Result<(), Invalid> {
+        self.index = 0;
+        self.exceeded = false;
+        self.inner.init(key, algorithm, nonce, aad)
+    }
+
+    fn next<A: Algorithm>(
+        &mut self,
+        algorithm: &mut A,
+        last: bool,
+    ) -> Result<(&[u8], &[u8]), Exceeded> {
+        if self.exceeded {
+            return Err(Exceeded::default());
+        }
+        let (nonce, aad) = self.inner.next(algorithm, last)?;
+        let data = (self.callback)(self.index);
+        let data = data.as_ref();
+        let len = u32::try_from(data.len()).map_err(|_| Exceeded::default())?;
+        self.aad.clear();
+        self.aad.extend_from_slice(aad);
+        self.aad.extend_from_slice(&len.to_be_bytes());
+        self.aad.extend_from_slice(data);
+
+        if let Some(index) = self.index.checked_add(1) {
+            self.index = index;
+        } else {
+            self.exceeded = true;
+        }
+        Ok((nonce, &self.aad))
+    }
+
+    #[inline]
+    fn remaining(&self) -> u64 {
+        if self.exceeded {
+            0
+        } else {
+            let remaining = (1 << 32) - u64::from(self.index);
+            self.inner.remaining().min(remaining)
+        }
+    }
+
+    #[inline]
+    fn header(&self) -> &[u8] {
+        self.inner.header()
+    }
+
+    #[inline]
+    fn trailer_len(&self) -> usize {
+        self.inner.trailer_len()
+    }
+
+    #[inline]
+    fn trailer(&self) -> &[u8] {
+        self.inner.trailer()
+    }
+
+    #[inline]
+    fn detect_len(&self) -> usize {
+        self.inner.detect_len()
+    }
+
+    #[inline]
+    fn detect(&mut self, prefix: &[u8]) -> Result<(), UnsupportedVersion> {
+        self.inner.detect(prefix)
+    }
+
+    #[inline]
+    fn stream_len(&self) -> Option<u64> {
+        self.inner.stream_len()
+    }
+
+    #[inline]
+    fn buf_size(&self) -> Option<usize> {
+        self.inner.buf_size()
+    }
+}
+
+/// A framing that records the plaintext length of the stream in its
+/// header.
+///
+/// The header consists of the total number of plaintext bytes of all
+/// fragments - encoded as 8 byte big-endian integer - followed by the
+/// header of the `inner` framing. The length is authenticated as part
+/// of the associated data of every fragment. Hence, the decrypting party
+/// knows where the final fragment ends before decrypting it - e.g. to
+/// detect trailing data in strict mode.
+///
+/// The encrypting party has to know the length of the stream up front.
+/// An `EncWriter` fails with a `LengthMismatch` error as soon as more
+/// bytes are written and on close if fewer bytes have been written. The
+/// decrypting party reads the length from the stream and can use
+/// `Delimited::default()`. A `DecReader` never reads beyond the recorded
+/// end of the stream and fails with a `LengthMismatch` error if the
+/// stream is truncated.
+///
+/// # Examples
+///
+/// ```
+/// use std::io::{self, Write};
+/// use sio::{Key, Nonce, Aad, EncWriter, DecWriter, BUF_SIZE, CHACHA20_POLY1305};
+/// use sio::framing::{Delimited, Sio};
+///
+/// // Obviously, don't use this all-zeros key for anything real.
+/// let key: Key<CHACHA20_POLY1305> = Key::new([0; Key::<CHACHA20_POLY1305>::SIZE]);
+///
+/// let mut ciphertext: Vec<u8> = Vec::default();
+/// let mut writer = EncWriter::with_framing(
+///     &mut ciphertext,
+///     &key,
+///     Nonce::new([0; Nonce::<CHACHA20_POLY1305>::SIZE]),
+///     Aad::empty(),
+///     BUF_SIZE,
+///     Delimited::new(Sio::default(), 11),
+/// )
+/// .unwrap();
+/// writer.write_all(b"Hello World").unwrap();
+/// writer.close().unwrap();
+///
+/// ciphertext.extend_from_slice(b"some trailing data");
+///
+/// let mut writer = DecWriter::with_framing(
+///     io::sink(),
+///     &key,
+///     Nonce::new([0; Nonce::<CHACHA20_POLY1305>::SIZE]),
+///     Aad::empty(),
+///     BUF_SIZE,
+///     Delimited::<Sio>::default(),
+/// )
+/// .unwrap()
+/// .strict();
+///
+/// let err = writer.write_all(ciphertext.as_slice()).unwrap_err();
+/// assert_eq!(err.to_string(), "unexpected data after the final fragment");
+/// ```
+#[derive(Clone, Default)]
+pub struct Delimited<F> {
+    inner: F,
+    len: u64,
+    header: Vec<u8>,
+    aad: Vec<u8>,
+}
+
+impl<F: Framing> Delimited<F> {
+    /// Returns a framing that records the plaintext length `len` of
+    /// the stream and surrounds the fragments of the `inner` framing.
+    pub fn new(inner: F, len: u64) -> Self {
+        Delimited {
+            inner,
+            len,
+            header: Vec::default(),
+            aad: Vec::default(),
+        }
+    }
+
+    /// Returns the wrapped framing.
+    #[inline]
+    pub fn inner(&self) -> &F {
+        &self.inner
+    }
+
+    fn set_header(&mut self) {
+        self.header.clear();
+        self.header.extend_from_slice(&self.len.to_be_bytes());
+        self.header.extend_from_slice(self.inner.header());
+    }
+}
+
+impl<F: Framing> Framing for Delimited<F> {
+    fn init<A: Algorithm>(
+        &mut self,
+        key: &[u8; 32],
+        algorithm: &A,
+        nonce: &[u8],
+        aad: &[u8],
+    ) -> Result<(), Invalid> {
+        self.inner.init(key, algorithm, nonce, aad)?;
+        self.set_header();
+        Ok(())
+    }
+
+    fn next<A: Algorithm>(
+        &mut self,
+        algorithm: &mut A,
+        last: bool,
+    ) -> Result<(&[u8], &[u8]), Exceeded> {
+        let (nonce, aad) = self.inner.next(algorithm, last)?;
+        self.aad.clear();
+        self.aad.extend_from_slice(aad);
+        self.aad.extend_from_slice(&self.len.to_be_bytes());
+        Ok((nonce, &self.aad))
+    }
+
+    #[inline]
+    fn remaining(&self) -> u64 {
+        self.inner.remaining()
+    }
+
+    #[inline]
+    fn batchable(&self) -> bool {
+        self.inner.batchable()
+    }
+
+    #[inline]
+    fn header(&self) -> &[u8] {
+        &self.header
+    }
+
+    #[inline]
+    fn trailer_len(&self) -> usize {
+        self.inner.trailer_len()
+    }
+
+    #[inline]
+    fn trailer(&self) -> &[u8] {
+        self.inner.trailer()
+    }
+
+    #[inline]
+    fn detect_len(&self) -> usize {
+        8 + self.inner.detect_len()
+    }
+
+    fn detect(&mut self, prefix: &[u8]) -> Result<(), UnsupportedVersion> {
+        // A stream shorter than the length is not authentic. It is
+        // rejected once the header gets verified.
+        let n = prefix.len().min(8);
+        let mut len = [0; 8];
+        len[..n].copy_from_slice(&prefix[..n]);
+        self.len = u64::from_be_bytes(len);
+        self.inner.detect(&prefix[n..])?;
+        self.set_header();
+        Ok(())
+    }
+
+    #[inline]
+    fn stream_len(&self) -> Option<u64> {
+        Some(self.len)
+    }
+
+    #[inline]
+    fn buf_size(&self) -> Option<usize> {
+        self.inner.buf_size()
+    }
+}
+
+/// A framing that records the fragment size of the stream in its header.
+///
+/// The header consists of the fragment size - i.e. the buffer size used
+/// to encrypt the stream - encoded as 4 byte big-endian integer followed
+/// by the header of the `inner` framing. The fragment size is
+/// authenticated as part of the associated data of every fragment. Hence,
+/// the decrypting party does not have to know the buffer size up front.
+/// It reads the fragment size from the stream and can use
+/// `FragmentSize::default()` - e.g. via `DecWriter::new_auto`.
+///
+/// The encrypting party must use the same buffer size as recorded by the
+/// framing. Otherwise, creating the `EncWriter` fails with
+/// `Invalid::BufSize`.
+///
+/// # Examples
+///
+/// ```
+/// use std::io::Write;
+/// use sio::{Key, Nonce, Aad, EncWriter, DecWriter, CHACHA20_POLY1305};
+/// use sio::framing::{FragmentSize, Framing, Sio};
+///
+/// // Obviously, don't use this all-zeros key for anything real.
+/// let key: Key<CHACHA20_POLY1305> = Key::new([0; Key::<CHACHA20_POLY1305>::SIZE]);
+///
+/// let mut ciphertext: Vec<u8> = Vec::default();
+/// let mut writer = EncWriter::with_framing(
+///     &mut ciphertext,
+///     &key,
+///     Nonce::new([0; Nonce::<CHACHA20_POLY1305>::SIZE]),
+///     Aad::empty(),
+///     64 * 1024,
+///     FragmentSize::new(Sio::default(), 64 * 1024),
+/// )
+/// .unwrap();
+/// writer.write_all(&[0; 100 * 1024]).unwrap();
+/// writer.close().unwrap();
+///
+/// let mut plaintext: Vec<u8> = Vec::default();
+/// let mut writer = DecWriter::new_auto(
+///     &mut plaintext,
+///     &key,
+///     Nonce::new([0; Nonce::<CHACHA20_POLY1305>::SIZE]),
+///     Aad::empty(),
+/// );
+/// writer.write_all(ciphertext.as_slice()).unwrap();
+/// assert_eq!(writer.framing().buf_size(), Some(64 * 1024));
+/// writer.close().unwrap();
+///
+/// assert_eq!(plaintext, vec![0; 100 * 1024]);
+/// ```
+#[derive(Clone, Default)]
+pub struct FragmentSize<F> {
+    inner: F,
+    buf_size: u32,
+    header: Vec<u8>,
+    aad: Vec<u8>,
+}
+
+impl<F: Framing> FragmentSize<F> {
+    /// Returns a framing that records the fragment size `buf_size` of
+    /// the stream and surrounds the fragments of the `inner` framing.
+    ///
+    /// The `buf_size` must be the buffer size of the `EncWriter`.
+    pub fn new(inner: F, buf_size: usize) -> Self {
+        FragmentSize {
+            inner,
+            buf_size: u32::try_from(buf_size).unwrap_or(u32::MAX),
+            header: Vec::default(),
+            aad: Vec::default(),
+        }
+    }
+
+    /// Returns the wrapped framing.
+    #[inline]
+    pub fn inner(&self) -> &F {
+        &self.inner
+    }
+
+    fn set_header(&mut self) {
+        self.header.clear();
+        self.header.extend_from_slice(&self.buf_size.to_be_bytes());
+        self.header.extend_from_slice(self.inner.header());
+    }
+}
+
+impl<F: Framing> Framing for FragmentSize<F> {
+    fn init<A: Algorithm>(
+        &mut self,
+        key: &[u8; 32],
+        algorithm: &A,
+        nonce: &[u8],
+        aad: &[u8],
+    ) -> Result<(), Invalid> {
+        self.inner.init(key, algorithm, nonce, aad)?;
+        self.set_header();
+        Ok(())
+    }
+
+    fn next<A: Algorithm>(
+        &mut self,
+        algorithm: &mut A,
+        last: bool,
+    ) -> Result<(&[u8], &[u8]), Exceeded> {
+        let (nonce, aad) = self.inner.next(algorithm, last)?;
+        self.aad.clear();
+        self.aad.extend_from_slice(aad);
+        self.aad.extend_from_slice(&self.buf_size.to_be_bytes());
+        Ok((nonce, &self.aad))
+    }
+
+    #[inline]
+    fn remaining(&self) -> u64 {
+        self.inner.remaining()
+    }
+
+    #[inline]
+    fn batchable(&self) -> bool {
+        self.inner.batchable()
+    }
+
+    #[inline]
+    fn header(&self) -> &[u8] {
+        &self.header
+    }
+
+    #[inline]
+    fn trailer_len(&self) -> usize {
+        self.inner.trailer_len()
+    }
+
+    #[inline]
+    fn trailer(&self) -> &[u8] {
+        self.inner.trailer()
+    }
+
+    #[inline]
+    fn detect_len(&self) -> usize {
+        4 + self.inner.detect_len()
+    }
+
+    fn detect(&mut self, prefix: &[u8]) -> Result<(), UnsupportedVersion> {
+        // A stream shorter than the fragment size is not authentic. It
+        // is rejected once the header gets verified.
+        let n = prefix.len().min(4);
+        let mut buf_size = [0; 4];
+        buf_size[..n].copy_from_slice(&prefix[..n]);
+        self.buf_size = u32::from_be_bytes(buf_size);
+        self.inner.detect(&prefix[n..])?;
+        self.set_header();
+        Ok(())
+    }
+
+    #[inline]
+    fn stream_len(&self) -> Option<u64> {
+        self.inner.stream_len()
+    }
+
+    #[inline]
+    fn buf_size(&self) -> Option<usize> {
+        usize::try_from(self.buf_size).ok()
+    }
+}
@@ -0,0 +1,90 @@
+// Copyright (c) 2019 Andreas Auernhammer. All rights reserved.
+// Use of this source code is governed by a license that can be
+// found in the LICENSE file.
+
+use std::sync::RwLock;
+
+/// A hook registered via `set_unclosed_hook`.
+pub type UnclosedHook = Box<dyn Fn(&UnclosedWriter) + Send + Sync>;
+
+static HOOK: RwLock<Option<UnclosedHook>> = RwLock::new(None);
+
+/// Describes a writer that has been dropped without being closed.
+///
+/// It is passed to the hook registered via `set_unclosed_hook`.
+#[derive(Clone, Debug)]
+pub struct UnclosedWriter {
+    type_name: &'static str,
+}
+
+impl UnclosedWriter {
+    /// Returns the full type name of the writer - e.g.
+    /// `sio::writer::EncWriter<sio::CHACHA20_POLY1305, std::fs::File>`.
+    #[inline]
+    pub fn type_name(&self) -> &'static str {
+        self.type_name
+    }
+}
+
+/// Registers a process-wide hook that is called whenever an `EncWriter`,
+/// `DecWriter`, one of the heapless writers or one of the `cdc` writers is
+/// dropped without being closed. It replaces any previously registered hook.
+///
+/// By default, dropping a writer that has not been closed panics since the
+/// encryption or decryption has not been completed. A panic inside `drop`
+/// is hard to diagnose in a production service, though. Once a hook is
+/// registered, the writer calls the hook instead of panicking. The hook can
+/// log the incident - e.g. together with a backtrace that points to the
+/// offending call site - update a metric or abort the process.
+///
+/// The hook is not called if the writer failed before - e.g. due to an I/O
+/// error - or if the thread is already panicking.
+///
+/// # Examples
+///
+/// ```
+/// use std::backtrace::Backtrace;
+/// use sio::{Key, Nonce, Aad, EncWriter, CHACHA20_POLY1305};
+///
+/// sio::set_unclosed_hook(|writer| {
+///     eprintln!(
+///         "{} dropped without close at:\n{}",
+///         writer.type_name(),
+///         Backtrace::capture()
+///     );
+/// });
+///
+/// // Obviously, don't use this all-zeros key for anything real.
+/// let key: Key<CHACHA20_POLY1305> = Key::new([0; Key::<CHACHA20_POLY1305>::SIZE]);
+/// let writer = EncWriter::new(
+///     Vec::default(),
+///     &key,
+///     Nonce::new([0; Nonce::<CHACHA20_POLY1305>::SIZE]),
+///     Aad::empty(),
+/// );
+/// drop(writer); // Calls the hook instead of panicking.
+/// ```
+pub fn set_unclosed_hook<F: Fn(&UnclosedWriter) + Send + Sync + 'static>(hook: F) {
+    *HOOK.write().unwrap_or_else(|err| err.into_inner()) = Some(Box::new(hook));
+}
+
+/// Unregisters the hook registered via `set_unclosed_hook` and returns it.
+/// Writers that are dropped without being closed panic again afterwards.
+pub fn take_unclosed_hook() -> Option<UnclosedHook> {
+    HOOK.write().unwrap_or_else(|err| err.into_inner()).take()
+}
+
+/// Calls the registered hook - if any - for the writer of type `W` and
+/// returns whether a hook has been called.
+pub(crate) fn unclosed<W: ?Sized>() -> bool {
+    // A hook that panicked does not leave the lock in an inconsistent state.
+    match *HOOK.read().unwrap_or_else(|err| err.into_inner()) {
+        Some(ref hook) => {
+            hook(&UnclosedWriter {
+                type_name: std::any::type_name::<W>(),
+            });
+            true
+        }
+        None => false,
+    }
+}
@@ -0,0 +1,366 @@
+// Copyright (c) 2019 Andreas Auernhammer. All rights reserved.
+// Use of this source code is governed by a license that can be
+// found in the LICENSE file.
+
+//! Hybrid encryption of streams using HPKE ([RFC 9180](https://www.rfc-editor.org/rfc/rfc9180)).
+//!
+//! The stream key is established with the HPKE cipher suite
+//! `DHKEM(X25519, HKDF-SHA256)`, `HKDF-SHA256` and the export-only AEAD.
+//! The sender sets up an HPKE context for the recipient's public key and
+//! exports the stream key using the exporter context [`EXPORTER_CONTEXT`].
+//! The encapsulated key `enc` is written in front of the ciphertext.
+//!
+//! Hence, any RFC 9180 implementation can establish the same stream key
+//! given `enc`, the recipient's secret key and the `info` - and decrypt
+//! the stream using the `sio` channel construction with a zero nonce.
+//!
+//! The base mode (`seal` / `open`) keeps the sender anonymous while the
+//! auth mode (`seal_auth` / `open_auth`) additionally authenticates the
+//! sender's X25519 key. The recipient's side of the HPKE primitives is
+//! exposed by [`decap`] and [`Context`] - e.g. to check them against the
+//! test vectors of RFC 9180.
+//!
+//! # Examples
+//!
+//! ```
+//! use std::io::{Read, Write};
+//! use sio::{Aad, CHACHA20_POLY1305};
+//! use sio::hpke;
+//! use sio::x25519::SecretKey;
+//!
+//! let secret_key = SecretKey::generate().unwrap();
+//! let public_key = secret_key.public_key();
+//!
+//! let mut ciphertext: Vec<u8> = Vec::default();
+//! let mut writer = hpke::seal::<CHACHA20_POLY1305, _>(
+//!     &mut ciphertext,
+//!     &public_key,
+//!     b"my application",
+//!     Aad::empty(),
+//! )
+//! .unwrap();
+//! writer.write_all(b"Hello World").unwrap();
+//! writer.close().unwrap();
+//!
+//! let mut reader = hpke::open::<CHACHA20_POLY1305, _>(
+//!     ciphertext.as_slice(),
+//!     &secret_key,
+//!     b"my application",
+//!     Aad::empty(),
+//! )
+//! .unwrap();
+//! let mut plaintext = String::default();
+//! reader.read_to_string(&mut plaintext).unwrap();
+//! assert_eq!(plaintext, "Hello World");
+//! ```
+
+use super::writer::Close;
+use super::x25519::{PublicKey, SecretKey};
+use super::{Aad, Algorithm, DecReader, EncWriter, Invalid, Key, Nonce};
+use ring::{digest, hkdf, hmac};
+use std::io::{self, Read, Write};
+
+/// The size of the header - the encapsulated key `enc` - in bytes.
+pub const HEADER_LEN: usize = 32;
+
+/// The HPKE exporter context used to export the stream key.
+pub const EXPORTER_CONTEXT: &[u8] = b"sio stream key";
+
+/// The KEM identifier of `DHKEM(X25519, HKDF-SHA256)`.
+const KEM_ID: u16 = 0x0020;
+
+/// The KDF identifier of `HKDF-SHA256`.
+const KDF_ID: u16 = 0x0001;
+
+/// The AEAD identifier of the export-only AEAD.
+const AEAD_ID: u16 = 0xFFFF;
+
+const MODE_BASE: u8 = 0x00;
+const MODE_AUTH: u8 = 0x02;
+
+/// Returns an `EncWriter` that encrypts a stream for the `recipient`
+/// using the HPKE base mode.
+///
+/// It writes the header - the encapsulated key - to the `inner` writer
+/// immediately. The `info` must match the `info` used for decryption.
+pub fn seal<A: Algorithm, W: Write + Close>(
+    inner: W,
+    recipient: &PublicKey,
+    info: &[u8],
+    aad: Aad<A>,
+) -> io::Result<EncWriter<A, W>> {
+    let ephemeral = SecretKey::generate()?;
+    let enc = ephemeral.public_key();
+    let mut dh = [0; 32];
+    diffie_hellman(&mut dh, &ephemeral, recipient)?;
+    let shared_secret = kem_shared_secret(&dh, &[enc.as_ref(), recipient.as_ref()]);
+    let context = Context::key_schedule(MODE_BASE, &shared_secret, info);
+    seal_with(inner, &enc, &context, aad)
+}
+
+/// Returns an `EncWriter` that encrypts a stream from the `sender` for
+/// the `recipient` using the HPKE auth mode.
+///
+/// It writes the header - the encapsulated key - to the `inner` writer
+/// immediately. The `info` must match the `info` used for decryption.
+pub fn seal_auth<A: Algorithm, W: Write + Close>(
+    inner: W,
+    recipient: &PublicKey,
+    sender: &SecretKey,
+    info: &[u8],
+    aad: Aad<A>,
+) -> io::Result<EncWriter<A, W>> {
+    let ephemeral = SecretKey::generate()?;
+    let enc = ephemeral.public_key();
+    let mut dh = [0; 2 * 32];
+    diffie_hellman(&mut dh[..32], &ephemeral, recipient)?;
+    diffie_hellman(&mut dh[32..], sender, recipient)?;
+    let sender = sender.public_key();
+    let kem_context = [enc.as_ref(), recipient.as_ref(), sender.as_ref()];
+    let shared_secret = kem_shared_secret(&dh, &kem_context);
+    let context = Context::key_schedule(MODE_AUTH, &shared_secret, info);
+    seal_with(inner, &enc, &context, aad)
+}
+
+/// Returns a `DecReader` that decrypts a stream sent to the owner of the
+/// `secret_key` using the HPKE base mode.
+///
+/// It reads the header - the encapsulated key - from the `inner` reader
+/// immediately.
+pub fn open<A: Algorithm, R: Read>(
+    mut inner: R,
+    secret_key: &SecretKey,
+    info: &[u8],
+    aad: Aad<A>,
+) -> io::Result<DecReader<A, R>> {
+    let enc = read_header(&mut inner)?;
+    let context = Context::base(&enc, secret_key, info)?;
+    Ok(DecReader::new(
+        inner,
+        &context.stream_key(),
+        Nonce::zero(),
+        aad,
+    ))
+}
+
+/// Returns a `DecReader` that decrypts a stream sent by the owner of the
+/// `sender` key to the owner of the `secret_key` using the HPKE auth mode.
+///
+/// It reads the header - the encapsulated key - from the `inner` reader
+/// immediately.
+pub fn open_auth<A: Algorithm, R: Read>(
+    mut inner: R,
+    secret_key: &SecretKey,
+    sender: &PublicKey,
+    info: &[u8],
+    aad: Aad<A>,
+) -> io::Result<DecReader<A, R>> {
+    let enc = read_header(&mut inner)?;
+    let context = Context::auth(&enc, secret_key, sender, info)?;
+    Ok(DecReader::new(
+        inner,
+        &context.stream_key(),
+        Nonce::zero(),
+        aad,
+    ))
+}
+
+/// Returns the shared secret of the encapsulated key `enc` and the
+/// recipient's `secret_key`.
+///
+/// It implements `Decap` of the `DHKEM(X25519, HKDF-SHA256)` and fails
+/// with `Invalid::Key` if the X25519 shared secret is all-zero.
+pub fn decap(enc: &PublicKey, secret_key: &SecretKey) -> Result<[u8; 32], Invalid> {
+    let mut dh = [0; 32];
+    diffie_hellman(&mut dh, secret_key, enc)?;
+    let recipient = secret_key.public_key();
+    Ok(kem_shared_secret(&dh, &[enc.as_ref(), recipient.as_ref()]))
+}
+
+/// The recipient's HPKE context of the export-only AEAD.
+///
+/// The stream key of `open` and `open_auth` is the secret exported with
+/// the [`EXPORTER_CONTEXT`].
+pub struct Context {
+    exporter_secret: [u8; 32],
+}
+
+impl Context {
+    /// Sets up the context of the encapsulated key `enc` for the owner of
+    /// the `secret_key` using the HPKE base mode - i.e. `SetupBaseR`.
+    pub fn base(enc: &PublicKey, secret_key: &SecretKey, info: &[u8]) -> Result<Self, Invalid> {
+        let shared_secret = decap(enc, secret_key)?;
+        Ok(Self::key_schedule(MODE_BASE, &shared_secret, info))
+    }
+
+    /// Sets up the context of the encapsulated key `enc` sent by the owner
+    /// of the `sender` key to the owner of the `secret_key` using the HPKE
+    /// auth mode - i.e. `SetupAuthR`.
+    pub fn auth(
+        enc: &PublicKey,
+        secret_key: &SecretKey,
+        sender: &PublicKey,
+        info: &[u8],
+    ) -> Result<Self, Invalid> {
+        let mut dh = [0; 2 * 32];
+        diffie_hellman(&mut dh[..32], secret_key, enc)?;
+        diffie_hellman(&mut dh[32..], secret_key, sender)?;
+        let recipient = secret_key.public_key();
+        let kem_context = [enc.as_ref(), recipient.as_ref(), sender.as_ref()];
+        let shared_secret = kem_shared_secret(&dh, &kem_context);
+        Ok(Self::key_schedule(MODE_AUTH, &shared_secret, info))
+    }
+
+    /// Returns the exporter secret derived by the key schedule.
+    #[inline]
+    pub fn exporter_secret(&self) -> &[u8; 32] {
+        &self.exporter_secret
+    }
+
+    /// Writes the secret exported for the `exporter_context` to `out` -
+    /// i.e. `Export(exporter_context, out.len())`.
+    ///
+    /// # Panics
+    ///
+    /// It panics if `out` is longer than `255 * 32` bytes.
+    pub fn export(&self, exporter_context: &[u8], out: &mut [u8]) {
+        let suite_id = hpke_suite_id();
+        labeled_expand(
+            &suite_id,
+            &self.exporter_secret,
+            b"sec",
+            exporter_context,
+            out,
+        );
+    }
+
+    /// Implements the HPKE `KeySchedule` - without a PSK.
+    fn key_schedule(mode: u8, shared_secret: &[u8; 32], info: &[u8]) -> Self {
+        let suite_id = hpke_suite_id();
+        let psk_id_hash = labeled_extract(&suite_id, &[], b"psk_id_hash", &[]);
+        let info_hash = labeled_extract(&suite_id, &[], b"info_hash", &[info]);
+
+        let mut key_schedule_context = Vec::with_capacity(1 + 2 * 32);
+        key_schedule_context.push(mode);
+        key_schedule_context.extend_from_slice(&psk_id_hash);
+        key_schedule_context.extend_from_slice(&info_hash);
+
+        let secret = labeled_extract(&suite_id, shared_secret, b"secret", &[]);
+        let mut exporter_secret = [0; 32];
+        labeled_expand(
+            &suite_id,
+            &secret,
+            b"exp",
+            &key_schedule_context,
+            &mut exporter_secret,
+        );
+        Context { exporter_secret }
+    }
+
+    /// Returns the secret exported for the `EXPORTER_CONTEXT`.
+    fn stream_key<A: Algorithm>(&self) -> Key<A> {
+        let mut key = [0; 32];
+        self.export(EXPORTER_CONTEXT, &mut key);
+        Key::new(key)
+    }
+}
+
+fn seal_with<A: Algorithm, W: Write + Close>(
+    mut inner: W,
+    enc: &PublicKey,
+    context: &Context,
+    aad: Aad<A>,
+) -> io::Result<EncWriter<A, W>> {
+    inner.write_all(enc.as_ref())?;
+    Ok(EncWriter::new(
+        inner,
+        &context.stream_key(),
+        Nonce::zero(),
+        aad,
+    ))
+}
+
+fn read_header<R: Read>(inner: &mut R) -> io::Result<PublicKey> {
+    let mut enc = [0; HEADER_LEN];
+    inner.read_exact(&mut enc)?;
+    Ok(PublicKey::new(enc))
+}
+
+/// Writes the X25519 shared secret of `secret_key` and `public_key` to
+/// `out`. As required by RFC 9180, it rejects an all-zero shared secret.
+fn diffie_hellman(
+    out: &mut [u8],
+    secret_key: &SecretKey,
+    public_key: &PublicKey,
+) -> Result<(), Invalid> {
+    let shared = secret_key.diffie_hellman(public_key);
+    if !shared.was_contributory() {
+        return Err(Invalid::Key);
+    }
+    out.copy_from_slice(shared.as_bytes());
+    Ok(())
+}
+
+/// Implements `ExtractAndExpand` of the `DHKEM(X25519, HKDF-SHA256)`.
+fn kem_shared_secret(dh: &[u8], kem_context: &[&[u8; 32]]) -> [u8; 32] {
+    let suite_id = kem_suite_id();
+    let eae_prk = labeled_extract(&suite_id, &[], b"eae_prk", &[dh]);
+
+    let kem_context: Vec<u8> = kem_context.iter().flat_map(|k| k.iter()).cloned().collect();
+    let mut shared_secret = [0; 32];
+    labeled_expand(
+        &suite_id,
+        &eae_prk,
+        b"shared_secret",
+        &kem_context,
+        &mut shared_secret,
+    );
+    shared_secret
+}
+
+fn kem_suite_id() -> [u8; 5] {
+    let mut suite_id = [0; 5];
+    suite_id[..3].copy_from_slice(b"KEM");
+    suite_id[3..].copy_from_slice(&KEM_ID.to_be_bytes());
+    suite_id
+}
+
+fn hpke_suite_id() -> [u8; 10] {
+    let mut suite_id = [0; 10];
+    suite_id[..4].copy_from_slice(b"HPKE");
+    suite_id[4..6].copy_from_slice(&KEM_ID.to_be_bytes());
+    suite_id[6..8].copy_from_slice(&KDF_ID.to_be_bytes());
+    suite_id[8..].copy_from_slice(&AEAD_ID.to_be_bytes());
+    suite_id
+}
+
+/// Implements `LabeledExtract(salt, label, ikm)` where the `ikm` is
+/// the concatenation of all `ikm` slices.
+fn labeled_extract(suite_id: &[u8], salt: &[u8], label: &[u8], ikm: &[&[u8]]) -> [u8; 32] {
+    let salt = hmac::SigningKey::new(&digest::SHA256, salt);
+    let mut ctx = hmac::SigningContext::with_key(&salt);
+    ctx.update(b"HPKE-v1");
+    ctx.update(suite_id);
+    ctx.update(label);
+    for ikm in ikm {
+        ctx.update(ikm);
+    }
+    let mut prk = [0; 32];
+    prk.copy_from_slice(ctx.sign().as_ref());
+    prk
+}
+
+/// Implements `LabeledExpand(prk, label, info, out.len())`.
+fn labeled_expand(suite_id: &[u8], prk: &[u8; 32], label: &[u8], info: &[u8], out: &mut [u8]) {
+    let mut labeled_info = Vec::with_capacity(2 + 7 + suite_id.len() + label.len() + info.len());
+    labeled_info.extend_from_slice(&(out.len() as u16).to_be_bytes());
+    labeled_info.extend_from_slice(b"HPKE-v1");
+    labeled_info.extend_from_slice(suite_id);
+    labeled_info.extend_from_slice(label);
+    labeled_info.extend_from_slice(info);
+    hkdf::expand(
+        &hmac::SigningKey::new(&digest::SHA256, prk),
+        &labeled_info,
+        out,
+    );
+}
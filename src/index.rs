@@ -0,0 +1,340 @@
+// Copyright (c) 2019 Andreas Auernhammer. All rights reserved.
+// Use of this source code is governed by a license that can be
+// found in the LICENSE file.
+
+//! Sidecar indices of encrypted streams.
+//!
+//! An `IndexWriter` sits between an `EncWriter` and the writer that
+//! receives the ciphertext. While the ciphertext passes through, it emits
+//! a compact index - the sidecar - to a second writer. The sidecar lists
+//! the ciphertext offset, the length and a hash of the authentication tag
+//! of every fragment.
+//!
+//! Once loaded as `Index`, the sidecar maps any plaintext offset to the
+//! fragment containing it in constant time. This allows serving range
+//! requests over encrypted objects - e.g. in an object storage - by only
+//! fetching the required fragments.
+//!
+//! # Examples
+//!
+//! ```
+//! use std::io::Write;
+//! use sio::{Key, Nonce, Aad, EncWriter, CHACHA20_POLY1305};
+//! use sio::index::{Index, IndexWriter};
+//!
+//! // Obviously, don't use this all-zeros key for anything real.
+//! let key: Key<CHACHA20_POLY1305> = Key::new([0; Key::<CHACHA20_POLY1305>::SIZE]);
+//!
+//! let mut ciphertext: Vec<u8> = Vec::default();
+//! let mut sidecar: Vec<u8> = Vec::default();
+//! let mut writer = EncWriter::with_buffer_size(
+//!     IndexWriter::<CHACHA20_POLY1305, _, _>::new(&mut ciphertext, &mut sidecar, 1024),
+//!     &key,
+//!     Nonce::new([0; Nonce::<CHACHA20_POLY1305>::SIZE]),
+//!     Aad::empty(),
+//!     1024,
+//! )
+//! .unwrap();
+//! writer.write_all(&[0; 5000]).unwrap();
+//! writer.close().unwrap();
+//!
+//! let index = Index::read_from(sidecar.as_slice()).unwrap();
+//! assert_eq!(index.fragments(), 5);
+//!
+//! let entry = index.locate(4500).unwrap();
+//! assert_eq!(entry.index(), 4);
+//! assert_eq!(entry.offset(), 4 * (1024 + 16));
+//! assert_eq!(entry.len() as usize, ciphertext.len() - 4 * (1024 + 16));
+//! ```
+
+use super::framing::Framing;
+use super::writer::Close;
+use super::Algorithm;
+use std::convert::{TryFrom, TryInto};
+use std::io::{self, Read, Write};
+use std::marker::PhantomData;
+
+/// The size of one fragment entry within a sidecar.
+const ENTRY_SIZE: usize = 8 + 4 + 8;
+
+/// The size of the footer of a sidecar.
+const FOOTER_SIZE: usize = 8 + 4 + 4;
+
+/// Returns the first 8 bytes of the SHA-256 hash of the `tag`.
+fn tag_hash(tag: &[u8]) -> [u8; 8] {
+    use ring::digest;
+
+    let mut hash = [0; 8];
+    hash.copy_from_slice(&digest::digest(&digest::SHA256, tag).as_ref()[..8]);
+    hash
+}
+
+/// Wraps the writer receiving the ciphertext of an `EncWriter` and
+/// emits a sidecar index of the ciphertext stream to a second writer.
+///
+/// The sidecar consists of one 20 byte entry per fragment - containing
+/// the ciphertext offset, the ciphertext length and the first 8 bytes of
+/// the SHA-256 hash of the authentication tag - followed by a 16 byte
+/// footer containing the number of fragments, the buffer size and the
+/// tag size. All integers are encoded as big-endian.
+///
+/// The `IndexWriter` must use the same buffer size and algorithm as
+/// the `EncWriter`. The footer is written when the `IndexWriter` gets
+/// closed - i.e. when the `EncWriter` gets closed.
+pub struct IndexWriter<A: Algorithm, W: Write, I: Write> {
+    inner: W,
+    sidecar: I,
+    buf_size: usize,
+
+    // The number of header bytes that have not been written yet
+    // and the length of the trailer of the framing.
+    header: usize,
+    trailer_len: usize,
+
+    // The ciphertext offset of the current fragment and the number
+    // of its bytes written so far.
+    offset: u64,
+    pos: usize,
+
+    // The last bytes of the current fragment. They contain the tag
+    // of the final fragment and the trailer.
+    tail: Vec<u8>,
+
+    fragments: u64,
+    _algorithm: PhantomData<A>,
+}
+
+impl<A: Algorithm, W: Write, I: Write> IndexWriter<A, W, I> {
+    /// Creates a new `IndexWriter` for streams encrypted with the
+    /// default `Sio` framing and the given buffer size.
+    pub fn new(inner: W, sidecar: I, buf_size: usize) -> Self {
+        IndexWriter {
+            inner,
+            sidecar,
+            buf_size,
+            header: 0,
+            trailer_len: 0,
+            offset: 0,
+            pos: 0,
+            tail: Vec::with_capacity(A::TAG_LEN),
+            fragments: 0,
+            _algorithm: PhantomData,
+        }
+    }
+
+    /// Creates a new `IndexWriter` for streams encrypted with the given
+    /// `framing` and buffer size. The header and trailer of the `framing`
+    /// must not depend on the parameters passed to `Framing::init`.
+    pub fn with_framing<F: Framing>(inner: W, sidecar: I, buf_size: usize, framing: &F) -> Self {
+        let header = framing.header().len();
+        let trailer_len = framing.trailer_len();
+        IndexWriter {
+            header,
+            trailer_len,
+            offset: header as u64,
+            tail: Vec::with_capacity(A::TAG_LEN + trailer_len),
+            ..Self::new(inner, sidecar, buf_size)
+        }
+    }
+
+    /// Unwraps the `IndexWriter` and returns the inner writer
+    /// and the sidecar writer.
+    #[inline]
+    pub fn into_inner(self) -> (W, I) {
+        (self.inner, self.sidecar)
+    }
+
+    /// Records the ciphertext bytes in `buf` which have been written
+    /// to the inner writer.
+    fn record(&mut self, mut buf: &[u8]) -> io::Result<()> {
+        let n = self.header.min(buf.len());
+        self.header -= n;
+        buf = &buf[n..];
+
+        let fragment_len = self.buf_size + A::TAG_LEN;
+        let cap = A::TAG_LEN + self.trailer_len;
+        while !buf.is_empty() {
+            let n = buf.len().min(fragment_len - self.pos);
+            self.tail.extend_from_slice(&buf[..n]);
+            if self.tail.len() > cap {
+                self.tail.drain(..self.tail.len() - cap);
+            }
+            self.pos += n;
+            buf = &buf[n..];
+
+            if self.pos == fragment_len {
+                self.write_entry(fragment_len, 0)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes the entry of the current fragment of `len` bytes whose tag
+    /// is followed by `trailer` more bytes in the tail.
+    fn write_entry(&mut self, len: usize, trailer: usize) -> io::Result<()> {
+        let tag = &self.tail[self.tail.len() - trailer - A::TAG_LEN..self.tail.len() - trailer];
+        let mut entry = [0; ENTRY_SIZE];
+        entry[..8].copy_from_slice(&self.offset.to_be_bytes());
+        entry[8..12].copy_from_slice(&(len as u32).to_be_bytes());
+        entry[12..].copy_from_slice(&tag_hash(tag));
+        self.sidecar.write_all(&entry)?;
+
+        self.offset += len as u64;
+        self.fragments += 1;
+        self.pos = 0;
+        self.tail.clear();
+        Ok(())
+    }
+}
+
+impl<A: Algorithm, W: Write, I: Write> Write for IndexWriter<A, W, I> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.record(&buf[..n])?;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush().and_then(|()| self.sidecar.flush())
+    }
+}
+
+impl<A: Algorithm, W: Write + Close, I: Write> Close for IndexWriter<A, W, I> {
+    fn close(&mut self) -> io::Result<()> {
+        // If the final fragment is a full fragment, its entry has already
+        // been written and only the trailer remains.
+        if self.pos > self.trailer_len {
+            self.write_entry(self.pos - self.trailer_len, self.trailer_len)?;
+        }
+
+        let mut footer = [0; FOOTER_SIZE];
+        footer[..8].copy_from_slice(&self.fragments.to_be_bytes());
+        footer[8..12].copy_from_slice(&(self.buf_size as u32).to_be_bytes());
+        footer[12..].copy_from_slice(&(A::TAG_LEN as u32).to_be_bytes());
+        self.sidecar.write_all(&footer)?;
+        self.sidecar.flush()?;
+        self.inner.close()
+    }
+}
+
+/// A fragment entry of an `Index`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Entry {
+    index: u64,
+    offset: u64,
+    len: u32,
+    tag_hash: [u8; 8],
+}
+
+impl Entry {
+    /// Returns the index of the fragment within the stream.
+    #[inline]
+    pub fn index(&self) -> u64 {
+        self.index
+    }
+
+    /// Returns the offset of the fragment within the ciphertext stream.
+    #[inline]
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Returns the length of the fragment ciphertext in bytes.
+    #[inline]
+    #[allow(clippy::len_without_is_empty)]
+    pub fn len(&self) -> u32 {
+        self.len
+    }
+
+    /// Returns the first 8 bytes of the SHA-256 hash of the
+    /// authentication tag of the fragment.
+    #[inline]
+    pub fn tag_hash(&self) -> &[u8; 8] {
+        &self.tag_hash
+    }
+}
+
+/// The sidecar index of an encrypted stream.
+#[derive(Clone, Debug)]
+pub struct Index {
+    entries: Vec<Entry>,
+    buf_size: usize,
+    tag_len: usize,
+}
+
+impl Index {
+    /// Reads a sidecar - as written by an `IndexWriter` - from `r`.
+    pub fn read_from<R: Read>(mut r: R) -> io::Result<Self> {
+        let invalid = || io::Error::new(io::ErrorKind::InvalidData, "malformed sidecar index");
+
+        let mut sidecar = Vec::default();
+        r.read_to_end(&mut sidecar)?;
+        if sidecar.len() < FOOTER_SIZE {
+            return Err(invalid());
+        }
+        let (entries, footer) = sidecar.split_at(sidecar.len() - FOOTER_SIZE);
+        let fragments = u64::from_be_bytes(footer[..8].try_into().unwrap());
+        let buf_size = u32::from_be_bytes(footer[8..12].try_into().unwrap()) as usize;
+        let tag_len = u32::from_be_bytes(footer[12..].try_into().unwrap()) as usize;
+        if buf_size == 0
+            || entries.len() % ENTRY_SIZE != 0
+            || (entries.len() / ENTRY_SIZE) as u64 != fragments
+        {
+            return Err(invalid());
+        }
+
+        let entries = entries
+            .chunks(ENTRY_SIZE)
+            .enumerate()
+            .map(|(i, entry)| Entry {
+                index: i as u64,
+                offset: u64::from_be_bytes(entry[..8].try_into().unwrap()),
+                len: u32::from_be_bytes(entry[8..12].try_into().unwrap()),
+                tag_hash: entry[12..].try_into().unwrap(),
+            })
+            .collect();
+        Ok(Index {
+            entries,
+            buf_size,
+            tag_len,
+        })
+    }
+
+    /// Returns the number of fragments of the stream.
+    #[inline]
+    pub fn fragments(&self) -> u64 {
+        self.entries.len() as u64
+    }
+
+    /// Returns the buffer size used to encrypt the stream.
+    #[inline]
+    pub fn buf_size(&self) -> usize {
+        self.buf_size
+    }
+
+    /// Returns the size of the plaintext of the stream.
+    pub fn plaintext_len(&self) -> u64 {
+        match self.entries.last() {
+            Some(last) => {
+                last.index * self.buf_size as u64 + u64::from(last.len) - self.tag_len as u64
+            }
+            None => 0,
+        }
+    }
+
+    /// Returns the entry of the fragment with the given `index`.
+    #[inline]
+    pub fn get(&self, index: u64) -> Option<&Entry> {
+        self.entries.get(usize::try_from(index).ok()?)
+    }
+
+    /// Returns the entry of the fragment that contains the plaintext
+    /// byte at `offset` - if any.
+    #[inline]
+    pub fn locate(&self, offset: u64) -> Option<&Entry> {
+        if offset >= self.plaintext_len() {
+            return None;
+        }
+        self.get(offset / self.buf_size as u64)
+    }
+}
@@ -0,0 +1,138 @@
+// Copyright (c) 2019 Andreas Auernhammer. All rights reserved.
+// Use of this source code is governed by a license that can be
+// found in the LICENSE file.
+
+use super::framing::{Auto, Framing, Version};
+use super::utils::read_full;
+use super::Layout;
+use std::io::{self, Read};
+
+/// Information about an encrypted stream that is available without
+/// the secret key.
+///
+/// It is returned by `inspect`. The format version and the size of a
+/// stream are always known. The algorithm, the fragment size and the key
+/// ID are only known if the header of the stream records them. None of
+/// the current format versions does. Therefore, these fields are reserved
+/// for future format versions and tooling should handle their absence.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StreamInfo {
+    version: Version,
+    header_len: usize,
+    len: u64,
+    algorithm: Option<&'static str>,
+    buf_size: Option<usize>,
+    key_id: Option<Vec<u8>>,
+}
+
+impl StreamInfo {
+    /// Returns the format version of the stream.
+    #[inline]
+    pub fn version(&self) -> Version {
+        self.version
+    }
+
+    /// Returns the size of the header preceding the fragments.
+    #[inline]
+    pub fn header_len(&self) -> usize {
+        self.header_len
+    }
+
+    /// Returns the total size of the encrypted stream - including its
+    /// header.
+    #[inline]
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// Returns true if the stream is empty. An empty stream is not a
+    /// valid encrypted stream since even an empty plaintext produces one
+    /// fragment.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the name of the AEAD algorithm - if recorded by the header.
+    #[inline]
+    pub fn algorithm(&self) -> Option<&'static str> {
+        self.algorithm
+    }
+
+    /// Returns the fragment size - if recorded by the header.
+    #[inline]
+    pub fn buf_size(&self) -> Option<usize> {
+        self.buf_size
+    }
+
+    /// Returns the ID of the key the stream was encrypted with - if
+    /// recorded by the header.
+    #[inline]
+    pub fn key_id(&self) -> Option<&[u8]> {
+        self.key_id.as_deref()
+    }
+
+    /// Returns the size of the plaintext of the stream assuming the given
+    /// `layout` - or `None` if no stream with this layout has this size.
+    ///
+    /// Since the current format versions do not record the fragment size
+    /// nor the algorithm, the caller has to provide the layout.
+    pub fn plaintext_len(&self, layout: &Layout) -> Option<u64> {
+        layout.plaintext_len(self.len.checked_sub(self.header_len as u64)?)
+    }
+}
+
+/// Reads the encrypted stream from `reader` until its end and returns the
+/// information about it that is available without the secret key.
+///
+/// It does not verify the stream in any way. A stream with an unknown
+/// format version is rejected with an `UnsupportedVersion` error. Any
+/// other data is reported as `Legacy` stream. Tooling can use `inspect`
+/// to catalog encrypted objects - e.g. to find streams that should be
+/// re-encrypted with a newer format version.
+///
+/// # Examples
+///
+/// ```
+/// use std::io::Write;
+/// use sio::{Key, Nonce, Aad, EncWriter, Layout, BUF_SIZE, CHACHA20_POLY1305};
+/// use sio::framing::{Version, V1};
+///
+/// // Obviously, don't use this all-zeros key for anything real.
+/// let key: Key<CHACHA20_POLY1305> = Key::new([0; Key::<CHACHA20_POLY1305>::SIZE]);
+///
+/// let mut ciphertext: Vec<u8> = Vec::default();
+/// let mut writer = EncWriter::with_framing(
+///     &mut ciphertext,
+///     &key,
+///     Nonce::new([0; Nonce::<CHACHA20_POLY1305>::SIZE]),
+///     Aad::empty(),
+///     BUF_SIZE,
+///     V1::default(),
+/// )
+/// .unwrap();
+/// writer.write_all(b"Hello World").unwrap();
+/// writer.close().unwrap();
+///
+/// let info = sio::inspect(ciphertext.as_slice()).unwrap();
+/// assert_eq!(info.version(), Version::V1);
+///
+/// let layout = Layout::of::<CHACHA20_POLY1305>(BUF_SIZE).unwrap();
+/// assert_eq!(info.plaintext_len(&layout), Some(11));
+/// ```
+pub fn inspect<R: Read>(mut reader: R) -> io::Result<StreamInfo> {
+    let mut framing = Auto::default();
+    let mut prefix = vec![0; framing.detect_len()];
+    let n = read_full(&mut reader, &mut prefix)?;
+    framing.detect(&prefix[..n])?;
+
+    let len = n as u64 + io::copy(&mut reader, &mut io::sink())?;
+    Ok(StreamInfo {
+        version: framing.version(),
+        header_len: framing.header().len(),
+        len,
+        algorithm: None,
+        buf_size: None,
+        key_id: None,
+    })
+}
@@ -0,0 +1,75 @@
+// Copyright (c) 2019 Andreas Auernhammer. All rights reserved.
+// Use of this source code is governed by a license that can be
+// found in the LICENSE file.
+
+use super::{decrypt_vec, encrypt_vec, Aad, Algorithm, Key, Nonce};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::io;
+
+/// Serializes the `value` as JSON, encrypts and authenticates it and
+/// returns the ciphertext.
+///
+/// The ciphertext is a regular `Sio` stream with the default framing and
+/// buffer size - exactly what `encrypt_vec` produces for the JSON encoding
+/// of the `value`. Hence, it can be stored as self-contained blob and be
+/// decrypted by `open_json` or by any `DecWriter` or `DecReader`.
+///
+/// A `value` that cannot be serialized - e.g. a map with non-string
+/// keys - is rejected with an error of kind `InvalidInput`.
+///
+/// # Examples
+///
+/// ```
+/// use std::collections::BTreeMap;
+/// use sio::{Key, Nonce, Aad, CHACHA20_POLY1305};
+///
+/// // Obviously, don't use this all-zeros key for anything real.
+/// let key: Key<CHACHA20_POLY1305> = Key::new([0; Key::<CHACHA20_POLY1305>::SIZE]);
+///
+/// let mut config = BTreeMap::new();
+/// config.insert("user".to_string(), "alice".to_string());
+///
+/// let ciphertext = sio::seal_json(
+///     &key,
+///     Nonce::new([0; Nonce::<CHACHA20_POLY1305>::SIZE]),
+///     Aad::from("config".as_bytes()),
+///     &config,
+/// )
+/// .unwrap();
+///
+/// let decrypted: BTreeMap<String, String> = sio::open_json(
+///     &key,
+///     Nonce::new([0; Nonce::<CHACHA20_POLY1305>::SIZE]),
+///     Aad::from("config".as_bytes()),
+///     &ciphertext,
+/// )
+/// .unwrap();
+/// assert_eq!(decrypted, config);
+/// ```
+pub fn seal_json<A: Algorithm, T: Serialize + ?Sized>(
+    key: &Key<A>,
+    nonce: Nonce<A>,
+    aad: Aad<A>,
+    value: &T,
+) -> io::Result<Vec<u8>> {
+    let plaintext = serde_json::to_vec(value)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+    Ok(encrypt_vec(key, nonce, aad, &plaintext))
+}
+
+/// Decrypts and verifies the `ciphertext` - e.g. produced by `seal_json` -
+/// and deserializes the JSON plaintext into a `T`.
+///
+/// A plaintext that is authentic but not a valid JSON encoding of a `T`
+/// is rejected with an error of kind `InvalidData`.
+pub fn open_json<A: Algorithm, T: DeserializeOwned>(
+    key: &Key<A>,
+    nonce: Nonce<A>,
+    aad: Aad<A>,
+    ciphertext: &[u8],
+) -> io::Result<T> {
+    let plaintext = decrypt_vec(key, nonce, aad, ciphertext)?;
+    serde_json::from_slice(&plaintext)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
@@ -0,0 +1,71 @@
+// Copyright (c) 2019 Andreas Auernhammer. All rights reserved.
+// Use of this source code is governed by a license that can be
+// found in the LICENSE file.
+
+use super::{Algorithm, Invalid, Key};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use serde_json::Value;
+
+impl<A: Algorithm> Key<A> {
+    /// Imports a symmetric key from a JSON Web Key (RFC 7517).
+    ///
+    /// The JWK must be an octet sequence key (`"kty": "oct"`) whose `alg`
+    /// matches the algorithm `A` - i.e. `A256GCM` for AES-256-GCM and
+    /// `C20P` for ChaCha20-Poly1305. If present, `use` must be `enc` and
+    /// `key_ops` must permit encryption or decryption. Hence, a key meant
+    /// for a different cipher or for signing cannot be imported by accident.
+    ///
+    /// It fails with `Invalid::Key` if the JWK is malformed, does not match
+    /// the algorithm `A` or does not contain a 256 bit key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sio::{Key, CHACHA20_POLY1305};
+    ///
+    /// let jwk = r#"{
+    ///     "kty": "oct",
+    ///     "alg": "C20P",
+    ///     "use": "enc",
+    ///     "k": "AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA"
+    /// }"#;
+    /// let key: Key<CHACHA20_POLY1305> = Key::from_jwk(jwk).unwrap();
+    /// assert_eq!(key.as_ref(), &[0; 32]);
+    /// ```
+    pub fn from_jwk(jwk: &str) -> Result<Self, Invalid> {
+        let jwk: Value = serde_json::from_str(jwk).map_err(|_| Invalid::Key)?;
+        let field = |name| jwk.get(name).map(|v| v.as_str().ok_or(Invalid::Key));
+
+        if field("kty").transpose()? != Some("oct") {
+            return Err(Invalid::Key);
+        }
+        match (field("alg").transpose()?, A::JWK_ALG) {
+            (Some(alg), Some(expected)) if alg == expected => (),
+            _ => return Err(Invalid::Key),
+        }
+        if let Some(usage) = field("use").transpose()? {
+            if usage != "enc" {
+                return Err(Invalid::Key);
+            }
+        }
+        if let Some(ops) = jwk.get("key_ops") {
+            let ops = ops.as_array().ok_or(Invalid::Key)?;
+            let permitted = ops
+                .iter()
+                .any(|op| matches!(op.as_str(), Some("encrypt") | Some("decrypt")));
+            if !permitted {
+                return Err(Invalid::Key);
+            }
+        }
+
+        let k = field("k").transpose()?.ok_or(Invalid::Key)?;
+        let k = URL_SAFE_NO_PAD.decode(k).map_err(|_| Invalid::Key)?;
+        if k.len() != A::KEY_LEN || k.len() != 32 {
+            return Err(Invalid::Key);
+        }
+        let mut key = [0; 32];
+        key.copy_from_slice(&k);
+        Ok(Key::new(key))
+    }
+}
@@ -0,0 +1,125 @@
+// Copyright (c) 2019 Andreas Auernhammer. All rights reserved.
+// Use of this source code is governed by a license that can be
+// found in the LICENSE file.
+
+use super::{Algorithm, Invalid, MAX_BUF_SIZE};
+
+/// The layout of an encrypted stream that maps plaintext offsets to
+/// ciphertext offsets and back.
+///
+/// A stream encrypted with the default `Sio` framing consists of fragments
+/// of `buf_size` plaintext bytes followed by a `tag_len` bytes long tag.
+/// Only the final fragment may be shorter. Even an empty stream consists
+/// of one (final) fragment. The layout assumes no header or trailer.
+///
+/// Storage layers can use a `Layout` to plan reads - e.g. to translate a
+/// range of plaintext into the range of ciphertext covering it.
+///
+/// # Examples
+///
+/// ```
+/// use sio::{Layout, CHACHA20_POLY1305};
+///
+/// let layout = Layout::of::<CHACHA20_POLY1305>(1024).unwrap();
+///
+/// assert_eq!(layout.plaintext_to_ciphertext(1500), Some((1, 1040 + 476)));
+/// assert_eq!(layout.ciphertext_to_plaintext(1040 + 476), Some((1, 1500)));
+///
+/// // Offsets within the tag of a fragment have no plaintext offset.
+/// assert_eq!(layout.ciphertext_to_plaintext(1030), None);
+///
+/// assert_eq!(layout.ciphertext_len(1500), Some(1500 + 2 * 16));
+/// assert_eq!(layout.plaintext_len(1500 + 2 * 16), Some(1500));
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Layout {
+    buf_size: usize,
+    tag_len: usize,
+}
+
+impl Layout {
+    /// Returns the layout of streams with the given buffer size and tag
+    /// length. The `buf_size` must not be `0` nor greater than
+    /// `MAX_BUF_SIZE`.
+    pub fn new(buf_size: usize, tag_len: usize) -> Result<Self, Invalid> {
+        if buf_size == 0 || buf_size > MAX_BUF_SIZE {
+            return Err(Invalid::BufSize);
+        }
+        Ok(Layout { buf_size, tag_len })
+    }
+
+    /// Returns the layout of streams encrypted with the algorithm `A`
+    /// and the given buffer size.
+    #[inline]
+    pub fn of<A: Algorithm>(buf_size: usize) -> Result<Self, Invalid> {
+        Self::new(buf_size, A::TAG_LEN)
+    }
+
+    /// Returns the buffer size - i.e. the plaintext size of a fragment.
+    #[inline]
+    pub fn buf_size(&self) -> usize {
+        self.buf_size
+    }
+
+    /// Returns the length of the tag of a fragment.
+    #[inline]
+    pub fn tag_len(&self) -> usize {
+        self.tag_len
+    }
+
+    /// Returns the ciphertext size of a fragment except the final one.
+    #[inline]
+    pub fn fragment_len(&self) -> u64 {
+        (self.buf_size + self.tag_len) as u64
+    }
+
+    /// Returns the ciphertext offset at which the fragment with
+    /// the given `index` starts.
+    #[inline]
+    pub fn fragment_offset(&self, index: u64) -> Option<u64> {
+        index.checked_mul(self.fragment_len())
+    }
+
+    /// Returns the index of the fragment containing the plaintext byte at
+    /// `offset` and the offset of its ciphertext byte.
+    pub fn plaintext_to_ciphertext(&self, offset: u64) -> Option<(u64, u64)> {
+        let index = offset / self.buf_size as u64;
+        let pos = offset % self.buf_size as u64;
+        Some((index, self.fragment_offset(index)?.checked_add(pos)?))
+    }
+
+    /// Returns the index of the fragment containing the ciphertext byte at
+    /// `offset` and the offset of its plaintext byte - or `None` if the
+    /// byte belongs to the tag of the fragment.
+    pub fn ciphertext_to_plaintext(&self, offset: u64) -> Option<(u64, u64)> {
+        let index = offset / self.fragment_len();
+        let pos = offset % self.fragment_len();
+        if pos >= self.buf_size as u64 {
+            return None;
+        }
+        Some((index, index * self.buf_size as u64 + pos))
+    }
+
+    /// Returns the size of the ciphertext of a stream with `plaintext_len`
+    /// bytes of plaintext - provided it can be represented as `u64`.
+    pub fn ciphertext_len(&self, plaintext_len: u64) -> Option<u64> {
+        let fragments = plaintext_len.div_ceil(self.buf_size as u64).max(1);
+        fragments
+            .checked_mul(self.tag_len as u64)?
+            .checked_add(plaintext_len)
+    }
+
+    /// Returns the size of the plaintext of a stream with `ciphertext_len`
+    /// bytes of ciphertext - or `None` if no stream has this size.
+    pub fn plaintext_len(&self, ciphertext_len: u64) -> Option<u64> {
+        let fragments = ciphertext_len.div_ceil(self.fragment_len()).max(1);
+        let final_len = ciphertext_len - (fragments - 1) * self.fragment_len();
+
+        // Only the final fragment of an empty stream consists of the tag only.
+        let tag_len = self.tag_len as u64;
+        if final_len < tag_len || (final_len == tag_len && fragments > 1) {
+            return None;
+        }
+        Some((fragments - 1) * self.buf_size as u64 + final_len - tag_len)
+    }
+}
@@ -5,7 +5,8 @@
 //! Authenticated encryption for I/O streams.
 //!
 //! The `sio` crate implements authenticated encryption for the `Read` and `Write` traits.
-//! Therefore, it provides wrapper types for encryption (`EncWriter`) and decryption (`DecWriter`).
+//! Therefore, it provides wrapper types for encryption (`EncWriter`) and decryption (`DecWriter`
+//! and `DecReader`).
 //!
 //! The most core part of this crate is the `Algorithm` trait, which represents
 //! an authenticated encryption algorithm ([AEAD](https://en.wikipedia.org/wiki/Authenticated_encryption#Authenticated_encryption_with_associated_data_(AEAD))).
@@ -24,6 +25,76 @@
 //!     <td>Use <a href="https://briansmith.org/rustdoc/ring/"><code>ring</code></a> to provide
 //!     default implementation of AES-256-GCM based on Google's <a href="https://github.com/google/boringssl">BoringSSL</a>
 //!     by implementing the <code>Algorithm</code> trait.
+//! <tr><td><code>ed25519</code>
+//!     <td>Use <a href="https://briansmith.org/rustdoc/ring/"><code>ring</code></a> to sign
+//!     encrypted streams with Ed25519 and to verify these signatures.
+//! <tr><td><code>zstd</code>
+//!     <td>Use <a href="https://docs.rs/zstd/"><code>zstd</code></a> to provide writers that
+//!     compress data before encrypting it and decompress it after decrypting it.
+//! <tr><td><code>gzip</code>, <code>brotli</code>
+//!     <td>Use <a href="https://docs.rs/flate2/"><code>flate2</code></a> respectively
+//!     <a href="https://docs.rs/brotli/"><code>brotli</code></a> to provide gzip and brotli
+//!     as <code>Codec</code> of the compressing writers.
+//! <tr><td><code>x25519</code>
+//!     <td>Use <a href="https://docs.rs/x25519-dalek/"><code>x25519-dalek</code></a> to
+//!     encrypt streams for the owner of an X25519 public key - either with an ephemeral
+//!     X25519 key (<code>sio::x25519</code>) or with HPKE (<code>sio::hpke</code>).
+//! <tr><td><code>rsa-oaep</code>
+//!     <td>Use <a href="https://docs.rs/rsa/"><code>rsa</code></a> to encrypt streams for the
+//!     owner of an RSA public key by wrapping a random data key with RSA-OAEP
+//!     (<code>sio::rsa_oaep</code>).
+//! <tr><td><code>password</code>
+//!     <td>Use <a href="https://docs.rs/argon2/"><code>argon2</code></a> to encrypt streams
+//!     with a key derived from a passphrase.
+//! <tr><td><code>futures-io</code>
+//!     <td>Use <a href="https://docs.rs/futures-io/"><code>futures-io</code></a> to provide
+//!     asynchronous readers and writers (<code>AsyncEncWriter</code>, <code>AsyncDecWriter</code>
+//!     and <code>AsyncDecReader</code>) that work with <code>async-std</code>, <code>smol</code>
+//!     and any other runtime implementing the <code>futures</code> I/O traits. The
+//!     <code>AsyncParallelEncWriter</code> seals the fragments of a stream on worker threads.
+//! <tr><td><code>futures</code>
+//!     <td>Implement the <code>Sink</code> and <code>Stream</code> traits of the
+//!     <a href="https://docs.rs/futures/"><code>futures</code></a> crates for
+//!     <code>AsyncEncWriter</code> and <code>AsyncDecReader</code>.
+//! <tr><td><code>pyo3</code>
+//!     <td>Use <a href="https://pyo3.rs/"><code>pyo3</code></a> to provide Python bindings
+//!     (<code>sio::python</code>) that expose <code>EncWriter</code> and <code>DecWriter</code>
+//!     as Python file-like objects.
+//! <tr><td><code>jwk</code>
+//!     <td>Use <a href="https://docs.rs/serde_json/"><code>serde_json</code></a> to import
+//!     symmetric keys from JSON Web Keys (<code>Key::from_jwk</code>).
+//! <tr><td><code>serde</code>
+//!     <td>Use <a href="https://serde.rs/"><code>serde</code></a> to encrypt any serializable
+//!     value as JSON (<code>seal_json</code>) and to decrypt it again (<code>open_json</code>).
+//! <tr><td><code>fec</code>
+//!     <td>Use <a href="https://docs.rs/reed-solomon-erasure/"><code>reed-solomon-erasure</code></a>
+//!     to add parity shards to encrypted streams (<code>sio::fec</code>) such that damaged
+//!     fragments can be reconstructed before they get authenticated.
+//! <tr><td><code>panic-free</code>
+//!     <td>Guarantee that no code path panics: Dropping a writer that has not been closed
+//!     and misusing a writer - e.g. writing after close - are no longer detected by a
+//!     panic. Misuse is reported as error of kind <code>InvalidInput</code> instead.
+//! <tr><td><code>af-alg</code>
+//!     <td>Add the <code>AF_ALG_AES_256_GCM</code> backend that computes AES-GCM via the
+//!     <code>AF_ALG</code> sockets of the Linux kernel crypto API - e.g. on a crypto
+//!     accelerator. Only available on Linux.
+//! <tr><td><code>deoxys</code>
+//!     <td>Add the <code>DEOXYS_II_256_128</code> algorithm - a portable, constant-time
+//!     implementation of the nonce-misuse resistant Deoxys-II from the CAESAR portfolio.
+//! <tr><td><code>ocb3</code>
+//!     <td>Use <a href="https://docs.rs/aes/"><code>aes</code></a> to provide the
+//!     <code>AES_256_OCB</code> algorithm - AES-256 in OCB3 mode as specified by RFC 7253.
+//! <tr><td><code>tpm</code>
+//!     <td>Unseal stream keys from a TPM 2.0 via the <code>tpm2_unseal</code> tool of the
+//!     <a href="https://github.com/tpm2-software/tpm2-tools">tpm2-tools</a>
+//!     (<code>sio::tpm</code>) such that they never have to be stored on disk.
+//! <tr><td><code>splice</code>
+//!     <td>Forward sealed fragments between file descriptors without copying them into user
+//!     space (<code>sio::splice</code>) - via <code>sendfile</code> and <code>splice</code> on
+//!     Linux. Only available on Unix.
+//! <tr><td><code>cli</code>
+//!     <td>Build the <code>sio</code> command line tool that encrypts, decrypts, verifies
+//!     and inspects streams - e.g. from shell scripts.
 //! </table>
 //!
 //! # Introduction
@@ -123,15 +194,94 @@
 //! STDOUT. Than, we open the `foo.txt` file again and copy its content to `out`. Observe
 //! that we invoke a `close` method at the end again. Refer to the `Close` trait for an
 //! explanation about why this call is necessary.
+//!
+//! # Empty streams
+//!
+//! An empty stream is not the absence of ciphertext. Closing an `EncWriter` without
+//! writing anything - or calling `EncWriter::finish_empty` - produces one final fragment
+//! without plaintext. The same applies to `encrypt_vec`, `seal_one` and the asynchronous
+//! writers. Decrypting this fragment succeeds without producing any plaintext: a `DecReader`
+//! returns `0` - i.e. EOF - on every read and a `DecWriter` writes nothing to its inner writer.
+//! In contrast, no ciphertext at all is not authentic since it might be a truncated stream.
+//!
+//! Zero-length writes never change the ciphertext. A write of `0` bytes to an `EncWriter`
+//! or `DecWriter` returns `Ok(0)`, and a read into an empty buffer returns `Ok(0)` without
+//! reading from the inner reader.
+//!
+//! # Forwarding ciphertext
+//!
+//! Proxies and storage nodes often forward already encrypted data without decrypting it.
+//! In this case, no `EncWriter` or `DecWriter` is involved at all. With the `splice`
+//! feature, `sio::splice::forward` moves the sealed fragments from one file descriptor to
+//! another - e.g. from a file to a socket. On Linux, it uses `sendfile` or `splice` and,
+//! therefore, avoids copying the fragments into user space.
 
-pub use self::aead::{Aad, Algorithm, Key, Nonce};
-pub use self::error::{Invalid, NotAuthentic};
-pub use self::utils::NopCloser;
-pub use self::writer::{Close, DecWriter, EncWriter};
+pub use self::aead::{Aad, Algorithm, Counter, Key, Nonce, NonceLayout, Token};
+pub use self::cruncher::Cruncher;
+pub use self::datagram::{DatagramOpener, DatagramSealer};
+pub use self::error::{
+    DigestMismatch, Exceeded, Invalid, LengthMismatch, NotAuthentic, QuotaExceeded, Replayed,
+    TrailingData, UnsupportedVersion,
+};
+pub use self::file::{decrypt_file_atomic, EncryptedFile};
+pub use self::fixed::{FixedDecWriter, FixedEncWriter, HeaplessDecWriter, HeaplessEncWriter};
+pub use self::hook::{set_unclosed_hook, take_unclosed_hook, UnclosedHook, UnclosedWriter};
+pub use self::inspect::{inspect, StreamInfo};
+pub use self::layout::Layout;
+pub use self::metadata::Metadata;
+pub use self::multipart::PartLayout;
+pub use self::oneshot::{
+    decrypt_string, decrypt_vec, encrypt_vec, open_one, open_one_in_place, seal_one,
+    seal_one_in_place,
+};
+pub use self::parallel::decrypt_parallel;
+pub use self::pool::{BufferAllocator, BufferPool};
+pub use self::provider::KeyProvider;
+pub use self::range::decrypt_range;
+pub use self::reader::DecReader;
+pub use self::replay::ReplayWindow;
+pub use self::report::CorruptionReport;
+pub use self::sealer::{Opener, Sealer};
+pub use self::sector::SectorDevice;
+pub use self::session::Session;
+pub use self::state::DecryptState;
+pub use self::store::{CounterStore, FileStore};
+pub use self::throttle::Throttle;
+pub use self::utils::{CallbackWriter, NopCloser};
+pub use self::verify::verify;
+pub use self::writer::{Close, Closer, DecWriter, EncWriter, Idempotent, WriteClose};
+
+pub mod framing;
+pub mod noise;
+pub mod pipe;
 
 mod aead;
+mod cruncher;
+mod datagram;
 mod error;
+mod file;
+mod fixed;
+mod hook;
+mod inspect;
+mod layout;
+mod metadata;
+mod multipart;
+mod oneshot;
+mod parallel;
+mod pool;
+mod provider;
+mod range;
+mod reader;
+mod replay;
+mod report;
+mod sealer;
+mod sector;
+mod session;
+mod state;
+mod store;
+mod throttle;
 mod utils;
+mod verify;
 mod writer;
 
 #[cfg(feature = "aesgcm")]
@@ -139,6 +289,108 @@ mod aesgcm;
 #[cfg(feature = "aesgcm")]
 pub use self::aesgcm::AES_256_GCM;
 
+#[cfg(feature = "ring")]
+pub mod audit;
+
+#[cfg(feature = "ring")]
+pub mod index;
+
+#[cfg(feature = "ring")]
+mod aad;
+#[cfg(feature = "ring")]
+pub use self::aad::{AadDigest, AadHasher};
+
+#[cfg(feature = "ring")]
+mod digest;
+#[cfg(feature = "ring")]
+pub use self::digest::{decrypt_verify_digest, Digests};
+
+#[cfg(feature = "ring")]
+pub mod cdc;
+
+#[cfg(feature = "ring")]
+pub mod convergent;
+
+#[cfg(feature = "ring")]
+pub mod manifest;
+
+#[cfg(feature = "ring")]
+mod derive;
+
+#[cfg(feature = "ring")]
+mod master;
+#[cfg(feature = "ring")]
+pub use self::master::KEY_ID_LEN;
+
+#[cfg(feature = "ring")]
+mod commit;
+#[cfg(feature = "ring")]
+pub use self::commit::Committing;
+
+#[cfg(all(feature = "af-alg", target_os = "linux"))]
+mod af_alg;
+#[cfg(all(feature = "af-alg", target_os = "linux"))]
+pub use self::af_alg::AF_ALG_AES_256_GCM;
+
+#[cfg(feature = "deoxys")]
+mod deoxys;
+#[cfg(feature = "deoxys")]
+pub use self::deoxys::DEOXYS_II_256_128;
+
+#[cfg(feature = "ocb3")]
+mod ocb;
+#[cfg(feature = "ocb3")]
+pub use self::ocb::AES_256_OCB;
+
+#[cfg(feature = "ed25519")]
+pub mod sign;
+
+#[cfg(feature = "x25519")]
+pub mod x25519;
+
+#[cfg(feature = "rsa-oaep")]
+pub mod rsa_oaep;
+
+#[cfg(feature = "x25519")]
+pub mod hpke;
+
+#[cfg(feature = "tpm")]
+pub mod tpm;
+
+#[cfg(all(feature = "splice", unix))]
+pub mod splice;
+
+#[cfg(feature = "password")]
+mod password;
+#[cfg(feature = "password")]
+pub use self::password::PasswordParams;
+
+#[cfg(any(feature = "zstd", feature = "gzip", feature = "brotli"))]
+mod compress;
+#[cfg(any(feature = "zstd", feature = "gzip", feature = "brotli"))]
+pub use self::compress::{Codec, CompressedDecWriter, CompressedEncWriter};
+
+#[cfg(feature = "jwk")]
+mod jwk;
+
+#[cfg(feature = "serde")]
+mod json;
+#[cfg(feature = "serde")]
+pub use self::json::{open_json, seal_json};
+
+#[cfg(feature = "fec")]
+pub mod fec;
+
+#[cfg(feature = "futures-io")]
+mod async_io;
+#[cfg(feature = "futures-io")]
+pub use self::async_io::{
+    AsyncDecReader, AsyncDecWriter, AsyncEncWriter, AsyncParallelEncWriter, AsyncSeekDecReader,
+};
+
+#[cfg(all(feature = "pyo3", feature = "c20p1305"))]
+pub mod python;
+
 #[cfg(feature = "c20p1305")]
 mod c20p1305;
 #[cfg(feature = "c20p1305")]
@@ -146,3 +398,7 @@ pub use self::c20p1305::CHACHA20_POLY1305;
 
 pub const MAX_BUF_SIZE: usize = (1 << 24) - 1;
 pub const BUF_SIZE: usize = 1 << 14;
+
+/// The largest nonce length - in bytes - of an `Algorithm` supported
+/// by this crate.
+pub const MAX_NONCE_LEN: usize = 24;
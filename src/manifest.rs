@@ -0,0 +1,326 @@
+// Copyright (c) 2019 Andreas Auernhammer. All rights reserved.
+// Use of this source code is governed by a license that can be
+// found in the LICENSE file.
+
+//! Encrypted manifests of many encrypted streams.
+//!
+//! A `Manifest` maps logical names - e.g. file paths - to the location of
+//! an encrypted stream within some object - e.g. a backup archive - and
+//! the salt of its key. The key of every stream is derived from a master
+//! key and its salt. The manifest itself is encrypted and authenticated
+//! with a key derived from the same master key. Hence, a backup set only
+//! requires a single secret key.
+//!
+//! The logical name of an entry is authenticated as associated data of
+//! its stream. Therefore, swapping streams within the set is detected.
+//!
+//! # Examples
+//!
+//! ```
+//! use std::io::{Cursor, Read};
+//! use sio::{Key, CHACHA20_POLY1305};
+//! use sio::manifest::Manifest;
+//!
+//! // Obviously, don't use this all-zeros key for anything real.
+//! let master_key: Key<CHACHA20_POLY1305> = Key::new([0; Key::<CHACHA20_POLY1305>::SIZE]);
+//!
+//! let mut archive: Vec<u8> = Vec::default();
+//! let mut manifest = Manifest::default();
+//! for (name, content) in &[("a.txt", "Hello"), ("b.txt", "World")] {
+//!     let offset = archive.len() as u64;
+//!     manifest
+//!         .append(&master_key, *name, "archive", offset, content.as_bytes(), &mut archive)
+//!         .unwrap();
+//! }
+//!
+//! let mut encrypted_manifest: Vec<u8> = Vec::default();
+//! manifest.write_to(&master_key, &mut encrypted_manifest).unwrap();
+//!
+//! let manifest = Manifest::read_from(&master_key, encrypted_manifest.as_slice()).unwrap();
+//! let mut reader = manifest.open(&master_key, "b.txt", Cursor::new(&archive)).unwrap();
+//! let mut content = String::default();
+//! reader.read_to_string(&mut content).unwrap();
+//! assert_eq!(content, "World");
+//! ```
+
+use super::writer::Close;
+use super::{Aad, Algorithm, DecReader, EncWriter, Invalid, Key, Layout, Nonce, BUF_SIZE};
+use ring::rand::{SecureRandom, SystemRandom};
+use ring::{digest, hkdf, hmac};
+use std::collections::btree_map::{self, BTreeMap};
+use std::convert::TryInto;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+
+/// The size of the random salt used to derive the key of an entry.
+pub const SALT_LEN: usize = 32;
+
+/// The HKDF info used to derive the key of an entry.
+const ENTRY_INFO: &[u8] = b"sio manifest entry key";
+
+/// The HKDF info used to derive the key of the manifest.
+const MANIFEST_INFO: &[u8] = b"sio manifest key";
+
+/// The associated data of the encrypted manifest.
+const MANIFEST_AAD: &[u8] = b"sio manifest";
+
+/// Derives a key from the `master_key` and the `salt` using HKDF-SHA256.
+fn derive_key<A: Algorithm>(master_key: &Key<A>, salt: &[u8], info: &[u8]) -> Key<A> {
+    let mut key = [0; 32];
+    let salt = hmac::SigningKey::new(&digest::SHA256, salt);
+    hkdf::extract_and_expand(&salt, master_key.as_ref(), info, &mut key);
+    Key::new(key)
+}
+
+/// The location and key-derivation salt of an encrypted stream.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Entry {
+    object: String,
+    offset: u64,
+    len: u64,
+    salt: [u8; SALT_LEN],
+}
+
+impl Entry {
+    /// Returns the name of the object containing the encrypted stream.
+    #[inline]
+    pub fn object(&self) -> &str {
+        &self.object
+    }
+
+    /// Returns the offset of the encrypted stream within its object.
+    #[inline]
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Returns the length of the encrypted stream in bytes.
+    #[inline]
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// Returns true if the encrypted stream is empty. This is never
+    /// the case for an entry added by `Manifest::append`.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the salt used to derive the key of the encrypted stream
+    /// from the master key.
+    #[inline]
+    pub fn salt(&self) -> &[u8; SALT_LEN] {
+        &self.salt
+    }
+}
+
+/// A map from logical names to encrypted streams.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Manifest {
+    entries: BTreeMap<String, Entry>,
+}
+
+impl Manifest {
+    /// Encrypts everything read from `plaintext` with a key derived from
+    /// the `master_key` and a random salt, writes the ciphertext to
+    /// `ciphertext` and adds an entry for it under the given `name`.
+    ///
+    /// The caller is responsible for writing the ciphertext at `offset`
+    /// of the `object`. An existing entry with the same `name` is
+    /// replaced.
+    pub fn append<A: Algorithm, R: Read, W: Write>(
+        &mut self,
+        master_key: &Key<A>,
+        name: impl Into<String>,
+        object: impl Into<String>,
+        offset: u64,
+        plaintext: R,
+        ciphertext: W,
+    ) -> io::Result<&Entry> {
+        let name = name.into();
+        let mut salt = [0; SALT_LEN];
+        SystemRandom::new()
+            .fill(&mut salt)
+            .map_err(|_| Invalid::Key)?;
+
+        let key = derive_key(master_key, &salt, ENTRY_INFO);
+        let mut writer =
+            EncWriter::new_unclosed(ciphertext, &key, Nonce::zero(), Aad::from(name.as_bytes()));
+        let n = writer.copy_from(plaintext)?;
+        writer.close()?;
+
+        let entry = Entry {
+            object: object.into(),
+            offset,
+            len: Layout::of::<A>(BUF_SIZE)?
+                .ciphertext_len(n)
+                .ok_or(Invalid::BufSize)?,
+            salt,
+        };
+        self.entries.insert(name.clone(), entry);
+        Ok(&self.entries[&name])
+    }
+
+    /// Returns the entry with the given `name`, if any.
+    #[inline]
+    pub fn get(&self, name: &str) -> Option<&Entry> {
+        self.entries.get(name)
+    }
+
+    /// Removes the entry with the given `name` and returns it, if any.
+    #[inline]
+    pub fn remove(&mut self, name: &str) -> Option<Entry> {
+        self.entries.remove(name)
+    }
+
+    /// Returns an iterator over all names and their entries - sorted
+    /// by name.
+    #[inline]
+    pub fn iter(&self) -> btree_map::Iter<'_, String, Entry> {
+        self.entries.iter()
+    }
+
+    /// Returns the number of entries.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns true if the manifest has no entries.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns a `DecReader` that decrypts and verifies the stream of the
+    /// entry with the given `name`. The `object` must be the object of the
+    /// entry.
+    ///
+    /// It returns an error of kind `NotFound` if there is no such entry.
+    pub fn open<A: Algorithm, R: Read + Seek>(
+        &self,
+        master_key: &Key<A>,
+        name: &str,
+        mut object: R,
+    ) -> io::Result<DecReader<A, io::Take<R>>> {
+        let (name, entry) = self
+            .entries
+            .get_key_value(name)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no such manifest entry"))?;
+        object.seek(SeekFrom::Start(entry.offset))?;
+        Ok(DecReader::new(
+            object.take(entry.len),
+            &derive_key(master_key, &entry.salt, ENTRY_INFO),
+            Nonce::zero(),
+            Aad::from(name.as_bytes()),
+        ))
+    }
+
+    /// Encrypts the manifest with a key derived from the `master_key` and
+    /// a random salt and writes it to `inner`.
+    pub fn write_to<A: Algorithm, W: Write + Close>(
+        &self,
+        master_key: &Key<A>,
+        mut inner: W,
+    ) -> io::Result<()> {
+        let mut salt = [0; SALT_LEN];
+        SystemRandom::new()
+            .fill(&mut salt)
+            .map_err(|_| Invalid::Key)?;
+        inner.write_all(&salt)?;
+
+        let mut writer = EncWriter::new(
+            inner,
+            &derive_key(master_key, &salt, MANIFEST_INFO),
+            Nonce::zero(),
+            Aad::from(MANIFEST_AAD),
+        );
+        writer.write_all(&self.encode())?;
+        writer.close()
+    }
+
+    /// Reads, decrypts and verifies a manifest written by `write_to`.
+    pub fn read_from<A: Algorithm, R: Read>(master_key: &Key<A>, mut inner: R) -> io::Result<Self> {
+        let mut salt = [0; SALT_LEN];
+        inner.read_exact(&mut salt)?;
+
+        let mut buf = Vec::default();
+        DecReader::new(
+            inner,
+            &derive_key(master_key, &salt, MANIFEST_INFO),
+            Nonce::zero(),
+            Aad::from(MANIFEST_AAD),
+        )
+        .read_to_end(&mut buf)?;
+        Self::decode(&buf)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "malformed manifest"))
+    }
+
+    /// Returns the encoding of the manifest.
+    ///
+    /// The encoding consists of the number of entries followed by the
+    /// entries. Each entry consists of its name, its object, its offset,
+    /// its length and its salt. Names and objects are prefixed with their
+    /// length. All integers are encoded big-endian - lengths as 4 byte
+    /// and offsets as 8 byte integers.
+    fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::default();
+        buf.extend_from_slice(&(self.entries.len() as u32).to_be_bytes());
+        for (name, entry) in &self.entries {
+            buf.extend_from_slice(&(name.len() as u32).to_be_bytes());
+            buf.extend_from_slice(name.as_bytes());
+            buf.extend_from_slice(&(entry.object.len() as u32).to_be_bytes());
+            buf.extend_from_slice(entry.object.as_bytes());
+            buf.extend_from_slice(&entry.offset.to_be_bytes());
+            buf.extend_from_slice(&entry.len.to_be_bytes());
+            buf.extend_from_slice(&entry.salt);
+        }
+        buf
+    }
+
+    fn decode(mut buf: &[u8]) -> Option<Self> {
+        fn next<'a>(buf: &mut &'a [u8], n: usize) -> Option<&'a [u8]> {
+            if buf.len() < n {
+                return None;
+            }
+            let (v, rest) = buf.split_at(n);
+            *buf = rest;
+            Some(v)
+        }
+        fn next_string(buf: &mut &[u8]) -> Option<String> {
+            let len = u32::from_be_bytes(next(buf, 4)?.try_into().ok()?);
+            let s = next(buf, len as usize)?;
+            String::from_utf8(s.to_vec()).ok()
+        }
+        fn next_u64(buf: &mut &[u8]) -> Option<u64> {
+            Some(u64::from_be_bytes(next(buf, 8)?.try_into().ok()?))
+        }
+
+        let mut entries = BTreeMap::default();
+        let n = u32::from_be_bytes(next(&mut buf, 4)?.try_into().ok()?);
+        for _ in 0..n {
+            let name = next_string(&mut buf)?;
+            let entry = Entry {
+                object: next_string(&mut buf)?,
+                offset: next_u64(&mut buf)?,
+                len: next_u64(&mut buf)?,
+                salt: next(&mut buf, SALT_LEN)?.try_into().ok()?,
+            };
+            entries.insert(name, entry);
+        }
+        if !buf.is_empty() {
+            return None;
+        }
+        Some(Manifest { entries })
+    }
+}
+
+impl<'a> IntoIterator for &'a Manifest {
+    type Item = (&'a String, &'a Entry);
+    type IntoIter = btree_map::Iter<'a, String, Entry>;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.iter()
+    }
+}
@@ -0,0 +1,92 @@
+// Copyright (c) 2019 Andreas Auernhammer. All rights reserved.
+// Use of this source code is governed by a license that can be
+// found in the LICENSE file.
+
+use super::writer::Close;
+use super::{Aad, Algorithm, DecReader, EncWriter, Invalid, Key, Nonce};
+use ring::rand::{SecureRandom, SystemRandom};
+use ring::{digest, hkdf, hmac};
+use std::io::{self, Read, Write};
+
+/// The size of the random key ID that precedes streams encrypted
+/// with a key derived from a master key.
+pub const KEY_ID_LEN: usize = 32;
+
+/// The HKDF info used to derive the key of a stream.
+const INFO: &[u8] = b"sio object key";
+
+/// Derives the key of a stream from the `master_key` and the `key_id`
+/// using HKDF-SHA256.
+fn derive_key<A: Algorithm>(master_key: &Key<A>, key_id: &[u8; KEY_ID_LEN]) -> Key<A> {
+    let mut key = [0; 32];
+    let salt = hmac::SigningKey::new(&digest::SHA256, key_id);
+    hkdf::extract_and_expand(&salt, master_key.as_ref(), INFO, &mut key);
+    Key::new(key)
+}
+
+impl<A: Algorithm, W: Write + Close> EncWriter<A, W> {
+    /// Creates a new `EncWriter` that encrypts a stream with a unique key
+    /// derived from the `master_key`.
+    ///
+    /// It picks a random 32 byte key ID and writes it to the `inner` writer
+    /// immediately. The key of the stream is derived from the `master_key`
+    /// and the key ID using HKDF-SHA256. Since every stream is encrypted
+    /// with its own key, a single master key can protect a (practically)
+    /// unlimited number of streams without any nonce management.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::{Read, Write};
+    /// use sio::{Key, Aad, EncWriter, DecReader, CHACHA20_POLY1305};
+    ///
+    /// // Obviously, don't use this all-zeros key for anything real.
+    /// let master_key: Key<CHACHA20_POLY1305> = Key::new([0; Key::<CHACHA20_POLY1305>::SIZE]);
+    ///
+    /// let mut ciphertext: Vec<u8> = Vec::default();
+    /// let mut writer =
+    ///     EncWriter::with_master_key(&mut ciphertext, &master_key, Aad::empty()).unwrap();
+    /// writer.write_all(b"Hello World").unwrap();
+    /// writer.close().unwrap();
+    ///
+    /// let mut reader =
+    ///     DecReader::with_master_key(ciphertext.as_slice(), &master_key, Aad::empty()).unwrap();
+    /// let mut plaintext = String::default();
+    /// reader.read_to_string(&mut plaintext).unwrap();
+    ///
+    /// assert_eq!(plaintext, "Hello World");
+    /// ```
+    pub fn with_master_key(mut inner: W, master_key: &Key<A>, aad: Aad<A>) -> io::Result<Self> {
+        let mut key_id = [0; KEY_ID_LEN];
+        SystemRandom::new()
+            .fill(&mut key_id)
+            .map_err(|_| Invalid::Key)?;
+
+        inner.write_all(&key_id)?;
+        Ok(EncWriter::new(
+            inner,
+            &derive_key(master_key, &key_id),
+            Nonce::zero(),
+            aad,
+        ))
+    }
+}
+
+impl<A: Algorithm, R: Read> DecReader<A, R> {
+    /// Creates a new `DecReader` that decrypts a stream encrypted with a
+    /// key derived from the `master_key` - i.e. by
+    /// `EncWriter::with_master_key`.
+    ///
+    /// It reads the key ID of the stream from the `inner` reader
+    /// immediately.
+    pub fn with_master_key(mut inner: R, master_key: &Key<A>, aad: Aad<A>) -> io::Result<Self> {
+        let mut key_id = [0; KEY_ID_LEN];
+        inner.read_exact(&mut key_id)?;
+        Ok(DecReader::new(
+            inner,
+            &derive_key(master_key, &key_id),
+            Nonce::zero(),
+            aad,
+        ))
+    }
+}
@@ -0,0 +1,64 @@
+// Copyright (c) 2019 Andreas Auernhammer. All rights reserved.
+// Use of this source code is governed by a license that can be
+// found in the LICENSE file.
+
+use std::collections::BTreeMap;
+use std::convert::TryInto;
+
+/// A small map of metadata - e.g. the content type or the original
+/// file name - that is encrypted and authenticated together with a
+/// stream.
+///
+/// The metadata is stored in its own leading fragment. Therefore, its
+/// encoded size must not exceed the fragment size of the stream. Each
+/// entry takes 8 bytes in addition to its key and value and the map
+/// itself takes 4 bytes.
+pub type Metadata = BTreeMap<String, String>;
+
+/// Returns the encoding of the `metadata`.
+///
+/// The encoding consists of the number of entries followed by the
+/// entries. Each entry consists of the length of the key, the key,
+/// the length of the value and the value. All lengths are encoded
+/// as 4 byte big-endian integers.
+pub(crate) fn encode(metadata: &Metadata) -> Vec<u8> {
+    let mut buf = Vec::default();
+    buf.extend_from_slice(&(metadata.len() as u32).to_be_bytes());
+    for (key, value) in metadata {
+        buf.extend_from_slice(&(key.len() as u32).to_be_bytes());
+        buf.extend_from_slice(key.as_bytes());
+        buf.extend_from_slice(&(value.len() as u32).to_be_bytes());
+        buf.extend_from_slice(value.as_bytes());
+    }
+    buf
+}
+
+/// Decodes the metadata at the beginning of `buf`. Any bytes following
+/// the encoding must be zero.
+pub(crate) fn decode(mut buf: &[u8]) -> Option<Metadata> {
+    fn next<'a>(buf: &mut &'a [u8], n: usize) -> Option<&'a [u8]> {
+        if buf.len() < n {
+            return None;
+        }
+        let (v, rest) = buf.split_at(n);
+        *buf = rest;
+        Some(v)
+    }
+    fn next_string(buf: &mut &[u8]) -> Option<String> {
+        let len = u32::from_be_bytes(next(buf, 4)?.try_into().ok()?);
+        let s = next(buf, len as usize)?;
+        String::from_utf8(s.to_vec()).ok()
+    }
+
+    let mut metadata = Metadata::default();
+    let entries = u32::from_be_bytes(next(&mut buf, 4)?.try_into().ok()?);
+    for _ in 0..entries {
+        let key = next_string(&mut buf)?;
+        let value = next_string(&mut buf)?;
+        metadata.insert(key, value);
+    }
+    if buf.iter().any(|b| *b != 0) {
+        return None;
+    }
+    Some(metadata)
+}
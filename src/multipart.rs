@@ -0,0 +1,261 @@
+// Copyright (c) 2019 Andreas Auernhammer. All rights reserved.
+// Use of this source code is governed by a license that can be
+// found in the LICENSE file.
+
+use super::{
+    Aad, Algorithm, Close, EncWriter, Invalid, Key, Layout, Nonce, BUF_SIZE, MAX_BUF_SIZE,
+};
+use std::convert::TryFrom;
+use std::io::Write;
+use std::marker::PhantomData;
+
+/// The layout of an encrypted stream that gets uploaded in multiple
+/// parts - e.g. as S3 multipart upload.
+///
+/// A `PartLayout` rounds a desired part size down to a whole number of
+/// fragments. When the ciphertext produced by an `EncWriter` with the
+/// same buffer size is split into parts of `part_size` bytes, each part
+/// contains only complete fragments. Hence, every part can be decrypted
+/// and verified on its own once its fragment indices are known.
+///
+/// The `PartLayout` also reports the exact ciphertext length of every
+/// part for a given plaintext length. The layout assumes the default
+/// `Sio` framing which adds no header or trailer.
+///
+/// If every part must have exactly a given size - e.g. S3's minimum
+/// part size of 5 MiB - use `exact` instead. It picks the fragment size
+/// such that the part size is a whole multiple of it and `enc_writer`
+/// returns an `EncWriter` that writes exactly one part to the inner
+/// writer at once.
+///
+/// # Examples
+///
+/// ```
+/// use std::io::Write;
+/// use sio::{Key, Nonce, Aad, EncWriter, PartLayout, CHACHA20_POLY1305};
+///
+/// // Obviously, don't use this all-zeros key for anything real.
+/// let key: Key<CHACHA20_POLY1305> = Key::new([0; Key::<CHACHA20_POLY1305>::SIZE]);
+///
+/// let layout = PartLayout::<CHACHA20_POLY1305>::new(5 * 1024 * 1024).unwrap();
+///
+/// let data = vec![0; 12 * 1024 * 1024];
+/// let mut ciphertext: Vec<u8> = Vec::default();
+/// let mut writer = EncWriter::with_buffer_size(
+///     &mut ciphertext,
+///     &key,
+///     Nonce::new([0; Nonce::<CHACHA20_POLY1305>::SIZE]),
+///     Aad::empty(),
+///     layout.buf_size(),
+/// )
+/// .unwrap();
+/// writer.write_all(&data).unwrap();
+/// writer.close().unwrap();
+///
+/// let parts = layout.parts(data.len() as u64).unwrap();
+/// assert_eq!(parts, 3);
+/// for (i, part) in ciphertext.chunks(layout.part_size() as usize).enumerate() {
+///     assert_eq!(Some(part.len() as u64), layout.part_len(data.len() as u64, i as u64));
+/// }
+/// ```
+pub struct PartLayout<A: Algorithm> {
+    buf_size: usize,
+    fragments_per_part: u64,
+    _algorithm: PhantomData<A>,
+}
+
+impl<A: Algorithm> PartLayout<A> {
+    /// Returns the layout of parts with at most `part_size` bytes
+    /// of ciphertext using the default buffer size of 16 KiB.
+    ///
+    /// The `part_size` must be large enough to hold at least one
+    /// fragment.
+    pub fn new(part_size: u64) -> Result<Self, Invalid> {
+        Self::with_buffer_size(part_size, BUF_SIZE)
+    }
+
+    /// Returns the layout of parts with at most `part_size` bytes of
+    /// ciphertext using the specified buffer size. The `buf_size` must
+    /// not be `0` nor greater than `MAX_BUF_SIZE`.
+    ///
+    /// The `part_size` must be large enough to hold at least one
+    /// fragment.
+    pub fn with_buffer_size(part_size: u64, buf_size: usize) -> Result<Self, Invalid> {
+        if buf_size == 0 || buf_size > MAX_BUF_SIZE {
+            return Err(Invalid::BufSize);
+        }
+        let fragments_per_part = part_size / (buf_size + A::TAG_LEN) as u64;
+        if fragments_per_part == 0 {
+            return Err(Invalid::BufSize);
+        }
+        Ok(PartLayout {
+            buf_size,
+            fragments_per_part,
+            _algorithm: PhantomData,
+        })
+    }
+
+    /// Returns the layout of parts with exactly `part_size` bytes of
+    /// ciphertext using the largest buffer size not greater than the
+    /// default of 16 KiB.
+    ///
+    /// It fails if `part_size` cannot be split into equally sized
+    /// fragments - e.g. when it is smaller than one fragment.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sio::{PartLayout, CHACHA20_POLY1305};
+    ///
+    /// let layout = PartLayout::<CHACHA20_POLY1305>::exact(5 * 1024 * 1024).unwrap();
+    /// assert_eq!(layout.part_size(), 5 * 1024 * 1024);
+    /// assert_eq!(layout.buf_size(), 16368);
+    /// assert_eq!(layout.fragments_per_part(), 320);
+    /// ```
+    pub fn exact(part_size: u64) -> Result<Self, Invalid> {
+        Self::exact_with_max_buffer_size(part_size, BUF_SIZE)
+    }
+
+    /// Returns the layout of parts with exactly `part_size` bytes of
+    /// ciphertext using the largest buffer size not greater than
+    /// `max_buf_size`. The `max_buf_size` must not be `0` nor greater
+    /// than `MAX_BUF_SIZE`.
+    ///
+    /// It fails if `part_size` cannot be split into equally sized
+    /// fragments with a buffer size of at most `max_buf_size`.
+    pub fn exact_with_max_buffer_size(
+        part_size: u64,
+        max_buf_size: usize,
+    ) -> Result<Self, Invalid> {
+        if max_buf_size == 0 || max_buf_size > MAX_BUF_SIZE {
+            return Err(Invalid::BufSize);
+        }
+        // The fewest fragments per part imply the largest buffer size.
+        // So we look for the smallest divisor of `part_size` such that
+        // every fragment holds at least one and at most `max_buf_size`
+        // bytes of plaintext.
+        let min = part_size
+            .div_ceil((max_buf_size + A::TAG_LEN) as u64)
+            .max(1);
+        let max = part_size / (A::TAG_LEN as u64 + 1);
+
+        let mut fragments_per_part = None;
+        let mut i = 1;
+        while i <= part_size / i {
+            if part_size % i == 0 {
+                for n in [i, part_size / i] {
+                    if n >= min && n <= max && fragments_per_part.is_none_or(|m| n < m) {
+                        fragments_per_part = Some(n);
+                    }
+                }
+            }
+            i += 1;
+        }
+        match fragments_per_part {
+            Some(n) => Ok(PartLayout {
+                buf_size: (part_size / n) as usize - A::TAG_LEN,
+                fragments_per_part: n,
+                _algorithm: PhantomData,
+            }),
+            None => Err(Invalid::BufSize),
+        }
+    }
+
+    /// Returns an `EncWriter` with the buffer size of this layout that
+    /// writes the ciphertext of one part - i.e. `part_size` bytes - to
+    /// `inner` at once. Only the last write, on `close`, may be shorter.
+    ///
+    /// Explicitly flushing the `EncWriter` writes the buffered ciphertext
+    /// to `inner` immediately and, therefore, breaks the alignment of all
+    /// subsequent writes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::Write;
+    /// use sio::{Key, Nonce, Aad, PartLayout, CHACHA20_POLY1305};
+    ///
+    /// // Obviously, don't use this all-zeros key for anything real.
+    /// let key: Key<CHACHA20_POLY1305> = Key::new([0; Key::<CHACHA20_POLY1305>::SIZE]);
+    ///
+    /// let layout = PartLayout::<CHACHA20_POLY1305>::exact(64 * 1024).unwrap();
+    /// let mut ciphertext: Vec<u8> = Vec::default();
+    /// let mut writer = layout
+    ///     .enc_writer(
+    ///         &mut ciphertext,
+    ///         &key,
+    ///         Nonce::new([0; Nonce::<CHACHA20_POLY1305>::SIZE]),
+    ///         Aad::empty(),
+    ///     )
+    ///     .unwrap();
+    /// writer.write_all(&[0; 100 * 1024]).unwrap();
+    /// writer.close().unwrap();
+    ///
+    /// assert_eq!(Some(ciphertext.len() as u64), layout.ciphertext_len(100 * 1024));
+    /// ```
+    pub fn enc_writer<W: Write + Close>(
+        &self,
+        inner: W,
+        key: &Key<A>,
+        nonce: Nonce<A>,
+        aad: Aad<A>,
+    ) -> Result<EncWriter<A, W>, Invalid> {
+        let part_size = usize::try_from(self.part_size()).map_err(|_| Invalid::BufSize)?;
+        EncWriter::with_buffer_size(inner, key, nonce, aad, self.buf_size)?
+            .output_buffer(part_size)
+            .map_err(|_| Invalid::BufSize)
+    }
+
+    /// Returns the buffer size the `EncWriter` has to use.
+    #[inline]
+    pub fn buf_size(&self) -> usize {
+        self.buf_size
+    }
+
+    /// Returns the number of fragments per part.
+    #[inline]
+    pub fn fragments_per_part(&self) -> u64 {
+        self.fragments_per_part
+    }
+
+    /// Returns the ciphertext size of every part except the last one.
+    #[inline]
+    pub fn part_size(&self) -> u64 {
+        self.fragments_per_part * (self.buf_size + A::TAG_LEN) as u64
+    }
+
+    /// Returns the plaintext size of every part except the last one.
+    #[inline]
+    pub fn part_plaintext_size(&self) -> u64 {
+        self.fragments_per_part * self.buf_size as u64
+    }
+
+    /// Returns the size of the ciphertext of a stream with `plaintext_len`
+    /// bytes of plaintext - provided it can be represented as `u64`.
+    pub fn ciphertext_len(&self, plaintext_len: u64) -> Option<u64> {
+        Layout::new(self.buf_size, A::TAG_LEN)
+            .ok()?
+            .ciphertext_len(plaintext_len)
+    }
+
+    /// Returns the number of parts of a stream with `plaintext_len`
+    /// bytes of plaintext.
+    pub fn parts(&self, plaintext_len: u64) -> Option<u64> {
+        Some(
+            self.ciphertext_len(plaintext_len)?
+                .div_ceil(self.part_size()),
+        )
+    }
+
+    /// Returns the ciphertext length of the given `part` of a stream with
+    /// `plaintext_len` bytes of plaintext - or `None` if there is no such
+    /// part.
+    pub fn part_len(&self, plaintext_len: u64, part: u64) -> Option<u64> {
+        let ciphertext_len = self.ciphertext_len(plaintext_len)?;
+        let offset = part.checked_mul(self.part_size())?;
+        if offset >= ciphertext_len {
+            return None;
+        }
+        Some((ciphertext_len - offset).min(self.part_size()))
+    }
+}
@@ -0,0 +1,248 @@
+// Copyright (c) 2019 Andreas Auernhammer. All rights reserved.
+// Use of this source code is governed by a license that can be
+// found in the LICENSE file.
+
+//! Transport encryption after a Noise handshake.
+//!
+//! A Noise handshake - e.g. `Noise_XX` - ends with two cipher states: one
+//! key for each direction. This module turns these keys into a duplex
+//! channel that encrypts every transport message as `sio` stream. Hence,
+//! messages of any size are split into authenticated fragments - instead
+//! of being limited to the 64 KiB of a Noise transport message - and the
+//! receiver can process a message before it has been received entirely.
+//!
+//! Each direction is a `CipherState` that owns the key and counts the
+//! messages. The nonce of a message consists of a nonce prefix - supplied
+//! together with the key - followed by the big-endian message counter. The
+//! sender and the receiver count their messages independently. Therefore,
+//! the messages must be processed in the order in which they have been
+//! sent - as over any reliable transport like TCP.
+//!
+//! Both parties can replace the key of a direction via `rekey` - e.g.
+//! after a certain number of messages. As in Noise, the new key is derived
+//! from the current key by encrypting zeros with a nonce that is never
+//! used for a message. A rekey must happen at the same point of the
+//! message sequence on both sides. Otherwise, no further message will be
+//! authentic.
+//!
+//! # Examples
+//!
+//! ```
+//! use std::io::{Read, Write};
+//! use sio::{Key, Aad, CHACHA20_POLY1305};
+//! use sio::noise::Transport;
+//!
+//! // The keys of the two cipher states produced by the handshake.
+//! // Obviously, don't use these keys for anything real.
+//! let (c1, c2) = ([1; 32], [2; 32]);
+//!
+//! let mut initiator = Transport::<CHACHA20_POLY1305>::initiator(Key::new(c1), Key::new(c2), &[]).unwrap();
+//! let mut responder = Transport::<CHACHA20_POLY1305>::responder(Key::new(c1), Key::new(c2), &[]).unwrap();
+//!
+//! let mut message: Vec<u8> = Vec::default();
+//! let mut writer = initiator.send().enc_writer(&mut message, Aad::empty()).unwrap();
+//! writer.write_all(b"Hello World").unwrap();
+//! writer.close().unwrap();
+//!
+//! let mut plaintext: Vec<u8> = Vec::default();
+//! let mut reader = responder.recv().dec_reader(message.as_slice(), Aad::empty()).unwrap();
+//! reader.read_to_end(&mut plaintext).unwrap();
+//! assert_eq!(plaintext, b"Hello World");
+//! ```
+
+use super::writer::Close;
+use super::{
+    Aad, Algorithm, DecReader, DecWriter, EncWriter, Exceeded, Invalid, Key, Nonce, MAX_NONCE_LEN,
+};
+use std::io::{Read, Write};
+
+/// One direction of a Noise transport - i.e. a key together with the
+/// number of messages encrypted or decrypted with it.
+pub struct CipherState<A: Algorithm> {
+    key: Key<A>,
+    prefix: [u8; MAX_NONCE_LEN - 4],
+    prefix_len: usize,
+    n: u64,
+}
+
+impl<A: Algorithm> CipherState<A> {
+    /// Creates a new `CipherState` from the `key` and the nonce `prefix`
+    /// of a cipher state produced by a Noise handshake.
+    ///
+    /// The `prefix` must be shorter than a `Nonce`. The remaining bytes of
+    /// the `Nonce` contain the message counter. Hence, a longer prefix
+    /// reduces the number of messages that can be sent before the key must
+    /// be replaced. It fails with `Invalid::Nonce` if the `prefix` is too
+    /// long.
+    pub fn new(key: Key<A>, prefix: &[u8]) -> Result<Self, Invalid> {
+        if prefix.len() >= Nonce::<A>::SIZE {
+            return Err(Invalid::Nonce);
+        }
+        let mut state = CipherState {
+            key,
+            prefix: [0; MAX_NONCE_LEN - 4],
+            prefix_len: prefix.len(),
+            n: 0,
+        };
+        state.prefix[..prefix.len()].copy_from_slice(prefix);
+        Ok(state)
+    }
+
+    /// Returns the number of messages processed with the current key.
+    #[inline]
+    pub fn messages(&self) -> u64 {
+        self.n
+    }
+
+    /// Returns the number of messages that can be processed before the
+    /// key must be replaced via `rekey`.
+    #[inline]
+    pub fn remaining(&self) -> u64 {
+        self.limit() - self.n
+    }
+
+    /// Returns a new `EncWriter` that encrypts the next message.
+    ///
+    /// It fails with `Exceeded` once no more messages can be encrypted
+    /// with the current key.
+    pub fn enc_writer<W: Write + Close>(
+        &mut self,
+        inner: W,
+        aad: Aad<A>,
+    ) -> Result<EncWriter<A, W>, Exceeded> {
+        let nonce = self.next_nonce()?;
+        Ok(EncWriter::new(inner, &self.key, nonce, aad))
+    }
+
+    /// Returns a new `DecWriter` that decrypts the next message.
+    ///
+    /// It fails with `Exceeded` once no more messages can be decrypted
+    /// with the current key.
+    pub fn dec_writer<W: Write + Close>(
+        &mut self,
+        inner: W,
+        aad: Aad<A>,
+    ) -> Result<DecWriter<A, W>, Exceeded> {
+        let nonce = self.next_nonce()?;
+        Ok(DecWriter::new(inner, &self.key, nonce, aad))
+    }
+
+    /// Returns a new `DecReader` that decrypts the next message.
+    ///
+    /// It fails with `Exceeded` once no more messages can be decrypted
+    /// with the current key.
+    pub fn dec_reader<R: Read>(
+        &mut self,
+        inner: R,
+        aad: Aad<A>,
+    ) -> Result<DecReader<A, R>, Exceeded> {
+        let nonce = self.next_nonce()?;
+        Ok(DecReader::new(inner, &self.key, nonce, aad))
+    }
+
+    /// Replaces the key with a new key derived from the current one and
+    /// resets the message counter.
+    ///
+    /// The new key consists of the first 32 bytes of the encryption of 32
+    /// zero bytes under the current key. The nonce is the one of the last
+    /// message counter, which is reserved and never used for a message.
+    pub fn rekey(&mut self) -> Result<(), Invalid> {
+        let mut nonce = [0xff; MAX_NONCE_LEN];
+        nonce[..Nonce::<A>::SIZE].copy_from_slice(self.nonce(u64::MAX).as_ref());
+
+        let mut key = vec![0; 32 + A::TAG_LEN];
+        A::new(self.key.as_ref()).seal_in_place(&nonce[..A::NONCE_LEN], &[], &mut key)?;
+        let mut bytes = [0; 32];
+        bytes.copy_from_slice(&key[..32]);
+        self.key = Key::new(bytes);
+        self.n = 0;
+        Ok(())
+    }
+
+    /// Returns the nonce of the next message and increments the counter.
+    fn next_nonce(&mut self) -> Result<Nonce<A>, Exceeded> {
+        if self.n >= self.limit() {
+            return Err(Exceeded::default());
+        }
+        let nonce = self.nonce(self.n);
+        self.n += 1;
+        Ok(nonce)
+    }
+
+    /// Returns the nonce for the message `counter`. The counter is
+    /// truncated to the bytes following the prefix.
+    fn nonce(&self, counter: u64) -> Nonce<A> {
+        let mut nonce = self.prefix;
+        let nonce = &mut nonce[..Nonce::<A>::SIZE];
+        let len = (nonce.len() - self.prefix_len).min(8);
+        let start = nonce.len() - len;
+        nonce[start..].copy_from_slice(&counter.to_be_bytes()[8 - len..]);
+        Nonce::from_slice(nonce)
+    }
+
+    /// Returns the number of messages per key. The highest counter
+    /// is reserved for `rekey`.
+    fn limit(&self) -> u64 {
+        match Nonce::<A>::SIZE - self.prefix_len {
+            len if len >= 8 => u64::MAX,
+            len => (1 << (8 * len)) - 1,
+        }
+    }
+}
+
+/// The duplex channel established by a Noise handshake.
+///
+/// A `Transport` consists of two `CipherState`s: one for the messages
+/// sent to the peer and one for the messages received from it.
+pub struct Transport<A: Algorithm> {
+    send: CipherState<A>,
+    recv: CipherState<A>,
+}
+
+impl<A: Algorithm> Transport<A> {
+    /// Creates a new `Transport` from the `CipherState` for sending and
+    /// the one for receiving messages.
+    pub fn new(send: CipherState<A>, recv: CipherState<A>) -> Self {
+        Transport { send, recv }
+    }
+
+    /// Creates the `Transport` of the handshake initiator from the keys
+    /// `c1` and `c2` returned by the `Split()` of the handshake. The
+    /// initiator sends with `c1` and receives with `c2`. Both directions
+    /// use the same nonce `prefix`.
+    pub fn initiator(c1: Key<A>, c2: Key<A>, prefix: &[u8]) -> Result<Self, Invalid> {
+        Ok(Self::new(
+            CipherState::new(c1, prefix)?,
+            CipherState::new(c2, prefix)?,
+        ))
+    }
+
+    /// Creates the `Transport` of the handshake responder from the keys
+    /// `c1` and `c2` returned by the `Split()` of the handshake. The
+    /// responder sends with `c2` and receives with `c1`. Both directions
+    /// use the same nonce `prefix`.
+    pub fn responder(c1: Key<A>, c2: Key<A>, prefix: &[u8]) -> Result<Self, Invalid> {
+        Ok(Self::new(
+            CipherState::new(c2, prefix)?,
+            CipherState::new(c1, prefix)?,
+        ))
+    }
+
+    /// Returns the `CipherState` for messages sent to the peer.
+    #[inline]
+    pub fn send(&mut self) -> &mut CipherState<A> {
+        &mut self.send
+    }
+
+    /// Returns the `CipherState` for messages received from the peer.
+    #[inline]
+    pub fn recv(&mut self) -> &mut CipherState<A> {
+        &mut self.recv
+    }
+
+    /// Splits the `Transport` into the `CipherState` for sending and the
+    /// one for receiving messages - e.g. to use them on different threads.
+    pub fn split(self) -> (CipherState<A>, CipherState<A>) {
+        (self.send, self.recv)
+    }
+}
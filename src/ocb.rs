@@ -0,0 +1,229 @@
+// Copyright (c) 2019 Andreas Auernhammer. All rights reserved.
+// Use of this source code is governed by a license that can be
+// found in the LICENSE file.
+
+extern crate aes;
+
+use crate::aead::Algorithm;
+use crate::error::{Invalid, NotAuthentic};
+use aes::cipher::{BlockDecrypt, BlockEncrypt, KeyInit};
+use aes::{Aes256, Block};
+
+/// AES-256 in OCB3 mode as specified by RFC 7253 with 96 bit nonces and
+/// 128 bit tags.
+///
+/// OCB authenticates the plaintext with the same AES invocations that
+/// encrypt it. Hence, it requires roughly one AES invocation per block and
+/// no carry-less multiplication - which makes it considerably faster than
+/// AES-GCM on CPUs that support AES instructions but not `PCLMULQDQ`.
+///
+/// It uses the `aes` crate, which selects AES-NI or ARMv8 instructions at
+/// runtime and falls back to a constant-time software implementation.
+#[allow(non_camel_case_types)]
+pub struct AES_256_OCB {
+    cipher: Aes256,
+    l_star: u128,
+    l_dollar: u128,
+    /// `L_i` for the number of trailing zeros `i` of any block index.
+    l: [u128; 64],
+}
+
+/// The number of blocks passed to the cipher at once such that it can
+/// pipeline the AES invocations.
+const PAR: usize = 8;
+
+impl Algorithm for AES_256_OCB {
+    const KEY_LEN: usize = 256 / 8;
+    const NONCE_LEN: usize = 96 / 8;
+    const TAG_LEN: usize = 128 / 8;
+
+    fn new(key: &[u8; Self::KEY_LEN]) -> Self {
+        let cipher = Aes256::new(key.into());
+        let mut ocb = AES_256_OCB {
+            cipher,
+            l_star: 0,
+            l_dollar: 0,
+            l: [0; 64],
+        };
+        ocb.l_star = ocb.encrypt(0);
+        ocb.l_dollar = double(ocb.l_star);
+        let mut l = ocb.l_dollar;
+        for l_i in ocb.l.iter_mut() {
+            l = double(l);
+            *l_i = l;
+        }
+        ocb
+    }
+
+    fn seal_in_place<'a>(
+        &self,
+        nonce: &[u8],
+        aad: &[u8],
+        in_out: &'a mut [u8],
+    ) -> Result<&'a [u8], Invalid> {
+        if nonce.len() != Self::NONCE_LEN {
+            return Err(Invalid::Nonce);
+        }
+        if in_out.len() < Self::TAG_LEN {
+            return Err(Invalid::BufSize);
+        }
+        let (msg, tag) = in_out.split_at_mut(in_out.len() - Self::TAG_LEN);
+        let t = self.crypt(nonce, aad, msg, false);
+        tag.copy_from_slice(&t.to_be_bytes());
+        Ok(in_out)
+    }
+
+    fn open_in_place<'a>(
+        &self,
+        nonce: &[u8],
+        aad: &[u8],
+        in_out: &'a mut [u8],
+    ) -> Result<&'a [u8], NotAuthentic> {
+        if nonce.len() != Self::NONCE_LEN || in_out.len() < Self::TAG_LEN {
+            return Err(NotAuthentic);
+        }
+        let (msg, tag) = in_out.split_at_mut(in_out.len() - Self::TAG_LEN);
+        let t = self.crypt(nonce, aad, msg, true).to_be_bytes();
+        let diff = t.iter().zip(tag.iter()).fold(0, |d, (x, y)| d | (x ^ y));
+        if diff != 0 {
+            // Never reveal the plaintext of a fragment that is not authentic.
+            for b in msg.iter_mut() {
+                *b = 0;
+            }
+            return Err(NotAuthentic);
+        }
+        Ok(msg)
+    }
+}
+
+impl AES_256_OCB {
+    #[inline]
+    fn encrypt(&self, x: u128) -> u128 {
+        let mut block = Block::from(x.to_be_bytes());
+        self.cipher.encrypt_block(&mut block);
+        u128::from_be_bytes(block.into())
+    }
+
+    /// Returns `L_ntz(i)` - the value added to the offset of block `i`.
+    #[inline]
+    fn l(&self, i: usize) -> u128 {
+        self.l[i.trailing_zeros() as usize]
+    }
+
+    /// Returns the initial offset derived from the nonce.
+    fn offset(&self, nonce: &[u8]) -> u128 {
+        let mut block = [0; 16];
+        block[3] = 1; // TAGLEN mod 128 = 0, zero padding and a single 1 bit.
+        block[4..].copy_from_slice(nonce);
+        let bottom = u32::from(block[15] & 0x3f);
+        block[15] &= 0xc0;
+
+        let top = self.encrypt(u128::from_be_bytes(block));
+        let stretch = ((top >> 64) as u64) ^ ((top >> 56) as u64);
+        if bottom == 0 {
+            top
+        } else {
+            (top << bottom) | (u128::from(stretch) >> (64 - bottom))
+        }
+    }
+
+    /// En/decrypts `msg` in place and returns the tag.
+    fn crypt(&self, nonce: &[u8], aad: &[u8], msg: &mut [u8], decrypt: bool) -> u128 {
+        let mut offset = self.offset(nonce);
+        let mut checksum = 0;
+        let (full, partial) = msg.split_at_mut(msg.len() - msg.len() % 16);
+
+        let mut i = 0;
+        for batch in full.chunks_mut(16 * PAR) {
+            let mut offsets = [0; PAR];
+            let mut blocks = [Block::default(); PAR];
+            let n = batch.len() / 16;
+            for k in 0..n {
+                i += 1;
+                offset ^= self.l(i);
+                offsets[k] = offset;
+                let x = read(&batch[16 * k..]);
+                if !decrypt {
+                    checksum ^= x;
+                }
+                blocks[k] = Block::from((x ^ offset).to_be_bytes());
+            }
+            if decrypt {
+                self.cipher.decrypt_blocks(&mut blocks[..n]);
+            } else {
+                self.cipher.encrypt_blocks(&mut blocks[..n]);
+            }
+            for k in 0..n {
+                let y = u128::from_be_bytes(blocks[k].into()) ^ offsets[k];
+                if decrypt {
+                    checksum ^= y;
+                }
+                batch[16 * k..16 * (k + 1)].copy_from_slice(&y.to_be_bytes());
+            }
+        }
+        if !partial.is_empty() {
+            offset ^= self.l_star;
+            let pad = self.encrypt(offset).to_be_bytes();
+            if !decrypt {
+                checksum ^= padded(partial);
+            }
+            for (b, p) in partial.iter_mut().zip(pad.iter()) {
+                *b ^= p;
+            }
+            if decrypt {
+                checksum ^= padded(partial);
+            }
+        }
+        self.encrypt(checksum ^ offset ^ self.l_dollar) ^ self.hash(aad)
+    }
+
+    /// Computes `HASH(K, A)` of the associated data.
+    fn hash(&self, aad: &[u8]) -> u128 {
+        let mut offset = 0;
+        let mut sum = 0;
+        let (full, partial) = aad.split_at(aad.len() - aad.len() % 16);
+
+        let mut i = 0;
+        for batch in full.chunks(16 * PAR) {
+            let mut blocks = [Block::default(); PAR];
+            let n = batch.len() / 16;
+            for (k, block) in blocks[..n].iter_mut().enumerate() {
+                i += 1;
+                offset ^= self.l(i);
+                *block = Block::from((read(&batch[16 * k..]) ^ offset).to_be_bytes());
+            }
+            self.cipher.encrypt_blocks(&mut blocks[..n]);
+            for block in &blocks[..n] {
+                sum ^= u128::from_be_bytes((*block).into());
+            }
+        }
+        if !partial.is_empty() {
+            offset ^= self.l_star;
+            sum ^= self.encrypt(padded(partial) ^ offset);
+        }
+        sum
+    }
+}
+
+/// Reads the first 16 bytes of `b` as big-endian block.
+#[inline]
+fn read(b: &[u8]) -> u128 {
+    let mut block = [0; 16];
+    block.copy_from_slice(&b[..16]);
+    u128::from_be_bytes(block)
+}
+
+/// Pads a partial block with a single 1 bit and zeros.
+#[inline]
+fn padded(b: &[u8]) -> u128 {
+    let mut block = [0; 16];
+    block[..b.len()].copy_from_slice(b);
+    block[b.len()] = 0x80;
+    u128::from_be_bytes(block)
+}
+
+/// Multiplies `x` by two in GF(2^128) without branching on secret data.
+#[inline]
+fn double(x: u128) -> u128 {
+    (x << 1) ^ ((x >> 127) * 0x87)
+}
@@ -0,0 +1,266 @@
+// Copyright (c) 2019 Andreas Auernhammer. All rights reserved.
+// Use of this source code is governed by a license that can be
+// found in the LICENSE file.
+
+use super::aead::Counter;
+use super::{
+    Aad, Algorithm, DecWriter, EncWriter, Invalid, Key, Layout, Nonce, NotAuthentic, BUF_SIZE,
+    MAX_BUF_SIZE,
+};
+use std::io::{self, Write};
+
+/// The largest tag length supported by the one-shot functions.
+pub(crate) const MAX_TAG_LEN: usize = 64;
+
+/// Calls `f` with the nonce and associated data of the final fragment of
+/// a `Sio` stream that consists of this fragment only.
+///
+/// It computes them like the `Sio` framing does but without allocating.
+pub(crate) fn final_fragment<A: Algorithm, T>(
+    algorithm: &A,
+    nonce: Nonce<A>,
+    aad: Aad<A>,
+    f: impl FnOnce(&[u8], &[u8]) -> T,
+) -> Result<T, Invalid> {
+    if A::TAG_LEN > MAX_TAG_LEN {
+        return Err(Invalid::BufSize);
+    }
+    let mut counter = Counter::zero(nonce.as_ref());
+    let mut associated_data = [0; 1 + MAX_TAG_LEN];
+    let associated_data = &mut associated_data[..1 + A::TAG_LEN];
+    algorithm.seal_in_place(
+        counter.next().map_err(|_| Invalid::Nonce)?,
+        aad.as_ref(),
+        &mut associated_data[1..],
+    )?;
+    associated_data[0] = 0x80;
+    Ok(f(
+        counter.next().map_err(|_| Invalid::Nonce)?,
+        associated_data,
+    ))
+}
+
+/// Encrypts and authenticates the `plaintext` as a stream consisting of
+/// one final fragment and returns the ciphertext.
+///
+/// `seal_one` is a fast path for small blobs. It produces exactly the same
+/// ciphertext as an `EncWriter` with the default `Sio` framing and any
+/// buffer size that is at least as large as the `plaintext` - without
+/// constructing a writer. Hence, the ciphertext can be decrypted by a
+/// `DecWriter` or `DecReader` with such a buffer size - e.g. the default
+/// one for plaintexts up to 16 KiB - or by `open_one`.
+///
+/// The `plaintext` must not be larger than `MAX_BUF_SIZE`.
+///
+/// # Examples
+///
+/// ```
+/// use sio::{Key, Nonce, Aad, CHACHA20_POLY1305};
+///
+/// // Obviously, don't use this all-zeros key for anything real.
+/// let key: Key<CHACHA20_POLY1305> = Key::new([0; Key::<CHACHA20_POLY1305>::SIZE]);
+///
+/// let ciphertext = sio::seal_one(
+///     &key,
+///     Nonce::new([0; Nonce::<CHACHA20_POLY1305>::SIZE]),
+///     Aad::empty(),
+///     b"Hello World",
+/// )
+/// .unwrap();
+///
+/// let plaintext = sio::open_one(
+///     &key,
+///     Nonce::new([0; Nonce::<CHACHA20_POLY1305>::SIZE]),
+///     Aad::empty(),
+///     &ciphertext,
+/// )
+/// .unwrap();
+/// assert_eq!(plaintext, b"Hello World");
+/// ```
+pub fn seal_one<A: Algorithm>(
+    key: &Key<A>,
+    nonce: Nonce<A>,
+    aad: Aad<A>,
+    plaintext: &[u8],
+) -> Result<Vec<u8>, Invalid> {
+    if plaintext.len() > MAX_BUF_SIZE {
+        return Err(Invalid::BufSize);
+    }
+    let mut ciphertext = vec![0; plaintext.len() + A::TAG_LEN];
+    ciphertext[..plaintext.len()].copy_from_slice(plaintext);
+    seal_one_in_place(key, nonce, aad, &mut ciphertext)?;
+    Ok(ciphertext)
+}
+
+/// Decrypts and verifies a stream consisting of one final fragment - e.g.
+/// produced by `seal_one` - and returns the plaintext.
+///
+/// Any stream with a different layout - in particular a stream with more
+/// than one fragment - is rejected as not authentic.
+pub fn open_one<A: Algorithm>(
+    key: &Key<A>,
+    nonce: Nonce<A>,
+    aad: Aad<A>,
+    ciphertext: &[u8],
+) -> Result<Vec<u8>, NotAuthentic> {
+    let mut plaintext = ciphertext.to_vec();
+    let len = open_one_in_place(key, nonce, aad, &mut plaintext)?.len();
+    plaintext.truncate(len);
+    Ok(plaintext)
+}
+
+/// Encrypts and authenticates the plaintext in `in_out` in place like
+/// `seal_one` - but without allocating.
+///
+/// The plaintext is followed by `A::TAG_LEN` bytes in `in_out` that get
+/// overwritten with the tag. Hence, `in_out` contains the ciphertext of
+/// a stream consisting of one final fragment once `seal_one_in_place`
+/// returns successfully. Tiny records - e.g. session tokens or queue
+/// messages - can be protected without any heap usage while staying
+/// compatible with the streaming writers.
+///
+/// The plaintext must not be larger than `MAX_BUF_SIZE`.
+///
+/// # Examples
+///
+/// ```
+/// use sio::{Key, Nonce, Aad, Algorithm, CHACHA20_POLY1305};
+///
+/// // Obviously, don't use this all-zeros key for anything real.
+/// let key: Key<CHACHA20_POLY1305> = Key::new([0; Key::<CHACHA20_POLY1305>::SIZE]);
+///
+/// let mut record = [0; 11 + CHACHA20_POLY1305::TAG_LEN];
+/// record[..11].copy_from_slice(b"Hello World");
+/// sio::seal_one_in_place(
+///     &key,
+///     Nonce::new([0; Nonce::<CHACHA20_POLY1305>::SIZE]),
+///     Aad::empty(),
+///     &mut record,
+/// )
+/// .unwrap();
+///
+/// let plaintext = sio::open_one_in_place(
+///     &key,
+///     Nonce::new([0; Nonce::<CHACHA20_POLY1305>::SIZE]),
+///     Aad::empty(),
+///     &mut record,
+/// )
+/// .unwrap();
+/// assert_eq!(plaintext, b"Hello World");
+/// ```
+pub fn seal_one_in_place<A: Algorithm>(
+    key: &Key<A>,
+    nonce: Nonce<A>,
+    aad: Aad<A>,
+    in_out: &mut [u8],
+) -> Result<(), Invalid> {
+    if in_out.len() < A::TAG_LEN || in_out.len() - A::TAG_LEN > MAX_BUF_SIZE {
+        return Err(Invalid::BufSize);
+    }
+    let algorithm = A::new(key.as_ref());
+    final_fragment(&algorithm, nonce, aad, |nonce, aad| {
+        algorithm.seal_in_place(nonce, aad, in_out).map(|_| ())
+    })?
+}
+
+/// Decrypts and verifies the stream consisting of one final fragment in
+/// `in_out` in place like `open_one` - but without allocating. It returns
+/// the plaintext, which is a prefix of `in_out`.
+pub fn open_one_in_place<'a, A: Algorithm>(
+    key: &Key<A>,
+    nonce: Nonce<A>,
+    aad: Aad<A>,
+    in_out: &'a mut [u8],
+) -> Result<&'a [u8], NotAuthentic> {
+    if in_out.len() < A::TAG_LEN {
+        return Err(NotAuthentic);
+    }
+    let algorithm = &A::new(key.as_ref());
+    final_fragment(algorithm, nonce, aad, move |nonce, aad| {
+        algorithm.open_in_place(nonce, aad, in_out)
+    })
+    .map_err(|_| NotAuthentic)?
+}
+
+/// Encrypts and authenticates the `plaintext` with the default `Sio`
+/// framing and buffer size and returns the ciphertext.
+///
+/// `encrypt_vec` is a convenience for data held in memory. It produces
+/// exactly the same ciphertext as an `EncWriter` with the default buffer
+/// size. Hence, the ciphertext can be decrypted by `decrypt_vec` as well
+/// as by a `DecWriter` or `DecReader`.
+///
+/// # Examples
+///
+/// ```
+/// use sio::{Key, Nonce, Aad, CHACHA20_POLY1305};
+///
+/// // Obviously, don't use this all-zeros key for anything real.
+/// let key: Key<CHACHA20_POLY1305> = Key::new([0; Key::<CHACHA20_POLY1305>::SIZE]);
+///
+/// let ciphertext = sio::encrypt_vec(
+///     &key,
+///     Nonce::new([0; Nonce::<CHACHA20_POLY1305>::SIZE]),
+///     Aad::empty(),
+///     "Hello World".as_bytes(),
+/// );
+///
+/// let plaintext = sio::decrypt_string(
+///     &key,
+///     Nonce::new([0; Nonce::<CHACHA20_POLY1305>::SIZE]),
+///     Aad::empty(),
+///     &ciphertext,
+/// )
+/// .unwrap();
+/// assert_eq!(plaintext, "Hello World");
+/// ```
+pub fn encrypt_vec<A: Algorithm>(
+    key: &Key<A>,
+    nonce: Nonce<A>,
+    aad: Aad<A>,
+    plaintext: &[u8],
+) -> Vec<u8> {
+    let capacity = Layout::of::<A>(BUF_SIZE)
+        .ok()
+        .and_then(|layout| layout.ciphertext_len(plaintext.len() as u64))
+        .unwrap_or_default();
+    let mut ciphertext = Vec::with_capacity(capacity as usize);
+    let mut writer = EncWriter::new(&mut ciphertext, key, nonce, aad);
+
+    // Writing to a Vec cannot fail and an in-memory plaintext cannot
+    // exceed the data limit of the stream.
+    writer.write_all(plaintext).unwrap();
+    writer.close().unwrap();
+    ciphertext
+}
+
+/// Decrypts and verifies the `ciphertext` produced with the default `Sio`
+/// framing and buffer size - e.g. by `encrypt_vec` - and returns the
+/// plaintext.
+pub fn decrypt_vec<A: Algorithm>(
+    key: &Key<A>,
+    nonce: Nonce<A>,
+    aad: Aad<A>,
+    ciphertext: &[u8],
+) -> io::Result<Vec<u8>> {
+    let mut plaintext = Vec::with_capacity(ciphertext.len());
+    let mut writer = DecWriter::new(&mut plaintext, key, nonce, aad);
+    writer.write_all(ciphertext)?;
+    writer.close()?;
+    Ok(plaintext)
+}
+
+/// Decrypts and verifies the `ciphertext` like `decrypt_vec` and returns
+/// the plaintext as `String`.
+///
+/// A plaintext that is not valid UTF-8 is rejected with an error of
+/// kind `InvalidData`.
+pub fn decrypt_string<A: Algorithm>(
+    key: &Key<A>,
+    nonce: Nonce<A>,
+    aad: Aad<A>,
+    ciphertext: &[u8],
+) -> io::Result<String> {
+    let plaintext = decrypt_vec(key, nonce, aad, ciphertext)?;
+    String::from_utf8(plaintext).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
@@ -0,0 +1,130 @@
+// Copyright (c) 2019 Andreas Auernhammer. All rights reserved.
+// Use of this source code is governed by a license that can be
+// found in the LICENSE file.
+
+use super::framing::{Framing, Sio};
+use super::{Aad, Algorithm, Invalid, Key, Nonce, NotAuthentic, MAX_BUF_SIZE};
+use std::io::{self, Write};
+use std::thread;
+
+/// The number of fragments each thread decrypts per batch.
+const FRAGMENTS_PER_THREAD: usize = 16;
+
+/// Decrypts the entire `ciphertext` - e.g. a memory-mapped file - using
+/// up to `threads` threads and writes the plaintext to `inner`.
+///
+/// The `ciphertext` must have been produced by an `EncWriter` with the
+/// default `Sio` framing and the given buffer size. It is decrypted in
+/// batches of fragments. The fragments of a batch are verified
+/// concurrently and the plaintext is written to `inner` in order
+/// once the entire batch has been verified.
+///
+/// Like a `DecWriter`, `decrypt_parallel` may write the plaintext of
+/// some batches to `inner` before it detects that a later fragment is
+/// not authentic.
+///
+/// # Examples
+///
+/// ```
+/// use std::io::Write;
+/// use sio::{Key, Nonce, Aad, EncWriter, CHACHA20_POLY1305};
+///
+/// // Obviously, don't use this all-zeros key for anything real.
+/// let key: Key<CHACHA20_POLY1305> = Key::new([0; Key::<CHACHA20_POLY1305>::SIZE]);
+///
+/// let data = vec![0; 1 << 20];
+/// let mut ciphertext: Vec<u8> = Vec::default();
+/// let mut writer = EncWriter::new(
+///     &mut ciphertext,
+///     &key,
+///     Nonce::new([0; Nonce::<CHACHA20_POLY1305>::SIZE]),
+///     Aad::empty(),
+/// );
+/// writer.write_all(data.as_slice()).unwrap();
+/// writer.close().unwrap();
+///
+/// let mut plaintext: Vec<u8> = Vec::default();
+/// sio::decrypt_parallel(
+///     &mut plaintext,
+///     &key,
+///     Nonce::new([0; Nonce::<CHACHA20_POLY1305>::SIZE]),
+///     Aad::empty(),
+///     ciphertext.as_slice(),
+///     sio::BUF_SIZE,
+///     4,
+/// )
+/// .unwrap();
+///
+/// assert_eq!(plaintext, data);
+/// ```
+pub fn decrypt_parallel<A: Algorithm + Sync, W: Write>(
+    mut inner: W,
+    key: &Key<A>,
+    nonce: Nonce<A>,
+    aad: Aad<A>,
+    ciphertext: &[u8],
+    buf_size: usize,
+    threads: usize,
+) -> io::Result<()> {
+    if buf_size == 0 || buf_size > MAX_BUF_SIZE {
+        return Err(Invalid::BufSize.into());
+    }
+    let mut algorithm = A::new(key.as_ref());
+    let mut framing = Sio::default();
+    framing.init(key.as_ref(), &algorithm, nonce.as_ref(), aad.as_ref())?;
+
+    // Even an empty stream consists of one (final) fragment.
+    let fragment_len = buf_size + A::TAG_LEN;
+    let fragments = ciphertext.len().div_ceil(fragment_len).max(1);
+    if ciphertext.len() - (fragments - 1) * fragment_len < A::TAG_LEN {
+        return Err(NotAuthentic.into());
+    }
+
+    let threads = threads.max(1);
+    let mut buffer = Vec::with_capacity(threads * FRAGMENTS_PER_THREAD * fragment_len);
+    let mut params = Vec::with_capacity(threads * FRAGMENTS_PER_THREAD);
+    let mut index = 0;
+    for batch in ciphertext.chunks(threads * FRAGMENTS_PER_THREAD * fragment_len) {
+        // The nonce and associated data of all fragments are computed
+        // upfront since the framing is sequential.
+        params.clear();
+        for _ in batch.chunks(fragment_len) {
+            index += 1;
+            let (nonce, aad) = framing.next(&mut algorithm, index == fragments)?;
+            params.push((nonce.to_vec(), aad.to_vec()));
+        }
+
+        buffer.clear();
+        buffer.extend_from_slice(batch);
+        let chunk_len = batch.len().div_ceil(threads).div_ceil(fragment_len) * fragment_len;
+        let (algorithm, params) = (&algorithm, &params);
+        let authentic = thread::scope(|scope| {
+            let workers: Vec<_> = buffer
+                .chunks_mut(chunk_len)
+                .enumerate()
+                .map(|(i, chunk)| {
+                    scope.spawn(move || {
+                        let first = i * chunk_len / fragment_len;
+                        chunk
+                            .chunks_mut(fragment_len)
+                            .enumerate()
+                            .all(|(j, fragment)| {
+                                let (ref nonce, ref aad) = params[first + j];
+                                algorithm.open_in_place(nonce, aad, fragment).is_ok()
+                            })
+                    })
+                })
+                .collect();
+            workers
+                .into_iter()
+                .all(|worker| worker.join().unwrap_or(false))
+        });
+        if !authentic {
+            return Err(NotAuthentic.into());
+        }
+        for fragment in buffer.chunks(fragment_len) {
+            inner.write_all(&fragment[..fragment.len() - A::TAG_LEN])?;
+        }
+    }
+    inner.flush()
+}
@@ -0,0 +1,232 @@
+// Copyright (c) 2019 Andreas Auernhammer. All rights reserved.
+// Use of this source code is governed by a license that can be
+// found in the LICENSE file.
+
+use super::framing::Version;
+use super::writer::Close;
+use super::{Aad, Algorithm, DecReader, EncWriter, Invalid, Key, Nonce, UnsupportedVersion};
+use ring::rand::{SecureRandom, SystemRandom};
+use std::convert::TryInto;
+use std::io::{self, Read, Write};
+
+/// The format byte of password-encrypted streams. It follows the
+/// `Version::MAGIC` bytes.
+const FORMAT: u8 = 0x70;
+
+/// The identifier of Argon2id (version 0x13).
+const ARGON2ID: u8 = 0x01;
+
+const SALT_LEN: usize = 16;
+
+/// The size of the header of a password-encrypted stream.
+const HEADER_LEN: usize = Version::MAGIC.len() + 1 + 1 + 3 * 4 + SALT_LEN;
+
+/// The Argon2id parameters used to derive the key of a password-encrypted
+/// stream from the passphrase.
+///
+/// The parameters are stored in the header of the stream. Hence, they can
+/// be raised over time without breaking existing streams. However, the
+/// decrypting party rejects streams whose parameters exceed `MAX` - e.g.
+/// when an attacker tries to exhaust its memory.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PasswordParams {
+    memory: u32,
+    iterations: u32,
+    parallelism: u32,
+}
+
+impl PasswordParams {
+    /// The largest parameters accepted when decrypting a stream:
+    /// 1 GiB of memory, 64 iterations and 16 lanes.
+    pub const MAX: PasswordParams = PasswordParams {
+        memory: 1 << 20,
+        iterations: 64,
+        parallelism: 16,
+    };
+
+    /// Returns new Argon2id parameters using `memory` KiB of memory,
+    /// `iterations` passes over the memory and `parallelism` lanes.
+    pub fn new(memory: u32, iterations: u32, parallelism: u32) -> Result<Self, Invalid> {
+        let params = PasswordParams {
+            memory,
+            iterations,
+            parallelism,
+        };
+        params.argon2()?;
+        Ok(params)
+    }
+
+    /// Returns the amount of memory in KiB.
+    #[inline]
+    pub fn memory(&self) -> u32 {
+        self.memory
+    }
+
+    /// Returns the number of iterations.
+    #[inline]
+    pub fn iterations(&self) -> u32 {
+        self.iterations
+    }
+
+    /// Returns the number of lanes.
+    #[inline]
+    pub fn parallelism(&self) -> u32 {
+        self.parallelism
+    }
+
+    fn argon2(&self) -> Result<argon2::Argon2<'static>, Invalid> {
+        let params = argon2::Params::new(self.memory, self.iterations, self.parallelism, Some(32))
+            .map_err(|_| Invalid::Key)?;
+        Ok(argon2::Argon2::new(
+            argon2::Algorithm::Argon2id,
+            argon2::Version::V0x13,
+            params,
+        ))
+    }
+
+    fn exceeds(&self, max: &PasswordParams) -> bool {
+        self.memory > max.memory
+            || self.iterations > max.iterations
+            || self.parallelism > max.parallelism
+    }
+}
+
+impl Default for PasswordParams {
+    /// Returns 19 MiB of memory, 2 iterations and 1 lane - as
+    /// recommended by OWASP.
+    fn default() -> Self {
+        PasswordParams {
+            memory: 19 * 1024,
+            iterations: 2,
+            parallelism: 1,
+        }
+    }
+}
+
+/// Derives the key of a stream from the `passphrase` and the
+/// parameters and salt within the `header`.
+fn derive_key<A: Algorithm>(
+    passphrase: &[u8],
+    params: &PasswordParams,
+    header: &[u8; HEADER_LEN],
+) -> Result<Key<A>, Invalid> {
+    let mut key = [0; 32];
+    params
+        .argon2()?
+        .hash_password_into(passphrase, &header[HEADER_LEN - SALT_LEN..], &mut key)
+        .map_err(|_| Invalid::Key)?;
+    Ok(Key::new(key))
+}
+
+impl<A: Algorithm, W: Write + Close> EncWriter<A, W> {
+    /// Creates a new `EncWriter` that encrypts a stream with a key derived
+    /// from the `passphrase` using Argon2id with the default parameters.
+    ///
+    /// It picks a random salt and writes a header containing the Argon2id
+    /// parameters and the salt to the `inner` writer immediately. The
+    /// header is authenticated as associated data of the stream.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::{Read, Write};
+    /// use sio::{EncWriter, DecReader, CHACHA20_POLY1305};
+    ///
+    /// let mut ciphertext: Vec<u8> = Vec::default();
+    /// let mut writer =
+    ///     EncWriter::<CHACHA20_POLY1305, _>::with_password(&mut ciphertext, b"secret").unwrap();
+    /// writer.write_all(b"Hello World").unwrap();
+    /// writer.close().unwrap();
+    ///
+    /// let mut reader =
+    ///     DecReader::<CHACHA20_POLY1305, _>::with_password(ciphertext.as_slice(), b"secret").unwrap();
+    /// let mut plaintext = String::default();
+    /// reader.read_to_string(&mut plaintext).unwrap();
+    ///
+    /// assert_eq!(plaintext, "Hello World");
+    /// ```
+    pub fn with_password(inner: W, passphrase: &[u8]) -> io::Result<Self> {
+        Self::with_password_params(inner, passphrase, PasswordParams::default())
+    }
+
+    /// Creates a new `EncWriter` that encrypts a stream with a key derived
+    /// from the `passphrase` using Argon2id with the given `params`.
+    pub fn with_password_params(
+        mut inner: W,
+        passphrase: &[u8],
+        params: PasswordParams,
+    ) -> io::Result<Self> {
+        let mut header = [0; HEADER_LEN];
+        let magic = Version::MAGIC.len();
+        header[..magic].copy_from_slice(&Version::MAGIC);
+        header[magic] = FORMAT;
+        header[magic + 1] = ARGON2ID;
+        header[magic + 2..magic + 6].copy_from_slice(&params.memory.to_be_bytes());
+        header[magic + 6..magic + 10].copy_from_slice(&params.iterations.to_be_bytes());
+        header[magic + 10..magic + 14].copy_from_slice(&params.parallelism.to_be_bytes());
+        SystemRandom::new()
+            .fill(&mut header[HEADER_LEN - SALT_LEN..])
+            .map_err(|_| Invalid::Key)?;
+
+        let key = derive_key(passphrase, &params, &header)?;
+        inner.write_all(&header)?;
+        Ok(EncWriter::new(
+            inner,
+            &key,
+            Nonce::zero(),
+            Aad::from(&header[..]),
+        ))
+    }
+}
+
+impl<A: Algorithm, R: Read> DecReader<A, R> {
+    /// Creates a new `DecReader` that decrypts a stream encrypted with a
+    /// key derived from the `passphrase` - e.g. by
+    /// `EncWriter::with_password`.
+    ///
+    /// It reads the header of the stream from the `inner` reader
+    /// immediately. Streams whose Argon2id parameters exceed
+    /// `PasswordParams::MAX` are rejected.
+    pub fn with_password(inner: R, passphrase: &[u8]) -> io::Result<Self> {
+        Self::with_password_limit(inner, passphrase, PasswordParams::MAX)
+    }
+
+    /// Creates a new `DecReader` that decrypts a stream encrypted with a
+    /// key derived from the `passphrase`. Streams whose Argon2id parameters
+    /// exceed `max` are rejected.
+    pub fn with_password_limit(
+        mut inner: R,
+        passphrase: &[u8],
+        max: PasswordParams,
+    ) -> io::Result<Self> {
+        let mut header = [0; HEADER_LEN];
+        inner.read_exact(&mut header)?;
+
+        let magic = Version::MAGIC.len();
+        if header[..magic] != Version::MAGIC
+            || header[magic] != FORMAT
+            || header[magic + 1] != ARGON2ID
+        {
+            return Err(UnsupportedVersion.into());
+        }
+        let params = PasswordParams {
+            memory: u32::from_be_bytes(header[magic + 2..magic + 6].try_into().unwrap()),
+            iterations: u32::from_be_bytes(header[magic + 6..magic + 10].try_into().unwrap()),
+            parallelism: u32::from_be_bytes(header[magic + 10..magic + 14].try_into().unwrap()),
+        };
+        if params.exceeds(&max) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "password parameters exceed the limit",
+            ));
+        }
+
+        let key = derive_key(passphrase, &params, &header)?;
+        Ok(DecReader::new(
+            inner,
+            &key,
+            Nonce::zero(),
+            Aad::from(&header[..]),
+        ))
+    }
+}
@@ -0,0 +1,178 @@
+// Copyright (c) 2019 Andreas Auernhammer. All rights reserved.
+// Use of this source code is governed by a license that can be
+// found in the LICENSE file.
+
+//! In-memory pipes that encrypt or decrypt the data passing through them.
+//!
+//! `EncWriter` and `DecWriter` turn a writer into a writer. Some APIs
+//! demand the other side of the `Read`/`Write` duality, though - e.g. an
+//! upload API that reads the ciphertext from a `Read` while the plaintext
+//! is produced by code that writes to a `Write`. A pipe connects both
+//! sides: Everything written to the writer end of `encrypted` can be read
+//! as ciphertext from its reader end. `decrypted` is the reverse - it
+//! turns written ciphertext into readable plaintext.
+//!
+//! The writer and the reader end are usually used by different threads.
+//! The pipe buffers at most `CAPACITY` chunks. Once it is full, writes
+//! block until the reader catches up. The reader reaches the end of the
+//! stream once the writer end has been closed successfully. If the writer
+//! end is dropped without being closed - e.g. because the ciphertext is
+//! not authentic - the reader fails with an error of kind `UnexpectedEof`.
+//! If the reader end is dropped, writes fail with `BrokenPipe`.
+//!
+//! # Examples
+//!
+//! ```
+//! use std::io::{Read, Write};
+//! use std::thread;
+//! use sio::{Key, Nonce, Aad, CHACHA20_POLY1305};
+//! use sio::pipe;
+//!
+//! // Obviously, don't use this all-zeros key for anything real.
+//! let key: Key<CHACHA20_POLY1305> = Key::new([0; Key::<CHACHA20_POLY1305>::SIZE]);
+//!
+//! let (mut writer, mut reader) = pipe::encrypted(
+//!     &key,
+//!     Nonce::new([0; Nonce::<CHACHA20_POLY1305>::SIZE]),
+//!     Aad::empty(),
+//! );
+//! let producer = thread::spawn(move || {
+//!     writer.write_all(b"Hello World")?;
+//!     writer.close()
+//! });
+//!
+//! let mut ciphertext = Vec::default();
+//! reader.read_to_end(&mut ciphertext).unwrap();
+//! producer.join().unwrap().unwrap();
+//!
+//! let plaintext = sio::decrypt_vec(
+//!     &key,
+//!     Nonce::new([0; Nonce::<CHACHA20_POLY1305>::SIZE]),
+//!     Aad::empty(),
+//!     &ciphertext,
+//! )
+//! .unwrap();
+//! assert_eq!(plaintext, b"Hello World");
+//! ```
+
+use super::{Aad, Algorithm, Close, DecWriter, EncWriter, Key, Nonce};
+use std::io::{self, Read, Write};
+use std::sync::mpsc::{self, Receiver, SyncSender};
+
+/// The maximum number of chunks buffered by a pipe.
+pub const CAPACITY: usize = 16;
+
+/// Returns a pipe that encrypts and authenticates everything written to its
+/// writer end. The ciphertext can be read from its reader end.
+///
+/// The writer end is an `EncWriter` with the default buffer size. It must
+/// be closed to complete the ciphertext.
+pub fn encrypted<A: Algorithm>(
+    key: &Key<A>,
+    nonce: Nonce<A>,
+    aad: Aad<A>,
+) -> (EncWriter<A, PipeWriter>, PipeReader) {
+    let (writer, reader) = new();
+    (EncWriter::new(writer, key, nonce, aad), reader)
+}
+
+/// Returns a pipe that decrypts and verifies everything written to its
+/// writer end. The plaintext can be read from its reader end.
+///
+/// The writer end is a `DecWriter` with the default buffer size. It must
+/// be closed to verify the end of the ciphertext. The reader only reaches
+/// the end of the plaintext once the entire ciphertext has been verified.
+pub fn decrypted<A: Algorithm>(
+    key: &Key<A>,
+    nonce: Nonce<A>,
+    aad: Aad<A>,
+) -> (DecWriter<A, PipeWriter>, PipeReader) {
+    let (writer, reader) = new();
+    (DecWriter::new(writer, key, nonce, aad), reader)
+}
+
+/// Returns a new plain pipe that passes written data to the reader end.
+fn new() -> (PipeWriter, PipeReader) {
+    let (sender, receiver) = mpsc::sync_channel(CAPACITY);
+    (
+        PipeWriter { sender },
+        PipeReader {
+            receiver,
+            buffer: Vec::default(),
+            pos: 0,
+            closed: false,
+        },
+    )
+}
+
+enum Message {
+    Data(Vec<u8>),
+    Close,
+}
+
+/// The writer end of a pipe that passes all data to the `PipeReader`.
+pub struct PipeWriter {
+    sender: SyncSender<Message>,
+}
+
+impl PipeWriter {
+    fn send(&self, message: Message) -> io::Result<()> {
+        self.sender
+            .send(message)
+            .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "pipe reader has been dropped"))
+    }
+}
+
+impl Write for PipeWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if !buf.is_empty() {
+            self.send(Message::Data(buf.to_vec()))?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Close for PipeWriter {
+    fn close(&mut self) -> io::Result<()> {
+        self.send(Message::Close)
+    }
+}
+
+/// The reader end of a pipe.
+pub struct PipeReader {
+    receiver: Receiver<Message>,
+    buffer: Vec<u8>,
+    pos: usize,
+    closed: bool,
+}
+
+impl Read for PipeReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.pos == self.buffer.len() {
+            if self.closed {
+                return Ok(0);
+            }
+            match self.receiver.recv() {
+                Ok(Message::Data(data)) => {
+                    self.buffer = data;
+                    self.pos = 0;
+                }
+                Ok(Message::Close) => self.closed = true,
+                Err(_) => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        "pipe writer has been dropped without being closed",
+                    ))
+                }
+            }
+        }
+        let n = buf.len().min(self.buffer.len() - self.pos);
+        buf[..n].copy_from_slice(&self.buffer[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
@@ -0,0 +1,182 @@
+// Copyright (c) 2019 Andreas Auernhammer. All rights reserved.
+// Use of this source code is governed by a license that can be
+// found in the LICENSE file.
+
+use std::sync::{Arc, Mutex, MutexGuard};
+
+/// A source of fragment buffers for `EncWriter`s and `DecWriter`s.
+///
+/// By default, every writer allocates its fragment buffer on the heap.
+/// Applications that want to control where these buffers come from - e.g.
+/// to carve them out of a preallocated arena or to account for their
+/// memory - can create their writers with `with_allocator` instead. Such
+/// a writer obtains its fragment buffer from the `BufferAllocator` when it
+/// is created and releases it once it has been closed or dropped.
+///
+/// A `BufferPool` is a `BufferAllocator` that recycles released buffers.
+///
+/// # Examples
+///
+/// ```
+/// use std::io::Write;
+/// use std::sync::Arc;
+/// use std::sync::atomic::{AtomicUsize, Ordering};
+/// use sio::{Key, Nonce, Aad, BufferAllocator, EncWriter, BUF_SIZE, CHACHA20_POLY1305};
+///
+/// // An allocator that keeps track of the buffers in use.
+/// #[derive(Default)]
+/// struct Counting(AtomicUsize);
+///
+/// impl BufferAllocator for Counting {
+///     fn allocate(&self, len: usize) -> Box<[u8]> {
+///         self.0.fetch_add(1, Ordering::SeqCst);
+///         vec![0; len].into_boxed_slice()
+///     }
+///
+///     fn release(&self, _buffer: Box<[u8]>) {
+///         self.0.fetch_sub(1, Ordering::SeqCst);
+///     }
+/// }
+///
+/// // Obviously, don't use this all-zeros key for anything real.
+/// let key: Key<CHACHA20_POLY1305> = Key::new([0; Key::<CHACHA20_POLY1305>::SIZE]);
+///
+/// let allocator = Arc::new(Counting::default());
+/// let mut writer = EncWriter::with_allocator(
+///     Vec::default(),
+///     &key,
+///     Nonce::new([0; Nonce::<CHACHA20_POLY1305>::SIZE]),
+///     Aad::empty(),
+///     BUF_SIZE,
+///     allocator.clone(),
+/// )
+/// .unwrap();
+/// assert_eq!(allocator.0.load(Ordering::SeqCst), 1);
+///
+/// writer.write_all(b"Hello World").unwrap();
+/// writer.close().unwrap();
+/// assert_eq!(allocator.0.load(Ordering::SeqCst), 0);
+/// ```
+pub trait BufferAllocator: Send + Sync {
+    /// Returns a buffer of exactly `len` bytes. A writer rejects buffers
+    /// of any other size with `Invalid::BufSize`.
+    fn allocate(&self, len: usize) -> Box<[u8]>;
+
+    /// Takes back a buffer returned by `allocate` once the writer using
+    /// it has been closed or dropped. The buffer may contain plaintext.
+    ///
+    /// By default, the buffer is freed.
+    fn release(&self, buffer: Box<[u8]>) {
+        drop(buffer)
+    }
+}
+
+/// A pool of fragment buffers shared by many writers.
+///
+/// Every `EncWriter` and `DecWriter` allocates a buffer that can hold one
+/// fragment. Services that encrypt or decrypt many small objects
+/// concurrently can create their writers with `with_pool` instead. Such
+/// a writer borrows its fragment buffer from the pool and returns it once
+/// it has been closed or dropped. Hence, the buffer can be reused by the
+/// next writer.
+///
+/// A `BufferPool` is a cheap handle to the shared pool. Cloning it does
+/// not copy the buffers and all clones can be used from different threads.
+/// The pool keeps at most `capacity` idle buffers. Any buffer returned to
+/// a full pool is freed. Buffers are zeroed when they are returned to the
+/// pool such that no plaintext lingers in idle buffers.
+///
+/// # Examples
+///
+/// ```
+/// use std::io::Write;
+/// use sio::{Key, Nonce, Aad, BufferPool, EncWriter, BUF_SIZE, CHACHA20_POLY1305};
+///
+/// // Obviously, don't use this all-zeros key for anything real.
+/// let key: Key<CHACHA20_POLY1305> = Key::new([0; Key::<CHACHA20_POLY1305>::SIZE]);
+///
+/// let pool = BufferPool::new(64);
+/// for object in &["Hello", "World"] {
+///     let mut ciphertext: Vec<u8> = Vec::default();
+///     let mut writer = EncWriter::with_pool(
+///         &mut ciphertext,
+///         &key,
+///         Nonce::new([0; Nonce::<CHACHA20_POLY1305>::SIZE]),
+///         Aad::from(object.as_bytes()),
+///         BUF_SIZE,
+///         &pool,
+///     )
+///     .unwrap();
+///     writer.write_all(object.as_bytes()).unwrap();
+///     writer.close().unwrap();
+/// }
+/// assert_eq!(pool.len(), 1);
+/// ```
+#[derive(Clone, Debug)]
+pub struct BufferPool {
+    buffers: Arc<Mutex<Vec<Box<[u8]>>>>,
+    capacity: usize,
+}
+
+impl BufferPool {
+    /// Creates a new empty `BufferPool` that keeps at most `capacity`
+    /// idle buffers.
+    pub fn new(capacity: usize) -> Self {
+        BufferPool {
+            buffers: Arc::new(Mutex::new(Vec::with_capacity(capacity))),
+            capacity,
+        }
+    }
+
+    /// Returns the maximum number of idle buffers kept by the pool.
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Returns the number of idle buffers in the pool.
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    /// Returns true if the pool has no idle buffers.
+    pub fn is_empty(&self) -> bool {
+        self.lock().is_empty()
+    }
+
+    /// Removes all idle buffers from the pool and frees them.
+    pub fn clear(&self) {
+        self.lock().clear()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Vec<Box<[u8]>>> {
+        // The buffers are always in a consistent state. Therefore,
+        // a panic of another thread does not affect the pool.
+        self.buffers.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+impl BufferAllocator for BufferPool {
+    /// Returns an idle buffer of `len` bytes - or a newly allocated one
+    /// if the pool has no such buffer.
+    fn allocate(&self, len: usize) -> Box<[u8]> {
+        let mut buffers = self.lock();
+        match buffers.iter().rposition(|buffer| buffer.len() == len) {
+            Some(i) => buffers.swap_remove(i),
+            None => vec![0; len].into_boxed_slice(),
+        }
+    }
+
+    /// Zeroes the `buffer` and returns it to the pool - unless the pool
+    /// is full.
+    fn release(&self, mut buffer: Box<[u8]>) {
+        if buffer.is_empty() || self.capacity == 0 {
+            return;
+        }
+        buffer.iter_mut().for_each(|b| *b = 0);
+        let mut buffers = self.lock();
+        if buffers.len() < self.capacity {
+            buffers.push(buffer);
+        }
+    }
+}
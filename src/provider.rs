@@ -0,0 +1,93 @@
+// Copyright (c) 2019 Andreas Auernhammer. All rights reserved.
+// Use of this source code is governed by a license that can be
+// found in the LICENSE file.
+
+use super::writer::Close;
+use super::{Aad, Algorithm, DecReader, DecWriter, EncWriter, Key, Nonce};
+use std::io::{self, Read, Write};
+
+/// A source of secret keys.
+///
+/// A `KeyProvider` hands out the key of a stream only to the constructor
+/// of a writer or reader - e.g. `EncWriter::with_key_provider`. The key
+/// is dropped as soon as the writer or reader has been created and never
+/// returned to the caller. Hence, the key can be kept outside of the
+/// application - e.g. sealed by a TPM (see `sio::tpm`) - and is only
+/// present in memory while the stream is set up.
+pub trait KeyProvider<A: Algorithm> {
+    /// Returns the secret key.
+    fn key(&self) -> io::Result<Key<A>>;
+}
+
+impl<A: Algorithm> KeyProvider<A> for Key<A> {
+    #[inline]
+    fn key(&self) -> io::Result<Key<A>> {
+        Ok(Key::new(*self.as_ref()))
+    }
+}
+
+impl<A: Algorithm, W: Write + Close> EncWriter<A, W> {
+    /// Creates a new `EncWriter` that encrypts a stream with the key
+    /// returned by the `provider`.
+    ///
+    /// It fails if the `provider` cannot provide a key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::Write;
+    /// use sio::{Key, Nonce, Aad, EncWriter, CHACHA20_POLY1305};
+    ///
+    /// // Obviously, don't use this all-zeros key for anything real.
+    /// let key: Key<CHACHA20_POLY1305> = Key::new([0; Key::<CHACHA20_POLY1305>::SIZE]);
+    ///
+    /// let mut ciphertext: Vec<u8> = Vec::default();
+    /// let mut writer = EncWriter::with_key_provider(
+    ///     &mut ciphertext,
+    ///     &key,
+    ///     Nonce::zero(),
+    ///     Aad::empty(),
+    /// )
+    /// .unwrap();
+    /// writer.write_all(b"Hello World").unwrap();
+    /// writer.close().unwrap();
+    /// ```
+    pub fn with_key_provider<P: KeyProvider<A>>(
+        inner: W,
+        provider: &P,
+        nonce: Nonce<A>,
+        aad: Aad<A>,
+    ) -> io::Result<Self> {
+        Ok(EncWriter::new(inner, &provider.key()?, nonce, aad))
+    }
+}
+
+impl<A: Algorithm, W: Write + Close> DecWriter<A, W> {
+    /// Creates a new `DecWriter` that decrypts a stream with the key
+    /// returned by the `provider`.
+    ///
+    /// It fails if the `provider` cannot provide a key.
+    pub fn with_key_provider<P: KeyProvider<A>>(
+        inner: W,
+        provider: &P,
+        nonce: Nonce<A>,
+        aad: Aad<A>,
+    ) -> io::Result<Self> {
+        Ok(DecWriter::new(inner, &provider.key()?, nonce, aad))
+    }
+}
+
+impl<A: Algorithm, R: Read> DecReader<A, R> {
+    /// Creates a new `DecReader` that decrypts a stream with the key
+    /// returned by the `provider`.
+    ///
+    /// It fails if the `provider` cannot provide a key.
+    pub fn with_key_provider<P: KeyProvider<A>>(
+        inner: R,
+        provider: &P,
+        nonce: Nonce<A>,
+        aad: Aad<A>,
+    ) -> io::Result<Self> {
+        Ok(DecReader::new(inner, &provider.key()?, nonce, aad))
+    }
+}
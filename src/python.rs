@@ -0,0 +1,288 @@
+// Copyright (c) 2019 Andreas Auernhammer. All rights reserved.
+// Use of this source code is governed by a license that can be
+// found in the LICENSE file.
+
+//! Python bindings for encrypting and decrypting streams.
+//!
+//! This module exposes an `EncWriter` and a `DecWriter` as Python file-like
+//! objects. Both wrap a writable Python file object - e.g. a file opened in
+//! binary mode or an `io.BytesIO` - and use ChaCha20-Poly1305 and the same
+//! framing as the Rust types. Hence, ciphertext produced in Python can be
+//! decrypted in Rust and vice versa.
+//!
+//! The Python extension module can be built with [maturin](https://www.maturin.rs/)
+//! using the `pyproject.toml` of this repository.
+//!
+//! ```python
+//! import io
+//! import sio
+//!
+//! key, nonce = bytes(32), bytes(8) # Obviously, don't use this all-zeros key for anything real.
+//!
+//! ciphertext = io.BytesIO()
+//! with sio.EncWriter(ciphertext, key, nonce, b"aad") as writer:
+//!     writer.write(b"Hello World")
+//!
+//! plaintext = io.BytesIO()
+//! with sio.DecWriter(plaintext, key, nonce, b"aad") as writer:
+//!     writer.write(ciphertext.getvalue())
+//! ```
+//!
+//! Closing a writer - explicitly, when leaving a `with` block or when it
+//! gets garbage collected - completes the stream and flushes the wrapped
+//! file object. Like `gzip.GzipFile`, it does not close the wrapped file
+//! object. Decryption errors are raised as `ValueError`.
+
+use super::{Aad, DecWriter, EncWriter, Key, Nonce, NopCloser, BUF_SIZE, CHACHA20_POLY1305};
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+use std::convert::TryInto;
+use std::io::{self, Write};
+
+/// A Python file object that implements `Write`.
+struct PyFile(PyObject);
+
+impl Write for PyFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        Python::with_gil(|py| {
+            let n = self.0.call_method1(py, "write", (PyBytes::new(py, buf),))?;
+
+            // Buffered file objects return `None` or the length of `buf`.
+            Ok(n.extract::<Option<usize>>(py)?.unwrap_or(buf.len()))
+        })
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Python::with_gil(|py| self.0.call_method0(py, "flush").map(|_| ()))?;
+        Ok(())
+    }
+}
+
+/// Converts an `io::Error` into a Python exception. Decryption errors
+/// are raised as `ValueError`.
+fn into_py_err(err: io::Error) -> PyErr {
+    if err.kind() == io::ErrorKind::InvalidData {
+        PyValueError::new_err(err.to_string())
+    } else {
+        err.into()
+    }
+}
+
+/// Returns the `Key` and `Nonce` for the given bytes or raises a
+/// `ValueError` if they have the wrong length.
+fn key_and_nonce(
+    key: &[u8],
+    nonce: &[u8],
+) -> PyResult<(Key<CHACHA20_POLY1305>, Nonce<CHACHA20_POLY1305>)> {
+    let key: [u8; Key::<CHACHA20_POLY1305>::SIZE] = key.try_into().map_err(|_| {
+        PyValueError::new_err(format!(
+            "key must be {} bytes long",
+            Key::<CHACHA20_POLY1305>::SIZE
+        ))
+    })?;
+    let nonce: [u8; Nonce::<CHACHA20_POLY1305>::SIZE] = nonce.try_into().map_err(|_| {
+        PyValueError::new_err(format!(
+            "nonce must be {} bytes long",
+            Nonce::<CHACHA20_POLY1305>::SIZE
+        ))
+    })?;
+    Ok((Key::new(key), Nonce::new(nonce)))
+}
+
+/// Returns the `ValueError` raised when writing to a closed writer.
+fn closed() -> PyErr {
+    PyValueError::new_err("I/O operation on closed file")
+}
+
+/// A Python file-like object that encrypts everything written to it.
+///
+/// `EncWriter(file, key, nonce, aad=b"", buf_size=BUF_SIZE)` wraps the
+/// writable Python `file` object and writes the ciphertext to it.
+#[pyclass(name = "EncWriter", module = "sio")]
+pub struct PyEncWriter {
+    writer: Option<EncWriter<CHACHA20_POLY1305, NopCloser<PyFile>>>,
+}
+
+#[pymethods]
+impl PyEncWriter {
+    #[new]
+    #[pyo3(signature = (file, key, nonce, aad = None, buf_size = BUF_SIZE))]
+    fn new(
+        file: PyObject,
+        key: &[u8],
+        nonce: &[u8],
+        aad: Option<&[u8]>,
+        buf_size: usize,
+    ) -> PyResult<Self> {
+        let (key, nonce) = key_and_nonce(key, nonce)?;
+        let writer = EncWriter::with_buffer_size(
+            NopCloser::wrap(PyFile(file)),
+            &key,
+            nonce,
+            Aad::from(aad.unwrap_or_default()),
+            buf_size,
+        )
+        .map_err(|err| PyValueError::new_err(err.to_string()))?;
+        Ok(PyEncWriter {
+            writer: Some(writer),
+        })
+    }
+
+    /// Encrypts `data` and returns the number of bytes written.
+    fn write(&mut self, data: &[u8]) -> PyResult<usize> {
+        let writer = self.writer.as_mut().ok_or_else(closed)?;
+        writer.write_all(data).map_err(into_py_err)?;
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> PyResult<()> {
+        let writer = self.writer.as_mut().ok_or_else(closed)?;
+        writer.flush().map_err(into_py_err)
+    }
+
+    /// Completes the encryption and flushes the wrapped file object.
+    /// Closing a closed writer has no effect.
+    fn close(&mut self) -> PyResult<()> {
+        match self.writer.take() {
+            Some(writer) => writer.close().map_err(into_py_err),
+            None => Ok(()),
+        }
+    }
+
+    #[getter]
+    fn closed(&self) -> bool {
+        self.writer.is_none()
+    }
+
+    fn writable(&self) -> bool {
+        true
+    }
+
+    fn readable(&self) -> bool {
+        false
+    }
+
+    fn seekable(&self) -> bool {
+        false
+    }
+
+    fn __enter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    #[pyo3(signature = (*_args))]
+    fn __exit__(&mut self, _args: &Bound<'_, pyo3::types::PyTuple>) -> PyResult<bool> {
+        self.close().map(|()| false)
+    }
+}
+
+impl Drop for PyEncWriter {
+    fn drop(&mut self) {
+        // Like Python's file objects, a writer gets closed when it is
+        // garbage collected. Errors cannot be reported anymore.
+        if let Some(writer) = self.writer.take() {
+            let _ = writer.close();
+        }
+    }
+}
+
+/// A Python file-like object that decrypts and verifies everything
+/// written to it.
+///
+/// `DecWriter(file, key, nonce, aad=b"", buf_size=BUF_SIZE)` wraps the
+/// writable Python `file` object and writes the plaintext to it.
+#[pyclass(name = "DecWriter", module = "sio")]
+pub struct PyDecWriter {
+    writer: Option<DecWriter<CHACHA20_POLY1305, NopCloser<PyFile>>>,
+}
+
+#[pymethods]
+impl PyDecWriter {
+    #[new]
+    #[pyo3(signature = (file, key, nonce, aad = None, buf_size = BUF_SIZE))]
+    fn new(
+        file: PyObject,
+        key: &[u8],
+        nonce: &[u8],
+        aad: Option<&[u8]>,
+        buf_size: usize,
+    ) -> PyResult<Self> {
+        let (key, nonce) = key_and_nonce(key, nonce)?;
+        let writer = DecWriter::with_buffer_size(
+            NopCloser::wrap(PyFile(file)),
+            &key,
+            nonce,
+            Aad::from(aad.unwrap_or_default()),
+            buf_size,
+        )
+        .map_err(|err| PyValueError::new_err(err.to_string()))?;
+        Ok(PyDecWriter {
+            writer: Some(writer),
+        })
+    }
+
+    /// Decrypts `data` and returns the number of bytes written.
+    fn write(&mut self, data: &[u8]) -> PyResult<usize> {
+        let writer = self.writer.as_mut().ok_or_else(closed)?;
+        writer.write_all(data).map_err(into_py_err)?;
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> PyResult<()> {
+        let writer = self.writer.as_mut().ok_or_else(closed)?;
+        writer.flush().map_err(into_py_err)
+    }
+
+    /// Verifies the final fragment and flushes the wrapped file object.
+    /// Closing a closed writer has no effect.
+    fn close(&mut self) -> PyResult<()> {
+        match self.writer.take() {
+            Some(writer) => writer.close().map_err(into_py_err),
+            None => Ok(()),
+        }
+    }
+
+    #[getter]
+    fn closed(&self) -> bool {
+        self.writer.is_none()
+    }
+
+    fn writable(&self) -> bool {
+        true
+    }
+
+    fn readable(&self) -> bool {
+        false
+    }
+
+    fn seekable(&self) -> bool {
+        false
+    }
+
+    fn __enter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    #[pyo3(signature = (*_args))]
+    fn __exit__(&mut self, _args: &Bound<'_, pyo3::types::PyTuple>) -> PyResult<bool> {
+        self.close().map(|()| false)
+    }
+}
+
+impl Drop for PyDecWriter {
+    fn drop(&mut self) {
+        if let Some(writer) = self.writer.take() {
+            let _ = writer.close();
+        }
+    }
+}
+
+/// The `sio` Python module.
+#[pymodule]
+pub fn sio(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyEncWriter>()?;
+    m.add_class::<PyDecWriter>()?;
+    m.add("BUF_SIZE", BUF_SIZE)?;
+    Ok(())
+}
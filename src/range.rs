@@ -0,0 +1,134 @@
+// Copyright (c) 2019 Andreas Auernhammer. All rights reserved.
+// Use of this source code is governed by a license that can be
+// found in the LICENSE file.
+
+use super::framing::{Framing, Sio};
+use super::writer::BATCH_LEN;
+use super::{Aad, Algorithm, Key, Layout, Nonce, NotAuthentic, MAX_NONCE_LEN};
+use std::io::{self, Read, Seek, SeekFrom};
+use std::ops::Range;
+
+/// Decrypts the plaintext bytes within `range` of the encrypted stream
+/// stored in `reader` and returns exactly these bytes.
+///
+/// The stream must have been produced by an `EncWriter` with the default
+/// `Sio` framing and the given buffer size. `decrypt_range` seeks to the
+/// fragments covering the `range` and only reads, decrypts and verifies
+/// them. Hence, it is the building block for serving range requests over
+/// encrypted objects.
+///
+/// Since only the covering fragments are verified, `decrypt_range` cannot
+/// detect that a stream has been truncated - unless the `range` covers
+/// the final fragment. A `range` that exceeds the plaintext fails with
+/// `ErrorKind::InvalidInput`.
+///
+/// # Examples
+///
+/// ```
+/// use std::io::{Cursor, Write};
+/// use sio::{Key, Nonce, Aad, EncWriter, CHACHA20_POLY1305};
+///
+/// // Obviously, don't use this all-zeros key for anything real.
+/// let key: Key<CHACHA20_POLY1305> = Key::new([0; Key::<CHACHA20_POLY1305>::SIZE]);
+///
+/// let mut ciphertext: Vec<u8> = Vec::default();
+/// let mut writer = EncWriter::with_buffer_size(
+///     &mut ciphertext,
+///     &key,
+///     Nonce::new([0; Nonce::<CHACHA20_POLY1305>::SIZE]),
+///     Aad::empty(),
+///     4,
+/// )
+/// .unwrap();
+/// writer.write_all(b"Hello World").unwrap();
+/// writer.close().unwrap();
+///
+/// let plaintext = sio::decrypt_range(
+///     Cursor::new(ciphertext),
+///     &key,
+///     Nonce::new([0; Nonce::<CHACHA20_POLY1305>::SIZE]),
+///     Aad::empty(),
+///     4,
+///     3..8,
+/// )
+/// .unwrap();
+/// assert_eq!(plaintext.as_slice(), b"lo Wo");
+/// ```
+pub fn decrypt_range<A: Algorithm, R: Read + Seek>(
+    mut reader: R,
+    key: &Key<A>,
+    nonce: Nonce<A>,
+    aad: Aad<A>,
+    buf_size: usize,
+    range: Range<u64>,
+) -> io::Result<Vec<u8>> {
+    let layout = Layout::of::<A>(buf_size)?;
+    let mut algorithm = A::new(key.as_ref());
+    let mut framing = Sio::default();
+    framing.init(key.as_ref(), &algorithm, nonce.as_ref(), aad.as_ref())?;
+
+    let fragment_len = layout.fragment_len();
+    let size = reader.seek(SeekFrom::End(0))?;
+    let plaintext_len = layout.plaintext_len(size).ok_or(NotAuthentic)?;
+
+    // Even an empty stream consists of one (final) fragment.
+    let final_index = size.saturating_sub(1) / fragment_len;
+    let final_len = size - final_index * fragment_len;
+    if range.start > range.end || range.end > plaintext_len {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "range exceeds the plaintext",
+        ));
+    }
+    if range.start == range.end {
+        return Ok(Vec::default());
+    }
+
+    let first = range.start / buf_size as u64;
+    let last = (range.end - 1) / buf_size as u64;
+    reader.seek(SeekFrom::Start(first * fragment_len))?;
+
+    let mut plaintext = Vec::with_capacity((range.end - range.start) as usize);
+    let mut buffer = vec![0; BATCH_LEN * fragment_len as usize];
+    let mut nonces = [0; BATCH_LEN * MAX_NONCE_LEN];
+    let mut index = first;
+    while index <= last {
+        // The non-final fragments are decrypted in batches. The final
+        // fragment has different associated data and is decrypted alone.
+        framing.seek(index)?;
+        let (n, len) = if index == final_index {
+            let len = final_len as usize;
+            reader.read_exact(&mut buffer[..len])?;
+            let (nonce, aad) = framing.next(&mut algorithm, true)?;
+            algorithm.open_in_place(nonce, aad, &mut buffer[..len])?;
+            (1, len)
+        } else {
+            let n = (last.min(final_index - 1) - index + 1).min(BATCH_LEN as u64) as usize;
+            let len = n * fragment_len as usize;
+            reader.read_exact(&mut buffer[..len])?;
+            let mut aad = Vec::default();
+            for nonce in nonces[..n * A::NONCE_LEN].chunks_mut(A::NONCE_LEN) {
+                let (next_nonce, next_aad) = framing.next(&mut algorithm, false)?;
+                nonce.copy_from_slice(next_nonce);
+                aad = next_aad.to_vec();
+            }
+            algorithm.open_batch(
+                &nonces[..n * A::NONCE_LEN],
+                &aad,
+                &mut buffer[..len],
+                fragment_len as usize,
+            )?;
+            (n, len)
+        };
+
+        for (i, fragment) in buffer[..len].chunks(fragment_len as usize).enumerate() {
+            let fragment = &fragment[..fragment.len() - A::TAG_LEN];
+            let offset = (index + i as u64) * buf_size as u64;
+            let start = range.start.saturating_sub(offset) as usize;
+            let end = ((range.end - offset) as usize).min(fragment.len());
+            plaintext.extend_from_slice(&fragment[start..end]);
+        }
+        index += n as u64;
+    }
+    Ok(plaintext)
+}
@@ -0,0 +1,550 @@
+// Copyright (c) 2019 Andreas Auernhammer. All rights reserved.
+// Use of this source code is governed by a license that can be
+// found in the LICENSE file.
+
+#[cfg(feature = "ring")]
+use super::audit::Audit;
+use super::framing::{self, Framing, Sio};
+use super::metadata::{self, Metadata};
+use super::utils::read_full;
+use super::{
+    Aad, Algorithm, Invalid, Key, Layout, LengthMismatch, Nonce, NotAuthentic, BUF_SIZE,
+    MAX_BUF_SIZE,
+};
+use std::io;
+use std::io::Read;
+
+/// Wraps a reader and decrypts and verifies everything read from it.
+///
+/// **`DecReader` returns unauthentic plaintext. Only the final
+/// successful read, which returns `0`, guarantees that the entire
+/// stream has been authentic. Therefore, you must read the `DecReader`
+/// until it returns `0` or an error.**
+///
+/// Each fragment is verified before any of its plaintext is returned.
+/// However, the `DecReader` can only detect that a stream has been
+/// truncated once the inner reader reaches its end. Then, reading
+/// fails with a `NotAuthentic` error.
+///
+/// # Examples
+///
+/// ```
+/// use std::io::{Read, Write};
+/// use sio::{Key, Nonce, Aad, EncWriter, DecReader, CHACHA20_POLY1305};
+///
+/// // Obviously, don't use this all-zeros key for anything real.
+/// let key: Key<CHACHA20_POLY1305> = Key::new([0; Key::<CHACHA20_POLY1305>::SIZE]);
+///
+/// let mut ciphertext: Vec<u8> = Vec::default();
+/// let mut writer = EncWriter::new(
+///     &mut ciphertext,
+///     &key,
+///     Nonce::new([0; Nonce::<CHACHA20_POLY1305>::SIZE]),
+///     Aad::empty(),
+/// );
+/// writer.write_all(b"Hello World").unwrap();
+/// writer.close().unwrap();
+///
+/// let mut reader = DecReader::new(
+///     ciphertext.as_slice(),
+///     &key,
+///     Nonce::new([0; Nonce::<CHACHA20_POLY1305>::SIZE]),
+///     Aad::empty(),
+/// );
+/// let mut plaintext = String::default();
+/// reader.read_to_string(&mut plaintext).unwrap();
+///
+/// assert_eq!(plaintext, "Hello World");
+/// ```
+pub struct DecReader<A: Algorithm, R: Read, F: Framing = Sio> {
+    inner: R,
+    algorithm: A,
+    buffer: Box<[u8]>,
+    buf_size: usize,
+    framing: F,
+
+    // The plaintext of the current fragment that has not been
+    // returned yet is stored at `buffer[pos..end]`.
+    pos: usize,
+    end: usize,
+
+    // To detect whether a fragment is the final one, the DecReader
+    // reads one byte more than a fragment and the trailer. The `carry`
+    // bytes following the current fragment belong to the next fragment.
+    carry: usize,
+
+    // Whether the header of the framing has been verified.
+    header_verified: bool,
+
+    // In tolerant mode, a copy of the current ciphertext fragment is kept
+    // such that a fragment that cannot be decrypted can be checked for
+    // being a final fragment followed by more data. If `None` the tolerant
+    // mode is disabled.
+    ciphertext: Option<Box<[u8]>>,
+
+    // The number of ciphertext bytes - including the header and trailer -
+    // processed so far.
+    consumed: u64,
+
+    // The number of fragments decrypted so far.
+    fragments: u64,
+
+    // Once the final fragment has been decrypted, no more ciphertext
+    // must be processed.
+    done: bool,
+
+    // If set, the first fragment of the stream is expected to contain
+    // the metadata of the stream. Once it has been decrypted, the
+    // metadata is available.
+    metadata_expected: bool,
+    metadata: Option<Metadata>,
+
+    // Reports authentication failures to the audit hook.
+    #[cfg(feature = "ring")]
+    audit: Audit,
+
+    // If an error occurs, we must fail any subsequent read operation.
+    errored: bool,
+}
+
+impl<A: Algorithm, R: Read> DecReader<A, R> {
+    /// Creates a new `DecReader` with a default buffer size of 16 KiB.
+    ///
+    /// Anything read from the `DecReader` gets decrypted and verified
+    /// using the provided `key` and `nonce`. The `aad` is only verified
+    /// and must match the associated data used during encryption.
+    pub fn new(inner: R, key: &Key<A>, nonce: Nonce<A>, aad: Aad<A>) -> Self {
+        Self::with_buffer_size(inner, key, nonce, aad, BUF_SIZE).unwrap()
+    }
+
+    /// Creates a new `DecReader` with the specified buffer size as fragment
+    /// size. The `buf_size` must not be `0` nor greater than `MAX_BUF_SIZE`
+    /// and must match the buffer size used to encrypt the data.
+    pub fn with_buffer_size(
+        inner: R,
+        key: &Key<A>,
+        nonce: Nonce<A>,
+        aad: Aad<A>,
+        buf_size: usize,
+    ) -> Result<Self, Invalid> {
+        Self::with_framing(inner, key, nonce, aad, buf_size, Sio::default())
+    }
+}
+
+impl<A: Algorithm, R: Read, F: Framing> DecReader<A, R, F> {
+    /// Creates a new `DecReader` with the specified buffer size as fragment
+    /// size that uses the given `framing` instead of the default `Sio`
+    /// channel construction. The `buf_size` must not be `0` nor greater
+    /// than `MAX_BUF_SIZE`.
+    pub fn with_framing(
+        inner: R,
+        key: &Key<A>,
+        nonce: Nonce<A>,
+        aad: Aad<A>,
+        buf_size: usize,
+        mut framing: F,
+    ) -> Result<Self, Invalid> {
+        if buf_size == 0 || buf_size > MAX_BUF_SIZE {
+            return Err(Invalid::BufSize);
+        }
+        let algorithm = A::new(key.as_ref());
+        framing.init(key.as_ref(), &algorithm, nonce.as_ref(), aad.as_ref())?;
+
+        let buffer_len = buf_size + A::TAG_LEN + framing.trailer_len() + 1;
+        Ok(DecReader {
+            inner,
+            algorithm,
+            buffer: vec![0; buffer_len].into_boxed_slice(),
+            buf_size,
+            framing,
+            pos: 0,
+            end: 0,
+            carry: 0,
+            header_verified: false,
+            ciphertext: None,
+            consumed: 0,
+            fragments: 0,
+            done: false,
+            metadata_expected: false,
+            metadata: None,
+            #[cfg(feature = "ring")]
+            audit: Audit::new(aad.as_ref()),
+            errored: false,
+        })
+    }
+
+    /// Enables the tolerant mode of the `DecReader`.
+    ///
+    /// In tolerant mode, the `DecReader` stops cleanly at the final
+    /// fragment and returns `0` even if the inner reader contains more
+    /// data. This allows decrypting multiple encrypted streams that have
+    /// been concatenated one after another. The `consumed` method reports
+    /// where the next stream starts.
+    ///
+    /// If the framing records the length of the stream, like `Delimited`,
+    /// the `DecReader` does not read beyond its end. Otherwise, it reads
+    /// ahead and the inner reader has to be repositioned before decrypting
+    /// the next stream. Then, a final fragment followed by more data is
+    /// found by trying all of its possible lengths, the shortest first.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::{Cursor, Read, Seek, SeekFrom, Write};
+    /// use sio::{Key, Nonce, Aad, EncWriter, DecReader, CHACHA20_POLY1305};
+    ///
+    /// // Obviously, don't use this all-zeros key for anything real.
+    /// let key: Key<CHACHA20_POLY1305> = Key::new([0; Key::<CHACHA20_POLY1305>::SIZE]);
+    ///
+    /// let mut ciphertext: Vec<u8> = Vec::default();
+    /// for message in &["Hello", "World"] {
+    ///     let mut writer = EncWriter::new(
+    ///         &mut ciphertext,
+    ///         &key,
+    ///         Nonce::new([0; Nonce::<CHACHA20_POLY1305>::SIZE]),
+    ///         Aad::empty(),
+    ///     );
+    ///     writer.write_all(message.as_bytes()).unwrap();
+    ///     writer.close().unwrap();
+    /// }
+    ///
+    /// let mut journal = Cursor::new(ciphertext);
+    /// let mut offset = 0;
+    /// for message in &["Hello", "World"] {
+    ///     journal.seek(SeekFrom::Start(offset)).unwrap();
+    ///     let mut reader = DecReader::new(
+    ///         &mut journal,
+    ///         &key,
+    ///         Nonce::new([0; Nonce::<CHACHA20_POLY1305>::SIZE]),
+    ///         Aad::empty(),
+    ///     )
+    ///     .tolerant();
+    ///
+    ///     let mut plaintext = String::default();
+    ///     reader.read_to_string(&mut plaintext).unwrap();
+    ///     assert_eq!(&plaintext, message);
+    ///     offset += reader.consumed();
+    /// }
+    /// ```
+    pub fn tolerant(mut self) -> Self {
+        self.ciphertext = Some(vec![0; self.buffer.len()].into_boxed_slice());
+        self
+    }
+
+    /// Expects the first fragment of the stream to contain metadata.
+    ///
+    /// The stream must have been produced by an `EncWriter` with attached
+    /// metadata. The metadata is not returned as part of the plaintext. It
+    /// is available through `metadata` instead.
+    pub fn expect_metadata(mut self) -> Self {
+        self.metadata_expected = true;
+        self
+    }
+
+    /// Returns the metadata of the stream.
+    ///
+    /// If no plaintext has been read yet, it reads and verifies the
+    /// first fragment which contains the metadata. It returns an error
+    /// if the `DecReader` does not expect metadata.
+    pub fn metadata(&mut self) -> io::Result<&Metadata> {
+        if self.errored {
+            return Err(io::Error::from(io::ErrorKind::Other));
+        }
+        if !self.metadata_expected {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "DecReader does not expect metadata",
+            ));
+        }
+        if self.metadata.is_none() {
+            self.read_fragment()?;
+        }
+        // The first fragment contains the metadata.
+        self.metadata
+            .as_ref()
+            .ok_or_else(|| io::Error::from(io::ErrorKind::UnexpectedEof))
+    }
+
+    /// Returns the number of ciphertext bytes that belong to the
+    /// fragments decrypted so far - including the header of the
+    /// framing, if any.
+    ///
+    /// Once the `DecReader` returned `0`, this is the size of the encrypted
+    /// stream. If the framing records the length of the stream - e.g.
+    /// `Delimited` - the `DecReader` does not read beyond its end. Hence,
+    /// the next one of several concatenated streams can be read from the
+    /// inner reader right away. Otherwise, the size of the stream may be
+    /// smaller than the number of bytes read from the inner reader.
+    #[inline]
+    pub fn consumed(&self) -> u64 {
+        self.consumed
+    }
+
+    /// Returns the framing of the `DecReader` - e.g. to query the
+    /// format version detected by the `Auto` framing.
+    #[inline]
+    pub fn framing(&self) -> &F {
+        &self.framing
+    }
+
+    /// Unwraps the `DecReader` and returns the inner reader.
+    ///
+    /// Any data that has been read from the inner reader but not returned
+    /// yet is lost.
+    #[inline]
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    /// Lets the framing detect the layout of the stream and reads and
+    /// verifies the header of the framing. Any bytes read beyond the
+    /// header are placed at the beginning of the buffer and their number
+    /// is returned.
+    fn read_header(&mut self) -> io::Result<usize> {
+        let mut prefix = vec![0; self.framing.detect_len()];
+        let n = match read_full(&mut self.inner, &mut prefix) {
+            Ok(n) => n,
+            Err(err) => {
+                self.errored = true;
+                return Err(err);
+            }
+        };
+        prefix.truncate(n);
+        if let Err(err) = self.framing.detect(&prefix) {
+            self.errored = true;
+            return Err(err.into());
+        }
+        if let Some(buf_size) = self.framing.buf_size() {
+            // A fragment size that no `EncWriter` can produce
+            // is not authentic.
+            if buf_size == 0 || buf_size > MAX_BUF_SIZE {
+                self.errored = true;
+                return Err(NotAuthentic.into());
+            }
+            if buf_size != self.buf_size {
+                let buffer_len = buf_size + A::TAG_LEN + self.framing.trailer_len() + 1;
+                self.buffer = vec![0; buffer_len].into_boxed_slice();
+                if let Some(ref mut ciphertext) = self.ciphertext {
+                    *ciphertext = vec![0; buffer_len].into_boxed_slice();
+                }
+                self.buf_size = buf_size;
+            }
+        }
+
+        let header_len = self.framing.header().len();
+        let mut header = vec![0; header_len];
+        let k = prefix.len().min(header_len);
+        header[..k].copy_from_slice(&prefix[..k]);
+        let n = match read_full(&mut self.inner, &mut header[k..]) {
+            Ok(n) => k + n,
+            Err(err) => {
+                self.errored = true;
+                return Err(err);
+            }
+        };
+        if n != header_len || header != self.framing.header() {
+            self.errored = true;
+            return Err(NotAuthentic.into());
+        }
+        self.consumed += n as u64;
+        self.header_verified = true;
+
+        let carry = prefix.len() - k;
+        self.buffer[..carry].copy_from_slice(&prefix[k..]);
+        Ok(carry)
+    }
+
+    /// Reads the next fragment from the inner reader and decrypts and
+    /// verifies it.
+    fn read_fragment(&mut self) -> io::Result<()> {
+        let trailer_len = self.framing.trailer_len();
+        if self.header_verified {
+            let fragment_len = self.buf_size + A::TAG_LEN;
+            self.buffer
+                .copy_within(fragment_len..fragment_len + self.carry, 0);
+        } else {
+            // The header may record the fragment size.
+            self.carry = self.read_header()?;
+        }
+        let fragment_len = self.buf_size + A::TAG_LEN;
+
+        // If the framing records the length of the stream, we read exactly
+        // up to its end. Otherwise, we read one byte more than a fragment
+        // and the trailer to detect whether the fragment is the final one.
+        let overhead = (self.framing.header().len() + trailer_len) as u64;
+        let stream_len = self
+            .framing
+            .stream_len()
+            .and_then(|len| {
+                Layout::new(self.buf_size, A::TAG_LEN)
+                    .ok()?
+                    .ciphertext_len(len)
+            })
+            .and_then(|len| len.checked_add(overhead));
+        let target = match stream_len {
+            Some(stream_len) => {
+                let remaining = stream_len.saturating_sub(self.consumed);
+                if remaining <= (fragment_len + trailer_len) as u64 {
+                    remaining as usize
+                } else {
+                    fragment_len
+                }
+            }
+            None => fragment_len + trailer_len + 1,
+        };
+        let len = if self.carry < target {
+            match read_full(&mut self.inner, &mut self.buffer[self.carry..target]) {
+                Ok(n) => self.carry + n,
+                Err(err) => {
+                    self.errored = true;
+                    return Err(err);
+                }
+            }
+        } else {
+            self.carry
+        };
+        let last = match stream_len {
+            Some(stream_len) => {
+                if target == 0 || len != target {
+                    self.errored = true;
+                    return Err(LengthMismatch.into());
+                }
+                self.carry = 0;
+                self.consumed + len as u64 == stream_len
+            }
+            None => {
+                let last = len <= fragment_len + trailer_len;
+                self.carry = if last { 0 } else { len - fragment_len };
+                last
+            }
+        };
+        let available = len;
+        let len = if last {
+            len.saturating_sub(trailer_len)
+        } else {
+            fragment_len
+        };
+
+        // Without a recorded length, a fragment that cannot be decrypted
+        // may be a shorter final fragment followed by more data.
+        let framing = match self.ciphertext {
+            Some(ref mut ciphertext) if stream_len.is_none() => {
+                ciphertext[..available].copy_from_slice(&self.buffer[..available]);
+                Some(self.framing.clone())
+            }
+            _ => None,
+        };
+
+        let (nonce, aad) = match self.framing.next(&mut self.algorithm, last) {
+            Ok(v) => v,
+            Err(err) => {
+                self.errored = true;
+                let bytes = self.fragments * self.buf_size as u64;
+                return Err(err.processed(self.fragments, bytes).into());
+            }
+        };
+
+        let plaintext = self
+            .algorithm
+            .open_in_place(nonce, aad, &mut self.buffer[..len])
+            .map(|plaintext| plaintext.len());
+        let plaintext = match plaintext {
+            Ok(_) if last && self.buffer[len..len + trailer_len] != *self.framing.trailer() => {
+                Err(NotAuthentic)
+            }
+            plaintext => plaintext,
+        };
+
+        let (len, plaintext, last) = match plaintext {
+            Ok(plaintext) => (len, plaintext, last),
+            Err(err) => {
+                match framing.and_then(|f| self.find_final_fragment(&f, available, len, last)) {
+                    Some(n) => (n, n - A::TAG_LEN, true),
+                    None => {
+                        self.errored = true;
+                        #[cfg(feature = "ring")]
+                        self.audit.report(self.fragments, self.consumed, len);
+                        return Err(err.into());
+                    }
+                }
+            }
+        };
+
+        // The metadata fragment is always a full fragment and
+        // does not contain any plaintext of the stream.
+        let start = if self.metadata_expected && self.fragments == 0 {
+            let metadata = if plaintext == self.buf_size {
+                metadata::decode(&self.buffer[..plaintext])
+            } else {
+                None
+            };
+            if metadata.is_none() {
+                self.errored = true;
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "malformed metadata",
+                ));
+            }
+            self.metadata = metadata;
+            plaintext
+        } else {
+            0
+        };
+        self.pos = start;
+        self.end = plaintext;
+        self.fragments += 1;
+        self.consumed += len as u64;
+        if last {
+            self.consumed += trailer_len as u64;
+        }
+        self.done = last;
+        Ok(())
+    }
+
+    /// Searches the copy of the first `available` bytes of buffered
+    /// ciphertext for a valid final fragment followed by the trailer and
+    /// more data. The given `framing` must be in the state before the
+    /// current fragment of `len` bytes has been processed. If the current
+    /// fragment is the `last` one, only final fragments shorter than `len`
+    /// are considered.
+    ///
+    /// It returns the length of the final fragment, if any.
+    fn find_final_fragment(
+        &mut self,
+        framing: &F,
+        available: usize,
+        len: usize,
+        last: bool,
+    ) -> Option<usize> {
+        let ciphertext = self.ciphertext.as_ref()?;
+        let max_len = if last { len.saturating_sub(1) } else { len };
+        framing::find_final_fragment(
+            &mut self.algorithm,
+            framing,
+            &ciphertext[..available],
+            &mut self.buffer,
+            max_len,
+        )
+    }
+}
+
+impl<A: Algorithm, R: Read, F: Framing> Read for DecReader<A, R, F> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.errored {
+            return Err(io::Error::from(io::ErrorKind::Other));
+        }
+        // A fragment may not contain any plaintext - e.g. if it only
+        // contains the metadata.
+        while self.pos == self.end {
+            if self.done || buf.is_empty() {
+                return Ok(0);
+            }
+            self.read_fragment()?;
+        }
+
+        let n = buf.len().min(self.end - self.pos);
+        buf[..n].copy_from_slice(&self.buffer[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
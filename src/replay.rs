@@ -0,0 +1,138 @@
+// Copyright (c) 2019 Andreas Auernhammer. All rights reserved.
+// Use of this source code is governed by a license that can be
+// found in the LICENSE file.
+
+use super::Replayed;
+
+/// A sliding window that detects replayed messages by their sequence
+/// number.
+///
+/// Transports that may lose, duplicate or reorder messages cannot simply
+/// require increasing sequence numbers. Instead, a `ReplayWindow` keeps
+/// track of the `size` most recent sequence numbers - relative to the
+/// largest one accepted so far - and accepts each of them at most once.
+/// Older sequence numbers are rejected since it cannot tell whether they
+/// have been accepted before. This is the anti-replay window of IPsec
+/// and DTLS.
+///
+/// A larger window tolerates more reordering at the cost of `size / 8`
+/// bytes of memory. The default window tracks 64 sequence numbers.
+///
+/// A message must only be accepted after it has been verified. Otherwise,
+/// an attacker could move the window by forging messages. The
+/// `DatagramOpener` and the `Opener` take care of that.
+///
+/// # Examples
+///
+/// ```
+/// use sio::ReplayWindow;
+///
+/// let mut window = ReplayWindow::new(128);
+/// assert!(window.accept(200).is_ok());
+/// assert!(window.accept(73).is_ok());
+/// assert!(window.accept(73).is_err()); // replayed
+/// assert!(window.accept(72).is_err()); // too old
+/// ```
+#[derive(Clone, Debug)]
+pub struct ReplayWindow {
+    size: u32,
+    /// The largest sequence number accepted so far.
+    top: Option<u32>,
+    /// Bit `n % bits` is set if the sequence number `n` has been accepted.
+    seen: Vec<u64>,
+}
+
+impl ReplayWindow {
+    /// Creates a new `ReplayWindow` that tracks the `size` most recent
+    /// sequence numbers.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the `size` is `0`.
+    pub fn new(size: u32) -> Self {
+        assert!(size > 0, "the size of the replay window must not be 0");
+        ReplayWindow {
+            size,
+            top: None,
+            seen: vec![0; (size as usize).div_ceil(64)],
+        }
+    }
+
+    /// Returns the number of sequence numbers tracked by the window.
+    #[inline]
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+
+    /// Returns the largest sequence number accepted so far - if any.
+    #[inline]
+    pub fn top(&self) -> Option<u32> {
+        self.top
+    }
+
+    /// Checks whether `seq_num` would be accepted without accepting it.
+    ///
+    /// It fails with `Replayed` if `seq_num` has already been accepted or
+    /// is too old.
+    pub fn check(&self, seq_num: u32) -> Result<(), Replayed> {
+        match self.top {
+            Some(top) if seq_num <= top => {
+                if top - seq_num >= self.size || self.is_set(seq_num) {
+                    Err(Replayed)
+                } else {
+                    Ok(())
+                }
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Accepts `seq_num` and moves the window if `seq_num` is larger than
+    /// all sequence numbers accepted so far.
+    ///
+    /// It fails with `Replayed` if `seq_num` has already been accepted or
+    /// is too old.
+    pub fn accept(&mut self, seq_num: u32) -> Result<(), Replayed> {
+        self.check(seq_num)?;
+        match self.top {
+            Some(top) if seq_num <= top => (),
+            Some(top) => {
+                let bits = 64 * self.seen.len() as u64;
+                if u64::from(seq_num - top) >= bits {
+                    self.seen.iter_mut().for_each(|word| *word = 0);
+                } else {
+                    (top + 1..seq_num).for_each(|n| self.clear(n));
+                }
+                self.top = Some(seq_num);
+            }
+            None => self.top = Some(seq_num),
+        }
+        let (word, bit) = self.position(seq_num);
+        self.seen[word] |= bit;
+        Ok(())
+    }
+
+    #[inline]
+    fn position(&self, seq_num: u32) -> (usize, u64) {
+        let n = seq_num as usize % (64 * self.seen.len());
+        (n / 64, 1 << (n % 64))
+    }
+
+    #[inline]
+    fn is_set(&self, seq_num: u32) -> bool {
+        let (word, bit) = self.position(seq_num);
+        self.seen[word] & bit != 0
+    }
+
+    #[inline]
+    fn clear(&mut self, seq_num: u32) {
+        let (word, bit) = self.position(seq_num);
+        self.seen[word] &= !bit;
+    }
+}
+
+impl Default for ReplayWindow {
+    fn default() -> Self {
+        Self::new(64)
+    }
+}
@@ -0,0 +1,64 @@
+// Copyright (c) 2019 Andreas Auernhammer. All rights reserved.
+// Use of this source code is governed by a license that can be
+// found in the LICENSE file.
+
+use std::ops::Range;
+
+/// A report of all fragments a `DecWriter` in forensic mode has skipped
+/// because they were not authentic.
+///
+/// It is returned by `DecWriter::close_forensic` and allows operators to
+/// decide whether a partial restore is acceptable - e.g. because only a
+/// few bytes of a large backup are damaged.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CorruptionReport {
+    fragments: Vec<u64>,
+    ranges: Vec<Range<u64>>,
+    final_verified: bool,
+}
+
+impl CorruptionReport {
+    /// Returns the sequence numbers of all fragments that have been
+    /// skipped - in ascending order.
+    #[inline]
+    pub fn fragments(&self) -> &[u64] {
+        &self.fragments
+    }
+
+    /// Returns the plaintext ranges of all fragments that have been
+    /// skipped - in ascending order. The plaintext of these ranges has
+    /// not been written to the inner writer.
+    #[inline]
+    pub fn ranges(&self) -> &[Range<u64>] {
+        &self.ranges
+    }
+
+    /// Returns the number of plaintext bytes that have been skipped.
+    pub fn damaged_bytes(&self) -> u64 {
+        self.ranges.iter().map(|r| r.end - r.start).sum()
+    }
+
+    /// Returns true if the final fragment has been verified. Otherwise,
+    /// the final fragment was damaged or missing and the stream may have
+    /// been truncated.
+    #[inline]
+    pub fn final_verified(&self) -> bool {
+        self.final_verified
+    }
+
+    /// Returns true if no fragment has been skipped and the final
+    /// fragment has been verified. Then the plaintext is complete.
+    #[inline]
+    pub fn is_intact(&self) -> bool {
+        self.fragments.is_empty() && self.final_verified
+    }
+
+    pub(crate) fn push(&mut self, fragment: u64, range: Range<u64>) {
+        self.fragments.push(fragment);
+        self.ranges.push(range);
+    }
+
+    pub(crate) fn set_final_verified(&mut self) {
+        self.final_verified = true;
+    }
+}
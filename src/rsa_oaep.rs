@@ -0,0 +1,151 @@
+// Copyright (c) 2019 Andreas Auernhammer. All rights reserved.
+// Use of this source code is governed by a license that can be
+// found in the LICENSE file.
+
+//! Public-key encryption of streams using RSA-OAEP.
+//!
+//! The sender generates a random data key for every stream and wraps it
+//! with the recipient's RSA public key using RSA-OAEP with SHA-256. The
+//! wrapped data key is written in front of the ciphertext, preceded by its
+//! length as 2 byte big-endian integer. Hence, the recipient can unwrap
+//! the data key using its RSA private key.
+//!
+//! RSA-OAEP is only provided for recipients that cannot handle X25519.
+//! New deployments should prefer `sio::x25519` or `sio::hpke`.
+//!
+//! Since every stream uses a new key, the nonce is always zero.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use std::io::{Read, Write};
+//! use sio::{Aad, CHACHA20_POLY1305};
+//! use sio::rsa_oaep::{self, RsaPrivateKey, RsaPublicKey};
+//!
+//! # fn load_private_key() -> RsaPrivateKey { unimplemented!() }
+//! let private_key: RsaPrivateKey = load_private_key();
+//! let public_key = RsaPublicKey::from(&private_key);
+//!
+//! let mut ciphertext: Vec<u8> = Vec::default();
+//! let mut writer = rsa_oaep::seal::<CHACHA20_POLY1305, _>(
+//!     &mut ciphertext,
+//!     &public_key,
+//!     Aad::empty(),
+//! )
+//! .unwrap();
+//! writer.write_all(b"Hello World").unwrap();
+//! writer.close().unwrap();
+//!
+//! let mut reader = rsa_oaep::open::<CHACHA20_POLY1305, _>(
+//!     ciphertext.as_slice(),
+//!     &private_key,
+//!     Aad::empty(),
+//! )
+//! .unwrap();
+//! let mut plaintext = String::default();
+//! reader.read_to_string(&mut plaintext).unwrap();
+//! assert_eq!(plaintext, "Hello World");
+//! ```
+
+use super::writer::Close;
+use super::{Aad, Algorithm, DecReader, EncWriter, Invalid, Key, Nonce, NotAuthentic};
+use ring::rand::{SecureRandom, SystemRandom};
+use rsa::rand_core::{self, CryptoRng, RngCore};
+use rsa::sha2::Sha256;
+use rsa::traits::PublicKeyParts;
+use rsa::Oaep;
+use std::io::{self, Read, Write};
+
+pub use rsa::{RsaPrivateKey, RsaPublicKey};
+
+/// The largest supported size of a wrapped data key - i.e. the size of
+/// an RSA-8192 modulus.
+pub const MAX_WRAPPED_KEY_LEN: usize = 1024;
+
+/// The OAEP label of wrapped data keys.
+const LABEL: &str = "sio rsa-oaep data key";
+
+/// A cryptographically secure random number generator for the `rsa`
+/// crate that is backed by `ring`.
+struct Rng(SystemRandom);
+
+impl RngCore for Rng {
+    fn next_u32(&mut self) -> u32 {
+        rand_core::impls::next_u32_via_fill(self)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        rand_core::impls::next_u64_via_fill(self)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.try_fill_bytes(dest)
+            .expect("the system random number generator failed")
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand_core::Error> {
+        self.0
+            .fill(dest)
+            .map_err(|_| rand_core::Error::new(Invalid::Key))
+    }
+}
+
+impl CryptoRng for Rng {}
+
+/// Returns an `EncWriter` that encrypts a stream for the owner of the
+/// `recipient` public key.
+///
+/// It writes the header - the wrapped data key - to the `inner` writer
+/// immediately. The modulus of the `recipient` must not be larger than
+/// 8192 bits.
+pub fn seal<A: Algorithm, W: Write + Close>(
+    mut inner: W,
+    recipient: &RsaPublicKey,
+    aad: Aad<A>,
+) -> io::Result<EncWriter<A, W>> {
+    if recipient.size() > MAX_WRAPPED_KEY_LEN {
+        return Err(Invalid::Key.into());
+    }
+    let mut rng = Rng(SystemRandom::new());
+    let mut key = [0; 32];
+    rng.try_fill_bytes(&mut key).map_err(|_| Invalid::Key)?;
+    let wrapped = recipient
+        .encrypt(&mut rng, Oaep::new_with_label::<Sha256, _>(LABEL), &key)
+        .map_err(|_| Invalid::Key)?;
+
+    inner.write_all(&(wrapped.len() as u16).to_be_bytes())?;
+    inner.write_all(&wrapped)?;
+    Ok(EncWriter::new(inner, &Key::new(key), Nonce::zero(), aad))
+}
+
+/// Returns a `DecReader` that decrypts a stream sent to the owner of the
+/// `private_key`.
+///
+/// It reads the header - the wrapped data key - from the `inner` reader
+/// immediately. A wrapped data key that cannot be unwrapped is reported
+/// as `NotAuthentic` error.
+pub fn open<A: Algorithm, R: Read>(
+    mut inner: R,
+    private_key: &RsaPrivateKey,
+    aad: Aad<A>,
+) -> io::Result<DecReader<A, R>> {
+    let mut len = [0; 2];
+    inner.read_exact(&mut len)?;
+    let len = u16::from_be_bytes(len) as usize;
+    if len > MAX_WRAPPED_KEY_LEN {
+        return Err(NotAuthentic.into());
+    }
+    let mut wrapped = vec![0; len];
+    inner.read_exact(&mut wrapped)?;
+
+    let mut rng = Rng(SystemRandom::new());
+    let key = private_key
+        .decrypt_blinded(&mut rng, Oaep::new_with_label::<Sha256, _>(LABEL), &wrapped)
+        .map_err(|_| NotAuthentic)?;
+    if key.len() != 32 {
+        return Err(NotAuthentic.into());
+    }
+    let mut bytes = [0; 32];
+    bytes.copy_from_slice(&key);
+    Ok(DecReader::new(inner, &Key::new(bytes), Nonce::zero(), aad))
+}
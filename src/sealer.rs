@@ -0,0 +1,221 @@
+// Copyright (c) 2019 Andreas Auernhammer. All rights reserved.
+// Use of this source code is governed by a license that can be
+// found in the LICENSE file.
+
+use super::oneshot::final_fragment;
+use super::{
+    Aad, Algorithm, Exceeded, Invalid, Key, Nonce, NotAuthentic, ReplayWindow, MAX_BUF_SIZE,
+};
+use std::io;
+
+/// Encrypts many independent messages under one key.
+///
+/// Queue and event systems often store every record on its own such that
+/// each record can be decrypted individually. A `Sealer` encrypts each
+/// message like `seal_one` - i.e. as a stream consisting of one final
+/// fragment - and derives a unique nonce for every message. Hence, a
+/// message can be decrypted by `open_one` using the nonce returned by
+/// `seal`. In contrast to `seal_one`, the key schedule of the algorithm
+/// is computed only once.
+///
+/// The nonce of a message is the `Nonce` passed to `new` with its last
+/// 4 bytes replaced by a big-endian message counter. The counter starts
+/// at the value of these 4 bytes. Therefore, a `Sealer` encrypts at most
+/// 2^32 messages and fails with `Exceeded` afterwards. Applications that
+/// use the same key for more than one `Sealer` must ensure that their
+/// nonce ranges don't overlap - e.g. by using distinct nonce prefixes.
+///
+/// # Examples
+///
+/// ```
+/// use sio::{Key, Nonce, Aad, Sealer, CHACHA20_POLY1305};
+///
+/// // Obviously, don't use this all-zeros key for anything real.
+/// let key: Key<CHACHA20_POLY1305> = Key::new([0; Key::<CHACHA20_POLY1305>::SIZE]);
+///
+/// let mut sealer = Sealer::new(&key, Nonce::new([0; Nonce::<CHACHA20_POLY1305>::SIZE]));
+/// let records: Vec<_> = ["Hello", "World"]
+///     .iter()
+///     .map(|message| sealer.seal(Aad::empty(), message.as_bytes()).unwrap())
+///     .collect();
+///
+/// let (nonce, ciphertext) = &records[1];
+/// let plaintext = sio::open_one(&key, *nonce, Aad::empty(), ciphertext).unwrap();
+/// assert_eq!(plaintext, b"World");
+/// ```
+pub struct Sealer<A: Algorithm> {
+    algorithm: A,
+    nonce: Nonce<A>,
+    seq_num: u32,
+    exceeded: bool,
+}
+
+impl<A: Algorithm> Sealer<A> {
+    /// Creates a new `Sealer` that derives the nonces of its messages
+    /// from `nonce`.
+    pub fn new(key: &Key<A>, nonce: Nonce<A>) -> Self {
+        let counter = &nonce.as_ref()[Nonce::<A>::SIZE - 4..];
+        Sealer {
+            algorithm: A::new(key.as_ref()),
+            nonce,
+            seq_num: u32::from_be_bytes([counter[0], counter[1], counter[2], counter[3]]),
+            exceeded: false,
+        }
+    }
+
+    /// Encrypts and authenticates the `plaintext` and returns the nonce
+    /// of the message together with its ciphertext.
+    ///
+    /// The `plaintext` must not be larger than `MAX_BUF_SIZE`. Rejected
+    /// messages don't consume a nonce.
+    pub fn seal(&mut self, aad: Aad<A>, plaintext: &[u8]) -> io::Result<(Nonce<A>, Vec<u8>)> {
+        if plaintext.len() > MAX_BUF_SIZE {
+            return Err(Invalid::BufSize.into());
+        }
+        let mut ciphertext = vec![0; plaintext.len() + A::TAG_LEN];
+        ciphertext[..plaintext.len()].copy_from_slice(plaintext);
+        let nonce = self.seal_in_place(aad, &mut ciphertext)?;
+        Ok((nonce, ciphertext))
+    }
+
+    /// Encrypts and authenticates the plaintext in `in_out` in place like
+    /// `seal_one_in_place` and returns the nonce of the message.
+    ///
+    /// The caller must have checked the size of the plaintext.
+    pub(crate) fn seal_in_place(&mut self, aad: Aad<A>, in_out: &mut [u8]) -> io::Result<Nonce<A>> {
+        if self.exceeded {
+            return Err(Exceeded::default().into());
+        }
+
+        let mut nonce = self.nonce.as_ref().to_vec();
+        let len = nonce.len();
+        nonce[len - 4..].copy_from_slice(&self.seq_num.to_be_bytes());
+        let nonce = Nonce::from_slice(&nonce);
+
+        let algorithm = &self.algorithm;
+        final_fragment(algorithm, nonce, aad, |nonce, aad| {
+            algorithm.seal_in_place(nonce, aad, in_out).map(|_| ())
+        })??;
+
+        match self.seq_num.checked_add(1) {
+            Some(seq_num) => self.seq_num = seq_num,
+            None => self.exceeded = true,
+        }
+        Ok(nonce)
+    }
+
+    /// Returns the number of messages that can still be encrypted.
+    #[inline]
+    pub fn remaining(&self) -> u64 {
+        if self.exceeded {
+            0
+        } else {
+            u64::from(u32::MAX - self.seq_num) + 1
+        }
+    }
+}
+
+/// Decrypts and verifies the messages of a `Sealer` and rejects replayed
+/// messages.
+///
+/// An `Opener` accepts messages in any order but every message at most
+/// once. It reads the message counter from the last 4 bytes of the nonce
+/// of a message and tracks the counters it has accepted in a
+/// `ReplayWindow`. Messages that are older than the window are rejected
+/// as well. Messages that are not authentic never change the window.
+///
+/// # Examples
+///
+/// ```
+/// use sio::{Key, Nonce, Aad, Opener, Sealer, CHACHA20_POLY1305};
+///
+/// // Obviously, don't use this all-zeros key for anything real.
+/// let key: Key<CHACHA20_POLY1305> = Key::new([0; Key::<CHACHA20_POLY1305>::SIZE]);
+/// let nonce = Nonce::new([0; Nonce::<CHACHA20_POLY1305>::SIZE]);
+///
+/// let mut sealer = Sealer::new(&key, nonce);
+/// let (nonce_1, record_1) = sealer.seal(Aad::empty(), b"Hello").unwrap();
+/// let (nonce_2, record_2) = sealer.seal(Aad::empty(), b"World").unwrap();
+///
+/// let mut opener = Opener::new(&key, nonce);
+/// assert_eq!(opener.open(nonce_2, Aad::empty(), &record_2).unwrap(), b"World");
+/// assert_eq!(opener.open(nonce_1, Aad::empty(), &record_1).unwrap(), b"Hello");
+/// assert!(opener.open(nonce_1, Aad::empty(), &record_1).is_err()); // replayed
+/// ```
+pub struct Opener<A: Algorithm> {
+    algorithm: A,
+    nonce: Nonce<A>,
+    window: ReplayWindow,
+}
+
+impl<A: Algorithm> Opener<A> {
+    /// Creates a new `Opener` with the default `ReplayWindow` that decrypts
+    /// the messages of a `Sealer` created with the same `key` and `nonce`.
+    pub fn new(key: &Key<A>, nonce: Nonce<A>) -> Self {
+        Self::with_window(key, nonce, ReplayWindow::default())
+    }
+
+    /// Creates a new `Opener` that tracks the accepted messages in the
+    /// given `window`.
+    pub fn with_window(key: &Key<A>, nonce: Nonce<A>, window: ReplayWindow) -> Self {
+        Opener {
+            algorithm: A::new(key.as_ref()),
+            nonce,
+            window,
+        }
+    }
+
+    /// Returns the window of accepted messages.
+    #[inline]
+    pub fn window(&self) -> &ReplayWindow {
+        &self.window
+    }
+
+    /// Decrypts and verifies the message with the given `nonce` and returns
+    /// its plaintext.
+    ///
+    /// It fails with `NotAuthentic` if the `nonce` has not been produced by
+    /// a `Sealer` with the same nonce or if the `ciphertext` has been
+    /// modified or encrypted with a different key or associated data. It
+    /// fails with `Replayed` if the message has already been accepted or
+    /// is too old.
+    pub fn open(&mut self, nonce: Nonce<A>, aad: Aad<A>, ciphertext: &[u8]) -> io::Result<Vec<u8>> {
+        let (prefix, seq_num) = nonce.as_ref().split_at(Nonce::<A>::SIZE - 4);
+        if prefix != &self.nonce.as_ref()[..prefix.len()] {
+            return Err(NotAuthentic.into());
+        }
+        let seq_num = u32::from_be_bytes([seq_num[0], seq_num[1], seq_num[2], seq_num[3]]);
+        self.open_seq_num(seq_num, aad, ciphertext)
+    }
+
+    /// Decrypts and verifies the message with the sequence number
+    /// `seq_num` and accepts it if it is authentic and not replayed.
+    pub(crate) fn open_seq_num(
+        &mut self,
+        seq_num: u32,
+        aad: Aad<A>,
+        ciphertext: &[u8],
+    ) -> io::Result<Vec<u8>> {
+        if ciphertext.len() < A::TAG_LEN {
+            return Err(NotAuthentic.into());
+        }
+        self.window.check(seq_num)?;
+
+        let mut nonce = self.nonce.as_ref().to_vec();
+        let len = nonce.len();
+        nonce[len - 4..].copy_from_slice(&seq_num.to_be_bytes());
+
+        let mut plaintext = ciphertext.to_vec();
+        let algorithm = &self.algorithm;
+        let len = final_fragment(algorithm, Nonce::from_slice(&nonce), aad, |nonce, aad| {
+            algorithm
+                .open_in_place(nonce, aad, &mut plaintext)
+                .map(|p| p.len())
+        })
+        .map_err(|_| NotAuthentic)??;
+        plaintext.truncate(len);
+
+        self.window.accept(seq_num)?;
+        Ok(plaintext)
+    }
+}
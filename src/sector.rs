@@ -0,0 +1,235 @@
+// Copyright (c) 2019 Andreas Auernhammer. All rights reserved.
+// Use of this source code is governed by a license that can be
+// found in the LICENSE file.
+
+use super::utils;
+use super::{Aad, Algorithm, Exceeded, Invalid, Key, NotAuthentic, MAX_BUF_SIZE, MAX_NONCE_LEN};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+
+/// The size of the header that precedes the ciphertext of every sector.
+/// It contains the big-endian write generation of the sector.
+const HEADER_LEN: usize = 4;
+
+/// Encrypted storage that consists of fixed-size sectors - e.g. the
+/// backing store of an encrypted disk image or loopback device.
+///
+/// An `EncWriter` produces append-only streams. A block device, in
+/// contrast, overwrites arbitrary sectors in place. A `SectorDevice`
+/// encrypts and authenticates every sector on its own such that each
+/// sector can be read and overwritten independently of all others.
+///
+/// Every sector is stored as a 4 byte write generation, followed by the
+/// ciphertext of the sector and the authentication tag. Hence, a sector of
+/// `sector_size` bytes occupies `sector_size + 4 + A::TAG_LEN` bytes of the
+/// `inner` storage. The nonce of a sector is derived from the sector number
+/// and its generation, which is incremented whenever the sector gets
+/// overwritten. Therefore, no nonce is used twice and a sector cannot be
+/// moved to a different position without being detected.
+///
+/// Sectors that have never been written - i.e. sectors beyond the end of
+/// the `inner` storage or sectors that consist of zeros only - cannot be
+/// read. They must be written first - e.g. when the device is formatted.
+///
+/// # Security
+///
+/// Before a sector gets overwritten, its current content is verified to
+/// obtain its generation. An attacker who can replace a sector with a
+/// previous version of it - or with zeros - can cause the next write to
+/// reuse a nonce. Reusing a nonce breaks the security of the encryption
+/// algorithm. Therefore, a `SectorDevice` must only be used when the
+/// storage cannot be rolled back by an attacker. Further, the `key` must
+/// not be used for anything else.
+///
+/// # Examples
+///
+/// ```
+/// use std::io::Cursor;
+/// use sio::{Key, Aad, SectorDevice, CHACHA20_POLY1305};
+///
+/// // Obviously, don't use this all-zeros key for anything real.
+/// let key: Key<CHACHA20_POLY1305> = Key::new([0; Key::<CHACHA20_POLY1305>::SIZE]);
+///
+/// let mut device = SectorDevice::new(Cursor::new(Vec::default()), &key, Aad::empty());
+/// device.write_sector(3, &[1; 4096]).unwrap();
+/// device.write_sector(3, &[2; 4096]).unwrap();
+///
+/// let mut sector = [0; 4096];
+/// device.read_sector(3, &mut sector).unwrap();
+/// assert_eq!(sector, [2; 4096]);
+/// assert_eq!(device.sectors().unwrap(), 4);
+/// ```
+pub struct SectorDevice<A: Algorithm, S: Read + Write + Seek> {
+    inner: S,
+    algorithm: A,
+    aad: Vec<u8>,
+    sector_size: usize,
+    buffer: Box<[u8]>,
+}
+
+impl<A: Algorithm, S: Read + Write + Seek> SectorDevice<A, S> {
+    /// The default sector size of 4 KiB.
+    pub const DEFAULT_SECTOR_SIZE: usize = 4096;
+
+    /// Creates a new `SectorDevice` with the default sector size of 4 KiB.
+    ///
+    /// The `aad` is authenticated as part of every sector.
+    pub fn new(inner: S, key: &Key<A>, aad: Aad<A>) -> Self {
+        Self::with_sector_size(inner, key, aad, Self::DEFAULT_SECTOR_SIZE).unwrap()
+    }
+
+    /// Creates a new `SectorDevice` with the specified sector size. The
+    /// `sector_size` must not be `0` nor greater than `MAX_BUF_SIZE` and
+    /// must match the sector size used to write any existing sectors.
+    pub fn with_sector_size(
+        inner: S,
+        key: &Key<A>,
+        aad: Aad<A>,
+        sector_size: usize,
+    ) -> Result<Self, Invalid> {
+        if sector_size == 0 || sector_size > MAX_BUF_SIZE {
+            return Err(Invalid::BufSize);
+        }
+        Ok(SectorDevice {
+            inner,
+            algorithm: A::new(key.as_ref()),
+            aad: aad.as_ref().to_vec(),
+            sector_size,
+            buffer: vec![0; HEADER_LEN + sector_size + A::TAG_LEN].into_boxed_slice(),
+        })
+    }
+
+    /// Returns the size of a sector.
+    #[inline]
+    pub fn sector_size(&self) -> usize {
+        self.sector_size
+    }
+
+    /// Returns the number of sectors that fit into the `inner` storage -
+    /// including sectors that have never been written.
+    pub fn sectors(&mut self) -> io::Result<u64> {
+        let size = self.inner.seek(SeekFrom::End(0))?;
+        Ok(size.div_ceil(self.buffer.len() as u64))
+    }
+
+    /// Decrypts and verifies the sector with the given number and writes
+    /// its plaintext to `buf`, which must be `sector_size` bytes long.
+    ///
+    /// It fails with `NotAuthentic` if the sector has been modified, moved
+    /// or has never been written.
+    pub fn read_sector(&mut self, sector: u64, buf: &mut [u8]) -> io::Result<()> {
+        if buf.len() != self.sector_size {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "buffer does not match the sector size",
+            ));
+        }
+        if self.load(sector)?.is_none() {
+            return Err(NotAuthentic.into());
+        }
+        buf.copy_from_slice(&self.buffer[HEADER_LEN..HEADER_LEN + self.sector_size]);
+        Ok(())
+    }
+
+    /// Encrypts `data`, which must be `sector_size` bytes long, and
+    /// overwrites the sector with the given number.
+    ///
+    /// The current content of the sector - if any - gets verified first.
+    /// It fails with `NotAuthentic` if the sector has been modified and
+    /// with `Exceeded` if the sector has been overwritten too often. The
+    /// sector remains unchanged in both cases.
+    pub fn write_sector(&mut self, sector: u64, data: &[u8]) -> io::Result<()> {
+        if data.len() != self.sector_size {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "data does not match the sector size",
+            ));
+        }
+        let generation = self
+            .load(sector)?
+            .unwrap_or(0)
+            .checked_add(1)
+            .ok_or_else(Exceeded::default)?;
+
+        let nonce = self.nonce(sector, generation)?;
+        self.buffer[..HEADER_LEN].copy_from_slice(&generation.to_be_bytes());
+        self.buffer[HEADER_LEN..HEADER_LEN + self.sector_size].copy_from_slice(data);
+        self.algorithm.seal_in_place(
+            &nonce[..A::NONCE_LEN],
+            &self.aad,
+            &mut self.buffer[HEADER_LEN..],
+        )?;
+
+        let offset = self.offset(sector)?;
+        self.inner.seek(SeekFrom::Start(offset))?;
+        utils::write_all(
+            &mut self.inner,
+            &self.buffer,
+            format_args!("the ciphertext of sector {}", sector),
+        )
+    }
+
+    /// Unwraps the `SectorDevice` and returns the inner storage.
+    #[inline]
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+
+    /// Reads the sector with the given number into the buffer and decrypts
+    /// and verifies it. It returns the generation of the sector - or `None`
+    /// if the sector has never been written.
+    fn load(&mut self, sector: u64) -> io::Result<Option<u32>> {
+        let offset = self.offset(sector)?;
+        let size = self.inner.seek(SeekFrom::End(0))?;
+        if offset >= size {
+            return Ok(None);
+        }
+        if size - offset < self.buffer.len() as u64 {
+            return Err(NotAuthentic.into());
+        }
+        self.inner.seek(SeekFrom::Start(offset))?;
+        self.inner.read_exact(&mut self.buffer)?;
+        if self.buffer.iter().all(|b| *b == 0) {
+            return Ok(None);
+        }
+
+        let mut generation = [0; HEADER_LEN];
+        generation.copy_from_slice(&self.buffer[..HEADER_LEN]);
+        let generation = u32::from_be_bytes(generation);
+        if generation == 0 {
+            return Err(NotAuthentic.into());
+        }
+        let nonce = self.nonce(sector, generation)?;
+        self.algorithm.open_in_place(
+            &nonce[..A::NONCE_LEN],
+            &self.aad,
+            &mut self.buffer[HEADER_LEN..],
+        )?;
+        Ok(Some(generation))
+    }
+
+    /// Returns the nonce of the sector with the given number and
+    /// generation. The generation occupies the last 4 bytes of the nonce
+    /// and the sector number the - at most 8 - bytes before.
+    fn nonce(&self, sector: u64, generation: u32) -> io::Result<[u8; MAX_NONCE_LEN]> {
+        let mut nonce = [0; MAX_NONCE_LEN];
+        let nonce_len = A::NONCE_LEN;
+        let len = (nonce_len - 4).min(8);
+        if len < 8 && sector >> (8 * len) != 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "sector number exceeds the nonce",
+            ));
+        }
+        nonce[nonce_len - 4 - len..nonce_len - 4].copy_from_slice(&sector.to_be_bytes()[8 - len..]);
+        nonce[nonce_len - 4..nonce_len].copy_from_slice(&generation.to_be_bytes());
+        Ok(nonce)
+    }
+
+    /// Returns the offset of the sector with the given number within
+    /// the `inner` storage.
+    fn offset(&self, sector: u64) -> io::Result<u64> {
+        sector
+            .checked_mul(self.buffer.len() as u64)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "sector number too large"))
+    }
+}
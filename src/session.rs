@@ -0,0 +1,260 @@
+// Copyright (c) 2019 Andreas Auernhammer. All rights reserved.
+// Use of this source code is governed by a license that can be
+// found in the LICENSE file.
+
+use super::writer::Close;
+use super::{
+    Aad, Algorithm, CounterStore, DecWriter, EncWriter, Exceeded, Key, Nonce, MAX_NONCE_LEN,
+};
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+/// A key together with the nonces that have been used with it.
+///
+/// Every `EncWriter` must use a unique key-nonce combination. Reusing a
+/// nonce for the same key breaks the security of the encryption algorithm.
+/// A `Session` takes this burden from the individual call sites: It owns
+/// the key and hands out a fresh nonce for every stream it encrypts. The
+/// nonce of a stream is returned together with its `EncWriter` such that
+/// it can be stored next to the ciphertext.
+///
+/// The nonces are derived from a stream counter that starts at `0`. The
+/// counter is encoded as big-endian integer into the last - at most 8 -
+/// bytes of the `Nonce`. Hence, a `Session` must be the only user of its
+/// key. A process that restarts must continue where the previous session
+/// has stopped - e.g. using `resume` with a persisted counter or by
+/// creating the session `with_store`.
+///
+/// A `Session` refuses to encrypt more than `limit` streams. By default,
+/// the limit is 2^32 streams - or less if the `Nonce` is too short to
+/// encode that many. Once the limit has been reached, `enc_writer` fails
+/// with `Exceeded` and the key should be rotated.
+///
+/// A `Session` can be shared between threads. Every stream gets a
+/// different nonce - even if streams are created concurrently.
+///
+/// # Examples
+///
+/// ```
+/// use std::io::Write;
+/// use sio::{Key, Aad, Session, CHACHA20_POLY1305};
+///
+/// // Obviously, don't use this all-zeros key for anything real.
+/// let session = Session::new(Key::<CHACHA20_POLY1305>::new([0; Key::<CHACHA20_POLY1305>::SIZE]));
+///
+/// let mut ciphertext: Vec<u8> = Vec::default();
+/// let (nonce, mut writer) = session.enc_writer(&mut ciphertext, Aad::empty()).unwrap();
+/// writer.write_all(b"Hello World").unwrap();
+/// writer.close().unwrap();
+///
+/// let mut plaintext: Vec<u8> = Vec::default();
+/// let mut writer = session.dec_writer(&mut plaintext, nonce, Aad::empty());
+/// writer.write_all(&ciphertext).unwrap();
+/// writer.close().unwrap();
+///
+/// assert_eq!(plaintext, b"Hello World");
+/// ```
+pub struct Session<A: Algorithm> {
+    key: Key<A>,
+    next: AtomicU64,
+    limit: u64,
+    store: Option<Persisted>,
+}
+
+/// The `CounterStore` of a persistent `Session`.
+struct Persisted {
+    store: Box<dyn CounterStore>,
+    reserved: AtomicU64,
+    reserve: u64,
+    lock: Mutex<()>,
+}
+
+impl<A: Algorithm> Session<A> {
+    /// The default maximum number of streams encrypted by a `Session`.
+    pub const DEFAULT_LIMIT: u64 = 1 << 32;
+
+    /// The default number of stream counters a persistent `Session`
+    /// reserves with one update of its `CounterStore`.
+    pub const DEFAULT_RESERVE: u64 = 1024;
+
+    /// Creates a new `Session` that owns the `key`.
+    pub fn new(key: Key<A>) -> Self {
+        Session {
+            key,
+            next: AtomicU64::new(0),
+            limit: Self::DEFAULT_LIMIT.min(Self::max_streams()),
+            store: None,
+        }
+    }
+
+    /// Creates a new persistent `Session` that owns the `key` and keeps
+    /// its stream counter in the `store`.
+    ///
+    /// The session continues with the counter value loaded from the
+    /// `store`. Before it encrypts a stream with a counter that has not
+    /// been reserved yet, it reserves the next `DEFAULT_RESERVE` counters
+    /// by storing the end of the reservation. Hence, a crashed process
+    /// never reuses a nonce once it restarts with the same `store`. It
+    /// just skips the counters it had reserved but not used.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sio::{Key, Aad, Session, FileStore, CHACHA20_POLY1305};
+    ///
+    /// let path = std::env::temp_dir().join("sio-doc-session");
+    /// # let _ = std::fs::remove_file(&path);
+    ///
+    /// // Obviously, don't use this all-zeros key for anything real.
+    /// let key = Key::<CHACHA20_POLY1305>::new([0; Key::<CHACHA20_POLY1305>::SIZE]);
+    /// let session = Session::with_store(key, FileStore::new(&path)).unwrap();
+    /// let (nonce, writer) = session.enc_writer(Vec::default(), Aad::empty()).unwrap();
+    /// writer.close().unwrap();
+    ///
+    /// // After a restart, the session continues after its last reservation.
+    /// let key = Key::<CHACHA20_POLY1305>::new([0; Key::<CHACHA20_POLY1305>::SIZE]);
+    /// let session = Session::with_store(key, FileStore::new(&path)).unwrap();
+    /// assert_eq!(session.streams(), Session::<CHACHA20_POLY1305>::DEFAULT_RESERVE);
+    /// # std::fs::remove_file(&path).unwrap();
+    /// ```
+    pub fn with_store<S: CounterStore + 'static>(key: Key<A>, store: S) -> io::Result<Self> {
+        let next = store.load()?;
+        let mut session = Self::new(key).resume(next);
+        session.store = Some(Persisted {
+            store: Box::new(store),
+            reserved: AtomicU64::new(next),
+            reserve: Self::DEFAULT_RESERVE,
+            lock: Mutex::new(()),
+        });
+        Ok(session)
+    }
+
+    /// Sets the number of stream counters a persistent `Session` reserves
+    /// with one update of its `CounterStore`.
+    ///
+    /// A larger reservation causes fewer updates but skips more counters
+    /// after a crash. It has no effect if the session is not persistent.
+    pub fn reserve(mut self, streams: u64) -> Self {
+        if let Some(ref mut persisted) = self.store {
+            persisted.reserve = streams.max(1);
+        }
+        self
+    }
+
+    /// Sets the maximum number of streams - including the ones encrypted
+    /// by previous sessions that have been resumed - to `limit`.
+    ///
+    /// The limit cannot exceed the number of distinct nonces that can
+    /// be derived from the stream counter.
+    pub fn limit(mut self, limit: u64) -> Self {
+        self.limit = limit.min(Self::max_streams());
+        self
+    }
+
+    /// Continues a previous session of the same key that has encrypted
+    /// `streams` streams. The next stream gets the nonce derived from the
+    /// stream counter `streams`.
+    pub fn resume(self, streams: u64) -> Self {
+        self.next.store(streams, Ordering::SeqCst);
+        self
+    }
+
+    /// Returns the key of the session.
+    #[inline]
+    pub fn key(&self) -> &Key<A> {
+        &self.key
+    }
+
+    /// Returns the number of streams encrypted so far - including the
+    /// ones of resumed sessions. It should be persisted to `resume` the
+    /// session later.
+    #[inline]
+    pub fn streams(&self) -> u64 {
+        self.next.load(Ordering::SeqCst)
+    }
+
+    /// Returns the number of streams that can still be encrypted.
+    #[inline]
+    pub fn remaining(&self) -> u64 {
+        self.limit.saturating_sub(self.streams())
+    }
+
+    /// Returns a new `EncWriter` that encrypts a stream with a fresh
+    /// nonce. The nonce is required to decrypt the stream again.
+    ///
+    /// It fails with `Exceeded` once the session has reached its limit -
+    /// or with the error of the `CounterStore` if the session cannot
+    /// reserve more stream counters.
+    pub fn enc_writer<W: Write + Close>(
+        &self,
+        inner: W,
+        aad: Aad<A>,
+    ) -> io::Result<(Nonce<A>, EncWriter<A, W>)> {
+        let nonce = self.next_nonce()?;
+        Ok((nonce, EncWriter::new(inner, &self.key, nonce, aad)))
+    }
+
+    /// Returns a new `DecWriter` that decrypts the stream encrypted with
+    /// the given `nonce` - i.e. the nonce returned by `enc_writer`.
+    pub fn dec_writer<W: Write + Close>(
+        &self,
+        inner: W,
+        nonce: Nonce<A>,
+        aad: Aad<A>,
+    ) -> DecWriter<A, W> {
+        DecWriter::new(inner, &self.key, nonce, aad)
+    }
+
+    /// Reserves the next stream counter and returns its nonce.
+    fn next_nonce(&self) -> io::Result<Nonce<A>> {
+        let counter = self
+            .next
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |next| {
+                if next < self.limit {
+                    Some(next + 1)
+                } else {
+                    None
+                }
+            })
+            .map_err(|_| Exceeded::default())?;
+        if let Some(ref persisted) = self.store {
+            self.reserve_counter(persisted, counter)?;
+        }
+
+        let mut nonce = [0; MAX_NONCE_LEN - 4];
+        let nonce = &mut nonce[..Nonce::<A>::SIZE];
+        let len = nonce.len().min(8);
+        let start = nonce.len() - len;
+        nonce[start..].copy_from_slice(&counter.to_be_bytes()[8 - len..]);
+        Ok(Nonce::from_slice(nonce))
+    }
+
+    /// Ensures that the `counter` has been reserved in the `CounterStore`
+    /// before it gets used. A counter that cannot be reserved is skipped.
+    fn reserve_counter(&self, persisted: &Persisted, counter: u64) -> io::Result<()> {
+        if counter < persisted.reserved.load(Ordering::SeqCst) {
+            return Ok(());
+        }
+
+        // The store is never left in an inconsistent state. Therefore,
+        // a panic of another thread does not affect the reservation.
+        let _lock = persisted.lock.lock().unwrap_or_else(|err| err.into_inner());
+        if counter < persisted.reserved.load(Ordering::SeqCst) {
+            return Ok(());
+        }
+        let reserved = counter.saturating_add(persisted.reserve).min(self.limit);
+        persisted.store.store(reserved)?;
+        persisted.reserved.store(reserved, Ordering::SeqCst);
+        Ok(())
+    }
+
+    /// Returns the number of distinct nonces that can be derived from
+    /// the stream counter.
+    fn max_streams() -> u64 {
+        match Nonce::<A>::SIZE {
+            size if size >= 8 => u64::MAX,
+            size => 1 << (8 * size),
+        }
+    }
+}
@@ -0,0 +1,228 @@
+// Copyright (c) 2019 Andreas Auernhammer. All rights reserved.
+// Use of this source code is governed by a license that can be
+// found in the LICENSE file.
+
+//! Ed25519 signatures over encrypted streams.
+//!
+//! The AEAD of a stream only guarantees that the ciphertext has been
+//! produced by someone who knows the secret key. A signature provides
+//! non-repudiation - i.e. it proves which party produced the ciphertext.
+//!
+//! A `SignWriter` sits between an `EncWriter` and the writer receiving
+//! the ciphertext. When closed, it appends an Ed25519 signature over a
+//! SHA-256 transcript hash of the ciphertext. The signature is not
+//! encrypted. A `VerifyReader` strips the signature again and verifies
+//! it once the entire ciphertext has been read.
+//!
+//! # Examples
+//!
+//! ```
+//! use std::io::{Read, Write};
+//! use sio::{Key, Nonce, Aad, EncWriter, DecReader, CHACHA20_POLY1305};
+//! use sio::sign::{SignWriter, SigningKey, VerifyReader};
+//!
+//! // Obviously, don't use this all-zeros key for anything real.
+//! let key: Key<CHACHA20_POLY1305> = Key::new([0; Key::<CHACHA20_POLY1305>::SIZE]);
+//!
+//! // The seed of the signing key must be 32 secret random bytes.
+//! let signing_key = SigningKey::from_seed(&[1; 32]);
+//! let verifying_key = signing_key.verifying_key();
+//!
+//! let mut ciphertext: Vec<u8> = Vec::default();
+//! let mut writer = EncWriter::new(
+//!     SignWriter::new(&mut ciphertext, &signing_key),
+//!     &key,
+//!     Nonce::new([0; Nonce::<CHACHA20_POLY1305>::SIZE]),
+//!     Aad::empty(),
+//! );
+//! writer.write_all(b"Hello World").unwrap();
+//! writer.close().unwrap();
+//!
+//! let mut reader = DecReader::new(
+//!     VerifyReader::new(ciphertext.as_slice(), &verifying_key),
+//!     &key,
+//!     Nonce::new([0; Nonce::<CHACHA20_POLY1305>::SIZE]),
+//!     Aad::empty(),
+//! );
+//! let mut plaintext = String::default();
+//! reader.read_to_string(&mut plaintext).unwrap();
+//! assert_eq!(plaintext, "Hello World");
+//! ```
+
+use super::writer::Close;
+use ring::digest::{self, Context};
+use ring::signature::{self, Ed25519KeyPair, KeyPair};
+use std::io::{self, Read, Write};
+
+/// The size of an Ed25519 signature in bytes.
+pub const SIGNATURE_LEN: usize = 64;
+
+/// The domain separation prefix of the signed transcript.
+const CONTEXT: &[u8] = b"sio ed25519 signature v1";
+
+/// Returns the message that gets signed for the `transcript` hash.
+fn message(transcript: Context) -> Vec<u8> {
+    let mut msg = CONTEXT.to_vec();
+    msg.extend_from_slice(transcript.finish().as_ref());
+    msg
+}
+
+/// An Ed25519 private key used to sign encrypted streams.
+pub struct SigningKey(Ed25519KeyPair);
+
+impl SigningKey {
+    /// Creates the signing key from a 32 byte secret `seed`.
+    ///
+    /// The seed must be generated by a cryptographically secure
+    /// random number generator and kept secret.
+    pub fn from_seed(seed: &[u8; 32]) -> Self {
+        let key = Ed25519KeyPair::from_seed_unchecked(untrusted::Input::from(seed)).unwrap();
+        SigningKey(key)
+    }
+
+    /// Returns the public key that verifies the signatures
+    /// created by this signing key.
+    pub fn verifying_key(&self) -> VerifyingKey {
+        let mut key = [0; 32];
+        key.copy_from_slice(self.0.public_key().as_ref());
+        VerifyingKey(key)
+    }
+}
+
+/// An Ed25519 public key used to verify signed encrypted streams.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct VerifyingKey([u8; 32]);
+
+impl VerifyingKey {
+    /// Creates the verifying key from its 32 byte encoding.
+    pub fn new(key: [u8; 32]) -> Self {
+        VerifyingKey(key)
+    }
+
+    fn verify(&self, msg: &[u8], sig: &[u8]) -> bool {
+        signature::verify(
+            &signature::ED25519,
+            untrusted::Input::from(&self.0),
+            untrusted::Input::from(msg),
+            untrusted::Input::from(sig),
+        )
+        .is_ok()
+    }
+}
+
+impl AsRef<[u8; 32]> for VerifyingKey {
+    fn as_ref(&self) -> &[u8; 32] {
+        &self.0
+    }
+}
+
+/// Wraps the writer receiving the ciphertext of an `EncWriter` and
+/// appends an Ed25519 signature over the ciphertext when closed.
+pub struct SignWriter<'a, W: Write + Close> {
+    inner: W,
+    key: &'a SigningKey,
+    transcript: Context,
+}
+
+impl<'a, W: Write + Close> SignWriter<'a, W> {
+    /// Creates a new `SignWriter` that signs everything written to
+    /// the `inner` writer with the signing `key`.
+    pub fn new(inner: W, key: &'a SigningKey) -> Self {
+        SignWriter {
+            inner,
+            key,
+            transcript: Context::new(&digest::SHA256),
+        }
+    }
+}
+
+impl<W: Write + Close> Write for SignWriter<'_, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.transcript.update(&buf[..n]);
+        Ok(n)
+    }
+
+    #[inline]
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl<W: Write + Close> Close for SignWriter<'_, W> {
+    fn close(&mut self) -> io::Result<()> {
+        let transcript = std::mem::replace(&mut self.transcript, Context::new(&digest::SHA256));
+        let sig = self.key.0.sign(&message(transcript));
+        self.inner.write_all(sig.as_ref())?;
+        self.inner.close()
+    }
+}
+
+/// Wraps a reader providing signed ciphertext and verifies the Ed25519
+/// signature at its end.
+///
+/// The `VerifyReader` returns the ciphertext without the signature. Once
+/// the `inner` reader reaches its end, the signature is verified and an
+/// error of kind `ErrorKind::InvalidData` is returned if the signature
+/// is not valid.
+pub struct VerifyReader<'a, R: Read> {
+    inner: R,
+    key: &'a VerifyingKey,
+    transcript: Context,
+
+    // The last bytes read from the inner reader which
+    // may belong to the signature.
+    tail: Vec<u8>,
+    done: bool,
+}
+
+impl<'a, R: Read> VerifyReader<'a, R> {
+    /// Creates a new `VerifyReader` that verifies the signature of
+    /// the ciphertext read from `inner` with the verifying `key`.
+    pub fn new(inner: R, key: &'a VerifyingKey) -> Self {
+        VerifyReader {
+            inner,
+            key,
+            transcript: Context::new(&digest::SHA256),
+            tail: Vec::with_capacity(2 * SIGNATURE_LEN),
+            done: false,
+        }
+    }
+
+    fn verify(&mut self) -> io::Result<()> {
+        let transcript = std::mem::replace(&mut self.transcript, Context::new(&digest::SHA256));
+        if self.tail.len() != SIGNATURE_LEN || !self.key.verify(&message(transcript), &self.tail) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "invalid signature",
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for VerifyReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.done || buf.is_empty() {
+            return Ok(0);
+        }
+        loop {
+            let n = self.inner.read(buf)?;
+            if n == 0 {
+                self.verify()?;
+                self.done = true;
+                return Ok(0);
+            }
+            self.tail.extend_from_slice(&buf[..n]);
+
+            // We hold back the last bytes since they may be the signature.
+            if self.tail.len() > SIGNATURE_LEN {
+                let k = self.tail.len() - SIGNATURE_LEN;
+                buf[..k].copy_from_slice(&self.tail[..k]);
+                self.transcript.update(&buf[..k]);
+                self.tail.drain(..k);
+                return Ok(k);
+            }
+        }
+    }
+}
@@ -0,0 +1,163 @@
+// Copyright (c) 2019 Andreas Auernhammer. All rights reserved.
+// Use of this source code is governed by a license that can be
+// found in the LICENSE file.
+
+//! Zero-copy forwarding of sealed fragments.
+//!
+//! Proxies and storage nodes often pass encrypted streams on without
+//! decrypting them - e.g. from a client connection to a file or to
+//! another node. `forward` writes the sealed fragments read from one file
+//! descriptor to another one. On Linux, the fragments never get copied
+//! into user space: `forward` uses `sendfile` if the source is a regular
+//! file and moves the fragments through a pipe with `splice` otherwise.
+//! On other Unix systems, it falls back to regular reads and writes.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use std::fs::File;
+//! use std::net::TcpStream;
+//! use sio::{splice, Layout, CHACHA20_POLY1305};
+//!
+//! // Send an encrypted stream of 10 MiB plaintext to a peer.
+//! let layout = Layout::of::<CHACHA20_POLY1305>(sio::BUF_SIZE).unwrap();
+//! let mut file = File::open("object.sio").unwrap();
+//! let mut conn = TcpStream::connect("10.0.0.2:9000").unwrap();
+//!
+//! let len = layout.ciphertext_len(10 << 20).unwrap();
+//! let n = splice::forward(&mut file, &mut conn, len).unwrap();
+//! assert_eq!(n, len);
+//! ```
+
+use std::io::{self, Read, Write};
+use std::os::unix::io::AsRawFd;
+
+/// Forwards up to `len` bytes of ciphertext from `src` to `dst` and
+/// returns the number of bytes forwarded.
+///
+/// It returns less than `len` bytes only if `src` reaches its end before.
+/// The ciphertext is neither decrypted nor verified. Hence, a recipient
+/// must still decrypt it with a `DecWriter` or `DecReader`. To forward a
+/// complete stream, compute its `len` with a `Layout`.
+///
+/// On Linux, the ciphertext is not copied into user space. Both, `src`
+/// and `dst`, must be blocking file descriptors.
+pub fn forward<R, W>(src: &mut R, dst: &mut W, len: u64) -> io::Result<u64>
+where
+    R: Read + AsRawFd,
+    W: Write + AsRawFd,
+{
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    {
+        if let Some(n) = linux::forward(src.as_raw_fd(), dst.as_raw_fd(), len)? {
+            return Ok(n);
+        }
+    }
+    io::copy(&mut src.take(len), dst)
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+mod linux {
+    use std::io;
+    use std::os::unix::io::{AsRawFd, FromRawFd, OwnedFd, RawFd};
+    use std::{cmp, ptr};
+
+    /// The max. number of bytes moved by one `sendfile` or `splice` call.
+    const CHUNK_SIZE: usize = 1 << 20;
+
+    /// Forwards up to `len` bytes from `src` to `dst` within the kernel.
+    ///
+    /// It returns `None` - without forwarding anything - if neither
+    /// `sendfile` nor `splice` supports the file descriptors.
+    pub(super) fn forward(src: RawFd, dst: RawFd, len: u64) -> io::Result<Option<u64>> {
+        if let Some(n) = sendfile(src, dst, len)? {
+            return Ok(Some(n));
+        }
+        splice(src, dst, len)
+    }
+
+    fn sendfile(src: RawFd, dst: RawFd, len: u64) -> io::Result<Option<u64>> {
+        let mut forwarded = 0;
+        while forwarded < len {
+            let n = chunk(len - forwarded);
+            match cvt(unsafe { libc::sendfile(dst, src, ptr::null_mut(), n) }) {
+                Ok(0) => break,
+                Ok(n) => forwarded += n as u64,
+                Err(ref err) if err.kind() == io::ErrorKind::Interrupted => (),
+                Err(ref err) if forwarded == 0 && is_unsupported(err) => return Ok(None),
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(Some(forwarded))
+    }
+
+    fn splice(src: RawFd, dst: RawFd, len: u64) -> io::Result<Option<u64>> {
+        let mut fds = [0; 2];
+        if unsafe { libc::pipe2(fds.as_mut_ptr(), libc::O_CLOEXEC) } < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let pipe = unsafe { [OwnedFd::from_raw_fd(fds[0]), OwnedFd::from_raw_fd(fds[1])] };
+        let (pipe_r, pipe_w) = (pipe[0].as_raw_fd(), pipe[1].as_raw_fd());
+
+        let flags = libc::SPLICE_F_MOVE | libc::SPLICE_F_MORE;
+        let mut forwarded = 0;
+        while forwarded < len {
+            let n = chunk(len - forwarded);
+            let mut pending = match cvt(unsafe {
+                libc::splice(src, ptr::null_mut(), pipe_w, ptr::null_mut(), n, flags)
+            }) {
+                Ok(0) => break,
+                Ok(n) => n,
+                Err(ref err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                Err(ref err) if forwarded == 0 && is_unsupported(err) => return Ok(None),
+                Err(err) => return Err(err),
+            };
+
+            // The pipe must be drained completely. Otherwise, the next
+            // splice from `src` would forward the data out of order.
+            while pending > 0 {
+                match cvt(unsafe {
+                    libc::splice(
+                        pipe_r,
+                        ptr::null_mut(),
+                        dst,
+                        ptr::null_mut(),
+                        pending,
+                        flags,
+                    )
+                }) {
+                    Ok(0) => return Err(io::ErrorKind::WriteZero.into()),
+                    Ok(n) => {
+                        pending -= n;
+                        forwarded += n as u64;
+                    }
+                    Err(ref err) if err.kind() == io::ErrorKind::Interrupted => (),
+                    Err(err) => return Err(err),
+                }
+            }
+        }
+        Ok(Some(forwarded))
+    }
+
+    #[inline(always)]
+    fn chunk(remaining: u64) -> usize {
+        cmp::min(remaining, CHUNK_SIZE as u64) as usize
+    }
+
+    #[inline(always)]
+    fn cvt(n: libc::ssize_t) -> io::Result<usize> {
+        if n < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(n as usize)
+    }
+
+    /// Returns true if the error indicates that the file descriptors do
+    /// not support `sendfile` respectively `splice`.
+    fn is_unsupported(err: &io::Error) -> bool {
+        matches!(
+            err.raw_os_error(),
+            Some(libc::EINVAL) | Some(libc::ENOSYS) | Some(libc::EOPNOTSUPP)
+        )
+    }
+}
@@ -0,0 +1,176 @@
+// Copyright (c) 2019 Andreas Auernhammer. All rights reserved.
+// Use of this source code is governed by a license that can be
+// found in the LICENSE file.
+
+use super::framing::{Framing, Sio};
+use super::{Aad, Algorithm, Invalid, Key, Nonce, NotAuthentic, BUF_SIZE, MAX_BUF_SIZE};
+
+/// A decryption state machine that is not tied to any I/O model.
+///
+/// The `DecWriter` and `DecReader` decrypt data that is written to them or
+/// that they read from an inner reader. Event loops, protocol parsers and
+/// other code that manages its own buffers can use a `DecryptState`
+/// instead. It takes ciphertext slices of any size via `update` and yields
+/// the plaintext of every fragment as soon as it has been verified. The
+/// plaintext is a view into the internal fragment buffer. Hence, no data is
+/// copied besides buffering the ciphertext of the current fragment.
+///
+/// A `DecryptState` decrypts streams with the default `Sio` framing - e.g.
+/// produced by an `EncWriter` with the same buffer size. `needed` reports
+/// how many bytes of ciphertext the `DecryptState` requires before it can
+/// verify the next fragment. Once all ciphertext has been passed to
+/// `update`, `finish` verifies the final fragment.
+///
+/// # Examples
+///
+/// ```
+/// use sio::{Key, Nonce, Aad, DecryptState, CHACHA20_POLY1305};
+///
+/// // Obviously, don't use this all-zeros key for anything real.
+/// let key: Key<CHACHA20_POLY1305> = Key::new([0; Key::<CHACHA20_POLY1305>::SIZE]);
+///
+/// let ciphertext = sio::encrypt_vec(
+///     &key,
+///     Nonce::new([0; Nonce::<CHACHA20_POLY1305>::SIZE]),
+///     Aad::empty(),
+///     b"Hello World",
+/// );
+///
+/// let mut state = DecryptState::new(
+///     &key,
+///     Nonce::new([0; Nonce::<CHACHA20_POLY1305>::SIZE]),
+///     Aad::empty(),
+/// );
+///
+/// let mut plaintext: Vec<u8> = Vec::default();
+/// let mut input = ciphertext.as_slice();
+/// while !input.is_empty() {
+///     let (n, fragment) = state.update(input).unwrap();
+///     plaintext.extend_from_slice(fragment);
+///     input = &input[n..];
+/// }
+/// plaintext.extend_from_slice(state.finish().unwrap());
+/// assert_eq!(plaintext, b"Hello World");
+/// ```
+pub struct DecryptState<A: Algorithm> {
+    algorithm: A,
+    framing: Sio,
+    buffer: Box<[u8]>,
+    pos: usize,
+    errored: bool,
+    finished: bool,
+}
+
+impl<A: Algorithm> DecryptState<A> {
+    /// Creates a new `DecryptState` with a default buffer size of 16 KiB.
+    ///
+    /// The `key`, `nonce` and `aad` must match the ones used for
+    /// encryption.
+    pub fn new(key: &Key<A>, nonce: Nonce<A>, aad: Aad<A>) -> Self {
+        Self::with_buffer_size(key, nonce, aad, BUF_SIZE).unwrap()
+    }
+
+    /// Creates a new `DecryptState` with the specified buffer size as
+    /// fragment size. The `buf_size` must not be `0` nor greater than
+    /// `MAX_BUF_SIZE` and must match the buffer size used for encryption.
+    pub fn with_buffer_size(
+        key: &Key<A>,
+        nonce: Nonce<A>,
+        aad: Aad<A>,
+        buf_size: usize,
+    ) -> Result<Self, Invalid> {
+        if buf_size == 0 || buf_size > MAX_BUF_SIZE {
+            return Err(Invalid::BufSize);
+        }
+        let algorithm = A::new(key.as_ref());
+        let mut framing = Sio::default();
+        framing.init(key.as_ref(), &algorithm, nonce.as_ref(), aad.as_ref())?;
+        Ok(DecryptState {
+            algorithm,
+            framing,
+            buffer: vec![0; buf_size + A::TAG_LEN].into_boxed_slice(),
+            pos: 0,
+            errored: false,
+            finished: false,
+        })
+    }
+
+    /// Returns the number of bytes of ciphertext the `DecryptState` needs
+    /// before `update` can yield the plaintext of the next fragment.
+    ///
+    /// Once a complete fragment has been buffered, it returns `1`: The
+    /// fragment is only verified once it is known that it is not the final
+    /// fragment - i.e. once the next byte is passed to `update`. It returns
+    /// `0` once the `DecryptState` has been finished or has failed.
+    pub fn needed(&self) -> usize {
+        if self.errored || self.finished {
+            return 0;
+        }
+        match self.buffer.len() - self.pos {
+            0 => 1,
+            n => n,
+        }
+    }
+
+    /// Consumes a prefix of the `ciphertext` and returns the number of
+    /// bytes consumed together with the plaintext of the fragment verified
+    /// by this call - if any.
+    ///
+    /// The plaintext is empty unless a fragment has been verified. A
+    /// non-empty `ciphertext` is consumed only partially if the fragment
+    /// buffer is full. In this case, the buffered fragment gets verified
+    /// and its plaintext is returned while no byte is consumed. Hence,
+    /// `update` must be called until the entire `ciphertext` is consumed.
+    ///
+    /// Once `update` has failed or the `DecryptState` has been finished,
+    /// any further call fails with `NotAuthentic`.
+    pub fn update(&mut self, ciphertext: &[u8]) -> Result<(usize, &[u8]), NotAuthentic> {
+        if self.errored || self.finished {
+            return Err(NotAuthentic);
+        }
+        if ciphertext.is_empty() {
+            return Ok((0, &[]));
+        }
+        if self.pos == self.buffer.len() {
+            // More ciphertext follows. Hence, the buffered
+            // fragment is not the final one.
+            return self.open(false).map(|plaintext| (0, plaintext));
+        }
+        let n = ciphertext.len().min(self.buffer.len() - self.pos);
+        self.buffer[self.pos..self.pos + n].copy_from_slice(&ciphertext[..n]);
+        self.pos += n;
+        Ok((n, &[]))
+    }
+
+    /// Verifies the buffered ciphertext as final fragment and returns its
+    /// plaintext.
+    ///
+    /// It fails with `NotAuthentic` if the stream has been truncated or
+    /// modified or if `finish` has been called before.
+    pub fn finish(&mut self) -> Result<&[u8], NotAuthentic> {
+        if self.errored || self.finished {
+            return Err(NotAuthentic);
+        }
+        self.finished = true;
+        self.open(true)
+    }
+
+    /// Decrypts and verifies the buffered ciphertext as one fragment.
+    fn open(&mut self, last: bool) -> Result<&[u8], NotAuthentic> {
+        let len = self.pos;
+        self.pos = 0;
+        self.errored = true;
+        if len < A::TAG_LEN {
+            return Err(NotAuthentic);
+        }
+        let (nonce, aad) = self
+            .framing
+            .next(&mut self.algorithm, last)
+            .map_err(|_| NotAuthentic)?;
+        let plaintext = self
+            .algorithm
+            .open_in_place(nonce, aad, &mut self.buffer[..len])?;
+        self.errored = false;
+        Ok(plaintext)
+    }
+}
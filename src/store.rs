@@ -0,0 +1,118 @@
+// Copyright (c) 2019 Andreas Auernhammer. All rights reserved.
+// Use of this source code is governed by a license that can be
+// found in the LICENSE file.
+
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// A durable location for the stream counter of a `Session`.
+///
+/// A `Session` derives the nonce of every stream from a counter. Once a
+/// process restarts, it must continue with a counter value that has not
+/// been used before - otherwise it would reuse nonces. A `CounterStore`
+/// keeps the counter across restarts and crashes. A persistent `Session`
+/// stores a counter value before it uses any counter below it. Hence, the
+/// stored value is always an upper bound of all used counters.
+///
+/// The `FileStore` keeps the counter in a file. Applications can implement
+/// `CounterStore` for any other storage - e.g. a database row - as long as
+/// `store` returns only once the value has been persisted.
+pub trait CounterStore: Send + Sync {
+    /// Returns the stored counter value or `0` if no value has been
+    /// stored yet.
+    fn load(&self) -> io::Result<u64>;
+
+    /// Persists the counter value `next`. It must not return before the
+    /// value survives a crash.
+    fn store(&self, next: u64) -> io::Result<()>;
+}
+
+impl<S: CounterStore + ?Sized> CounterStore for Arc<S> {
+    fn load(&self) -> io::Result<u64> {
+        (**self).load()
+    }
+
+    fn store(&self, next: u64) -> io::Result<()> {
+        (**self).store(next)
+    }
+}
+
+/// A `CounterStore` that keeps the counter in a file.
+///
+/// The counter is stored as decimal number. Updates are atomic: The new
+/// value is written and synced to a temporary file next to the counter
+/// file, which then replaces the counter file. Hence, the file contains
+/// either the old or the new value - even if the process crashes during
+/// an update.
+///
+/// # Examples
+///
+/// ```
+/// use sio::{CounterStore, FileStore};
+///
+/// let path = std::env::temp_dir().join("sio-doc-counter");
+/// # let _ = std::fs::remove_file(&path);
+/// let store = FileStore::new(&path);
+/// assert_eq!(store.load().unwrap(), 0);
+///
+/// store.store(1024).unwrap();
+/// assert_eq!(FileStore::new(&path).load().unwrap(), 1024);
+/// # std::fs::remove_file(&path).unwrap();
+/// ```
+#[derive(Clone, Debug)]
+pub struct FileStore {
+    path: PathBuf,
+}
+
+impl FileStore {
+    /// Creates a new `FileStore` that keeps the counter in the file at
+    /// `path`. The file is created on the first `store`.
+    pub fn new<P: AsRef<Path>>(path: P) -> Self {
+        FileStore {
+            path: path.as_ref().to_path_buf(),
+        }
+    }
+
+    /// Returns the path of the counter file.
+    #[inline]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl CounterStore for FileStore {
+    fn load(&self) -> io::Result<u64> {
+        match fs::read_to_string(&self.path) {
+            Ok(content) => content
+                .trim()
+                .parse()
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err)),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(0),
+            Err(err) => Err(err),
+        }
+    }
+
+    fn store(&self, next: u64) -> io::Result<()> {
+        let mut tmp = self.path.clone().into_os_string();
+        tmp.push(".tmp");
+
+        let mut file = File::create(&tmp)?;
+        writeln!(file, "{}", next)?;
+        file.sync_all()?;
+        fs::rename(&tmp, &self.path)?;
+
+        // The rename itself is only durable once the directory
+        // has been synced.
+        #[cfg(unix)]
+        {
+            let dir = match self.path.parent() {
+                Some(dir) if !dir.as_os_str().is_empty() => dir,
+                _ => Path::new("."),
+            };
+            File::open(dir)?.sync_all()?;
+        }
+        Ok(())
+    }
+}
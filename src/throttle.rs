@@ -0,0 +1,141 @@
+// Copyright (c) 2019 Andreas Auernhammer. All rights reserved.
+// Use of this source code is governed by a license that can be
+// found in the LICENSE file.
+
+use super::writer::Close;
+use std::io::{self, Write};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Wraps a writer and limits the rate at which data is written to it.
+///
+/// A `Throttle` blocks the calling thread until writing more data does
+/// not exceed the configured rate of bytes per second. It implements
+/// `Write` and `Close` and can be placed between an `EncWriter` and the
+/// writer receiving the ciphertext - e.g. to throttle uploads - without
+/// breaking the `Close` chain.
+///
+/// A `Throttle` does not accumulate unused capacity for more than one
+/// second. Hence, it does not allow bursts after some idle time.
+///
+/// # Examples
+///
+/// ```
+/// use std::io::Write;
+/// use sio::{Key, Nonce, Aad, EncWriter, Throttle, CHACHA20_POLY1305};
+///
+/// // Obviously, don't use this all-zeros key for anything real.
+/// let key: Key<CHACHA20_POLY1305> = Key::new([0; Key::<CHACHA20_POLY1305>::SIZE]);
+///
+/// let mut ciphertext: Vec<u8> = Vec::default();
+/// let mut writer = EncWriter::new(
+///     Throttle::new(&mut ciphertext, 1 << 20), // 1 MiB/s
+///     &key,
+///     Nonce::new([0; Nonce::<CHACHA20_POLY1305>::SIZE]),
+///     Aad::empty(),
+/// );
+/// writer.write_all(b"Hello World").unwrap();
+/// writer.close().unwrap();
+/// ```
+pub struct Throttle<W: Write> {
+    inner: W,
+    rate: u64,
+
+    // The point in time since which `written` bytes have been
+    // written to the inner writer.
+    start: Instant,
+    written: u64,
+}
+
+impl<W: Write> Throttle<W> {
+    /// Wraps the `inner` writer and limits the rate at which data is
+    /// written to it to `rate` bytes per second.
+    ///
+    /// # Panics
+    ///
+    /// It panics if the `rate` is `0`.
+    pub fn new(inner: W, rate: u64) -> Self {
+        assert!(rate > 0, "the rate must not be 0");
+        Throttle {
+            inner,
+            rate,
+            start: Instant::now(),
+            written: 0,
+        }
+    }
+
+    /// Returns the rate in bytes per second.
+    #[inline]
+    pub fn rate(&self) -> u64 {
+        self.rate
+    }
+
+    /// Changes the rate to `rate` bytes per second.
+    ///
+    /// # Panics
+    ///
+    /// It panics if the `rate` is `0`.
+    pub fn set_rate(&mut self, rate: u64) {
+        assert!(rate > 0, "the rate must not be 0");
+        self.rate = rate;
+        self.start = Instant::now();
+        self.written = 0;
+    }
+
+    /// Returns a reference to the inner writer.
+    #[inline]
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    /// Unwraps the `Throttle` and returns the inner writer.
+    #[inline]
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+
+    /// Returns the point in time - relative to `start` - at which
+    /// `n` bytes may have been written.
+    fn due(&self, n: u64) -> Duration {
+        Duration::from_secs_f64(n as f64 / self.rate as f64)
+    }
+
+    /// Blocks until `n` more bytes can be written without
+    /// exceeding the rate.
+    fn wait(&mut self, n: usize) {
+        let elapsed = self.start.elapsed();
+        if elapsed > self.due(self.written) + Duration::from_secs(1) {
+            self.start = Instant::now();
+            self.written = 0;
+        }
+        let due = self.due(self.written + n as u64);
+        let elapsed = self.start.elapsed();
+        if due > elapsed {
+            thread::sleep(due - elapsed);
+        }
+    }
+}
+
+impl<W: Write> Write for Throttle<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        // Write at most 1/8 of the rate at once to
+        // avoid long pauses between writes.
+        let n = buf.len().min((self.rate / 8).max(1) as usize);
+        self.wait(n);
+        let n = self.inner.write(&buf[..n])?;
+        self.written += n as u64;
+        Ok(n)
+    }
+
+    #[inline]
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl<W: Write + Close> Close for Throttle<W> {
+    #[inline]
+    fn close(&mut self) -> io::Result<()> {
+        self.inner.close()
+    }
+}
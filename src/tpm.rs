@@ -0,0 +1,115 @@
+// Copyright (c) 2019 Andreas Auernhammer. All rights reserved.
+// Use of this source code is governed by a license that can be
+// found in the LICENSE file.
+
+//! Stream keys sealed by a TPM 2.0.
+//!
+//! A `SealedKey` refers to a key that has been sealed to a TPM - e.g.
+//! with `tpm2_create` and `tpm2_evictcontrol` of the
+//! [tpm2-tools](https://github.com/tpm2-software/tpm2-tools). The key
+//! never has to be stored on disk. Instead, the TPM unseals it whenever
+//! a writer or reader gets created and only if its policy is satisfied -
+//! e.g. if the PCRs match the ones of a trusted boot.
+//!
+//! The key is unsealed by running `tpm2_unseal`. It is read from the
+//! standard output of the tool and only passed to the constructor of the
+//! writer or reader. It is never returned to the caller.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use std::io::Write;
+//! use sio::tpm::SealedKey;
+//! use sio::{Aad, EncWriter, Nonce, CHACHA20_POLY1305};
+//!
+//! // A key sealed to the persistent handle 0x81010001 that can only
+//! // be unsealed if the PCRs 0, 2, 4 and 7 match the sealing policy.
+//! let key = SealedKey::new("0x81010001").auth("pcr:sha256:0,2,4,7");
+//!
+//! let mut ciphertext: Vec<u8> = Vec::default();
+//! let mut writer = EncWriter::<CHACHA20_POLY1305, _>::with_key_provider(
+//!     &mut ciphertext,
+//!     &key,
+//!     Nonce::zero(),
+//!     Aad::empty(),
+//! )
+//! .unwrap();
+//! writer.write_all(b"Hello World").unwrap();
+//! writer.close().unwrap();
+//! ```
+
+use super::{Algorithm, Invalid, Key, KeyProvider};
+use std::ffi::OsString;
+use std::io;
+use std::process::{Command, Stdio};
+
+/// A key sealed by a TPM 2.0 that is unsealed via `tpm2_unseal`.
+#[derive(Clone, Debug)]
+pub struct SealedKey {
+    object: OsString,
+    auth: Option<OsString>,
+    program: OsString,
+}
+
+impl SealedKey {
+    /// Returns a `SealedKey` that refers to the sealed TPM `object` -
+    /// either a persistent handle, like `0x81010001`, or a context file.
+    pub fn new<S: Into<OsString>>(object: S) -> Self {
+        SealedKey {
+            object: object.into(),
+            auth: None,
+            program: OsString::from("tpm2_unseal"),
+        }
+    }
+
+    /// Sets the authorization that satisfies the policy of the sealed
+    /// object - e.g. `pcr:sha256:0,2,4,7` for a PCR policy or
+    /// `session:session.ctx` for a policy session.
+    pub fn auth<S: Into<OsString>>(mut self, auth: S) -> Self {
+        self.auth = Some(auth.into());
+        self
+    }
+
+    /// Sets the program that unseals the key. By default, `tpm2_unseal`
+    /// is looked up in the `PATH`.
+    ///
+    /// The program gets invoked with the same arguments as
+    /// `tpm2_unseal` and must write the key to its standard output.
+    pub fn program<S: Into<OsString>>(mut self, program: S) -> Self {
+        self.program = program.into();
+        self
+    }
+}
+
+impl<A: Algorithm> KeyProvider<A> for SealedKey {
+    /// Unseals the key. It fails if the program cannot be run, exits with
+    /// an error - e.g. because the policy is not satisfied - or does not
+    /// return a key of `A::KEY_LEN` bytes.
+    fn key(&self) -> io::Result<Key<A>> {
+        let mut command = Command::new(&self.program);
+        command.arg("-c").arg(&self.object);
+        if let Some(ref auth) = self.auth {
+            command.arg("-p").arg(auth);
+        }
+        let output = command
+            .stdin(Stdio::null())
+            .stderr(Stdio::piped())
+            .output()?;
+
+        let mut secret = output.stdout;
+        let key = if !output.status.success() {
+            Err(io::Error::other(format!(
+                "failed to unseal the key: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            )))
+        } else if secret.len() != A::KEY_LEN {
+            Err(Invalid::Key.into())
+        } else {
+            let mut key = [0; 32];
+            key[..A::KEY_LEN].copy_from_slice(&secret);
+            Ok(Key::new(key))
+        };
+        secret.iter_mut().for_each(|b| *b = 0);
+        key
+    }
+}
@@ -1,6 +1,105 @@
 use super::writer::Close;
-use std::io;
-use std::io::Write;
+use std::io::{Read, Write};
+use std::{fmt, io};
+
+/// Reports a misuse of the API - e.g. writing to a closed writer. It
+/// panics with the message `msg` unless the `panic-free` feature is
+/// enabled. Then, it returns an error of kind `InvalidInput` instead.
+pub(crate) fn misuse(msg: &'static str) -> io::Error {
+    if cfg!(feature = "panic-free") {
+        io::Error::new(io::ErrorKind::InvalidInput, msg)
+    } else {
+        panic!("{}", msg)
+    }
+}
+
+/// Writes the entire `buf` to `w` - like `Write::write_all`. If `w`
+/// does not accept any more data, it fails with `ErrorKind::WriteZero`
+/// and reports `what` could not be written.
+pub(crate) fn write_all<W: Write + ?Sized>(
+    w: &mut W,
+    mut buf: &[u8],
+    what: fmt::Arguments,
+) -> io::Result<()> {
+    while !buf.is_empty() {
+        match w.write(buf) {
+            Ok(0) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::WriteZero,
+                    format!("failed to write {}", what),
+                ))
+            }
+            Ok(n) => buf = &buf[n..],
+            Err(ref err) if err.kind() == io::ErrorKind::Interrupted => {}
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(())
+}
+
+/// A buffer that coalesces many small writes - e.g. the ciphertext of
+/// several small fragments - into fewer writes to an inner writer.
+pub(crate) struct OutputBuffer {
+    buf: Vec<u8>,
+    size: usize,
+}
+
+impl OutputBuffer {
+    /// Creates a new `OutputBuffer` that holds up to `size` bytes.
+    pub(crate) fn new(size: usize) -> Self {
+        OutputBuffer {
+            buf: Vec::with_capacity(size),
+            size,
+        }
+    }
+
+    /// Changes the size of the buffer to `size` bytes. Already buffered
+    /// data is kept.
+    pub(crate) fn resize(&mut self, size: usize) {
+        self.size = size;
+    }
+
+    /// Appends `data` to the buffer. The buffer is written to `w` first
+    /// if it cannot hold `data`. Data that does not fit into an empty
+    /// buffer is written to `w` directly.
+    pub(crate) fn write<W: Write + ?Sized>(
+        &mut self,
+        w: &mut W,
+        data: &[u8],
+        what: fmt::Arguments,
+    ) -> io::Result<()> {
+        if self.buf.len() + data.len() > self.size {
+            self.flush(w)?;
+        }
+        if data.len() >= self.size {
+            return write_all(w, data, what);
+        }
+        self.buf.extend_from_slice(data);
+        Ok(())
+    }
+
+    /// Writes the buffered data to `w`.
+    pub(crate) fn flush<W: Write + ?Sized>(&mut self, w: &mut W) -> io::Result<()> {
+        let r = write_all(w, &self.buf, format_args!("the buffered ciphertext"));
+        self.buf.clear();
+        r
+    }
+}
+
+/// Reads from `r` until `buf` is full or `r` reaches its end and returns
+/// the number of bytes read.
+pub(crate) fn read_full<R: Read>(r: &mut R, buf: &mut [u8]) -> io::Result<usize> {
+    let mut n = 0;
+    while n < buf.len() {
+        match r.read(&mut buf[n..]) {
+            Ok(0) => break,
+            Ok(m) => n += m,
+            Err(ref err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(n)
+}
 
 impl<T: Close + ?Sized> Close for &mut T {
     #[inline(always)]
@@ -30,6 +129,31 @@ impl<W: Close + ?Sized> Close for Box<W> {
     }
 }
 
+/// Closing a `dyn Write` trait object only flushes it - like a
+/// `NopCloser`. Hence, a `Box<dyn Write>` or `&mut dyn Write` can be
+/// used as inner writer. Sinks that must be closed should be boxed
+/// as `dyn WriteClose` instead.
+impl<'a> Close for dyn Write + 'a {
+    #[inline(always)]
+    fn close(&mut self) -> io::Result<()> {
+        self.flush()
+    }
+}
+
+impl<'a> Close for dyn Write + Send + 'a {
+    #[inline(always)]
+    fn close(&mut self) -> io::Result<()> {
+        self.flush()
+    }
+}
+
+impl<'a> Close for dyn Write + Send + Sync + 'a {
+    #[inline(always)]
+    fn close(&mut self) -> io::Result<()> {
+        self.flush()
+    }
+}
+
 impl<W: Write + Close> Close for io::BufWriter<W> {
     #[inline]
     fn close(&mut self) -> io::Result<()> {
@@ -44,6 +168,17 @@ impl<W: Write + Close> Close for io::LineWriter<W> {
     }
 }
 
+/// Closing a `UnixStream` shuts down its writing half. Hence, the peer
+/// reaches the end of the stream while it can still send data back.
+#[cfg(unix)]
+impl Close for std::os::unix::net::UnixStream {
+    #[inline]
+    fn close(&mut self) -> io::Result<()> {
+        self.flush()
+            .and_then(|_| self.shutdown(std::net::Shutdown::Write))
+    }
+}
+
 /// NopCloser wraps a writer and implements the `Close` trait by
 /// performing a `flush` when the `close` method is called. It should
 /// only be used to wrap a writer which does not implement the `Close`
@@ -114,6 +249,20 @@ impl<W: Write> Write for NopCloser<W> {
     }
 }
 
+impl<W: Write + io::Read> io::Read for NopCloser<W> {
+    #[inline(always)]
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.0.read(buf)
+    }
+}
+
+impl<W: Write + io::Seek> io::Seek for NopCloser<W> {
+    #[inline(always)]
+    fn seek(&mut self, pos: io::SeekFrom) -> io::Result<u64> {
+        self.0.seek(pos)
+    }
+}
+
 impl<W: Write> Close for NopCloser<W> {
     #[inline(always)]
     fn close(&mut self) -> io::Result<()> {
@@ -134,3 +283,102 @@ impl<W: Write> AsMut<W> for NopCloser<W> {
         &mut self.0
     }
 }
+
+/// CallbackWriter implements `Write` and `Close` by passing all data to
+/// a callback. It can be used to push the output of an `EncWriter` or
+/// `DecWriter` into FFI callbacks, ring buffers or custom transports that
+/// don't provide a `Write` implementation.
+///
+/// Every `write` passes the entire buffer to the `on_write` callback. The
+/// `on_close` callback is invoked when the `CallbackWriter` gets closed -
+/// i.e. once the `EncWriter` or `DecWriter` has written all of its data.
+/// It is invoked at most once. Closing the `CallbackWriter` again has no
+/// effect.
+///
+/// # Examples
+///
+/// ```
+/// use std::io::Write;
+/// use sio::{Key, Nonce, Aad, EncWriter, CallbackWriter, CHACHA20_POLY1305};
+///
+/// // Obviously, don't use this all-zeros key for anything real.
+/// let key: Key<CHACHA20_POLY1305> = Key::new([0; Key::<CHACHA20_POLY1305>::SIZE]);
+///
+/// let mut packets: Vec<Vec<u8>> = Vec::default();
+/// let mut closed = false;
+/// let mut writer = EncWriter::new(
+///     CallbackWriter::new(
+///         |ciphertext: &[u8]| {
+///             packets.push(ciphertext.to_vec());
+///             Ok(())
+///         },
+///         || {
+///             closed = true;
+///             Ok(())
+///         },
+///     ),
+///     &key,
+///     Nonce::new([0; Nonce::<CHACHA20_POLY1305>::SIZE]),
+///     Aad::empty(),
+/// );
+///
+/// writer.write_all(b"Hello World").unwrap();
+/// writer.close().unwrap();
+///
+/// assert!(closed);
+/// assert!(!packets.is_empty());
+/// ```
+pub struct CallbackWriter<W, C>
+where
+    W: FnMut(&[u8]) -> io::Result<()>,
+    C: FnOnce() -> io::Result<()>,
+{
+    on_write: W,
+    on_close: Option<C>,
+}
+
+impl<W, C> CallbackWriter<W, C>
+where
+    W: FnMut(&[u8]) -> io::Result<()>,
+    C: FnOnce() -> io::Result<()>,
+{
+    /// Creates a new `CallbackWriter` that passes all data to `on_write`
+    /// and calls `on_close` when it gets closed.
+    #[inline(always)]
+    pub fn new(on_write: W, on_close: C) -> Self {
+        CallbackWriter {
+            on_write,
+            on_close: Some(on_close),
+        }
+    }
+}
+
+impl<W, C> Write for CallbackWriter<W, C>
+where
+    W: FnMut(&[u8]) -> io::Result<()>,
+    C: FnOnce() -> io::Result<()>,
+{
+    #[inline]
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        (self.on_write)(buf).map(|_| buf.len())
+    }
+
+    #[inline(always)]
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<W, C> Close for CallbackWriter<W, C>
+where
+    W: FnMut(&[u8]) -> io::Result<()>,
+    C: FnOnce() -> io::Result<()>,
+{
+    #[inline]
+    fn close(&mut self) -> io::Result<()> {
+        match self.on_close.take() {
+            Some(on_close) => on_close(),
+            None => Ok(()),
+        }
+    }
+}
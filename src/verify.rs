@@ -0,0 +1,82 @@
+// Copyright (c) 2019 Andreas Auernhammer. All rights reserved.
+// Use of this source code is governed by a license that can be
+// found in the LICENSE file.
+
+use super::framing::{Framing, Sio};
+use super::utils::read_full;
+use super::{Aad, Algorithm, Invalid, Key, Nonce, NotAuthentic, MAX_BUF_SIZE};
+use std::io::{self, Read};
+
+/// Verifies the entire encrypted stream read from `reader` without
+/// emitting any plaintext and returns the length of the plaintext.
+///
+/// Every fragment - including the final one - gets authenticated. The
+/// stream must have been produced by an `EncWriter` with the default
+/// `Sio` framing and the given buffer size. `verify` only allocates one
+/// fragment and decrypts each fragment in place. It is intended for scrub
+/// jobs that periodically check the integrity of stored ciphertexts.
+///
+/// # Examples
+///
+/// ```
+/// use std::io::Write;
+/// use sio::{Key, Nonce, Aad, EncWriter, BUF_SIZE, CHACHA20_POLY1305};
+///
+/// // Obviously, don't use this all-zeros key for anything real.
+/// let key: Key<CHACHA20_POLY1305> = Key::new([0; Key::<CHACHA20_POLY1305>::SIZE]);
+///
+/// let mut ciphertext: Vec<u8> = Vec::default();
+/// let mut writer = EncWriter::new(
+///     &mut ciphertext,
+///     &key,
+///     Nonce::new([0; Nonce::<CHACHA20_POLY1305>::SIZE]),
+///     Aad::empty(),
+/// );
+/// writer.write_all(b"Hello World").unwrap();
+/// writer.close().unwrap();
+///
+/// let len = sio::verify(
+///     ciphertext.as_slice(),
+///     &key,
+///     Nonce::new([0; Nonce::<CHACHA20_POLY1305>::SIZE]),
+///     Aad::empty(),
+///     BUF_SIZE,
+/// )
+/// .unwrap();
+/// assert_eq!(len, 11);
+/// ```
+pub fn verify<A: Algorithm, R: Read>(
+    mut reader: R,
+    key: &Key<A>,
+    nonce: Nonce<A>,
+    aad: Aad<A>,
+    buf_size: usize,
+) -> io::Result<u64> {
+    if buf_size == 0 || buf_size > MAX_BUF_SIZE {
+        return Err(Invalid::BufSize.into());
+    }
+    let mut algorithm = A::new(key.as_ref());
+    let mut framing = Sio::default();
+    framing.init(key.as_ref(), &algorithm, nonce.as_ref(), aad.as_ref())?;
+
+    // We read one byte more than a fragment to detect
+    // whether a fragment is the final one.
+    let fragment_len = buf_size + A::TAG_LEN;
+    let mut buffer = vec![0; fragment_len + 1];
+    let mut len = read_full(&mut reader, &mut buffer)?;
+    let mut plaintext_len: u64 = 0;
+    while len > fragment_len {
+        let (nonce, aad) = framing.next(&mut algorithm, false)?;
+        algorithm.open_in_place(nonce, aad, &mut buffer[..fragment_len])?;
+        plaintext_len += buf_size as u64;
+
+        buffer[0] = buffer[fragment_len];
+        len = 1 + read_full(&mut reader, &mut buffer[1..])?;
+    }
+    if len < A::TAG_LEN {
+        return Err(NotAuthentic.into());
+    }
+    let (nonce, aad) = framing.next(&mut algorithm, true)?;
+    algorithm.open_in_place(nonce, aad, &mut buffer[..len])?;
+    Ok(plaintext_len + (len - A::TAG_LEN) as u64)
+}
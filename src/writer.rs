@@ -2,10 +2,26 @@
 // Use of this source code is governed by a license that can be
 // found in the LICENSE file.
 
-use super::aead::Counter;
-use super::{Aad, Algorithm, Invalid, Key, Nonce, BUF_SIZE, MAX_BUF_SIZE};
+#[cfg(feature = "ring")]
+use super::audit::Audit;
+#[cfg(feature = "ring")]
+use super::digest::{Digests, Hasher};
+use super::framing::{self, FragmentSize, Framing, Sio};
+use super::hook;
+use super::metadata::{self, Metadata};
+use super::pool::{BufferAllocator, BufferPool};
+use super::report::CorruptionReport;
+use super::utils::{self, OutputBuffer};
+use super::{
+    Aad, Algorithm, Invalid, Key, Layout, LengthMismatch, Nonce, NopCloser, NotAuthentic,
+    QuotaExceeded, Token, TrailingData, BUF_SIZE, MAX_BUF_SIZE, MAX_NONCE_LEN,
+};
+use std::fmt;
 use std::io;
-use std::io::Write;
+use std::io::{IoSlice, Read, Seek, SeekFrom, Write};
+use std::mem;
+use std::ops::Range;
+use std::sync::Arc;
 use std::thread::panicking;
 
 /// Wraps a writer and encrypts and authenticates everything written to it.
@@ -18,6 +34,9 @@ use std::thread::panicking;
 /// when the buffer size of the `BufWriter` is significantly larger than the
 /// fragment size of the `EncWriter`.
 ///
+/// The in-memory buffer grows on demand up to the fragment size. Hence,
+/// encrypting a small stream does not allocate a complete fragment.
+///
 /// When the `EncWriter` is dropped, any buffered content will be encrypted
 /// as well as authenticated and written out. However, any errors that happen
 /// in the process of flushing the buffer when the `EncWriter` is dropped will
@@ -55,14 +74,52 @@ use std::thread::panicking;
 /// writer.write_all(plaintext).unwrap();
 /// writer.close().unwrap(); // Complete the encryption process explicitly.
 /// ```
-pub struct EncWriter<A: Algorithm, W: Write + internal::Close> {
+pub struct EncWriter<A: Algorithm, W: Write + internal::Close, F: Framing = Sio> {
     inner: W,
     algorithm: A,
     buffer: Box<[u8]>,
     pos: usize,
     buf_size: usize,
-    nonce: Counter<A>,
-    aad: [u8; 16 + 1], // TODO: replace with [u8; A::TAG_LEN + 1]
+    framing: F,
+
+    // Whether the first fragment contains the metadata of the stream.
+    metadata: bool,
+
+    // The number of fragments encrypted so far.
+    fragments: u64,
+
+    // If set, write accepts at most one fragment of plaintext at once.
+    // The ciphertext of a sealed fragment - `buffer[sent..sealed]` -
+    // has to be written to the inner writer before accepting more
+    // plaintext. The `finished` flag indicates that the final fragment
+    // has been sealed.
+    backpressure: bool,
+    sealed: usize,
+    sent: usize,
+    finished: bool,
+
+    // If set, the total number of plaintext bytes must not exceed
+    // `max_plaintext`. `plaintext_len` counts the plaintext bytes
+    // written so far.
+    max_plaintext: Option<u64>,
+    plaintext_len: u64,
+
+    // If set, the ciphertext of several fragments is collected and
+    // written to the inner writer at once.
+    output: Option<OutputBuffer>,
+
+    // The buffer for encrypting up to `BATCH_LEN` fragments at once.
+    // It is allocated once a write spans enough fragments.
+    batch: Box<[u8]>,
+
+    // If set, the fragment buffer has been obtained from the allocator
+    // and is released to it once the EncWriter gets dropped.
+    allocator: Option<Arc<dyn BufferAllocator>>,
+
+    // Computes the digests of the plaintext and the ciphertext
+    // if enabled.
+    #[cfg(feature = "ring")]
+    hasher: Option<Hasher>,
 
     // If an error occurs, we must fail any subsequent write of flush operation.
     // If set to true, this flag tells the write and flush implementation to fail
@@ -115,6 +172,36 @@ impl<A: Algorithm, W: Write + internal::Close> EncWriter<A, W> {
         Self::with_buffer_size(inner, key, nonce, aad, BUF_SIZE).unwrap()
     }
 
+    /// Creates a new `EncWriter` with a default buffer size of 16 KiB from
+    /// a one-time `Token`.
+    ///
+    /// It behaves like `new` with the key and nonce of the `token`. Since
+    /// the `token` is consumed, it cannot be used for a second `EncWriter`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::Write;
+    /// use sio::{Key, Nonce, Aad, Token, EncWriter, CHACHA20_POLY1305};
+    ///
+    /// // Obviously, don't use this all-zeros key for anything real.
+    /// let key: Key<CHACHA20_POLY1305> = Key::new([0; Key::<CHACHA20_POLY1305>::SIZE]);
+    /// let token = Token::new(key, Nonce::new([0; Nonce::<CHACHA20_POLY1305>::SIZE]));
+    ///
+    /// // The nonce is required for decryption. Usually, it's stored
+    /// // together with the encrypted data.
+    /// let nonce = token.nonce();
+    ///
+    /// let mut ciphertext: Vec<u8> = Vec::default();
+    /// let mut writer = EncWriter::from_token(&mut ciphertext, token, Aad::empty());
+    /// writer.write_all(b"Hello World").unwrap();
+    /// writer.close().unwrap();
+    /// ```
+    pub fn from_token(inner: W, token: Token<A>, aad: Aad<A>) -> Self {
+        let (key, nonce) = token.into_parts();
+        Self::new(inner, &key, nonce, aad)
+    }
+
     /// Creates a new `EncWriter` with the specified buffer size as fragment
     /// size. The `buf_size` must not be `0` nor greater than `MAX_BUF_SIZE`.
     ///
@@ -171,29 +258,308 @@ impl<A: Algorithm, W: Write + internal::Close> EncWriter<A, W> {
         nonce: Nonce<A>,
         aad: Aad<A>,
         buf_size: usize,
+    ) -> Result<Self, Invalid> {
+        Self::with_framing(inner, key, nonce, aad, buf_size, Sio::default())
+    }
+
+    /// Creates a new `EncWriter` with the specified buffer size as fragment
+    /// size that borrows its fragment buffer from the `pool`. The buffer
+    /// is returned to the pool once the `EncWriter` has been closed or
+    /// dropped. The `buf_size` must not be `0` nor greater than
+    /// `MAX_BUF_SIZE`.
+    ///
+    /// Services that encrypt many small objects concurrently can share one
+    /// `BufferPool` between all their writers to avoid allocating a new
+    /// fragment buffer for every object. See `BufferPool` for an example.
+    pub fn with_pool(
+        inner: W,
+        key: &Key<A>,
+        nonce: Nonce<A>,
+        aad: Aad<A>,
+        buf_size: usize,
+        pool: &BufferPool,
+    ) -> Result<Self, Invalid> {
+        let allocator = Arc::new(pool.clone());
+        Self::with_allocator(inner, key, nonce, aad, buf_size, allocator)
+    }
+
+    /// Creates a new `EncWriter` with the specified buffer size as fragment
+    /// size that obtains its fragment buffer from the `allocator`. The
+    /// buffer is released to the allocator once the `EncWriter` has been
+    /// closed or dropped. The `buf_size` must not be `0` nor greater than
+    /// `MAX_BUF_SIZE`.
+    ///
+    /// It returns `Invalid::BufSize` if the allocator does not provide a
+    /// buffer of the requested size. See `BufferAllocator` for an example.
+    pub fn with_allocator(
+        inner: W,
+        key: &Key<A>,
+        nonce: Nonce<A>,
+        aad: Aad<A>,
+        buf_size: usize,
+        allocator: Arc<dyn BufferAllocator>,
+    ) -> Result<Self, Invalid> {
+        let framing = Sio::default();
+        Self::with_framing_and_allocator(inner, key, nonce, aad, buf_size, framing, Some(allocator))
+    }
+
+    /// Creates a new `EncWriter` that continues the existing encrypted
+    /// stream of `existing_len` bytes at the beginning of `inner`.
+    ///
+    /// The stream must have been encrypted with the same `key`, `nonce`,
+    /// `aad` and the default buffer size. The final fragment of the stream
+    /// is read and verified. Its plaintext is buffered such that it gets
+    /// re-sealed - as regular fragment if more data is written - and the
+    /// sequence numbers continue where the existing stream ended. The
+    /// `inner` writer is positioned at the beginning of the final fragment
+    /// such that the final fragment gets overwritten.
+    ///
+    /// # Security
+    ///
+    /// A final fragment that is not full gets re-sealed with the same key
+    /// and nonce. The re-sealed fragment reveals no additional plaintext
+    /// since its plaintext only grows. However, an attacker who observes
+    /// the stream before and after appending obtains two different fragments
+    /// for the same key and nonce and may be able to forge fragments afterwards.
+    /// Therefore, only append to streams whose previous versions are not
+    /// observable by an attacker - e.g. local log files.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::{Cursor, Write};
+    /// use sio::{Key, Nonce, Aad, EncWriter, DecWriter, NopCloser, CHACHA20_POLY1305};
+    ///
+    /// // Obviously, don't use this all-zeros key for anything real.
+    /// let key: Key<CHACHA20_POLY1305> = Key::new([0; Key::<CHACHA20_POLY1305>::SIZE]);
+    ///
+    /// let mut log = Cursor::new(Vec::default());
+    /// let mut writer = EncWriter::new(
+    ///     NopCloser::wrap(&mut log),
+    ///     &key,
+    ///     Nonce::new([0; Nonce::<CHACHA20_POLY1305>::SIZE]),
+    ///     Aad::empty(),
+    /// );
+    /// writer.write_all(b"Hello").unwrap();
+    /// writer.close().unwrap();
+    ///
+    /// // After a restart, the log is opened again.
+    /// let existing_len = log.get_ref().len() as u64;
+    /// let mut writer = EncWriter::append(
+    ///     NopCloser::wrap(&mut log),
+    ///     &key,
+    ///     Nonce::new([0; Nonce::<CHACHA20_POLY1305>::SIZE]),
+    ///     Aad::empty(),
+    ///     existing_len,
+    /// )
+    /// .unwrap();
+    /// writer.write_all(b" World").unwrap();
+    /// writer.close().unwrap();
+    ///
+    /// let mut plaintext: Vec<u8> = Vec::default();
+    /// let mut writer = DecWriter::new(
+    ///     &mut plaintext,
+    ///     &key,
+    ///     Nonce::new([0; Nonce::<CHACHA20_POLY1305>::SIZE]),
+    ///     Aad::empty(),
+    /// );
+    /// writer.write_all(log.get_ref()).unwrap();
+    /// writer.close().unwrap();
+    ///
+    /// assert_eq!(plaintext.as_slice(), b"Hello World");
+    /// ```
+    pub fn append(
+        mut inner: W,
+        key: &Key<A>,
+        nonce: Nonce<A>,
+        aad: Aad<A>,
+        existing_len: u64,
+    ) -> io::Result<Self>
+    where
+        W: Read + Seek,
+    {
+        let fragment_len = (BUF_SIZE + A::TAG_LEN) as u64;
+        let (index, len) = match existing_len.checked_sub(1) {
+            Some(n) => (
+                n / fragment_len,
+                existing_len - (n / fragment_len) * fragment_len,
+            ),
+            None => (0, 0),
+        };
+        if existing_len > 0 && len < A::TAG_LEN as u64 {
+            return Err(NotAuthentic.into());
+        }
+
+        let mut buffer = vec![0; BUF_SIZE + A::TAG_LEN].into_boxed_slice();
+        let mut framing = Sio::default();
+        let mut algorithm = A::new(key.as_ref());
+        framing.init(key.as_ref(), &algorithm, nonce.as_ref(), aad.as_ref())?;
+        framing.seek(index)?;
+
+        inner.seek(SeekFrom::Start(index * fragment_len))?;
+        let pos = if existing_len > 0 {
+            let len = len as usize;
+            inner.read_exact(&mut buffer[..len])?;
+            let mut last = framing.clone();
+            let (nonce, aad) = last.next(&mut algorithm, true)?;
+            algorithm.open_in_place(nonce, aad, &mut buffer[..len])?;
+
+            // Reading moved the inner writer behind the final fragment.
+            inner.seek(SeekFrom::Start(index * fragment_len))?;
+            len - A::TAG_LEN
+        } else {
+            0
+        };
+        Ok(EncWriter {
+            inner,
+            algorithm,
+            buffer,
+            pos,
+            buf_size: BUF_SIZE,
+            framing,
+            metadata: false,
+            fragments: index,
+            backpressure: false,
+            sealed: 0,
+            sent: 0,
+            finished: false,
+            max_plaintext: None,
+            plaintext_len: 0,
+            output: None,
+            batch: Box::default(),
+            allocator: None,
+            #[cfg(feature = "ring")]
+            hasher: None,
+            errored: false,
+            closed: false,
+        })
+    }
+}
+
+impl<A: Algorithm, W: Write> EncWriter<A, NopCloser<W>> {
+    /// Creates a new `EncWriter` with a default buffer size of 16 KiB
+    /// for an `inner` writer that does not implement `Close`.
+    ///
+    /// The `inner` writer is wrapped into a `NopCloser`. Therefore,
+    /// closing the `EncWriter` only flushes the `inner` writer.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::{self, Write};
+    /// use sio::{Key, Nonce, Aad, EncWriter, CHACHA20_POLY1305};
+    ///
+    /// // Obviously, don't use this all-zeros key for anything real.
+    /// let key: Key<CHACHA20_POLY1305> = Key::new([0; Key::<CHACHA20_POLY1305>::SIZE]);
+    ///
+    /// let mut writer = EncWriter::new_unclosed(
+    ///     io::sink(),
+    ///     &key,
+    ///     Nonce::new([0; Nonce::<CHACHA20_POLY1305>::SIZE]),
+    ///     Aad::empty(),
+    /// );
+    /// writer.write_all(b"Hello World").unwrap();
+    /// writer.close().unwrap();
+    /// ```
+    pub fn new_unclosed(inner: W, key: &Key<A>, nonce: Nonce<A>, aad: Aad<A>) -> Self {
+        Self::new(NopCloser::wrap(inner), key, nonce, aad)
+    }
+}
+
+impl<A: Algorithm, W: Write + internal::Close, F: Framing> EncWriter<A, W, F> {
+    /// Creates a new `EncWriter` with the specified buffer size as fragment
+    /// size that uses the given `framing` instead of the default `Sio`
+    /// channel construction. The `buf_size` must not be `0` nor greater
+    /// than `MAX_BUF_SIZE`.
+    ///
+    /// Data encrypted with one framing can only be decrypted using the
+    /// same framing. See the `framing` module for the available framings.
+    ///
+    /// # Examples
+    ///
+    /// Creating an `EncWriter` that uses the STREAM construction.
+    ///
+    /// ```
+    /// use std::io::Write;
+    /// use sio::{Key, Nonce, Aad, EncWriter, BUF_SIZE, CHACHA20_POLY1305};
+    /// use sio::framing::Stream;
+    ///
+    /// // Obviously, don't use this all-zeros key for anything real.
+    /// let key: Key<CHACHA20_POLY1305> = Key::new([0; Key::<CHACHA20_POLY1305>::SIZE]);
+    ///
+    /// let mut ciphertext: Vec<u8> = Vec::default();
+    /// let mut writer = EncWriter::with_framing(
+    ///     &mut ciphertext,
+    ///     &key,
+    ///     Nonce::new([0; Nonce::<CHACHA20_POLY1305>::SIZE]),
+    ///     Aad::empty(),
+    ///     BUF_SIZE,
+    ///     Stream::default(),
+    /// )
+    /// .unwrap();
+    ///
+    /// // Perform some write and flush operations
+    /// // ...
+    ///
+    /// writer.close().unwrap();
+    /// ```
+    pub fn with_framing(
+        inner: W,
+        key: &Key<A>,
+        nonce: Nonce<A>,
+        aad: Aad<A>,
+        buf_size: usize,
+        framing: F,
+    ) -> Result<Self, Invalid> {
+        Self::with_framing_and_allocator(inner, key, nonce, aad, buf_size, framing, None)
+    }
+
+    /// Creates a new `EncWriter` that obtains its fragment buffer from the
+    /// `allocator` - if any.
+    fn with_framing_and_allocator(
+        inner: W,
+        key: &Key<A>,
+        nonce: Nonce<A>,
+        aad: Aad<A>,
+        buf_size: usize,
+        mut framing: F,
+        allocator: Option<Arc<dyn BufferAllocator>>,
     ) -> Result<Self, Invalid> {
         if buf_size == 0 || buf_size > MAX_BUF_SIZE {
             return Err(Invalid::BufSize);
         }
+        if framing.buf_size().is_some_and(|n| n != buf_size) {
+            return Err(Invalid::BufSize);
+        }
         let algorithm = A::new(key.as_ref());
-        let mut nonce = Counter::zero(nonce);
-        let mut associated_data = [0; 1 + 16];
-        algorithm
-            .seal_in_place(
-                &nonce.next().unwrap(),
-                aad.as_ref(),
-                &mut associated_data[1..],
-            )
-            .unwrap();
+        framing.init(key.as_ref(), &algorithm, nonce.as_ref(), aad.as_ref())?;
 
+        // Without an allocator, the buffer grows on demand such that
+        // small streams do not allocate a complete fragment.
+        let buffer = match allocator {
+            Some(ref allocator) => allocate(Some(allocator.as_ref()), buf_size + A::TAG_LEN)?,
+            None => Box::default(),
+        };
         Ok(EncWriter {
-            inner: inner,
-            algorithm: A::new(key.as_ref()),
-            buffer: vec![0; buf_size + A::TAG_LEN].into_boxed_slice(),
+            inner,
+            algorithm,
+            buffer,
             pos: 0,
-            buf_size: buf_size,
-            nonce: nonce,
-            aad: associated_data,
+            buf_size,
+            framing,
+            metadata: false,
+            fragments: 0,
+            backpressure: false,
+            sealed: 0,
+            sent: 0,
+            finished: false,
+            max_plaintext: None,
+            plaintext_len: 0,
+            output: None,
+            batch: Box::default(),
+            allocator,
+            #[cfg(feature = "ring")]
+            hasher: None,
             errored: false,
             closed: false,
         })
@@ -205,124 +571,1067 @@ impl<A: Algorithm, W: Write + internal::Close> EncWriter<A, W> {
         internal::Close::close(&mut self)
     }
 
-    #[inline(always)]
-    pub fn closer(self) -> impl Write + Close {
-        Closer::wrap(self)
-    }
-
-    /// Encrypt and authenticate the buffer and write the ciphertext
-    /// to the inner writer.
-    fn write_buffer(&mut self, len: usize) -> io::Result<()> {
-        let nonce = match self.nonce.next() {
-            Ok(nonce) => nonce,
-            Err(err) => {
-                self.errored = true;
-                return Err(err.into());
-            }
-        };
-
-        let ciphertext = match self.algorithm.seal_in_place(
-            nonce,
-            &self.aad,
-            &mut self.buffer[..len + A::TAG_LEN],
-        ) {
-            Ok(ciphertext) => ciphertext,
-            Err(err) => {
-                self.errored = true;
-                return Err(err.into());
-            }
-        };
-
-        match self.inner.write_all(ciphertext) {
-            Ok(v) => Ok(v),
-            Err(err) => {
-                self.errored = true;
-                Err(err)
-            }
+    /// Completes the encryption process of an empty stream and closes
+    /// the `EncWriter`.
+    ///
+    /// An empty stream consists of one final fragment that contains no
+    /// plaintext. It is exactly what `close` produces when nothing has
+    /// been written. `finish_empty` makes this explicit: it fails with
+    /// `ErrorKind::InvalidInput` - without completing the stream - if any
+    /// plaintext has been written before.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sio::{Key, Nonce, Aad, Algorithm, EncWriter, CHACHA20_POLY1305};
+    ///
+    /// // Obviously, don't use this all-zeros key for anything real.
+    /// let key: Key<CHACHA20_POLY1305> = Key::new([0; Key::<CHACHA20_POLY1305>::SIZE]);
+    ///
+    /// let mut ciphertext: Vec<u8> = Vec::default();
+    /// let writer = EncWriter::new(
+    ///     &mut ciphertext,
+    ///     &key,
+    ///     Nonce::new([0; Nonce::<CHACHA20_POLY1305>::SIZE]),
+    ///     Aad::empty(),
+    /// );
+    /// writer.finish_empty().unwrap();
+    ///
+    /// assert_eq!(ciphertext.len(), CHACHA20_POLY1305::TAG_LEN);
+    /// ```
+    #[must_use = "An EncWriter must be closed to successfully complete the encryption process. Ignoring this result may cause incomplete ciphertext data."]
+    pub fn finish_empty(mut self) -> io::Result<()> {
+        if self.plaintext_len > 0 {
+            self.errored = true;
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "the EncWriter is not empty",
+            ));
         }
+        internal::Close::close(&mut self)
     }
-}
 
-impl<A: Algorithm, W: Write + internal::Close> Write for EncWriter<A, W> {
-    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-        if self.errored {
-            return Err(io::Error::from(io::ErrorKind::Other));
-        }
+    #[inline(always)]
+    pub fn closer(self) -> Closer<Self> {
+        Closer::wrap(self)
+    }
 
-        let n = buf.len();
-        let remaining = self.buf_size - self.pos;
-        if n <= remaining {
-            self.buffer[self.pos..self.pos + n].copy_from_slice(buf);
-            self.pos += n;
-            return Ok(n);
+    /// Encrypts and authenticates all chunks produced by `chunks` and
+    /// closes the `EncWriter`. It returns the number of plaintext bytes
+    /// encrypted.
+    ///
+    /// `consume` connects chunk producers - e.g. channel receivers or
+    /// paginated APIs - to the `EncWriter` without a manual write loop.
+    /// If the producer fails, `consume` returns its error without closing
+    /// the `EncWriter`. Hence, the ciphertext is incomplete and cannot be
+    /// decrypted.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::sync::mpsc;
+    /// use sio::{Key, Nonce, Aad, EncWriter, CHACHA20_POLY1305};
+    ///
+    /// // Obviously, don't use this all-zeros key for anything real.
+    /// let key: Key<CHACHA20_POLY1305> = Key::new([0; Key::<CHACHA20_POLY1305>::SIZE]);
+    ///
+    /// let (sender, receiver) = mpsc::channel();
+    /// sender.send(Ok(b"Hello ".to_vec())).unwrap();
+    /// sender.send(Ok(b"World".to_vec())).unwrap();
+    /// drop(sender);
+    ///
+    /// let mut ciphertext: Vec<u8> = Vec::default();
+    /// let writer = EncWriter::new(
+    ///     &mut ciphertext,
+    ///     &key,
+    ///     Nonce::new([0; Nonce::<CHACHA20_POLY1305>::SIZE]),
+    ///     Aad::empty(),
+    /// );
+    /// let n = writer.consume(receiver).unwrap();
+    ///
+    /// assert_eq!(n, 11);
+    /// ```
+    pub fn consume<I>(mut self, chunks: I) -> io::Result<u64>
+    where
+        I: IntoIterator<Item = io::Result<Vec<u8>>>,
+    {
+        let mut n = 0;
+        for chunk in chunks {
+            let chunk = chunk.inspect_err(|_| self.errored = true)?;
+            self.write_all(&chunk)?;
+            n += chunk.len() as u64;
         }
-
-        self.buffer[self.pos..self.buf_size].copy_from_slice(&buf[..remaining]);
-        self.write_buffer(self.buf_size)?;
-        self.pos = 0;
-        let buf = &buf[remaining..];
-
-        let chunks = buf.chunks(self.buf_size);
-        chunks
-            .clone()
-            .take(chunks.len() - 1) // Since we take only n-1 elements...
-            .try_for_each(|chunk| {
-                self.buffer[..self.buf_size].copy_from_slice(chunk);
-                self.write_buffer(self.buf_size)
-            })?;
-
-        let last = chunks.last().unwrap(); // ... thereis always a last one.
-        self.buffer[..last.len()].copy_from_slice(last); // ... there is always a last one.
-        self.pos = last.len();
+        self.close()?;
         Ok(n)
     }
 
+    /// Returns a mutable reference to the inner writer.
+    #[cfg(feature = "futures-io")]
     #[inline]
-    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
-        self.write(buf).and(Ok(()))
+    pub(crate) fn get_mut(&mut self) -> &mut W {
+        &mut self.inner
     }
 
-    fn flush(&mut self) -> io::Result<()> {
-        if self.errored {
-            return Err(io::Error::from(io::ErrorKind::Other));
-        }
-        let r = self.inner.flush();
-        self.errored = r.is_err();
-        r
+    /// Returns the number of fragments that can still be encrypted
+    /// using the key-nonce combination of the `EncWriter`.
+    ///
+    /// Once all fragments have been used, any further write fails with
+    /// an `Exceeded` error. Use a new key or nonce for a new `EncWriter`
+    /// before reaching this limit.
+    #[inline]
+    pub fn remaining_fragments(&self) -> u64 {
+        self.framing.remaining()
     }
-}
-
-impl<A: Algorithm, W: Write + internal::Close> internal::Close for EncWriter<A, W> {
-    fn close(&mut self) -> io::Result<()> {
-        if self.errored {
-            return Err(io::Error::from(io::ErrorKind::Other));
-        }
-        self.closed = true;
-        self.aad[0] = 0x80; // For the last fragment change the AAD
 
-        self.write_buffer(self.pos)
-            .and_then(|()| self.inner.close())
+    /// Returns the number of plaintext bytes that can still be written
+    /// to the `EncWriter`. It is an upper bound that includes the
+    /// plaintext that is currently buffered.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::Write;
+    /// use sio::{Key, Nonce, Aad, EncWriter, CHACHA20_POLY1305};
+    ///
+    /// // Obviously, don't use this all-zeros key for anything real.
+    /// let key: Key<CHACHA20_POLY1305> = Key::new([0; Key::<CHACHA20_POLY1305>::SIZE]);
+    ///
+    /// let mut writer = EncWriter::with_buffer_size(
+    ///     Vec::default(),
+    ///     &key,
+    ///     Nonce::new([0; Nonce::<CHACHA20_POLY1305>::SIZE]),
+    ///     Aad::empty(),
+    ///     1024,
+    /// )
+    /// .unwrap();
+    /// writer.write_all(&[0; 100]).unwrap();
+    ///
+    /// let remaining = writer.remaining_bytes();
+    /// assert_eq!(remaining, ((1 << 32) - 1) * 1024 - 100);
+    /// writer.close().unwrap();
+    /// ```
+    #[inline]
+    pub fn remaining_bytes(&self) -> u64 {
+        self.framing
+            .remaining()
+            .saturating_mul(self.buf_size as u64)
+            .saturating_sub(self.pos as u64)
     }
-}
 
-impl<A: Algorithm, W: Write + internal::Close> Drop for EncWriter<A, W> {
-    fn drop(&mut self) {
-        // We must not check whether the EncWriter has been closed if
-        // we encountered an error during a write or flush call.
-        if !self.errored {
-            if !self.closed {
-                // We don't want to panic again if some code (between
-                // EncWriter::new(...) and EncWriter.close()) already
-                // panic'd. Otherwise we would cause a "double-panic".
-                if !panicking() {
-                    panic!("EncWriter must be closed explicitly via the close method before being dropped!")
-                }
-            }
+    /// Returns the number of plaintext bytes that can still be written
+    /// to the `EncWriter` before a write fails - either because the limit
+    /// set via `max_plaintext` would be exceeded or because the key-nonce
+    /// combination is exhausted.
+    #[inline]
+    pub fn remaining(&self) -> u64 {
+        match self.max_plaintext {
+            Some(limit) => (limit - self.plaintext_len).min(self.remaining_bytes()),
+            None => self.remaining_bytes(),
         }
     }
-}
 
-/// Wraps a writer and decrypts and verifies everything written to it.
+    /// Limits the total amount of plaintext written to the `EncWriter`
+    /// to `limit` bytes.
+    ///
+    /// A write that would exceed the limit fails with a `QuotaExceeded`
+    /// error and is rejected as a whole. With `backpressure`, a write
+    /// accepts plaintext up to the limit and only fails once the limit
+    /// has been reached. The `EncWriter` remains usable and can be closed.
+    /// Hence, per-tenant storage limits can be enforced exactly while
+    /// encrypting. Plaintext written before `max_plaintext` is called
+    /// counts towards the limit as well.
+    ///
+    /// It fails with a `QuotaExceeded` error if more than `limit` bytes
+    /// have already been written.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::Write;
+    /// use sio::{Key, Nonce, Aad, EncWriter, QuotaExceeded, CHACHA20_POLY1305};
+    ///
+    /// // Obviously, don't use this all-zeros key for anything real.
+    /// let key: Key<CHACHA20_POLY1305> = Key::new([0; Key::<CHACHA20_POLY1305>::SIZE]);
+    ///
+    /// let mut ciphertext: Vec<u8> = Vec::default();
+    /// let mut writer = EncWriter::new(
+    ///     &mut ciphertext,
+    ///     &key,
+    ///     Nonce::new([0; Nonce::<CHACHA20_POLY1305>::SIZE]),
+    ///     Aad::empty(),
+    /// )
+    /// .max_plaintext(16)
+    /// .unwrap();
+    /// writer.write_all(b"Hello World").unwrap();
+    /// assert_eq!(writer.remaining(), 5);
+    ///
+    /// let err = writer.write_all(b"Hello World").unwrap_err();
+    /// assert!(err.get_ref().unwrap().is::<QuotaExceeded>());
+    /// writer.close().unwrap();
+    /// ```
+    pub fn max_plaintext(mut self, limit: u64) -> io::Result<Self> {
+        if self.plaintext_len > limit {
+            self.errored = true;
+            return Err(QuotaExceeded::new(limit, self.plaintext_len).into());
+        }
+        self.max_plaintext = Some(limit);
+        Ok(self)
+    }
+
+    /// Accounts for `n` more plaintext bytes and fails with a
+    /// `QuotaExceeded` error if they would exceed the limit - or with a
+    /// `LengthMismatch` error if they would exceed the length of the
+    /// stream recorded by the framing.
+    fn consume_quota(&mut self, n: usize) -> io::Result<()> {
+        let len = self.plaintext_len.saturating_add(n as u64);
+        if let Some(stream_len) = self.framing.stream_len() {
+            // The metadata fragment counts towards the recorded length.
+            let metadata_len = if self.metadata { self.buf_size } else { 0 };
+            if len.saturating_add(metadata_len as u64) > stream_len {
+                self.errored = true;
+                return Err(LengthMismatch.into());
+            }
+        }
+        match self.max_plaintext {
+            Some(limit) if len > limit => Err(QuotaExceeded::new(limit, self.plaintext_len).into()),
+            _ => {
+                self.plaintext_len = len;
+                Ok(())
+            }
+        }
+    }
+
+    /// Reads all plaintext from `reader` until EOF, encrypts it and writes
+    /// the ciphertext to the inner writer. It returns the number of bytes
+    /// read from the `reader`.
+    ///
+    /// In contrast to `io::copy`, the plaintext is read directly into the
+    /// fragment buffer of the `EncWriter` without copying it through an
+    /// intermediate buffer first.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sio::{Key, Nonce, Aad, EncWriter, CHACHA20_POLY1305};
+    ///
+    /// // Obviously, don't use this all-zeros key for anything real.
+    /// let key: Key<CHACHA20_POLY1305> = Key::new([0; Key::<CHACHA20_POLY1305>::SIZE]);
+    ///
+    /// let plaintext = [0; 100 * 1024];
+    /// let mut ciphertext: Vec<u8> = Vec::default();
+    /// let mut writer = EncWriter::new(
+    ///     &mut ciphertext,
+    ///     &key,
+    ///     Nonce::new([0; Nonce::<CHACHA20_POLY1305>::SIZE]),
+    ///     Aad::empty(),
+    /// );
+    /// let n = writer.copy_from(&plaintext[..]).unwrap();
+    /// writer.close().unwrap();
+    ///
+    /// assert_eq!(n, 100 * 1024);
+    /// ```
+    pub fn copy_from<R: Read>(&mut self, mut reader: R) -> io::Result<u64> {
+        if self.errored {
+            return Err(io::Error::from(io::ErrorKind::Other));
+        }
+        self.drain()?;
+
+        let mut copied = 0;
+        loop {
+            // We read one byte more than fits into the fragment. A full
+            // fragment is only encrypted once we know that it is followed
+            // by more plaintext. Otherwise, it may be the final fragment.
+            self.reserve(self.pos + 1);
+            let end = self.buffer.len().min(self.buf_size + 1);
+            let n = match reader.read(&mut self.buffer[self.pos..end]) {
+                Ok(0) => return Ok(copied),
+                Ok(n) => n,
+                Err(ref err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                Err(err) => return Err(err),
+            };
+            self.consume_quota(n)?;
+            copied += n as u64;
+            let end = self.pos + n;
+            // `digest_plaintext` cannot borrow the buffer of `self`.
+            #[cfg(feature = "ring")]
+            {
+                if let Some(ref mut hasher) = self.hasher {
+                    hasher.plaintext(&self.buffer[self.pos..end]);
+                }
+            }
+            self.pos = end;
+
+            if self.pos > self.buf_size {
+                let next = self.buffer[self.buf_size];
+                self.write_buffer(self.buf_size, false)?;
+                self.buffer[0] = next;
+                self.pos = 1;
+            }
+        }
+    }
+
+    /// Writes all slices of `bufs` like `write_all` would write their
+    /// concatenation.
+    ///
+    /// The slices are absorbed into the fragment buffer one after another,
+    /// across fragment boundaries, without concatenating them first.
+    /// Hence, many small slices - e.g. handed over by a network stack - are
+    /// as cheap as one large buffer. With backpressure, it retries until
+    /// all slices have been accepted.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::IoSlice;
+    /// use sio::{Key, Nonce, Aad, EncWriter, CHACHA20_POLY1305};
+    ///
+    /// // Obviously, don't use this all-zeros key for anything real.
+    /// let key: Key<CHACHA20_POLY1305> = Key::new([0; Key::<CHACHA20_POLY1305>::SIZE]);
+    ///
+    /// let mut ciphertext: Vec<u8> = Vec::default();
+    /// let mut writer = EncWriter::new(
+    ///     &mut ciphertext,
+    ///     &key,
+    ///     Nonce::new([0; Nonce::<CHACHA20_POLY1305>::SIZE]),
+    ///     Aad::empty(),
+    /// );
+    /// writer
+    ///     .write_all_vectored(&mut [IoSlice::new(b"Hello"), IoSlice::new(b" World")])
+    ///     .unwrap();
+    /// writer.close().unwrap();
+    /// ```
+    pub fn write_all_vectored(&mut self, mut bufs: &mut [IoSlice<'_>]) -> io::Result<()> {
+        IoSlice::advance_slices(&mut bufs, 0);
+        while !bufs.is_empty() {
+            match self.write_vectored(bufs) {
+                Ok(0) => return Err(io::ErrorKind::WriteZero.into()),
+                Ok(n) => IoSlice::advance_slices(&mut bufs, n),
+                Err(ref err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(())
+    }
+
+    /// Lets the `EncWriter` honor the backpressure of the inner writer.
+    ///
+    /// By default, `write` consumes the entire plaintext and encrypts and
+    /// writes all complete fragments at once. With backpressure, `write`
+    /// accepts at most as much plaintext as fits into the current fragment.
+    /// Once a fragment is complete, it is sealed and its ciphertext has
+    /// to be written to the inner writer before `write` accepts any more
+    /// plaintext. Therefore, the `EncWriter` never buffers more than one
+    /// fragment - even when the inner writer is slow.
+    ///
+    /// The ciphertext is written with individual `write` calls. If the
+    /// inner writer fails with `ErrorKind::WouldBlock`, the `EncWriter`
+    /// returns that error and keeps the remaining ciphertext until the next
+    /// `write`, `flush` or `close` call. The header and trailer of the
+    /// framing are always written at once.
+    ///
+    /// It fails with an error of kind `InvalidInput` if an output buffer
+    /// has been enabled via `output_buffer`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::Write;
+    /// use sio::{Key, Nonce, Aad, EncWriter, CHACHA20_POLY1305};
+    ///
+    /// // Obviously, don't use this all-zeros key for anything real.
+    /// let key: Key<CHACHA20_POLY1305> = Key::new([0; Key::<CHACHA20_POLY1305>::SIZE]);
+    ///
+    /// let mut ciphertext: Vec<u8> = Vec::default();
+    /// let mut writer = EncWriter::with_buffer_size(
+    ///     &mut ciphertext,
+    ///     &key,
+    ///     Nonce::new([0; Nonce::<CHACHA20_POLY1305>::SIZE]),
+    ///     Aad::empty(),
+    ///     1024,
+    /// )
+    /// .unwrap()
+    /// .backpressure()
+    /// .unwrap();
+    ///
+    /// assert_eq!(writer.write(&[0; 4096]).unwrap(), 1024);
+    /// writer.write_all(&[0; 3072]).unwrap();
+    /// writer.close().unwrap();
+    ///
+    /// assert_eq!(ciphertext.len(), 4096 + 4 * 16);
+    /// ```
+    pub fn backpressure(mut self) -> io::Result<Self> {
+        if self.output.is_some() {
+            self.errored = true;
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "backpressure cannot be combined with an output buffer",
+            ));
+        }
+        self.backpressure = true;
+        Ok(self)
+    }
+
+    /// Lets the `EncWriter` collect the ciphertext of several fragments
+    /// in an output buffer of `size` bytes and write it to the inner
+    /// writer at once.
+    ///
+    /// By default, the ciphertext of every fragment is written to the
+    /// inner writer as soon as it has been sealed. With small fragments,
+    /// this causes many small writes - e.g. syscalls. The output buffer
+    /// is written to the inner writer once it is full as well as on
+    /// `flush` and `close`.
+    ///
+    /// It fails with an `Invalid::BufSize` error if the `size` is `0` and
+    /// with an error of kind `InvalidInput` if `backpressure` is enabled.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::Write;
+    /// use sio::{Key, Nonce, Aad, EncWriter, CHACHA20_POLY1305};
+    ///
+    /// // Obviously, don't use this all-zeros key for anything real.
+    /// let key: Key<CHACHA20_POLY1305> = Key::new([0; Key::<CHACHA20_POLY1305>::SIZE]);
+    ///
+    /// let mut ciphertext: Vec<u8> = Vec::default();
+    /// let mut writer = EncWriter::with_buffer_size(
+    ///     &mut ciphertext,
+    ///     &key,
+    ///     Nonce::new([0; Nonce::<CHACHA20_POLY1305>::SIZE]),
+    ///     Aad::empty(),
+    ///     512,
+    /// )
+    /// .unwrap()
+    /// .output_buffer(64 * 1024)
+    /// .unwrap();
+    /// writer.write_all(&[0; 4096]).unwrap();
+    /// writer.close().unwrap();
+    ///
+    /// assert_eq!(ciphertext.len(), 4096 + 8 * 16);
+    /// ```
+    pub fn output_buffer(mut self, size: usize) -> io::Result<Self> {
+        if size == 0 {
+            self.errored = true;
+            return Err(Invalid::BufSize.into());
+        }
+        if self.backpressure {
+            self.errored = true;
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "an output buffer cannot be combined with backpressure",
+            ));
+        }
+        match self.output {
+            Some(ref mut output) => output.resize(size),
+            None => self.output = Some(OutputBuffer::new(size)),
+        }
+        Ok(self)
+    }
+
+    /// Lets the `EncWriter` compute the SHA-256 digests of the plaintext
+    /// and the ciphertext while encrypting. The digests are returned by
+    /// `close_with_digests`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any data has already been written to the `EncWriter`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::Write;
+    /// use sio::{Key, Nonce, Aad, EncWriter, CHACHA20_POLY1305};
+    ///
+    /// // Obviously, don't use this all-zeros key for anything real.
+    /// let key: Key<CHACHA20_POLY1305> = Key::new([0; Key::<CHACHA20_POLY1305>::SIZE]);
+    ///
+    /// let mut ciphertext: Vec<u8> = Vec::default();
+    /// let mut writer = EncWriter::new(
+    ///     &mut ciphertext,
+    ///     &key,
+    ///     Nonce::new([0; Nonce::<CHACHA20_POLY1305>::SIZE]),
+    ///     Aad::empty(),
+    /// )
+    /// .with_digests();
+    /// writer.write_all(b"Hello World").unwrap();
+    /// let digests = writer.close_with_digests().unwrap();
+    ///
+    /// assert_eq!(digests.plaintext().len(), 32);
+    /// assert_eq!(digests.ciphertext().len(), 32);
+    /// ```
+    #[cfg(feature = "ring")]
+    pub fn with_digests(mut self) -> Self {
+        let pos = if self.metadata { self.buf_size } else { 0 };
+        assert!(
+            self.fragments == 0 && self.pos == pos,
+            "with_digests must be called before writing to the EncWriter"
+        );
+        self.hasher = Some(Hasher::new());
+        self
+    }
+
+    /// Completes the encryption process like `close` and returns the
+    /// digests of the plaintext and the ciphertext.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the digests have not been enabled via `with_digests` -
+    /// unless the `panic-free` feature is enabled.
+    #[cfg(feature = "ring")]
+    pub fn close_with_digests(mut self) -> io::Result<Digests> {
+        if self.hasher.is_none() {
+            self.errored = true;
+            return Err(utils::misuse(
+                "close_with_digests requires an EncWriter created with_digests",
+            ));
+        }
+        internal::Close::close(&mut self)?;
+        self.hasher
+            .take()
+            .map(|hasher| hasher.finish())
+            .ok_or_else(|| io::Error::from(io::ErrorKind::Other))
+    }
+
+    /// Adds the plaintext `data` to the plaintext digest - if enabled.
+    #[inline]
+    #[cfg_attr(not(feature = "ring"), allow(unused_variables))]
+    fn digest_plaintext(&mut self, data: &[u8]) {
+        #[cfg(feature = "ring")]
+        {
+            if let Some(ref mut hasher) = self.hasher {
+                hasher.plaintext(data);
+            }
+        }
+    }
+
+    /// Attaches the `metadata` to the stream.
+    ///
+    /// The metadata is encrypted and authenticated in its own leading
+    /// fragment. A `DecReader` that expects metadata provides it before
+    /// returning any plaintext. It returns an error if the encoded
+    /// metadata does not fit into one fragment.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any data or metadata has already been written to
+    /// the `EncWriter`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::{Read, Write};
+    /// use sio::{Key, Nonce, Aad, EncWriter, DecReader, Metadata, CHACHA20_POLY1305};
+    ///
+    /// // Obviously, don't use this all-zeros key for anything real.
+    /// let key: Key<CHACHA20_POLY1305> = Key::new([0; Key::<CHACHA20_POLY1305>::SIZE]);
+    ///
+    /// let mut metadata = Metadata::default();
+    /// metadata.insert("content-type".to_string(), "text/plain".to_string());
+    ///
+    /// let mut ciphertext: Vec<u8> = Vec::default();
+    /// let mut writer = EncWriter::new(
+    ///     &mut ciphertext,
+    ///     &key,
+    ///     Nonce::new([0; Nonce::<CHACHA20_POLY1305>::SIZE]),
+    ///     Aad::empty(),
+    /// )
+    /// .attach_metadata(&metadata)
+    /// .unwrap();
+    /// writer.write_all(b"Hello World").unwrap();
+    /// writer.close().unwrap();
+    ///
+    /// let mut reader = DecReader::new(
+    ///     ciphertext.as_slice(),
+    ///     &key,
+    ///     Nonce::new([0; Nonce::<CHACHA20_POLY1305>::SIZE]),
+    ///     Aad::empty(),
+    /// )
+    /// .expect_metadata();
+    /// assert_eq!(reader.metadata().unwrap(), &metadata);
+    ///
+    /// let mut plaintext = String::default();
+    /// reader.read_to_string(&mut plaintext).unwrap();
+    /// assert_eq!(plaintext, "Hello World");
+    /// ```
+    pub fn attach_metadata(mut self, metadata: &Metadata) -> Result<Self, Invalid> {
+        assert!(
+            self.fragments == 0 && self.pos == 0 && !self.metadata,
+            "attach_metadata must be called before writing to the EncWriter"
+        );
+        let metadata = metadata::encode(metadata);
+        if metadata.len() > self.buf_size {
+            // Nothing has been written to the inner writer. Hence, the
+            // EncWriter can be dropped without being closed.
+            self.closed = true;
+            return Err(Invalid::Metadata);
+        }
+
+        // The metadata fragment is padded with zeros such that it is
+        // always a full fragment.
+        self.reserve(self.buf_size);
+        self.buffer[..metadata.len()].copy_from_slice(&metadata);
+        self.buffer[metadata.len()..self.buf_size]
+            .iter_mut()
+            .for_each(|b| *b = 0);
+        self.pos = self.buf_size;
+        self.metadata = true;
+        Ok(self)
+    }
+
+    /// Encrypts and authenticates all slices of `bufs` as one contiguous
+    /// plaintext without concatenating them first. It returns the total
+    /// number of bytes written.
+    fn write_slices(&mut self, bufs: &[IoSlice<'_>]) -> io::Result<usize> {
+        let n = bufs.iter().map(|buf| buf.len()).sum();
+        self.consume_quota(n)?;
+        for buf in bufs {
+            self.write_plaintext(buf)?;
+            self.digest_plaintext(buf);
+        }
+        Ok(n)
+    }
+
+    /// Encrypts and authenticates the plaintext `buf`. Complete fragments
+    /// are written to the inner writer while the rest remains buffered.
+    fn write_plaintext(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = buf.len();
+        let remaining = self.buf_size - self.pos;
+        if n <= remaining {
+            self.reserve(self.pos + n);
+            self.buffer[self.pos..self.pos + n].copy_from_slice(buf);
+            self.pos += n;
+            return Ok(n);
+        }
+
+        self.reserve(self.buf_size);
+        self.buffer[self.pos..self.buf_size].copy_from_slice(&buf[..remaining]);
+        self.write_buffer(self.buf_size, false)?;
+        self.pos = 0;
+        let buf = &buf[remaining..];
+
+        // The last chunk may be the final fragment. Hence, it
+        // remains buffered - even if it is a complete fragment.
+        let (chunks, last) = buf.split_at((buf.len() - 1) / self.buf_size * self.buf_size);
+        if chunks.len() > self.buf_size && self.framing.batchable() {
+            self.write_batches(chunks)?;
+        } else {
+            chunks.chunks(self.buf_size).try_for_each(|chunk| {
+                self.buffer[..self.buf_size].copy_from_slice(chunk);
+                self.write_buffer(self.buf_size, false)
+            })?;
+        }
+
+        self.buffer[..last.len()].copy_from_slice(last);
+        self.pos = last.len();
+        Ok(n)
+    }
+
+    /// Grows the buffer such that it can hold `len` bytes of plaintext
+    /// and the tag - but not beyond one fragment.
+    ///
+    /// The buffer at least doubles whenever it grows. Hence, a stream
+    /// causes only a few reallocations before the buffer has reached its
+    /// final size.
+    fn reserve(&mut self, len: usize) {
+        let fragment_len = self.buf_size + A::TAG_LEN;
+        let len = (len + A::TAG_LEN).min(fragment_len);
+        if self.buffer.len() >= len {
+            return;
+        }
+        let new_len = len
+            .max(2 * self.buffer.len())
+            .max(MIN_BUFFER_LEN)
+            .min(fragment_len);
+        let mut buffer = vec![0; new_len].into_boxed_slice();
+        buffer[..self.buffer.len()].copy_from_slice(&self.buffer);
+        self.buffer = buffer;
+    }
+
+    /// Encrypt and authenticate the buffer and write the ciphertext
+    /// to the inner writer.
+    fn write_buffer(&mut self, len: usize, last: bool) -> io::Result<()> {
+        self.seal_buffer(len, last)?;
+        let mut r = write_output(
+            &mut self.inner,
+            &mut self.output,
+            &self.buffer[..len + A::TAG_LEN],
+            format_args!("the ciphertext of fragment {}", self.fragments - 1),
+        );
+        if last {
+            r = r.and_then(|()| self.write_trailer());
+        }
+        if r.is_err() {
+            self.errored = true;
+        }
+        r
+    }
+
+    /// Encrypt and authenticate the first `len` bytes of the buffer in
+    /// place. The header of the framing is written before the first
+    /// fragment.
+    fn seal_buffer(&mut self, len: usize, last: bool) -> io::Result<()> {
+        self.reserve(len);
+        if self.fragments == 0 {
+            self.write_header()?;
+        }
+
+        let (nonce, aad) = match self.framing.next(&mut self.algorithm, last) {
+            Ok(v) => v,
+            Err(err) => {
+                self.errored = true;
+                let bytes = self.fragments * self.buf_size as u64;
+                return Err(err.processed(self.fragments, bytes).into());
+            }
+        };
+        self.fragments += 1;
+
+        let len = len + A::TAG_LEN;
+        if let Err(err) = self
+            .algorithm
+            .seal_in_place(nonce, aad, &mut self.buffer[..len])
+        {
+            self.errored = true;
+            return Err(err.into());
+        }
+        #[cfg(feature = "ring")]
+        {
+            if let Some(ref mut hasher) = self.hasher {
+                hasher.ciphertext(&self.buffer[..len]);
+                if last {
+                    hasher.ciphertext(self.framing.trailer());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Encrypts and authenticates the complete, non-final fragments of
+    /// `plaintext` in batches of up to `BATCH_LEN` fragments and writes
+    /// the ciphertext to the inner writer.
+    fn write_batches(&mut self, plaintext: &[u8]) -> io::Result<()> {
+        if self.fragments == 0 {
+            self.write_header()?;
+        }
+        let fragment_len = self.buf_size + A::TAG_LEN;
+        if self.batch.is_empty() {
+            self.batch = vec![0; BATCH_LEN * fragment_len].into_boxed_slice();
+        }
+
+        let mut nonces = [0; BATCH_LEN * MAX_NONCE_LEN];
+        let mut aad = Vec::default();
+        for batch in plaintext.chunks(BATCH_LEN * self.buf_size) {
+            let n = batch.len() / self.buf_size;
+            for (chunk, fragment) in batch
+                .chunks(self.buf_size)
+                .zip(self.batch.chunks_mut(fragment_len))
+            {
+                fragment[..self.buf_size].copy_from_slice(chunk);
+            }
+            for nonce in nonces[..n * A::NONCE_LEN].chunks_mut(A::NONCE_LEN) {
+                match self.framing.next(&mut self.algorithm, false) {
+                    Ok((next_nonce, next_aad)) => {
+                        nonce.copy_from_slice(next_nonce);
+                        aad.clear();
+                        aad.extend_from_slice(next_aad);
+                    }
+                    Err(err) => {
+                        self.errored = true;
+                        let bytes = self.fragments * self.buf_size as u64;
+                        return Err(err.processed(self.fragments, bytes).into());
+                    }
+                }
+                self.fragments += 1;
+            }
+
+            let len = n * fragment_len;
+            if let Err(err) = self.algorithm.seal_batch(
+                &nonces[..n * A::NONCE_LEN],
+                &aad,
+                &mut self.batch[..len],
+                fragment_len,
+            ) {
+                self.errored = true;
+                return Err(err.into());
+            }
+            #[cfg(feature = "ring")]
+            {
+                if let Some(ref mut hasher) = self.hasher {
+                    hasher.ciphertext(&self.batch[..len]);
+                }
+            }
+            if let Err(err) = write_output(
+                &mut self.inner,
+                &mut self.output,
+                &self.batch[..len],
+                format_args!(
+                    "the ciphertext of fragments {} to {}",
+                    self.fragments - n as u64,
+                    self.fragments - 1
+                ),
+            ) {
+                self.errored = true;
+                return Err(err);
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes the header of the framing to the inner writer.
+    fn write_header(&mut self) -> io::Result<()> {
+        let header = self.framing.header();
+        if let Err(err) = write_output(
+            &mut self.inner,
+            &mut self.output,
+            header,
+            format_args!("the header"),
+        ) {
+            self.errored = true;
+            return Err(err);
+        }
+        #[cfg(feature = "ring")]
+        {
+            if let Some(ref mut hasher) = self.hasher {
+                hasher.ciphertext(header);
+            }
+        }
+        Ok(())
+    }
+
+    /// Accepts at most as much plaintext as fits into the current
+    /// fragment. A complete fragment is only sealed once more plaintext
+    /// arrives since it may be the final fragment.
+    fn write_bounded(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.drain()?;
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        if self.pos == self.buf_size {
+            self.seal_buffer(self.buf_size, false)?;
+            self.sealed = self.buf_size + A::TAG_LEN;
+            self.pos = 0;
+            self.drain()?;
+        }
+
+        let mut n = buf.len().min(self.buf_size - self.pos);
+        if let Some(limit) = self.max_plaintext {
+            // A bounded write may accept less than `buf` - up to the limit.
+            let remaining = limit - self.plaintext_len;
+            if remaining == 0 {
+                return Err(QuotaExceeded::new(limit, self.plaintext_len).into());
+            }
+            n = n.min(remaining.min(usize::MAX as u64) as usize);
+        }
+        self.consume_quota(n)?;
+        self.reserve(self.pos + n);
+        self.buffer[self.pos..self.pos + n].copy_from_slice(&buf[..n]);
+        self.digest_plaintext(&buf[..n]);
+        self.pos += n;
+        Ok(n)
+    }
+
+    /// Writes the trailer of the framing to the inner writer.
+    fn write_trailer(&mut self) -> io::Result<()> {
+        let trailer = self.framing.trailer();
+        write_output(
+            &mut self.inner,
+            &mut self.output,
+            trailer,
+            format_args!("the trailer"),
+        )
+    }
+
+    /// Writes the buffered ciphertext - if any - to the inner writer.
+    fn flush_output(&mut self) -> io::Result<()> {
+        if let Some(ref mut output) = self.output {
+            if let Err(err) = output.flush(&mut self.inner) {
+                self.errored = true;
+                return Err(err);
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes the ciphertext of the sealed fragment - if any - to the
+    /// inner writer.
+    fn drain(&mut self) -> io::Result<()> {
+        while self.sent < self.sealed {
+            match self.inner.write(&self.buffer[self.sent..self.sealed]) {
+                Ok(0) => {
+                    self.errored = true;
+                    return Err(io::Error::new(
+                        io::ErrorKind::WriteZero,
+                        format!(
+                            "failed to write the ciphertext of fragment {}",
+                            self.fragments - 1
+                        ),
+                    ));
+                }
+                Ok(n) => self.sent += n,
+                Err(ref err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => {
+                    self.errored = err.kind() != io::ErrorKind::WouldBlock;
+                    return Err(err);
+                }
+            }
+        }
+        self.sealed = 0;
+        self.sent = 0;
+        Ok(())
+    }
+}
+
+impl<A: Algorithm, W: Write + internal::Close, F: Framing> Write for EncWriter<A, W, F> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.errored {
+            return Err(io::Error::from(io::ErrorKind::Other));
+        }
+        if self.backpressure {
+            return self.write_bounded(buf);
+        }
+        self.write_slices(&[IoSlice::new(buf)])
+    }
+
+    fn write_vectored(&mut self, bufs: &[IoSlice<'_>]) -> io::Result<usize> {
+        if self.errored {
+            return Err(io::Error::from(io::ErrorKind::Other));
+        }
+        if !self.backpressure {
+            return self.write_slices(bufs);
+        }
+
+        // With backpressure, we stop at the first slice that is not
+        // accepted completely.
+        let mut n = 0;
+        for buf in bufs {
+            match self.write_bounded(buf) {
+                Ok(k) if k < buf.len() => return Ok(n + k),
+                Ok(k) => n += k,
+                Err(err) if n == 0 => return Err(err),
+                Err(_) => break,
+            }
+        }
+        Ok(n)
+    }
+
+    fn write_all(&mut self, mut buf: &[u8]) -> io::Result<()> {
+        if !self.backpressure {
+            return self.write(buf).and(Ok(()));
+        }
+        // With backpressure, write accepts at most one fragment at once.
+        while !buf.is_empty() {
+            match self.write(buf) {
+                Ok(n) => buf = &buf[n..],
+                Err(ref err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if self.errored {
+            return Err(io::Error::from(io::ErrorKind::Other));
+        }
+        self.drain()?;
+        self.flush_output()?;
+        let r = self.inner.flush();
+        self.errored = r.is_err();
+        r
+    }
+}
+
+impl<A: Algorithm, W: Write + internal::Close, F: Framing> internal::Close for EncWriter<A, W, F> {
+    fn close(&mut self) -> io::Result<()> {
+        if self.errored {
+            return Err(io::Error::from(io::ErrorKind::Other));
+        }
+        self.closed = true;
+        self.drain()?;
+        if self.finished {
+            // A previous close failed with `ErrorKind::WouldBlock`
+            // after sealing the final fragment.
+            return self.write_trailer().and_then(|()| self.inner.close());
+        }
+        if let Some(len) = self.framing.stream_len() {
+            if len != self.fragments * self.buf_size as u64 + self.pos as u64 {
+                self.errored = true;
+                return Err(LengthMismatch.into());
+            }
+        }
+        if self.backpressure {
+            self.seal_buffer(self.pos, true)?;
+            self.sealed = self.pos + A::TAG_LEN;
+            self.finished = true;
+            self.drain()?;
+            return self.write_trailer().and_then(|()| self.inner.close());
+        }
+        self.write_buffer(self.pos, true)
+            .and_then(|()| self.flush_output())
+            .and_then(|()| self.inner.close())
+    }
+}
+
+/// The size an `EncWriter` allocates for its buffer when it gets written
+/// to the first time - unless the fragment is smaller.
+const MIN_BUFFER_LEN: usize = 1024;
+
+/// The maximum number of fragments that are en/decrypted with one
+/// call of `Algorithm::seal_batch` or `Algorithm::open_batch`.
+pub(crate) const BATCH_LEN: usize = 8;
+
+/// Returns a buffer of `len` bytes obtained from the `allocator` - if
+/// any - or a newly allocated one.
+fn allocate(allocator: Option<&dyn BufferAllocator>, len: usize) -> Result<Box<[u8]>, Invalid> {
+    match allocator {
+        Some(allocator) => {
+            let buffer = allocator.allocate(len);
+            if buffer.len() != len {
+                return Err(Invalid::BufSize);
+            }
+            Ok(buffer)
+        }
+        None => Ok(vec![0; len].into_boxed_slice()),
+    }
+}
+
+/// Writes `data` to the `output` buffer - if any - or to the `inner`
+/// writer otherwise.
+fn write_output<W: Write>(
+    inner: &mut W,
+    output: &mut Option<OutputBuffer>,
+    data: &[u8],
+    what: fmt::Arguments,
+) -> io::Result<()> {
+    match output {
+        Some(output) => output.write(inner, data, what),
+        None => utils::write_all(inner, data, what),
+    }
+}
+
+impl<A: Algorithm, W: Write + internal::Close, F: Framing> Drop for EncWriter<A, W, F> {
+    fn drop(&mut self) {
+        if let Some(allocator) = self.allocator.take() {
+            allocator.release(mem::take(&mut self.buffer));
+        }
+
+        // We must not check whether the EncWriter has been closed if
+        // we encountered an error during a write or flush call.
+        //
+        // We don't want to panic again if some code (between
+        // EncWriter::new(...) and EncWriter.close()) already
+        // panic'd. Otherwise we would cause a "double-panic".
+        if !self.errored
+            && !self.closed
+            && !panicking()
+            && !hook::unclosed::<Self>()
+            && !cfg!(feature = "panic-free")
+        {
+            panic!("EncWriter must be closed explicitly via the close method before being dropped!")
+        }
+    }
+}
+
+/// Wraps a writer and decrypts and verifies everything written to it.
 ///
 /// `DecWriter` splits data into fixed-size ciphertext fragments, produced
 /// by `EncWriter`, and decrypts and verifies each fragment separately. It
@@ -370,14 +1679,63 @@ impl<A: Algorithm, W: Write + internal::Close> Drop for EncWriter<A, W> {
 ///
 /// println!("{}", String::from_utf8_lossy(plaintext.as_slice())); // Let's print the plaintext.
 /// ```
-pub struct DecWriter<A: Algorithm, W: Write + internal::Close> {
+pub struct DecWriter<A: Algorithm, W: Write + internal::Close, F: Framing = Sio> {
     inner: W,
     algorithm: A,
     buffer: Box<[u8]>,
     pos: usize,
     buf_size: usize,
-    nonce: Counter<A>,
-    aad: [u8; 16 + 1], // TODO: replace with [u8; A::TAG_LEN + 1]
+    framing: F,
+
+    // In strict and tolerant mode, a copy of the current ciphertext
+    // fragment is kept such that a fragment that cannot be decrypted can
+    // be checked for being a final fragment followed by more data. If
+    // `None` neither mode is enabled.
+    ciphertext: Option<Box<[u8]>>,
+
+    // In strict mode, data following the final fragment is reported as
+    // `TrailingData` instead of being rejected as not authentic.
+    strict: bool,
+
+    // In tolerant mode, the DecWriter stops at the final fragment instead
+    // of rejecting any data following it.
+    tolerant: bool,
+
+    // The first bytes of the stream are collected until the framing
+    // has detected the layout of the stream. If `None` the layout has
+    // been detected.
+    prefix: Option<Vec<u8>>,
+
+    // The number of header bytes that have not been verified yet.
+    header: usize,
+
+    // The number of ciphertext bytes - including the header and trailer -
+    // processed so far.
+    consumed: u64,
+
+    // The number of fragments decrypted so far.
+    fragments: u64,
+
+    // Once the final fragment has been decrypted, no more ciphertext
+    // must be processed.
+    done: bool,
+
+    // In hold-back mode, the plaintext is kept until the final fragment
+    // has been verified. If `None` the plaintext is forwarded immediately.
+    held: Option<Vec<u8>>,
+    hold_limit: usize,
+
+    // In forensic mode, fragments that are not authentic are skipped and
+    // recorded in the report. If `None` the mode is disabled.
+    report: Option<CorruptionReport>,
+
+    // If set, the fragment buffer has been obtained from the allocator
+    // and is released to it once the DecWriter gets dropped.
+    allocator: Option<Arc<dyn BufferAllocator>>,
+
+    // Reports authentication failures to the audit hook.
+    #[cfg(feature = "ring")]
+    audit: Audit,
 
     // If an error occurs, we must fail any subsequent write of flush operation.
     // If set to true, this flag tells the write and flush implementation to fail
@@ -432,173 +1790,1020 @@ impl<A: Algorithm, W: Write + internal::Close> DecWriter<A, W> {
         Self::with_buffer_size(inner, key, nonce, aad, BUF_SIZE).unwrap()
     }
 
-    /// Creates a new `DecWriter` with the specified buffer size as fragment
-    /// size. The `buf_size` must not be `0` nor greater than `MAX_BUF_SIZE`
-    /// and must match the buffer size used to encrypt the data.
+    /// Creates a new `DecWriter` with the specified buffer size as fragment
+    /// size. The `buf_size` must not be `0` nor greater than `MAX_BUF_SIZE`
+    /// and must match the buffer size used to encrypt the data.
+    ///
+    /// Anything written to the `DecWriter` gets decrypted and verified
+    /// using the provided `key` and `nonce`. The `aad` is only verified
+    /// and neither decrypted nor written to the `inner` writer.
+    ///
+    /// It's important to always use the same buffer/fragment size for
+    /// encrypting and decrypting. Trying to decrypt data that has been
+    /// encrypted with a different fragment size will fail. Therefore,
+    /// the buffer size is usually fixed for one (kind of) application.
+    ///
+    /// # Examples
+    ///
+    /// Creating an `DecWriter` with a fragment size of 64 KiB.
+    ///
+    /// ```
+    /// use std::io::Write;
+    /// use sio::{Key, Nonce, Aad, DecWriter, CHACHA20_POLY1305};
+    ///
+    /// // Load your secret keys from a secure location or derive
+    /// // them using a secure (password-based) key-derivation-function, like Argon2id.
+    /// // Obviously, don't use this all-zeros key for anything real.
+    /// let key: Key<CHACHA20_POLY1305> = Key::new([0; Key::<CHACHA20_POLY1305>::SIZE]);
+    ///
+    /// // Use the same nonce that was used for encryption.
+    /// let nonce = Nonce::new([0; Nonce::<CHACHA20_POLY1305>::SIZE]);
+    ///
+    /// // Use the same associated data (AAD) that was used for encryption.
+    /// let aad = Aad::from("Some authenticated but not encrypted data".as_bytes());
+    ///
+    /// let mut plaintext: Vec<u8> = Vec::default();  // Store the plaintext in memory.
+    /// let mut writer = DecWriter::with_buffer_size(
+    ///     &mut plaintext,
+    ///     &key,
+    ///     nonce,
+    ///     aad,
+    ///     64 * 1024,
+    /// )
+    /// .unwrap();
+    ///
+    /// // Perform some write and flush operations
+    /// // ...
+    /// // For example:
+    /// writer.write(&[17, 137, 205, 68, 28, 113, 101, 52, 193, 68, 213, 16, 104,
+    ///                80, 203, 255, 183, 120, 46, 225, 192, 178, 253, 57, 67, 75,
+    ///                53, 57, 45, 94]).unwrap();
+    ///
+    /// writer.close().unwrap(); // Complete the decryption process explicitly!
+    ///
+    /// println!("{}", String::from_utf8_lossy(plaintext.as_slice())); // Let's print the plaintext.
+    /// ```
+    pub fn with_buffer_size(
+        inner: W,
+        key: &Key<A>,
+        nonce: Nonce<A>,
+        aad: Aad<A>,
+        buf_size: usize,
+    ) -> Result<Self, Invalid> {
+        Self::with_framing(inner, key, nonce, aad, buf_size, Sio::default())
+    }
+
+    /// Creates a new `DecWriter` with the specified buffer size as fragment
+    /// size that borrows its fragment buffer from the `pool`. The buffer
+    /// is returned to the pool once the `DecWriter` has been closed or
+    /// dropped. The `buf_size` must not be `0` nor greater than
+    /// `MAX_BUF_SIZE` and must match the buffer size used to encrypt
+    /// the data.
+    pub fn with_pool(
+        inner: W,
+        key: &Key<A>,
+        nonce: Nonce<A>,
+        aad: Aad<A>,
+        buf_size: usize,
+        pool: &BufferPool,
+    ) -> Result<Self, Invalid> {
+        let allocator = Arc::new(pool.clone());
+        Self::with_allocator(inner, key, nonce, aad, buf_size, allocator)
+    }
+
+    /// Creates a new `DecWriter` with the specified buffer size as fragment
+    /// size that obtains its fragment buffer from the `allocator`. The
+    /// buffer is released to the allocator once the `DecWriter` has been
+    /// closed or dropped. The `buf_size` must not be `0` nor greater than
+    /// `MAX_BUF_SIZE` and
+    /// must match the buffer size used to encrypt the data.
+    ///
+    /// It returns `Invalid::BufSize` if the allocator does not provide a
+    /// buffer of the requested size. See `BufferAllocator` for an example.
+    pub fn with_allocator(
+        inner: W,
+        key: &Key<A>,
+        nonce: Nonce<A>,
+        aad: Aad<A>,
+        buf_size: usize,
+        allocator: Arc<dyn BufferAllocator>,
+    ) -> Result<Self, Invalid> {
+        let framing = Sio::default();
+        Self::with_framing_and_allocator(inner, key, nonce, aad, buf_size, framing, Some(allocator))
+    }
+}
+
+impl<A: Algorithm, W: Write + internal::Close> DecWriter<A, W, FragmentSize<Sio>> {
+    /// Creates a new `DecWriter` that reads the buffer size used to
+    /// encrypt the stream from the stream itself.
+    ///
+    /// The stream must have been encrypted with the `FragmentSize`
+    /// framing wrapping the `Sio` framing. The `DecWriter` allocates its
+    /// buffer once it has read the fragment size from the header. A
+    /// stream that does not record a valid fragment size is rejected as
+    /// not authentic.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::Write;
+    /// use sio::{Key, Nonce, Aad, EncWriter, DecWriter, CHACHA20_POLY1305};
+    /// use sio::framing::{FragmentSize, Sio};
+    ///
+    /// // Obviously, don't use this all-zeros key for anything real.
+    /// let key: Key<CHACHA20_POLY1305> = Key::new([0; Key::<CHACHA20_POLY1305>::SIZE]);
+    ///
+    /// let mut ciphertext: Vec<u8> = Vec::default();
+    /// let mut writer = EncWriter::with_framing(
+    ///     &mut ciphertext,
+    ///     &key,
+    ///     Nonce::new([0; Nonce::<CHACHA20_POLY1305>::SIZE]),
+    ///     Aad::empty(),
+    ///     64 * 1024,
+    ///     FragmentSize::new(Sio::default(), 64 * 1024),
+    /// )
+    /// .unwrap();
+    /// writer.write_all(&[0; 100 * 1024]).unwrap();
+    /// writer.close().unwrap();
+    ///
+    /// let mut plaintext: Vec<u8> = Vec::default();
+    /// let mut writer = DecWriter::new_auto(
+    ///     &mut plaintext,
+    ///     &key,
+    ///     Nonce::new([0; Nonce::<CHACHA20_POLY1305>::SIZE]),
+    ///     Aad::empty(),
+    /// );
+    /// writer.write_all(ciphertext.as_slice()).unwrap();
+    /// writer.close().unwrap();
+    ///
+    /// assert_eq!(plaintext, vec![0; 100 * 1024]);
+    /// ```
+    pub fn new_auto(inner: W, key: &Key<A>, nonce: Nonce<A>, aad: Aad<A>) -> Self {
+        Self::with_framing(inner, key, nonce, aad, 1, FragmentSize::default()).unwrap()
+    }
+}
+
+impl<A: Algorithm, W: Write> DecWriter<A, NopCloser<W>> {
+    /// Creates a new `DecWriter` with a default buffer size of 16 KiB
+    /// for an `inner` writer that does not implement `Close`.
+    ///
+    /// The `inner` writer is wrapped into a `NopCloser`. Therefore,
+    /// closing the `DecWriter` only flushes the `inner` writer.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::{self, Write};
+    /// use sio::{Key, Nonce, Aad, EncWriter, DecWriter, CHACHA20_POLY1305};
+    ///
+    /// // Obviously, don't use this all-zeros key for anything real.
+    /// let key: Key<CHACHA20_POLY1305> = Key::new([0; Key::<CHACHA20_POLY1305>::SIZE]);
+    ///
+    /// let mut ciphertext: Vec<u8> = Vec::default();
+    /// let mut writer = EncWriter::new(
+    ///     &mut ciphertext,
+    ///     &key,
+    ///     Nonce::new([0; Nonce::<CHACHA20_POLY1305>::SIZE]),
+    ///     Aad::empty(),
+    /// );
+    /// writer.write_all(b"Hello World").unwrap();
+    /// writer.close().unwrap();
+    ///
+    /// let mut writer = DecWriter::new_unclosed(
+    ///     io::stdout(),
+    ///     &key,
+    ///     Nonce::new([0; Nonce::<CHACHA20_POLY1305>::SIZE]),
+    ///     Aad::empty(),
+    /// );
+    /// writer.write_all(ciphertext.as_slice()).unwrap();
+    /// writer.close().unwrap();
+    /// ```
+    pub fn new_unclosed(inner: W, key: &Key<A>, nonce: Nonce<A>, aad: Aad<A>) -> Self {
+        Self::new(NopCloser::wrap(inner), key, nonce, aad)
+    }
+}
+
+impl<A: Algorithm, W: Write + internal::Close, F: Framing> DecWriter<A, W, F> {
+    /// Creates a new `DecWriter` with the specified buffer size as fragment
+    /// size that uses the given `framing` instead of the default `Sio`
+    /// channel construction. The `buf_size` must not be `0` nor greater
+    /// than `MAX_BUF_SIZE`.
+    ///
+    /// Data decrypted with one framing can only be encrypted using the
+    /// same framing. See the `framing` module for the available framings.
+    ///
+    /// # Examples
+    ///
+    /// Creating an `DecWriter` that uses the STREAM construction.
+    ///
+    /// ```
+    /// use std::io::Write;
+    /// use sio::{Key, Nonce, Aad, EncWriter, DecWriter, BUF_SIZE, CHACHA20_POLY1305};
+    /// use sio::framing::Stream;
+    ///
+    /// // Obviously, don't use this all-zeros key for anything real.
+    /// let key: Key<CHACHA20_POLY1305> = Key::new([0; Key::<CHACHA20_POLY1305>::SIZE]);
+    ///
+    /// let mut ciphertext: Vec<u8> = Vec::default();
+    /// let mut writer = EncWriter::with_framing(
+    ///     &mut ciphertext,
+    ///     &key,
+    ///     Nonce::new([0; Nonce::<CHACHA20_POLY1305>::SIZE]),
+    ///     Aad::empty(),
+    ///     BUF_SIZE,
+    ///     Stream::default(),
+    /// )
+    /// .unwrap();
+    /// writer.write_all(b"Hello World").unwrap();
+    /// writer.close().unwrap();
+    ///
+    /// let mut plaintext: Vec<u8> = Vec::default();
+    /// let mut writer = DecWriter::with_framing(
+    ///     &mut plaintext,
+    ///     &key,
+    ///     Nonce::new([0; Nonce::<CHACHA20_POLY1305>::SIZE]),
+    ///     Aad::empty(),
+    ///     BUF_SIZE,
+    ///     Stream::default(),
+    /// )
+    /// .unwrap();
+    /// writer.write_all(ciphertext.as_slice()).unwrap();
+    /// writer.close().unwrap();
+    ///
+    /// assert_eq!(plaintext.as_slice(), b"Hello World");
+    /// ```
+    pub fn with_framing(
+        inner: W,
+        key: &Key<A>,
+        nonce: Nonce<A>,
+        aad: Aad<A>,
+        buf_size: usize,
+        framing: F,
+    ) -> Result<Self, Invalid> {
+        Self::with_framing_and_allocator(inner, key, nonce, aad, buf_size, framing, None)
+    }
+
+    /// Creates a new `DecWriter` that obtains its fragment buffer from the
+    /// `allocator` - if any.
+    fn with_framing_and_allocator(
+        inner: W,
+        key: &Key<A>,
+        nonce: Nonce<A>,
+        aad: Aad<A>,
+        buf_size: usize,
+        mut framing: F,
+        allocator: Option<Arc<dyn BufferAllocator>>,
+    ) -> Result<Self, Invalid> {
+        if buf_size == 0 || buf_size > MAX_BUF_SIZE {
+            return Err(Invalid::BufSize);
+        }
+        let algorithm = A::new(key.as_ref());
+        framing.init(key.as_ref(), &algorithm, nonce.as_ref(), aad.as_ref())?;
+
+        // The buffer can hold the trailer in addition to a fragment
+        // such that a fragment is only processed once it is known
+        // whether it is the final one.
+        let buffer_len = buf_size + A::TAG_LEN + framing.trailer_len();
+        let buffer = allocate(allocator.as_deref(), buffer_len)?;
+        Ok(DecWriter {
+            inner,
+            algorithm,
+            buffer,
+            pos: 0,
+            buf_size,
+            prefix: match framing.detect_len() {
+                0 => None,
+                n => Some(Vec::with_capacity(n)),
+            },
+            header: framing.header().len(),
+            framing,
+            ciphertext: None,
+            strict: false,
+            tolerant: false,
+            consumed: 0,
+            fragments: 0,
+            done: false,
+            held: None,
+            hold_limit: 0,
+            report: None,
+            allocator,
+            #[cfg(feature = "ring")]
+            audit: Audit::new(aad.as_ref()),
+            errored: false,
+            closed: false,
+        })
+    }
+
+    #[must_use = "A DecWriter must be closed to successfully complete the decryption process. Ignoring this result may cause incomplete plaintext data."]
+    #[inline(always)]
+    pub fn close(mut self) -> io::Result<()> {
+        internal::Close::close(&mut self)
+    }
+
+    #[inline(always)]
+    pub fn closer(self) -> Closer<Self> {
+        Closer::wrap(self)
+    }
+
+    /// Decrypts and verifies all ciphertext chunks produced by `chunks`
+    /// and closes the `DecWriter`. It returns the number of ciphertext
+    /// bytes consumed.
+    ///
+    /// `consume` is the decrypting counterpart of `EncWriter::consume`.
+    /// If the producer fails, `consume` returns its error without closing
+    /// the `DecWriter`. Hence, the plaintext written so far must not be
+    /// trusted since the end of the stream has not been verified.
+    pub fn consume<I>(mut self, chunks: I) -> io::Result<u64>
+    where
+        I: IntoIterator<Item = io::Result<Vec<u8>>>,
+    {
+        let mut n = 0;
+        for chunk in chunks {
+            let chunk = chunk.inspect_err(|_| self.errored = true)?;
+            self.write_all(&chunk)?;
+            n += chunk.len() as u64;
+        }
+        self.close()?;
+        Ok(n)
+    }
+
+    /// Returns a mutable reference to the inner writer.
+    #[cfg(feature = "futures-io")]
+    #[inline]
+    pub(crate) fn get_mut(&mut self) -> &mut W {
+        &mut self.inner
+    }
+
+    /// Enables the strict mode of the `DecWriter`.
+    ///
+    /// By default, any ciphertext written after the final fragment is
+    /// rejected as not authentic - unless the framing records the length
+    /// of the stream, e.g. `Delimited`. In strict mode, the `DecWriter`
+    /// finds the final fragment - even if more data follows it - and
+    /// returns a `TrailingData` error once the plaintext of the final
+    /// fragment has been written to the inner writer. Enabling the strict
+    /// mode overrides the tolerant mode and vice versa.
+    ///
+    /// If the framing records the length of the stream, the final fragment
+    /// is decrypted as soon as it is complete. Otherwise, a fragment that
+    /// cannot be decrypted is checked for being a shorter final fragment
+    /// followed by more data - trying all possible lengths, the shortest
+    /// first. Hence, rejecting trailing data becomes more expensive the
+    /// longer the final fragment is. Use `Delimited` to detect trailing
+    /// data without trying multiple lengths.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::{self, Write};
+    /// use sio::{Key, Nonce, Aad, EncWriter, DecWriter, CHACHA20_POLY1305};
+    ///
+    /// // Obviously, don't use this all-zeros key for anything real.
+    /// let key: Key<CHACHA20_POLY1305> = Key::new([0; Key::<CHACHA20_POLY1305>::SIZE]);
+    ///
+    /// let mut ciphertext: Vec<u8> = Vec::default();
+    /// let mut writer = EncWriter::new(
+    ///     &mut ciphertext,
+    ///     &key,
+    ///     Nonce::new([0; Nonce::<CHACHA20_POLY1305>::SIZE]),
+    ///     Aad::empty(),
+    /// );
+    /// writer.write_all(b"Hello World").unwrap();
+    /// writer.close().unwrap();
+    ///
+    /// ciphertext.extend_from_slice(b"some trailing data");
+    ///
+    /// let mut writer = DecWriter::new(
+    ///     io::sink(),
+    ///     &key,
+    ///     Nonce::new([0; Nonce::<CHACHA20_POLY1305>::SIZE]),
+    ///     Aad::empty(),
+    /// )
+    /// .strict();
+    ///
+    /// writer.write_all(ciphertext.as_slice()).unwrap();
+    /// let err = writer.close().unwrap_err();
+    /// assert_eq!(err.to_string(), "unexpected data after the final fragment");
+    /// ```
+    pub fn strict(mut self) -> Self {
+        self.ciphertext = Some(vec![0; self.buffer.len()].into_boxed_slice());
+        self.strict = true;
+        self.tolerant = false;
+        self
+    }
+
+    /// Enables the tolerant mode of the `DecWriter`.
+    ///
+    /// In tolerant mode, the `DecWriter` stops cleanly at the final
+    /// fragment and ignores any data following it. This allows decrypting
+    /// multiple encrypted streams that have been concatenated one after
+    /// another. Once the final fragment has been decrypted, any further
+    /// `write` returns `Ok(0)` and the `consumed` method reports where the
+    /// next stream starts. Enabling the tolerant mode overrides the strict
+    /// mode and vice versa.
+    ///
+    /// Like in strict mode, the end of a stream whose framing does not
+    /// record its length is found by trying all possible lengths of the
+    /// final fragment. With `Delimited`, the `DecWriter` stops at the
+    /// recorded end right away.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::Write;
+    /// use sio::{Key, Nonce, Aad, EncWriter, DecWriter, CHACHA20_POLY1305};
+    ///
+    /// // Obviously, don't use this all-zeros key for anything real.
+    /// let key: Key<CHACHA20_POLY1305> = Key::new([0; Key::<CHACHA20_POLY1305>::SIZE]);
+    ///
+    /// let mut ciphertext: Vec<u8> = Vec::default();
+    /// for message in &["Hello", "World"] {
+    ///     let mut writer = EncWriter::new(
+    ///         &mut ciphertext,
+    ///         &key,
+    ///         Nonce::new([0; Nonce::<CHACHA20_POLY1305>::SIZE]),
+    ///         Aad::empty(),
+    ///     );
+    ///     writer.write_all(message.as_bytes()).unwrap();
+    ///     writer.close().unwrap();
+    /// }
+    ///
+    /// let mut plaintext: Vec<u8> = Vec::default();
+    /// let mut writer = DecWriter::new(
+    ///     &mut plaintext,
+    ///     &key,
+    ///     Nonce::new([0; Nonce::<CHACHA20_POLY1305>::SIZE]),
+    ///     Aad::empty(),
+    /// )
+    /// .tolerant();
+    /// writer.write_all(ciphertext.as_slice()).unwrap();
+    /// let consumed = writer.finish().unwrap();
+    ///
+    /// assert_eq!(plaintext.as_slice(), b"Hello");
+    /// assert_eq!(consumed as usize, ciphertext.len() / 2);
+    /// ```
+    pub fn tolerant(mut self) -> Self {
+        self.ciphertext = Some(vec![0; self.buffer.len()].into_boxed_slice());
+        self.strict = false;
+        self.tolerant = true;
+        self
+    }
+
+    /// Enables the hold-back mode of the `DecWriter`.
+    ///
+    /// By default, the `DecWriter` forwards the plaintext of every fragment
+    /// once it has been verified. If the stream turns out to be truncated
+    /// later on, the inner writer has already received a prefix of the
+    /// plaintext. In hold-back mode, the `DecWriter` keeps all plaintext
+    /// in memory and only forwards it once the final fragment has been
+    /// verified. Hence, the inner writer never sees any plaintext of a
+    /// stream that is not authentic or truncated.
+    ///
+    /// At most `limit` plaintext bytes are held back. Writing a stream
+    /// with more plaintext fails.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::Write;
+    /// use sio::{Key, Nonce, Aad, EncWriter, DecWriter, CHACHA20_POLY1305};
+    ///
+    /// // Obviously, don't use this all-zeros key for anything real.
+    /// let key: Key<CHACHA20_POLY1305> = Key::new([0; Key::<CHACHA20_POLY1305>::SIZE]);
+    ///
+    /// let mut ciphertext: Vec<u8> = Vec::default();
+    /// let mut writer = EncWriter::with_buffer_size(
+    ///     &mut ciphertext,
+    ///     &key,
+    ///     Nonce::new([0; Nonce::<CHACHA20_POLY1305>::SIZE]),
+    ///     Aad::empty(),
+    ///     64,
+    /// )
+    /// .unwrap();
+    /// writer.write_all(&[0; 1000]).unwrap();
+    /// writer.close().unwrap();
+    ///
+    /// let mut plaintext: Vec<u8> = Vec::default();
+    /// let mut writer = DecWriter::with_buffer_size(
+    ///     &mut plaintext,
+    ///     &key,
+    ///     Nonce::new([0; Nonce::<CHACHA20_POLY1305>::SIZE]),
+    ///     Aad::empty(),
+    ///     64,
+    /// )
+    /// .unwrap()
+    /// .hold_back(1 << 20);
+    ///
+    /// // Drop the final fragment.
+    /// writer.write_all(&ciphertext[..ciphertext.len() - 56]).unwrap();
+    /// assert!(writer.close().is_err());
+    /// assert!(plaintext.is_empty());
+    /// ```
+    pub fn hold_back(mut self, limit: usize) -> Self {
+        self.held = Some(Vec::default());
+        self.hold_limit = limit;
+        self
+    }
+
+    /// Enables the forensic mode of the `DecWriter`.
     ///
-    /// Anything written to the `DecWriter` gets decrypted and verified
-    /// using the provided `key` and `nonce`. The `aad` is only verified
-    /// and neither decrypted nor written to the `inner` writer.
+    /// By default, the `DecWriter` aborts at the first fragment that is
+    /// not authentic. In forensic mode, such a fragment is skipped - i.e.
+    /// its plaintext is not written to the inner writer - and decryption
+    /// continues with the next fragment. All skipped fragments are listed
+    /// in the `CorruptionReport` returned by `close_forensic`. Hence, data
+    /// recovery tools can restore everything that is still authentic.
     ///
-    /// It's important to always use the same buffer/fragment size for
-    /// encrypting and decrypting. Trying to decrypt data that has been
-    /// encrypted with a different fragment size will fail. Therefore,
-    /// the buffer size is usually fixed for one (kind of) application.
+    /// The forensic mode must never be used to decrypt data that is
+    /// processed further without checking the damaged ranges. A skipped
+    /// fragment may also be the result of an attacker modifying the
+    /// ciphertext. Further, a missing final fragment is reported as
+    /// damaged fragment instead of as truncated stream.
     ///
     /// # Examples
     ///
-    /// Creating an `DecWriter` with a fragment size of 64 KiB.
-    ///
     /// ```
     /// use std::io::Write;
-    /// use sio::{Key, Nonce, Aad, DecWriter, CHACHA20_POLY1305};
+    /// use sio::{Key, Nonce, Aad, EncWriter, DecWriter, CHACHA20_POLY1305};
     ///
-    /// // Load your secret keys from a secure location or derive
-    /// // them using a secure (password-based) key-derivation-function, like Argon2id.
     /// // Obviously, don't use this all-zeros key for anything real.
     /// let key: Key<CHACHA20_POLY1305> = Key::new([0; Key::<CHACHA20_POLY1305>::SIZE]);
     ///
-    /// // Use the same nonce that was used for encryption.
-    /// let nonce = Nonce::new([0; Nonce::<CHACHA20_POLY1305>::SIZE]);
+    /// let mut ciphertext: Vec<u8> = Vec::default();
+    /// let mut writer = EncWriter::with_buffer_size(
+    ///     &mut ciphertext,
+    ///     &key,
+    ///     Nonce::new([0; Nonce::<CHACHA20_POLY1305>::SIZE]),
+    ///     Aad::empty(),
+    ///     64,
+    /// )
+    /// .unwrap();
+    /// writer.write_all(&[1; 200]).unwrap();
+    /// writer.close().unwrap();
     ///
-    /// // Use the same associated data (AAD) that was used for encryption.
-    /// let aad = Aad::from("Some authenticated but not encrypted data".as_bytes());
+    /// ciphertext[100] ^= 1; // Damage the second fragment.
     ///
-    /// let mut plaintext: Vec<u8> = Vec::default();  // Store the plaintext in memory.
+    /// let mut plaintext: Vec<u8> = Vec::default();
     /// let mut writer = DecWriter::with_buffer_size(
     ///     &mut plaintext,
     ///     &key,
-    ///     nonce,
-    ///     aad,
-    ///     64 * 1024,
+    ///     Nonce::new([0; Nonce::<CHACHA20_POLY1305>::SIZE]),
+    ///     Aad::empty(),
+    ///     64,
     /// )
-    /// .unwrap();
+    /// .unwrap()
+    /// .forensic();
+    /// writer.write_all(ciphertext.as_slice()).unwrap();
     ///
-    /// // Perform some write and flush operations
-    /// // ...
-    /// // For example:
-    /// writer.write(&[17, 137, 205, 68, 28, 113, 101, 52, 193, 68, 213, 16, 104,
-    ///                80, 203, 255, 183, 120, 46, 225, 192, 178, 253, 57, 67, 75,
-    ///                53, 57, 45, 94]).unwrap();
+    /// let report = writer.close_forensic().unwrap();
+    /// assert_eq!(report.fragments(), &[1]);
+    /// assert_eq!(report.ranges(), &[64..128]);
+    /// assert!(report.final_verified());
+    /// assert_eq!(plaintext.len(), 200 - 64);
+    /// ```
+    pub fn forensic(mut self) -> Self {
+        self.report = Some(CorruptionReport::default());
+        self
+    }
+
+    /// Returns the plaintext ranges of all fragments that have been
+    /// skipped in forensic mode so far.
+    #[inline]
+    pub fn damaged(&self) -> &[Range<u64>] {
+        self.report.as_ref().map_or(&[], |r| r.ranges())
+    }
+
+    /// Completes the decryption process like `close` and returns a
+    /// report of all fragments that have been skipped in forensic mode.
     ///
-    /// writer.close().unwrap(); // Complete the decryption process explicitly!
+    /// # Panics
+    ///
+    /// This method panics if the forensic mode is not enabled - unless the
+    /// `panic-free` feature is enabled.
+    #[must_use = "A DecWriter must be closed to successfully complete the decryption process. Ignoring this result may cause incomplete plaintext data."]
+    pub fn close_forensic(mut self) -> io::Result<CorruptionReport> {
+        if self.report.is_none() {
+            self.errored = true;
+            return Err(utils::misuse("the forensic mode is not enabled"));
+        }
+        internal::Close::close(&mut self)?;
+        self.report
+            .take()
+            .ok_or_else(|| io::Error::from(io::ErrorKind::Other))
+    }
+
+    /// Returns the number of ciphertext bytes that belong to the
+    /// fragments decrypted so far - including the header of the
+    /// framing, if any.
+    ///
+    /// In tolerant mode, once a `write` returned fewer bytes than
+    /// provided, the final fragment has been decrypted and this is the
+    /// size of the encrypted stream.
+    #[inline]
+    pub fn consumed(&self) -> u64 {
+        self.consumed
+    }
+
+    /// Checks whether an encrypted stream of `len` bytes - including the
+    /// header and trailer of the framing - can be decrypted by this
+    /// `DecWriter` and fails with `Invalid::CiphertextLen` otherwise.
+    ///
+    /// Every fragment but the final one consists of exactly `buf_size`
+    /// bytes of ciphertext and the tag, and the final fragment contains
+    /// at least the tag - even if it follows a full fragment. Hence, only
+    /// some lengths are possible and the number of fragments is limited
+    /// by the framing. If the framing records the length of the stream -
+    /// e.g. `Delimited` - only the exact length is possible. Checking the
+    /// length of a stream up front rejects obviously truncated or padded
+    /// data before any fragment gets decrypted. A length that is possible
+    /// does not imply that the stream is authentic.
+    ///
+    /// Before the framing has detected the format of the stream, or the
+    /// fragment size has been detected, only the minimum length of a
+    /// stream is checked. In tolerant mode, any data may follow the final
+    /// fragment and, therefore, every length above the minimum is
+    /// possible.
+    ///
+    /// # Examples
     ///
-    /// println!("{}", String::from_utf8_lossy(plaintext.as_slice())); // Let's print the plaintext.
     /// ```
-    pub fn with_buffer_size(
-        inner: W,
-        key: &Key<A>,
-        nonce: Nonce<A>,
-        aad: Aad<A>,
-        buf_size: usize,
-    ) -> Result<Self, Invalid> {
-        if buf_size == 0 || buf_size > MAX_BUF_SIZE {
-            return Err(Invalid::BufSize);
+    /// use sio::{Key, Nonce, Aad, DecWriter, Invalid, CHACHA20_POLY1305};
+    ///
+    /// // Obviously, don't use this all-zeros key for anything real.
+    /// let key: Key<CHACHA20_POLY1305> = Key::new([0; Key::<CHACHA20_POLY1305>::SIZE]);
+    ///
+    /// let mut plaintext: Vec<u8> = Vec::default();
+    /// let writer = DecWriter::with_buffer_size(
+    ///     &mut plaintext,
+    ///     &key,
+    ///     Nonce::new([0; Nonce::<CHACHA20_POLY1305>::SIZE]),
+    ///     Aad::empty(),
+    ///     64,
+    /// )
+    /// .unwrap();
+    ///
+    /// assert!(writer.can_accept(16).is_ok()); // An empty stream.
+    /// assert!(writer.can_accept(80 + 16).is_ok()); // An empty final fragment.
+    /// assert!(writer.can_accept(80 + 17).is_ok());
+    /// assert_eq!(writer.can_accept(80 + 15), Err(Invalid::CiphertextLen));
+    /// assert_eq!(writer.can_accept(15), Err(Invalid::CiphertextLen));
+    /// # writer.close().unwrap_err();
+    /// ```
+    pub fn can_accept(&self, len: u64) -> Result<(), Invalid> {
+        let layout = Layout::new(self.buf_size, A::TAG_LEN)?;
+        let tag_len = A::TAG_LEN as u64;
+        let detected = self.prefix.is_none();
+        let overhead = if detected {
+            (self.framing.header().len() + self.framing.trailer_len()) as u64
+        } else {
+            self.framing.trailer_len() as u64
+        };
+        let len = match len.checked_sub(overhead) {
+            Some(len) if len >= tag_len => len,
+            _ => return Err(Invalid::CiphertextLen),
+        };
+        let possible = if !detected {
+            true
+        } else if let Some(stream_len) = self.framing.stream_len() {
+            match layout.ciphertext_len(stream_len) {
+                Some(expected) if self.tolerant => len >= expected,
+                Some(expected) => len == expected,
+                None => false,
+            }
+        } else if self.tolerant {
+            true
+        } else {
+            let fragment_len = layout.fragment_len();
+            let rest = len % fragment_len;
+            let fragments = len / fragment_len + u64::from(rest > 0);
+            (rest == 0 || rest >= tag_len)
+                && fragments <= self.fragments.saturating_add(self.framing.remaining())
+        };
+        if possible {
+            Ok(())
+        } else {
+            Err(Invalid::CiphertextLen)
         }
-        let algorithm = A::new(key.as_ref());
-        let mut nonce = Counter::zero(nonce);
-        let mut associated_data = [0; 1 + 16];
-        algorithm
-            .seal_in_place(
-                &nonce.next().unwrap(),
-                aad.as_ref(),
-                &mut associated_data[1..],
-            )
-            .unwrap();
+    }
 
-        Ok(DecWriter {
-            inner: inner,
-            algorithm: A::new(key.as_ref()),
-            buffer: vec![0; buf_size + A::TAG_LEN].into_boxed_slice(),
-            pos: 0,
-            buf_size: buf_size,
-            nonce: nonce,
-            aad: associated_data,
-            errored: false,
-            closed: false,
-        })
+    /// Returns the framing of the `DecWriter` - e.g. to query the
+    /// format version detected by the `Auto` framing.
+    #[inline]
+    pub fn framing(&self) -> &F {
+        &self.framing
     }
 
+    /// Completes the decryption process like `close` and returns the
+    /// number of ciphertext bytes that belong to the decrypted stream.
+    ///
+    /// In tolerant mode, any data following the final fragment is not
+    /// counted. Hence, the returned value is the offset of the next
+    /// stream within concatenated streams.
     #[must_use = "A DecWriter must be closed to successfully complete the decryption process. Ignoring this result may cause incomplete plaintext data."]
-    #[inline(always)]
-    pub fn close(mut self) -> io::Result<()> {
-        internal::Close::close(&mut self)
+    pub fn finish(mut self) -> io::Result<u64> {
+        internal::Close::close(&mut self).map(|()| self.consumed)
     }
 
-    #[inline(always)]
-    pub fn closer(self) -> impl Write + Close {
-        Closer::wrap(self)
-    }
+    /// Decrypt and verifies the first `len` bytes of the buffer and write
+    /// the plaintext to the inner writer. If the fragment is the `last` one,
+    /// it must be followed by the trailer of the framing.
+    fn write_buffer(&mut self, len: usize, last: bool) -> io::Result<()> {
+        // Without a recorded length, a fragment that cannot be decrypted
+        // may be a shorter final fragment followed by more data.
+        let framing = match self.ciphertext {
+            Some(ref mut ciphertext) if self.framing.stream_len().is_none() => {
+                ciphertext[..self.pos].copy_from_slice(&self.buffer[..self.pos]);
+                Some(self.framing.clone())
+            }
+            _ => None,
+        };
 
-    /// Decrypt and verifies the buffer and write the plaintext
-    /// to the inner writer.
-    fn write_buffer(&mut self, len: usize) -> io::Result<()> {
-        let nonce = match self.nonce.next() {
-            Ok(nonce) => nonce,
+        let trailer_len = self.framing.trailer_len();
+        let (nonce, aad) = match self.framing.next(&mut self.algorithm, last) {
+            Ok(v) => v,
             Err(err) => {
                 self.errored = true;
-                return Err(err.into());
+                let bytes = self.fragments * self.buf_size as u64;
+                return Err(err.processed(self.fragments, bytes).into());
             }
         };
 
-        let plaintext =
-            match self
-                .algorithm
-                .open_in_place(nonce, &self.aad, &mut self.buffer[..len])
-            {
-                Ok(plaintext) => plaintext,
-                Err(err) => {
+        let plaintext = self
+            .algorithm
+            .open_in_place(nonce, aad, &mut self.buffer[..len])
+            .map(|plaintext| plaintext.len());
+        let plaintext = match plaintext {
+            Ok(_) if last && self.buffer[len..len + trailer_len] != *self.framing.trailer() => {
+                Err(NotAuthentic)
+            }
+            plaintext => plaintext,
+        };
+
+        let (len, plaintext, last) = match plaintext {
+            Ok(plaintext) => (len, plaintext, last),
+            Err(err) => match framing.and_then(|f| self.find_final_fragment(&f, len, last)) {
+                Some(n) => (n, n - A::TAG_LEN, true),
+                None => {
+                    #[cfg(feature = "ring")]
+                    self.audit.report(self.fragments, self.consumed, len);
+                    if let Some(ref mut report) = self.report {
+                        let start = self.fragments * self.buf_size as u64;
+                        let end = start + len.saturating_sub(A::TAG_LEN) as u64;
+                        report.push(self.fragments, start..end);
+                        return self.skip_fragment(len, last);
+                    }
                     self.errored = true;
                     return Err(err.into());
                 }
-            };
+            },
+        };
+        self.fragments += 1;
+        self.consumed += len as u64;
+        if last {
+            self.consumed += trailer_len as u64;
+        }
+        self.done = last;
+        if let (true, Some(report)) = (last, self.report.as_mut()) {
+            report.set_final_verified();
+        }
 
-        match self.inner.write_all(plaintext) {
-            Ok(v) => Ok(v),
-            Err(err) => {
+        let r = match self.held {
+            Some(ref mut held) => {
+                if held.len() + plaintext > self.hold_limit {
+                    Err(io::Error::other("plaintext exceeds the hold-back limit"))
+                } else {
+                    held.extend_from_slice(&self.buffer[..plaintext]);
+                    if last {
+                        utils::write_all(
+                            &mut self.inner,
+                            held,
+                            format_args!("the plaintext up to fragment {}", self.fragments - 1),
+                        )
+                    } else {
+                        Ok(())
+                    }
+                }
+            }
+            None => utils::write_all(
+                &mut self.inner,
+                &self.buffer[..plaintext],
+                format_args!("the plaintext of fragment {}", self.fragments - 1),
+            ),
+        };
+        if r.is_err() {
+            self.errored = true;
+        }
+        r
+    }
+
+    /// Skips the current fragment of `len` bytes in forensic mode without
+    /// writing any plaintext. If it is the `last` one, any plaintext held
+    /// back is written to the inner writer.
+    fn skip_fragment(&mut self, len: usize, last: bool) -> io::Result<()> {
+        self.fragments += 1;
+        self.consumed += len as u64;
+        if !last {
+            return Ok(());
+        }
+        self.consumed += self.framing.trailer_len() as u64;
+        self.done = true;
+        let r = match self.held {
+            Some(ref held) => utils::write_all(
+                &mut self.inner,
+                held,
+                format_args!("the plaintext up to fragment {}", self.fragments - 1),
+            ),
+            None => Ok(()),
+        };
+        if r.is_err() {
+            self.errored = true;
+        }
+        r
+    }
+
+    /// Searches the copy of the buffered ciphertext for a valid final
+    /// fragment followed by the trailer and more data. The given `framing`
+    /// must be in the state before the current fragment of `len` bytes
+    /// has been processed. If the current fragment is the `last` one, only
+    /// final fragments shorter than `len` are considered.
+    ///
+    /// It returns the length of the final fragment, if any.
+    fn find_final_fragment(&mut self, framing: &F, len: usize, last: bool) -> Option<usize> {
+        let ciphertext = self.ciphertext.as_ref()?;
+        let max_len = if last { len.saturating_sub(1) } else { len };
+        framing::find_final_fragment(
+            &mut self.algorithm,
+            framing,
+            &ciphertext[..self.pos],
+            &mut self.buffer,
+            max_len,
+        )
+    }
+
+    /// Verifies the header bytes at the beginning of `buf` - if the header
+    /// has not been verified completely yet - and returns the rest of `buf`.
+    fn verify_header<'a>(&mut self, buf: &'a [u8]) -> io::Result<&'a [u8]> {
+        let header = self.framing.header();
+        let pos = header.len() - self.header;
+        let n = self.header.min(buf.len());
+        if buf[..n] != header[pos..pos + n] {
+            self.errored = true;
+            return Err(NotAuthentic.into());
+        }
+        self.header -= n;
+        self.consumed += n as u64;
+        Ok(&buf[n..])
+    }
+
+    /// Replaces the buffer size - and all buffers - with the fragment
+    /// size recorded by the framing. A fragment size that no `EncWriter`
+    /// can produce is not authentic.
+    fn set_buf_size(&mut self, buf_size: usize) -> io::Result<()> {
+        if buf_size == 0 || buf_size > MAX_BUF_SIZE {
+            return Err(NotAuthentic.into());
+        }
+        if buf_size == self.buf_size {
+            return Ok(());
+        }
+        let buffer_len = buf_size + A::TAG_LEN + self.framing.trailer_len();
+        let buffer = allocate(self.allocator.as_deref(), buffer_len)?;
+        let buffer = mem::replace(&mut self.buffer, buffer);
+        if let Some(ref allocator) = self.allocator {
+            allocator.release(buffer);
+        }
+        if let Some(ref mut ciphertext) = self.ciphertext {
+            *ciphertext = vec![0; buffer_len].into_boxed_slice();
+        }
+        self.buf_size = buf_size;
+        Ok(())
+    }
+
+    /// Lets the framing detect the layout of the stream from its first
+    /// bytes and processes them afterwards.
+    fn detect(&mut self, prefix: &[u8]) -> io::Result<()> {
+        if let Err(err) = self.framing.detect(prefix) {
+            self.errored = true;
+            return Err(err.into());
+        }
+        if let Some(buf_size) = self.framing.buf_size() {
+            if let Err(err) = self.set_buf_size(buf_size) {
                 self.errored = true;
-                Err(err)
+                return Err(err);
+            }
+        }
+        self.header = self.framing.header().len();
+        self.write_ciphertext(prefix).and(Ok(()))
+    }
+
+    /// Returns the length of the current fragment if it is the final
+    /// one according to the length recorded by the framing.
+    fn final_len(&self) -> Option<usize> {
+        let len = self.framing.stream_len()?;
+        let buf_size = self.buf_size as u64;
+        let fragments = len.div_ceil(buf_size).max(1);
+        if self.fragments + 1 != fragments {
+            return None;
+        }
+        Some((len - self.fragments * buf_size) as usize + A::TAG_LEN)
+    }
+
+    /// Handles ciphertext written after the final fragment. In tolerant
+    /// mode, it is not consumed and `n` - the number of bytes that belong
+    /// to the stream - is returned.
+    fn trailing_data(&mut self, n: usize) -> io::Result<usize> {
+        if self.tolerant {
+            return Ok(n);
+        }
+        self.errored = true;
+        if self.strict || self.framing.stream_len().is_some() {
+            Err(TrailingData.into())
+        } else {
+            Err(NotAuthentic.into())
+        }
+    }
+
+    fn write_ciphertext(&mut self, buf: &[u8]) -> io::Result<usize> {
+        // The final fragment may end before the ciphertext buffered so far.
+        let offset = self.consumed + self.pos as u64;
+
+        let n = buf.len();
+        let mut buf = self.verify_header(buf)?;
+        let fragment_len = self.buf_size + A::TAG_LEN;
+        let trailer_len = self.framing.trailer_len();
+        while !buf.is_empty() {
+            // In tolerant mode, the final fragment may end somewhere within
+            // `buf`. Then we only accept the bytes up to its end.
+            if self.done {
+                return self.trailing_data(n - buf.len());
+            }
+
+            // A full buffer is only processed once more data arrives.
+            // Otherwise, it may contain the final fragment.
+            if self.pos == self.buffer.len() {
+                self.write_buffer(fragment_len, false)?;
+                if self.done {
+                    let n = self.consumed.saturating_sub(offset);
+                    return self.trailing_data(n as usize);
+                }
+                self.buffer.copy_within(fragment_len.., 0);
+                self.pos -= fragment_len;
+            }
+
+            // If the framing records the length of the stream, the final
+            // fragment is processed as soon as it is complete.
+            let final_len = self.final_len();
+            let end = final_len.map_or(self.buffer.len(), |len| len + trailer_len);
+            let k = buf.len().min(end - self.pos);
+            self.buffer[self.pos..self.pos + k].copy_from_slice(&buf[..k]);
+            self.pos += k;
+            buf = &buf[k..];
+            if let Some(len) = final_len.filter(|_| self.pos == end) {
+                self.write_buffer(len, true)?;
             }
         }
+        Ok(n)
     }
 }
 
-impl<A: Algorithm, W: Write + internal::Close> Write for DecWriter<A, W> {
+impl<A: Algorithm, W: Write + internal::Close, F: Framing> Write for DecWriter<A, W, F> {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
         if self.errored {
             return Err(io::Error::from(io::ErrorKind::Other));
         }
-
-        let n = buf.len();
-        let remaining = self.buf_size + A::TAG_LEN - self.pos;
-        if n <= remaining {
-            self.buffer[self.pos..self.pos + n].copy_from_slice(buf);
-            self.pos += n;
-            return Ok(n);
+        if self.done && !buf.is_empty() {
+            return self.trailing_data(0);
         }
 
-        self.buffer[self.pos..].copy_from_slice(&buf[..remaining]);
-        self.write_buffer(self.buf_size + A::TAG_LEN)?;
-        self.pos = 0;
-        let buf = &buf[remaining..];
-
-        let chunks = buf.chunks(self.buf_size + A::TAG_LEN);
-        chunks
-            .clone()
-            .take(chunks.len() - 1) // Since we take only n-1 elements...
-            .try_for_each(|chunk| {
-                self.buffer.copy_from_slice(chunk);
-                self.write_buffer(self.buf_size + A::TAG_LEN)
-            })?;
+        let mut n = 0;
+        if let Some(mut prefix) = self.prefix.take() {
+            let detect_len = self.framing.detect_len();
+            n = buf.len().min(detect_len - prefix.len());
+            prefix.extend_from_slice(&buf[..n]);
+            if prefix.len() < detect_len {
+                self.prefix = Some(prefix);
+                return Ok(n);
+            }
+            self.detect(&prefix)?;
+        }
+        Ok(n + self.write_ciphertext(&buf[n..])?)
+    }
 
-        let last = chunks.last().unwrap(); // ... there is always a last one.
-        self.buffer[..last.len()].copy_from_slice(last);
-        self.pos = last.len();
+    fn write_vectored(&mut self, bufs: &[IoSlice<'_>]) -> io::Result<usize> {
+        // In tolerant mode, we stop after the final fragment.
+        let mut n = 0;
+        for buf in bufs {
+            let k = self.write(buf)?;
+            n += k;
+            if k < buf.len() {
+                break;
+            }
+        }
         Ok(n)
     }
 
     #[inline]
     fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        // In tolerant mode, any data after the final fragment is ignored.
         self.write(buf).and(Ok(()))
     }
 
@@ -612,37 +2817,55 @@ impl<A: Algorithm, W: Write + internal::Close> Write for DecWriter<A, W> {
     }
 }
 
-impl<A: Algorithm, W: Write + internal::Close> internal::Close for DecWriter<A, W> {
+impl<A: Algorithm, W: Write + internal::Close, F: Framing> internal::Close for DecWriter<A, W, F> {
     fn close(&mut self) -> io::Result<()> {
         if self.errored {
             return Err(io::Error::from(io::ErrorKind::Other));
         }
         self.closed = true;
-        self.aad[0] = 0x80; // For the last fragment change the AAD
-
-        self.write_buffer(self.pos)
-            .and_then(|()| self.inner.close())
+        if let Some(prefix) = self.prefix.take() {
+            self.detect(&prefix)?;
+        }
+        if !self.done {
+            if self.header > 0 {
+                self.errored = true;
+                return Err(NotAuthentic.into());
+            }
+            let trailer_len = self.framing.trailer_len();
+            let consumed = self.consumed;
+            self.write_buffer(self.pos.saturating_sub(trailer_len), true)?;
+            if self.consumed - consumed < self.pos as u64 {
+                self.trailing_data(0)?;
+            }
+        }
+        self.inner.close()
     }
 }
 
-impl<A: Algorithm, W: Write + internal::Close> Drop for DecWriter<A, W> {
+impl<A: Algorithm, W: Write + internal::Close, F: Framing> Drop for DecWriter<A, W, F> {
     fn drop(&mut self) {
+        if let Some(allocator) = self.allocator.take() {
+            allocator.release(mem::take(&mut self.buffer));
+        }
+
         // We must not check whether the DecWriter has been closed if
         // we encountered an error during a write or flush call.
-        if !self.errored {
-            if !self.closed {
-                // We don't want to panic again if some code (between
-                // DecWriter::new(...) and DecWriter.close()) already
-                // panic'd. Otherwise we would cause a "double-panic".
-                if !panicking() {
-                    panic!("DecWriter must be closed explicitly via the close method before being dropped!")
-                }
-            }
+        //
+        // We don't want to panic again if some code (between
+        // DecWriter::new(...) and DecWriter.close()) already
+        // panic'd. Otherwise we would cause a "double-panic".
+        if !self.errored
+            && !self.closed
+            && !panicking()
+            && !hook::unclosed::<Self>()
+            && !cfg!(feature = "panic-free")
+        {
+            panic!("DecWriter must be closed explicitly via the close method before being dropped!")
         }
     }
 }
 
-mod internal {
+pub(crate) mod internal {
     pub trait Close {
         fn close(&mut self) -> std::io::Result<()>;
     }
@@ -712,8 +2935,8 @@ mod internal {
 /// By calling `closer` you get an implementation of `Close` that preserves the
 /// "no write-after-close" guarantee of `EncWriter` / `DecWriter` using runtime
 /// checks. In particular, trying to perform a write after calling close once
-/// causes a panic. Therefore, you should use `closer` with caution and only when
-/// really needed.
+/// causes a panic - or an error if the `panic-free` feature is enabled.
+/// Therefore, you should use `closer` with caution and only when really needed.
 pub trait Close {
     fn close(&mut self) -> io::Result<()>;
 }
@@ -725,28 +2948,156 @@ impl<T: Close + ?Sized> internal::Close for T {
     }
 }
 
-struct Closer<W: Write + internal::Close> {
+/// A writer that implements `Close` - usable as trait object.
+///
+/// Plugin architectures often choose the sink of an `EncWriter` or
+/// `DecWriter` at runtime. A `Box<dyn WriteClose + Send>` can be used as
+/// inner writer and closing the `EncWriter` / `DecWriter` closes the sink
+/// in the box. Any type that implements `Write` and `Close` implements
+/// `WriteClose`.
+///
+/// A `Box<dyn Write + Send>` can be used as inner writer as well. However,
+/// closing it only flushes the sink - like a `NopCloser`.
+///
+/// # Examples
+///
+/// ```
+/// use std::io::Write;
+/// use sio::{Key, Nonce, Aad, EncWriter, WriteClose, CHACHA20_POLY1305};
+///
+/// // Obviously, don't use this all-zeros key for anything real.
+/// let key: Key<CHACHA20_POLY1305> = Key::new([0; Key::<CHACHA20_POLY1305>::SIZE]);
+///
+/// let sink: Box<dyn WriteClose + Send> = Box::new(Vec::default());
+/// let mut writer = EncWriter::new(
+///     sink,
+///     &key,
+///     Nonce::new([0; Nonce::<CHACHA20_POLY1305>::SIZE]),
+///     Aad::empty(),
+/// );
+/// writer.write_all(b"Hello World").unwrap();
+/// writer.close().unwrap();
+/// ```
+pub trait WriteClose: Write + Close {}
+
+impl<T: Write + Close + ?Sized> WriteClose for T {}
+
+/// A writer that implements `Close` for an `EncWriter` or `DecWriter`.
+///
+/// It is returned by the `closer` method of `EncWriter` and `DecWriter`
+/// and can be stored in struct fields or boxed as trait object. Any write
+/// after a successful `close` fails with the error "write must not be
+/// called after close" of kind `InvalidInput` if the `panic-free` feature
+/// is enabled. Otherwise, it panics with the same message.
+pub struct Closer<W: Write + internal::Close> {
+    inner: Idempotent<W>,
+}
+
+impl<W: Write + internal::Close> Closer<W> {
+    #[inline(always)]
+    pub(crate) fn wrap(inner: W) -> Self {
+        Self {
+            inner: Idempotent::wrap(inner),
+        }
+    }
+}
+
+impl<W: Write + internal::Close> Write for Closer<W> {
+    #[inline]
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.inner.is_closed() {
+            return Err(utils::misuse("write must not be called after close"));
+        }
+        self.inner.write(buf)
+    }
+
+    #[inline(always)]
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl<W: Write + internal::Close> Close for Closer<W> {
+    #[inline]
+    fn close(&mut self) -> io::Result<()> {
+        self.inner.close()
+    }
+}
+
+/// A writer that makes closing its inner writer idempotent.
+///
+/// Framework code - e.g. a pipeline that closes all of its stages on
+/// shutdown - often cannot prove that a writer is closed exactly once.
+/// An `Idempotent` closes the inner writer on the first call of `close`
+/// only. Any later call returns the result of the first one: `Ok` if the
+/// inner writer has been closed successfully and an error otherwise.
+/// Writes after `close` are rejected with an error of kind `InvalidInput`
+/// instead of causing a panic. Once a write has failed, all further writes
+/// and `close` fail as well - except for errors of kind `WouldBlock`,
+/// which can be retried.
+///
+/// It can wrap any `Close` implementation as well as an `EncWriter` or
+/// `DecWriter`. Like any other writer, a wrapped `EncWriter` or `DecWriter`
+/// must still be closed before it gets dropped.
+///
+/// # Examples
+///
+/// ```
+/// use std::io::Write;
+/// use sio::{Close, Idempotent, NopCloser};
+///
+/// let mut writer = Idempotent::wrap(NopCloser::wrap(Vec::default()));
+/// writer.write_all(b"Hello World").unwrap();
+///
+/// writer.close().unwrap();
+/// writer.close().unwrap(); // Closing twice is fine.
+/// assert!(writer.write_all(b"!").is_err());
+/// ```
+pub struct Idempotent<W: Write + internal::Close> {
     inner: W,
     closed: bool,
     errored: bool,
 }
 
-impl<W: Write + internal::Close> Closer<W> {
+impl<W: Write + internal::Close> Idempotent<W> {
+    /// Wraps a writer.
     #[inline(always)]
     pub fn wrap(inner: W) -> Self {
         Self {
-            inner: inner,
+            inner,
             closed: false,
             errored: false,
         }
     }
+
+    /// Returns true if `close` has been called and has not failed with
+    /// an error of kind `WouldBlock`.
+    #[inline(always)]
+    pub fn is_closed(&self) -> bool {
+        self.closed
+    }
+
+    /// Returns a reference to the inner writer.
+    #[inline(always)]
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    /// Unwraps the `Idempotent` and returns the inner writer.
+    #[inline(always)]
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
 }
 
-impl<W: Write + internal::Close> Write for Closer<W> {
+impl<W: Write + internal::Close> Write for Idempotent<W> {
     #[inline]
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
         if self.closed {
-            panic!("write must not be called after close");
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "write must not be called after close",
+            ));
         }
         if self.errored {
             return Err(io::Error::from(io::ErrorKind::Other));
@@ -754,7 +3105,8 @@ impl<W: Write + internal::Close> Write for Closer<W> {
         match self.inner.write(buf) {
             Ok(n) => Ok(n),
             Err(val) => {
-                self.errored = true;
+                // An EncWriter with backpressure can be retried.
+                self.errored = val.kind() != io::ErrorKind::WouldBlock;
                 Err(val)
             }
         }
@@ -766,7 +3118,7 @@ impl<W: Write + internal::Close> Write for Closer<W> {
     }
 }
 
-impl<W: Write + internal::Close> Close for Closer<W> {
+impl<W: Write + internal::Close> Close for Idempotent<W> {
     #[inline]
     fn close(&mut self) -> io::Result<()> {
         if self.errored {
@@ -775,9 +3127,14 @@ impl<W: Write + internal::Close> Close for Closer<W> {
         if self.closed {
             Ok(())
         } else {
-            self.closed = true;
             let r = internal::Close::close(&mut self.inner);
-            self.errored = r.is_err();
+            match r {
+                Err(ref err) if err.kind() == io::ErrorKind::WouldBlock => {}
+                _ => {
+                    self.closed = true;
+                    self.errored = r.is_err();
+                }
+            }
             r
         }
     }
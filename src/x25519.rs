@@ -0,0 +1,162 @@
+// Copyright (c) 2019 Andreas Auernhammer. All rights reserved.
+// Use of this source code is governed by a license that can be
+// found in the LICENSE file.
+
+//! Public-key encryption of streams using X25519.
+//!
+//! The sender generates an ephemeral X25519 key for every stream and
+//! derives the stream key from the Diffie-Hellman shared secret with the
+//! recipient's public key using HKDF-SHA256. The ephemeral public key is
+//! written in front of the ciphertext. Hence, the recipient can derive the
+//! same stream key using its secret key - without any pre-shared symmetric
+//! key. The sender stays anonymous.
+//!
+//! Since every stream uses a new key, the nonce is always zero.
+//!
+//! # Examples
+//!
+//! ```
+//! use std::io::{Read, Write};
+//! use sio::{Aad, CHACHA20_POLY1305};
+//! use sio::x25519::{self, SecretKey};
+//!
+//! let secret_key = SecretKey::generate().unwrap();
+//! let public_key = secret_key.public_key();
+//!
+//! let mut ciphertext: Vec<u8> = Vec::default();
+//! let mut writer = x25519::seal::<CHACHA20_POLY1305, _>(
+//!     &mut ciphertext,
+//!     &public_key,
+//!     Aad::empty(),
+//! )
+//! .unwrap();
+//! writer.write_all(b"Hello World").unwrap();
+//! writer.close().unwrap();
+//!
+//! let mut reader = x25519::open::<CHACHA20_POLY1305, _>(
+//!     ciphertext.as_slice(),
+//!     &secret_key,
+//!     Aad::empty(),
+//! )
+//! .unwrap();
+//! let mut plaintext = String::default();
+//! reader.read_to_string(&mut plaintext).unwrap();
+//! assert_eq!(plaintext, "Hello World");
+//! ```
+
+use super::writer::Close;
+use super::{Aad, Algorithm, DecReader, EncWriter, Invalid, Key, Nonce};
+use ring::rand::{SecureRandom, SystemRandom};
+use ring::{digest, hkdf, hmac};
+use std::io::{self, Read, Write};
+
+/// The size of the header - the ephemeral public key - in bytes.
+pub const HEADER_LEN: usize = 32;
+
+/// The HKDF info used to derive the stream key.
+const INFO: &[u8] = b"sio x25519 stream key";
+
+/// An X25519 secret key of the recipient of a stream.
+pub struct SecretKey(x25519_dalek::StaticSecret);
+
+impl SecretKey {
+    /// Creates the secret key from its 32 byte encoding.
+    pub fn from_bytes(bytes: [u8; 32]) -> Self {
+        SecretKey(x25519_dalek::StaticSecret::from(bytes))
+    }
+
+    /// Generates a new random secret key.
+    pub fn generate() -> Result<Self, Invalid> {
+        let mut bytes = [0; 32];
+        SystemRandom::new()
+            .fill(&mut bytes)
+            .map_err(|_| Invalid::Key)?;
+        Ok(Self::from_bytes(bytes))
+    }
+
+    /// Returns the public key of the secret key.
+    pub fn public_key(&self) -> PublicKey {
+        PublicKey(x25519_dalek::PublicKey::from(&self.0).to_bytes())
+    }
+
+    /// Computes the X25519 shared secret with the owner of the `public` key.
+    pub(crate) fn diffie_hellman(&self, public: &PublicKey) -> x25519_dalek::SharedSecret {
+        self.0
+            .diffie_hellman(&x25519_dalek::PublicKey::from(public.0))
+    }
+}
+
+/// Derives the key of the stream from the X25519 `shared` secret, the
+/// `ephemeral` public key of the sender and the public key of the
+/// `recipient`.
+fn derive_key<A: Algorithm>(
+    shared: x25519_dalek::SharedSecret,
+    ephemeral: &PublicKey,
+    recipient: &PublicKey,
+) -> Result<Key<A>, Invalid> {
+    // A low-order public key results in an all-zero shared secret.
+    if !shared.was_contributory() {
+        return Err(Invalid::Key);
+    }
+    let mut salt = [0; 2 * 32];
+    salt[..32].copy_from_slice(&ephemeral.0);
+    salt[32..].copy_from_slice(&recipient.0);
+
+    let mut key = [0; 32];
+    let salt = hmac::SigningKey::new(&digest::SHA256, &salt);
+    hkdf::extract_and_expand(&salt, shared.as_bytes(), INFO, &mut key);
+    Ok(Key::new(key))
+}
+
+/// An X25519 public key of the recipient of a stream.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PublicKey([u8; 32]);
+
+impl PublicKey {
+    /// Creates the public key from its 32 byte encoding.
+    pub fn new(bytes: [u8; 32]) -> Self {
+        PublicKey(bytes)
+    }
+}
+
+impl AsRef<[u8; 32]> for PublicKey {
+    fn as_ref(&self) -> &[u8; 32] {
+        &self.0
+    }
+}
+
+/// Returns an `EncWriter` that encrypts a stream for the `recipient`.
+///
+/// It writes the header - a new ephemeral public key - to the `inner`
+/// writer immediately.
+pub fn seal<A: Algorithm, W: Write + Close>(
+    mut inner: W,
+    recipient: &PublicKey,
+    aad: Aad<A>,
+) -> io::Result<EncWriter<A, W>> {
+    let ephemeral = SecretKey::generate()?;
+    let header = ephemeral.public_key();
+    let shared = ephemeral.diffie_hellman(recipient);
+    let key = derive_key(shared, &header, recipient)?;
+
+    inner.write_all(&header.0)?;
+    Ok(EncWriter::new(inner, &key, Nonce::zero(), aad))
+}
+
+/// Returns a `DecReader` that decrypts a stream sent to the owner
+/// of the `secret_key`.
+///
+/// It reads the header - the ephemeral public key of the sender - from
+/// the `inner` reader immediately.
+pub fn open<A: Algorithm, R: Read>(
+    mut inner: R,
+    secret_key: &SecretKey,
+    aad: Aad<A>,
+) -> io::Result<DecReader<A, R>> {
+    let mut header = [0; HEADER_LEN];
+    inner.read_exact(&mut header)?;
+    let header = PublicKey(header);
+    let shared = secret_key.diffie_hellman(&header);
+    let key = derive_key(shared, &header, &secret_key.public_key())?;
+    Ok(DecReader::new(inner, &key, Nonce::zero(), aad))
+}
@@ -0,0 +1,71 @@
+// Copyright (c) 2019 Andreas Auernhammer. All rights reserved.
+// Use of this source code is governed by a license that can be
+// found in the LICENSE file.
+
+use sio::*;
+use std::io::{self, Read, Write};
+
+#[cfg(feature = "aesgcm")]
+#[allow(clippy::upper_case_acronyms)]
+type AEAD = AES_256_GCM;
+
+#[cfg(not(feature = "aesgcm"))]
+#[allow(clippy::upper_case_acronyms)]
+type AEAD = CHACHA20_POLY1305;
+
+fn digest(data: &[u8]) -> AadDigest {
+    let mut hasher = AadHasher::new();
+    hasher.read_from(data).unwrap();
+    hasher.finish()
+}
+
+#[test]
+fn incremental() {
+    let data: Vec<u8> = (0..10_000).map(|i| i as u8).collect();
+
+    let mut hasher = AadHasher::new();
+    data.chunks(7).for_each(|chunk| hasher.update(chunk));
+    assert_eq!(hasher.finish(), digest(&data));
+
+    assert_ne!(digest(&data), digest(&data[1..]));
+    assert_ne!(
+        digest(&data).as_ref(),
+        ring::digest::digest(&ring::digest::SHA256, &data).as_ref()
+    );
+}
+
+#[test]
+fn aad() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let manifest = vec![1; 1 << 20];
+
+    let mut ciphertext = Vec::default();
+    let mut writer = EncWriter::new(
+        &mut ciphertext,
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        digest(&manifest).aad(),
+    );
+    writer.write_all(b"Hello World")?;
+    writer.close()?;
+
+    let mut plaintext = Vec::default();
+    let mut reader = DecReader::new(
+        ciphertext.as_slice(),
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        digest(&manifest).aad(),
+    );
+    reader.read_to_end(&mut plaintext)?;
+    assert_eq!(plaintext, b"Hello World");
+
+    let mut reader = DecReader::new(
+        ciphertext.as_slice(),
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        digest(&manifest[1..]).aad(),
+    );
+    let err = reader.read_to_end(&mut Vec::default()).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    Ok(())
+}
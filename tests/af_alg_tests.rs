@@ -0,0 +1,111 @@
+// Copyright (c) 2019 Andreas Auernhammer. All rights reserved.
+// Use of this source code is governed by a license that can be
+// found in the LICENSE file.
+
+#![cfg(all(feature = "af-alg", target_os = "linux"))]
+
+use sio::*;
+use std::env;
+use std::io::{self, Write};
+
+// The tests only run if the kernel provides AF_ALG - e.g. it
+// is not available in some containers and sandboxes. Skipped tests
+// report it on stderr. If SIO_REQUIRE_AF_ALG is set, they fail instead.
+fn available() -> bool {
+    if AF_ALG_AES_256_GCM::is_available() {
+        return true;
+    }
+    assert!(
+        env::var_os("SIO_REQUIRE_AF_ALG").is_none(),
+        "the kernel does not provide AES-256-GCM via AF_ALG"
+    );
+    eprintln!("skipped: the kernel does not provide AES-256-GCM via AF_ALG");
+    false
+}
+
+#[test]
+fn unavailable() {
+    if AF_ALG_AES_256_GCM::is_available() {
+        return;
+    }
+    let key: Key<AF_ALG_AES_256_GCM> = Key::new([0; Key::<AF_ALG_AES_256_GCM>::SIZE]);
+    let result = EncWriter::with_buffer_size(
+        Vec::default(),
+        &key,
+        Nonce::new([0; Nonce::<AF_ALG_AES_256_GCM>::SIZE]),
+        Aad::empty(),
+        BUF_SIZE,
+    );
+    assert!(matches!(result, Err(Invalid::Key)));
+}
+
+#[test]
+fn roundtrip() -> io::Result<()> {
+    if !available() {
+        return Ok(());
+    }
+    let key: Key<AF_ALG_AES_256_GCM> = Key::new([1; Key::<AF_ALG_AES_256_GCM>::SIZE]);
+    for size in &[0, 1, 64, 65, 64 * 16 + 1] {
+        let data: Vec<u8> = (0..*size).map(|i| i as u8).collect();
+        let mut ciphertext = Vec::default();
+        let mut writer = EncWriter::with_buffer_size(
+            &mut ciphertext,
+            &key,
+            Nonce::new([0; Nonce::<AF_ALG_AES_256_GCM>::SIZE]),
+            Aad::from(b"af_alg".as_ref()),
+            64,
+        )?;
+        writer.write_all(&data)?;
+        writer.close()?;
+
+        let mut plaintext = Vec::default();
+        decrypt_parallel(
+            &mut plaintext,
+            &key,
+            Nonce::new([0; Nonce::<AF_ALG_AES_256_GCM>::SIZE]),
+            Aad::from(b"af_alg".as_ref()),
+            &ciphertext,
+            64,
+            3,
+        )?;
+        assert_eq!(plaintext, data);
+
+        if let Some(b) = ciphertext.last_mut() {
+            *b ^= 1;
+        }
+        let err = decrypt_parallel(
+            io::sink(),
+            &key,
+            Nonce::new([0; Nonce::<AF_ALG_AES_256_GCM>::SIZE]),
+            Aad::from(b"af_alg".as_ref()),
+            &ciphertext,
+            64,
+            3,
+        )
+        .unwrap_err();
+        assert!(err.get_ref().is_some_and(|e| e.is::<NotAuthentic>()));
+    }
+    Ok(())
+}
+
+#[cfg(feature = "aesgcm")]
+#[test]
+fn compatible() {
+    if !available() {
+        return;
+    }
+    let data = vec![7; 5 * BUF_SIZE + 3];
+    let kernel = encrypt_vec(
+        &Key::<AF_ALG_AES_256_GCM>::new([1; 32]),
+        Nonce::new([2; Nonce::<AF_ALG_AES_256_GCM>::SIZE]),
+        Aad::from(b"af_alg".as_ref()),
+        &data,
+    );
+    let ring = encrypt_vec(
+        &Key::<AES_256_GCM>::new([1; 32]),
+        Nonce::new([2; Nonce::<AES_256_GCM>::SIZE]),
+        Aad::from(b"af_alg".as_ref()),
+        &data,
+    );
+    assert_eq!(kernel, ring);
+}
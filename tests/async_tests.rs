@@ -0,0 +1,680 @@
+// Copyright (c) 2019 Andreas Auernhammer. All rights reserved.
+// Use of this source code is governed by a license that can be
+// found in the LICENSE file.
+
+#![cfg(feature = "futures-io")]
+
+use futures_lite::future::{block_on, poll_once};
+use futures_lite::io::{AsyncRead, AsyncReadExt, AsyncSeekExt, AsyncWrite, AsyncWriteExt, Cursor};
+use sio::*;
+use std::io::{self, SeekFrom, Write};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+#[cfg(feature = "aesgcm")]
+#[allow(clippy::upper_case_acronyms)]
+type AEAD = AES_256_GCM;
+
+#[cfg(not(feature = "aesgcm"))]
+#[allow(clippy::upper_case_acronyms)]
+type AEAD = CHACHA20_POLY1305;
+
+// An async writer that accepts only a few bytes at once and
+// returns `Poll::Pending` every other time.
+#[derive(Default)]
+struct SlowWriter {
+    data: Vec<u8>,
+    ready: bool,
+    closed: bool,
+}
+
+impl AsyncWrite for SlowWriter {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        self.ready = !self.ready;
+        if !self.ready {
+            cx.waker().wake_by_ref();
+            return Poll::Pending;
+        }
+        let n = buf.len().min(7);
+        self.data.extend_from_slice(&buf[..n]);
+        Poll::Ready(Ok(n))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.closed = true;
+        Poll::Ready(Ok(()))
+    }
+}
+
+// An async reader that returns only a few bytes at once and
+// returns `Poll::Pending` every other time.
+struct SlowReader<'a> {
+    data: &'a [u8],
+    ready: bool,
+}
+
+impl<'a> SlowReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        SlowReader { data, ready: false }
+    }
+}
+
+impl AsyncRead for SlowReader<'_> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        self.ready = !self.ready;
+        if !self.ready {
+            cx.waker().wake_by_ref();
+            return Poll::Pending;
+        }
+        let n = buf.len().min(self.data.len()).min(7);
+        buf[..n].copy_from_slice(&self.data[..n]);
+        self.data = &self.data[n..];
+        Poll::Ready(Ok(n))
+    }
+}
+
+#[test]
+fn write() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let plaintext = vec![1; 3 * 64 + 5];
+
+    let mut inner = SlowWriter::default();
+    block_on(async {
+        let mut writer = AsyncEncWriter::with_buffer_size(
+            &mut inner,
+            &key,
+            Nonce::new([0; Nonce::<AEAD>::SIZE]),
+            Aad::from("Some authenticated but not encrypted data".as_bytes()),
+            64,
+        )
+        .unwrap();
+        writer.write_all(&plaintext).await?;
+        writer.close().await
+    })?;
+    assert!(inner.closed);
+    let mut expected = Vec::default();
+    let mut writer = EncWriter::with_buffer_size(
+        &mut expected,
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::from("Some authenticated but not encrypted data".as_bytes()),
+        64,
+    )?;
+    writer.write_all(&plaintext).and_then(|()| writer.close())?;
+    assert_eq!(inner.data, expected);
+    Ok(())
+}
+
+#[test]
+fn write_empty() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+
+    let mut inner = SlowWriter::default();
+    block_on(async {
+        let mut writer = AsyncEncWriter::new(
+            &mut inner,
+            &key,
+            Nonce::new([0; Nonce::<AEAD>::SIZE]),
+            Aad::from("Some authenticated but not encrypted data".as_bytes()),
+        );
+        writer.close().await
+    })?;
+    let mut expected = Vec::default();
+    let writer = EncWriter::new(
+        &mut expected,
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::from("Some authenticated but not encrypted data".as_bytes()),
+    );
+    writer.close()?;
+    assert_eq!(inner.data, expected);
+    Ok(())
+}
+
+#[test]
+fn write_cancelled() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let plaintext = vec![1; 3 * 64 + 5];
+
+    let mut inner = SlowWriter::default();
+    let mut cancelled = 0;
+    block_on(async {
+        let mut writer = AsyncEncWriter::with_buffer_size(
+            &mut inner,
+            &key,
+            Nonce::new([0; Nonce::<AEAD>::SIZE]),
+            Aad::from("Some authenticated but not encrypted data".as_bytes()),
+            64,
+        )
+        .unwrap();
+
+        // Poll every future only once and drop it if it is pending.
+        let mut written = 0;
+        while written < plaintext.len() {
+            match poll_once(writer.write(&plaintext[written..])).await {
+                Some(n) => written += n?,
+                None => cancelled += 1,
+            }
+        }
+        loop {
+            match poll_once(writer.close()).await {
+                Some(result) => return result,
+                None => cancelled += 1,
+            }
+        }
+    })?;
+    assert!(cancelled > 0);
+    assert!(inner.closed);
+    let mut expected = Vec::default();
+    let mut writer = EncWriter::with_buffer_size(
+        &mut expected,
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::from("Some authenticated but not encrypted data".as_bytes()),
+        64,
+    )?;
+    writer.write_all(&plaintext).and_then(|()| writer.close())?;
+    assert_eq!(inner.data, expected);
+    Ok(())
+}
+
+#[test]
+fn write_parallel() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    for size in &[0, 1, 64, 3 * 64, 17 * 64 + 5] {
+        let plaintext: Vec<u8> = (0..*size).map(|i| i as u8).collect();
+
+        let mut inner = SlowWriter::default();
+        block_on(async {
+            let mut writer = AsyncParallelEncWriter::with_buffer_size(
+                &mut inner,
+                &key,
+                Nonce::new([0; Nonce::<AEAD>::SIZE]),
+                Aad::from("Some authenticated but not encrypted data".as_bytes()),
+                64,
+                4,
+            )
+            .unwrap()
+            .window(3);
+            writer.write_all(&plaintext).await?;
+            writer.flush().await?;
+            writer.close().await
+        })?;
+        assert!(inner.closed);
+
+        let mut ciphertext = Vec::default();
+        let mut writer = EncWriter::with_buffer_size(
+            &mut ciphertext,
+            &key,
+            Nonce::new([0; Nonce::<AEAD>::SIZE]),
+            Aad::from("Some authenticated but not encrypted data".as_bytes()),
+            64,
+        )?;
+        writer.write_all(&plaintext)?;
+        writer.close()?;
+        assert_eq!(inner.data, ciphertext);
+    }
+    Ok(())
+}
+
+#[test]
+fn write_parallel_cancelled() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let plaintext = vec![1; 9 * 64 + 5];
+
+    let mut inner = SlowWriter::default();
+    let mut cancelled = 0;
+    block_on(async {
+        let mut writer = AsyncParallelEncWriter::with_buffer_size(
+            &mut inner,
+            &key,
+            Nonce::new([0; Nonce::<AEAD>::SIZE]),
+            Aad::from("Some authenticated but not encrypted data".as_bytes()),
+            64,
+            2,
+        )
+        .unwrap();
+
+        // Poll every future only once and drop it if it is pending.
+        let mut written = 0;
+        while written < plaintext.len() {
+            match poll_once(writer.write(&plaintext[written..])).await {
+                Some(n) => written += n?,
+                None => cancelled += 1,
+            }
+        }
+        loop {
+            match poll_once(writer.close()).await {
+                Some(result) => return result,
+                None => cancelled += 1,
+            }
+        }
+    })?;
+    assert!(cancelled > 0);
+    assert!(inner.closed);
+    let mut expected = Vec::default();
+    let mut writer = EncWriter::with_buffer_size(
+        &mut expected,
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::from("Some authenticated but not encrypted data".as_bytes()),
+        64,
+    )?;
+    writer.write_all(&plaintext).and_then(|()| writer.close())?;
+    assert_eq!(inner.data, expected);
+
+    let mut inner = SlowWriter::default();
+    let err = block_on(async {
+        let mut writer = AsyncParallelEncWriter::new(
+            &mut inner,
+            &key,
+            Nonce::new([0; Nonce::<AEAD>::SIZE]),
+            Aad::empty(),
+            2,
+        );
+        writer.close().await?;
+        writer.write_all(b"Hello World").await
+    })
+    .unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::Other);
+    Ok(())
+}
+
+#[test]
+fn decrypt() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let plaintext = vec![1; 3 * 64 + 5];
+    let mut ciphertext = Vec::default();
+    let mut writer = EncWriter::with_buffer_size(
+        &mut ciphertext,
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::from("Some authenticated but not encrypted data".as_bytes()),
+        64,
+    )?;
+    writer.write_all(&plaintext).and_then(|()| writer.close())?;
+
+    let mut inner = SlowWriter::default();
+    block_on(async {
+        let mut writer = AsyncDecWriter::with_buffer_size(
+            &mut inner,
+            &key,
+            Nonce::new([0; Nonce::<AEAD>::SIZE]),
+            Aad::from("Some authenticated but not encrypted data".as_bytes()),
+            64,
+        )
+        .unwrap();
+        writer.write_all(&ciphertext).await?;
+        writer.close().await
+    })?;
+    assert!(inner.closed);
+    assert_eq!(inner.data, plaintext);
+    Ok(())
+}
+
+#[test]
+fn decrypt_cancelled() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let plaintext = vec![1; 3 * 64 + 5];
+    let mut ciphertext = Vec::default();
+    let mut writer = EncWriter::with_buffer_size(
+        &mut ciphertext,
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::from("Some authenticated but not encrypted data".as_bytes()),
+        64,
+    )?;
+    writer.write_all(&plaintext).and_then(|()| writer.close())?;
+
+    let mut inner = SlowWriter::default();
+    let mut cancelled = 0;
+    block_on(async {
+        let mut writer = AsyncDecWriter::with_buffer_size(
+            &mut inner,
+            &key,
+            Nonce::new([0; Nonce::<AEAD>::SIZE]),
+            Aad::from("Some authenticated but not encrypted data".as_bytes()),
+            64,
+        )
+        .unwrap();
+
+        // Poll every future only once and drop it if it is pending.
+        let mut written = 0;
+        while written < ciphertext.len() {
+            match poll_once(writer.write(&ciphertext[written..])).await {
+                Some(n) => written += n?,
+                None => cancelled += 1,
+            }
+        }
+        loop {
+            match poll_once(writer.close()).await {
+                Some(result) => return result,
+                None => cancelled += 1,
+            }
+        }
+    })?;
+    assert!(cancelled > 0);
+    assert!(inner.closed);
+    assert_eq!(inner.data, plaintext);
+    Ok(())
+}
+
+#[test]
+fn decrypt_modified() {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let mut ciphertext = Vec::default();
+    let mut writer = EncWriter::with_buffer_size(
+        &mut ciphertext,
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::from("Some authenticated but not encrypted data".as_bytes()),
+        64,
+    )
+    .unwrap();
+    writer
+        .write_all(&[1; 3 * 64 + 5])
+        .and_then(|()| writer.close())
+        .unwrap();
+    ciphertext[70] ^= 1;
+
+    let result = block_on(async {
+        let mut writer = AsyncDecWriter::with_buffer_size(
+            SlowWriter::default(),
+            &key,
+            Nonce::new([0; Nonce::<AEAD>::SIZE]),
+            Aad::from("Some authenticated but not encrypted data".as_bytes()),
+            64,
+        )
+        .unwrap();
+        writer.write_all(&ciphertext).await?;
+        writer.close().await
+    });
+    assert_eq!(result.unwrap_err().kind(), io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn decrypt_truncated() {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let mut ciphertext = Vec::default();
+    let mut writer = EncWriter::with_buffer_size(
+        &mut ciphertext,
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::from("Some authenticated but not encrypted data".as_bytes()),
+        64,
+    )
+    .unwrap();
+    writer
+        .write_all(&[1; 3 * 64 + 5])
+        .and_then(|()| writer.close())
+        .unwrap();
+
+    let result = block_on(async {
+        let mut writer = AsyncDecWriter::with_buffer_size(
+            SlowWriter::default(),
+            &key,
+            Nonce::new([0; Nonce::<AEAD>::SIZE]),
+            Aad::from("Some authenticated but not encrypted data".as_bytes()),
+            64,
+        )
+        .unwrap();
+        writer
+            .write_all(&ciphertext[..2 * (64 + AEAD::TAG_LEN)])
+            .await?;
+        writer.close().await
+    });
+    assert_eq!(result.unwrap_err().kind(), io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn read() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    for size in [0, 1, 64, 3 * 64, 3 * 64 + 5] {
+        let plaintext = vec![1; size];
+        let mut ciphertext = Vec::default();
+        let mut writer = EncWriter::with_buffer_size(
+            &mut ciphertext,
+            &key,
+            Nonce::new([0; Nonce::<AEAD>::SIZE]),
+            Aad::from("Some authenticated but not encrypted data".as_bytes()),
+            64,
+        )?;
+        writer.write_all(&plaintext).and_then(|()| writer.close())?;
+
+        let mut decrypted = Vec::default();
+        block_on(async {
+            let mut reader = AsyncDecReader::with_buffer_size(
+                SlowReader::new(&ciphertext),
+                &key,
+                Nonce::new([0; Nonce::<AEAD>::SIZE]),
+                Aad::from("Some authenticated but not encrypted data".as_bytes()),
+                64,
+            )
+            .unwrap();
+            reader.read_to_end(&mut decrypted).await
+        })?;
+        assert_eq!(decrypted, plaintext);
+    }
+    Ok(())
+}
+
+#[test]
+fn read_empty() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let mut ciphertext = Vec::default();
+    let writer = EncWriter::new(
+        &mut ciphertext,
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::from("Some authenticated but not encrypted data".as_bytes()),
+    );
+    writer.close()?;
+
+    block_on(async {
+        let mut reader = AsyncDecReader::new(
+            SlowReader::new(&ciphertext),
+            &key,
+            Nonce::new([0; Nonce::<AEAD>::SIZE]),
+            Aad::from("Some authenticated but not encrypted data".as_bytes()),
+        );
+        let mut buf = [0; 16];
+        assert_eq!(reader.read(&mut buf).await?, 0);
+        assert_eq!(reader.read(&mut buf).await?, 0);
+
+        let mut reader = AsyncDecReader::new(
+            SlowReader::new(&[]),
+            &key,
+            Nonce::new([0; Nonce::<AEAD>::SIZE]),
+            Aad::from("Some authenticated but not encrypted data".as_bytes()),
+        );
+        let err = reader.read(&mut buf).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        Ok(())
+    })
+}
+
+#[test]
+fn read_modified() {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let mut ciphertext = Vec::default();
+    let mut writer = EncWriter::with_buffer_size(
+        &mut ciphertext,
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::from("Some authenticated but not encrypted data".as_bytes()),
+        64,
+    )
+    .unwrap();
+    writer
+        .write_all(&[1; 3 * 64 + 5])
+        .and_then(|()| writer.close())
+        .unwrap();
+    ciphertext[70] ^= 1;
+
+    let result = block_on(async {
+        let mut reader = AsyncDecReader::with_buffer_size(
+            SlowReader::new(&ciphertext),
+            &key,
+            Nonce::new([0; Nonce::<AEAD>::SIZE]),
+            Aad::from("Some authenticated but not encrypted data".as_bytes()),
+            64,
+        )
+        .unwrap();
+        reader.read_to_end(&mut Vec::default()).await
+    });
+    assert_eq!(result.unwrap_err().kind(), io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn read_truncated() {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let mut ciphertext = Vec::default();
+    let mut writer = EncWriter::with_buffer_size(
+        &mut ciphertext,
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::from("Some authenticated but not encrypted data".as_bytes()),
+        64,
+    )
+    .unwrap();
+    writer
+        .write_all(&[1; 3 * 64 + 5])
+        .and_then(|()| writer.close())
+        .unwrap();
+
+    let result = block_on(async {
+        let mut reader = AsyncDecReader::with_buffer_size(
+            SlowReader::new(&ciphertext[..2 * (64 + AEAD::TAG_LEN)]),
+            &key,
+            Nonce::new([0; Nonce::<AEAD>::SIZE]),
+            Aad::from("Some authenticated but not encrypted data".as_bytes()),
+            64,
+        )
+        .unwrap();
+        reader.read_to_end(&mut Vec::default()).await
+    });
+    assert_eq!(result.unwrap_err().kind(), io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn seek() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let plaintext: Vec<u8> = (0..3 * 64 + 5).map(|i| i as u8).collect();
+    let mut ciphertext = Vec::default();
+    let mut writer = EncWriter::with_buffer_size(
+        &mut ciphertext,
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::from("Some authenticated but not encrypted data".as_bytes()),
+        64,
+    )?;
+    writer.write_all(&plaintext).and_then(|()| writer.close())?;
+
+    block_on(async {
+        let mut reader = AsyncSeekDecReader::with_buffer_size(
+            Cursor::new(&ciphertext),
+            &key,
+            Nonce::new([0; Nonce::<AEAD>::SIZE]),
+            Aad::from("Some authenticated but not encrypted data".as_bytes()),
+            64,
+        )
+        .unwrap();
+        for (start, end) in [(0, 10), (60, 70), (190, 197), (128, 128), (5, 197), (3, 5)] {
+            reader.seek(SeekFrom::Start(start as u64)).await?;
+            let mut decrypted = vec![0; end - start];
+            reader.read_exact(&mut decrypted).await?;
+            assert_eq!(decrypted, &plaintext[start..end]);
+        }
+
+        assert_eq!(reader.seek(SeekFrom::End(-2)).await?, 195);
+        let mut decrypted = Vec::default();
+        reader.read_to_end(&mut decrypted).await?;
+        assert_eq!(decrypted, &plaintext[195..]);
+
+        assert_eq!(reader.seek(SeekFrom::Current(-100)).await?, 97);
+        assert!(reader.seek(SeekFrom::Current(-100)).await.is_err());
+        Ok(())
+    })
+}
+
+#[test]
+fn seek_modified() {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let mut ciphertext = Vec::default();
+    let mut writer = EncWriter::with_buffer_size(
+        &mut ciphertext,
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::from("Some authenticated but not encrypted data".as_bytes()),
+        64,
+    )
+    .unwrap();
+    writer
+        .write_all(&[1; 3 * 64 + 5])
+        .and_then(|()| writer.close())
+        .unwrap();
+    ciphertext[70] ^= 1;
+
+    block_on(async {
+        let mut reader = AsyncSeekDecReader::with_buffer_size(
+            Cursor::new(&ciphertext),
+            &key,
+            Nonce::new([0; Nonce::<AEAD>::SIZE]),
+            Aad::from("Some authenticated but not encrypted data".as_bytes()),
+            64,
+        )
+        .unwrap();
+
+        // Only the fragments that are read are verified.
+        reader.seek(SeekFrom::Start(128)).await.unwrap();
+        reader.read_to_end(&mut Vec::default()).await.unwrap();
+
+        reader.seek(SeekFrom::Start(60)).await.unwrap();
+        let result = reader.read_to_end(&mut Vec::default()).await;
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::InvalidData);
+    });
+}
+
+#[test]
+fn seek_truncated() {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let mut ciphertext = Vec::default();
+    let mut writer = EncWriter::with_buffer_size(
+        &mut ciphertext,
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::from("Some authenticated but not encrypted data".as_bytes()),
+        64,
+    )
+    .unwrap();
+    writer
+        .write_all(&[1; 3 * 64 + 5])
+        .and_then(|()| writer.close())
+        .unwrap();
+
+    let result = block_on(async {
+        let mut reader = AsyncSeekDecReader::with_buffer_size(
+            Cursor::new(&ciphertext[..2 * (64 + AEAD::TAG_LEN)]),
+            &key,
+            Nonce::new([0; Nonce::<AEAD>::SIZE]),
+            Aad::from("Some authenticated but not encrypted data".as_bytes()),
+            64,
+        )
+        .unwrap();
+        reader.seek(SeekFrom::Start(64)).await.unwrap();
+        reader.read_to_end(&mut Vec::default()).await
+    });
+    assert_eq!(result.unwrap_err().kind(), io::ErrorKind::InvalidData);
+}
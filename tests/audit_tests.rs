@@ -0,0 +1,68 @@
+// Copyright (c) 2019 Andreas Auernhammer. All rights reserved.
+// Use of this source code is governed by a license that can be
+// found in the LICENSE file.
+
+use sio::audit::{self, Event};
+use sio::*;
+use std::io::{self, Read, Write};
+use std::sync::Mutex;
+
+#[cfg(feature = "aesgcm")]
+#[allow(clippy::upper_case_acronyms)]
+type AEAD = AES_256_GCM;
+
+#[cfg(not(feature = "aesgcm"))]
+#[allow(clippy::upper_case_acronyms)]
+type AEAD = CHACHA20_POLY1305;
+
+static EVENTS: Mutex<Vec<Event>> = Mutex::new(Vec::new());
+
+#[test]
+fn report_not_authentic() -> io::Result<()> {
+    audit::set_hook(Box::new(|event| EVENTS.lock().unwrap().push(event.clone())));
+
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let aad = b"audit test";
+    let fragment_len = 64 + AEAD::TAG_LEN;
+
+    let mut ciphertext = Vec::default();
+    let mut writer = EncWriter::with_buffer_size(
+        &mut ciphertext,
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::from(aad.as_ref()),
+        64,
+    )?;
+    writer.write_all(&[0; 1000]).and_then(|()| writer.close())?;
+    ciphertext[2 * fragment_len + 1] ^= 1;
+
+    let mut writer = DecWriter::with_buffer_size(
+        io::sink(),
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::from(aad.as_ref()),
+        64,
+    )?;
+    assert!(writer.write_all(&ciphertext).is_err());
+
+    let mut reader = DecReader::with_buffer_size(
+        ciphertext.as_slice(),
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::from(aad.as_ref()),
+        64,
+    )?;
+    assert!(reader.read_to_end(&mut Vec::default()).is_err());
+
+    let _ = audit::take_hook();
+    let events = EVENTS.lock().unwrap();
+    assert_eq!(events.len(), 2);
+    for event in events.iter() {
+        assert_eq!(event.sequence(), 2);
+        assert_eq!(event.offset(), 2 * fragment_len as u64);
+        assert_eq!(event.len(), fragment_len);
+        assert_ne!(event.aad_fingerprint(), &[0; 16]);
+        assert_eq!(event.aad_fingerprint(), events[0].aad_fingerprint());
+    }
+    Ok(())
+}
@@ -0,0 +1,178 @@
+// Copyright (c) 2019 Andreas Auernhammer. All rights reserved.
+// Use of this source code is governed by a license that can be
+// found in the LICENSE file.
+
+use sio::cdc::{self, CdcDecWriter, CdcEncWriter, Chunker};
+use sio::*;
+use std::collections::HashSet;
+use std::io::{self, Write};
+
+#[cfg(feature = "aesgcm")]
+#[allow(clippy::upper_case_acronyms)]
+type AEAD = AES_256_GCM;
+
+#[cfg(not(feature = "aesgcm"))]
+#[allow(clippy::upper_case_acronyms)]
+type AEAD = CHACHA20_POLY1305;
+
+fn random(len: usize, seed: u64) -> Vec<u8> {
+    let mut state = seed;
+    (0..len)
+        .map(|_| {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (state >> 56) as u8
+        })
+        .collect()
+}
+
+#[test]
+fn roundtrip() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    for size in &[0, 1, 255, 4096, 100_000] {
+        let data = random(*size, 1);
+
+        // The records do not depend on how the data is split into writes.
+        let mut ciphertexts = Vec::default();
+        for write_size in &[1000, 1, 17, 4096, 1 << 20] {
+            let mut ciphertext = Vec::default();
+            let mut writer = CdcEncWriter::with_chunker(
+                &mut ciphertext,
+                &key,
+                Aad::from(b"cdc".as_ref()),
+                Chunker::new(256, 1024, 4096).unwrap(),
+            );
+            for chunk in data.chunks(*write_size) {
+                writer.write_all(chunk)?;
+            }
+            writer.close()?;
+            ciphertexts.push(ciphertext);
+        }
+        assert!(ciphertexts.iter().all(|c| *c == ciphertexts[0]));
+
+        let mut plaintext = Vec::default();
+        let mut writer = CdcDecWriter::new(&mut plaintext, &key, Aad::from(b"cdc".as_ref()));
+        writer
+            .write_all(&ciphertexts[0])
+            .and_then(|()| writer.close())?;
+        assert_eq!(plaintext, data);
+    }
+    Ok(())
+}
+
+#[test]
+fn chunk_sizes() {
+    let chunker = Chunker::new(256, 1024, 4096).unwrap();
+    let data = random(1 << 20, 2);
+    let mut rest = data.as_slice();
+    let mut chunks = 0;
+    while !rest.is_empty() {
+        let n = chunker.cut(rest);
+        assert!(n <= chunker.max());
+        assert!(n >= chunker.min() || n == rest.len());
+        rest = &rest[n..];
+        chunks += 1;
+    }
+    let avg = data.len() / chunks;
+    assert!(avg > chunker.min() && avg < chunker.max());
+}
+
+#[test]
+fn dedup() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let data = random(200_000, 3);
+    let mut modified = data.clone();
+    modified.insert(100_000, 42);
+
+    let mut records = Vec::default();
+    for data in &[data, modified] {
+        let mut ciphertext = Vec::default();
+        let mut writer = CdcEncWriter::with_chunker(
+            &mut ciphertext,
+            &key,
+            Aad::from(b"cdc".as_ref()),
+            Chunker::new(256, 1024, 4096).unwrap(),
+        );
+        for chunk in data.chunks(4096) {
+            writer.write_all(chunk)?;
+        }
+        writer.close()?;
+        records.push(
+            cdc::records::<AEAD>(&ciphertext)
+                .map(|r| r.map(|r| r.to_vec()))
+                .collect::<io::Result<Vec<_>>>()?,
+        );
+    }
+    let (original, modified) = (&records[0], &records[1]);
+
+    let known: HashSet<_> = original.iter().collect();
+    let changed = modified.iter().filter(|r| !known.contains(r)).count();
+    // The changed chunk, maybe its neighbor and the final record.
+    assert!(
+        changed <= 3,
+        "{} of {} records changed",
+        changed,
+        modified.len()
+    );
+    Ok(())
+}
+
+#[test]
+fn modified() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let mut ciphertext = Vec::default();
+    let mut writer = CdcEncWriter::with_chunker(
+        &mut ciphertext,
+        &key,
+        Aad::from(b"cdc".as_ref()),
+        Chunker::new(256, 1024, 4096).unwrap(),
+    );
+    for chunk in random(20_000, 4).chunks(4096) {
+        writer.write_all(chunk)?;
+    }
+    writer.close()?;
+
+    let records: Vec<&[u8]> = cdc::records::<AEAD>(&ciphertext).collect::<io::Result<_>>()?;
+    assert!(records.len() > 3);
+
+    let mut flipped = ciphertext.clone();
+    flipped[cdc::HEADER_LEN + 1] ^= 1;
+
+    let mut swapped = Vec::default();
+    swapped.extend_from_slice(records[1]);
+    swapped.extend_from_slice(records[0]);
+    for record in &records[2..] {
+        swapped.extend_from_slice(record);
+    }
+
+    let removed: Vec<u8> = records[1..].concat();
+    let truncated = records[..records.len() - 1].concat();
+    for stream in &[flipped, swapped, removed, truncated] {
+        let mut writer = CdcDecWriter::new(io::sink(), &key, Aad::from(b"cdc".as_ref()));
+        let err = writer
+            .write_all(stream)
+            .and_then(|()| writer.close())
+            .unwrap_err();
+        assert!(err.get_ref().is_some_and(|e| e.is::<NotAuthentic>()));
+    }
+
+    let mut trailing = ciphertext.clone();
+    trailing.push(0);
+    let mut writer = CdcDecWriter::new(io::sink(), &key, Aad::from(b"cdc".as_ref()));
+    let err = writer
+        .write_all(&trailing)
+        .and_then(|()| writer.close())
+        .unwrap_err();
+    assert!(err.get_ref().is_some_and(|e| e.is::<TrailingData>()));
+    Ok(())
+}
+
+#[test]
+fn invalid_chunker() {
+    assert!(Chunker::new(256, 1000, 4096).is_err());
+    assert!(Chunker::new(2048, 1024, 4096).is_err());
+    assert!(Chunker::new(256, 1024, 512).is_err());
+    assert!(Chunker::new(0, 1024, 4096).is_err());
+    assert!(Chunker::new(256, 1024, MAX_BUF_SIZE + 1).is_err());
+}
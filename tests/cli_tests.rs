@@ -0,0 +1,98 @@
+// Copyright (c) 2019 Andreas Auernhammer. All rights reserved.
+// Use of this source code is governed by a license that can be
+// found in the LICENSE file.
+
+#![cfg(feature = "cli")]
+
+use sio::*;
+use std::io::{self, Write};
+use std::process::{Command, Output, Stdio};
+
+const KEY: &str = "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f";
+
+fn sio(args: &[&str], input: &[u8]) -> io::Result<Output> {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_sio"))
+        .args(args)
+        .env("SIO_TEST_KEY", KEY)
+        .env("SIO_TEST_PASSPHRASE", "secret")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    child.stdin.take().unwrap().write_all(input)?;
+    child.wait_with_output()
+}
+
+#[test]
+fn cli_key() -> io::Result<()> {
+    let data: Vec<u8> = (0..50_000).map(|i| i as u8).collect();
+    let args = ["--key-env", "SIO_TEST_KEY", "--nonce", "0102030405060708"];
+
+    let output = sio(&[&["encrypt"][..], &args].concat(), &data)?;
+    assert!(output.status.success());
+    let ciphertext = output.stdout;
+
+    // The ciphertext must be a regular stream of the default framing.
+    let mut key = [0; 32];
+    for (i, b) in key.iter_mut().enumerate() {
+        *b = i as u8;
+    }
+    let mut plaintext = Vec::default();
+    let mut writer = DecWriter::new(
+        &mut plaintext,
+        &Key::<CHACHA20_POLY1305>::new(key),
+        Nonce::new([1, 2, 3, 4, 5, 6, 7, 8]),
+        Aad::empty(),
+    );
+    writer.write_all(&ciphertext)?;
+    writer.close()?;
+    assert_eq!(plaintext, data);
+
+    let output = sio(&[&["decrypt"][..], &args].concat(), &ciphertext)?;
+    assert!(output.status.success());
+    assert_eq!(output.stdout, data);
+
+    let output = sio(&[&["verify"][..], &args].concat(), &ciphertext)?;
+    assert!(output.status.success());
+    assert_eq!(output.stdout, b"50000\n");
+
+    let output = sio(&[&["verify"][..], &args].concat(), &ciphertext[1..])?;
+    assert!(!output.status.success());
+    Ok(())
+}
+
+#[test]
+fn cli_passphrase() -> io::Result<()> {
+    let args = ["--passphrase-env", "SIO_TEST_PASSPHRASE"];
+    let output = sio(&[&["encrypt"][..], &args].concat(), b"Hello World")?;
+    assert!(output.status.success());
+
+    let output = sio(&[&["decrypt"][..], &args].concat(), &output.stdout)?;
+    assert!(output.status.success());
+    assert_eq!(output.stdout, b"Hello World");
+    Ok(())
+}
+
+#[test]
+fn cli_inspect() -> io::Result<()> {
+    let output = sio(&["encrypt", "--key-env", "SIO_TEST_KEY"], &[0; 100])?;
+    let output = sio(&["inspect"], &output.stdout)?;
+    assert!(output.status.success());
+    let info = String::from_utf8(output.stdout).unwrap();
+    assert!(info.contains("version: Legacy"));
+    assert!(info.contains("plaintext: 100 bytes"));
+    Ok(())
+}
+
+#[test]
+fn cli_usage() -> io::Result<()> {
+    let output = sio(&["encrypt"], b"")?;
+    assert_eq!(output.status.code(), Some(1));
+
+    let output = sio(&["unknown"], b"")?;
+    assert_eq!(output.status.code(), Some(2));
+
+    let output = sio(&["encrypt", "--key-env", "SIO_TEST_KEY", "--bogus"], b"")?;
+    assert_eq!(output.status.code(), Some(1));
+    Ok(())
+}
@@ -6,9 +6,11 @@ use sio::*;
 use std::{io, io::Write};
 
 #[cfg(feature = "aesgcm")]
+#[allow(clippy::upper_case_acronyms)]
 type AEAD = AES_256_GCM;
 
 #[cfg(not(feature = "aesgcm"))]
+#[allow(clippy::upper_case_acronyms)]
 type AEAD = CHACHA20_POLY1305;
 
 struct BadSink;
@@ -30,6 +32,7 @@ impl Close for BadSink {
 
 #[test]
 #[should_panic]
+#[cfg(not(feature = "panic-free"))]
 fn enc_writer_missing_close() {
     let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
     let _ = EncWriter::new(
@@ -42,6 +45,7 @@ fn enc_writer_missing_close() {
 
 #[test]
 #[should_panic]
+#[cfg(not(feature = "panic-free"))]
 fn enc_writer_missing_close_after_write() {
     let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
     let mut writer = EncWriter::new(
@@ -67,6 +71,7 @@ fn enc_writer_missing_close_after_error() {
 
 #[test]
 #[should_panic]
+#[cfg(not(feature = "panic-free"))]
 fn enc_writer_missing_close_after_panic() {
     let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
     let _ = EncWriter::new(
@@ -80,6 +85,7 @@ fn enc_writer_missing_close_after_panic() {
 
 #[test]
 #[should_panic]
+#[cfg(not(feature = "panic-free"))]
 fn dec_writer_missing_close() {
     let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
     let _ = DecWriter::new(
@@ -92,6 +98,7 @@ fn dec_writer_missing_close() {
 
 #[test]
 #[should_panic]
+#[cfg(not(feature = "panic-free"))]
 fn dec_writer_missing_close_after_write() {
     let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
     let mut writer = DecWriter::new(
@@ -117,6 +124,7 @@ fn dec_writer_missing_close_after_error() {
 
 #[test]
 #[should_panic]
+#[cfg(not(feature = "panic-free"))]
 fn dec_writer_missing_close_after_panic() {
     let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
     let _ = DecWriter::new(
@@ -127,3 +135,422 @@ fn dec_writer_missing_close_after_panic() {
     );
     panic!();
 }
+
+// A sink that accepts `limit` bytes and then
+// returns `Ok(0)` for any further write.
+struct FullSink {
+    limit: usize,
+}
+
+impl io::Write for FullSink {
+    fn write(&mut self, b: &[u8]) -> io::Result<usize> {
+        let n = b.len().min(self.limit);
+        self.limit -= n;
+        Ok(n)
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Close for FullSink {
+    fn close(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+fn is_write_zero(err: &io::Error, fragment: &str) -> bool {
+    err.kind() == io::ErrorKind::WriteZero && err.to_string().ends_with(fragment)
+}
+
+#[test]
+fn enc_writer_write_zero() {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    for (limit, fragment) in &[(0, "fragment 0"), (2 * BUF_SIZE, "fragment 1")] {
+        let mut writer = EncWriter::new(
+            FullSink { limit: *limit },
+            &key,
+            Nonce::new([0; Nonce::<AEAD>::SIZE]),
+            Aad::empty(),
+        );
+        let err = writer.write_all(&[0; 3 * BUF_SIZE]).unwrap_err();
+        assert!(is_write_zero(&err, fragment), "{}", err);
+    }
+
+    let writer = EncWriter::new(
+        FullSink { limit: 10 },
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+    );
+    let err = writer.close().unwrap_err();
+    assert!(is_write_zero(&err, "fragment 0"), "{}", err);
+}
+
+#[test]
+fn dec_writer_write_zero() {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let mut ciphertext = Vec::default();
+    let mut writer = EncWriter::new(
+        &mut ciphertext,
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+    );
+    writer.write_all(&[0; 3 * BUF_SIZE]).unwrap();
+    writer.close().unwrap();
+
+    let mut writer = DecWriter::new(
+        FullSink { limit: BUF_SIZE },
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+    );
+    let err = writer.write_all(&ciphertext).unwrap_err();
+    assert!(is_write_zero(&err, "fragment 1"), "{}", err);
+}
+
+struct Pipeline {
+    writer: Closer<EncWriter<AEAD, Vec<u8>>>,
+}
+
+#[test]
+fn closer_in_struct() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let mut pipeline = Pipeline {
+        writer: EncWriter::new(
+            Vec::default(),
+            &key,
+            Nonce::new([0; Nonce::<AEAD>::SIZE]),
+            Aad::empty(),
+        )
+        .closer(),
+    };
+    pipeline.writer.write_all(b"Hello World")?;
+    pipeline.writer.close()?;
+    pipeline.writer.close() // Closing a Closer twice is fine.
+}
+
+#[test]
+fn new_unclosed() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let mut ciphertext = io::Cursor::new(Vec::default());
+    let mut writer = EncWriter::new_unclosed(
+        &mut ciphertext,
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+    );
+    writer.write_all(b"Hello World")?;
+    writer.close()?;
+
+    let mut plaintext = io::Cursor::new(Vec::default());
+    let mut writer = DecWriter::new_unclosed(
+        &mut plaintext,
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+    );
+    writer.write_all(ciphertext.get_ref())?;
+    writer.close()?;
+    assert_eq!(plaintext.get_ref().as_slice(), b"Hello World");
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn unix_stream() -> io::Result<()> {
+    use std::io::Read;
+    use std::os::unix::net::UnixStream;
+    use std::thread;
+
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let data = vec![0; 3 * BUF_SIZE + 1];
+    let (sender, receiver) = UnixStream::pair()?;
+
+    let plaintext = data.clone();
+    let writer = thread::spawn(move || -> io::Result<()> {
+        let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+        let mut writer = EncWriter::new(
+            sender,
+            &key,
+            Nonce::new([0; Nonce::<AEAD>::SIZE]),
+            Aad::empty(),
+        );
+        writer.write_all(&plaintext)?;
+        writer.close()
+    });
+
+    // The receiver only reaches the end of the stream
+    // once the sender has been closed.
+    let mut reader = DecReader::new(
+        receiver,
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+    );
+    let mut plaintext = Vec::default();
+    reader.read_to_end(&mut plaintext)?;
+    writer.join().unwrap()?;
+    assert_eq!(plaintext, data);
+    Ok(())
+}
+
+#[test]
+fn callback_writer() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let plaintext = vec![1; 3 * BUF_SIZE + 1];
+
+    let mut ciphertext = Vec::default();
+    let mut closed = 0;
+    let mut writer = EncWriter::new(
+        CallbackWriter::new(
+            |buf: &[u8]| {
+                ciphertext.extend_from_slice(buf);
+                Ok(())
+            },
+            || {
+                closed += 1;
+                Ok(())
+            },
+        ),
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+    );
+    writer.write_all(&plaintext)?;
+    writer.close()?;
+    assert_eq!(closed, 1);
+
+    let mut decrypted = Vec::default();
+    let mut writer = DecWriter::new(
+        CallbackWriter::new(
+            |buf: &[u8]| {
+                decrypted.extend_from_slice(buf);
+                Ok(())
+            },
+            || Ok(()),
+        ),
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+    );
+    writer.write_all(&ciphertext)?;
+    writer.close()?;
+    assert_eq!(decrypted, plaintext);
+    Ok(())
+}
+
+#[test]
+fn callback_writer_error() {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let mut writer = EncWriter::new(
+        CallbackWriter::new(
+            |_: &[u8]| Err(io::Error::from(io::ErrorKind::BrokenPipe)),
+            || Ok(()),
+        ),
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+    );
+    writer.write_all(b"Hello World").unwrap();
+    let err = writer.close().unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::BrokenPipe);
+}
+
+#[test]
+#[cfg(feature = "panic-free")]
+fn missing_close_panic_free() {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let mut writer = EncWriter::new(
+        Vec::default(),
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+    );
+    let _ = writer.write_all(b"Hello World");
+    drop(writer);
+
+    let mut writer = DecWriter::new(
+        Vec::default(),
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+    );
+    let _ = writer.write_all(b"Hello World");
+    drop(writer);
+}
+
+#[test]
+#[cfg(feature = "panic-free")]
+fn write_after_close_panic_free() {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let mut writer = EncWriter::new(
+        Vec::default(),
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+    )
+    .closer();
+    writer.close().unwrap();
+
+    let err = writer.write(b"Hello World").unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+
+    let writer = DecWriter::new(
+        Vec::default(),
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+    );
+    let err = writer.close_forensic().unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+}
+
+// A writer that counts how often it has been closed.
+#[derive(Default)]
+struct CountingSink {
+    data: Vec<u8>,
+    closes: usize,
+}
+
+impl io::Write for &mut CountingSink {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.data.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Close for &mut CountingSink {
+    fn close(&mut self) -> io::Result<()> {
+        self.closes += 1;
+        Ok(())
+    }
+}
+
+#[test]
+fn idempotent_close() -> io::Result<()> {
+    let mut sink = CountingSink::default();
+    let mut writer = Idempotent::wrap(&mut sink);
+    writer.write_all(b"Hello")?;
+    assert!(!writer.is_closed());
+    writer.close()?;
+    writer.close()?;
+    assert!(writer.is_closed());
+
+    let err = writer.write_all(b"World").unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    let sink = writer.into_inner();
+    assert_eq!(sink.closes, 1);
+    assert_eq!(sink.data, b"Hello");
+    Ok(())
+}
+
+#[test]
+fn idempotent_failed_close() {
+    let mut writer = Idempotent::wrap(BadSink);
+    assert!(writer.close().is_err());
+    assert!(writer.close().is_err());
+    assert!(writer.write_all(b"Hello").is_err());
+
+    let mut writer = Idempotent::wrap(BadSink);
+    assert!(writer.write_all(b"Hello").is_err());
+    assert!(writer.close().is_err());
+}
+
+#[test]
+fn idempotent_enc_writer() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let mut ciphertext = Vec::default();
+    let mut writer = Idempotent::wrap(EncWriter::new(
+        &mut ciphertext,
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+    ));
+    writer.write_all(b"Hello World")?;
+    writer.close()?;
+    writer.close()?;
+    assert!(writer.write_all(b"!").is_err());
+    drop(writer);
+
+    let plaintext = decrypt_vec(
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+        &ciphertext,
+    )?;
+    assert_eq!(plaintext, b"Hello World");
+    Ok(())
+}
+
+// Plugins may choose the sink at runtime. Hence, boxed trait objects
+// must be usable as inner writers.
+#[test]
+fn boxed_sinks() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let sink: Box<dyn WriteClose + Send> = Box::new(
+        EncWriter::new(
+            Vec::default(),
+            &key,
+            Nonce::new([1; Nonce::<AEAD>::SIZE]),
+            Aad::empty(),
+        )
+        .closer(),
+    );
+    let mut writer = EncWriter::new(
+        sink,
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+    );
+    writer.write_all(b"Hello World")?;
+    writer.close()?;
+
+    let mut ciphertext = Vec::default();
+    let sink: Box<dyn Write + Send + '_> = Box::new(&mut ciphertext);
+    let mut writer = EncWriter::new(
+        sink,
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+    );
+    writer.write_all(b"Hello World")?;
+    writer.close()?;
+
+    let mut plaintext = Vec::default();
+    let sink: &mut dyn Write = &mut plaintext;
+    let mut writer = DecWriter::new(
+        sink,
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+    );
+    writer.write_all(&ciphertext)?;
+    writer.close()?;
+    assert_eq!(plaintext, b"Hello World");
+    Ok(())
+}
+
+#[test]
+#[should_panic]
+#[cfg(not(feature = "panic-free"))]
+fn boxed_sink_closed() {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let closer = EncWriter::new(
+        Vec::default(),
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+    )
+    .closer();
+    let mut sink: Box<dyn WriteClose + Send + Sync> = Box::new(closer);
+    sink.write_all(b"Hello World").unwrap();
+    sink.close().unwrap();
+
+    // Closing the box must close the `EncWriter` in it.
+    let _ = sink.write(b"!");
+}
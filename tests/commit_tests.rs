@@ -0,0 +1,136 @@
+// Copyright (c) 2019 Andreas Auernhammer. All rights reserved.
+// Use of this source code is governed by a license that can be
+// found in the LICENSE file.
+
+use sio::*;
+use std::{io, io::Write};
+
+#[cfg(feature = "aesgcm")]
+#[allow(clippy::upper_case_acronyms)]
+type AEAD = Committing<AES_256_GCM>;
+
+#[cfg(not(feature = "aesgcm"))]
+#[allow(clippy::upper_case_acronyms)]
+type AEAD = Committing<CHACHA20_POLY1305>;
+
+#[test]
+fn write() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+
+    let data = [0; 1 << 20];
+    let mut plaintext = Vec::with_capacity(data.len());
+    let mut ciphertext = Vec::with_capacity(data.len());
+
+    let mut writer = EncWriter::new(
+        &mut ciphertext,
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+    );
+    writer.write_all(&data).and_then(|()| writer.close())?;
+
+    let fragments = data.len().div_ceil(BUF_SIZE);
+    assert_eq!(ciphertext.len(), data.len() + fragments * AEAD::TAG_LEN);
+
+    let mut writer = DecWriter::new(
+        &mut plaintext,
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+    );
+    writer
+        .write_all(ciphertext.as_slice())
+        .and_then(|()| writer.close())?;
+
+    assert_eq!(data.as_ref(), plaintext.as_slice());
+    Ok(())
+}
+
+#[test]
+fn wrong_key() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let other_key: Key<AEAD> = Key::new([1; Key::<AEAD>::SIZE]);
+
+    let mut ciphertext = Vec::default();
+    let mut writer = EncWriter::new(
+        &mut ciphertext,
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+    );
+    writer
+        .write_all(b"Hello World")
+        .and_then(|()| writer.close())?;
+
+    let mut writer = DecWriter::new(
+        io::sink(),
+        &other_key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+    );
+    let err = writer
+        .write_all(ciphertext.as_slice())
+        .and_then(|()| writer.close())
+        .unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    Ok(())
+}
+
+#[test]
+fn modified_commitment() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+
+    let mut ciphertext = Vec::default();
+    let mut writer = EncWriter::new(
+        &mut ciphertext,
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+    );
+    writer
+        .write_all(b"Hello World")
+        .and_then(|()| writer.close())?;
+
+    let last = ciphertext.len() - 1;
+    ciphertext[last] ^= 1;
+    let mut writer = DecWriter::new(
+        io::sink(),
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+    );
+    let err = writer
+        .write_all(ciphertext.as_slice())
+        .and_then(|()| writer.close())
+        .unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    Ok(())
+}
+
+#[test]
+fn commitment_depends_on_nonce() -> io::Result<()> {
+    // The commitments must not reveal that two streams
+    // have been encrypted with the same key.
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+
+    let mut commitments = Vec::default();
+    for nonce in &[[0; Nonce::<AEAD>::SIZE], [1; Nonce::<AEAD>::SIZE]] {
+        let mut ciphertext = Vec::default();
+        let mut writer = EncWriter::with_buffer_size(
+            &mut ciphertext,
+            &key,
+            Nonce::new(*nonce),
+            Aad::empty(),
+            64,
+        )?;
+        writer.write_all(&[0; 100]).and_then(|()| writer.close())?;
+
+        let fragment_len = 64 + AEAD::TAG_LEN;
+        let first = &ciphertext[fragment_len - 32..fragment_len];
+        let last = &ciphertext[ciphertext.len() - 32..];
+        assert_ne!(first, last);
+        commitments.push(first.to_vec());
+    }
+    assert_ne!(commitments[0], commitments[1]);
+    Ok(())
+}
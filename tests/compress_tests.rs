@@ -0,0 +1,239 @@
+// Copyright (c) 2019 Andreas Auernhammer. All rights reserved.
+// Use of this source code is governed by a license that can be
+// found in the LICENSE file.
+
+#![cfg(any(feature = "zstd", feature = "gzip", feature = "brotli"))]
+
+use sio::*;
+use std::io::{self, Write};
+
+#[cfg(feature = "aesgcm")]
+#[allow(clippy::upper_case_acronyms)]
+type AEAD = AES_256_GCM;
+
+#[cfg(not(feature = "aesgcm"))]
+#[allow(clippy::upper_case_acronyms)]
+type AEAD = CHACHA20_POLY1305;
+
+#[cfg(feature = "zstd")]
+#[test]
+fn compress() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    for size in &[0, 1, 1000, 3 * BUF_SIZE + 1] {
+        for level in &[0, 1, 19] {
+            let data: Vec<u8> = (0..*size).map(|i| (i % 7) as u8).collect();
+            let mut ciphertext = Vec::default();
+            let mut writer = CompressedEncWriter::new(
+                &mut ciphertext,
+                &key,
+                Nonce::new([0; Nonce::<AEAD>::SIZE]),
+                Aad::empty(),
+                *level,
+            )?;
+            writer.write_all(&data)?;
+            writer.close()?;
+
+            let mut plaintext = Vec::default();
+            let mut writer = CompressedDecWriter::new(
+                &mut plaintext,
+                &key,
+                Nonce::new([0; Nonce::<AEAD>::SIZE]),
+                Aad::empty(),
+            )?;
+            writer.write_all(&ciphertext)?;
+            writer.close()?;
+            assert_eq!(plaintext, data);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(feature = "zstd")]
+#[test]
+fn compress_incomplete_frame() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let compressed = zstd::stream::encode_all(&[0; 1000][..], 3)?;
+
+    // An authentic stream that lacks the end of the zstd frame.
+    let mut ciphertext = Vec::default();
+    let mut writer = EncWriter::new(
+        &mut ciphertext,
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+    );
+    writer.write_all(&compressed[..compressed.len() - 3])?;
+    writer.close()?;
+
+    let mut writer = CompressedDecWriter::new(
+        io::sink(),
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+    )?;
+    let err = writer
+        .write_all(&ciphertext)
+        .and_then(|()| writer.close())
+        .unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    Ok(())
+}
+
+fn codecs() -> Vec<(Codec, i32)> {
+    vec![
+        #[cfg(feature = "zstd")]
+        (Codec::Zstd, 3),
+        #[cfg(feature = "gzip")]
+        (Codec::Gzip, 6),
+        #[cfg(feature = "brotli")]
+        (Codec::Brotli, 5),
+    ]
+}
+
+#[test]
+fn compress_codecs() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    for (codec, level) in codecs() {
+        for size in &[0, 1, 1000, 3 * BUF_SIZE + 1] {
+            let data: Vec<u8> = (0..*size).map(|i| (i % 7) as u8).collect();
+            let mut ciphertext = Vec::default();
+            let writer = EncWriter::new(
+                &mut ciphertext,
+                &key,
+                Nonce::new([0; Nonce::<AEAD>::SIZE]),
+                Aad::empty(),
+            );
+            let mut writer = CompressedEncWriter::with_codec(writer, codec, level)?;
+            writer.write_all(&data)?;
+            writer.close()?;
+
+            let mut plaintext = Vec::default();
+            let mut writer = CompressedDecWriter::with_codec(
+                &mut plaintext,
+                &key,
+                Nonce::new([0; Nonce::<AEAD>::SIZE]),
+                Aad::empty(),
+                BUF_SIZE,
+                codec,
+            )?;
+            writer.write_all(&ciphertext)?;
+            writer.close()?;
+            assert_eq!(plaintext, data);
+        }
+
+        let mut ciphertext = Vec::default();
+        let writer = EncWriter::new(
+            &mut ciphertext,
+            &key,
+            Nonce::new([0; Nonce::<AEAD>::SIZE]),
+            Aad::empty(),
+        );
+        let mut writer = CompressedEncWriter::with_codec(writer, codec, level)?;
+        writer.write_all(&[0; 1 << 20])?;
+        writer.close()?;
+        assert!(ciphertext.len() < 1 << 12, "{:?}", codec);
+    }
+    Ok(())
+}
+
+#[test]
+fn compress_incomplete_stream() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    for (codec, level) in codecs() {
+        let data: Vec<u8> = (0..1000).map(|i| (i % 13) as u8).collect();
+        let mut ciphertext = Vec::default();
+        let writer = EncWriter::new(
+            &mut ciphertext,
+            &key,
+            Nonce::new([0; Nonce::<AEAD>::SIZE]),
+            Aad::empty(),
+        );
+        let mut writer = CompressedEncWriter::with_codec(writer, codec, level)?;
+        writer.write_all(&data)?;
+        writer.close()?;
+
+        // Decrypt the ciphertext without decompressing the plaintext.
+        let compressed = decrypt_vec(
+            &key,
+            Nonce::new([0; Nonce::<AEAD>::SIZE]),
+            Aad::empty(),
+            &ciphertext,
+        )?;
+
+        // An authentic stream that lacks the end of the compressed stream.
+        let mut ciphertext = Vec::default();
+        let mut writer = EncWriter::new(
+            &mut ciphertext,
+            &key,
+            Nonce::new([0; Nonce::<AEAD>::SIZE]),
+            Aad::empty(),
+        );
+        writer.write_all(&compressed[..compressed.len() - 3])?;
+        writer.close()?;
+
+        let mut writer = CompressedDecWriter::with_codec(
+            io::sink(),
+            &key,
+            Nonce::new([0; Nonce::<AEAD>::SIZE]),
+            Aad::empty(),
+            BUF_SIZE,
+            codec,
+        )?;
+        let result = writer.write_all(&ciphertext).and_then(|()| writer.close());
+        assert!(result.is_err(), "{:?}", codec);
+    }
+    Ok(())
+}
+
+#[test]
+fn compress_invalid_level() {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let invalid: Vec<(Codec, i32)> = vec![
+        #[cfg(feature = "gzip")]
+        (Codec::Gzip, 10),
+        #[cfg(feature = "brotli")]
+        (Codec::Brotli, 12),
+        #[cfg(feature = "brotli")]
+        (Codec::Brotli, -1),
+    ];
+    for (codec, level) in invalid {
+        let writer = EncWriter::new(
+            io::sink(),
+            &key,
+            Nonce::new([0; Nonce::<AEAD>::SIZE]),
+            Aad::empty(),
+        );
+        assert!(CompressedEncWriter::with_codec(writer, codec, level).is_err());
+    }
+}
+
+#[cfg(feature = "gzip")]
+#[test]
+fn compress_gzip_interop() -> io::Result<()> {
+    use std::io::Read;
+
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let data: Vec<u8> = (0..10_000).map(|i| (i % 7) as u8).collect();
+    let mut ciphertext = Vec::default();
+    let writer = EncWriter::new(
+        &mut ciphertext,
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+    );
+    let mut writer = CompressedEncWriter::with_codec(writer, Codec::Gzip, 6)?;
+    writer.write_all(&data)?;
+    writer.close()?;
+
+    // Decrypt the ciphertext without decompressing the plaintext.
+    let compressed = decrypt_vec(
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+        &ciphertext,
+    )?;
+    let mut plaintext = Vec::default();
+    flate2::read::GzDecoder::new(compressed.as_slice()).read_to_end(&mut plaintext)?;
+    assert_eq!(plaintext, data);
+    Ok(())
+}
@@ -0,0 +1,50 @@
+// Copyright (c) 2019 Andreas Auernhammer. All rights reserved.
+// Use of this source code is governed by a license that can be
+// found in the LICENSE file.
+
+use sio::convergent;
+use sio::*;
+use std::io;
+
+#[cfg(feature = "aesgcm")]
+#[allow(clippy::upper_case_acronyms)]
+type AEAD = AES_256_GCM;
+
+#[cfg(not(feature = "aesgcm"))]
+#[allow(clippy::upper_case_acronyms)]
+type AEAD = CHACHA20_POLY1305;
+
+#[test]
+fn convergent() -> io::Result<()> {
+    for size in &[0, 1, BUF_SIZE, 3 * BUF_SIZE + 1] {
+        let data: Vec<u8> = (0..*size).map(|i| i as u8).collect();
+        let (key, ciphertext) = convergent::encrypt::<AEAD>(b"secret", &data, Aad::empty());
+        assert_eq!(convergent::decrypt(&key, &ciphertext, Aad::empty())?, data);
+    }
+    Ok(())
+}
+
+#[test]
+fn convergent_deterministic() {
+    let (key, first) = convergent::encrypt::<AEAD>(b"secret", b"chunk", Aad::empty());
+    let (_, second) = convergent::encrypt::<AEAD>(b"secret", b"chunk", Aad::empty());
+    assert_eq!(first, second);
+    assert_eq!(
+        key.as_ref(),
+        convergent::key::<AEAD>(b"secret", b"chunk").as_ref()
+    );
+
+    // Different chunks or convergence secrets produce different ciphertexts.
+    let (_, other) = convergent::encrypt::<AEAD>(b"secret", b"chunk!", Aad::empty());
+    assert_ne!(first[..], other[..first.len()]);
+    let (_, other) = convergent::encrypt::<AEAD>(b"other secret", b"chunk", Aad::empty());
+    assert_ne!(first, other);
+}
+
+#[test]
+fn convergent_not_authentic() {
+    let (key, mut ciphertext) = convergent::encrypt::<AEAD>(b"secret", b"chunk", Aad::empty());
+    ciphertext[0] ^= 1;
+    let err = convergent::decrypt(&key, &ciphertext, Aad::empty()).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+}
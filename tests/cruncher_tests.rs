@@ -0,0 +1,111 @@
+// Copyright (c) 2019 Andreas Auernhammer. All rights reserved.
+// Use of this source code is governed by a license that can be
+// found in the LICENSE file.
+
+use sio::*;
+use std::io::{self, Read};
+use std::sync::Mutex;
+
+#[cfg(feature = "aesgcm")]
+#[allow(clippy::upper_case_acronyms)]
+type AEAD = AES_256_GCM;
+
+#[cfg(not(feature = "aesgcm"))]
+#[allow(clippy::upper_case_acronyms)]
+type AEAD = CHACHA20_POLY1305;
+
+fn nonce(i: usize) -> Nonce<AEAD> {
+    Nonce::new((i as u64).to_be_bytes())
+}
+
+#[test]
+fn cruncher() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let sizes: [u64; 8] = [
+        0,
+        1,
+        64,
+        1000,
+        4 * BUF_SIZE as u64 + 7,
+        100,
+        3,
+        BUF_SIZE as u64,
+    ];
+    let objects: Vec<Vec<u8>> = sizes.iter().map(|n| vec![*n as u8; *n as usize]).collect();
+    let mut ciphertexts: Vec<Vec<u8>> = vec![Vec::default(); objects.len()];
+
+    let pool = BufferPool::new(8);
+    let mut cruncher = Cruncher::new(3).pool(pool.clone());
+    for (i, (object, ciphertext)) in objects.iter().zip(ciphertexts.iter_mut()).enumerate() {
+        let job = cruncher.add(object.as_slice(), ciphertext, &key, nonce(i), Aad::empty());
+        assert_eq!(job, i);
+    }
+    assert_eq!(cruncher.len(), objects.len());
+
+    let progress = Mutex::new(vec![0; objects.len()]);
+    let results = cruncher.run_with_progress(|job, bytes| {
+        let mut progress = progress.lock().unwrap();
+        assert!(bytes > progress[job]);
+        progress[job] = bytes;
+    });
+    assert_eq!(progress.into_inner().unwrap(), sizes);
+    assert!(pool.len() <= pool.capacity());
+
+    for (i, result) in results.into_iter().enumerate() {
+        assert_eq!(result?, sizes[i]);
+        let plaintext = decrypt_vec(&key, nonce(i), Aad::empty(), &ciphertexts[i])?;
+        assert_eq!(plaintext, objects[i]);
+    }
+    Ok(())
+}
+
+struct Failing;
+
+impl Read for Failing {
+    fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
+        Err(io::Error::other("source failed"))
+    }
+}
+
+#[test]
+fn failing_job() {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let mut cruncher: Cruncher<AEAD, Box<dyn Read + Send>, Vec<u8>> = Cruncher::new(2);
+    cruncher.add(
+        Box::new(&b"Hello"[..]),
+        Vec::default(),
+        &key,
+        nonce(0),
+        Aad::empty(),
+    );
+    cruncher.add(
+        Box::new(Failing),
+        Vec::default(),
+        &key,
+        nonce(1),
+        Aad::empty(),
+    );
+    cruncher.add(
+        Box::new(&b"World"[..]),
+        Vec::default(),
+        &key,
+        nonce(2),
+        Aad::empty(),
+    );
+
+    let results = cruncher.run();
+    assert_eq!(results[0].as_ref().unwrap(), &5);
+    assert_eq!(
+        results[1].as_ref().unwrap_err().to_string(),
+        "source failed"
+    );
+    assert_eq!(results[2].as_ref().unwrap(), &5);
+}
+
+#[test]
+fn no_jobs() {
+    let cruncher: Cruncher<AEAD, &[u8], Vec<u8>> = Cruncher::new(0);
+    assert!(cruncher.is_empty());
+    assert!(cruncher.run().is_empty());
+    assert!(Cruncher::<AEAD, &[u8], Vec<u8>>::with_buffer_size(1, 0).is_err());
+}
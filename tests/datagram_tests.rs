@@ -0,0 +1,140 @@
+// Copyright (c) 2019 Andreas Auernhammer. All rights reserved.
+// Use of this source code is governed by a license that can be
+// found in the LICENSE file.
+
+use sio::*;
+use std::io;
+
+#[cfg(feature = "aesgcm")]
+#[allow(clippy::upper_case_acronyms)]
+type AEAD = AES_256_GCM;
+
+#[cfg(not(feature = "aesgcm"))]
+#[allow(clippy::upper_case_acronyms)]
+type AEAD = CHACHA20_POLY1305;
+
+fn is_replayed(err: io::Error) -> bool {
+    err.get_ref().is_some_and(|e| e.is::<Replayed>())
+}
+
+#[test]
+fn datagram() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let nonce = Nonce::new([3; Nonce::<AEAD>::SIZE]);
+    let mut sealer = DatagramSealer::new(&key, nonce);
+    let mut opener = DatagramOpener::new(&key, nonce);
+    let messages: Vec<Vec<u8>> = (0..16).map(|i| vec![i; 10 * i as usize]).collect();
+    let datagrams = messages
+        .iter()
+        .map(|message| sealer.seal(Aad::from(b"udp".as_ref()), message))
+        .collect::<io::Result<Vec<_>>>()?;
+
+    // The ciphertext of a datagram is a stream of one final fragment.
+    let mut nonce = [3; Nonce::<AEAD>::SIZE];
+    nonce[Nonce::<AEAD>::SIZE - 4..].copy_from_slice(&[3, 3, 3, 4]);
+    let plaintext = open_one(
+        &key,
+        Nonce::new(nonce),
+        Aad::from(b"udp".as_ref()),
+        &datagrams[1][4..],
+    )?;
+    assert_eq!(plaintext, messages[1]);
+
+    for i in (0..datagrams.len()).rev() {
+        let (seq_num, plaintext) = opener.open(Aad::from(b"udp".as_ref()), &datagrams[i])?;
+        assert_eq!(seq_num, 0x0303_0303 + i as u32);
+        assert_eq!(plaintext, messages[i]);
+    }
+    for datagram in &datagrams {
+        let err = opener
+            .open(Aad::from(b"udp".as_ref()), datagram)
+            .unwrap_err();
+        assert!(is_replayed(err));
+    }
+    Ok(())
+}
+
+#[test]
+fn datagram_window() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let nonce = Nonce::new([3; Nonce::<AEAD>::SIZE]);
+    let mut sealer = DatagramSealer::new(&key, nonce);
+    let mut opener = DatagramOpener::new(&key, nonce);
+    let datagrams = (0..200)
+        .map(|_| sealer.seal(Aad::empty(), b"Hello"))
+        .collect::<io::Result<Vec<_>>>()?;
+
+    opener.open(Aad::empty(), &datagrams[100])?;
+    opener.open(Aad::empty(), &datagrams[37])?;
+    assert!(is_replayed(
+        opener.open(Aad::empty(), &datagrams[36]).unwrap_err()
+    ));
+    assert!(is_replayed(
+        opener.open(Aad::empty(), &datagrams[0]).unwrap_err()
+    ));
+
+    opener.open(Aad::empty(), &datagrams[199])?;
+    assert!(is_replayed(
+        opener.open(Aad::empty(), &datagrams[100]).unwrap_err()
+    ));
+    for datagram in &datagrams[136..199] {
+        opener.open(Aad::empty(), datagram)?;
+    }
+    Ok(())
+}
+
+#[test]
+fn datagram_not_authentic() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let nonce = Nonce::new([3; Nonce::<AEAD>::SIZE]);
+    let mut sealer = DatagramSealer::new(&key, nonce);
+    let mut opener = DatagramOpener::new(&key, nonce);
+    let datagram = sealer.seal(Aad::empty(), b"Hello World")?;
+
+    for i in 0..datagram.len() {
+        let mut modified = datagram.clone();
+        modified[i] ^= 1;
+        let err = opener.open(Aad::empty(), &modified).unwrap_err();
+        assert!(err.get_ref().is_some_and(|e| e.is::<NotAuthentic>()));
+    }
+    assert!(opener.open(Aad::empty(), &datagram[..3]).is_err());
+    assert!(opener.open(Aad::from(b"udp".as_ref()), &datagram).is_err());
+
+    // Forged datagrams must not consume the sequence number.
+    opener.open(Aad::empty(), &datagram)?;
+    Ok(())
+}
+
+#[test]
+fn datagram_exceeded() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let mut sealer = DatagramSealer::new(&key, Nonce::new([0xff; Nonce::<AEAD>::SIZE]));
+    assert_eq!(sealer.remaining(), 1);
+    assert!(sealer
+        .seal(Aad::empty(), &vec![0; MAX_BUF_SIZE + 1])
+        .is_err());
+
+    let datagram = sealer.seal(Aad::empty(), b"Hello")?;
+    assert_eq!(datagram[..4], [0xff; 4]);
+    let err = sealer.seal(Aad::empty(), b"World").unwrap_err();
+    assert!(err.get_ref().is_some_and(|e| e.is::<Exceeded>()));
+    Ok(())
+}
+
+#[test]
+fn datagram_large_window() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let nonce = Nonce::new([0; Nonce::<AEAD>::SIZE]);
+    let mut sealer = DatagramSealer::new(&key, nonce);
+    let datagrams = (0..1000)
+        .map(|_| sealer.seal(Aad::empty(), b"Hello"))
+        .collect::<io::Result<Vec<_>>>()?;
+
+    let mut opener = DatagramOpener::with_window(&key, nonce, ReplayWindow::new(1000));
+    assert_eq!(opener.window().size(), 1000);
+    for datagram in datagrams.iter().rev() {
+        opener.open(Aad::empty(), datagram)?;
+    }
+    assert_eq!(opener.window().top(), Some(999));
+    Ok(())
+}
@@ -0,0 +1,240 @@
+// Copyright (c) 2019 Andreas Auernhammer. All rights reserved.
+// Use of this source code is governed by a license that can be
+// found in the LICENSE file.
+
+#![cfg(feature = "deoxys")]
+
+use sio::*;
+use std::io::{self, Read, Write};
+
+fn hex(s: &str) -> Vec<u8> {
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+        .collect()
+}
+
+fn seal(aad_len: usize, msg_len: usize) -> Vec<u8> {
+    let mut key = [0; 32];
+    for (i, b) in key.iter_mut().enumerate() {
+        *b = 0x10 + i as u8;
+    }
+    let nonce: Vec<u8> = (0x20..0x2f).collect();
+    let aad: Vec<u8> = (0..aad_len).map(|i| i as u8).collect();
+    let mut in_out: Vec<u8> = (0..msg_len).map(|i| i as u8).collect();
+    in_out.extend_from_slice(&[0; 16]);
+
+    let deoxys = DEOXYS_II_256_128::new(&key);
+    let sealed = deoxys
+        .seal_in_place(&nonce, &aad, &mut in_out)
+        .unwrap()
+        .to_vec();
+
+    let msg: Vec<u8> = (0..msg_len).map(|i| i as u8).collect();
+    assert_eq!(
+        deoxys.open_in_place(&nonce, &aad, &mut in_out).unwrap(),
+        msg.as_slice()
+    );
+    sealed
+}
+
+#[test]
+fn test_vectors() {
+    assert_eq!(seal(0, 0), hex("2b97bd77712f0cde975309959dfe1d7c"));
+    assert_eq!(seal(32, 0), hex("54708ae5565a71f147bdb94d7ba3aed7"));
+    assert_eq!(
+        seal(0, 32),
+        hex("9da20db1c2781f6669257d87e2a4d9be1970f7581bef2c995e1149331e5e8cc192ce3aec3a4b72ff9eab71c2a93492fa")
+    );
+    assert_eq!(
+        seal(32, 32),
+        hex("11129c30d4884a5d03b01cd6bcc7334f84b1f8fd1962b6b513f6d8f6df54aecf91ad6cf7b1a967adc8904dd35cc445d6")
+    );
+    assert_eq!(
+        seal(0, 33),
+        hex("bf339a27eaf483b8bc4ee59067460cf208cdcd64900aabd787ee1047d78a2a32391138ebf9269c3e6fedf67ebc1602963e")
+    );
+    assert_eq!(
+        seal(33, 33),
+        hex("7885cda8e93272cc3b91f553d90085fc1c3a13eb3115675e1008fbe84c2aab00e8cf097ff3e24d421f1dd3a7804c7665be")
+    );
+}
+
+#[test]
+fn roundtrip() -> io::Result<()> {
+    let key: Key<DEOXYS_II_256_128> = Key::new([1; Key::<DEOXYS_II_256_128>::SIZE]);
+    let data: Vec<u8> = (0..1000).map(|i| i as u8).collect();
+
+    let mut ciphertext = Vec::default();
+    let mut writer = EncWriter::with_buffer_size(
+        &mut ciphertext,
+        &key,
+        Nonce::new([0; Nonce::<DEOXYS_II_256_128>::SIZE]),
+        Aad::from(b"deoxys".as_ref()),
+        100,
+    )?;
+    writer.write_all(&data)?;
+    writer.close()?;
+
+    let mut plaintext = Vec::default();
+    let mut writer = DecWriter::with_buffer_size(
+        &mut plaintext,
+        &key,
+        Nonce::new([0; Nonce::<DEOXYS_II_256_128>::SIZE]),
+        Aad::from(b"deoxys".as_ref()),
+        100,
+    )?;
+    writer.write_all(&ciphertext)?;
+    writer.close()?;
+    assert_eq!(plaintext, data);
+    Ok(())
+}
+
+#[test]
+fn not_authentic() {
+    let deoxys = DEOXYS_II_256_128::new(&[2; 32]);
+    let nonce = [3; 15];
+    let mut in_out = vec![7; 40];
+    let sealed = deoxys
+        .seal_in_place(&nonce, b"aad", &mut in_out)
+        .unwrap()
+        .to_vec();
+
+    for i in 0..sealed.len() {
+        let mut modified = sealed.clone();
+        modified[i] ^= 1;
+        assert!(deoxys.open_in_place(&nonce, b"aad", &mut modified).is_err());
+        assert!(modified[..24].iter().all(|b| *b == 0));
+    }
+    let mut modified = sealed.clone();
+    assert!(deoxys.open_in_place(&nonce, b"aae", &mut modified).is_err());
+    let mut modified = sealed.clone();
+    assert!(deoxys
+        .open_in_place(&[4; 15], b"aad", &mut modified)
+        .is_err());
+    assert!(deoxys.open_in_place(&nonce, b"aad", &mut [0; 15]).is_err());
+    assert!(deoxys
+        .seal_in_place(&[0; 12], b"aad", &mut [0; 16])
+        .is_err());
+}
+
+#[test]
+#[cfg(feature = "ring")]
+fn master_key() -> io::Result<()> {
+    let key: Key<DEOXYS_II_256_128> = Key::new([1; Key::<DEOXYS_II_256_128>::SIZE]);
+
+    let mut ciphertext = Vec::default();
+    let mut writer = EncWriter::with_master_key(&mut ciphertext, &key, Aad::empty())?;
+    writer.write_all(b"Hello World")?;
+    writer.close()?;
+
+    let mut plaintext = Vec::default();
+    DecReader::with_master_key(ciphertext.as_slice(), &key, Aad::empty())?
+        .read_to_end(&mut plaintext)?;
+    assert_eq!(plaintext, b"Hello World");
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "ring")]
+fn convergent() -> io::Result<()> {
+    let (key, ciphertext) =
+        convergent::encrypt::<DEOXYS_II_256_128>(b"secret", b"Hello World", Aad::empty());
+    assert_eq!(
+        convergent::decrypt(&key, &ciphertext, Aad::empty())?,
+        b"Hello World"
+    );
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "ring")]
+fn manifest() -> io::Result<()> {
+    let key: Key<DEOXYS_II_256_128> = Key::new([1; Key::<DEOXYS_II_256_128>::SIZE]);
+
+    let mut object = Vec::default();
+    let mut manifest = manifest::Manifest::default();
+    manifest.append(&key, "file", "object", 0, &b"Hello World"[..], &mut object)?;
+    let mut encrypted = Vec::default();
+    manifest.write_to(&key, &mut encrypted)?;
+
+    let manifest = manifest::Manifest::read_from(&key, encrypted.as_slice())?;
+    let mut plaintext = Vec::default();
+    manifest
+        .open(&key, "file", io::Cursor::new(object))?
+        .read_to_end(&mut plaintext)?;
+    assert_eq!(plaintext, b"Hello World");
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "x25519")]
+fn x25519() -> io::Result<()> {
+    let secret_key = x25519::SecretKey::generate()?;
+
+    let mut ciphertext = Vec::default();
+    let mut writer = x25519::seal::<DEOXYS_II_256_128, _>(
+        &mut ciphertext,
+        &secret_key.public_key(),
+        Aad::empty(),
+    )?;
+    writer.write_all(b"Hello World")?;
+    writer.close()?;
+
+    let mut plaintext = Vec::default();
+    x25519::open::<DEOXYS_II_256_128, _>(ciphertext.as_slice(), &secret_key, Aad::empty())?
+        .read_to_end(&mut plaintext)?;
+    assert_eq!(plaintext, b"Hello World");
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "x25519")]
+fn hpke() -> io::Result<()> {
+    let secret_key = x25519::SecretKey::generate()?;
+    let sender = x25519::SecretKey::generate()?;
+
+    let mut ciphertext = Vec::default();
+    let mut writer = hpke::seal_auth::<DEOXYS_II_256_128, _>(
+        &mut ciphertext,
+        &secret_key.public_key(),
+        &sender,
+        b"info",
+        Aad::empty(),
+    )?;
+    writer.write_all(b"Hello World")?;
+    writer.close()?;
+
+    let mut plaintext = Vec::default();
+    hpke::open_auth::<DEOXYS_II_256_128, _>(
+        ciphertext.as_slice(),
+        &secret_key,
+        &sender.public_key(),
+        b"info",
+        Aad::empty(),
+    )?
+    .read_to_end(&mut plaintext)?;
+    assert_eq!(plaintext, b"Hello World");
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "password")]
+fn password() -> io::Result<()> {
+    let params = PasswordParams::new(64, 1, 1).unwrap();
+
+    let mut ciphertext = Vec::default();
+    let mut writer = EncWriter::<DEOXYS_II_256_128, _>::with_password_params(
+        &mut ciphertext,
+        b"secret",
+        params,
+    )?;
+    writer.write_all(b"Hello World")?;
+    writer.close()?;
+
+    let mut plaintext = Vec::default();
+    DecReader::<DEOXYS_II_256_128, _>::with_password(ciphertext.as_slice(), b"secret")?
+        .read_to_end(&mut plaintext)?;
+    assert_eq!(plaintext, b"Hello World");
+    Ok(())
+}
@@ -0,0 +1,171 @@
+// Copyright (c) 2019 Andreas Auernhammer. All rights reserved.
+// Use of this source code is governed by a license that can be
+// found in the LICENSE file.
+
+use ring::digest::{digest, SHA256};
+use sio::*;
+use std::io::{self, Read, Write};
+
+#[cfg(feature = "aesgcm")]
+#[allow(clippy::upper_case_acronyms)]
+type AEAD = AES_256_GCM;
+
+#[cfg(not(feature = "aesgcm"))]
+#[allow(clippy::upper_case_acronyms)]
+type AEAD = CHACHA20_POLY1305;
+
+// A writer that records whether it has been closed.
+#[derive(Default)]
+struct Sink {
+    data: Vec<u8>,
+    closed: bool,
+}
+
+impl Write for &mut Sink {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.data.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Close for &mut Sink {
+    fn close(&mut self) -> io::Result<()> {
+        self.closed = true;
+        Ok(())
+    }
+}
+
+fn sha256(data: &[u8]) -> [u8; 32] {
+    let mut sum = [0; 32];
+    sum.copy_from_slice(digest(&SHA256, data).as_ref());
+    sum
+}
+
+#[test]
+fn verify_digest() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    for size in &[0, 1, BUF_SIZE, 3 * BUF_SIZE + 1] {
+        let data: Vec<u8> = (0..*size).map(|i| i as u8).collect();
+        let ciphertext = encrypt_vec(
+            &key,
+            Nonce::new([0; Nonce::<AEAD>::SIZE]),
+            Aad::empty(),
+            &data,
+        );
+
+        let mut sink = Sink::default();
+        decrypt_verify_digest(
+            ciphertext.as_slice(),
+            &mut sink,
+            &key,
+            Nonce::new([0; Nonce::<AEAD>::SIZE]),
+            Aad::empty(),
+            &sha256(&data),
+        )?;
+        assert!(sink.closed);
+        assert_eq!(sink.data, data);
+    }
+    Ok(())
+}
+
+#[test]
+fn digest_mismatch() {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let data = vec![1; 2 * BUF_SIZE];
+    let ciphertext = encrypt_vec(
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+        &data,
+    );
+
+    let mut sink = Sink::default();
+    let err = decrypt_verify_digest(
+        ciphertext.as_slice(),
+        &mut sink,
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+        &sha256(b""),
+    )
+    .unwrap_err();
+    assert!(err.get_ref().is_some_and(|e| e.is::<DigestMismatch>()));
+    assert!(!sink.closed);
+}
+
+#[test]
+fn not_authentic() {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let data = vec![1; 2 * BUF_SIZE];
+    let ciphertext = encrypt_vec(
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+        &data,
+    );
+
+    let mut modified = ciphertext.clone();
+    let len = modified.len();
+    modified[len - 1] ^= 1;
+
+    let mut sink = Sink::default();
+    let err = decrypt_verify_digest(
+        modified.as_slice(),
+        &mut sink,
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+        &sha256(&data),
+    )
+    .unwrap_err();
+    assert!(err.get_ref().is_some_and(|e| e.is::<NotAuthentic>()));
+    assert!(!sink.closed);
+
+    let mut sink = Sink::default();
+    let result = decrypt_verify_digest(
+        &ciphertext[..BUF_SIZE + 16],
+        &mut sink,
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+        &sha256(&data),
+    );
+    assert!(result.is_err());
+    assert!(!sink.closed);
+}
+
+struct Failing;
+
+impl Read for Failing {
+    fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
+        Err(io::Error::other("source failed"))
+    }
+}
+
+#[test]
+fn failing_reader() {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let ciphertext = encrypt_vec(
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+        b"Hello World",
+    );
+
+    let mut sink = Sink::default();
+    let err = decrypt_verify_digest(
+        ciphertext.as_slice().chain(Failing),
+        &mut sink,
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+        &sha256(b"Hello World"),
+    )
+    .unwrap_err();
+    assert_eq!(err.to_string(), "source failed");
+    assert!(!sink.closed);
+}
@@ -0,0 +1,179 @@
+// Copyright (c) 2019 Andreas Auernhammer. All rights reserved.
+// Use of this source code is governed by a license that can be
+// found in the LICENSE file.
+
+use sio::*;
+use std::io::{self, Read, Write};
+
+#[cfg(feature = "aesgcm")]
+#[allow(clippy::upper_case_acronyms)]
+type AEAD = AES_256_GCM;
+
+#[cfg(not(feature = "aesgcm"))]
+#[allow(clippy::upper_case_acronyms)]
+type AEAD = CHACHA20_POLY1305;
+
+#[test]
+fn finish_empty() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let nonce = Nonce::new([0; Nonce::<AEAD>::SIZE]);
+    let mut ciphertext = Vec::default();
+    EncWriter::new(&mut ciphertext, &key, nonce, Aad::empty()).finish_empty()?;
+    assert_eq!(ciphertext.len(), AEAD::TAG_LEN);
+    assert_eq!(ciphertext, encrypt_vec(&key, nonce, Aad::empty(), &[]));
+    assert_eq!(ciphertext, seal_one(&key, nonce, Aad::empty(), &[])?);
+
+    let mut ciphertext = Vec::default();
+    let mut writer = EncWriter::new(&mut ciphertext, &key, nonce, Aad::empty());
+    writer.write_all(&[])?;
+    writer.flush()?;
+    writer.finish_empty()?;
+    assert_eq!(ciphertext, encrypt_vec(&key, nonce, Aad::empty(), &[]));
+    Ok(())
+}
+
+#[test]
+fn finish_not_empty() {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let nonce = Nonce::new([0; Nonce::<AEAD>::SIZE]);
+    let mut ciphertext = Vec::default();
+    let mut writer = EncWriter::new(&mut ciphertext, &key, nonce, Aad::empty());
+    writer.write_all(b"Hello").unwrap();
+
+    // The writer must not panic when it gets dropped.
+    let err = writer.finish_empty().unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    assert!(ciphertext.is_empty());
+}
+
+#[test]
+fn write_empty() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let nonce = Nonce::new([0; Nonce::<AEAD>::SIZE]);
+    let data: Vec<u8> = (0..3 * 64 + 5).map(|i| i as u8).collect();
+    let mut expected = Vec::default();
+    let mut writer = EncWriter::with_buffer_size(&mut expected, &key, nonce, Aad::empty(), 64)?;
+    writer.write_all(&data)?;
+    writer.close()?;
+
+    let mut ciphertext = Vec::default();
+    let mut writer = EncWriter::with_buffer_size(&mut ciphertext, &key, nonce, Aad::empty(), 64)?;
+    for chunk in data.chunks(64) {
+        assert_eq!(writer.write(&[])?, 0);
+        writer.write_all(chunk)?;
+    }
+    assert_eq!(writer.write(&[])?, 0);
+    writer.close()?;
+    assert_eq!(ciphertext, expected);
+
+    let mut plaintext = Vec::default();
+    let mut writer = DecWriter::with_buffer_size(&mut plaintext, &key, nonce, Aad::empty(), 64)?;
+    for chunk in ciphertext.chunks(80) {
+        assert_eq!(writer.write(&[])?, 0);
+        writer.write_all(chunk)?;
+    }
+    assert_eq!(writer.write(&[])?, 0);
+    writer.close()?;
+    assert_eq!(plaintext, data);
+    Ok(())
+}
+
+#[test]
+fn read_empty() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let nonce = Nonce::new([0; Nonce::<AEAD>::SIZE]);
+    let ciphertext = encrypt_vec(&key, nonce, Aad::empty(), &[]);
+    let mut reader = DecReader::new(ciphertext.as_slice(), &key, nonce, Aad::empty());
+    let mut buf = [0; 16];
+    assert_eq!(reader.read(&mut [])?, 0);
+    assert_eq!(reader.read(&mut buf)?, 0);
+    assert_eq!(reader.read(&mut buf)?, 0);
+    assert_eq!(reader.consumed(), ciphertext.len() as u64);
+
+    let mut plaintext = Vec::default();
+    DecReader::new(ciphertext.as_slice(), &key, nonce, Aad::empty()).read_to_end(&mut plaintext)?;
+    assert!(plaintext.is_empty());
+    Ok(())
+}
+
+#[test]
+fn decrypt_empty() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let nonce = Nonce::new([0; Nonce::<AEAD>::SIZE]);
+    let ciphertext = encrypt_vec(&key, nonce, Aad::empty(), &[]);
+    assert!(decrypt_vec(&key, nonce, Aad::empty(), &ciphertext)?.is_empty());
+    assert!(open_one(&key, nonce, Aad::empty(), &ciphertext)?.is_empty());
+
+    let mut plaintext = Vec::default();
+    let mut writer = DecWriter::new(&mut plaintext, &key, nonce, Aad::empty());
+    writer.write_all(&ciphertext)?;
+    writer.close()?;
+    assert!(plaintext.is_empty());
+
+    let mut plaintext = Vec::default();
+    let mut writer = FixedDecWriter::<AEAD, _, 64>::new(&mut plaintext, &key, nonce, Aad::empty());
+    writer.write_all(&ciphertext)?;
+    writer.close()?;
+    assert!(plaintext.is_empty());
+    Ok(())
+}
+
+// No ciphertext at all is not an empty stream.
+#[test]
+fn decrypt_nothing() {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let nonce = Nonce::new([0; Nonce::<AEAD>::SIZE]);
+    let err = decrypt_vec(&key, nonce, Aad::empty(), &[]).unwrap_err();
+    assert!(err.get_ref().is_some_and(|e| e.is::<NotAuthentic>()));
+    assert!(open_one(&key, nonce, Aad::empty(), &[]).is_err());
+
+    let mut reader = DecReader::new(&[][..], &key, nonce, Aad::empty());
+    assert_eq!(reader.read(&mut []).unwrap(), 0);
+    let err = reader.read(&mut [0; 16]).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+    let mut plaintext = Vec::default();
+    let mut writer = DecWriter::new(&mut plaintext, &key, nonce, Aad::empty());
+    assert_eq!(writer.write(&[]).unwrap(), 0);
+    assert!(writer.close().is_err());
+}
+
+#[test]
+fn delimited_empty() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let nonce = Nonce::new([0; Nonce::<AEAD>::SIZE]);
+    let mut ciphertext = Vec::default();
+    EncWriter::with_framing(
+        &mut ciphertext,
+        &key,
+        nonce,
+        Aad::empty(),
+        BUF_SIZE,
+        framing::Delimited::new(framing::Sio::default(), 0),
+    )?
+    .finish_empty()?;
+
+    let mut reader = DecReader::with_framing(
+        ciphertext.as_slice(),
+        &key,
+        nonce,
+        Aad::empty(),
+        BUF_SIZE,
+        framing::Delimited::<framing::Sio>::default(),
+    )?;
+    assert_eq!(reader.read(&mut [0; 16])?, 0);
+    assert_eq!(reader.read(&mut [0; 16])?, 0);
+    Ok(())
+}
+
+#[test]
+fn fixed_empty() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let nonce = Nonce::new([0; Nonce::<AEAD>::SIZE]);
+    let mut ciphertext = Vec::default();
+    let mut writer = FixedEncWriter::<AEAD, _, 64>::new(&mut ciphertext, &key, nonce, Aad::empty());
+    assert_eq!(writer.write(&[])?, 0);
+    writer.close()?;
+    assert_eq!(ciphertext, encrypt_vec(&key, nonce, Aad::empty(), &[]));
+    Ok(())
+}
@@ -0,0 +1,159 @@
+// Copyright (c) 2019 Andreas Auernhammer. All rights reserved.
+// Use of this source code is governed by a license that can be
+// found in the LICENSE file.
+
+#![cfg(feature = "fec")]
+
+use sio::fec::{FecDecWriter, FecEncWriter, CHECKSUM_LEN};
+use sio::*;
+use std::io::{self, Write};
+
+#[cfg(feature = "aesgcm")]
+#[allow(clippy::upper_case_acronyms)]
+type AEAD = AES_256_GCM;
+
+#[cfg(not(feature = "aesgcm"))]
+#[allow(clippy::upper_case_acronyms)]
+type AEAD = CHACHA20_POLY1305;
+
+const SHARD_LEN: usize = 64 + AEAD::TAG_LEN + CHECKSUM_LEN;
+
+#[test]
+fn fec() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    for size in &[0, 1, 64, 4 * 64, 1000] {
+        let data: Vec<u8> = (0..*size).map(|i| i as u8).collect();
+
+        let mut ciphertext = Vec::default();
+        let mut writer = FecEncWriter::with_buffer_size(
+            &mut ciphertext,
+            &key,
+            Nonce::new([0; Nonce::<AEAD>::SIZE]),
+            Aad::empty(),
+            64,
+            4,
+            2,
+        )?;
+        for chunk in data.chunks(100) {
+            writer.write_all(chunk)?;
+        }
+        writer.close()?;
+
+        let mut plaintext = Vec::default();
+        let mut writer = FecDecWriter::with_buffer_size(
+            &mut plaintext,
+            &key,
+            Nonce::new([0; Nonce::<AEAD>::SIZE]),
+            Aad::empty(),
+            64,
+            4,
+            2,
+        )?;
+        for chunk in ciphertext.chunks(77) {
+            writer.write_all(chunk)?;
+        }
+        assert_eq!(writer.close()?, 0);
+        assert_eq!(plaintext, data);
+    }
+    Ok(())
+}
+
+#[test]
+fn fec_repair() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let data: Vec<u8> = (0..1000).map(|i| i as u8).collect();
+    let mut ciphertext = Vec::default();
+    let mut writer = FecEncWriter::with_buffer_size(
+        &mut ciphertext,
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+        64,
+        4,
+        2,
+    )?;
+    for chunk in data.chunks(100) {
+        writer.write_all(chunk)?;
+    }
+    writer.close()?;
+
+    // Damage two data shards of the first group, one data and one
+    // parity shard of the second group and the last data shard.
+    ciphertext[3] ^= 1;
+    ciphertext[2 * SHARD_LEN + 10] ^= 1;
+    ciphertext[6 * SHARD_LEN + 20] ^= 1;
+    ciphertext[11 * SHARD_LEN] ^= 1;
+    ciphertext[(6 * 3 + 3) * SHARD_LEN + 1] ^= 1;
+
+    let mut plaintext = Vec::default();
+    let mut writer = FecDecWriter::with_buffer_size(
+        &mut plaintext,
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+        64,
+        4,
+        2,
+    )?;
+    for chunk in ciphertext.chunks(77) {
+        writer.write_all(chunk)?;
+    }
+    assert_eq!(writer.close()?, 4);
+    assert_eq!(plaintext, data);
+    Ok(())
+}
+
+#[test]
+fn fec_too_many_damaged_shards() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let data: Vec<u8> = (0..1000).map(|i| i as u8).collect();
+    let mut ciphertext = Vec::default();
+    let mut writer = FecEncWriter::with_buffer_size(
+        &mut ciphertext,
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+        64,
+        4,
+        2,
+    )?;
+    for chunk in data.chunks(100) {
+        writer.write_all(chunk)?;
+    }
+    writer.close()?;
+    for i in 0..3 {
+        ciphertext[i * SHARD_LEN] ^= 1;
+    }
+
+    let mut writer = FecDecWriter::with_buffer_size(
+        io::sink(),
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+        64,
+        4,
+        2,
+    )?;
+    let result = ciphertext
+        .chunks(77)
+        .try_for_each(|chunk| writer.write_all(chunk))
+        .and_then(|()| writer.close());
+    assert!(result.is_err());
+    Ok(())
+}
+
+#[test]
+fn fec_invalid_shards() {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    for (data, parity) in &[(0, 1), (1, 0), (200, 57)] {
+        let writer = FecEncWriter::new(
+            Vec::default(),
+            &key,
+            Nonce::new([0; Nonce::<AEAD>::SIZE]),
+            Aad::empty(),
+            *data,
+            *parity,
+        );
+        assert!(writer.is_err());
+    }
+}
@@ -0,0 +1,187 @@
+// Copyright (c) 2019 Andreas Auernhammer. All rights reserved.
+// Use of this source code is governed by a license that can be
+// found in the LICENSE file.
+
+use sio::*;
+use std::io::{self, Cursor, Read, Seek, SeekFrom, Write};
+use std::{env, fs, path::PathBuf, process};
+
+#[cfg(feature = "aesgcm")]
+#[allow(clippy::upper_case_acronyms)]
+type AEAD = AES_256_GCM;
+
+#[cfg(not(feature = "aesgcm"))]
+#[allow(clippy::upper_case_acronyms)]
+type AEAD = CHACHA20_POLY1305;
+
+fn temp_dir(name: &str) -> io::Result<PathBuf> {
+    let dir = env::temp_dir().join(format!("sio-{}-{}", name, process::id()));
+    fs::create_dir_all(&dir)?;
+    Ok(dir)
+}
+
+#[test]
+fn decrypt_atomic() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let dir = temp_dir("decrypt-atomic")?;
+    let (src, dst) = (dir.join("src"), dir.join("dst"));
+    let data = vec![7; 100 * 1024];
+
+    let mut ciphertext = Vec::default();
+    let mut writer = EncWriter::new(
+        &mut ciphertext,
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+    );
+    writer.write_all(&data).and_then(|()| writer.close())?;
+    fs::write(&src, ciphertext)?;
+
+    decrypt_file_atomic(
+        &src,
+        &dst,
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+    )?;
+    assert_eq!(fs::read(&dst)?, data);
+    assert_eq!(fs::read_dir(&dir)?.count(), 2);
+    fs::remove_dir_all(&dir)
+}
+
+#[test]
+fn decrypt_atomic_not_authentic() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let dir = temp_dir("decrypt-atomic-not-authentic")?;
+    let (src, dst) = (dir.join("src"), dir.join("dst"));
+
+    let mut ciphertext = Vec::default();
+    let mut writer = EncWriter::new(
+        &mut ciphertext,
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+    );
+    writer
+        .write_all(&[7; 100 * 1024])
+        .and_then(|()| writer.close())?;
+    let len = ciphertext.len();
+    ciphertext[len - 1] ^= 1;
+    fs::write(&src, ciphertext)?;
+    fs::write(&dst, b"old content")?;
+
+    let err = decrypt_file_atomic(
+        &src,
+        &dst,
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+    )
+    .unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    assert_eq!(fs::read(&dst)?, b"old content");
+    assert_eq!(fs::read_dir(&dir)?.count(), 2);
+    fs::remove_dir_all(&dir)
+}
+
+#[test]
+fn encrypted_file_append() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let data: Vec<u8> = (0..1000).map(|i| i as u8).collect();
+    let mut file = EncryptedFile::with_buffer_size(
+        Cursor::new(Vec::default()),
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+        64,
+    )?;
+    assert_eq!(file.len()?, 0);
+
+    let mut n = 0;
+    for size in &[0, 1, 62, 1, 64, 100, 5, 767] {
+        file.append(&data[n..n + size])?;
+        n += size;
+        assert_eq!(file.len()?, n as u64);
+    }
+    assert_eq!(n, data.len());
+
+    let mut plaintext = Vec::default();
+    file.read_to_end(&mut plaintext)?;
+    assert_eq!(plaintext, data);
+
+    // The file content is a regular encrypted stream.
+    let ciphertext = file.into_inner().into_inner();
+    let mut plaintext = Vec::default();
+    let mut writer = DecWriter::with_buffer_size(
+        &mut plaintext,
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+        64,
+    )?;
+    writer.write_all(&ciphertext)?;
+    writer.close()?;
+    assert_eq!(plaintext, data);
+    Ok(())
+}
+
+#[test]
+fn encrypted_file_seek() -> io::Result<()> {
+    let data: Vec<u8> = (0..1000).map(|i| i as u8).collect();
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let mut ciphertext = Vec::default();
+    let mut writer = EncWriter::with_buffer_size(
+        &mut ciphertext,
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+        64,
+    )?;
+    writer.write_all(&data).and_then(|()| writer.close())?;
+
+    let mut file = EncryptedFile::with_buffer_size(
+        Cursor::new(ciphertext),
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+        64,
+    )?;
+    for offset in &[999, 0, 64, 63, 500, 960, 1000] {
+        let mut plaintext = Vec::default();
+        assert_eq!(file.seek(SeekFrom::Start(*offset))?, *offset);
+        file.read_to_end(&mut plaintext)?;
+        assert_eq!(plaintext, &data[*offset as usize..]);
+    }
+    assert_eq!(file.seek(SeekFrom::End(-10))?, 990);
+    assert_eq!(file.seek(SeekFrom::Current(-990))?, 0);
+    assert!(file.seek(SeekFrom::Current(-1)).is_err());
+    Ok(())
+}
+
+#[test]
+fn encrypted_file_truncated() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let mut file = EncryptedFile::with_buffer_size(
+        Cursor::new(Vec::default()),
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+        64,
+    )?;
+    file.append(&[0; 200])?;
+
+    let mut ciphertext = file.into_inner().into_inner();
+    ciphertext.truncate(2 * (64 + AEAD::TAG_LEN));
+    let mut file = EncryptedFile::with_buffer_size(
+        Cursor::new(ciphertext),
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+        64,
+    )?;
+    let err = file.len().unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    let err = file.append(&[0; 1]).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    Ok(())
+}
@@ -0,0 +1,169 @@
+// Copyright (c) 2019 Andreas Auernhammer. All rights reserved.
+// Use of this source code is governed by a license that can be
+// found in the LICENSE file.
+
+use sio::*;
+use std::io::{self, Write};
+
+#[cfg(feature = "aesgcm")]
+#[allow(clippy::upper_case_acronyms)]
+type AEAD = AES_256_GCM;
+
+#[cfg(not(feature = "aesgcm"))]
+#[allow(clippy::upper_case_acronyms)]
+type AEAD = CHACHA20_POLY1305;
+
+const FRAGMENT_LEN: usize = 64 + <AEAD as Algorithm>::TAG_LEN;
+
+#[test]
+fn fixed_writer() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    assert_eq!(FixedEncWriter::<AEAD, Vec<u8>, FRAGMENT_LEN>::BUF_SIZE, 64);
+
+    for len in [0, 1, 63, 64, 65, 128, 200] {
+        let data = vec![7; len];
+
+        let mut ciphertext = Vec::default();
+        let mut writer = FixedEncWriter::<_, _, FRAGMENT_LEN>::new(
+            &mut ciphertext,
+            &key,
+            Nonce::new([0; Nonce::<AEAD>::SIZE]),
+            Aad::from(b"aad".as_ref()),
+        );
+        for chunk in data.chunks(10) {
+            writer.write_all(chunk)?;
+        }
+        writer.close()?;
+
+        // The ciphertext matches the one of an EncWriter with the same buffer size.
+        let mut expected = Vec::default();
+        let mut writer = EncWriter::with_buffer_size(
+            &mut expected,
+            &key,
+            Nonce::new([0; Nonce::<AEAD>::SIZE]),
+            Aad::from(b"aad".as_ref()),
+            64,
+        )
+        .unwrap();
+        writer.write_all(&data)?;
+        writer.close()?;
+        assert_eq!(ciphertext, expected);
+
+        let mut plaintext = Vec::default();
+        let mut writer = FixedDecWriter::<_, _, FRAGMENT_LEN>::new(
+            &mut plaintext,
+            &key,
+            Nonce::new([0; Nonce::<AEAD>::SIZE]),
+            Aad::from(b"aad".as_ref()),
+        );
+        writer.write_all(&ciphertext)?;
+        writer.close()?;
+        assert_eq!(plaintext, data);
+    }
+    Ok(())
+}
+
+#[test]
+fn fixed_writer_not_authentic() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let mut ciphertext = Vec::default();
+    let mut writer = FixedEncWriter::<_, _, FRAGMENT_LEN>::new(
+        &mut ciphertext,
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+    );
+    writer.write_all(&[0; 100])?;
+    writer.close()?;
+
+    // A truncated stream lacks its final fragment.
+    let mut writer = FixedDecWriter::<_, _, FRAGMENT_LEN>::new(
+        Vec::default(),
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+    );
+    writer.write_all(&ciphertext[..FRAGMENT_LEN])?;
+    let err = writer.close().unwrap_err();
+    assert!(err.get_ref().is_some_and(|e| e.is::<NotAuthentic>()));
+
+    ciphertext[0] ^= 1;
+    let mut writer = FixedDecWriter::<_, _, FRAGMENT_LEN>::new(
+        Vec::default(),
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+    );
+    let err = writer.write_all(&ciphertext).unwrap_err();
+    assert!(err.get_ref().is_some_and(|e| e.is::<NotAuthentic>()));
+    Ok(())
+}
+
+#[test]
+fn heapless_writer() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let data = [7; 300];
+
+    let mut scratch = [0; FRAGMENT_LEN];
+    let mut ciphertext = Vec::default();
+    let mut writer = HeaplessEncWriter::with_buffer(
+        &mut ciphertext,
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+        &mut scratch,
+    )
+    .unwrap();
+    assert_eq!(writer.buf_size(), 64);
+    writer.write_all(&data)?;
+    writer.close()?;
+
+    let mut expected = Vec::default();
+    let mut writer = FixedEncWriter::<_, _, FRAGMENT_LEN>::new(
+        &mut expected,
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+    );
+    writer.write_all(&data)?;
+    writer.close()?;
+    assert_eq!(ciphertext, expected);
+
+    let mut plaintext = [0; 300];
+    let mut writer = HeaplessDecWriter::with_buffer(
+        NopCloser::wrap(&mut plaintext[..]),
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+        &mut scratch,
+    )
+    .unwrap();
+    writer.write_all(&ciphertext)?;
+    writer.close()?;
+    assert_eq!(plaintext, data);
+    Ok(())
+}
+
+#[test]
+fn heapless_writer_invalid_buffer() {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let mut scratch = [0; <AEAD as Algorithm>::TAG_LEN];
+    assert!(HeaplessEncWriter::with_buffer(
+        Vec::default(),
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+        &mut scratch,
+    )
+    .is_err());
+
+    let mut scratch = vec![0; MAX_BUF_SIZE + <AEAD as Algorithm>::TAG_LEN + 1];
+    assert!(HeaplessDecWriter::with_buffer(
+        Vec::default(),
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+        &mut scratch,
+    )
+    .is_err());
+}
@@ -0,0 +1,873 @@
+// Copyright (c) 2019 Andreas Auernhammer. All rights reserved.
+// Use of this source code is governed by a license that can be
+// found in the LICENSE file.
+
+use sio::framing::{Auto, Delimited, DerivedKeys, FragmentAad, Framing, Sio, Stream, Version, V1};
+use sio::*;
+use std::io::{self, Read, Write};
+
+#[cfg(feature = "aesgcm")]
+#[allow(clippy::upper_case_acronyms)]
+type AEAD = AES_256_GCM;
+
+#[cfg(not(feature = "aesgcm"))]
+#[allow(clippy::upper_case_acronyms)]
+type AEAD = CHACHA20_POLY1305;
+
+#[test]
+fn stream_write() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+
+    let data = [0; 1 << 20];
+    let mut plaintext = Vec::with_capacity(data.len());
+    let mut ciphertext = Vec::with_capacity(data.len());
+
+    let mut writer = EncWriter::with_framing(
+        &mut ciphertext,
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::from(b"Some authenticated data".as_ref()),
+        BUF_SIZE,
+        Stream::default(),
+    )?;
+    writer.write_all(&data).and_then(|()| writer.close())?;
+
+    let mut writer = DecWriter::with_framing(
+        &mut plaintext,
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::from(b"Some authenticated data".as_ref()),
+        BUF_SIZE,
+        Stream::default(),
+    )?;
+    writer
+        .write_all(&ciphertext)
+        .and_then(|()| writer.close())?;
+
+    assert_eq!(data.as_ref(), plaintext.as_slice());
+    Ok(())
+}
+
+#[test]
+fn stream_nonce_layout() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([1; Key::<AEAD>::SIZE]);
+    let prefix = [1, 2, 3, 4, 5, 6, 7, 0];
+    let data = [7; 100];
+    let buf_size = 64;
+
+    let mut ciphertext = Vec::default();
+    let mut writer = EncWriter::with_framing(
+        &mut ciphertext,
+        &key,
+        Nonce::new(prefix),
+        Aad::from(b"aad".as_ref()),
+        buf_size,
+        Stream::default(),
+    )?;
+    writer.write_all(&data).and_then(|()| writer.close())?;
+
+    // nonce = prefix (7 bytes) || counter (4 bytes, big-endian) || last-block flag
+    let algorithm = AEAD::new(key.as_ref());
+    let mut expected = Vec::default();
+    for (i, chunk) in data.chunks(buf_size).enumerate() {
+        let mut nonce = [0; 12];
+        nonce[..7].copy_from_slice(&prefix[..7]);
+        nonce[7..11].copy_from_slice(&(i as u32).to_be_bytes());
+        nonce[11] = ((i + 1) * buf_size >= data.len()) as u8;
+
+        let mut fragment = chunk.to_vec();
+        fragment.resize(chunk.len() + AEAD::TAG_LEN, 0);
+        expected.extend_from_slice(algorithm.seal_in_place(&nonce, b"aad", &mut fragment)?);
+    }
+    assert_eq!(expected, ciphertext);
+    Ok(())
+}
+
+#[test]
+fn stream_invalid_nonce() {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let writer = EncWriter::with_framing(
+        io::sink(),
+        &key,
+        Nonce::new([0, 0, 0, 0, 0, 0, 0, 1]),
+        Aad::empty(),
+        BUF_SIZE,
+        Stream::default(),
+    );
+    assert!(writer.is_err());
+}
+
+#[test]
+fn framing_mismatch() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+
+    let mut ciphertext = Vec::default();
+    let mut writer = EncWriter::new(
+        &mut ciphertext,
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+    );
+    writer
+        .write_all(b"Hello World")
+        .and_then(|()| writer.close())?;
+
+    let mut writer = DecWriter::with_framing(
+        io::sink(),
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+        BUF_SIZE,
+        Stream::default(),
+    )?;
+    let err = writer
+        .write_all(&ciphertext)
+        .and_then(|()| writer.close())
+        .unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    Ok(())
+}
+
+fn derived_keys_encrypt(key: &Key<AEAD>, data: &[u8], buf_size: usize) -> io::Result<Vec<u8>> {
+    let mut ciphertext = Vec::default();
+    let mut writer = EncWriter::with_framing(
+        &mut ciphertext,
+        key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+        buf_size,
+        DerivedKeys::default(),
+    )?;
+    writer.write_all(data).and_then(|()| writer.close())?;
+    Ok(ciphertext)
+}
+
+fn derived_keys_decrypt(key: &Key<AEAD>, data: &[u8], buf_size: usize) -> io::Result<Vec<u8>> {
+    let mut plaintext = Vec::default();
+    let mut writer = DecWriter::with_framing(
+        &mut plaintext,
+        key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+        buf_size,
+        DerivedKeys::default(),
+    )?;
+    writer.write_all(data).and_then(|()| writer.close())?;
+    Ok(plaintext)
+}
+
+#[test]
+fn derived_keys_write() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let data = [0; 1 << 20];
+
+    let ciphertext = derived_keys_encrypt(&key, &data, BUF_SIZE)?;
+    let plaintext = derived_keys_decrypt(&key, &ciphertext, BUF_SIZE)?;
+    assert_eq!(data.as_ref(), plaintext.as_slice());
+    Ok(())
+}
+
+#[test]
+fn derived_keys_reordered_fragments() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let data = [0; 3 * 64];
+
+    let ciphertext = derived_keys_encrypt(&key, &data, 64)?;
+    let fragment_len = 64 + AEAD::TAG_LEN;
+    assert_ne!(
+        ciphertext[..fragment_len],
+        ciphertext[fragment_len..2 * fragment_len]
+    );
+
+    let mut reordered = ciphertext.clone();
+    reordered[..fragment_len].copy_from_slice(&ciphertext[fragment_len..2 * fragment_len]);
+    reordered[fragment_len..2 * fragment_len].copy_from_slice(&ciphertext[..fragment_len]);
+    assert!(derived_keys_decrypt(&key, &reordered, 64).is_err());
+
+    let truncated = &ciphertext[..2 * fragment_len];
+    assert!(derived_keys_decrypt(&key, truncated, 64).is_err());
+    Ok(())
+}
+
+#[test]
+fn remaining_fragments() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+
+    let mut writer = EncWriter::with_framing(
+        io::sink(),
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+        64,
+        Stream::default(),
+    )?;
+    assert_eq!(writer.remaining_fragments(), 1 << 32);
+    writer.write_all(&[0; 3 * 64 + 1])?;
+    assert_eq!(writer.remaining_fragments(), (1 << 32) - 3);
+    writer.close()?;
+
+    let mut writer = EncWriter::with_framing(
+        io::sink(),
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+        64,
+        DerivedKeys::default(),
+    )?;
+    assert_eq!(writer.remaining_fragments(), u64::MAX);
+    writer.write_all(&[0; 3 * 64 + 1])?;
+    assert_eq!(writer.remaining_fragments(), u64::MAX - 2);
+    writer.close()
+}
+
+#[derive(Clone, Default)]
+struct Magic(Sio);
+
+impl Framing for Magic {
+    fn init<A: Algorithm>(
+        &mut self,
+        key: &[u8; 32],
+        algorithm: &A,
+        nonce: &[u8],
+        aad: &[u8],
+    ) -> Result<(), Invalid> {
+        self.0.init(key, algorithm, nonce, aad)
+    }
+
+    fn next<A: Algorithm>(
+        &mut self,
+        algorithm: &mut A,
+        last: bool,
+    ) -> Result<(&[u8], &[u8]), Exceeded> {
+        self.0.next(algorithm, last)
+    }
+
+    fn remaining(&self) -> u64 {
+        self.0.remaining()
+    }
+
+    fn header(&self) -> &[u8] {
+        b"MAGIC"
+    }
+
+    fn trailer_len(&self) -> usize {
+        3
+    }
+
+    fn trailer(&self) -> &[u8] {
+        b"END"
+    }
+}
+
+fn encrypt_magic(data: &[u8]) -> io::Result<Vec<u8>> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let mut ciphertext = Vec::default();
+    let mut writer = EncWriter::with_framing(
+        &mut ciphertext,
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+        64,
+        Magic::default(),
+    )?;
+    writer.write_all(data).and_then(|()| writer.close())?;
+    Ok(ciphertext)
+}
+
+fn dec_writer_magic<W: Write + Close>(inner: W) -> io::Result<DecWriter<AEAD, W, Magic>> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let writer = DecWriter::with_framing(
+        inner,
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+        64,
+        Magic::default(),
+    )?;
+    Ok(writer)
+}
+
+fn dec_reader_magic<R: Read>(inner: R) -> io::Result<DecReader<AEAD, R, Magic>> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let reader = DecReader::with_framing(
+        inner,
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+        64,
+        Magic::default(),
+    )?;
+    Ok(reader)
+}
+
+#[test]
+fn custom_framing() -> io::Result<()> {
+    for size in &[0, 1, 63, 64, 65, 128, 1000] {
+        let data: Vec<u8> = (0..*size).map(|i| i as u8).collect();
+        let ciphertext = encrypt_magic(&data)?;
+        assert!(ciphertext.starts_with(b"MAGIC"));
+        assert!(ciphertext.ends_with(b"END"));
+
+        let mut plaintext = Vec::default();
+        let mut writer = dec_writer_magic(&mut plaintext)?;
+        for chunk in ciphertext.chunks(7) {
+            writer.write_all(chunk)?;
+        }
+        writer.close()?;
+        assert_eq!(plaintext, data);
+
+        let mut plaintext = Vec::default();
+        let mut reader = dec_reader_magic(ciphertext.as_slice())?;
+        reader.read_to_end(&mut plaintext)?;
+        assert_eq!(plaintext, data);
+        assert_eq!(reader.consumed(), ciphertext.len() as u64);
+    }
+    Ok(())
+}
+
+#[test]
+fn custom_framing_modified_header_and_trailer() -> io::Result<()> {
+    let ciphertext = encrypt_magic(&[0; 1000])?;
+    for i in &[0, 4, ciphertext.len() - 3, ciphertext.len() - 1] {
+        let mut ciphertext = ciphertext.clone();
+        ciphertext[*i] ^= 1;
+
+        let mut writer = dec_writer_magic(io::sink())?;
+        let err = writer
+            .write_all(&ciphertext)
+            .and_then(|()| writer.close())
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        let mut reader = dec_reader_magic(ciphertext.as_slice())?;
+        let err = reader.read_to_end(&mut Vec::default()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+    Ok(())
+}
+
+#[test]
+fn custom_framing_concatenated() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let mut ciphertext = Vec::default();
+    for (byte, len) in &[(1, 100), (2, 10)] {
+        let mut writer = EncWriter::with_framing(
+            &mut ciphertext,
+            &key,
+            Nonce::new([0; Nonce::<AEAD>::SIZE]),
+            Aad::empty(),
+            64,
+            Delimited::new(Magic::default(), *len as u64),
+        )?;
+        writer.write_all(&vec![*byte; *len])?;
+        writer.close()?;
+    }
+    assert_eq!(&ciphertext[8..13], b"MAGIC");
+    let first = 8 + 5 + 100 + 2 * AEAD::TAG_LEN + 3;
+
+    let mut plaintext = Vec::default();
+    let mut writer = DecWriter::with_framing(
+        &mut plaintext,
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+        64,
+        Delimited::<Magic>::default(),
+    )?
+    .tolerant();
+    writer.write_all(&ciphertext)?;
+    assert_eq!(writer.finish()?, first as u64);
+    assert_eq!(plaintext, vec![1; 100]);
+
+    // The DecReader stops at the end of the first stream.
+    let mut journal = ciphertext.as_slice();
+    let mut plaintext = Vec::default();
+    let mut reader = DecReader::with_framing(
+        &mut journal,
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+        64,
+        Delimited::<Magic>::default(),
+    )?;
+    reader.read_to_end(&mut plaintext)?;
+    assert_eq!(reader.consumed(), first as u64);
+    assert_eq!(plaintext, vec![1; 100]);
+    assert_eq!(journal, &ciphertext[first..]);
+    Ok(())
+}
+
+fn encrypt_with<F: Framing>(data: &[u8], framing: F) -> io::Result<Vec<u8>> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let mut ciphertext = Vec::default();
+    let mut writer = EncWriter::with_framing(
+        &mut ciphertext,
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::from(b"Some authenticated data".as_ref()),
+        64,
+        framing,
+    )?;
+    writer.write_all(data).and_then(|()| writer.close())?;
+    Ok(ciphertext)
+}
+
+fn decrypt_auto(ciphertext: &[u8], framing: Auto) -> io::Result<(Vec<u8>, Version)> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let mut plaintext = Vec::default();
+    let mut writer = DecWriter::with_framing(
+        &mut plaintext,
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::from(b"Some authenticated data".as_ref()),
+        64,
+        framing.clone(),
+    )?;
+    for chunk in ciphertext.chunks(3) {
+        writer.write_all(chunk)?;
+    }
+    let version = writer.framing().version();
+    writer.close()?;
+
+    let mut reader = DecReader::with_framing(
+        ciphertext,
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::from(b"Some authenticated data".as_ref()),
+        64,
+        framing,
+    )?;
+    let mut data = Vec::default();
+    reader.read_to_end(&mut data)?;
+    assert_eq!(data, plaintext);
+    assert_eq!(reader.framing().version(), version);
+    Ok((plaintext, version))
+}
+
+fn is_unsupported_version(err: &io::Error) -> bool {
+    err.get_ref()
+        .is_some_and(|err| err.is::<UnsupportedVersion>())
+}
+
+#[test]
+fn auto_detect_version() -> io::Result<()> {
+    for size in &[0, 1, 64, 65, 1000] {
+        let data: Vec<u8> = (0..*size).map(|i| i as u8).collect();
+
+        let ciphertext = encrypt_with(&data, Sio::default())?;
+        assert_eq!(
+            decrypt_auto(&ciphertext, Auto::default())?,
+            (data.clone(), Version::Legacy)
+        );
+
+        let ciphertext = encrypt_with(&data, V1::default())?;
+        assert!(ciphertext.starts_with(Version::V1.header()));
+        assert_eq!(
+            decrypt_auto(&ciphertext, Auto::default())?,
+            (data.clone(), Version::V1)
+        );
+
+        assert_eq!(ciphertext, encrypt_with(&data, Auto::default())?);
+    }
+    Ok(())
+}
+
+#[test]
+fn auto_allowed_versions() -> io::Result<()> {
+    let legacy = encrypt_with(b"Hello World", Sio::default())?;
+    let v1 = encrypt_with(b"Hello World", V1::default())?;
+
+    let err = decrypt_auto(&legacy, Auto::allow(&[Version::V1])).unwrap_err();
+    assert!(is_unsupported_version(&err));
+    let err = decrypt_auto(&v1, Auto::allow(&[Version::Legacy])).unwrap_err();
+    assert!(is_unsupported_version(&err));
+
+    decrypt_auto(&v1, Auto::allow(&[Version::V1]))?;
+    decrypt_auto(&legacy, Auto::allow(&[Version::Legacy]))?;
+    Ok(())
+}
+
+#[test]
+fn auto_unknown_version() -> io::Result<()> {
+    let mut ciphertext = encrypt_with(b"Hello World", V1::default())?;
+    ciphertext[Version::MAGIC.len()] = 0x02;
+
+    let err = decrypt_auto(&ciphertext, Auto::default()).unwrap_err();
+    assert!(is_unsupported_version(&err));
+    Ok(())
+}
+
+#[test]
+fn auto_stripped_header() -> io::Result<()> {
+    let ciphertext = encrypt_with(b"Hello World", V1::default())?;
+    let stripped = &ciphertext[Version::V1.header().len()..];
+
+    let err = decrypt_auto(stripped, Auto::default()).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    assert!(!is_unsupported_version(&err));
+    Ok(())
+}
+
+#[test]
+fn counter() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([1; Key::<AEAD>::SIZE]);
+    let nonce = [2; Nonce::<AEAD>::SIZE];
+    let plaintext = b"Hello World";
+
+    // Reproduce a stream consisting of one final fragment
+    // using the nonce sequence of the Sio framing.
+    let algorithm = AEAD::new(key.as_ref());
+    let mut counter = Counter::zero(&nonce);
+    assert_eq!(counter.seq_num(), 0);
+
+    let mut aad = vec![0; 1 + AEAD::TAG_LEN];
+    algorithm.seal_in_place(counter.next()?, b"aad", &mut aad[1..])?;
+    aad[0] = 0x80;
+    let mut ciphertext = plaintext.to_vec();
+    ciphertext.resize(plaintext.len() + AEAD::TAG_LEN, 0);
+    algorithm.seal_in_place(counter.next()?, &aad, &mut ciphertext)?;
+    assert_eq!(counter.seq_num(), 2);
+
+    let expected = seal_one(
+        &key,
+        Nonce::new(nonce),
+        Aad::from(b"aad".as_ref()),
+        plaintext,
+    )?;
+    assert_eq!(ciphertext, expected);
+
+    let mut counter = Counter::new(&nonce, u32::MAX);
+    assert_eq!(counter.remaining(), 1);
+    assert_eq!(
+        counter.next()?[..],
+        [2, 2, 2, 2, 2, 2, 2, 2, 255, 255, 255, 255]
+    );
+    assert_eq!(counter.remaining(), 0);
+    assert!(counter.next().is_err());
+
+    let mut counter = Counter::default();
+    assert_eq!(counter.next()?[..], [0; 12]);
+    assert_eq!(counter.next()?[..], [0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0]);
+    Ok(())
+}
+
+#[test]
+fn sio_nonce_layout() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let nonce = [1, 2, 3, 4, 0, 0, 0, 0];
+    let data = vec![1; 3 * BUF_SIZE];
+    let layout = NonceLayout::big_endian(8)?;
+
+    let mut ciphertext = Vec::default();
+    let mut writer = EncWriter::with_framing(
+        &mut ciphertext,
+        &key,
+        Nonce::new(nonce),
+        Aad::empty(),
+        BUF_SIZE,
+        Sio::with_nonce_layout(layout),
+    )?;
+    writer.write_all(&data)?;
+    writer.close()?;
+
+    let mut default = Vec::default();
+    let mut writer = EncWriter::new(&mut default, &key, Nonce::new(nonce), Aad::empty());
+    writer.write_all(&data)?;
+    writer.close()?;
+    assert_eq!(ciphertext.len(), default.len());
+    assert_ne!(ciphertext, default);
+
+    // The first fragment uses sequence number 1 in big-endian encoding.
+    let algorithm = AEAD::new(key.as_ref());
+    let mut associated_data = vec![0; 1 + AEAD::TAG_LEN];
+    algorithm.seal_in_place(
+        &[1, 2, 3, 4, 0, 0, 0, 0, 0, 0, 0, 0],
+        &[],
+        &mut associated_data[1..],
+    )?;
+    let mut fragment = ciphertext[..BUF_SIZE + AEAD::TAG_LEN].to_vec();
+    let plaintext = algorithm.open_in_place(
+        &[1, 2, 3, 4, 0, 0, 0, 0, 0, 0, 0, 1],
+        &associated_data,
+        &mut fragment,
+    )?;
+    assert_eq!(plaintext, &data[..BUF_SIZE]);
+
+    let mut plaintext = Vec::default();
+    let mut writer = DecWriter::with_framing(
+        &mut plaintext,
+        &key,
+        Nonce::new(nonce),
+        Aad::empty(),
+        BUF_SIZE,
+        Sio::with_nonce_layout(layout),
+    )?;
+    writer.write_all(&ciphertext)?;
+    writer.close()?;
+    assert_eq!(plaintext, data);
+
+    assert!(EncWriter::with_framing(
+        Vec::default(),
+        &key,
+        Nonce::new([1; 8]),
+        Aad::empty(),
+        BUF_SIZE,
+        Sio::with_nonce_layout(layout),
+    )
+    .is_err());
+    assert!(NonceLayout::little_endian(3).is_err());
+    assert!(NonceLayout::little_endian(9).is_err());
+    Ok(())
+}
+
+#[test]
+fn derived_nonce() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let nonce = Nonce::<AEAD>::derive(b"derivation key", b"object", 1);
+    assert_eq!(
+        nonce.as_ref(),
+        Nonce::<AEAD>::derive(b"derivation key", b"object", 1).as_ref()
+    );
+    for (derivation_key, object_id, version) in [
+        (&b"derivation key"[..], &b"object"[..], 2),
+        (&b"derivation key"[..], &b"object2"[..], 1),
+        (&b"other key"[..], &b"object"[..], 1),
+    ] {
+        let other = Nonce::<AEAD>::derive(derivation_key, object_id, version);
+        assert_ne!(nonce.as_ref(), other.as_ref());
+    }
+
+    // The nonce can be re-derived for decryption instead of being stored.
+    let ciphertext = encrypt_vec(&key, nonce, Aad::empty(), b"Hello World");
+    let plaintext = decrypt_vec(
+        &key,
+        Nonce::derive(b"derivation key", b"object", 1),
+        Aad::empty(),
+        &ciphertext,
+    )?;
+    assert_eq!(plaintext, b"Hello World");
+    Ok(())
+}
+
+#[test]
+fn can_accept() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let nonce = Nonce::new([0; Nonce::<AEAD>::SIZE]);
+
+    let mut ciphertext = Vec::default();
+    let mut writer = EncWriter::with_framing(
+        &mut ciphertext,
+        &key,
+        nonce,
+        Aad::empty(),
+        64,
+        V1::default(),
+    )?;
+    writer.write_all(&[0; 100])?;
+    writer.close()?;
+    let tag_len = AEAD::TAG_LEN as u64;
+    let overhead = ciphertext.len() as u64 - 100 - 2 * tag_len;
+
+    let mut plaintext = Vec::default();
+    let writer =
+        DecWriter::with_framing(&mut plaintext, &key, nonce, Aad::empty(), 64, V1::default())?;
+    assert!(writer.can_accept(ciphertext.len() as u64).is_ok());
+    assert!(writer.can_accept(overhead + tag_len).is_ok());
+    assert!(writer.can_accept(overhead + tag_len - 1).is_err());
+    assert!(writer.can_accept(overhead + 64 + 2 * tag_len).is_ok());
+    assert!(writer.can_accept(overhead + 64 + 2 * tag_len - 1).is_err());
+    writer.close().unwrap_err();
+
+    // Until the version has been detected, the header is unknown.
+    let mut plaintext = Vec::default();
+    let mut writer = DecWriter::with_framing(
+        &mut plaintext,
+        &key,
+        nonce,
+        Aad::empty(),
+        64,
+        Auto::default(),
+    )?;
+    assert!(writer.can_accept(ciphertext.len() as u64).is_ok());
+    assert!(writer.can_accept(overhead + 64 + 2 * tag_len - 1).is_ok());
+    writer.write_all(&ciphertext[..Version::MAGIC.len() + 1])?;
+    assert!(writer.can_accept(ciphertext.len() as u64).is_ok());
+    assert!(writer.can_accept(overhead + 64 + 2 * tag_len - 1).is_err());
+    writer.write_all(&ciphertext[Version::MAGIC.len() + 1..])?;
+    writer.close()?;
+
+    // A full fragment followed by an empty final fragment.
+    let algorithm = AEAD::new(&[0; 32]);
+    let mut framing = Sio::default();
+    framing.init(&[0; 32], &algorithm, nonce.as_ref(), &[])?;
+    let mut ciphertext = vec![0; 64 + 2 * AEAD::TAG_LEN];
+    let (full, last) = ciphertext.split_at_mut(64 + AEAD::TAG_LEN);
+    let (nonce_0, aad_0) = framing.next(&mut AEAD::new(&[0; 32]), false)?;
+    algorithm.seal_in_place(nonce_0, aad_0, full)?;
+    let (nonce_1, aad_1) = framing.next(&mut AEAD::new(&[0; 32]), true)?;
+    algorithm.seal_in_place(nonce_1, aad_1, last)?;
+
+    let mut plaintext = Vec::default();
+    let mut writer = DecWriter::with_buffer_size(&mut plaintext, &key, nonce, Aad::empty(), 64)?;
+    assert!(writer.can_accept(ciphertext.len() as u64).is_ok());
+    writer.write_all(&ciphertext)?;
+    writer.close()?;
+    assert_eq!(plaintext, [0; 64]);
+
+    // The framing records the length of the stream.
+    let mut ciphertext = Vec::default();
+    let mut writer = EncWriter::with_framing(
+        &mut ciphertext,
+        &key,
+        nonce,
+        Aad::empty(),
+        64,
+        Delimited::new(Sio::default(), 100),
+    )?;
+    writer.write_all(&[0; 100])?;
+    writer.close()?;
+
+    let mut plaintext = Vec::default();
+    let mut writer = DecWriter::with_framing(
+        &mut plaintext,
+        &key,
+        nonce,
+        Aad::empty(),
+        64,
+        Delimited::<Sio>::default(),
+    )?;
+    writer.write_all(&ciphertext[..8])?;
+    assert!(writer.can_accept(ciphertext.len() as u64).is_ok());
+    assert!(writer.can_accept(ciphertext.len() as u64 - 1).is_err());
+    assert!(writer.can_accept(ciphertext.len() as u64 + 1).is_err());
+    writer.write_all(&ciphertext[8..])?;
+    writer.close()?;
+    Ok(())
+}
+
+fn fragment_aad_encrypt<C>(data: &[u8], callback: C) -> io::Result<Vec<u8>>
+where
+    C: FnMut(u32) -> Vec<u8> + Clone,
+{
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let mut ciphertext = Vec::default();
+    let mut writer = EncWriter::with_framing(
+        &mut ciphertext,
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::from(b"Some authenticated data".as_ref()),
+        64,
+        FragmentAad::new(Sio::default(), callback),
+    )?;
+    writer.write_all(data).and_then(|()| writer.close())?;
+    Ok(ciphertext)
+}
+
+fn fragment_aad_decrypt<C>(ciphertext: &[u8], callback: C) -> io::Result<Vec<u8>>
+where
+    C: FnMut(u32) -> Vec<u8> + Clone,
+{
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let mut plaintext = Vec::default();
+    let mut writer = DecWriter::with_framing(
+        &mut plaintext,
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::from(b"Some authenticated data".as_ref()),
+        64,
+        FragmentAad::new(Sio::default(), callback),
+    )?;
+    writer.write_all(ciphertext).and_then(|()| writer.close())?;
+    Ok(plaintext)
+}
+
+#[test]
+fn fragment_aad() -> io::Result<()> {
+    let data: Vec<u8> = (0..5 * 64 + 7).map(|i| i as u8).collect();
+    let manifest = |index: u32| format!("fragment {}", index).into_bytes();
+    let ciphertext = fragment_aad_encrypt(&data, manifest)?;
+    assert_eq!(fragment_aad_decrypt(&ciphertext, manifest)?, data);
+
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let mut plaintext = Vec::default();
+    DecReader::with_framing(
+        ciphertext.as_slice(),
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::from(b"Some authenticated data".as_ref()),
+        64,
+        FragmentAad::new(Sio::default(), manifest),
+    )?
+    .read_to_end(&mut plaintext)?;
+    assert_eq!(plaintext, data);
+
+    // Even without any additional data, the length of the data is
+    // authenticated. Hence, the stream is not a `Sio` stream.
+    let ciphertext = fragment_aad_encrypt(&data, |_| Vec::default())?;
+    let mut expected = Vec::default();
+    let mut writer = EncWriter::with_buffer_size(
+        &mut expected,
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::from(b"Some authenticated data".as_ref()),
+        64,
+    )?;
+    writer.write_all(&data).and_then(|()| writer.close())?;
+    assert_ne!(ciphertext, expected);
+    Ok(())
+}
+
+#[test]
+fn fragment_aad_mismatch() -> io::Result<()> {
+    let data = [7; 5 * 64 + 7];
+    let ciphertext = fragment_aad_encrypt(&data, |index| vec![index as u8])?;
+    for fragment in 0..6 {
+        let result = fragment_aad_decrypt(&ciphertext, move |index| {
+            if index == fragment {
+                vec![0xff]
+            } else {
+                vec![index as u8]
+            }
+        });
+        let err = result.unwrap_err();
+        assert!(err.get_ref().is_some_and(|e| e.is::<NotAuthentic>()));
+    }
+    assert!(fragment_aad_decrypt(&ciphertext, |_| Vec::default()).is_err());
+    Ok(())
+}
+
+#[test]
+fn fragment_aad_shifted_split() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let data = [7; 2 * 64 + 7];
+
+    let mut ciphertext = Vec::default();
+    let framing = FragmentAad::new(FragmentAad::new(Sio::default(), |_| "ab"), |_| "c");
+    let mut writer = EncWriter::with_framing(
+        &mut ciphertext,
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+        64,
+        framing,
+    )?;
+    writer.write_all(&data).and_then(|()| writer.close())?;
+
+    // The same bytes split at a different position must not authenticate.
+    let mut plaintext = Vec::default();
+    let framing = FragmentAad::new(FragmentAad::new(Sio::default(), |_| "a"), |_| "bc");
+    let mut writer = DecWriter::with_framing(
+        &mut plaintext,
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+        64,
+        framing,
+    )?;
+    let err = writer
+        .write_all(&ciphertext)
+        .and_then(|()| writer.close())
+        .unwrap_err();
+    assert!(err.get_ref().is_some_and(|e| e.is::<NotAuthentic>()));
+    Ok(())
+}
@@ -0,0 +1,60 @@
+// Copyright (c) 2019 Andreas Auernhammer. All rights reserved.
+// Use of this source code is governed by a license that can be
+// found in the LICENSE file.
+
+use sio::*;
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+
+#[cfg(feature = "aesgcm")]
+#[allow(clippy::upper_case_acronyms)]
+type AEAD = AES_256_GCM;
+
+#[cfg(not(feature = "aesgcm"))]
+#[allow(clippy::upper_case_acronyms)]
+type AEAD = CHACHA20_POLY1305;
+
+// The hook is process-wide. Hence, all checks are part of one test.
+#[test]
+fn unclosed_hook() {
+    let dropped = Arc::new(Mutex::new(Vec::default()));
+    let events = dropped.clone();
+    set_unclosed_hook(move |writer| events.lock().unwrap().push(writer.type_name()));
+
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let mut writer = EncWriter::new(
+        Vec::default(),
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+    );
+    writer.write_all(b"Hello World").unwrap();
+    drop(writer);
+
+    let writer = DecWriter::new(
+        Vec::default(),
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+    );
+    drop(writer);
+
+    // A closed writer does not trigger the hook.
+    let writer = EncWriter::new(
+        Vec::default(),
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+    );
+    writer.close().unwrap();
+
+    {
+        let dropped = dropped.lock().unwrap();
+        assert_eq!(dropped.len(), 2);
+        assert!(dropped[0].contains("EncWriter"));
+        assert!(dropped[1].contains("DecWriter"));
+    }
+
+    assert!(take_unclosed_hook().is_some());
+    assert!(take_unclosed_hook().is_none());
+}
@@ -0,0 +1,186 @@
+// Copyright (c) 2019 Andreas Auernhammer. All rights reserved.
+// Use of this source code is governed by a license that can be
+// found in the LICENSE file.
+
+#![cfg(feature = "x25519")]
+
+use sio::hpke::{self, Context, HEADER_LEN};
+use sio::x25519::{PublicKey, SecretKey};
+use sio::*;
+use std::io::{self, Read, Write};
+
+#[cfg(feature = "aesgcm")]
+#[allow(clippy::upper_case_acronyms)]
+type AEAD = AES_256_GCM;
+
+#[cfg(not(feature = "aesgcm"))]
+#[allow(clippy::upper_case_acronyms)]
+type AEAD = CHACHA20_POLY1305;
+
+const INFO: &[u8] = b"hpke_tests";
+
+fn hex(s: &str) -> Vec<u8> {
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+        .collect()
+}
+
+fn encrypt(data: &[u8], recipient: &PublicKey, sender: Option<&SecretKey>) -> io::Result<Vec<u8>> {
+    let mut ciphertext = Vec::default();
+    let mut writer = match sender {
+        Some(sender) => {
+            hpke::seal_auth::<AEAD, _>(&mut ciphertext, recipient, sender, INFO, Aad::empty())?
+        }
+        None => hpke::seal::<AEAD, _>(&mut ciphertext, recipient, INFO, Aad::empty())?,
+    };
+    writer.write_all(data)?;
+    writer.close()?;
+    Ok(ciphertext)
+}
+
+fn decrypt(
+    ciphertext: &[u8],
+    secret_key: &SecretKey,
+    sender: Option<&PublicKey>,
+    info: &[u8],
+) -> io::Result<Vec<u8>> {
+    let mut reader = match sender {
+        Some(sender) => {
+            hpke::open_auth::<AEAD, _>(ciphertext, secret_key, sender, info, Aad::empty())?
+        }
+        None => hpke::open::<AEAD, _>(ciphertext, secret_key, info, Aad::empty())?,
+    };
+    let mut plaintext = Vec::default();
+    reader.read_to_end(&mut plaintext)?;
+    Ok(plaintext)
+}
+
+#[test]
+fn hpke_base() -> io::Result<()> {
+    let secret_key = SecretKey::from_bytes([7; 32]);
+    for size in &[0, 1, BUF_SIZE, 3 * BUF_SIZE + 1] {
+        let data: Vec<u8> = (0..*size).map(|i| i as u8).collect();
+        let ciphertext = encrypt(&data, &secret_key.public_key(), None)?;
+        assert_eq!(decrypt(&ciphertext[..], &secret_key, None, INFO)?, data);
+    }
+    Ok(())
+}
+
+#[test]
+fn hpke_auth() -> io::Result<()> {
+    let secret_key = SecretKey::from_bytes([7; 32]);
+    let sender = SecretKey::from_bytes([8; 32]);
+    for size in &[0, 1, BUF_SIZE, 3 * BUF_SIZE + 1] {
+        let data: Vec<u8> = (0..*size).map(|i| i as u8).collect();
+        let ciphertext = encrypt(&data, &secret_key.public_key(), Some(&sender))?;
+        let plaintext = decrypt(&ciphertext, &secret_key, Some(&sender.public_key()), INFO)?;
+        assert_eq!(plaintext, data);
+    }
+    Ok(())
+}
+
+#[test]
+fn hpke_wrong_info() -> io::Result<()> {
+    let secret_key = SecretKey::from_bytes([7; 32]);
+    let ciphertext = encrypt(b"Hello World", &secret_key.public_key(), None)?;
+    let err = decrypt(&ciphertext, &secret_key, None, b"other").unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    Ok(())
+}
+
+#[test]
+fn hpke_wrong_sender() -> io::Result<()> {
+    let secret_key = SecretKey::from_bytes([7; 32]);
+    let sender = SecretKey::from_bytes([8; 32]);
+    let ciphertext = encrypt(b"Hello World", &secret_key.public_key(), Some(&sender))?;
+
+    let other = SecretKey::from_bytes([9; 32]).public_key();
+    assert!(decrypt(&ciphertext, &secret_key, Some(&other), INFO).is_err());
+
+    // A stream sent in base mode is not authenticated by any sender.
+    assert!(decrypt(&ciphertext, &secret_key, None, INFO).is_err());
+    Ok(())
+}
+
+#[test]
+fn hpke_modified_header() -> io::Result<()> {
+    let secret_key = SecretKey::from_bytes([7; 32]);
+    let mut ciphertext = encrypt(b"Hello World", &secret_key.public_key(), None)?;
+    ciphertext[HEADER_LEN - 1] ^= 1;
+    assert!(decrypt(&ciphertext, &secret_key, None, INFO).is_err());
+    Ok(())
+}
+
+// The test vector of RFC 9180, Appendix A.7.1: DHKEM(X25519, HKDF-SHA256),
+// HKDF-SHA256, export-only AEAD in base mode.
+#[test]
+fn hpke_base_test_vector() -> io::Result<()> {
+    let mut sk_em = [0; 32];
+    sk_em.copy_from_slice(&hex(
+        "095182b502f1f91f63ba584c7c3ec473d617b8b4c2cec3fad5af7fa6748165ed",
+    ));
+    let mut sk_rm = [0; 32];
+    sk_rm.copy_from_slice(&hex(
+        "33d196c830a12f9ac65d6e565a590d80f04ee9b19c83c87f2c170d972a812848",
+    ));
+    let ephemeral = SecretKey::from_bytes(sk_em);
+    let secret_key = SecretKey::from_bytes(sk_rm);
+    let info = hex("4f6465206f6e2061204772656369616e2055726e");
+
+    let enc = ephemeral.public_key();
+    assert_eq!(
+        enc.as_ref()[..],
+        hex("e5e8f9bfff6c2f29791fc351d2c25ce1299aa5eaca78a757c0b4fb4bcd830918")[..]
+    );
+    assert_eq!(
+        secret_key.public_key().as_ref()[..],
+        hex("194141ca6c3c3beb4792cd97ba0ea1faff09d98435012345766ee33aae2d7664")[..]
+    );
+    assert_eq!(
+        hpke::decap(&enc, &secret_key)?[..],
+        hex("e81716ce8f73141d4f25ee9098efc968c91e5b8ce52ffff59d64039e82918b66")[..]
+    );
+
+    let context = Context::base(&enc, &secret_key, &info)?;
+    assert_eq!(
+        context.exporter_secret()[..],
+        hex("79dc8e0509cf4a3364ca027e5a0138235281611ca910e435e8ed58167c72f79b")[..]
+    );
+    for (exporter_context, value) in &[
+        (
+            "",
+            "7a36221bd56d50fb51ee65edfd98d06a23c4dc87085aa5866cb7087244bd2a36",
+        ),
+        (
+            "00",
+            "d5535b87099c6c3ce80dc112a2671c6ec8e811a2f284f948cec6dd1708ee33f0",
+        ),
+        (
+            "54657374436f6e74657874",
+            "ffaabc85a776136ca0c378e5d084c9140ab552b78f039d2e8775f26efff4c70e",
+        ),
+    ] {
+        let mut exported = [0; 32];
+        context.export(&hex(exporter_context), &mut exported);
+        assert_eq!(exported[..], hex(value)[..]);
+    }
+
+    // The stream key is the secret exported for the EXPORTER_CONTEXT.
+    let mut key = [0; 32];
+    context.export(hpke::EXPORTER_CONTEXT, &mut key);
+    let mut ciphertext = enc.as_ref().to_vec();
+    let mut writer = EncWriter::new(
+        &mut ciphertext,
+        &Key::<AEAD>::new(key),
+        Nonce::zero(),
+        Aad::empty(),
+    );
+    writer.write_all(b"Hello World")?;
+    writer.close()?;
+    assert_eq!(
+        decrypt(&ciphertext, &secret_key, None, &info)?,
+        b"Hello World"
+    );
+    Ok(())
+}
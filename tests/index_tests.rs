@@ -0,0 +1,128 @@
+// Copyright (c) 2019 Andreas Auernhammer. All rights reserved.
+// Use of this source code is governed by a license that can be
+// found in the LICENSE file.
+
+use sio::framing::{Framing, Sio, V1};
+use sio::index::{Index, IndexWriter};
+use sio::*;
+use std::io::{self, Write};
+
+#[cfg(feature = "aesgcm")]
+#[allow(clippy::upper_case_acronyms)]
+type AEAD = AES_256_GCM;
+
+#[cfg(not(feature = "aesgcm"))]
+#[allow(clippy::upper_case_acronyms)]
+type AEAD = CHACHA20_POLY1305;
+
+#[test]
+fn index() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let fragment_len = 64 + AEAD::TAG_LEN;
+    for size in &[0, 1, 63, 64, 65, 128, 1000] {
+        let data: Vec<u8> = (0..*size).map(|i| i as u8).collect();
+        for header in &[0, V1::default().header().len()] {
+            let mut ciphertext = Vec::default();
+            let mut sidecar = Vec::default();
+            if *header == 0 {
+                let mut writer = EncWriter::with_framing(
+                    IndexWriter::<AEAD, _, _>::with_framing(
+                        &mut ciphertext,
+                        &mut sidecar,
+                        64,
+                        &Sio::default(),
+                    ),
+                    &key,
+                    Nonce::new([0; Nonce::<AEAD>::SIZE]),
+                    Aad::empty(),
+                    64,
+                    Sio::default(),
+                )?;
+                writer.write_all(&data).and_then(|()| writer.close())?;
+            } else {
+                let mut writer = EncWriter::with_framing(
+                    IndexWriter::<AEAD, _, _>::with_framing(
+                        &mut ciphertext,
+                        &mut sidecar,
+                        64,
+                        &V1::default(),
+                    ),
+                    &key,
+                    Nonce::new([0; Nonce::<AEAD>::SIZE]),
+                    Aad::empty(),
+                    64,
+                    V1::default(),
+                )?;
+                writer.write_all(&data).and_then(|()| writer.close())?;
+            }
+            let index = Index::read_from(sidecar.as_slice())?;
+            assert_eq!(index.buf_size(), 64);
+            assert_eq!(index.plaintext_len(), *size as u64);
+            assert_eq!(index.fragments(), (*size as u64).div_ceil(64).max(1));
+
+            let mut offset = *header as u64;
+            for i in 0..index.fragments() {
+                let entry = index.get(i).unwrap();
+                assert_eq!(entry.index(), i);
+                assert_eq!(entry.offset(), offset);
+                offset += u64::from(entry.len());
+            }
+            assert_eq!(offset, ciphertext.len() as u64);
+            assert!(index.get(index.fragments()).is_none());
+
+            for i in (0..*size).step_by(7) {
+                let entry = index.locate(i as u64).unwrap();
+                assert_eq!(entry.index(), i as u64 / 64);
+                assert!(entry.offset() + u64::from(entry.len()) <= ciphertext.len() as u64);
+                assert!(u64::from(entry.len()) <= fragment_len as u64);
+            }
+            assert!(index.locate(*size as u64).is_none());
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn index_tag_hashes() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+
+    let mut sidecar = Vec::default();
+    let mut writer = EncWriter::with_buffer_size(
+        IndexWriter::<AEAD, _, _>::with_framing(io::sink(), &mut sidecar, 64, &Sio::default()),
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+        64,
+    )?;
+    writer.write_all(&[0; 1000]).and_then(|()| writer.close())?;
+    let index = Index::read_from(sidecar.as_slice())?;
+
+    let mut sidecar = Vec::default();
+    let mut writer = EncWriter::with_buffer_size(
+        IndexWriter::<AEAD, _, _>::with_framing(io::sink(), &mut sidecar, 64, &Sio::default()),
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+        64,
+    )?;
+    writer.write_all(&[1; 1000]).and_then(|()| writer.close())?;
+    let other = Index::read_from(sidecar.as_slice())?;
+
+    for i in 0..index.fragments() {
+        let entry = index.get(i).unwrap();
+        assert_ne!(entry.tag_hash(), other.get(i).unwrap().tag_hash());
+    }
+    Ok(())
+}
+
+#[test]
+fn index_malformed() {
+    let err = Index::read_from([0; 15].as_ref()).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+    let mut sidecar = vec![0; 16];
+    sidecar[7] = 1; // One fragment but no entry.
+    sidecar[11] = 64;
+    let err = Index::read_from(sidecar.as_slice()).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+}
@@ -0,0 +1,81 @@
+// Copyright (c) 2019 Andreas Auernhammer. All rights reserved.
+// Use of this source code is governed by a license that can be
+// found in the LICENSE file.
+
+use sio::framing::{Sio, Version, V1};
+use sio::*;
+use std::io::{self, Write};
+
+#[cfg(feature = "aesgcm")]
+#[allow(clippy::upper_case_acronyms)]
+type AEAD = AES_256_GCM;
+
+#[cfg(not(feature = "aesgcm"))]
+#[allow(clippy::upper_case_acronyms)]
+type AEAD = CHACHA20_POLY1305;
+
+#[test]
+fn inspect() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let layout = Layout::of::<AEAD>(1024)?;
+    for size in &[0, 1, 1024, 5000] {
+        let data = vec![0; *size];
+
+        let mut ciphertext = Vec::default();
+        let mut writer = EncWriter::with_framing(
+            &mut ciphertext,
+            &key,
+            Nonce::new([0; Nonce::<AEAD>::SIZE]),
+            Aad::empty(),
+            1024,
+            Sio::default(),
+        )?;
+        writer.write_all(&data).and_then(|()| writer.close())?;
+
+        let info = sio::inspect(ciphertext.as_slice())?;
+        assert_eq!(info.version(), Version::Legacy);
+        assert_eq!(info.header_len(), 0);
+        assert_eq!(info.len(), ciphertext.len() as u64);
+        assert_eq!(info.plaintext_len(&layout), Some(*size as u64));
+
+        let mut ciphertext = Vec::default();
+        let mut writer = EncWriter::with_framing(
+            &mut ciphertext,
+            &key,
+            Nonce::new([0; Nonce::<AEAD>::SIZE]),
+            Aad::empty(),
+            1024,
+            V1::default(),
+        )?;
+        writer.write_all(&data).and_then(|()| writer.close())?;
+
+        let info = sio::inspect(ciphertext.as_slice())?;
+        assert_eq!(info.version(), Version::V1);
+        assert_eq!(info.header_len(), Version::V1.header().len());
+        assert_eq!(info.len(), ciphertext.len() as u64);
+        assert_eq!(info.plaintext_len(&layout), Some(*size as u64));
+        assert!(info.algorithm().is_none());
+        assert!(info.buf_size().is_none());
+        assert!(info.key_id().is_none());
+    }
+    Ok(())
+}
+
+#[test]
+fn inspect_unsupported_version() {
+    let mut ciphertext = Version::MAGIC.to_vec();
+    ciphertext.push(0xff);
+    ciphertext.extend_from_slice(&[0; 64]);
+
+    let err = sio::inspect(ciphertext.as_slice()).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn inspect_short() -> io::Result<()> {
+    let info = sio::inspect(&[1, 2, 3][..])?;
+    assert_eq!(info.version(), Version::Legacy);
+    assert_eq!(info.len(), 3);
+    assert_eq!(info.plaintext_len(&Layout::of::<AEAD>(1024)?), None);
+    Ok(())
+}
@@ -0,0 +1,64 @@
+// Copyright (c) 2019 Andreas Auernhammer. All rights reserved.
+// Use of this source code is governed by a license that can be
+// found in the LICENSE file.
+
+#![cfg(feature = "jwk")]
+
+use sio::*;
+
+// The base64url encoding of the bytes 0..32.
+const K: &str = "AAECAwQFBgcICQoLDA0ODxAREhMUFRYXGBkaGxwdHh8";
+
+fn jwk(fields: &str) -> String {
+    format!(r#"{{"kty":"oct",{}"k":"{}"}}"#, fields, K)
+}
+
+#[test]
+fn from_jwk() {
+    let expected: Vec<u8> = (0..32).collect();
+
+    let key: Key<CHACHA20_POLY1305> = Key::from_jwk(&jwk(r#""alg":"C20P","#)).unwrap();
+    assert_eq!(&key.as_ref()[..], expected.as_slice());
+
+    let key: Key<CHACHA20_POLY1305> = Key::from_jwk(&jwk(
+        r#""alg":"C20P","use":"enc","key_ops":["encrypt","decrypt"],"kid":"1","#,
+    ))
+    .unwrap();
+    assert_eq!(&key.as_ref()[..], expected.as_slice());
+}
+
+#[cfg(feature = "aesgcm")]
+#[test]
+fn from_jwk_aesgcm() {
+    assert!(Key::<AES_256_GCM>::from_jwk(&jwk(r#""alg":"A256GCM","#)).is_ok());
+    assert_eq!(
+        Key::<AES_256_GCM>::from_jwk(&jwk(r#""alg":"C20P","#)).err(),
+        Some(Invalid::Key)
+    );
+}
+
+#[test]
+fn from_jwk_invalid() {
+    let invalid = [
+        "".to_string(),
+        "[]".to_string(),
+        jwk(""),                             // missing alg
+        jwk(r#""alg":"A256GCM","#),          // wrong cipher
+        jwk(r#""alg":"A128KW","#),           // key wrapping
+        jwk(r#""alg":"C20P","use":"sig","#), // signing key
+        jwk(r#""alg":"C20P","key_ops":["sign"],"#),
+        jwk(r#""alg":7,"#),
+        r#"{"kty":"oct","alg":"C20P","k":"AAEC"}"#.to_string(), // too short
+        format!(r#"{{"kty":"oct","alg":"C20P","k":"{}="}}"#, K), // padded
+        format!(r#"{{"kty":"RSA","alg":"C20P","k":"{}"}}"#, K),
+        r#"{"kty":"oct","alg":"C20P"}"#.to_string(),
+    ];
+    for jwk in invalid.iter() {
+        assert_eq!(
+            Key::<CHACHA20_POLY1305>::from_jwk(jwk).err(),
+            Some(Invalid::Key),
+            "{}",
+            jwk
+        );
+    }
+}
@@ -0,0 +1,78 @@
+// Copyright (c) 2019 Andreas Auernhammer. All rights reserved.
+// Use of this source code is governed by a license that can be
+// found in the LICENSE file.
+
+use sio::*;
+use std::io::{self, Write};
+
+#[cfg(feature = "aesgcm")]
+#[allow(clippy::upper_case_acronyms)]
+type AEAD = AES_256_GCM;
+
+#[cfg(not(feature = "aesgcm"))]
+#[allow(clippy::upper_case_acronyms)]
+type AEAD = CHACHA20_POLY1305;
+
+#[test]
+fn layout_len() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    for buf_size in &[1, 7, 64] {
+        let layout = Layout::of::<AEAD>(*buf_size)?;
+        for size in 0..4 * buf_size + 2 {
+            let mut ciphertext = Vec::default();
+            let mut writer = EncWriter::with_buffer_size(
+                &mut ciphertext,
+                &key,
+                Nonce::new([0; Nonce::<AEAD>::SIZE]),
+                Aad::empty(),
+                *buf_size,
+            )?;
+            writer.write_all(&vec![0; size])?;
+            writer.close()?;
+
+            assert_eq!(
+                layout.ciphertext_len(size as u64),
+                Some(ciphertext.len() as u64)
+            );
+            assert_eq!(
+                layout.plaintext_len(ciphertext.len() as u64),
+                Some(size as u64)
+            );
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn layout_invalid_len() {
+    let layout = Layout::new(64, 16).unwrap();
+    assert_eq!(layout.plaintext_len(0), None);
+    assert_eq!(layout.plaintext_len(15), None);
+    assert_eq!(layout.plaintext_len(16), Some(0));
+    assert_eq!(layout.plaintext_len(80 + 15), None);
+    assert_eq!(layout.plaintext_len(80 + 16), None);
+    assert_eq!(layout.plaintext_len(80 + 17), Some(65));
+    assert_eq!(layout.ciphertext_len(u64::MAX), None);
+
+    assert!(Layout::new(0, 16).is_err());
+    assert!(Layout::new(MAX_BUF_SIZE + 1, 16).is_err());
+}
+
+#[test]
+fn layout_offsets() {
+    let layout = Layout::new(64, 16).unwrap();
+    for offset in 0..1000 {
+        let (index, ciphertext_offset) = layout.plaintext_to_ciphertext(offset).unwrap();
+        assert_eq!(index, offset / 64);
+        assert_eq!(
+            layout.ciphertext_to_plaintext(ciphertext_offset),
+            Some((index, offset))
+        );
+    }
+    for offset in 64..80 {
+        assert_eq!(layout.ciphertext_to_plaintext(offset), None);
+    }
+    assert_eq!(layout.fragment_offset(3), Some(240));
+    assert_eq!(layout.fragment_offset(u64::MAX), None);
+    assert_eq!(layout.plaintext_to_ciphertext(u64::MAX), None);
+}
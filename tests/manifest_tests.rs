@@ -0,0 +1,97 @@
+// Copyright (c) 2019 Andreas Auernhammer. All rights reserved.
+// Use of this source code is governed by a license that can be
+// found in the LICENSE file.
+
+use sio::manifest::Manifest;
+use sio::*;
+use std::io::{self, Cursor, Read};
+
+#[cfg(feature = "aesgcm")]
+#[allow(clippy::upper_case_acronyms)]
+type AEAD = AES_256_GCM;
+
+#[cfg(not(feature = "aesgcm"))]
+#[allow(clippy::upper_case_acronyms)]
+type AEAD = CHACHA20_POLY1305;
+
+fn archive(master_key: &Key<AEAD>, files: &[(&str, Vec<u8>)]) -> io::Result<(Manifest, Vec<u8>)> {
+    let mut archive = Vec::default();
+    let mut manifest = Manifest::default();
+    for (name, content) in files {
+        let offset = archive.len() as u64;
+        let entry = manifest.append(
+            master_key,
+            *name,
+            "archive",
+            offset,
+            content.as_slice(),
+            &mut archive,
+        )?;
+        assert_eq!(entry.offset() + entry.len(), archive.len() as u64);
+    }
+    Ok((manifest, archive))
+}
+
+#[test]
+fn manifest() -> io::Result<()> {
+    let master_key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let files = vec![
+        ("empty", vec![]),
+        ("small", vec![1; 100]),
+        ("large", (0..3 * BUF_SIZE + 7).map(|i| i as u8).collect()),
+    ];
+    let (manifest, archive) = archive(&master_key, &files)?;
+
+    let mut encrypted = Vec::default();
+    manifest.write_to(&master_key, &mut encrypted)?;
+    let decrypted = Manifest::read_from(&master_key, encrypted.as_slice())?;
+    assert_eq!(decrypted, manifest);
+    assert_eq!(decrypted.len(), files.len());
+
+    for (name, content) in &files {
+        let mut plaintext = Vec::default();
+        decrypted
+            .open(&master_key, name, Cursor::new(&archive))?
+            .read_to_end(&mut plaintext)?;
+        assert_eq!(&plaintext, content);
+    }
+
+    match decrypted.open(&master_key, "missing", Cursor::new(&archive)) {
+        Err(err) => assert_eq!(err.kind(), io::ErrorKind::NotFound),
+        Ok(_) => panic!("opened a missing entry"),
+    }
+    Ok(())
+}
+
+#[test]
+fn manifest_not_authentic() -> io::Result<()> {
+    let master_key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let (manifest, _) = archive(&master_key, &[("a", vec![0; 10])])?;
+
+    let mut encrypted = Vec::default();
+    manifest.write_to(&master_key, &mut encrypted)?;
+    let last = encrypted.len() - 1;
+    encrypted[last] ^= 1;
+    assert!(Manifest::read_from(&master_key, encrypted.as_slice()).is_err());
+
+    let other_key: Key<AEAD> = Key::new([1; Key::<AEAD>::SIZE]);
+    encrypted[last] ^= 1;
+    assert!(Manifest::read_from(&other_key, encrypted.as_slice()).is_err());
+    Ok(())
+}
+
+#[test]
+fn manifest_swapped_streams() -> io::Result<()> {
+    let master_key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let (manifest, mut archive) = archive(&master_key, &[("a", vec![0; 10]), ("b", vec![1; 10])])?;
+
+    let len = manifest.get("a").unwrap().len() as usize;
+    assert_eq!(manifest.get("b").unwrap().offset() as usize, len);
+    let (a, b) = archive.split_at_mut(len);
+    a.swap_with_slice(b);
+
+    let mut plaintext = Vec::default();
+    let mut reader = manifest.open(&master_key, "a", Cursor::new(&archive))?;
+    assert!(reader.read_to_end(&mut plaintext).is_err());
+    Ok(())
+}
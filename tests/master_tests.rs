@@ -0,0 +1,89 @@
+// Copyright (c) 2019 Andreas Auernhammer. All rights reserved.
+// Use of this source code is governed by a license that can be
+// found in the LICENSE file.
+
+use sio::*;
+use std::io::{self, Read, Write};
+
+#[cfg(feature = "aesgcm")]
+#[allow(clippy::upper_case_acronyms)]
+type AEAD = AES_256_GCM;
+
+#[cfg(not(feature = "aesgcm"))]
+#[allow(clippy::upper_case_acronyms)]
+type AEAD = CHACHA20_POLY1305;
+
+#[test]
+fn master_key() -> io::Result<()> {
+    let master_key: Key<AEAD> = Key::new([7; Key::<AEAD>::SIZE]);
+    for size in &[0, 1, BUF_SIZE, 3 * BUF_SIZE + 1] {
+        let data: Vec<u8> = (0..*size).map(|i| i as u8).collect();
+
+        let mut ciphertext = Vec::default();
+        let mut writer = EncWriter::with_master_key(&mut ciphertext, &master_key, Aad::empty())?;
+        writer.write_all(&data).and_then(|()| writer.close())?;
+
+        let mut plaintext = Vec::default();
+        let mut reader =
+            DecReader::with_master_key(ciphertext.as_slice(), &master_key, Aad::empty())?;
+        reader.read_to_end(&mut plaintext)?;
+        assert_eq!(plaintext, data);
+    }
+    Ok(())
+}
+
+#[test]
+fn master_key_unique_keys() -> io::Result<()> {
+    let master_key: Key<AEAD> = Key::new([7; Key::<AEAD>::SIZE]);
+
+    let mut first = Vec::default();
+    let mut writer = EncWriter::with_master_key(&mut first, &master_key, Aad::empty())?;
+    writer
+        .write_all(b"Hello World")
+        .and_then(|()| writer.close())?;
+
+    let mut second = Vec::default();
+    let mut writer = EncWriter::with_master_key(&mut second, &master_key, Aad::empty())?;
+    writer
+        .write_all(b"Hello World")
+        .and_then(|()| writer.close())?;
+
+    assert_ne!(first[..KEY_ID_LEN], second[..KEY_ID_LEN]);
+    assert_ne!(first[KEY_ID_LEN..], second[KEY_ID_LEN..]);
+    Ok(())
+}
+
+#[test]
+fn master_key_modified_key_id() -> io::Result<()> {
+    let master_key: Key<AEAD> = Key::new([7; Key::<AEAD>::SIZE]);
+    let mut ciphertext = Vec::default();
+    let mut writer = EncWriter::with_master_key(&mut ciphertext, &master_key, Aad::empty())?;
+    writer
+        .write_all(b"Hello World")
+        .and_then(|()| writer.close())?;
+
+    ciphertext[0] ^= 1;
+    let err = DecReader::with_master_key(ciphertext.as_slice(), &master_key, Aad::empty())
+        .and_then(|mut reader| reader.read_to_end(&mut Vec::default()))
+        .unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    Ok(())
+}
+
+#[test]
+fn master_key_wrong_key() -> io::Result<()> {
+    let master_key: Key<AEAD> = Key::new([7; Key::<AEAD>::SIZE]);
+    let mut ciphertext = Vec::default();
+    let mut writer = EncWriter::with_master_key(&mut ciphertext, &master_key, Aad::empty())?;
+    writer
+        .write_all(b"Hello World")
+        .and_then(|()| writer.close())?;
+
+    let wrong_key: Key<AEAD> = Key::new([8; Key::<AEAD>::SIZE]);
+    assert!(
+        DecReader::with_master_key(ciphertext.as_slice(), &wrong_key, Aad::empty())
+            .and_then(|mut reader| reader.read_to_end(&mut Vec::default()))
+            .is_err()
+    );
+    Ok(())
+}
@@ -0,0 +1,128 @@
+// Copyright (c) 2019 Andreas Auernhammer. All rights reserved.
+// Use of this source code is governed by a license that can be
+// found in the LICENSE file.
+
+use sio::*;
+use std::io::{self, Write};
+
+#[cfg(feature = "aesgcm")]
+#[allow(clippy::upper_case_acronyms)]
+type AEAD = AES_256_GCM;
+
+#[cfg(not(feature = "aesgcm"))]
+#[allow(clippy::upper_case_acronyms)]
+type AEAD = CHACHA20_POLY1305;
+
+#[test]
+fn part_layout() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let fragment_len = 64 + AEAD::TAG_LEN as u64;
+    let layout = PartLayout::<AEAD>::with_buffer_size(3 * fragment_len + 10, 64)?;
+    assert_eq!(layout.fragments_per_part(), 3);
+    assert_eq!(layout.part_size(), 3 * fragment_len);
+    assert_eq!(layout.part_plaintext_size(), 3 * 64);
+
+    for size in &[0, 1, 64, 191, 192, 193, 1000] {
+        let mut ciphertext = Vec::default();
+        let mut writer = EncWriter::with_buffer_size(
+            &mut ciphertext,
+            &key,
+            Nonce::new([0; Nonce::<AEAD>::SIZE]),
+            Aad::empty(),
+            layout.buf_size(),
+        )?;
+        writer.write_all(&vec![0; *size])?;
+        writer.close()?;
+
+        let size = *size as u64;
+        assert_eq!(layout.ciphertext_len(size), Some(ciphertext.len() as u64));
+        let parts: Vec<_> = ciphertext.chunks(layout.part_size() as usize).collect();
+        assert_eq!(layout.parts(size), Some(parts.len() as u64));
+        for (i, part) in parts.iter().enumerate() {
+            assert_eq!(layout.part_len(size, i as u64), Some(part.len() as u64));
+        }
+        assert_eq!(layout.part_len(size, parts.len() as u64), None);
+    }
+    Ok(())
+}
+
+#[test]
+fn part_layout_too_small() {
+    let fragment_len = 64 + AEAD::TAG_LEN as u64;
+    assert!(PartLayout::<AEAD>::with_buffer_size(fragment_len - 1, 64).is_err());
+    assert!(PartLayout::<AEAD>::with_buffer_size(fragment_len, 0).is_err());
+    assert!(PartLayout::<AEAD>::with_buffer_size(fragment_len, 64).is_ok());
+}
+
+// A writer that records the size of every write.
+#[derive(Default)]
+struct Parts(Vec<usize>);
+
+impl Write for &mut Parts {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.push(buf.len());
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Close for &mut Parts {
+    fn close(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn exact_part_layout() {
+    let tag_len = AEAD::TAG_LEN as u64;
+    for part_size in &[5 * 1024 * 1024, 8 * 1024 * 1024, 1_000_000, 100 + tag_len] {
+        let layout = PartLayout::<AEAD>::exact(*part_size).unwrap();
+        assert_eq!(layout.part_size(), *part_size);
+        assert!(layout.buf_size() <= BUF_SIZE);
+        if let Ok(other) = PartLayout::<AEAD>::with_buffer_size(*part_size, layout.buf_size() + 1) {
+            assert_ne!(other.part_size(), *part_size);
+        }
+    }
+    let layout =
+        PartLayout::<AEAD>::exact_with_max_buffer_size(8 * 1024 * 1024, 64 * 1024).unwrap();
+    assert_eq!(layout.fragments_per_part(), 128);
+    assert_eq!(layout.buf_size() as u64, 64 * 1024 - tag_len);
+
+    assert!(PartLayout::<AEAD>::exact(0).is_err());
+    assert!(PartLayout::<AEAD>::exact(tag_len).is_err());
+    assert!(PartLayout::<AEAD>::exact(tag_len + 1).is_ok());
+    assert!(PartLayout::<AEAD>::exact_with_max_buffer_size(1024, 0).is_err());
+    // A prime part size larger than one fragment cannot be split.
+    assert!(PartLayout::<AEAD>::exact(16_411).is_err());
+}
+
+#[test]
+fn exact_part_writes() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let layout = PartLayout::<AEAD>::exact_with_max_buffer_size(1000, 100)?;
+    assert_eq!(layout.part_size(), 1000);
+
+    for size in &[0, 1, 3000, 5000, 10_000] {
+        let mut parts = Parts::default();
+        let mut writer = layout.enc_writer(
+            &mut parts,
+            &key,
+            Nonce::new([0; Nonce::<AEAD>::SIZE]),
+            Aad::empty(),
+        )?;
+        for chunk in vec![0; *size].chunks(77) {
+            writer.write_all(chunk)?;
+        }
+        writer.close()?;
+
+        let size = *size as u64;
+        assert_eq!(layout.parts(size), Some(parts.0.len() as u64));
+        for (i, len) in parts.0.iter().enumerate() {
+            assert_eq!(layout.part_len(size, i as u64), Some(*len as u64));
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,116 @@
+// Copyright (c) 2019 Andreas Auernhammer. All rights reserved.
+// Use of this source code is governed by a license that can be
+// found in the LICENSE file.
+
+use sio::noise::{CipherState, Transport};
+use sio::*;
+use std::io::{self, Read, Write};
+
+#[cfg(feature = "aesgcm")]
+#[allow(clippy::upper_case_acronyms)]
+type AEAD = AES_256_GCM;
+
+#[cfg(not(feature = "aesgcm"))]
+#[allow(clippy::upper_case_acronyms)]
+type AEAD = CHACHA20_POLY1305;
+
+fn send(transport: &mut Transport<AEAD>, data: &[u8]) -> io::Result<Vec<u8>> {
+    let mut message = Vec::default();
+    let mut writer = transport.send().enc_writer(&mut message, Aad::empty())?;
+    writer.write_all(data)?;
+    writer.close()?;
+    Ok(message)
+}
+
+fn recv(transport: &mut Transport<AEAD>, message: &[u8]) -> io::Result<Vec<u8>> {
+    let mut plaintext = Vec::default();
+    let mut reader = transport.recv().dec_reader(message, Aad::empty())?;
+    reader.read_to_end(&mut plaintext)?;
+    Ok(plaintext)
+}
+
+fn transports(prefix: &[u8]) -> (Transport<AEAD>, Transport<AEAD>) {
+    let (c1, c2) = ([1; 32], [2; 32]);
+    (
+        Transport::initiator(Key::new(c1), Key::new(c2), prefix).unwrap(),
+        Transport::responder(Key::new(c1), Key::new(c2), prefix).unwrap(),
+    )
+}
+
+#[test]
+fn duplex() -> io::Result<()> {
+    let (mut initiator, mut responder) = transports(b"noise");
+    for i in 0..4 {
+        let data = vec![i as u8; i * BUF_SIZE + 1];
+        let message = send(&mut initiator, &data)?;
+        assert_eq!(recv(&mut responder, &message)?, data);
+
+        let message = send(&mut responder, &data)?;
+        assert_eq!(recv(&mut initiator, &message)?, data);
+    }
+    assert_eq!(initiator.send().messages(), 4);
+    assert_eq!(responder.recv().messages(), 4);
+    Ok(())
+}
+
+#[test]
+fn distinct_messages() -> io::Result<()> {
+    let (mut initiator, mut responder) = transports(&[]);
+    let first = send(&mut initiator, b"Hello World")?;
+    let second = send(&mut initiator, b"Hello World")?;
+    assert_ne!(first, second);
+
+    // Messages must be received in order.
+    let err = recv(&mut responder, &second).unwrap_err();
+    assert!(err.get_ref().is_some_and(|e| e.is::<NotAuthentic>()));
+    Ok(())
+}
+
+#[test]
+fn rekey() -> io::Result<()> {
+    let (mut initiator, mut responder) = transports(&[]);
+    let message = send(&mut initiator, b"Hello World")?;
+    assert_eq!(recv(&mut responder, &message)?, b"Hello World");
+
+    initiator.send().rekey().unwrap();
+    responder.recv().rekey().unwrap();
+    assert_eq!(initiator.send().messages(), 0);
+    let message = send(&mut initiator, b"Hello World")?;
+    assert_eq!(recv(&mut responder, &message)?, b"Hello World");
+
+    // Only the sender replaces its key.
+    initiator.send().rekey().unwrap();
+    let message = send(&mut initiator, b"Hello World")?;
+    let err = recv(&mut responder, &message).unwrap_err();
+    assert!(err.get_ref().is_some_and(|e| e.is::<NotAuthentic>()));
+    Ok(())
+}
+
+#[test]
+fn exhausted() -> io::Result<()> {
+    let prefix = vec![0; Nonce::<AEAD>::SIZE - 1];
+    let (mut initiator, _) = transports(&prefix);
+    assert_eq!(initiator.send().remaining(), 255);
+    for _ in 0..255 {
+        send(&mut initiator, b"")?;
+    }
+    let err = initiator
+        .send()
+        .enc_writer(Vec::default(), Aad::empty())
+        .err()
+        .unwrap();
+    assert_eq!(err.fragments(), 0);
+
+    initiator.send().rekey().unwrap();
+    assert_eq!(initiator.send().remaining(), 255);
+    Ok(())
+}
+
+#[test]
+fn invalid_prefix() {
+    let prefix = vec![0; Nonce::<AEAD>::SIZE];
+    assert!(matches!(
+        CipherState::<AEAD>::new(Key::new([0; 32]), &prefix),
+        Err(Invalid::Nonce)
+    ));
+}
@@ -0,0 +1,97 @@
+// Copyright (c) 2019 Andreas Auernhammer. All rights reserved.
+// Use of this source code is governed by a license that can be
+// found in the LICENSE file.
+
+#![cfg(feature = "ocb3")]
+
+use sio::*;
+use std::io::{self, Write};
+
+fn seal(ocb: &AES_256_OCB, nonce: u64, aad: &[u8], msg: &[u8]) -> Vec<u8> {
+    let mut n = [0; 12];
+    n[4..].copy_from_slice(&nonce.to_be_bytes());
+    let mut in_out = msg.to_vec();
+    in_out.extend_from_slice(&[0; 16]);
+    ocb.seal_in_place(&n, aad, &mut in_out).unwrap();
+
+    let mut plaintext = in_out.clone();
+    assert_eq!(ocb.open_in_place(&n, aad, &mut plaintext).unwrap(), msg);
+    in_out
+}
+
+// The iterative test of RFC 7253, Appendix A, for AEAD_AES_256_OCB_TAGLEN128.
+#[test]
+fn rfc7253() {
+    let mut key = [0; 32];
+    key[31] = 128;
+    let ocb = AES_256_OCB::new(&key);
+
+    let mut c = Vec::default();
+    for i in 0..128 {
+        let s = vec![0; i];
+        c.extend(seal(&ocb, 3 * i as u64 + 1, &s, &s));
+        c.extend(seal(&ocb, 3 * i as u64 + 2, &[], &s));
+        c.extend(seal(&ocb, 3 * i as u64 + 3, &s, &[]));
+    }
+    assert_eq!(
+        seal(&ocb, 385, &c, &[]),
+        [
+            0xd9, 0x0e, 0xb8, 0xe9, 0xc9, 0x77, 0xc8, 0x8b, 0x79, 0xdd, 0x79, 0x3d, 0x7f, 0xfa,
+            0x16, 0x1c
+        ]
+    );
+}
+
+#[test]
+fn roundtrip() -> io::Result<()> {
+    let key: Key<AES_256_OCB> = Key::new([1; Key::<AES_256_OCB>::SIZE]);
+    let data: Vec<u8> = (0..10_000).map(|i| i as u8).collect();
+
+    let mut ciphertext = Vec::default();
+    let mut writer = EncWriter::with_buffer_size(
+        &mut ciphertext,
+        &key,
+        Nonce::new([0; Nonce::<AES_256_OCB>::SIZE]),
+        Aad::from(b"ocb".as_ref()),
+        1000,
+    )?;
+    writer.write_all(&data)?;
+    writer.close()?;
+
+    let mut plaintext = Vec::default();
+    let mut writer = DecWriter::with_buffer_size(
+        &mut plaintext,
+        &key,
+        Nonce::new([0; Nonce::<AES_256_OCB>::SIZE]),
+        Aad::from(b"ocb".as_ref()),
+        1000,
+    )?;
+    writer.write_all(&ciphertext)?;
+    writer.close()?;
+    assert_eq!(plaintext, data);
+    Ok(())
+}
+
+#[test]
+fn not_authentic() {
+    let ocb = AES_256_OCB::new(&[2; 32]);
+    let nonce = [3; 12];
+    let mut in_out = vec![7; 200];
+    let sealed = ocb
+        .seal_in_place(&nonce, b"aad", &mut in_out)
+        .unwrap()
+        .to_vec();
+
+    for i in 0..sealed.len() {
+        let mut modified = sealed.clone();
+        modified[i] ^= 1;
+        assert!(ocb.open_in_place(&nonce, b"aad", &mut modified).is_err());
+        assert!(modified[..184].iter().all(|b| *b == 0));
+    }
+    let mut modified = sealed.clone();
+    assert!(ocb.open_in_place(&nonce, b"aae", &mut modified).is_err());
+    let mut modified = sealed.clone();
+    assert!(ocb.open_in_place(&[4; 12], b"aad", &mut modified).is_err());
+    assert!(ocb.open_in_place(&nonce, b"aad", &mut [0; 15]).is_err());
+    assert!(ocb.seal_in_place(&[0; 15], b"aad", &mut [0; 16]).is_err());
+}
@@ -0,0 +1,206 @@
+// Copyright (c) 2019 Andreas Auernhammer. All rights reserved.
+// Use of this source code is governed by a license that can be
+// found in the LICENSE file.
+
+use sio::*;
+use std::io::{self, Write};
+
+#[cfg(feature = "aesgcm")]
+#[allow(clippy::upper_case_acronyms)]
+type AEAD = AES_256_GCM;
+
+#[cfg(not(feature = "aesgcm"))]
+#[allow(clippy::upper_case_acronyms)]
+type AEAD = CHACHA20_POLY1305;
+
+#[test]
+fn seal_one_matches_enc_writer() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([1; Key::<AEAD>::SIZE]);
+    let data: Vec<u8> = (0..BUF_SIZE).map(|i| i as u8).collect();
+    for size in [0, 1, 100, BUF_SIZE] {
+        let mut expected = Vec::default();
+        let mut writer = EncWriter::new(
+            &mut expected,
+            &key,
+            Nonce::new([2; Nonce::<AEAD>::SIZE]),
+            Aad::from("Some authenticated but not encrypted data".as_bytes()),
+        );
+        writer.write_all(&data[..size])?;
+        writer.close()?;
+
+        let ciphertext = seal_one(
+            &key,
+            Nonce::new([2; Nonce::<AEAD>::SIZE]),
+            Aad::from("Some authenticated but not encrypted data".as_bytes()),
+            &data[..size],
+        )?;
+        assert_eq!(ciphertext, expected);
+
+        let plaintext = open_one(
+            &key,
+            Nonce::new([2; Nonce::<AEAD>::SIZE]),
+            Aad::from("Some authenticated but not encrypted data".as_bytes()),
+            &ciphertext,
+        )?;
+        assert_eq!(plaintext, &data[..size]);
+    }
+    Ok(())
+}
+
+#[test]
+fn seal_one_too_large() {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let result = seal_one(
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+        &vec![0; MAX_BUF_SIZE + 1],
+    );
+    assert!(matches!(result, Err(Invalid::BufSize)));
+}
+
+#[test]
+fn open_one_not_authentic() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let nonce = || Nonce::new([0; Nonce::<AEAD>::SIZE]);
+
+    let mut ciphertext = seal_one(&key, nonce(), Aad::empty(), b"Hello World")?;
+    assert!(open_one(&key, nonce(), Aad::from(b"aad".as_ref()), &ciphertext).is_err());
+    assert!(open_one(
+        &key,
+        nonce(),
+        Aad::empty(),
+        &ciphertext[..AEAD::TAG_LEN - 1]
+    )
+    .is_err());
+    ciphertext[0] ^= 1;
+    assert!(open_one(&key, nonce(), Aad::empty(), &ciphertext).is_err());
+
+    // A stream with more than one fragment is rejected.
+    let mut ciphertext = Vec::default();
+    let mut writer = EncWriter::with_buffer_size(&mut ciphertext, &key, nonce(), Aad::empty(), 4)?;
+    writer.write_all(b"Hello World")?;
+    writer.close()?;
+    assert!(open_one(&key, nonce(), Aad::empty(), &ciphertext).is_err());
+    Ok(())
+}
+
+#[test]
+fn seal_one_in_place_matches_seal_one() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([1; Key::<AEAD>::SIZE]);
+    let nonce = || Nonce::new([2; Nonce::<AEAD>::SIZE]);
+    let aad = || Aad::from("Some authenticated but not encrypted data".as_bytes());
+
+    let mut record = [0; 32 + AEAD::TAG_LEN];
+    record[..32].copy_from_slice(&[7; 32]);
+    seal_one_in_place(&key, nonce(), aad(), &mut record)?;
+    assert_eq!(record.as_ref(), seal_one(&key, nonce(), aad(), &[7; 32])?);
+
+    let plaintext = open_one_in_place(&key, nonce(), aad(), &mut record)?;
+    assert_eq!(plaintext, &[7; 32]);
+
+    let mut empty = [0; AEAD::TAG_LEN];
+    seal_one_in_place(&key, nonce(), aad(), &mut empty)?;
+    assert!(open_one_in_place(&key, nonce(), aad(), &mut empty)?.is_empty());
+
+    let mut short = [0; AEAD::TAG_LEN - 1];
+    assert!(matches!(
+        seal_one_in_place(&key, nonce(), aad(), &mut short),
+        Err(Invalid::BufSize)
+    ));
+    assert!(open_one_in_place(&key, nonce(), aad(), &mut short).is_err());
+    Ok(())
+}
+
+#[test]
+fn encrypt_vec_matches_enc_writer() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([1; Key::<AEAD>::SIZE]);
+    let nonce = || Nonce::new([2; Nonce::<AEAD>::SIZE]);
+    let aad = || Aad::from("Some authenticated but not encrypted data".as_bytes());
+    let data: Vec<u8> = (0..3 * BUF_SIZE + 5).map(|i| i as u8).collect();
+    for size in [0, 1, BUF_SIZE, 3 * BUF_SIZE + 5] {
+        let mut expected = Vec::default();
+        let mut writer = EncWriter::new(&mut expected, &key, nonce(), aad());
+        writer.write_all(&data[..size])?;
+        writer.close()?;
+
+        let ciphertext = encrypt_vec(&key, nonce(), aad(), &data[..size]);
+        assert_eq!(ciphertext, expected);
+        assert_eq!(
+            decrypt_vec(&key, nonce(), aad(), &ciphertext)?,
+            &data[..size]
+        );
+    }
+    Ok(())
+}
+
+#[test]
+fn decrypt_string() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let nonce = || Nonce::new([0; Nonce::<AEAD>::SIZE]);
+
+    let ciphertext = encrypt_vec(&key, nonce(), Aad::empty(), "Hello World".as_bytes());
+    assert_eq!(
+        sio::decrypt_string(&key, nonce(), Aad::empty(), &ciphertext)?,
+        "Hello World"
+    );
+
+    let ciphertext = encrypt_vec(&key, nonce(), Aad::empty(), &[0xff, 0xfe]);
+    let err = sio::decrypt_string(&key, nonce(), Aad::empty(), &ciphertext).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+    let mut ciphertext = encrypt_vec(&key, nonce(), Aad::empty(), "Hello World".as_bytes());
+    ciphertext[0] ^= 1;
+    let err = decrypt_vec(&key, nonce(), Aad::empty(), &ciphertext).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    Ok(())
+}
+
+#[test]
+fn sealer() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let mut sealer = Sealer::new(&key, Nonce::new([7; Nonce::<AEAD>::SIZE]));
+
+    let messages: Vec<Vec<u8>> = (0..16).map(|i| vec![i; 10 * i as usize]).collect();
+    let mut records = Vec::default();
+    for message in &messages {
+        records.push(sealer.seal(Aad::from(b"queue".as_ref()), message)?);
+    }
+
+    let mut nonces: Vec<&[u8]> = records.iter().map(|(nonce, _)| nonce.as_ref()).collect();
+    nonces.dedup();
+    assert_eq!(nonces.len(), messages.len());
+
+    for ((nonce, ciphertext), message) in records.iter().zip(messages.iter()) {
+        assert_eq!(
+            *ciphertext,
+            seal_one(&key, *nonce, Aad::from(b"queue".as_ref()), message)?
+        );
+        let plaintext = open_one(&key, *nonce, Aad::from(b"queue".as_ref()), ciphertext)?;
+        assert_eq!(plaintext, *message);
+    }
+    Ok(())
+}
+
+#[test]
+fn sealer_exceeded() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let mut nonce = [0xff; Nonce::<AEAD>::SIZE];
+    nonce[Nonce::<AEAD>::SIZE - 1] = 0xfe;
+    let mut sealer = Sealer::new(&key, Nonce::new(nonce));
+    assert_eq!(sealer.remaining(), 2);
+
+    assert!(sealer
+        .seal(Aad::empty(), &vec![0; MAX_BUF_SIZE + 1])
+        .is_err());
+    assert_eq!(sealer.remaining(), 2);
+
+    sealer.seal(Aad::empty(), b"Hello")?;
+    let (nonce, _) = sealer.seal(Aad::empty(), b"World")?;
+    assert_eq!(nonce.as_ref(), [0xff; Nonce::<AEAD>::SIZE]);
+    assert_eq!(sealer.remaining(), 0);
+
+    let err = sealer.seal(Aad::empty(), b"!").err().unwrap();
+    assert!(err.get_ref().is_some_and(|e| e.is::<Exceeded>()));
+    Ok(())
+}
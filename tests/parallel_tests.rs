@@ -0,0 +1,92 @@
+// Copyright (c) 2019 Andreas Auernhammer. All rights reserved.
+// Use of this source code is governed by a license that can be
+// found in the LICENSE file.
+
+use sio::*;
+use std::io::{self, Write};
+
+#[cfg(feature = "aesgcm")]
+#[allow(clippy::upper_case_acronyms)]
+type AEAD = AES_256_GCM;
+
+#[cfg(not(feature = "aesgcm"))]
+#[allow(clippy::upper_case_acronyms)]
+type AEAD = CHACHA20_POLY1305;
+
+#[test]
+fn parallel() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    for size in &[0, 1, 64, 65, 64 * 16, 64 * 64, 64 * 64 + 1, 10_000] {
+        let data: Vec<u8> = (0..*size).map(|i| i as u8).collect();
+        let mut ciphertext = Vec::default();
+        let mut writer = EncWriter::with_buffer_size(
+            &mut ciphertext,
+            &key,
+            Nonce::new([0; Nonce::<AEAD>::SIZE]),
+            Aad::from(b"parallel".as_ref()),
+            64,
+        )?;
+        writer.write_all(&data)?;
+        writer.close()?;
+
+        for threads in &[0, 1, 3, 8] {
+            let mut plaintext = Vec::default();
+            decrypt_parallel(
+                &mut plaintext,
+                &key,
+                Nonce::new([0; Nonce::<AEAD>::SIZE]),
+                Aad::from(b"parallel".as_ref()),
+                &ciphertext,
+                64,
+                *threads,
+            )?;
+            assert_eq!(plaintext, data);
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn parallel_not_authentic() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let mut ciphertext = Vec::default();
+    let mut writer = EncWriter::with_buffer_size(
+        &mut ciphertext,
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::from(b"parallel".as_ref()),
+        64,
+    )?;
+    writer.write_all(&[0; 10_000])?;
+    writer.close()?;
+    let fragment_len = 64 + AEAD::TAG_LEN;
+
+    let mut modified = ciphertext.clone();
+    modified[70 * fragment_len] ^= 1;
+    let err = decrypt_parallel(
+        io::sink(),
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::from(b"parallel".as_ref()),
+        &modified,
+        64,
+        4,
+    )
+    .unwrap_err();
+    assert!(err.get_ref().is_some_and(|e| e.is::<NotAuthentic>()));
+
+    // Truncation at a fragment boundary must be detected.
+    for truncated in &[&ciphertext[..100 * fragment_len], &[]] {
+        let result = decrypt_parallel(
+            io::sink(),
+            &key,
+            Nonce::new([0; Nonce::<AEAD>::SIZE]),
+            Aad::from(b"parallel".as_ref()),
+            truncated,
+            64,
+            4,
+        );
+        assert!(result.is_err());
+    }
+    Ok(())
+}
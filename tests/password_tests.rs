@@ -0,0 +1,125 @@
+// Copyright (c) 2019 Andreas Auernhammer. All rights reserved.
+// Use of this source code is governed by a license that can be
+// found in the LICENSE file.
+
+#![cfg(feature = "password")]
+
+use sio::*;
+use std::io::{self, Read, Write};
+
+#[cfg(feature = "aesgcm")]
+#[allow(clippy::upper_case_acronyms)]
+type AEAD = AES_256_GCM;
+
+#[cfg(not(feature = "aesgcm"))]
+#[allow(clippy::upper_case_acronyms)]
+type AEAD = CHACHA20_POLY1305;
+
+#[test]
+fn password() -> io::Result<()> {
+    let params = PasswordParams::new(64, 1, 1).unwrap();
+    for size in &[0, 1, BUF_SIZE, 3 * BUF_SIZE + 1] {
+        let data: Vec<u8> = (0..*size).map(|i| i as u8).collect();
+
+        let mut ciphertext = Vec::default();
+        let mut writer =
+            EncWriter::<AEAD, _>::with_password_params(&mut ciphertext, b"secret", params)?;
+        writer.write_all(&data).and_then(|()| writer.close())?;
+
+        let mut plaintext = Vec::default();
+        let mut reader = DecReader::<AEAD, _>::with_password(ciphertext.as_slice(), b"secret")?;
+        reader.read_to_end(&mut plaintext)?;
+        assert_eq!(plaintext, data);
+    }
+    Ok(())
+}
+
+#[test]
+fn password_random_salt() -> io::Result<()> {
+    let params = PasswordParams::new(64, 1, 1).unwrap();
+
+    let mut first = Vec::default();
+    let mut writer = EncWriter::<AEAD, _>::with_password_params(&mut first, b"secret", params)?;
+    writer
+        .write_all(b"Hello World")
+        .and_then(|()| writer.close())?;
+
+    let mut second = Vec::default();
+    let mut writer = EncWriter::<AEAD, _>::with_password_params(&mut second, b"secret", params)?;
+    writer
+        .write_all(b"Hello World")
+        .and_then(|()| writer.close())?;
+
+    assert_ne!(first, second);
+    Ok(())
+}
+
+#[test]
+fn password_wrong_passphrase() -> io::Result<()> {
+    let params = PasswordParams::new(64, 1, 1).unwrap();
+    let mut ciphertext = Vec::default();
+    let mut writer =
+        EncWriter::<AEAD, _>::with_password_params(&mut ciphertext, b"secret", params)?;
+    writer
+        .write_all(b"Hello World")
+        .and_then(|()| writer.close())?;
+
+    let err = DecReader::<AEAD, _>::with_password(ciphertext.as_slice(), b"wrong")
+        .and_then(|mut reader| reader.read_to_end(&mut Vec::default()))
+        .unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    Ok(())
+}
+
+#[test]
+fn password_modified_params() -> io::Result<()> {
+    let params = PasswordParams::new(64, 1, 1).unwrap();
+    let mut ciphertext = Vec::default();
+    let mut writer =
+        EncWriter::<AEAD, _>::with_password_params(&mut ciphertext, b"secret", params)?;
+    writer
+        .write_all(b"Hello World")
+        .and_then(|()| writer.close())?;
+
+    ciphertext[13] ^= 1; // iterations
+    assert!(
+        DecReader::<AEAD, _>::with_password(ciphertext.as_slice(), b"secret")
+            .and_then(|mut reader| reader.read_to_end(&mut Vec::default()))
+            .is_err()
+    );
+    Ok(())
+}
+
+#[test]
+fn password_limit() -> io::Result<()> {
+    let params = PasswordParams::new(64, 1, 1).unwrap();
+    let mut ciphertext = Vec::default();
+    let mut writer =
+        EncWriter::<AEAD, _>::with_password_params(&mut ciphertext, b"secret", params)?;
+    writer
+        .write_all(b"Hello World")
+        .and_then(|()| writer.close())?;
+
+    let max = PasswordParams::new(32, 1, 1).unwrap();
+    assert!(DecReader::<AEAD, _>::with_password_limit(&ciphertext[..], b"secret", max).is_err());
+    Ok(())
+}
+
+#[test]
+fn password_not_password_encrypted() {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let mut ciphertext = Vec::default();
+    let mut writer = EncWriter::new(
+        &mut ciphertext,
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+    );
+    writer.write_all(&[0; 64]).unwrap();
+    writer.close().unwrap();
+
+    match DecReader::<AEAD, _>::with_password(&ciphertext[..], b"secret") {
+        Err(err) => assert_eq!(err.kind(), io::ErrorKind::InvalidData),
+        Ok(_) => panic!("expected an unsupported version error"),
+    }
+}
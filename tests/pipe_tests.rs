@@ -0,0 +1,81 @@
+// Copyright (c) 2019 Andreas Auernhammer. All rights reserved.
+// Use of this source code is governed by a license that can be
+// found in the LICENSE file.
+
+use sio::*;
+use std::io::{self, Read, Write};
+use std::thread;
+
+#[cfg(feature = "aesgcm")]
+#[allow(clippy::upper_case_acronyms)]
+type AEAD = AES_256_GCM;
+
+#[cfg(not(feature = "aesgcm"))]
+#[allow(clippy::upper_case_acronyms)]
+type AEAD = CHACHA20_POLY1305;
+
+#[test]
+fn pipe() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let data = vec![7; 5 * BUF_SIZE + 3];
+
+    let (mut writer, mut reader) =
+        pipe::encrypted(&key, Nonce::new([0; Nonce::<AEAD>::SIZE]), Aad::empty());
+    let plaintext = data.clone();
+    let producer = thread::spawn(move || {
+        writer.write_all(&plaintext)?;
+        writer.close()
+    });
+    let mut ciphertext = Vec::default();
+    reader.read_to_end(&mut ciphertext)?;
+    producer.join().unwrap()?;
+
+    let (mut writer, mut reader) =
+        pipe::decrypted(&key, Nonce::new([0; Nonce::<AEAD>::SIZE]), Aad::empty());
+    let producer = thread::spawn(move || {
+        writer.write_all(&ciphertext)?;
+        writer.close()
+    });
+    let mut plaintext = Vec::default();
+    reader.read_to_end(&mut plaintext)?;
+    producer.join().unwrap()?;
+
+    assert_eq!(plaintext, data);
+    Ok(())
+}
+
+#[test]
+fn pipe_not_authentic() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let mut ciphertext = encrypt_vec(
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+        &[7; 100],
+    );
+    ciphertext[0] ^= 1;
+
+    let (mut writer, mut reader) =
+        pipe::decrypted(&key, Nonce::new([0; Nonce::<AEAD>::SIZE]), Aad::empty());
+    let producer =
+        thread::spawn(move || writer.write_all(&ciphertext).and_then(|()| writer.close()));
+
+    let err = reader.read_to_end(&mut Vec::default()).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    assert!(producer.join().unwrap().is_err());
+    Ok(())
+}
+
+#[test]
+fn pipe_reader_dropped() {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let (mut writer, reader) =
+        pipe::encrypted(&key, Nonce::new([0; Nonce::<AEAD>::SIZE]), Aad::empty());
+    drop(reader);
+
+    let err = writer
+        .write_all(&[0; 2 * BUF_SIZE])
+        .and_then(|()| writer.close())
+        .unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::BrokenPipe);
+}
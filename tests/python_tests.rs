@@ -0,0 +1,151 @@
+// Copyright (c) 2019 Andreas Auernhammer. All rights reserved.
+// Use of this source code is governed by a license that can be
+// found in the LICENSE file.
+
+#![cfg(feature = "pyo3")]
+
+use pyo3::ffi::c_str;
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyModule};
+use sio::*;
+use std::ffi::CStr;
+use std::io::Write;
+
+// Runs the Python `code` with the `sio` module in scope
+// and returns the local variables.
+fn run<T, F>(code: &CStr, f: F) -> PyResult<T>
+where
+    F: FnOnce(&Bound<'_, PyDict>) -> PyResult<T>,
+{
+    pyo3::prepare_freethreaded_python();
+    Python::with_gil(|py| {
+        let module = PyModule::new(py, "sio")?;
+        sio::python::sio(&module)?;
+
+        let locals = PyDict::new(py);
+        locals.set_item("sio", module)?;
+        py.run(code, None, Some(&locals))?;
+        f(&locals)
+    })
+}
+
+#[test]
+fn encrypt() -> PyResult<()> {
+    let ciphertext: Vec<u8> = run(
+        c_str!(
+            r#"
+import io
+
+ciphertext = io.BytesIO()
+with sio.EncWriter(ciphertext, bytes(32), bytes(8), b"aad", buf_size=64) as writer:
+    assert writer.write(bytes(100)) == 100
+    assert writer.write(b"Hello World") == 11
+assert writer.closed
+"#
+        ),
+        |locals| {
+            locals
+                .get_item("ciphertext")?
+                .unwrap()
+                .call_method0("getvalue")?
+                .extract()
+        },
+    )?;
+
+    let key: Key<CHACHA20_POLY1305> = Key::new([0; Key::<CHACHA20_POLY1305>::SIZE]);
+    let mut expected = Vec::default();
+    let mut writer = EncWriter::with_buffer_size(
+        &mut expected,
+        &key,
+        Nonce::new([0; Nonce::<CHACHA20_POLY1305>::SIZE]),
+        Aad::from(b"aad".as_ref()),
+        64,
+    )
+    .unwrap();
+    writer.write_all(&[0; 100])?;
+    writer.write_all(b"Hello World")?;
+    writer.close()?;
+
+    assert_eq!(ciphertext, expected);
+    Ok(())
+}
+
+#[test]
+fn decrypt() -> PyResult<()> {
+    run(
+        c_str!(
+            r#"
+import io
+
+ciphertext = io.BytesIO()
+with sio.EncWriter(ciphertext, bytes(32), bytes(8)) as writer:
+    writer.write(bytes(3 * sio.BUF_SIZE + 5))
+
+plaintext = io.BytesIO()
+writer = sio.DecWriter(plaintext, bytes(32), bytes(8))
+writer.write(ciphertext.getvalue())
+writer.close()
+writer.close()
+assert plaintext.getvalue() == bytes(3 * sio.BUF_SIZE + 5)
+"#
+        ),
+        |_| Ok(()),
+    )
+}
+
+#[test]
+fn decrypt_modified() -> PyResult<()> {
+    run(
+        c_str!(
+            r#"
+import io
+
+ciphertext = io.BytesIO()
+with sio.EncWriter(ciphertext, bytes(32), bytes(8)) as writer:
+    writer.write(b"Hello World")
+
+ciphertext = bytearray(ciphertext.getvalue())
+ciphertext[0] ^= 1
+try:
+    with sio.DecWriter(io.BytesIO(), bytes(32), bytes(8)) as writer:
+        writer.write(bytes(ciphertext))
+    raise AssertionError("modified ciphertext has been accepted")
+except ValueError:
+    pass
+"#
+        ),
+        |_| Ok(()),
+    )
+}
+
+#[test]
+fn invalid_arguments() -> PyResult<()> {
+    run(
+        c_str!(
+            r#"
+import io
+
+for args in [(bytes(31), bytes(8)), (bytes(32), bytes(12))]:
+    try:
+        sio.EncWriter(io.BytesIO(), *args)
+        raise AssertionError("invalid key or nonce has been accepted")
+    except ValueError:
+        pass
+
+writer = sio.EncWriter(io.BytesIO(), bytes(32), bytes(8))
+writer.close()
+try:
+    writer.write(b"Hello World")
+    raise AssertionError("write after close has been accepted")
+except ValueError:
+    pass
+
+# Dropping an unclosed writer must not crash the interpreter.
+writer = sio.EncWriter(io.BytesIO(), bytes(32), bytes(8))
+writer.write(b"Hello World")
+del writer
+"#
+        ),
+        |_| Ok(()),
+    )
+}
@@ -0,0 +1,157 @@
+// Copyright (c) 2019 Andreas Auernhammer. All rights reserved.
+// Use of this source code is governed by a license that can be
+// found in the LICENSE file.
+
+use sio::*;
+use std::io::{self, Cursor, Write};
+
+#[cfg(feature = "aesgcm")]
+#[allow(clippy::upper_case_acronyms)]
+type AEAD = AES_256_GCM;
+
+#[cfg(not(feature = "aesgcm"))]
+#[allow(clippy::upper_case_acronyms)]
+type AEAD = CHACHA20_POLY1305;
+
+const BUF_SIZE: usize = 64;
+
+#[test]
+fn range() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    for size in &[0, 1, BUF_SIZE, 3 * BUF_SIZE, 3 * BUF_SIZE + 1] {
+        let data: Vec<u8> = (0..*size).map(|i| i as u8).collect();
+
+        let mut ciphertext = Vec::default();
+        let mut writer = EncWriter::with_buffer_size(
+            &mut ciphertext,
+            &key,
+            Nonce::new([0; Nonce::<AEAD>::SIZE]),
+            Aad::empty(),
+            BUF_SIZE,
+        )?;
+        writer.write_all(&data).and_then(|()| writer.close())?;
+
+        for start in 0..=*size {
+            for end in (start..=*size).step_by(7).chain(Some(*size)) {
+                let plaintext = sio::decrypt_range(
+                    Cursor::new(&ciphertext),
+                    &key,
+                    Nonce::new([0; Nonce::<AEAD>::SIZE]),
+                    Aad::empty(),
+                    BUF_SIZE,
+                    start as u64..end as u64,
+                )?;
+                assert_eq!(plaintext, &data[start..end]);
+            }
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn range_exceeds_plaintext() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let mut ciphertext = Vec::default();
+    let mut writer = EncWriter::with_buffer_size(
+        &mut ciphertext,
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+        BUF_SIZE,
+    )?;
+    writer
+        .write_all(&[0; BUF_SIZE + 1])
+        .and_then(|()| writer.close())?;
+
+    let err = sio::decrypt_range(
+        Cursor::new(&ciphertext),
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+        BUF_SIZE,
+        0..BUF_SIZE as u64 + 2,
+    )
+    .unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    Ok(())
+}
+
+#[test]
+fn range_not_authentic() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let mut ciphertext = Vec::default();
+    let mut writer = EncWriter::with_buffer_size(
+        &mut ciphertext,
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+        BUF_SIZE,
+    )?;
+    writer
+        .write_all(&[0; 3 * BUF_SIZE + 1])
+        .and_then(|()| writer.close())?;
+
+    // A stream truncated at a fragment boundary has no final fragment.
+    let truncated = ciphertext[..2 * (BUF_SIZE + AEAD::TAG_LEN)].to_vec();
+    let mut modified = ciphertext;
+    modified[BUF_SIZE + AEAD::TAG_LEN] ^= 1;
+
+    // Only the fragments covering the range are verified.
+    let n = BUF_SIZE as u64;
+    for (range, authentic) in [(n..n + 1, false), (0..n, true), (2 * n..3 * n + 1, true)] {
+        let result = sio::decrypt_range(
+            Cursor::new(&modified),
+            &key,
+            Nonce::new([0; Nonce::<AEAD>::SIZE]),
+            Aad::empty(),
+            BUF_SIZE,
+            range,
+        );
+        assert_eq!(result.is_ok(), authentic);
+    }
+
+    let err = sio::decrypt_range(
+        Cursor::new(&truncated),
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+        BUF_SIZE,
+        n..n + 1,
+    )
+    .unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    Ok(())
+}
+
+#[test]
+fn range_many_fragments() -> io::Result<()> {
+    // The fragments are decrypted in batches of several fragments.
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let size = 20 * BUF_SIZE + 5;
+    let data: Vec<u8> = (0..size).map(|i| i as u8).collect();
+
+    let mut ciphertext = Vec::default();
+    let mut writer = EncWriter::with_buffer_size(
+        &mut ciphertext,
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+        BUF_SIZE,
+    )?;
+    writer.write_all(&data).and_then(|()| writer.close())?;
+
+    for start in (0..=size).step_by(37) {
+        for end in (start..=size).step_by(61).chain(Some(size)) {
+            let plaintext = sio::decrypt_range(
+                Cursor::new(&ciphertext),
+                &key,
+                Nonce::new([0; Nonce::<AEAD>::SIZE]),
+                Aad::empty(),
+                BUF_SIZE,
+                start as u64..end as u64,
+            )?;
+            assert_eq!(plaintext, &data[start..end]);
+        }
+    }
+    Ok(())
+}
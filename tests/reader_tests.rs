@@ -0,0 +1,447 @@
+// Copyright (c) 2019 Andreas Auernhammer. All rights reserved.
+// Use of this source code is governed by a license that can be
+// found in the LICENSE file.
+
+use sio::*;
+use std::io::{self, Cursor, Read, Seek, SeekFrom, Write};
+
+#[cfg(feature = "aesgcm")]
+#[allow(clippy::upper_case_acronyms)]
+type AEAD = AES_256_GCM;
+
+#[cfg(not(feature = "aesgcm"))]
+#[allow(clippy::upper_case_acronyms)]
+type AEAD = CHACHA20_POLY1305;
+
+#[test]
+fn read() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    for size in &[0, 1, 63, 64, 65, 128, 1000] {
+        let data: Vec<u8> = (0..*size).map(|i| i as u8).collect();
+        let mut ciphertext = Vec::default();
+        let mut writer = EncWriter::with_buffer_size(
+            &mut ciphertext,
+            &key,
+            Nonce::new([0; Nonce::<AEAD>::SIZE]),
+            Aad::empty(),
+            64,
+        )?;
+        writer.write_all(&data).and_then(|()| writer.close())?;
+
+        let mut plaintext = Vec::default();
+        let mut reader = DecReader::with_buffer_size(
+            ciphertext.as_slice(),
+            &key,
+            Nonce::new([0; Nonce::<AEAD>::SIZE]),
+            Aad::empty(),
+            64,
+        )?;
+        reader.read_to_end(&mut plaintext)?;
+        assert_eq!(plaintext, data);
+        assert_eq!(reader.consumed(), ciphertext.len() as u64);
+    }
+    Ok(())
+}
+
+#[test]
+fn read_small_chunks() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let data: Vec<u8> = (0..1000).map(|i| i as u8).collect();
+
+    let mut ciphertext = Vec::default();
+    let mut writer = EncWriter::with_buffer_size(
+        &mut ciphertext,
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+        64,
+    )?;
+    writer.write_all(&data).and_then(|()| writer.close())?;
+
+    let mut plaintext = Vec::default();
+    let mut reader = DecReader::with_buffer_size(
+        ciphertext.as_slice(),
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+        64,
+    )?;
+    let mut buf = [0; 7];
+    loop {
+        match reader.read(&mut buf)? {
+            0 => break,
+            n => plaintext.extend_from_slice(&buf[..n]),
+        }
+    }
+    assert_eq!(plaintext, data);
+    Ok(())
+}
+
+#[test]
+fn read_truncated() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+
+    let mut ciphertext = Vec::default();
+    let mut writer = EncWriter::with_buffer_size(
+        &mut ciphertext,
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+        64,
+    )?;
+    writer.write_all(&[0; 1000]).and_then(|()| writer.close())?;
+
+    let fragment_len = 64 + AEAD::TAG_LEN;
+    let mut reader = DecReader::with_buffer_size(
+        &ciphertext[..2 * fragment_len],
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+        64,
+    )?;
+    let err = reader.read_to_end(&mut Vec::default()).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    Ok(())
+}
+
+#[test]
+fn read_trailing_data() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+
+    let mut ciphertext = Vec::default();
+    let mut writer = EncWriter::with_buffer_size(
+        &mut ciphertext,
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+        64,
+    )?;
+    writer.write_all(&[0; 1000]).and_then(|()| writer.close())?;
+    ciphertext.extend_from_slice(&[1; 10]);
+
+    let mut reader = DecReader::with_buffer_size(
+        ciphertext.as_slice(),
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+        64,
+    )?;
+    let err = reader.read_to_end(&mut Vec::default()).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    Ok(())
+}
+
+#[test]
+fn read_tolerant_concatenated() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let streams: Vec<Vec<u8>> = vec![vec![1; 1000], vec![2; 128], Vec::default(), vec![3; 5]];
+    let mut journal = Vec::default();
+    for data in &streams {
+        let mut writer = EncWriter::with_buffer_size(
+            &mut journal,
+            &key,
+            Nonce::new([0; Nonce::<AEAD>::SIZE]),
+            Aad::empty(),
+            64,
+        )?;
+        writer.write_all(data)?;
+        writer.close()?;
+    }
+
+    // The DecReader reads ahead. Hence, the journal has to be
+    // repositioned before reading the next stream.
+    let mut journal = Cursor::new(journal);
+    let mut offset = 0;
+    for data in &streams {
+        journal.seek(SeekFrom::Start(offset))?;
+        let mut reader = DecReader::with_buffer_size(
+            &mut journal,
+            &key,
+            Nonce::new([0; Nonce::<AEAD>::SIZE]),
+            Aad::empty(),
+            64,
+        )?
+        .tolerant();
+        let mut plaintext = Vec::default();
+        reader.read_to_end(&mut plaintext)?;
+        assert_eq!(&plaintext, data);
+        offset += reader.consumed();
+    }
+    assert_eq!(offset, journal.get_ref().len() as u64);
+    Ok(())
+}
+
+#[test]
+fn read_delimited_concatenated() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let streams: Vec<Vec<u8>> = vec![vec![1; 1000], vec![2; 128], Vec::default(), vec![3; 5]];
+    let mut journal = Vec::default();
+    for data in &streams {
+        let mut writer = EncWriter::with_framing(
+            &mut journal,
+            &key,
+            Nonce::new([0; Nonce::<AEAD>::SIZE]),
+            Aad::empty(),
+            64,
+            framing::Delimited::new(framing::Sio::default(), data.len() as u64),
+        )?;
+        writer.write_all(data)?;
+        writer.close()?;
+    }
+
+    // The DecReader does not read beyond the end of a stream whose
+    // framing records its length.
+    let len = journal.len() as u64;
+    let mut journal = journal.as_slice();
+    let mut offset = 0;
+    for data in &streams {
+        let mut reader = DecReader::with_framing(
+            &mut journal,
+            &key,
+            Nonce::new([0; Nonce::<AEAD>::SIZE]),
+            Aad::empty(),
+            64,
+            framing::Delimited::<framing::Sio>::default(),
+        )?;
+        let mut plaintext = Vec::default();
+        reader.read_to_end(&mut plaintext)?;
+        assert_eq!(&plaintext, data);
+        offset += reader.consumed();
+    }
+    assert!(journal.is_empty());
+    assert_eq!(offset, len);
+    Ok(())
+}
+
+#[test]
+fn read_delimited_truncated() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+
+    let mut ciphertext = Vec::default();
+    let mut writer = EncWriter::with_framing(
+        &mut ciphertext,
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+        64,
+        framing::Delimited::new(framing::Sio::default(), 1000),
+    )?;
+    writer.write_all(&[0; 1000]).and_then(|()| writer.close())?;
+
+    let fragment_len = 64 + AEAD::TAG_LEN;
+    for len in &[8, fragment_len, 2 * fragment_len + 1, ciphertext.len() - 1] {
+        let mut reader = DecReader::with_framing(
+            &ciphertext[..*len],
+            &key,
+            Nonce::new([0; Nonce::<AEAD>::SIZE]),
+            Aad::empty(),
+            64,
+            framing::Delimited::<framing::Sio>::default(),
+        )?;
+        let err = reader.read_to_end(&mut Vec::default()).unwrap_err();
+        assert!(err.get_ref().is_some_and(|e| e.is::<LengthMismatch>()));
+    }
+    Ok(())
+}
+
+#[test]
+fn write_length_mismatch() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let mut writer = EncWriter::with_framing(
+        io::sink(),
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+        BUF_SIZE,
+        framing::Delimited::new(framing::Sio::default(), 10),
+    )?;
+    writer.write_all(&[0; 5])?;
+    let err = writer.close().unwrap_err();
+    assert!(err.get_ref().is_some_and(|e| e.is::<LengthMismatch>()));
+
+    // Writing more than the recorded length fails right away.
+    let mut writer = EncWriter::with_framing(
+        io::sink(),
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+        BUF_SIZE,
+        framing::Delimited::new(framing::Sio::default(), 10),
+    )?;
+    let err = writer.write_all(&[0; 11]).unwrap_err();
+    assert!(err.get_ref().is_some_and(|e| e.is::<LengthMismatch>()));
+    Ok(())
+}
+
+#[test]
+fn read_metadata() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let mut metadata = Metadata::default();
+    metadata.insert("content-type".to_string(), "text/plain".to_string());
+    metadata.insert("name".to_string(), "foo.txt".to_string());
+
+    for size in &[0, 1, 64, 65, 1000] {
+        let data: Vec<u8> = (0..*size).map(|i| i as u8).collect();
+        let mut ciphertext = Vec::default();
+        let mut writer = EncWriter::with_buffer_size(
+            &mut ciphertext,
+            &key,
+            Nonce::new([0; Nonce::<AEAD>::SIZE]),
+            Aad::empty(),
+            64,
+        )?
+        .attach_metadata(&metadata)?;
+        writer.write_all(&data).and_then(|()| writer.close())?;
+
+        let mut reader = DecReader::with_buffer_size(
+            ciphertext.as_slice(),
+            &key,
+            Nonce::new([0; Nonce::<AEAD>::SIZE]),
+            Aad::empty(),
+            64,
+        )?
+        .expect_metadata();
+        assert_eq!(reader.metadata()?, &metadata);
+
+        let mut plaintext = Vec::default();
+        reader.read_to_end(&mut plaintext)?;
+        assert_eq!(plaintext, data);
+        assert_eq!(reader.metadata()?, &metadata);
+        assert_eq!(reader.consumed(), ciphertext.len() as u64);
+    }
+    Ok(())
+}
+
+#[test]
+fn read_delimited_metadata() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let mut metadata = Metadata::default();
+    metadata.insert("name".to_string(), "foo.txt".to_string());
+
+    for size in &[0, 1, 64, 65, 1000] {
+        let data: Vec<u8> = (0..*size).map(|i| i as u8).collect();
+        let mut ciphertext = Vec::default();
+
+        // The metadata fragment counts towards the recorded length.
+        let mut writer = EncWriter::with_framing(
+            &mut ciphertext,
+            &key,
+            Nonce::new([0; Nonce::<AEAD>::SIZE]),
+            Aad::empty(),
+            64,
+            framing::Delimited::new(framing::Sio::default(), data.len() as u64 + 64),
+        )?
+        .attach_metadata(&metadata)?;
+        writer.write_all(&data).and_then(|()| writer.close())?;
+
+        let mut reader = DecReader::with_framing(
+            ciphertext.as_slice(),
+            &key,
+            Nonce::new([0; Nonce::<AEAD>::SIZE]),
+            Aad::empty(),
+            64,
+            framing::Delimited::<framing::Sio>::default(),
+        )?
+        .expect_metadata();
+        assert_eq!(reader.metadata()?, &metadata);
+
+        let mut plaintext = Vec::default();
+        reader.read_to_end(&mut plaintext)?;
+        assert_eq!(plaintext, data);
+        assert_eq!(reader.consumed(), ciphertext.len() as u64);
+    }
+    Ok(())
+}
+
+#[test]
+fn read_metadata_without_calling_metadata() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let mut metadata = Metadata::default();
+    metadata.insert("name".to_string(), "foo.txt".to_string());
+
+    let mut ciphertext = Vec::default();
+    let mut writer = EncWriter::with_buffer_size(
+        &mut ciphertext,
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+        64,
+    )?
+    .attach_metadata(&metadata)?;
+    writer
+        .write_all(b"Hello World")
+        .and_then(|()| writer.close())?;
+
+    let mut plaintext = String::default();
+    let mut reader = DecReader::with_buffer_size(
+        ciphertext.as_slice(),
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+        64,
+    )?
+    .expect_metadata();
+    reader.read_to_string(&mut plaintext)?;
+    assert_eq!(plaintext, "Hello World");
+    assert_eq!(reader.metadata()?, &metadata);
+    Ok(())
+}
+
+#[test]
+fn read_metadata_not_expected() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+
+    let mut ciphertext = Vec::default();
+    let mut writer = EncWriter::with_buffer_size(
+        &mut ciphertext,
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+        64,
+    )?;
+    writer
+        .write_all(b"Hello World")
+        .and_then(|()| writer.close())?;
+
+    let mut reader = DecReader::with_buffer_size(
+        ciphertext.as_slice(),
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+        64,
+    )?;
+    let err = reader.metadata().unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+
+    let mut reader = DecReader::with_buffer_size(
+        ciphertext.as_slice(),
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+        64,
+    )?
+    .expect_metadata();
+    let err = reader.metadata().unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    Ok(())
+}
+
+#[test]
+fn metadata_too_large() {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let mut metadata = Metadata::default();
+    metadata.insert("name".to_string(), "x".repeat(64));
+
+    let writer = EncWriter::with_buffer_size(
+        io::sink(),
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+        64,
+    )
+    .unwrap();
+    assert_eq!(
+        writer.attach_metadata(&metadata).err(),
+        Some(Invalid::Metadata)
+    );
+}
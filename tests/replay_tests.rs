@@ -0,0 +1,118 @@
+// Copyright (c) 2019 Andreas Auernhammer. All rights reserved.
+// Use of this source code is governed by a license that can be
+// found in the LICENSE file.
+
+use sio::*;
+use std::collections::HashSet;
+use std::io;
+
+#[cfg(feature = "aesgcm")]
+#[allow(clippy::upper_case_acronyms)]
+type AEAD = AES_256_GCM;
+
+#[cfg(not(feature = "aesgcm"))]
+#[allow(clippy::upper_case_acronyms)]
+type AEAD = CHACHA20_POLY1305;
+
+#[test]
+fn replay_window() {
+    let mut window = ReplayWindow::default();
+    assert_eq!(window.size(), 64);
+    assert_eq!(window.top(), None);
+
+    assert!(window.accept(0).is_ok());
+    assert!(window.accept(0).is_err());
+    assert!(window.accept(u32::MAX).is_ok());
+    assert_eq!(window.top(), Some(u32::MAX));
+    assert!(window.check(u32::MAX - 63).is_ok());
+    assert!(window.check(u32::MAX - 64).is_err());
+    assert!(window.accept(0).is_err());
+}
+
+// Compares the window against a set of all accepted sequence numbers.
+#[test]
+fn replay_window_reference() {
+    for size in &[1, 63, 64, 65, 100, 1000] {
+        let mut window = ReplayWindow::new(*size);
+        let mut accepted = HashSet::new();
+        let mut top = 0;
+
+        // A deterministic sequence of forward jumps and reordered messages.
+        let mut x: u32 = 7;
+        for _ in 0..10_000 {
+            x = x.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+            let seq_num = match x % 4 {
+                0 => top + (x >> 8) % (2 * *size),
+                1 => top + 1,
+                _ => top.saturating_sub((x >> 8) % (*size + 8)),
+            };
+            let expected = !accepted.contains(&seq_num)
+                && (accepted.is_empty() || seq_num > top || top - seq_num < *size);
+            assert_eq!(window.check(seq_num).is_ok(), expected);
+            assert_eq!(window.accept(seq_num).is_ok(), expected);
+            if expected {
+                accepted.insert(seq_num);
+                top = top.max(seq_num);
+            }
+        }
+    }
+}
+
+#[test]
+#[should_panic]
+fn replay_window_zero() {
+    ReplayWindow::new(0);
+}
+
+#[test]
+fn opener() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let mut sealer = Sealer::new(&key, Nonce::new([7; Nonce::<AEAD>::SIZE]));
+    let records = (0..100)
+        .map(|i| sealer.seal(Aad::from(b"queue".as_ref()), &[i; 10]))
+        .collect::<io::Result<Vec<_>>>()?;
+
+    let mut opener = Opener::with_window(
+        &key,
+        Nonce::new([7; Nonce::<AEAD>::SIZE]),
+        ReplayWindow::new(128),
+    );
+    for (i, (nonce, ciphertext)) in records.iter().enumerate().rev() {
+        let plaintext = opener.open(*nonce, Aad::from(b"queue".as_ref()), ciphertext)?;
+        assert_eq!(plaintext, [i as u8; 10]);
+    }
+    for (nonce, ciphertext) in &records {
+        let err = opener
+            .open(*nonce, Aad::from(b"queue".as_ref()), ciphertext)
+            .unwrap_err();
+        assert!(err.get_ref().is_some_and(|e| e.is::<Replayed>()));
+    }
+    Ok(())
+}
+
+#[test]
+fn opener_not_authentic() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let nonce = Nonce::new([7; Nonce::<AEAD>::SIZE]);
+    let mut sealer = Sealer::new(&key, nonce);
+    let (record_nonce, ciphertext) = sealer.seal(Aad::empty(), b"Hello World")?;
+    let mut opener = Opener::new(&key, nonce);
+
+    // A message of another sealer.
+    let other = Nonce::new([8; Nonce::<AEAD>::SIZE]);
+    let (other_nonce, other_ciphertext) = Sealer::new(&key, other).seal(Aad::empty(), b"!")?;
+    let err = opener
+        .open(other_nonce, Aad::empty(), &other_ciphertext)
+        .unwrap_err();
+    assert!(err.get_ref().is_some_and(|e| e.is::<NotAuthentic>()));
+
+    let mut modified = ciphertext.clone();
+    modified[0] ^= 1;
+    assert!(opener.open(record_nonce, Aad::empty(), &modified).is_err());
+    assert!(opener.open(record_nonce, Aad::empty(), &[]).is_err());
+    assert_eq!(opener.window().top(), None);
+
+    opener.open(record_nonce, Aad::empty(), &ciphertext)?;
+    assert_eq!(opener.window().top(), Some(0x0707_0707));
+    Ok(())
+}
@@ -0,0 +1,136 @@
+// Copyright (c) 2019 Andreas Auernhammer. All rights reserved.
+// Use of this source code is governed by a license that can be
+// found in the LICENSE file.
+
+use sio::*;
+use std::io::{self, Cursor};
+
+#[cfg(feature = "aesgcm")]
+#[allow(clippy::upper_case_acronyms)]
+type AEAD = AES_256_GCM;
+
+#[cfg(not(feature = "aesgcm"))]
+#[allow(clippy::upper_case_acronyms)]
+type AEAD = CHACHA20_POLY1305;
+
+const SECTOR_SIZE: usize = 512;
+const STORED_SIZE: usize = 4 + SECTOR_SIZE + AEAD::TAG_LEN;
+
+fn open(storage: Vec<u8>) -> SectorDevice<AEAD, Cursor<Vec<u8>>> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    SectorDevice::with_sector_size(
+        Cursor::new(storage),
+        &key,
+        Aad::from(b"disk".as_ref()),
+        SECTOR_SIZE,
+    )
+    .unwrap()
+}
+
+fn is_not_authentic(err: &io::Error) -> bool {
+    err.get_ref().is_some_and(|e| e.is::<NotAuthentic>())
+}
+
+#[test]
+fn random_access() -> io::Result<()> {
+    let mut device = open(Vec::default());
+    for sector in &[5, 0, 2, 5, 9, 0] {
+        device.write_sector(*sector, &[*sector as u8 + 1; SECTOR_SIZE])?;
+    }
+    assert_eq!(device.sectors()?, 10);
+
+    let mut buf = [0; SECTOR_SIZE];
+    for sector in &[0, 2, 5, 9] {
+        device.read_sector(*sector, &mut buf)?;
+        assert_eq!(buf, [*sector as u8 + 1; SECTOR_SIZE]);
+    }
+    Ok(())
+}
+
+#[test]
+fn unwritten() -> io::Result<()> {
+    let mut device = open(Vec::default());
+    device.write_sector(2, &[1; SECTOR_SIZE])?;
+
+    let mut buf = [0; SECTOR_SIZE];
+    assert!(is_not_authentic(
+        &device.read_sector(1, &mut buf).unwrap_err()
+    ));
+    assert!(is_not_authentic(
+        &device.read_sector(3, &mut buf).unwrap_err()
+    ));
+
+    device.write_sector(1, &[2; SECTOR_SIZE])?;
+    device.read_sector(1, &mut buf)?;
+    assert_eq!(buf, [2; SECTOR_SIZE]);
+    Ok(())
+}
+
+#[test]
+fn overwrite() -> io::Result<()> {
+    let mut device = open(Vec::default());
+    device.write_sector(0, &[1; SECTOR_SIZE])?;
+    let first = device.into_inner().into_inner();
+
+    let mut device = open(first.clone());
+    device.write_sector(0, &[1; SECTOR_SIZE])?;
+    let second = device.into_inner().into_inner();
+
+    // The same plaintext is encrypted with a different nonce.
+    assert_ne!(first, second);
+    assert_eq!(second[..4], [0, 0, 0, 2]);
+    Ok(())
+}
+
+#[test]
+fn modified() -> io::Result<()> {
+    let mut device = open(Vec::default());
+    device.write_sector(0, &[1; SECTOR_SIZE])?;
+    device.write_sector(1, &[2; SECTOR_SIZE])?;
+    let storage = device.into_inner().into_inner();
+
+    let mut buf = [0; SECTOR_SIZE];
+    let mut modified = storage.clone();
+    modified[10] ^= 1;
+    let mut device = open(modified);
+    assert!(is_not_authentic(
+        &device.read_sector(0, &mut buf).unwrap_err()
+    ));
+    assert!(is_not_authentic(
+        &device.write_sector(0, &[3; SECTOR_SIZE]).unwrap_err()
+    ));
+    device.read_sector(1, &mut buf)?;
+
+    // Swap both sectors.
+    let mut swapped = storage[STORED_SIZE..].to_vec();
+    swapped.extend_from_slice(&storage[..STORED_SIZE]);
+    let mut device = open(swapped);
+    assert!(is_not_authentic(
+        &device.read_sector(0, &mut buf).unwrap_err()
+    ));
+    assert!(is_not_authentic(
+        &device.read_sector(1, &mut buf).unwrap_err()
+    ));
+
+    // Truncate the last sector.
+    let mut device = open(storage[..storage.len() - 1].to_vec());
+    assert!(is_not_authentic(
+        &device.read_sector(1, &mut buf).unwrap_err()
+    ));
+    Ok(())
+}
+
+#[test]
+fn invalid() {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let result = SectorDevice::with_sector_size(Cursor::new(Vec::default()), &key, Aad::empty(), 0);
+    assert!(matches!(result, Err(Invalid::BufSize)));
+
+    let mut device = open(Vec::default());
+    let err = device.write_sector(0, &[0; SECTOR_SIZE - 1]).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    let err = device
+        .write_sector(u64::MAX, &[0; SECTOR_SIZE])
+        .unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+}
@@ -0,0 +1,94 @@
+// Copyright (c) 2019 Andreas Auernhammer. All rights reserved.
+// Use of this source code is governed by a license that can be
+// found in the LICENSE file.
+
+#![cfg(feature = "serde")]
+
+use serde::{Deserialize, Serialize};
+use sio::*;
+use std::io;
+
+#[cfg(not(feature = "aesgcm"))]
+#[allow(clippy::upper_case_acronyms)]
+type AEAD = CHACHA20_POLY1305;
+
+#[cfg(feature = "aesgcm")]
+#[allow(clippy::upper_case_acronyms)]
+type AEAD = AES_256_GCM;
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Record {
+    id: u64,
+    name: String,
+    tags: Vec<String>,
+}
+
+#[test]
+fn seal_json() {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let record = Record {
+        id: 42,
+        name: "sio".to_string(),
+        tags: vec!["secure".to_string(), "io".to_string()],
+    };
+
+    let ciphertext = sio::seal_json(
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::from("record".as_bytes()),
+        &record,
+    )
+    .unwrap();
+    let plaintext = decrypt_vec(
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::from("record".as_bytes()),
+        &ciphertext,
+    )
+    .unwrap();
+    assert!(!ciphertext.windows(3).any(|w| w == b"sio"));
+    assert_eq!(
+        plaintext,
+        br#"{"id":42,"name":"sio","tags":["secure","io"]}"#
+    );
+
+    let decrypted: Record = sio::open_json(
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::from("record".as_bytes()),
+        &ciphertext,
+    )
+    .unwrap();
+    assert_eq!(decrypted, record);
+
+    let result: io::Result<Record> = sio::open_json(
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::from("other".as_bytes()),
+        &ciphertext,
+    );
+    let err = result.unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    assert!(err.get_ref().is_some_and(|e| e.is::<NotAuthentic>()));
+}
+
+#[test]
+fn open_json_type_mismatch() {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let ciphertext = encrypt_vec(
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+        br#"{"id":"not a number"}"#,
+    );
+
+    let result: io::Result<Record> = sio::open_json(
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+        &ciphertext,
+    );
+    let err = result.unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    assert!(err.get_ref().is_some_and(|e| e.is::<serde_json::Error>()));
+}
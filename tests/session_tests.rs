@@ -0,0 +1,197 @@
+// Copyright (c) 2019 Andreas Auernhammer. All rights reserved.
+// Use of this source code is governed by a license that can be
+// found in the LICENSE file.
+
+use sio::*;
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::{env, fs, process, thread};
+
+#[cfg(feature = "aesgcm")]
+#[allow(clippy::upper_case_acronyms)]
+type AEAD = AES_256_GCM;
+
+#[cfg(not(feature = "aesgcm"))]
+#[allow(clippy::upper_case_acronyms)]
+type AEAD = CHACHA20_POLY1305;
+
+#[test]
+fn session() -> io::Result<()> {
+    let session = Session::new(Key::<AEAD>::new([0; Key::<AEAD>::SIZE]));
+    assert_eq!(session.remaining(), Session::<AEAD>::DEFAULT_LIMIT);
+
+    let mut streams = Vec::default();
+    for i in 0..4u8 {
+        let mut ciphertext = Vec::default();
+        let (nonce, mut writer) = session.enc_writer(&mut ciphertext, Aad::empty())?;
+        writer.write_all(&[i; 100])?;
+        writer.close()?;
+        streams.push((nonce, ciphertext));
+    }
+    assert_eq!(session.streams(), 4);
+
+    let mut nonces: Vec<&[u8]> = streams.iter().map(|(nonce, _)| nonce.as_ref()).collect();
+    nonces.sort();
+    nonces.dedup();
+    assert_eq!(nonces.len(), streams.len());
+    assert_eq!(nonces[3], [0, 0, 0, 0, 0, 0, 0, 3]);
+
+    for (i, (nonce, ciphertext)) in streams.iter().enumerate() {
+        let mut plaintext = Vec::default();
+        let mut writer = session.dec_writer(&mut plaintext, *nonce, Aad::empty());
+        writer.write_all(ciphertext)?;
+        writer.close()?;
+        assert_eq!(plaintext, [i as u8; 100]);
+    }
+    Ok(())
+}
+
+#[test]
+fn session_limit() -> io::Result<()> {
+    let session = Session::new(Key::<AEAD>::new([0; Key::<AEAD>::SIZE]))
+        .limit(10)
+        .resume(8);
+    assert_eq!(session.remaining(), 2);
+
+    let (nonce, writer) = session.enc_writer(Vec::default(), Aad::empty())?;
+    assert_eq!(nonce.as_ref(), [0, 0, 0, 0, 0, 0, 0, 8]);
+    writer.close()?;
+    let (_, writer) = session.enc_writer(Vec::default(), Aad::empty())?;
+    writer.close()?;
+
+    assert_eq!(session.remaining(), 0);
+    assert!(session.enc_writer(Vec::default(), Aad::empty()).is_err());
+    assert_eq!(session.streams(), 10);
+    Ok(())
+}
+
+#[test]
+fn session_concurrent() {
+    let session = Arc::new(Session::new(Key::<AEAD>::new([0; Key::<AEAD>::SIZE])));
+    let workers: Vec<_> = (0..4)
+        .map(|_| {
+            let session = session.clone();
+            thread::spawn(move || {
+                (0..100)
+                    .map(|_| {
+                        let (nonce, writer) =
+                            session.enc_writer(Vec::default(), Aad::empty()).unwrap();
+                        writer.close().unwrap();
+                        nonce.as_ref().to_vec()
+                    })
+                    .collect::<Vec<_>>()
+            })
+        })
+        .collect();
+
+    let mut nonces: Vec<Vec<u8>> = workers
+        .into_iter()
+        .flat_map(|worker| worker.join().unwrap())
+        .collect();
+    nonces.sort();
+    nonces.dedup();
+    assert_eq!(nonces.len(), 400);
+}
+
+#[derive(Default)]
+struct MemoryStore {
+    value: AtomicU64,
+    updates: AtomicU64,
+}
+
+impl CounterStore for MemoryStore {
+    fn load(&self) -> io::Result<u64> {
+        Ok(self.value.load(Ordering::SeqCst))
+    }
+
+    fn store(&self, next: u64) -> io::Result<()> {
+        self.updates.fetch_add(1, Ordering::SeqCst);
+        self.value.store(next, Ordering::SeqCst);
+        Ok(())
+    }
+}
+
+struct BrokenStore;
+
+impl CounterStore for BrokenStore {
+    fn load(&self) -> io::Result<u64> {
+        Ok(0)
+    }
+
+    fn store(&self, _next: u64) -> io::Result<()> {
+        Err(io::Error::other("disk full"))
+    }
+}
+
+#[test]
+fn session_store() -> io::Result<()> {
+    let store = Arc::new(MemoryStore::default());
+    let key = || Key::<AEAD>::new([0; Key::<AEAD>::SIZE]);
+
+    let session = Session::with_store(key(), store.clone())?.reserve(4);
+    for _ in 0..5 {
+        let (_, writer) = session.enc_writer(Vec::default(), Aad::empty())?;
+        writer.close()?;
+    }
+    assert_eq!(store.value.load(Ordering::SeqCst), 8);
+    assert_eq!(store.updates.load(Ordering::SeqCst), 2);
+    drop(session);
+
+    // A restarted session skips the counters reserved but not used.
+    let session = Session::with_store(key(), store.clone())?;
+    assert_eq!(session.streams(), 8);
+    let (nonce, writer) = session.enc_writer(Vec::default(), Aad::empty())?;
+    writer.close()?;
+    assert_eq!(nonce.as_ref(), [0, 0, 0, 0, 0, 0, 0, 8]);
+    assert_eq!(
+        store.value.load(Ordering::SeqCst),
+        8 + Session::<AEAD>::DEFAULT_RESERVE
+    );
+    Ok(())
+}
+
+#[test]
+fn session_store_limit() -> io::Result<()> {
+    let store = Arc::new(MemoryStore::default());
+    let session =
+        Session::with_store(Key::<AEAD>::new([0; Key::<AEAD>::SIZE]), store.clone())?.limit(2);
+    for _ in 0..2 {
+        let (_, writer) = session.enc_writer(Vec::default(), Aad::empty())?;
+        writer.close()?;
+    }
+    assert!(session.enc_writer(Vec::default(), Aad::empty()).is_err());
+    assert_eq!(store.value.load(Ordering::SeqCst), 2);
+    Ok(())
+}
+
+#[test]
+fn session_store_error() -> io::Result<()> {
+    let session = Session::with_store(Key::<AEAD>::new([0; Key::<AEAD>::SIZE]), BrokenStore)?;
+    let err = session
+        .enc_writer(Vec::default(), Aad::empty())
+        .err()
+        .unwrap();
+    assert_eq!(err.to_string(), "disk full");
+    Ok(())
+}
+
+#[test]
+fn file_store() -> io::Result<()> {
+    let dir = env::temp_dir().join(format!("sio-store-{}", process::id()));
+    fs::create_dir_all(&dir)?;
+    let path = dir.join("counter");
+
+    let store = FileStore::new(&path);
+    assert_eq!(store.load()?, 0);
+    store.store(42)?;
+    assert_eq!(FileStore::new(&path).load()?, 42);
+    assert_eq!(fs::read_dir(&dir)?.count(), 1);
+
+    fs::write(&path, "not a number")?;
+    assert_eq!(
+        store.load().err().unwrap().kind(),
+        io::ErrorKind::InvalidData
+    );
+    fs::remove_dir_all(&dir)
+}
@@ -0,0 +1,97 @@
+// Copyright (c) 2019 Andreas Auernhammer. All rights reserved.
+// Use of this source code is governed by a license that can be
+// found in the LICENSE file.
+
+#![cfg(feature = "ed25519")]
+
+use sio::sign::{SignWriter, SigningKey, VerifyReader, SIGNATURE_LEN};
+use sio::*;
+use std::io::{self, Read, Write};
+
+#[cfg(feature = "aesgcm")]
+#[allow(clippy::upper_case_acronyms)]
+type AEAD = AES_256_GCM;
+
+#[cfg(not(feature = "aesgcm"))]
+#[allow(clippy::upper_case_acronyms)]
+type AEAD = CHACHA20_POLY1305;
+
+#[test]
+fn sign() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let signing_key = SigningKey::from_seed(&[7; 32]);
+    let verifying_key = signing_key.verifying_key();
+    for size in &[0, 1, BUF_SIZE, 3 * BUF_SIZE + 1] {
+        let data: Vec<u8> = (0..*size).map(|i| i as u8).collect();
+        let mut ciphertext = Vec::default();
+        let mut writer = EncWriter::new(
+            SignWriter::new(&mut ciphertext, &signing_key),
+            &key,
+            Nonce::new([0; Nonce::<AEAD>::SIZE]),
+            Aad::empty(),
+        );
+        writer.write_all(&data)?;
+        writer.close()?;
+
+        let mut reader = DecReader::new(
+            VerifyReader::new(ciphertext.as_slice(), &verifying_key),
+            &key,
+            Nonce::new([0; Nonce::<AEAD>::SIZE]),
+            Aad::empty(),
+        );
+        let mut plaintext = Vec::default();
+        reader.read_to_end(&mut plaintext)?;
+        assert_eq!(plaintext, data);
+    }
+    Ok(())
+}
+
+#[test]
+fn sign_invalid_signature() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let signing_key = SigningKey::from_seed(&[7; 32]);
+    let verifying_key = signing_key.verifying_key();
+    let mut ciphertext = Vec::default();
+    let mut writer = EncWriter::new(
+        SignWriter::new(&mut ciphertext, &signing_key),
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+    );
+    writer.write_all(&[0; BUF_SIZE + 1])?;
+    writer.close()?;
+
+    // A signature of another key must be rejected.
+    let other = SigningKey::from_seed(&[8; 32]).verifying_key();
+    let mut reader = DecReader::new(
+        VerifyReader::new(ciphertext.as_slice(), &other),
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+    );
+    let err = reader.read_to_end(&mut Vec::default()).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+    // A modified signature must be rejected.
+    let mut modified = ciphertext.clone();
+    *modified.last_mut().unwrap() ^= 1;
+    let mut reader = DecReader::new(
+        VerifyReader::new(modified.as_slice(), &verifying_key),
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+    );
+    let err = reader.read_to_end(&mut Vec::default()).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+    // A missing signature must be rejected.
+    let stripped = &ciphertext[..ciphertext.len() - SIGNATURE_LEN];
+    let mut reader = DecReader::new(
+        VerifyReader::new(stripped, &verifying_key),
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+    );
+    assert!(reader.read_to_end(&mut Vec::default()).is_err());
+    Ok(())
+}
@@ -0,0 +1,87 @@
+// Copyright (c) 2019 Andreas Auernhammer. All rights reserved.
+// Use of this source code is governed by a license that can be
+// found in the LICENSE file.
+
+#![cfg(feature = "futures-io")]
+
+use sio::*;
+use smol::io::{AsyncReadExt, AsyncWriteExt};
+use smol::net::{TcpListener, TcpStream};
+use std::io;
+
+#[cfg(feature = "aesgcm")]
+#[allow(clippy::upper_case_acronyms)]
+type AEAD = AES_256_GCM;
+
+#[cfg(not(feature = "aesgcm"))]
+#[allow(clippy::upper_case_acronyms)]
+type AEAD = CHACHA20_POLY1305;
+
+#[test]
+fn tcp() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let data: Vec<u8> = (0..3 * BUF_SIZE + 5).map(|i| i as u8).collect();
+
+    smol::block_on(async {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+
+        let server = smol::spawn(async move {
+            let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+            let (stream, _) = listener.accept().await?;
+            let mut reader = AsyncDecReader::new(
+                stream,
+                &key,
+                Nonce::new([0; Nonce::<AEAD>::SIZE]),
+                Aad::from("Some authenticated but not encrypted data".as_bytes()),
+            );
+            let mut plaintext = Vec::default();
+            reader.read_to_end(&mut plaintext).await?;
+            Ok::<_, io::Error>(plaintext)
+        });
+
+        let mut writer = AsyncEncWriter::new(
+            TcpStream::connect(addr).await?,
+            &key,
+            Nonce::new([0; Nonce::<AEAD>::SIZE]),
+            Aad::from("Some authenticated but not encrypted data".as_bytes()),
+        );
+        writer.write_all(&data).await?;
+        writer.close().await?; // Shuts down the write half of the TCP stream.
+
+        assert_eq!(server.await?, data);
+        Ok(())
+    })
+}
+
+#[test]
+fn file() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let data: Vec<u8> = (0..2 * BUF_SIZE + 17).map(|i| i as u8).collect();
+    let path = std::env::temp_dir().join(format!("sio-smol-{}", std::process::id()));
+
+    let result = smol::block_on(async {
+        let mut writer = AsyncEncWriter::new(
+            smol::fs::File::create(&path).await?,
+            &key,
+            Nonce::new([0; Nonce::<AEAD>::SIZE]),
+            Aad::empty(),
+        );
+        writer.write_all(&data).await?;
+        writer.close().await?;
+
+        let mut plaintext = Vec::default();
+        let mut writer = AsyncDecWriter::new(
+            &mut plaintext,
+            &key,
+            Nonce::new([0; Nonce::<AEAD>::SIZE]),
+            Aad::empty(),
+        );
+        smol::io::copy(smol::fs::File::open(&path).await?, &mut writer).await?;
+        writer.close().await?;
+        Ok::<_, io::Error>(plaintext)
+    });
+    std::fs::remove_file(&path)?;
+    assert_eq!(result?, data);
+    Ok(())
+}
@@ -0,0 +1,100 @@
+// Copyright (c) 2019 Andreas Auernhammer. All rights reserved.
+// Use of this source code is governed by a license that can be
+// found in the LICENSE file.
+
+#![cfg(all(feature = "splice", unix))]
+
+use sio::*;
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::os::unix::net::UnixStream;
+use std::{env, process, thread};
+
+#[cfg(feature = "aesgcm")]
+#[allow(clippy::upper_case_acronyms)]
+type AEAD = AES_256_GCM;
+
+#[cfg(not(feature = "aesgcm"))]
+#[allow(clippy::upper_case_acronyms)]
+type AEAD = CHACHA20_POLY1305;
+
+#[test]
+fn forward_file() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let data = vec![7; 3 * BUF_SIZE + 1];
+    let dir = env::temp_dir().join(format!("sio-splice-file-{}", process::id()));
+    fs::create_dir_all(&dir)?;
+
+    let mut ciphertext = Vec::default();
+    let mut writer = EncWriter::new(
+        &mut ciphertext,
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+    );
+    writer.write_all(&data).and_then(|()| writer.close())?;
+    fs::write(dir.join("src"), &ciphertext)?;
+
+    let layout = Layout::of::<AEAD>(BUF_SIZE)?;
+    let len = layout.ciphertext_len(data.len() as u64).unwrap();
+    let mut src = File::open(dir.join("src"))?;
+    let mut dst = File::create(dir.join("dst"))?;
+    assert_eq!(splice::forward(&mut src, &mut dst, len)?, len);
+
+    // A shorter length forwards only a prefix of the stream.
+    let mut src = File::open(dir.join("src"))?;
+    let mut prefix = File::create(dir.join("prefix"))?;
+    assert_eq!(splice::forward(&mut src, &mut prefix, 100)?, 100);
+    assert_eq!(fs::read(dir.join("prefix"))?, &ciphertext[..100]);
+
+    let mut plaintext = Vec::default();
+    let mut reader = DecReader::new(
+        File::open(dir.join("dst"))?,
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+    );
+    reader.read_to_end(&mut plaintext)?;
+    assert_eq!(plaintext, data);
+    fs::remove_dir_all(&dir)
+}
+
+#[test]
+fn forward_socket() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let data = vec![7; 3 * BUF_SIZE + 1];
+    let dir = env::temp_dir().join(format!("sio-splice-socket-{}", process::id()));
+    fs::create_dir_all(&dir)?;
+
+    let mut ciphertext = Vec::default();
+    let mut writer = EncWriter::new(
+        &mut ciphertext,
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+    );
+    writer.write_all(&data).and_then(|()| writer.close())?;
+
+    let (mut src, mut peer) = UnixStream::pair()?;
+    let sender = {
+        let ciphertext = ciphertext.clone();
+        thread::spawn(move || peer.write_all(&ciphertext))
+    };
+
+    // The source ends before `len` bytes have been forwarded.
+    let mut dst = File::create(dir.join("dst"))?;
+    let len = ciphertext.len() as u64;
+    assert_eq!(splice::forward(&mut src, &mut dst, len + 100)?, len);
+    sender.join().unwrap()?;
+
+    let mut plaintext = Vec::default();
+    let mut reader = DecReader::new(
+        File::open(dir.join("dst"))?,
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+    );
+    reader.read_to_end(&mut plaintext)?;
+    assert_eq!(plaintext, data);
+    fs::remove_dir_all(&dir)
+}
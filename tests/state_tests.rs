@@ -0,0 +1,138 @@
+// Copyright (c) 2019 Andreas Auernhammer. All rights reserved.
+// Use of this source code is governed by a license that can be
+// found in the LICENSE file.
+
+use sio::*;
+use std::io::{self, Write};
+
+#[cfg(feature = "aesgcm")]
+#[allow(clippy::upper_case_acronyms)]
+type AEAD = AES_256_GCM;
+
+#[cfg(not(feature = "aesgcm"))]
+#[allow(clippy::upper_case_acronyms)]
+type AEAD = CHACHA20_POLY1305;
+
+#[test]
+fn decrypt_state() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    for len in [0, 1, 63, 64, 65, 500] {
+        let data = vec![1; len];
+
+        let mut ciphertext = Vec::default();
+        let mut writer = EncWriter::with_buffer_size(
+            &mut ciphertext,
+            &key,
+            Nonce::new([0; Nonce::<AEAD>::SIZE]),
+            Aad::empty(),
+            64,
+        )
+        .unwrap();
+        writer.write_all(&data).and_then(|()| writer.close())?;
+
+        for chunk in [1, 7, 64, 1024] {
+            let mut state = DecryptState::with_buffer_size(
+                &key,
+                Nonce::new([0; Nonce::<AEAD>::SIZE]),
+                Aad::empty(),
+                64,
+            )
+            .unwrap();
+
+            let mut plaintext = Vec::default();
+            for mut input in ciphertext.chunks(chunk) {
+                while !input.is_empty() {
+                    let (n, fragment) = state.update(input).unwrap();
+                    plaintext.extend_from_slice(fragment);
+                    input = &input[n..];
+                }
+            }
+            plaintext.extend_from_slice(state.finish().unwrap());
+            assert_eq!(plaintext, data);
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn decrypt_state_needed() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let mut ciphertext = Vec::default();
+    let mut writer = EncWriter::with_buffer_size(
+        &mut ciphertext,
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+        64,
+    )
+    .unwrap();
+    writer.write_all(&[1; 100]).and_then(|()| writer.close())?;
+    let fragment_len = 64 + AEAD::TAG_LEN;
+
+    let mut state = DecryptState::with_buffer_size(
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+        64,
+    )
+    .unwrap();
+    assert_eq!(state.needed(), fragment_len);
+    assert_eq!(state.update(&ciphertext[..10]).unwrap(), (10, &[][..]));
+    assert_eq!(state.needed(), fragment_len - 10);
+
+    let (n, plaintext) = state.update(&ciphertext[10..]).unwrap();
+    assert_eq!((n, plaintext.len()), (fragment_len - 10, 0));
+    assert_eq!(state.needed(), 1);
+
+    let (n, plaintext) = state.update(&ciphertext[fragment_len..]).unwrap();
+    assert_eq!((n, plaintext), (0, &[1; 64][..]));
+    assert_eq!(state.needed(), fragment_len);
+
+    let (n, _) = state.update(&ciphertext[fragment_len..]).unwrap();
+    assert_eq!(n, ciphertext.len() - fragment_len);
+    assert_eq!(state.finish().unwrap(), &[1; 36][..]);
+    assert_eq!(state.needed(), 0);
+    assert!(state.finish().is_err());
+    Ok(())
+}
+
+#[test]
+fn decrypt_state_not_authentic() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let mut ciphertext = Vec::default();
+    let mut writer = EncWriter::with_buffer_size(
+        &mut ciphertext,
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+        64,
+    )
+    .unwrap();
+    writer.write_all(&[1; 100]).and_then(|()| writer.close())?;
+    let fragment_len = 64 + AEAD::TAG_LEN;
+
+    let mut modified = ciphertext.clone();
+    modified[fragment_len] ^= 1;
+    for (input, buf_size) in [
+        (&ciphertext[..fragment_len], 64),
+        (&ciphertext[..ciphertext.len() - 1], 64),
+        (&ciphertext[..], 32),
+        (&modified[..], 64),
+    ] {
+        let mut state = DecryptState::with_buffer_size(
+            &key,
+            Nonce::new([0; Nonce::<AEAD>::SIZE]),
+            Aad::empty(),
+            buf_size,
+        )
+        .unwrap();
+
+        let mut input = input;
+        let mut result = Ok(());
+        while result.is_ok() && !input.is_empty() {
+            result = state.update(input).map(|(n, _)| input = &input[n..]);
+        }
+        assert!(result.and_then(|()| state.finish()).is_err());
+    }
+    Ok(())
+}
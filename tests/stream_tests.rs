@@ -0,0 +1,117 @@
+// Copyright (c) 2019 Andreas Auernhammer. All rights reserved.
+// Use of this source code is governed by a license that can be
+// found in the LICENSE file.
+
+#![cfg(feature = "futures")]
+
+use bytes::Bytes;
+use futures_lite::future::block_on;
+use futures_util::{stream, SinkExt, StreamExt};
+use sio::*;
+use std::io::{self, Write};
+
+#[cfg(feature = "aesgcm")]
+#[allow(clippy::upper_case_acronyms)]
+type AEAD = AES_256_GCM;
+
+#[cfg(not(feature = "aesgcm"))]
+#[allow(clippy::upper_case_acronyms)]
+type AEAD = CHACHA20_POLY1305;
+
+#[test]
+fn sink() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let plaintext: Vec<u8> = (0..3 * BUF_SIZE + 7).map(|i| i as u8).collect();
+
+    let mut expected = Vec::default();
+    let mut writer = EncWriter::new(
+        &mut expected,
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+    );
+    writer.write_all(&plaintext)?;
+    writer.close()?;
+
+    let mut ciphertext = Vec::default();
+    block_on(async {
+        let mut writer = AsyncEncWriter::new(
+            &mut ciphertext,
+            &key,
+            Nonce::new([0; Nonce::<AEAD>::SIZE]),
+            Aad::empty(),
+        );
+        let chunks: Vec<io::Result<Bytes>> = plaintext
+            .chunks(5000)
+            .map(|chunk| Ok(Bytes::copy_from_slice(chunk)))
+            .collect();
+        writer.send_all(&mut stream::iter(chunks)).await?;
+        writer.close().await?;
+        assert!(writer.send(Bytes::from("more")).await.is_err());
+        Ok::<(), io::Error>(())
+    })?;
+    assert_eq!(ciphertext, expected);
+    Ok(())
+}
+
+#[test]
+fn stream() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let plaintext: Vec<u8> = (0..3 * BUF_SIZE + 7).map(|i| i as u8).collect();
+
+    let mut ciphertext = Vec::default();
+    let mut writer = EncWriter::new(
+        &mut ciphertext,
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+    );
+    writer.write_all(&plaintext)?;
+    writer.close()?;
+
+    let chunks: Vec<Bytes> = block_on(async {
+        AsyncDecReader::new(
+            ciphertext.as_slice(),
+            &key,
+            Nonce::new([0; Nonce::<AEAD>::SIZE]),
+            Aad::empty(),
+        )
+        .map(|chunk| chunk.unwrap())
+        .collect()
+        .await
+    });
+    assert_eq!(chunks.len(), 4);
+    assert_eq!(chunks.concat(), plaintext);
+    Ok(())
+}
+
+#[test]
+fn stream_modified() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let plaintext = vec![1; 2 * BUF_SIZE];
+
+    let mut ciphertext = Vec::default();
+    let mut writer = EncWriter::new(
+        &mut ciphertext,
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+    );
+    writer.write_all(&plaintext)?;
+    writer.close()?;
+    ciphertext[BUF_SIZE + 1] ^= 1;
+
+    let items: Vec<io::Result<Bytes>> = block_on(
+        AsyncDecReader::new(
+            ciphertext.as_slice(),
+            &key,
+            Nonce::new([0; Nonce::<AEAD>::SIZE]),
+            Aad::empty(),
+        )
+        .collect(),
+    );
+    assert_eq!(items.len(), 1);
+    let err = items.into_iter().next().unwrap().unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    Ok(())
+}
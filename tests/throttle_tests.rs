@@ -0,0 +1,58 @@
+// Copyright (c) 2019 Andreas Auernhammer. All rights reserved.
+// Use of this source code is governed by a license that can be
+// found in the LICENSE file.
+
+use sio::*;
+use std::io::{self, Write};
+use std::time::{Duration, Instant};
+
+#[cfg(feature = "aesgcm")]
+#[allow(clippy::upper_case_acronyms)]
+type AEAD = AES_256_GCM;
+
+#[cfg(not(feature = "aesgcm"))]
+#[allow(clippy::upper_case_acronyms)]
+type AEAD = CHACHA20_POLY1305;
+
+#[test]
+fn throttle() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let data = vec![0; 3 * BUF_SIZE];
+
+    let start = Instant::now();
+    let mut ciphertext = Vec::default();
+    let mut writer = EncWriter::new(
+        Throttle::new(&mut ciphertext, 4 * BUF_SIZE as u64),
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+    );
+    writer.write_all(&data)?;
+    writer.close()?;
+
+    // 3 fragments and their tags at 4 fragments per second.
+    assert!(start.elapsed() >= Duration::from_millis(750));
+    assert_eq!(ciphertext.len(), data.len() + 3 * AEAD::TAG_LEN);
+
+    let mut plaintext = Vec::default();
+    let mut writer = DecWriter::new(
+        &mut plaintext,
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+    );
+    writer.write_all(&ciphertext)?;
+    writer.close()?;
+    assert_eq!(plaintext, data);
+    Ok(())
+}
+
+#[test]
+fn throttle_close() -> io::Result<()> {
+    let mut sink = Vec::default();
+    let mut throttle = Throttle::new(&mut sink, 1 << 20);
+    throttle.write_all(b"Hello World")?;
+    throttle.close()?;
+    assert_eq!(throttle.into_inner().as_slice(), b"Hello World");
+    Ok(())
+}
@@ -0,0 +1,113 @@
+// Copyright (c) 2019 Andreas Auernhammer. All rights reserved.
+// Use of this source code is governed by a license that can be
+// found in the LICENSE file.
+
+#![cfg(all(feature = "tpm", unix))]
+
+use sio::tpm::SealedKey;
+use sio::*;
+use std::io::{self, Read, Write};
+use std::os::unix::fs::PermissionsExt;
+use std::{env, fs, process};
+
+#[cfg(feature = "aesgcm")]
+#[allow(clippy::upper_case_acronyms)]
+type AEAD = AES_256_GCM;
+
+#[cfg(not(feature = "aesgcm"))]
+#[allow(clippy::upper_case_acronyms)]
+type AEAD = CHACHA20_POLY1305;
+
+#[test]
+fn key_provider() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([7; Key::<AEAD>::SIZE]);
+
+    let mut ciphertext = Vec::default();
+    let mut writer = EncWriter::with_key_provider(
+        &mut ciphertext,
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+    )?;
+    writer
+        .write_all(b"Hello World")
+        .and_then(|()| writer.close())?;
+
+    let mut plaintext = Vec::default();
+    let mut reader = DecReader::new(
+        ciphertext.as_slice(),
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+    );
+    reader.read_to_end(&mut plaintext)?;
+    assert_eq!(plaintext, b"Hello World");
+    Ok(())
+}
+
+#[test]
+fn sealed_key() -> io::Result<()> {
+    // A stand-in for tpm2_unseal that only "unseals" the key if it
+    // gets invoked with the expected handle and policy.
+    let dir = env::temp_dir().join(format!("sio-tpm-{}", process::id()));
+    fs::create_dir_all(&dir)?;
+    let program = dir.join("tpm2_unseal");
+    fs::write(
+        &program,
+        "#!/bin/sh\n\
+         [ \"$*\" = \"-c 0x81010001 -p pcr:sha256:0,7\" ] || { echo 'policy check failed' >&2; exit 1; }\n\
+         printf '%032d' 7\n",
+    )?;
+    fs::set_permissions(&program, fs::Permissions::from_mode(0o755))?;
+
+    let sealed_key = SealedKey::new("0x81010001")
+        .auth("pcr:sha256:0,7")
+        .program(&program);
+    let mut ciphertext = Vec::default();
+    let mut writer = EncWriter::<AEAD, _>::with_key_provider(
+        &mut ciphertext,
+        &sealed_key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+    )?;
+    writer
+        .write_all(b"Hello World")
+        .and_then(|()| writer.close())?;
+
+    let mut key = [0; Key::<AEAD>::SIZE];
+    key.copy_from_slice(format!("{:032}", 7).as_bytes());
+    let mut plaintext = Vec::default();
+    let mut reader = DecReader::new(
+        ciphertext.as_slice(),
+        &Key::<AEAD>::new(key),
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+    );
+    reader.read_to_end(&mut plaintext)?;
+    assert_eq!(plaintext, b"Hello World");
+
+    // The policy is not satisfied.
+    let err = DecWriter::<AEAD, _>::with_key_provider(
+        io::sink(),
+        &SealedKey::new("0x81010001").program(&program),
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+    )
+    .err()
+    .unwrap();
+    assert!(err.to_string().contains("policy check failed"));
+
+    // The unsealed secret is not a key.
+    fs::write(&program, "#!/bin/sh\nprintf 'not a key'\n")?;
+    let err = DecReader::<AEAD, _>::with_key_provider(
+        ciphertext.as_slice(),
+        &sealed_key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+    )
+    .err()
+    .unwrap();
+    assert!(err.get_ref().is_some_and(|e| e.is::<Invalid>()));
+
+    fs::remove_dir_all(&dir)
+}
@@ -0,0 +1,85 @@
+// Copyright (c) 2019 Andreas Auernhammer. All rights reserved.
+// Use of this source code is governed by a license that can be
+// found in the LICENSE file.
+
+use sio::*;
+use std::io::{self, Write};
+
+#[cfg(feature = "aesgcm")]
+#[allow(clippy::upper_case_acronyms)]
+type AEAD = AES_256_GCM;
+
+#[cfg(not(feature = "aesgcm"))]
+#[allow(clippy::upper_case_acronyms)]
+type AEAD = CHACHA20_POLY1305;
+
+#[test]
+fn verify_stream() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    for buf_size in &[1, 7, BUF_SIZE] {
+        for size in &[0, 1, *buf_size, 3 * buf_size, 3 * buf_size + 1] {
+            let data = vec![0; *size];
+
+            let mut ciphertext = Vec::default();
+            let mut writer = EncWriter::with_buffer_size(
+                &mut ciphertext,
+                &key,
+                Nonce::new([0; Nonce::<AEAD>::SIZE]),
+                Aad::empty(),
+                *buf_size,
+            )?;
+            writer.write_all(&data).and_then(|()| writer.close())?;
+
+            let n = sio::verify(
+                ciphertext.as_slice(),
+                &key,
+                Nonce::new([0; Nonce::<AEAD>::SIZE]),
+                Aad::empty(),
+                *buf_size,
+            )?;
+            assert_eq!(n, *size as u64);
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn verify_not_authentic() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let mut ciphertext = Vec::default();
+    let mut writer = EncWriter::with_buffer_size(
+        &mut ciphertext,
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+        BUF_SIZE,
+    )?;
+    writer
+        .write_all(&[0; 3 * BUF_SIZE + 1])
+        .and_then(|()| writer.close())?;
+
+    let mut modified = ciphertext.clone();
+    modified[BUF_SIZE + AEAD::TAG_LEN + 1] ^= 1;
+    // Truncating the stream at a fragment boundary removes the final fragment.
+    let truncated = ciphertext[..3 * (BUF_SIZE + AEAD::TAG_LEN)].to_vec();
+    let mut extended = ciphertext.clone();
+    extended.push(0);
+
+    for (stream, buf_size) in &[
+        (modified, BUF_SIZE),
+        (truncated, BUF_SIZE),
+        (extended, BUF_SIZE),
+        (Vec::default(), BUF_SIZE),
+        (ciphertext, BUF_SIZE + 1),
+    ] {
+        let result = sio::verify(
+            stream.as_slice(),
+            &key,
+            Nonce::new([0; Nonce::<AEAD>::SIZE]),
+            Aad::empty(),
+            *buf_size,
+        );
+        assert!(result.is_err());
+    }
+    Ok(())
+}
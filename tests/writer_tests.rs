@@ -2,13 +2,17 @@
 // Use of this source code is governed by a license that can be
 // found in the LICENSE file.
 
+use sio::framing::{FragmentSize, Sio};
 use sio::*;
-use std::{io, io::Write};
+use std::io::{self, Cursor, IoSlice, Read, Write};
+use std::sync::atomic::{AtomicUsize, Ordering};
 
 #[cfg(feature = "aesgcm")]
+#[allow(clippy::upper_case_acronyms)]
 type AEAD = AES_256_GCM;
 
 #[cfg(not(feature = "aesgcm"))]
+#[allow(clippy::upper_case_acronyms)]
 type AEAD = CHACHA20_POLY1305;
 
 #[test]
@@ -105,3 +109,1519 @@ fn close() -> io::Result<()> {
     assert_eq!(data.as_ref(), plaintext.as_slice());
     Ok(())
 }
+
+fn encrypt(data: &[u8], buf_size: usize) -> io::Result<Vec<u8>> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let mut ciphertext = Vec::default();
+    let mut writer = EncWriter::with_buffer_size(
+        &mut ciphertext,
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+        buf_size,
+    )?;
+    writer.write_all(data).and_then(|()| writer.close())?;
+    Ok(ciphertext)
+}
+
+#[test]
+fn strict() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    for size in &[0, 1, 63, 64, 65, 1000] {
+        let data: Vec<u8> = (0..*size).map(|i| i as u8).collect();
+        let mut ciphertext = Vec::default();
+        let mut writer = EncWriter::with_framing(
+            &mut ciphertext,
+            &key,
+            Nonce::new([0; Nonce::<AEAD>::SIZE]),
+            Aad::empty(),
+            64,
+            framing::Delimited::new(framing::Sio::default(), data.len() as u64),
+        )?;
+        writer.write_all(&data)?;
+        writer.close()?;
+
+        let mut plaintext = Vec::default();
+        let mut writer = DecWriter::with_framing(
+            &mut plaintext,
+            &key,
+            Nonce::new([0; Nonce::<AEAD>::SIZE]),
+            Aad::empty(),
+            64,
+            framing::Delimited::<framing::Sio>::default(),
+        )?
+        .strict();
+        for chunk in ciphertext.chunks(7) {
+            writer.write_all(chunk)?;
+        }
+        writer.close()?;
+        assert_eq!(plaintext, data);
+    }
+    Ok(())
+}
+
+#[test]
+fn strict_trailing_data() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    for size in &[0, 100, 2 * 64] {
+        let data = vec![0; *size];
+        let mut ciphertext = Vec::default();
+        let mut writer = EncWriter::with_framing(
+            &mut ciphertext,
+            &key,
+            Nonce::new([0; Nonce::<AEAD>::SIZE]),
+            Aad::empty(),
+            64,
+            framing::Delimited::new(framing::Sio::default(), data.len() as u64),
+        )?;
+        writer.write_all(&data)?;
+        writer.close()?;
+        ciphertext.extend_from_slice(&[1; 200]);
+
+        let mut plaintext = Vec::default();
+        let mut writer = DecWriter::with_framing(
+            &mut plaintext,
+            &key,
+            Nonce::new([0; Nonce::<AEAD>::SIZE]),
+            Aad::empty(),
+            64,
+            framing::Delimited::<framing::Sio>::default(),
+        )?
+        .strict();
+        let err = writer.write_all(&ciphertext).unwrap_err();
+        assert!(err.get_ref().is_some_and(|e| e.is::<TrailingData>()));
+        drop(writer);
+        assert_eq!(plaintext, data);
+    }
+    Ok(())
+}
+
+#[test]
+fn strict_without_length() -> io::Result<()> {
+    // Without a recorded length, the final fragment is found by trying
+    // all of its possible lengths.
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    for size in &[0, 10, 64, 1000] {
+        for trailing in &[1, 10, 200] {
+            let data = vec![7; *size];
+            let mut ciphertext = encrypt(&data, 64)?;
+            ciphertext.extend_from_slice(&vec![1; *trailing]);
+
+            let mut plaintext = Vec::default();
+            let mut writer = DecWriter::with_buffer_size(
+                &mut plaintext,
+                &key,
+                Nonce::new([0; Nonce::<AEAD>::SIZE]),
+                Aad::empty(),
+                64,
+            )?
+            .strict();
+            let err = writer
+                .write_all(&ciphertext)
+                .and_then(|()| writer.close())
+                .unwrap_err();
+            assert!(err.get_ref().is_some_and(|e| e.is::<TrailingData>()));
+            assert_eq!(plaintext, data);
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn strict_not_authentic() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let mut ciphertext = Vec::default();
+    let mut writer = EncWriter::with_framing(
+        &mut ciphertext,
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+        64,
+        framing::Delimited::new(framing::Sio::default(), 1000),
+    )?;
+    writer.write_all(&[0; 1000])?;
+    writer.close()?;
+
+    // The recorded length is authenticated.
+    for i in &[7, 100] {
+        let mut ciphertext = ciphertext.clone();
+        ciphertext[*i] ^= 1;
+
+        let mut writer = DecWriter::with_framing(
+            io::sink(),
+            &key,
+            Nonce::new([0; Nonce::<AEAD>::SIZE]),
+            Aad::empty(),
+            64,
+            framing::Delimited::<framing::Sio>::default(),
+        )?
+        .strict();
+        let err = writer
+            .write_all(&ciphertext)
+            .and_then(|()| writer.close())
+            .unwrap_err();
+        assert!(err.get_ref().is_some_and(|e| e.is::<NotAuthentic>()));
+    }
+    Ok(())
+}
+
+#[test]
+fn delimited_length_mismatch() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let mut writer = EncWriter::with_framing(
+        Vec::default(),
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+        64,
+        framing::Delimited::new(framing::Sio::default(), 100),
+    )?;
+    writer.write_all(&[0; 99])?;
+    let err = writer.close().unwrap_err();
+    assert!(err.get_ref().is_some_and(|e| e.is::<LengthMismatch>()));
+    Ok(())
+}
+
+#[test]
+fn tolerant_concatenated() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    for sizes in &[[1000, 1000], [2 * 64, 10], [0, 500], [10, 0]] {
+        let mut journal = Vec::default();
+        let mut offsets = vec![0];
+        for size in sizes {
+            let mut writer = EncWriter::with_framing(
+                &mut journal,
+                &key,
+                Nonce::new([0; Nonce::<AEAD>::SIZE]),
+                Aad::empty(),
+                64,
+                framing::Delimited::new(framing::Sio::default(), *size as u64),
+            )?;
+            writer.write_all(&vec![*size as u8; *size])?;
+            writer.close()?;
+            offsets.push(journal.len() as u64);
+        }
+
+        let mut offset = 0;
+        for size in sizes {
+            let mut plaintext = Vec::default();
+            let mut writer = DecWriter::with_framing(
+                &mut plaintext,
+                &key,
+                Nonce::new([0; Nonce::<AEAD>::SIZE]),
+                Aad::empty(),
+                64,
+                framing::Delimited::<framing::Sio>::default(),
+            )?
+            .tolerant();
+            for chunk in journal[offset as usize..].chunks(7) {
+                writer.write_all(chunk)?;
+            }
+            offset += writer.finish()?;
+            assert_eq!(plaintext, vec![*size as u8; *size]);
+        }
+        assert_eq!(offset, journal.len() as u64);
+    }
+    Ok(())
+}
+
+#[test]
+fn tolerant_write_stops_at_final_fragment() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let mut ciphertext = Vec::default();
+    let mut writer = EncWriter::with_framing(
+        &mut ciphertext,
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+        64,
+        framing::Delimited::new(framing::Sio::default(), 100),
+    )?;
+    writer.write_all(&[0; 100])?;
+    writer.close()?;
+    let len = ciphertext.len();
+    ciphertext.extend_from_slice(&[1; 200]);
+
+    let mut writer = DecWriter::with_framing(
+        io::sink(),
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+        64,
+        framing::Delimited::<framing::Sio>::default(),
+    )?
+    .tolerant();
+    assert_eq!(writer.write(&ciphertext)?, len);
+    assert_eq!(writer.write(&ciphertext[len..])?, 0);
+    assert_eq!(writer.finish()?, len as u64);
+    Ok(())
+}
+
+#[test]
+fn tolerant_without_length() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    for sizes in &[[1000, 1000], [2 * 64, 10], [64, 64], [0, 500], [10, 0]] {
+        let mut journal = Vec::default();
+        for size in sizes {
+            journal.extend_from_slice(&encrypt(&vec![*size as u8; *size], 64)?);
+        }
+
+        let mut offset = 0;
+        for size in sizes {
+            let mut plaintext = Vec::default();
+            let mut writer = DecWriter::with_buffer_size(
+                &mut plaintext,
+                &key,
+                Nonce::new([0; Nonce::<AEAD>::SIZE]),
+                Aad::empty(),
+                64,
+            )?
+            .tolerant();
+            for chunk in journal[offset as usize..].chunks(7) {
+                writer.write_all(chunk)?;
+            }
+            offset += writer.finish()?;
+            assert_eq!(plaintext, vec![*size as u8; *size]);
+        }
+        assert_eq!(offset, journal.len() as u64);
+    }
+    Ok(())
+}
+
+#[test]
+fn tolerant_without_length_small_streams() -> io::Result<()> {
+    // Finding the end of a short final fragment must not try
+    // all lengths up to the fragment size.
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let mut journal = Vec::default();
+    for i in 0..1000 {
+        let mut writer = EncWriter::new(
+            &mut journal,
+            &key,
+            Nonce::new([0; Nonce::<AEAD>::SIZE]),
+            Aad::empty(),
+        );
+        writer.write_all(&[i as u8; 5])?;
+        writer.close()?;
+    }
+
+    let mut offset = 0;
+    for i in 0..1000 {
+        let mut plaintext = Vec::default();
+        let mut writer = DecWriter::new(
+            &mut plaintext,
+            &key,
+            Nonce::new([0; Nonce::<AEAD>::SIZE]),
+            Aad::empty(),
+        )
+        .tolerant();
+        writer.write_all(&journal[offset as usize..])?;
+        offset += writer.finish()?;
+        assert_eq!(plaintext, [i as u8; 5]);
+    }
+    assert_eq!(offset, journal.len() as u64);
+    Ok(())
+}
+
+#[test]
+fn tolerant_not_authentic() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let mut ciphertext = Vec::default();
+    let mut writer = EncWriter::with_framing(
+        &mut ciphertext,
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+        64,
+        framing::Delimited::new(framing::Sio::default(), 1000),
+    )?;
+    writer.write_all(&[0; 1000])?;
+    writer.close()?;
+    ciphertext[100] ^= 1;
+
+    let mut writer = DecWriter::with_framing(
+        io::sink(),
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+        64,
+        framing::Delimited::<framing::Sio>::default(),
+    )?
+    .tolerant();
+    let err = writer.write_all(&ciphertext).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    Ok(())
+}
+
+#[test]
+fn remaining_capacity() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let mut writer = EncWriter::with_buffer_size(
+        io::sink(),
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+        64,
+    )?;
+
+    // Sequence number 0 is used to authenticate the associated data.
+    let fragments = (1 << 32) - 1;
+    assert_eq!(writer.remaining_fragments(), fragments);
+    assert_eq!(writer.remaining_bytes(), fragments * 64);
+
+    writer.write_all(&[0; 3 * 64 + 1])?;
+    assert_eq!(writer.remaining_fragments(), fragments - 3);
+    assert_eq!(writer.remaining_bytes(), (fragments - 3) * 64 - 1);
+    writer.close()
+}
+
+fn append(log: &mut Cursor<Vec<u8>>, data: &[u8]) -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let existing_len = log.get_ref().len() as u64;
+    let mut writer = EncWriter::append(
+        NopCloser::wrap(log),
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+        existing_len,
+    )?;
+    writer.write_all(data).and_then(|()| writer.close())
+}
+
+#[test]
+fn append_to_stream() -> io::Result<()> {
+    let data: Vec<u8> = (0..4 * BUF_SIZE + 100).map(|i| i as u8).collect();
+    let mut log = Cursor::new(Vec::default());
+
+    let mut n = 0;
+    for size in &[0, 1, BUF_SIZE - 1, 0, BUF_SIZE, 1, 2 * BUF_SIZE + 99] {
+        append(&mut log, &data[n..n + size])?;
+        n += size;
+        assert_eq!(log.get_ref(), &encrypt(&data[..n], BUF_SIZE)?);
+    }
+    assert_eq!(n, data.len());
+    Ok(())
+}
+
+#[test]
+fn append_not_authentic() -> io::Result<()> {
+    let mut log = Cursor::new(encrypt(&[0; BUF_SIZE + 1], BUF_SIZE)?);
+    log.get_mut().truncate(BUF_SIZE + AEAD::TAG_LEN);
+
+    let err = append(&mut log, b"Hello World").unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    Ok(())
+}
+
+fn decrypt_hold_back(ciphertext: &[u8], limit: usize) -> (io::Result<()>, Vec<u8>) {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let mut plaintext = Vec::default();
+    let result = DecWriter::with_buffer_size(
+        &mut plaintext,
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+        64,
+    )
+    .map_err(io::Error::from)
+    .and_then(|writer| {
+        let mut writer = writer.hold_back(limit);
+        for chunk in ciphertext.chunks(100) {
+            writer.write_all(chunk)?;
+        }
+        writer.close()
+    });
+    (result, plaintext)
+}
+
+#[test]
+fn hold_back() -> io::Result<()> {
+    let data: Vec<u8> = (0..1000).map(|i| i as u8).collect();
+    let ciphertext = encrypt(&data, 64)?;
+
+    let (result, plaintext) = decrypt_hold_back(&ciphertext, data.len());
+    result?;
+    assert_eq!(plaintext, data);
+
+    let fragment_len = 64 + AEAD::TAG_LEN;
+    for len in &[fragment_len, 10 * fragment_len, ciphertext.len() - 1] {
+        let (result, plaintext) = decrypt_hold_back(&ciphertext[..*len], data.len());
+        assert!(result.is_err());
+        assert!(plaintext.is_empty());
+    }
+
+    let (result, plaintext) = decrypt_hold_back(&ciphertext, data.len() - 1);
+    assert!(result.is_err());
+    assert!(plaintext.is_empty());
+    Ok(())
+}
+
+fn decrypt_auto(ciphertext: &[u8], chunk_size: usize) -> io::Result<Vec<u8>> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let mut plaintext = Vec::default();
+    let mut writer = DecWriter::new_auto(
+        &mut plaintext,
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+    );
+    for chunk in ciphertext.chunks(chunk_size) {
+        writer.write_all(chunk)?;
+    }
+    writer.close()?;
+    Ok(plaintext)
+}
+
+#[test]
+fn new_auto() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    for buf_size in [1, 64, 1000, BUF_SIZE].iter() {
+        for len in [0, 1, *buf_size, 3 * *buf_size + 7].iter() {
+            let data: Vec<u8> = (0..*len).map(|i| i as u8).collect();
+            let mut ciphertext = Vec::default();
+            let mut writer = EncWriter::with_framing(
+                &mut ciphertext,
+                &key,
+                Nonce::new([0; Nonce::<AEAD>::SIZE]),
+                Aad::empty(),
+                *buf_size,
+                FragmentSize::new(Sio::default(), *buf_size),
+            )?;
+            writer.write_all(&data).and_then(|()| writer.close())?;
+
+            assert_eq!(decrypt_auto(&ciphertext, ciphertext.len())?, data);
+            assert_eq!(decrypt_auto(&ciphertext, 3)?, data);
+
+            let mut plaintext = Vec::default();
+            let mut reader = DecReader::with_framing(
+                ciphertext.as_slice(),
+                &key,
+                Nonce::new([0; Nonce::<AEAD>::SIZE]),
+                Aad::empty(),
+                BUF_SIZE,
+                FragmentSize::<Sio>::default(),
+            )?;
+            reader.read_to_end(&mut plaintext)?;
+            assert_eq!(plaintext, data);
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn new_auto_not_authentic() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let mut ciphertext = Vec::default();
+    let mut writer = EncWriter::with_framing(
+        &mut ciphertext,
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+        1024,
+        FragmentSize::new(Sio::default(), 1024),
+    )?;
+    writer.write_all(&[0; 3000]).and_then(|()| writer.close())?;
+
+    // The recorded fragment size is authenticated.
+    let mut tampered = ciphertext.clone();
+    tampered[..4].copy_from_slice(&512u32.to_be_bytes());
+    let err = decrypt_auto(&tampered, 100).unwrap_err();
+    assert!(err.get_ref().is_some_and(|e| e.is::<NotAuthentic>()));
+
+    // No EncWriter produces fragments of this size.
+    tampered[..4].copy_from_slice(&u32::MAX.to_be_bytes());
+    let err = decrypt_auto(&tampered, 100).unwrap_err();
+    assert!(err.get_ref().is_some_and(|e| e.is::<NotAuthentic>()));
+
+    // A stream that does not record its fragment size.
+    let err = decrypt_auto(&encrypt(&[0; 3000], 1024)?, 100).unwrap_err();
+    assert!(err.get_ref().is_some_and(|e| e.is::<NotAuthentic>()));
+
+    // The framing must record the buffer size of the EncWriter.
+    assert!(EncWriter::with_framing(
+        io::sink(),
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+        1024,
+        FragmentSize::new(Sio::default(), 512),
+    )
+    .is_err());
+    Ok(())
+}
+
+// A sink that accepts at most 7 bytes per write and
+// fails every other write with `ErrorKind::WouldBlock`.
+struct SlowSink {
+    data: Vec<u8>,
+    blocked: bool,
+}
+
+impl Write for SlowSink {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.blocked = !self.blocked;
+        if self.blocked {
+            return Err(io::Error::from(io::ErrorKind::WouldBlock));
+        }
+        let n = buf.len().min(7);
+        self.data.extend_from_slice(&buf[..n]);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Close for SlowSink {
+    fn close(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+fn retry<T>(mut f: impl FnMut() -> io::Result<T>) -> io::Result<T> {
+    loop {
+        match f() {
+            Err(ref err) if err.kind() == io::ErrorKind::WouldBlock => {}
+            r => return r,
+        }
+    }
+}
+
+#[test]
+fn backpressure() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let data: Vec<u8> = (0..1000).map(|i| i as u8).collect();
+
+    let mut sink = SlowSink {
+        data: Vec::default(),
+        blocked: false,
+    };
+    let mut writer = EncWriter::with_buffer_size(
+        &mut sink,
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+        64,
+    )?
+    .backpressure()?
+    .closer();
+
+    let mut buf = data.as_slice();
+    while !buf.is_empty() {
+        let n = retry(|| writer.write(buf))?;
+        assert!(n > 0 && n <= 64);
+        buf = &buf[n..];
+    }
+    retry(|| writer.flush())?;
+    retry(|| writer.close())?;
+    drop(writer);
+
+    assert_eq!(sink.data, encrypt(&data, 64)?);
+    Ok(())
+}
+
+// A reader that returns at most 7 bytes per read.
+struct SlowReader<'a>(&'a [u8]);
+
+impl io::Read for SlowReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = buf.len().min(self.0.len()).min(7);
+        buf[..n].copy_from_slice(&self.0[..n]);
+        self.0 = &self.0[n..];
+        Ok(n)
+    }
+}
+
+#[test]
+fn copy_from() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    for size in &[0, 1, 63, 64, 65, 128, 1000] {
+        let data: Vec<u8> = (0..*size).map(|i| i as u8).collect();
+        let expected = encrypt(&data, 64)?;
+
+        let mut ciphertext = Vec::default();
+        let mut writer = EncWriter::with_buffer_size(
+            &mut ciphertext,
+            &key,
+            Nonce::new([0; Nonce::<AEAD>::SIZE]),
+            Aad::empty(),
+            64,
+        )?;
+        writer.write_all(&data[..size / 3])?;
+        assert_eq!(
+            writer.copy_from(&data[size / 3..size / 2])?,
+            (size / 2 - size / 3) as u64
+        );
+        assert_eq!(
+            writer.copy_from(SlowReader(&data[size / 2..]))?,
+            (size - size / 2) as u64
+        );
+        writer.close()?;
+        assert_eq!(ciphertext, expected);
+    }
+    Ok(())
+}
+
+#[test]
+fn digests() -> io::Result<()> {
+    use ring::digest::{digest, SHA256};
+
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let data: Vec<u8> = (0..1000).map(|i| i as u8).collect();
+    for backpressure in &[false, true] {
+        let mut ciphertext = Vec::default();
+        let mut writer = EncWriter::with_buffer_size(
+            &mut ciphertext,
+            &key,
+            Nonce::new([0; Nonce::<AEAD>::SIZE]),
+            Aad::empty(),
+            64,
+        )?
+        .with_digests();
+        if *backpressure {
+            writer = writer.backpressure()?;
+        }
+        writer.write_all(&data[..100])?;
+        writer.copy_from(&data[100..])?;
+        let digests = writer.close_with_digests()?;
+
+        assert_eq!(ciphertext, encrypt(&data, 64)?);
+        assert_eq!(digests.plaintext(), digest(&SHA256, &data).as_ref());
+        assert_eq!(digests.ciphertext(), digest(&SHA256, &ciphertext).as_ref());
+    }
+    Ok(())
+}
+
+// A writer that counts the number of write calls.
+#[derive(Default)]
+struct CountingSink {
+    data: Vec<u8>,
+    writes: usize,
+}
+
+impl Write for CountingSink {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.writes += 1;
+        self.data.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn output_buffer() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let data: Vec<u8> = (0..1000).map(|i| i as u8).collect();
+
+    let mut sink = CountingSink::default();
+    let mut writer = EncWriter::with_buffer_size(
+        NopCloser::wrap(&mut sink),
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+        64,
+    )?
+    .output_buffer(4096)?;
+    writer.write_all(&data[..500])?;
+    writer.flush()?;
+    writer.write_all(&data[500..])?;
+    writer.close()?;
+
+    // One write for the fragments before the flush and
+    // one for the remaining fragments.
+    assert_eq!(sink.writes, 2);
+    assert_eq!(sink.data, encrypt(&data, 64)?);
+    Ok(())
+}
+
+#[test]
+fn output_buffer_small() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let data: Vec<u8> = (0..1000).map(|i| i as u8).collect();
+    for size in &[1, 64 + AEAD::TAG_LEN, 200] {
+        let mut ciphertext = Vec::default();
+        let mut writer = EncWriter::with_buffer_size(
+            &mut ciphertext,
+            &key,
+            Nonce::new([0; Nonce::<AEAD>::SIZE]),
+            Aad::empty(),
+            64,
+        )?
+        .output_buffer(*size)?;
+        writer.write_all(&data[..100])?;
+        writer.copy_from(&data[100..])?;
+        writer.close()?;
+
+        assert_eq!(ciphertext, encrypt(&data, 64)?);
+    }
+    Ok(())
+}
+
+#[test]
+fn output_buffer_invalid() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+
+    // The writers must not panic when they get dropped.
+    let err = EncWriter::new(
+        io::sink(),
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+    )
+    .output_buffer(0)
+    .err()
+    .unwrap();
+    assert!(err.get_ref().is_some_and(|e| e.is::<Invalid>()));
+
+    let err = EncWriter::new(
+        io::sink(),
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+    )
+    .backpressure()?
+    .output_buffer(4096)
+    .err()
+    .unwrap();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+
+    let err = EncWriter::new(
+        io::sink(),
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+    )
+    .output_buffer(4096)?
+    .backpressure()
+    .err()
+    .unwrap();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    Ok(())
+}
+
+static BATCHES: AtomicUsize = AtomicUsize::new(0);
+
+// An algorithm that counts the number of fragments sealed as batch.
+struct Batching(AEAD);
+
+impl Algorithm for Batching {
+    const KEY_LEN: usize = AEAD::KEY_LEN;
+    const NONCE_LEN: usize = AEAD::NONCE_LEN;
+    const TAG_LEN: usize = AEAD::TAG_LEN;
+
+    fn new(key: &[u8; 32]) -> Self {
+        Batching(AEAD::new(key))
+    }
+
+    fn seal_in_place<'a>(
+        &self,
+        nonce: &[u8],
+        aad: &[u8],
+        in_out: &'a mut [u8],
+    ) -> Result<&'a [u8], Invalid> {
+        self.0.seal_in_place(nonce, aad, in_out)
+    }
+
+    fn open_in_place<'a>(
+        &self,
+        nonce: &[u8],
+        aad: &[u8],
+        in_out: &'a mut [u8],
+    ) -> Result<&'a [u8], NotAuthentic> {
+        self.0.open_in_place(nonce, aad, in_out)
+    }
+
+    fn seal_batch(
+        &self,
+        nonces: &[u8],
+        aad: &[u8],
+        in_out: &mut [u8],
+        fragment_len: usize,
+    ) -> Result<(), Invalid> {
+        BATCHES.fetch_add(nonces.len() / Self::NONCE_LEN, Ordering::SeqCst);
+        self.0.seal_batch(nonces, aad, in_out, fragment_len)
+    }
+}
+
+#[test]
+fn seal_batch() -> io::Result<()> {
+    let key: Key<Batching> = Key::new([0; Key::<Batching>::SIZE]);
+    let data: Vec<u8> = (0..2000).map(|i| i as u8).collect();
+
+    let mut ciphertext = Vec::default();
+    let mut writer = EncWriter::with_buffer_size(
+        &mut ciphertext,
+        &key,
+        Nonce::new([0; Nonce::<Batching>::SIZE]),
+        Aad::empty(),
+        64,
+    )?;
+    writer.write_all(&data[..10])?;
+    writer.write_all(&data[10..])?;
+    writer.close()?;
+
+    // The first fragment is completed by the second write and sealed on its
+    // own. The final fragment remains buffered until the writer is closed.
+    assert_eq!(BATCHES.load(Ordering::SeqCst), 2000 / 64 - 1);
+    assert_eq!(ciphertext, encrypt(&data, 64)?);
+    Ok(())
+}
+
+#[test]
+fn batch_nonce_count_mismatch() {
+    let aead = AEAD::new(&[0; 32]);
+    let nonces = [0; 2 * AEAD::NONCE_LEN];
+    let mut in_out = [0; 3 * (16 + AEAD::TAG_LEN)];
+
+    let result = aead.seal_batch(&nonces, &[], &mut in_out, 16 + AEAD::TAG_LEN);
+    assert!(matches!(result, Err(Invalid::Nonce)));
+    assert!(aead
+        .open_batch(&nonces, &[], &mut in_out, 16 + AEAD::TAG_LEN)
+        .is_err());
+    assert!(aead.seal_batch(&nonces, &[], &mut in_out, 0).is_err());
+}
+
+fn is_quota_exceeded(err: &io::Error) -> bool {
+    err.get_ref()
+        .map(|err| err.is::<QuotaExceeded>())
+        .unwrap_or(false)
+}
+
+#[test]
+fn max_plaintext() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let data: Vec<u8> = (0..1000).map(|i| i as u8).collect();
+    for backpressure in &[false, true] {
+        let mut ciphertext = Vec::default();
+        let mut writer = EncWriter::with_buffer_size(
+            &mut ciphertext,
+            &key,
+            Nonce::new([0; Nonce::<AEAD>::SIZE]),
+            Aad::empty(),
+            64,
+        )?
+        .max_plaintext(500)?;
+        if *backpressure {
+            writer = writer.backpressure()?;
+        }
+        assert_eq!(writer.remaining(), 500);
+        writer.write_all(&data[..400])?;
+        assert_eq!(writer.remaining(), 100);
+
+        let err = writer.write_all(&data[400..]).unwrap_err();
+        assert!(is_quota_exceeded(&err));
+        if !*backpressure {
+            // The write has been rejected as a whole.
+            assert_eq!(writer.remaining(), 100);
+        }
+        writer.close()?;
+
+        let expected = if *backpressure { 500 } else { 400 };
+        assert_eq!(ciphertext, encrypt(&data[..expected], 64)?);
+    }
+    Ok(())
+}
+
+#[test]
+fn max_plaintext_copy_from() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let data: Vec<u8> = (0..1000).map(|i| i as u8).collect();
+
+    let mut writer = EncWriter::with_buffer_size(
+        Vec::default(),
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+        64,
+    )?
+    .max_plaintext(1000)?;
+    writer.copy_from(&data[..])?;
+    assert_eq!(writer.remaining(), 0);
+
+    let err = writer.copy_from(&data[..1]).unwrap_err();
+    assert!(is_quota_exceeded(&err));
+    writer.close()
+}
+
+#[test]
+fn max_plaintext_already_exceeded() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let mut writer = EncWriter::with_buffer_size(
+        Vec::default(),
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+        64,
+    )?;
+    writer.write_all(&[0; 100])?;
+
+    // The writer must not panic when it gets dropped.
+    let err = writer.max_plaintext(99).err().unwrap();
+    assert!(is_quota_exceeded(&err));
+    Ok(())
+}
+
+fn decrypt_forensic(ciphertext: &[u8], buf_size: usize) -> io::Result<(Vec<u8>, CorruptionReport)> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let mut plaintext = Vec::default();
+    let mut writer = DecWriter::with_buffer_size(
+        &mut plaintext,
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+        buf_size,
+    )
+    .map_err(io::Error::from)?
+    .forensic();
+    for chunk in ciphertext.chunks(100) {
+        writer.write_all(chunk)?;
+    }
+    let report = writer.close_forensic()?;
+    Ok((plaintext, report))
+}
+
+#[test]
+fn forensic() -> io::Result<()> {
+    let data: Vec<u8> = (0..1000).map(|i| i as u8).collect();
+    let mut ciphertext = encrypt(&data, 64)?;
+
+    let (plaintext, report) = decrypt_forensic(&ciphertext, 64)?;
+    assert_eq!(plaintext, data);
+    assert!(report.is_intact());
+
+    let fragment_len = 64 + AEAD::TAG_LEN;
+    ciphertext[fragment_len + 1] ^= 1;
+    ciphertext[5 * fragment_len] ^= 1;
+    ciphertext[15 * fragment_len + 2] ^= 1; // The final fragment
+
+    let (plaintext, report) = decrypt_forensic(&ciphertext, 64)?;
+    assert_eq!(report.fragments(), &[1, 5, 15]);
+    assert_eq!(report.ranges(), &[64..128, 320..384, 960..1000]);
+    assert_eq!(report.damaged_bytes(), 64 + 64 + 40);
+    assert!(!report.final_verified());
+    let mut expected = data[..64].to_vec();
+    expected.extend_from_slice(&data[128..320]);
+    expected.extend_from_slice(&data[384..960]);
+    assert_eq!(plaintext, expected);
+    Ok(())
+}
+
+#[test]
+fn forensic_truncated() -> io::Result<()> {
+    let data: Vec<u8> = (0..1000).map(|i| i as u8).collect();
+    let ciphertext = encrypt(&data, 64)?;
+
+    let fragment_len = 64 + AEAD::TAG_LEN;
+    let (plaintext, report) = decrypt_forensic(&ciphertext[..3 * fragment_len], 64)?;
+    assert_eq!(report.fragments(), &[2]);
+    assert_eq!(report.ranges()[0], 128..192);
+    assert!(!report.final_verified());
+    assert_eq!(plaintext, &data[..128]);
+    Ok(())
+}
+
+#[test]
+fn write_vectored() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let data: Vec<u8> = (0..1000).map(|i| i as u8).collect();
+    for backpressure in &[false, true] {
+        let mut ciphertext = Vec::default();
+        let mut writer = EncWriter::with_buffer_size(
+            &mut ciphertext,
+            &key,
+            Nonce::new([0; Nonce::<AEAD>::SIZE]),
+            Aad::empty(),
+            64,
+        )?;
+        if *backpressure {
+            writer = writer.backpressure()?;
+        }
+        let mut slices: Vec<IoSlice> = data.chunks(7).map(IoSlice::new).collect();
+        slices.insert(0, IoSlice::new(&[]));
+        slices.insert(10, IoSlice::new(&[]));
+        writer.write_all_vectored(&mut slices)?;
+        writer.close()?;
+        assert_eq!(ciphertext, encrypt(&data, 64)?);
+
+        let mut plaintext = Vec::default();
+        let mut writer = DecWriter::with_buffer_size(
+            &mut plaintext,
+            &key,
+            Nonce::new([0; Nonce::<AEAD>::SIZE]),
+            Aad::empty(),
+            64,
+        )?;
+        for chunk in ciphertext.chunks(100) {
+            let slices: Vec<IoSlice> = chunk.chunks(9).map(IoSlice::new).collect();
+            assert_eq!(writer.write_vectored(&slices)?, chunk.len());
+        }
+        writer.close()?;
+        assert_eq!(plaintext, data);
+    }
+    Ok(())
+}
+
+#[test]
+fn buffer_pool() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let data: Vec<u8> = (0..200).map(|i| i as u8).collect();
+    let pool = BufferPool::new(2);
+
+    for _ in 0..3 {
+        let mut ciphertext = Vec::default();
+        let mut writer = EncWriter::with_pool(
+            &mut ciphertext,
+            &key,
+            Nonce::new([0; Nonce::<AEAD>::SIZE]),
+            Aad::empty(),
+            64,
+            &pool,
+        )?;
+        writer.write_all(&data).and_then(|()| writer.close())?;
+        assert_eq!(ciphertext, encrypt(&data, 64)?);
+        assert_eq!(pool.len(), 1);
+
+        let mut plaintext = Vec::default();
+        let mut writer = DecWriter::with_pool(
+            &mut plaintext,
+            &key,
+            Nonce::new([0; Nonce::<AEAD>::SIZE]),
+            Aad::empty(),
+            64,
+            &pool,
+        )?;
+        writer
+            .write_all(&ciphertext)
+            .and_then(|()| writer.close())?;
+        assert_eq!(plaintext, data);
+    }
+
+    // Writers that are alive at the same time use different buffers.
+    let writers: Vec<_> = (0..4)
+        .map(|_| {
+            EncWriter::with_pool(
+                Vec::default(),
+                &key,
+                Nonce::new([0; Nonce::<AEAD>::SIZE]),
+                Aad::empty(),
+                64,
+                &pool,
+            )
+        })
+        .collect::<Result<_, _>>()?;
+    assert!(pool.is_empty());
+    for writer in writers {
+        writer.close()?;
+    }
+    assert_eq!(pool.len(), pool.capacity());
+
+    pool.clear();
+    assert!(pool.is_empty());
+    Ok(())
+}
+
+#[test]
+fn buffer_allocator() -> io::Result<()> {
+    struct Fixed(usize);
+
+    impl BufferAllocator for Fixed {
+        fn allocate(&self, _: usize) -> Box<[u8]> {
+            vec![0; self.0].into_boxed_slice()
+        }
+    }
+
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let data: Vec<u8> = (0..200).map(|i| i as u8).collect();
+
+    let mut ciphertext = Vec::default();
+    let mut writer = EncWriter::with_allocator(
+        &mut ciphertext,
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+        64,
+        std::sync::Arc::new(Fixed(64 + AEAD::TAG_LEN)),
+    )?;
+    writer.write_all(&data).and_then(|()| writer.close())?;
+    assert_eq!(ciphertext, encrypt(&data, 64)?);
+
+    let result = DecWriter::with_allocator(
+        Vec::default(),
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+        128,
+        std::sync::Arc::new(Fixed(64 + AEAD::TAG_LEN)),
+    );
+    assert!(matches!(result, Err(Invalid::BufSize)));
+    Ok(())
+}
+
+#[test]
+fn buffer_growth() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let data: Vec<u8> = (0..BUF_SIZE + 3000).map(|i| i as u8).collect();
+    for size in [0, 1, 1000, 1024, 1025, 4096, BUF_SIZE, BUF_SIZE + 3000] {
+        let expected = encrypt(&data[..size], BUF_SIZE)?;
+        for chunk_size in [1, 7, 1000, 5000] {
+            let mut ciphertext = Vec::default();
+            let mut writer = EncWriter::new(
+                &mut ciphertext,
+                &key,
+                Nonce::new([0; Nonce::<AEAD>::SIZE]),
+                Aad::empty(),
+            );
+            for chunk in data[..size].chunks(chunk_size) {
+                writer.write_all(chunk)?;
+            }
+            writer.close()?;
+            assert_eq!(ciphertext, expected);
+
+            let mut ciphertext = Vec::default();
+            let mut writer = EncWriter::new(
+                &mut ciphertext,
+                &key,
+                Nonce::new([0; Nonce::<AEAD>::SIZE]),
+                Aad::empty(),
+            )
+            .backpressure()?;
+            for chunk in data[..size].chunks(chunk_size) {
+                let mut chunk = chunk;
+                while !chunk.is_empty() {
+                    let n = writer.write(chunk)?;
+                    chunk = &chunk[n..];
+                }
+            }
+            writer.close()?;
+            assert_eq!(ciphertext, expected);
+        }
+
+        let mut ciphertext = Vec::default();
+        let mut writer = EncWriter::new(
+            &mut ciphertext,
+            &key,
+            Nonce::new([0; Nonce::<AEAD>::SIZE]),
+            Aad::empty(),
+        );
+        writer.copy_from(io::Read::chain(&data[..size / 2], &data[size / 2..size]))?;
+        writer.close()?;
+        assert_eq!(ciphertext, expected);
+    }
+    Ok(())
+}
+
+#[test]
+fn consume() {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let plaintext: Vec<u8> = (0..3 * BUF_SIZE + 7).map(|i| i as u8).collect();
+    let chunks = plaintext.chunks(1000).map(|chunk| Ok(chunk.to_vec()));
+
+    let mut ciphertext = Vec::default();
+    let writer = EncWriter::new(
+        &mut ciphertext,
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+    );
+    assert_eq!(writer.consume(chunks).unwrap(), plaintext.len() as u64);
+
+    let mut decrypted = Vec::default();
+    let writer = DecWriter::new(
+        &mut decrypted,
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+    );
+    let chunks = ciphertext.chunks(333).map(|chunk| Ok(chunk.to_vec()));
+    assert_eq!(writer.consume(chunks).unwrap(), ciphertext.len() as u64);
+    assert_eq!(decrypted, plaintext);
+}
+
+#[test]
+fn consume_error() {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let chunks = vec![
+        Ok(vec![0; 100]),
+        Err(io::Error::from(io::ErrorKind::ConnectionReset)),
+    ];
+
+    let writer = EncWriter::new(
+        Vec::default(),
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+    );
+    let err = writer.consume(chunks).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::ConnectionReset);
+}
+
+// An algorithm with N byte nonces that folds its nonces into the
+// 96 bit nonces of the underlying AEAD.
+struct Resized<const N: usize>(AEAD);
+
+impl<const N: usize> Resized<N> {
+    fn fold(nonce: &[u8]) -> [u8; 12] {
+        assert_eq!(nonce.len(), N);
+        let mut folded = [0; 12];
+        for (i, b) in nonce.iter().enumerate() {
+            folded[i % 12] ^= b.rotate_left((i / 12) as u32);
+        }
+        folded
+    }
+}
+
+impl<const N: usize> Algorithm for Resized<N> {
+    const KEY_LEN: usize = AEAD::KEY_LEN;
+    const NONCE_LEN: usize = N;
+    const TAG_LEN: usize = AEAD::TAG_LEN;
+
+    fn new(key: &[u8; 32]) -> Self {
+        Resized(AEAD::new(key))
+    }
+
+    fn seal_in_place<'a>(
+        &self,
+        nonce: &[u8],
+        aad: &[u8],
+        in_out: &'a mut [u8],
+    ) -> Result<&'a [u8], Invalid> {
+        self.0.seal_in_place(&Self::fold(nonce), aad, in_out)
+    }
+
+    fn open_in_place<'a>(
+        &self,
+        nonce: &[u8],
+        aad: &[u8],
+        in_out: &'a mut [u8],
+    ) -> Result<&'a [u8], NotAuthentic> {
+        self.0.open_in_place(&Self::fold(nonce), aad, in_out)
+    }
+}
+
+fn roundtrip_nonce_len<const N: usize, const M: usize>() -> io::Result<()> {
+    let key: Key<Resized<N>> = Key::new([0; 32]);
+    let data: Vec<u8> = (0..3 * 64 + 5).map(|i| i as u8).collect();
+
+    let mut nonce = [0; M];
+    nonce[0] = 1;
+    let mut ciphertext = Vec::default();
+    let mut writer =
+        EncWriter::with_buffer_size(&mut ciphertext, &key, Nonce::new(nonce), Aad::empty(), 64)?;
+    writer.write_all(&data)?;
+    writer.close()?;
+
+    let mut plaintext = Vec::default();
+    let mut writer =
+        DecWriter::with_buffer_size(&mut plaintext, &key, Nonce::new(nonce), Aad::empty(), 64)?;
+    writer.write_all(&ciphertext)?;
+    writer.close()?;
+    assert_eq!(plaintext, data);
+
+    nonce[M - 1] = 1;
+    let mut writer =
+        DecWriter::with_buffer_size(Vec::default(), &key, Nonce::new(nonce), Aad::empty(), 64)?;
+    assert!(writer
+        .write_all(&ciphertext)
+        .and_then(|()| writer.close())
+        .is_err());
+    Ok(())
+}
+
+#[test]
+fn large_nonce() -> io::Result<()> {
+    roundtrip_nonce_len::<24, 20>()
+}
+
+#[test]
+fn small_nonce() -> io::Result<()> {
+    roundtrip_nonce_len::<8, 4>()
+}
+
+// An algorithm with T byte tags. It truncates the tags of the
+// underlying AEAD and verifies a fragment by sealing its plaintext
+// again. This is slow but sufficient to exercise short tags.
+struct Truncated<const T: usize>(AEAD);
+
+impl<const T: usize> Truncated<T> {
+    // Returns the plaintext and the full tag of the fragment `ciphertext`.
+    fn reseal(&self, nonce: &[u8], aad: &[u8], ciphertext: &[u8]) -> (Vec<u8>, Vec<u8>) {
+        let mut keystream = vec![0; ciphertext.len() + AEAD::TAG_LEN];
+        self.0.seal_in_place(nonce, &[], &mut keystream).unwrap();
+        let mut sealed: Vec<u8> = ciphertext
+            .iter()
+            .zip(keystream.iter())
+            .map(|(c, k)| c ^ k)
+            .collect();
+        let plaintext = sealed.clone();
+        sealed.resize(ciphertext.len() + AEAD::TAG_LEN, 0);
+        self.0.seal_in_place(nonce, aad, &mut sealed).unwrap();
+        (plaintext, sealed[ciphertext.len()..].to_vec())
+    }
+}
+
+impl<const T: usize> Algorithm for Truncated<T> {
+    const KEY_LEN: usize = AEAD::KEY_LEN;
+    const NONCE_LEN: usize = AEAD::NONCE_LEN;
+    const TAG_LEN: usize = T;
+
+    fn new(key: &[u8; 32]) -> Self {
+        Truncated(AEAD::new(key))
+    }
+
+    fn seal_in_place<'a>(
+        &self,
+        nonce: &[u8],
+        aad: &[u8],
+        in_out: &'a mut [u8],
+    ) -> Result<&'a [u8], Invalid> {
+        if in_out.len() < T {
+            return Err(Invalid::BufSize);
+        }
+        let len = in_out.len() - T;
+        let mut sealed = in_out[..len].to_vec();
+        sealed.resize(len + AEAD::TAG_LEN, 0);
+        self.0.seal_in_place(nonce, aad, &mut sealed)?;
+        in_out.copy_from_slice(&sealed[..len + T]);
+        Ok(in_out)
+    }
+
+    fn open_in_place<'a>(
+        &self,
+        nonce: &[u8],
+        aad: &[u8],
+        in_out: &'a mut [u8],
+    ) -> Result<&'a [u8], NotAuthentic> {
+        if in_out.len() < T {
+            return Err(NotAuthentic);
+        }
+        let len = in_out.len() - T;
+        let (plaintext, tag) = self.reseal(nonce, aad, &in_out[..len]);
+        if tag[..T] != in_out[len..] {
+            return Err(NotAuthentic);
+        }
+        in_out[..len].copy_from_slice(&plaintext);
+        Ok(&in_out[..len])
+    }
+}
+
+#[test]
+fn truncated_tag() -> io::Result<()> {
+    let key: Key<Truncated<8>> = Key::new([0; 32]);
+    let data: Vec<u8> = (0..3 * 64 + 5).map(|i| i as u8).collect();
+
+    let mut ciphertext = Vec::default();
+    let mut writer = EncWriter::with_buffer_size(
+        &mut ciphertext,
+        &key,
+        Nonce::new([0; 8]),
+        Aad::from(b"aad".as_ref()),
+        64,
+    )?;
+    writer.write_all(&data)?;
+    writer.close()?;
+    assert_eq!(ciphertext.len(), data.len() + 4 * 8);
+
+    let mut plaintext = Vec::default();
+    let mut writer = DecWriter::with_buffer_size(
+        &mut plaintext,
+        &key,
+        Nonce::new([0; 8]),
+        Aad::from(b"aad".as_ref()),
+        64,
+    )?;
+    writer.write_all(&ciphertext)?;
+    writer.close()?;
+    assert_eq!(plaintext, data);
+
+    let sealed = seal_one(&key, Nonce::new([0; 8]), Aad::empty(), &data[..10])?;
+    assert_eq!(sealed.len(), 10 + 8);
+    let opened = open_one(&key, Nonce::new([0; 8]), Aad::empty(), &sealed)?;
+    assert_eq!(opened, &data[..10]);
+
+    ciphertext[64 + 8 + 1] ^= 1;
+    let mut writer = DecWriter::with_buffer_size(
+        Vec::default(),
+        &key,
+        Nonce::new([0; 8]),
+        Aad::from(b"aad".as_ref()),
+        64,
+    )?;
+    assert!(writer
+        .write_all(&ciphertext)
+        .and_then(|()| writer.close())
+        .is_err());
+    Ok(())
+}
+
+#[test]
+fn from_token() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let nonce = Nonce::new([1; Nonce::<AEAD>::SIZE]);
+    let token = Token::new(Key::new([0; Key::<AEAD>::SIZE]), nonce);
+    assert_eq!(token.nonce().as_ref(), nonce.as_ref());
+
+    let mut ciphertext = Vec::default();
+    let mut writer = EncWriter::from_token(&mut ciphertext, token, Aad::from(b"aad".as_ref()));
+    writer.write_all(b"Hello World")?;
+    writer.close()?;
+
+    let mut plaintext = Vec::default();
+    let mut writer = DecWriter::new(&mut plaintext, &key, nonce, Aad::from(b"aad".as_ref()));
+    writer.write_all(&ciphertext)?;
+    writer.close()?;
+    assert_eq!(plaintext, b"Hello World");
+    Ok(())
+}
+
+#[test]
+fn can_accept() -> io::Result<()> {
+    let key: Key<AEAD> = Key::new([0; Key::<AEAD>::SIZE]);
+    let mut lengths = Vec::default();
+    for size in 0..=3 * 64 + 1 {
+        lengths.push(encrypt(&vec![0; size], 64)?.len() as u64);
+    }
+    // Full fragments followed by an empty final fragment.
+    for fragments in 1..=3 {
+        lengths.push(fragments * (64 + AEAD::TAG_LEN as u64) + AEAD::TAG_LEN as u64);
+    }
+
+    let mut plaintext = Vec::default();
+    let writer = DecWriter::with_buffer_size(
+        &mut plaintext,
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+        64,
+    )?;
+    for len in 0..=*lengths.iter().max().unwrap() {
+        if lengths.contains(&len) {
+            assert!(writer.can_accept(len).is_ok());
+        } else {
+            assert!(matches!(
+                writer.can_accept(len),
+                Err(Invalid::CiphertextLen)
+            ));
+        }
+    }
+    assert!(writer.can_accept(u64::MAX).is_err());
+    writer.close().unwrap_err();
+
+    let mut plaintext = Vec::default();
+    let writer = DecWriter::with_buffer_size(
+        &mut plaintext,
+        &key,
+        Nonce::new([0; Nonce::<AEAD>::SIZE]),
+        Aad::empty(),
+        64,
+    )?
+    .tolerant();
+    assert!(writer.can_accept(AEAD::TAG_LEN as u64 - 1).is_err());
+    assert!(writer.can_accept(AEAD::TAG_LEN as u64).is_ok());
+    assert!(writer.can_accept(64 + AEAD::TAG_LEN as u64).is_ok());
+    writer.close().unwrap_err();
+    Ok(())
+}
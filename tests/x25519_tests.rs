@@ -0,0 +1,108 @@
+// Copyright (c) 2019 Andreas Auernhammer. All rights reserved.
+// Use of this source code is governed by a license that can be
+// found in the LICENSE file.
+
+#![cfg(feature = "x25519")]
+
+use sio::x25519::{self, SecretKey, HEADER_LEN};
+use sio::*;
+use std::io::{self, Read, Write};
+
+#[cfg(feature = "aesgcm")]
+#[allow(clippy::upper_case_acronyms)]
+type AEAD = AES_256_GCM;
+
+#[cfg(not(feature = "aesgcm"))]
+#[allow(clippy::upper_case_acronyms)]
+type AEAD = CHACHA20_POLY1305;
+
+#[test]
+fn x25519() -> io::Result<()> {
+    let secret_key = SecretKey::from_bytes([7; 32]);
+    for size in &[0, 1, BUF_SIZE, 3 * BUF_SIZE + 1] {
+        let data: Vec<u8> = (0..*size).map(|i| i as u8).collect();
+
+        let mut ciphertext = Vec::default();
+        let mut writer =
+            x25519::seal::<AEAD, _>(&mut ciphertext, &secret_key.public_key(), Aad::empty())?;
+        writer.write_all(&data).and_then(|()| writer.close())?;
+
+        let fragments = data.len().div_ceil(BUF_SIZE).max(1);
+        assert_eq!(
+            ciphertext.len(),
+            HEADER_LEN + data.len() + fragments * AEAD::TAG_LEN
+        );
+
+        let mut plaintext = Vec::default();
+        let mut reader = x25519::open::<AEAD, _>(ciphertext.as_slice(), &secret_key, Aad::empty())?;
+        reader.read_to_end(&mut plaintext)?;
+        assert_eq!(plaintext, data);
+    }
+    Ok(())
+}
+
+#[test]
+fn x25519_ephemeral_key() -> io::Result<()> {
+    let secret_key = SecretKey::generate().unwrap();
+
+    let mut first = Vec::default();
+    let mut writer = x25519::seal::<AEAD, _>(&mut first, &secret_key.public_key(), Aad::empty())?;
+    writer
+        .write_all(b"Hello World")
+        .and_then(|()| writer.close())?;
+
+    let mut second = Vec::default();
+    let mut writer = x25519::seal::<AEAD, _>(&mut second, &secret_key.public_key(), Aad::empty())?;
+    writer
+        .write_all(b"Hello World")
+        .and_then(|()| writer.close())?;
+
+    assert_ne!(first[..HEADER_LEN], second[..HEADER_LEN]);
+    assert_ne!(first[HEADER_LEN..], second[HEADER_LEN..]);
+    Ok(())
+}
+
+#[test]
+fn x25519_wrong_key() -> io::Result<()> {
+    let secret_key = SecretKey::from_bytes([7; 32]);
+    let mut ciphertext = Vec::default();
+    let mut writer =
+        x25519::seal::<AEAD, _>(&mut ciphertext, &secret_key.public_key(), Aad::empty())?;
+    writer
+        .write_all(b"Hello World")
+        .and_then(|()| writer.close())?;
+
+    let wrong_key = SecretKey::from_bytes([8; 32]);
+    let err = x25519::open::<AEAD, _>(ciphertext.as_slice(), &wrong_key, Aad::empty())
+        .and_then(|mut reader| reader.read_to_end(&mut Vec::default()))
+        .unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    Ok(())
+}
+
+#[test]
+fn x25519_modified_header() -> io::Result<()> {
+    let secret_key = SecretKey::from_bytes([7; 32]);
+    let mut ciphertext = Vec::default();
+    let mut writer =
+        x25519::seal::<AEAD, _>(&mut ciphertext, &secret_key.public_key(), Aad::empty())?;
+    writer
+        .write_all(b"Hello World")
+        .and_then(|()| writer.close())?;
+
+    ciphertext[0] ^= 1;
+    assert!(
+        x25519::open::<AEAD, _>(ciphertext.as_slice(), &secret_key, Aad::empty())
+            .and_then(|mut reader| reader.read_to_end(&mut Vec::default()))
+            .is_err()
+    );
+
+    // An all-zero public key is of low order.
+    ciphertext[..HEADER_LEN].copy_from_slice(&[0; HEADER_LEN]);
+    assert!(
+        x25519::open::<AEAD, _>(ciphertext.as_slice(), &secret_key, Aad::empty())
+            .and_then(|mut reader| reader.read_to_end(&mut Vec::default()))
+            .is_err()
+    );
+    Ok(())
+}